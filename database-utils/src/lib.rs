@@ -63,27 +63,525 @@ pub struct UpstreamConfig {
     #[serde(default)]
     pub replication_server_id: Option<u32>,
 
+    /// Resume MySQL binlog replication using GTID auto-positioning, rather than by binlog file
+    /// name and position. This allows replication to survive binlog files being purged, and
+    /// failing over to a different primary, without having to resnapshot.
+    ///
+    /// Requires `gtid_mode=ON` on the upstream database. If failing over, the new primary must use
+    /// the same binlog basename (`log_bin`) as the old one.
+    #[clap(long, env = "MYSQL_GTID_AUTO_POSITION")]
+    #[serde(default)]
+    pub mysql_gtid_auto_position: bool,
+
+    /// Comma-separated list of URLs of MySQL replicas of `--upstream-db-url` (such as read
+    /// replicas in other regions) that the binlog may be replicated from instead of the primary.
+    /// The snapshot is always taken from the primary.
+    ///
+    /// Each time replication starts, and every `--replication-source-check-interval-secs` while
+    /// replicating, the primary and each replica are checked, and replication switches to the
+    /// healthy server with the lowest round-trip latency. A replica is healthy if it's replicating
+    /// from the primary no more than `--replication-source-max-lag-secs` behind it, and has
+    /// executed every transaction ReadySet has replicated so far. Replication fails over to
+    /// another server if the one it's using becomes unhealthy, and fails back once a server with
+    /// much lower latency is healthy again.
+    ///
+    /// Requires `--mysql-gtid-auto-position`, and `gtid_mode=ON` and `log_replica_updates=ON` on
+    /// every replica, since replication resumes on a different server by GTID. Ignored otherwise.
+    /// Only supported for MySQL, since PostgreSQL replication slots can't move between servers.
+    #[clap(long, env = "REPLICATION_SOURCE_CANDIDATES", value_delimiter = ',')]
+    #[serde(default)]
+    pub replication_source_candidates: Vec<RedactedString>,
+
+    /// How far behind the primary, in seconds, a replica given by
+    /// `--replication-source-candidates` may be for replication to use it
+    #[clap(
+        long = "replication-source-max-lag-secs",
+        env = "REPLICATION_SOURCE_MAX_LAG_SECS",
+        default_value = "30",
+        value_parser = duration_from_seconds
+    )]
+    #[serde(default = "default_replication_source_max_lag")]
+    pub replication_source_max_lag: Duration,
+
+    /// How often, in seconds, to check the servers given by `--upstream-db-url` and
+    /// `--replication-source-candidates` while replicating, to decide whether to switch which one
+    /// replication uses
+    #[clap(
+        long = "replication-source-check-interval-secs",
+        env = "REPLICATION_SOURCE_CHECK_INTERVAL_SECS",
+        default_value = "10",
+        value_parser = duration_from_seconds
+    )]
+    #[serde(default = "default_replication_source_check_interval")]
+    pub replication_source_check_interval: Duration,
+
+    /// Accept MySQL binlog rows events logged with `binlog_row_image=MINIMAL`, rather than
+    /// requiring `binlog_row_image=FULL` on the upstream database.
+    ///
+    /// Updates and deletes are applied by primary key, and the columns missing from inserted rows
+    /// are looked up from the upstream database. Tables whose inserted rows can no longer be found
+    /// upstream are resnapshotted. Partial JSON updates (`binlog_row_value_options=PARTIAL_JSON`)
+    /// are not supported in this mode.
+    #[clap(long, env = "MYSQL_MINIMAL_ROW_IMAGE")]
+    #[serde(default)]
+    pub mysql_minimal_row_image: bool,
+
+    /// Replay MySQL binlog files from this directory instead of reading the binlog from the
+    /// upstream database, such as to backfill from archived binlogs, or to reproduce a replication
+    /// issue deterministically.
+    ///
+    /// Replication resumes from the binlog file and position ReadySet last replicated up to, which
+    /// must be in the directory, and continues through each following binlog file, waiting for
+    /// more to be written once it reaches the end of the last one. The upstream database is still
+    /// used to snapshot tables that haven't been snapshotted. Binlogs stored in S3 have to be
+    /// copied to a local directory first.
+    #[clap(long, env = "MYSQL_BINLOG_REPLAY_DIR")]
+    #[serde(default)]
+    pub mysql_binlog_replay_dir: Option<PathBuf>,
+
+    /// Snapshot tables from this dump file, taken with `mysqldump --source-data` (or
+    /// `--master-data`), instead of reading them from the upstream database, then replicate from
+    /// the binlog position recorded in the dump.
+    ///
+    /// Only used for the initial snapshot. Tables that aren't in the dump are snapshotted from the
+    /// upstream database as usual. The tables in the dump mustn't have been altered since it was
+    /// taken, the binlogs from its position onwards must still be available upstream, and binary
+    /// columns should be dumped with `--hex-blob`.
+    #[clap(long, env = "MYSQL_SNAPSHOT_DUMP")]
+    #[serde(default)]
+    pub mysql_snapshot_dump: Option<PathBuf>,
+
+    /// Snapshot tables from this dump file, taken with `pg_dump` from the snapshot exported when
+    /// ReadySet's replication slot was created, instead of reading them from the upstream
+    /// database, then replicate from the position the slot was created at.
+    ///
+    /// Only used for the initial snapshot. The slot has to be created, after the publication
+    /// ReadySet replicates from, with `CREATE_REPLICATION_SLOT <slot> LOGICAL pgoutput
+    /// EXPORT_SNAPSHOT` on a replication connection, and the dump taken with `pg_dump
+    /// --snapshot=<exported snapshot>` in plain format (without `--inserts`) while that connection
+    /// is still open. Every replicated table must be in the dump, and mustn't have been altered
+    /// since it was taken.
+    #[clap(long, env = "POSTGRES_SNAPSHOT_DUMP")]
+    #[serde(default)]
+    pub postgres_snapshot_dump: Option<PathBuf>,
+
+    /// The directory to write captures of the replication events received from the upstream
+    /// database to, when one is requested with the `capture_replication` tool. Captures are
+    /// refused unless this is set, and can only be written to files directly within it.
+    #[clap(long, env = "REPLICATION_CAPTURE_DIR")]
+    #[serde(default)]
+    pub replication_capture_dir: Option<PathBuf>,
+
+    /// If the MySQL binlog position ReadySet needs to resume replication from has been purged
+    /// upstream, automatically take a new snapshot of every table rather than failing.
+    #[clap(long, env = "RESNAPSHOT_ON_PURGED_BINLOG")]
+    #[serde(default)]
+    pub resnapshot_on_purged_binlog: bool,
+
+    /// What to do if the upstream MySQL database's binlog position has moved backwards since
+    /// ReadySet last replicated from it, such as after the primary is restored from a backup.
+    ///
+    /// * `error` - fail replication
+    /// * `resnapshot` - take a new snapshot of every table
+    /// * `reconcile-gtid` - resume replication with GTID auto-positioning if the upstream has
+    ///   executed every transaction ReadySet has replicated, and otherwise take a new snapshot
+    #[clap(
+        long,
+        env = "REPLICATION_REWIND_POLICY",
+        default_value_t = ReplicationRewindPolicy::Error
+    )]
+    #[serde(default)]
+    pub replication_rewind_policy: ReplicationRewindPolicy,
+
+    /// What to do when the upstream database makes a schema change that would drop or rebuild the
+    /// caches that depend on a replicated table: dropping the table, dropping one of its columns,
+    /// changing the type of one of its columns to a type that can't hold all of the column's
+    /// existing values, or an `ALTER TABLE` statement ReadySet can't parse.
+    ///
+    /// * `apply` - apply the schema change, like any other
+    /// * `quarantine` - don't apply the schema change, and stop replicating writes to the table,
+    ///   so that its caches keep serving the results they had before the schema change. The table
+    ///   stays quarantined, including across restarts, until it's next snapshotted (such as when
+    ///   it's resnapshotted on request), which applies the table's current upstream schema
+    /// * `reject` - fail replication, until the schema change is allowed by changing this option
+    #[clap(
+        long,
+        env = "DESTRUCTIVE_DDL_POLICY",
+        default_value_t = DestructiveDdlPolicy::Apply
+    )]
+    #[serde(default)]
+    pub destructive_ddl_policy: DestructiveDdlPolicy,
+
+    /// What the MySQL replicator does when it can't parse a schema change made upstream to a
+    /// replicated table, which would otherwise leave ReadySet's schema for the table diverged from
+    /// the upstream database's.
+    ///
+    /// * `ignore` - log the error and keep replicating the table with its old schema
+    /// * `skip` - drop the table, and all the caches that read from it, and stop replicating it,
+    ///   so that queries against it are proxied to the upstream database
+    /// * `pause` - fail replication with an error naming the table and statement, until the
+    ///   statement is supported or this option is changed
+    /// * `resnapshot` - drop the table and snapshot it again, which picks up its upstream schema
+    #[clap(long, env = "DDL_CONFLICT_POLICY", default_value_t = DdlConflictPolicy::Ignore)]
+    #[serde(default)]
+    pub ddl_conflict_policy: DdlConflictPolicy,
+
     /// The time to wait before restarting the replicator in seconds.
     #[clap(long, hide = true, default_value = "30", value_parser = duration_from_seconds)]
     #[serde(default = "default_replicator_restart_timeout")]
     pub replicator_restart_timeout: Duration,
 
+    /// How long, in seconds, the MySQL replicator keeps trying to reconnect (with exponential
+    /// backoff) if its connection to the binlog stream drops, before giving up and restarting
+    /// replication. Set to 0 to restart replication immediately.
+    #[clap(
+        long,
+        env = "REPLICATION_RECONNECT_TIMEOUT",
+        default_value = "60",
+        value_parser = duration_from_seconds
+    )]
+    #[serde(default = "default_replication_reconnect_timeout")]
+    pub replication_reconnect_timeout: Duration,
+
+    /// Log a warning when the Postgres replication slot is holding back more than this many bytes
+    /// of WAL on the upstream database, which can fill its disk if replication falls behind. Set
+    /// to 0 to disable the warning.
+    #[clap(
+        long,
+        env = "REPLICATION_SLOT_LAG_WARN_BYTES",
+        default_value = "1073741824"
+    )]
+    #[serde(default = "default_replication_slot_lag_warn_bytes")]
+    pub replication_slot_lag_warn_bytes: u64,
+
+    /// Drop the Postgres replication slot when ReadySet shuts down cleanly, so that the upstream
+    /// database doesn't retain WAL for it while ReadySet isn't running. Every table is snapshotted
+    /// again when replication next starts.
+    #[clap(long, env = "DROP_REPLICATION_SLOT_ON_SHUTDOWN")]
+    #[serde(default)]
+    pub drop_replication_slot_on_shutdown: bool,
+
+    /// How long, in seconds, to give the replicator to stop cleanly when ReadySet shuts down:
+    /// applying the writes it has buffered, persisting its replication offset, and disconnecting
+    /// from the upstream database. Replication is stopped abruptly if this runs out, in which case
+    /// it resumes from an earlier offset when it restarts. This should be less than both the 20
+    /// seconds ReadySet waits for its background tasks to stop and the time the process is given
+    /// to exit, such as Kubernetes' `terminationGracePeriodSeconds`.
+    #[clap(
+        long,
+        env = "REPLICATOR_SHUTDOWN_GRACE_PERIOD",
+        default_value = "10",
+        value_parser = duration_from_seconds
+    )]
+    #[serde(default = "default_replicator_shutdown_grace_period")]
+    pub replicator_shutdown_grace_period: Duration,
+
+    /// Persist the MySQL replicator's position in the binlog after at most this many actionable
+    /// binlog events, rather than after every event.
+    ///
+    /// Writes received between checkpoints are buffered by the replicator and applied along with
+    /// the next checkpoint, so after a crash replication resumes from the last checkpoint without
+    /// applying any writes twice. A checkpoint is always taken before a schema change or a switch
+    /// to a new binlog file. If neither this nor `--mysql-checkpoint-interval-ms` is set, a
+    /// checkpoint is taken after every event.
+    #[clap(long, env = "MYSQL_CHECKPOINT_EVENTS", value_parser = clap::value_parser!(u64).range(1..))]
+    #[serde(default)]
+    pub mysql_checkpoint_events: Option<u64>,
+
+    /// Persist the MySQL replicator's position in the binlog at least this often, in milliseconds,
+    /// while there are writes buffered since the last checkpoint. See `--mysql-checkpoint-events`.
+    #[clap(
+        long = "mysql-checkpoint-interval-ms",
+        env = "MYSQL_CHECKPOINT_INTERVAL_MS",
+        value_parser = duration_from_millis
+    )]
+    #[serde(default)]
+    pub mysql_checkpoint_interval: Option<Duration>,
+
+    /// Comma-separated list of tables to replicate, as `database.table`. Either part may be a glob
+    /// pattern, where `*` matches any sequence of characters and `?` matches any single
+    /// character. If not specified, all tables are replicated.
     #[clap(long, env = "REPLICATION_TABLES")]
     #[serde(default)]
     pub replication_tables: Option<RedactedString>,
 
+    /// Comma-separated list of tables to never replicate, in the same format as
+    /// `--replication-tables`. Takes precedence over `--replication-tables`.
+    #[clap(long, env = "REPLICATION_TABLES_IGNORE")]
+    #[serde(default)]
+    pub replication_tables_ignore: Option<RedactedString>,
+
+    /// Comma-separated list of existing PostgreSQL publications to replicate tables from, rather
+    /// than creating a publication for all tables. Only the tables in these publications are
+    /// replicated, and tables added to or dropped from them with `ALTER PUBLICATION` are picked
+    /// up (and snapshotted, if added) while replicating. Can't be combined with
+    /// `--replication-tables`.
+    #[clap(
+        long,
+        env = "REPLICATION_PUBLICATIONS",
+        value_delimiter = ',',
+        conflicts_with = "replication_tables"
+    )]
+    #[serde(default)]
+    pub replication_publications: Vec<String>,
+
     /// Sets the time (in seconds) between reports of progress snapshotting the database. A value
     /// of 0 disables reporting.
     #[clap(long, default_value = "30")]
     #[serde(default = "default_snapshot_report_interval_secs")]
     pub snapshot_report_interval_secs: u16,
 
+    /// The maximum number of tables to snapshot at the same time, which is also the maximum
+    /// number of connections to the upstream database used to read tables' contents. Defaults to
+    /// 8 for MySQL, and to every table at once (limited by `--replication-pool-size`) for
+    /// PostgreSQL.
+    #[clap(long, env = "SNAPSHOT_PARALLELISM")]
+    #[serde(default)]
+    pub snapshot_parallelism: Option<usize>,
+
+    /// If set, snapshot tables that have a primary key in chunks of this many rows, ordered by
+    /// the primary key, rather than in a single pass. For PostgreSQL, each chunk is read in its
+    /// own transaction, so a chunk that fails to be read is retried from the last row of the
+    /// previous chunk, rather than restarting the whole table.
+    #[clap(long, env = "SNAPSHOT_CHUNK_SIZE")]
+    #[serde(default)]
+    pub snapshot_chunk_size: Option<u64>,
+
+    /// Directory to persist the progress of chunked snapshots (see `--snapshot-chunk-size`) to,
+    /// so that a snapshot interrupted by a restart resumes each table from the last chunk that
+    /// was written to ReadySet rather than starting over. Ignored unless `--snapshot-chunk-size`
+    /// is also set.
+    ///
+    /// Resuming relies on the rows already written to ReadySet's base tables having been
+    /// persisted, and on the upstream database still having the binlog or WAL from the
+    /// position the interrupted snapshot was taken at.
+    #[clap(long, env = "SNAPSHOT_CHECKPOINT_DIR")]
+    #[serde(default)]
+    pub snapshot_checkpoint_dir: Option<PathBuf>,
+
+    /// If set, limit the number of rows read from the upstream database per second while
+    /// snapshotting, across all tables, to avoid competing with application traffic
+    #[clap(long, env = "SNAPSHOT_MAX_ROWS_PER_SEC")]
+    #[serde(default)]
+    pub snapshot_max_rows_per_sec: Option<u64>,
+
+    /// If set, limit the (estimated) number of bytes of rows read from the upstream database per
+    /// second while snapshotting, across all tables, to avoid competing with application traffic
+    #[clap(long, env = "SNAPSHOT_MAX_BYTES_PER_SEC")]
+    #[serde(default)]
+    pub snapshot_max_bytes_per_sec: Option<u64>,
+
     /// Sets the connection count for the pool that is used for replication and snapshotting.
     #[clap(long, default_value = "50")]
     #[serde(default)]
     pub replication_pool_size: usize,
+
+    /// Additional upstream databases to replicate from, alongside `--upstream-db-url`, each as
+    /// `<tables>=<url>`, where `<tables>` lists the tables to replicate from that database in the
+    /// same format as `--replication-tables`. Multiple upstreams are separated by spaces.
+    ///
+    /// Tables listed for an additional upstream are not replicated from `--upstream-db-url`, and
+    /// the lists of tables for each additional upstream must not overlap. Replication progress is
+    /// tracked separately for each additional upstream, keyed by its list of tables.
+    ///
+    /// The `n`th additional upstream replicates with the server id (and, for Postgres, from the
+    /// replication slot named after the server id) `n` above `--replication-server-id`, so server
+    /// ids given to other instances replicating from the same upstreams must leave room for them.
+    #[clap(
+        long = "additional-upstream",
+        env = "ADDITIONAL_UPSTREAMS",
+        value_delimiter = ' '
+    )]
+    #[serde(default)]
+    pub additional_upstreams: Vec<AdditionalUpstream>,
+
+    /// Rows to expire from append-only tables, such as tables of events or logs, so that they
+    /// don't grow without bound in ReadySet while the upstream database keeps their full history.
+    /// Each is given as `<table>:<column>=<retention>`, where `<table>` is qualified with its
+    /// schema, `<column>` is a timestamp column, and `<retention>` is a number followed by `s`,
+    /// `m`, `h` or `d` (eg `public.events:created_at=30d` keeps the last 30 days of events).
+    /// Multiple tables are separated by spaces.
+    ///
+    /// Inserts into these tables are replicated as usual, and rows older than the retention period
+    /// are periodically deleted from ReadySet but not from the upstream database. Updates and
+    /// deletes replicated for rows that have already expired are ignored.
+    #[clap(
+        long = "table-retention",
+        env = "TABLE_RETENTION",
+        value_delimiter = ' '
+    )]
+    #[serde(default)]
+    pub table_retention: Vec<TableRetention>,
+
+    /// Periodically check that each replicated table in ReadySet has the same rows as the
+    /// upstream database, this often in seconds, by comparing a checksum of the table's rows in
+    /// each. Tables that have diverged are reported by the `replicator.consistency_check.diverged`
+    /// metric and the `/consistency_check` controller endpoint. Disabled if not set.
+    ///
+    /// Each check reads every row of every replicated table from both ReadySet and the upstream
+    /// database, so this should be much longer than that takes. Both are read as of the same
+    /// replication position: writes to each table upstream are blocked just long enough to read
+    /// the position, and replication pauses while the table is read from ReadySet. For
+    /// PostgreSQL, blocking writes requires the `UPDATE`, `DELETE` or `TRUNCATE` privilege on each
+    /// table.
+    #[clap(
+        long = "consistency-check-interval-secs",
+        env = "CONSISTENCY_CHECK_INTERVAL_SECS",
+        value_parser = duration_from_seconds
+    )]
+    #[serde(default)]
+    pub consistency_check_interval: Option<Duration>,
+
+    /// Directory to export the schema of each replicated table to, in the format given by
+    /// `--schema-export-format`, whenever replication starts or a schema change is replicated.
+    /// Each table's schema is written to its own file, named after the table, which is removed if
+    /// the table is dropped or stops being replicated.
+    #[clap(long, env = "SCHEMA_EXPORT_DIR")]
+    #[serde(default)]
+    pub schema_export_dir: Option<PathBuf>,
+
+    /// URL of a schema registry to register the schema of each replicated table with, in the
+    /// format given by `--schema-export-format`, whenever replication starts or a schema change is
+    /// replicated. Schemas are registered with the Confluent Schema Registry API, under a subject
+    /// named `<schema>.<table>` for each table.
+    #[clap(long, env = "SCHEMA_EXPORT_URL")]
+    #[serde(default)]
+    pub schema_export_url: Option<String>,
+
+    /// The format to export the schema of replicated tables in, with `--schema-export-dir` or
+    /// `--schema-export-url`.
+    ///
+    /// * `json-schema` - a JSON Schema describing the rows of each table
+    /// * `avro` - an Avro record schema describing the rows of each table
+    ///
+    /// Both formats also list each table's primary and unique keys, and the SQL type of each
+    /// column.
+    #[clap(
+        long,
+        env = "SCHEMA_EXPORT_FORMAT",
+        default_value_t = SchemaExportFormat::JsonSchema
+    )]
+    #[serde(default)]
+    pub schema_export_format: SchemaExportFormat,
+
+    /// The name of the additional upstream this configuration replicates from, if any. Set by
+    /// [`UpstreamConfig::replication_sources`] for each of [`Self::additional_upstreams`].
+    #[clap(skip)]
+    #[serde(default)]
+    pub replication_source: Option<String>,
+}
+
+/// An upstream database to replicate a set of tables from, in addition to the one given by
+/// `--upstream-db-url`. See [`UpstreamConfig::additional_upstreams`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdditionalUpstream {
+    /// The tables to replicate from this upstream, in the same format as
+    /// [`UpstreamConfig::replication_tables`]
+    pub tables: String,
+    /// URL for the upstream database to connect to
+    pub url: RedactedString,
+}
+
+/// Parses `<tables>=<url>`
+///
+/// # Examples
+///
+/// ```rust
+/// use database_utils::AdditionalUpstream;
+///
+/// let upstream: AdditionalUpstream = "sales.*,crm.customers=mysql://root@sales-db/sales"
+///     .parse()
+///     .unwrap();
+/// assert_eq!(upstream.tables, "sales.*,crm.customers");
+/// assert_eq!(upstream.url.as_str(), "mysql://root@sales-db/sales");
+///
+/// assert!("mysql://root@sales-db/sales"
+///     .parse::<AdditionalUpstream>()
+///     .is_err());
+/// ```
+impl FromStr for AdditionalUpstream {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((tables, url)) if !tables.trim().is_empty() && !url.is_empty() => Ok(Self {
+                tables: tables.trim().to_owned(),
+                url: url.to_owned().into(),
+            }),
+            _ => Err(format!(
+                "Invalid additional upstream, expected `<tables>=<url>`: {s}"
+            )),
+        }
+    }
 }
 
+/// How long to keep the rows of an append-only table in ReadySet. See
+/// [`UpstreamConfig::table_retention`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableRetention {
+    /// The table to expire rows from, as `schema.table`
+    pub table: String,
+    /// The timestamp column used to determine the age of each row
+    pub column: String,
+    /// Rows with a value in [`Self::column`] older than this are expired
+    pub retention: Duration,
+}
+
+/// Parses `<table>:<column>=<retention>`
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use database_utils::TableRetention;
+///
+/// let retention: TableRetention = "public.events:created_at=30d".parse().unwrap();
+/// assert_eq!(retention.table, "public.events");
+/// assert_eq!(retention.column, "created_at");
+/// assert_eq!(retention.retention, Duration::from_secs(30 * 24 * 60 * 60));
+///
+/// assert!("public.events=30d".parse::<TableRetention>().is_err());
+/// assert!("events:created_at=30d".parse::<TableRetention>().is_err());
+/// assert!("public.events:created_at=30"
+///     .parse::<TableRetention>()
+///     .is_err());
+/// ```
+impl FromStr for TableRetention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err =
+            || format!("Invalid table retention, expected `<table>:<column>=<retention>`: {s}");
+        let (target, retention) = s.split_once('=').ok_or_else(err)?;
+        let (table, column) = target.split_once(':').ok_or_else(err)?;
+        if !table.contains('.') || column.trim().is_empty() {
+            return Err(err());
+        }
+
+        let retention = retention.trim();
+        let unit_secs = match retention.chars().last() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => return Err(err()),
+        };
+        let amount = retention[..retention.len() - 1]
+            .parse::<u64>()
+            .map_err(|_| err())?;
+
+        Ok(Self {
+            table: table.trim().to_owned(),
+            column: column.trim().to_owned(),
+            retention: Duration::from_secs(amount * unit_secs),
+        })
+    }
+}
+
+/// The server id we replicate with if [`UpstreamConfig::replication_server_id`] isn't set
+pub const DEFAULT_REPLICATION_SERVER_ID: u32 = u32::MAX - 55;
+
 impl UpstreamConfig {
     /// Read the certificate at [`Self::ssl_root_cert`] path and try to parse it as either PEM or
     /// DER encoded certificate
@@ -103,6 +601,92 @@ impl UpstreamConfig {
         }
     }
 
+    /// Returns the configuration to replicate from each upstream database: first
+    /// [`Self::upstream_db_url`], then each of [`Self::additional_upstreams`].
+    ///
+    /// The tables replicated from additional upstreams are ignored when replicating from
+    /// [`Self::upstream_db_url`], and each additional upstream replicates only its own tables, with
+    /// [`Self::replication_source`] set to the list of those tables. Each additional upstream is
+    /// given its own [`Self::replication_server_id`], counting up from ours, so that upstreams
+    /// which are actually the same database server don't share a binlog connection or
+    /// replication slot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use database_utils::{UpstreamConfig, DEFAULT_REPLICATION_SERVER_ID};
+    ///
+    /// let mut config = UpstreamConfig::from_url("mysql://root@main-db/main");
+    /// config.additional_upstreams = vec![
+    ///     "sales.*=mysql://root@sales-db/sales".parse().unwrap(),
+    ///     "crm.*=mysql://root@main-db/crm".parse().unwrap(),
+    /// ];
+    ///
+    /// let sources = config.replication_sources();
+    /// assert_eq!(sources.len(), 3);
+    /// assert_eq!(sources[0].replication_source, None);
+    /// assert_eq!(
+    ///     sources[0]
+    ///         .replication_tables_ignore
+    ///         .as_deref()
+    ///         .map(|s| s.as_str()),
+    ///     Some("sales.*,crm.*")
+    /// );
+    /// assert_eq!(sources[1].replication_source.as_deref(), Some("sales.*"));
+    /// assert_eq!(
+    ///     sources[1].upstream_db_url.as_deref().map(|s| s.as_str()),
+    ///     Some("mysql://root@sales-db/sales")
+    /// );
+    /// assert!(sources.iter().all(|s| s.additional_upstreams.is_empty()));
+    ///
+    /// // Every upstream replicates with a different server id
+    /// assert_eq!(sources[0].replication_server_id, None);
+    /// assert_eq!(
+    ///     sources[1].replication_server_id,
+    ///     Some(DEFAULT_REPLICATION_SERVER_ID + 1)
+    /// );
+    /// assert_eq!(
+    ///     sources[2].replication_server_id,
+    ///     Some(DEFAULT_REPLICATION_SERVER_ID + 2)
+    /// );
+    /// ```
+    pub fn replication_sources(&self) -> Vec<UpstreamConfig> {
+        let mut primary = UpstreamConfig {
+            additional_upstreams: vec![],
+            ..self.clone()
+        };
+        if !self.additional_upstreams.is_empty() {
+            let ignore = primary
+                .replication_tables_ignore
+                .iter()
+                .map(|ignore| ignore.as_str())
+                .chain(self.additional_upstreams.iter().map(|u| u.tables.as_str()))
+                .collect::<Vec<_>>()
+                .join(",");
+            primary.replication_tables_ignore = Some(ignore.into());
+        }
+
+        let server_id = self
+            .replication_server_id
+            .unwrap_or(DEFAULT_REPLICATION_SERVER_ID);
+        let additional = self
+            .additional_upstreams
+            .iter()
+            .zip(1..)
+            .map(|(upstream, n)| UpstreamConfig {
+                upstream_db_url: Some(upstream.url.clone()),
+                replication_server_id: Some(server_id.wrapping_add(n)),
+                replication_tables: Some(upstream.tables.clone().into()),
+                replication_publications: vec![],
+                replication_source_candidates: vec![],
+                replication_source: Some(upstream.tables.clone()),
+                additional_upstreams: vec![],
+                ..self.clone()
+            });
+
+        std::iter::once(primary).chain(additional).collect()
+    }
+
     pub fn from_url<S: AsRef<str>>(url: S) -> Self {
         UpstreamConfig {
             upstream_db_url: Some(url.as_ref().to_string().into()),
@@ -115,6 +699,26 @@ fn default_replicator_restart_timeout() -> Duration {
     UpstreamConfig::default().replicator_restart_timeout
 }
 
+fn default_replication_reconnect_timeout() -> Duration {
+    UpstreamConfig::default().replication_reconnect_timeout
+}
+
+fn default_replicator_shutdown_grace_period() -> Duration {
+    UpstreamConfig::default().replicator_shutdown_grace_period
+}
+
+fn default_replication_source_max_lag() -> Duration {
+    UpstreamConfig::default().replication_source_max_lag
+}
+
+fn default_replication_source_check_interval() -> Duration {
+    UpstreamConfig::default().replication_source_check_interval
+}
+
+fn default_replication_slot_lag_warn_bytes() -> u64 {
+    UpstreamConfig::default().replication_slot_lag_warn_bytes
+}
+
 fn default_snapshot_report_interval_secs() -> u16 {
     UpstreamConfig::default().snapshot_report_interval_secs
 }
@@ -123,6 +727,10 @@ fn duration_from_seconds(i: &str) -> Result<Duration, ParseIntError> {
     i.parse::<u64>().map(Duration::from_secs)
 }
 
+fn duration_from_millis(i: &str) -> Result<Duration, ParseIntError> {
+    i.parse::<u64>().map(Duration::from_millis)
+}
+
 impl Default for UpstreamConfig {
     fn default() -> Self {
         Self {
@@ -130,11 +738,136 @@ impl Default for UpstreamConfig {
             disable_upstream_ssl_verification: false,
             disable_setup_ddl_replication: false,
             replication_server_id: Default::default(),
+            mysql_gtid_auto_position: false,
+            replication_source_candidates: vec![],
+            replication_source_max_lag: Duration::from_secs(30),
+            replication_source_check_interval: Duration::from_secs(10),
+            mysql_minimal_row_image: false,
+            mysql_binlog_replay_dir: None,
+            mysql_snapshot_dump: None,
+            postgres_snapshot_dump: None,
+            replication_capture_dir: None,
+            resnapshot_on_purged_binlog: false,
+            replication_rewind_policy: ReplicationRewindPolicy::Error,
+            destructive_ddl_policy: DestructiveDdlPolicy::Apply,
+            ddl_conflict_policy: DdlConflictPolicy::Ignore,
             replicator_restart_timeout: Duration::from_secs(30),
+            replication_reconnect_timeout: Duration::from_secs(60),
+            replication_slot_lag_warn_bytes: 1024 * 1024 * 1024,
+            drop_replication_slot_on_shutdown: false,
+            replicator_shutdown_grace_period: Duration::from_secs(10),
+            mysql_checkpoint_events: None,
+            mysql_checkpoint_interval: None,
             replication_tables: Default::default(),
+            replication_publications: vec![],
+            replication_tables_ignore: Default::default(),
             snapshot_report_interval_secs: 30,
+            snapshot_parallelism: None,
+            snapshot_chunk_size: None,
+            snapshot_checkpoint_dir: None,
+            snapshot_max_rows_per_sec: None,
+            snapshot_max_bytes_per_sec: None,
             ssl_root_cert: None,
             replication_pool_size: 50,
+            additional_upstreams: vec![],
+            table_retention: vec![],
+            consistency_check_interval: None,
+            schema_export_dir: None,
+            schema_export_url: None,
+            schema_export_format: SchemaExportFormat::JsonSchema,
+            replication_source: None,
+        }
+    }
+}
+
+/// What the MySQL replicator does if the upstream database's binlog position is behind the
+/// replication offset ReadySet has stored. See [`UpstreamConfig::replication_rewind_policy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum ReplicationRewindPolicy {
+    /// Fail replication with an error
+    #[default]
+    Error,
+    /// Take a new snapshot of every table
+    Resnapshot,
+    /// Resume with GTID auto-positioning if possible, and otherwise take a new snapshot
+    ReconcileGtid,
+}
+
+impl Display for ReplicationRewindPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Resnapshot => write!(f, "resnapshot"),
+            Self::ReconcileGtid => write!(f, "reconcile-gtid"),
+        }
+    }
+}
+
+/// What the replicator does when the upstream database makes a destructive schema change to a
+/// replicated table. See [`UpstreamConfig::destructive_ddl_policy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum DestructiveDdlPolicy {
+    /// Apply the schema change
+    #[default]
+    Apply,
+    /// Stop replicating the table, without applying the schema change
+    Quarantine,
+    /// Fail replication with an error
+    Reject,
+}
+
+impl Display for DestructiveDdlPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Apply => write!(f, "apply"),
+            Self::Quarantine => write!(f, "quarantine"),
+            Self::Reject => write!(f, "reject"),
+        }
+    }
+}
+
+/// What the replicator does when it can't parse a schema change made upstream to a replicated
+/// table. See [`UpstreamConfig::ddl_conflict_policy`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum DdlConflictPolicy {
+    /// Log the error and ignore the schema change
+    #[default]
+    Ignore,
+    /// Drop the table and its caches, and stop replicating it
+    Skip,
+    /// Fail replication with an error
+    Pause,
+    /// Drop the table and snapshot it again
+    Resnapshot,
+}
+
+impl Display for DdlConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ignore => write!(f, "ignore"),
+            Self::Skip => write!(f, "skip"),
+            Self::Pause => write!(f, "pause"),
+            Self::Resnapshot => write!(f, "resnapshot"),
+        }
+    }
+}
+
+/// The format to export the schema of replicated tables in. See
+/// [`UpstreamConfig::schema_export_format`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum SchemaExportFormat {
+    /// JSON Schema
+    #[default]
+    JsonSchema,
+    /// An Avro record schema
+    Avro,
+}
+
+impl Display for SchemaExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::JsonSchema => write!(f, "json-schema"),
+            Self::Avro => write!(f, "avro"),
         }
     }
 }
@@ -6,6 +6,7 @@ mod memory_state;
 mod mk_key;
 mod persistent_state;
 mod single_state;
+mod spill_store;
 
 use std::borrow::Cow;
 use std::fmt::{self, Debug};
@@ -28,8 +29,11 @@ use readyset_errors::ReadySetResult;
 pub use crate::key::{PointKey, RangeKey};
 pub use crate::memory_state::MemoryState;
 pub use crate::persistent_state::{
-    DurabilityMode, PersistenceParameters, PersistentState, PersistentStateHandle, SnapshotMode,
+    migrate_persisted_replication_offset, read_persisted_replication_offset, DurabilityMode,
+    PersistedReplicationOffset, PersistenceParameters, PersistentState, PersistentStateHandle,
+    SnapshotMode,
 };
+pub use crate::spill_store::{SpillReadCallback, SpillStore};
 
 /// Information about state evicted via a call to [`State::evict_bytes`]
 pub struct EvictBytesResult<'a> {
@@ -65,7 +65,7 @@ use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::io::{self, Read};
 use std::ops::Bound;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -78,7 +78,7 @@ use parking_lot::{
     MappedRwLockReadGuard, MappedRwLockWriteGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
 };
 use readyset_client::internal::Index;
-use readyset_client::replication::ReplicationOffset;
+use readyset_client::replication::{ReplicationOffset, REPLICATION_OFFSET_VERSION};
 use readyset_client::{KeyComparison, KeyCount, SqlIdentifier};
 use readyset_data::DfValue;
 use readyset_errors::{internal_err, invariant, ReadySetError, ReadySetResult};
@@ -185,6 +185,92 @@ fn increment_epoch(db: &DB) -> Result<PersistentMeta<'static>> {
     Ok(meta)
 }
 
+/// If the replication offset in `meta` was persisted with an older encoding than
+/// [`REPLICATION_OFFSET_VERSION`], convert it to the current encoding and save the converted
+/// metadata back to the database
+fn migrate_replication_offset(db: &DB, meta: &mut PersistentMeta<'_>) -> ReadySetResult<()> {
+    if meta.replication_offset_version == REPLICATION_OFFSET_VERSION {
+        return Ok(());
+    }
+
+    if let Some(offset) = meta.replication_offset.take() {
+        let migrated = offset
+            .into_owned()
+            .migrate(meta.replication_offset_version)?;
+        info!(
+            from_version = meta.replication_offset_version,
+            to_version = REPLICATION_OFFSET_VERSION,
+            offset = %migrated,
+            "Converted persisted replication offset"
+        );
+        meta.replication_offset = Some(Cow::Owned(migrated));
+    }
+    meta.replication_offset_version = REPLICATION_OFFSET_VERSION;
+    db.save_meta(meta);
+    Ok(())
+}
+
+/// The replication offset persisted in the metadata of a [`PersistentState`], along with the
+/// version of the encoding it was persisted with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistedReplicationOffset {
+    /// The persisted replication offset, if any
+    pub offset: Option<ReplicationOffset>,
+    /// The version of the encoding `offset` was persisted with. See
+    /// [`REPLICATION_OFFSET_VERSION`]
+    pub version: u8,
+}
+
+/// Read the replication offset persisted in the [`PersistentState`] at `path`, without
+/// converting it to the current encoding.
+///
+/// The database is opened read-only, so this is safe to call on the state of a table that is
+/// currently in use.
+pub fn read_persisted_replication_offset(path: &Path) -> Result<PersistedReplicationOffset> {
+    let db = DB::open_for_read_only(&base_options(&Default::default()), path, false)?;
+    let meta = get_meta(&db)?;
+    Ok(PersistedReplicationOffset {
+        offset: meta.replication_offset.map(Cow::into_owned),
+        version: meta.replication_offset_version,
+    })
+}
+
+/// Convert the replication offset persisted in the [`PersistentState`] at `path` to the current
+/// encoding (see [`REPLICATION_OFFSET_VERSION`]), returning the converted offset.
+///
+/// This performs the same conversion that happens automatically when a [`PersistentState`] is
+/// opened, but unlike [`PersistentState::new`] never deletes the state if it can't be opened.
+pub fn migrate_persisted_replication_offset(path: &Path) -> Result<PersistedReplicationOffset> {
+    let meta = get_meta(&DB::open_for_read_only(
+        &base_options(&Default::default()),
+        path,
+        false,
+    )?)?;
+    if meta.serde_version != DfValue::SERDE_VERSION {
+        return Err(Error::SerdeVersionMismatch {
+            path: path.to_owned(),
+            persisted_version: meta.serde_version,
+            our_version: DfValue::SERDE_VERSION,
+        });
+    }
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let params = PersistenceParameters {
+        mode: DurabilityMode::Permanent,
+        db_dir: path.parent().map(Path::to_owned),
+        ..Default::default()
+    };
+    let state = PersistentState::new_inner(name.into(), path.to_owned(), vec![], &params)?;
+
+    Ok(PersistedReplicationOffset {
+        offset: state.replication_offset().cloned(),
+        version: REPLICATION_OFFSET_VERSION,
+    })
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum SnapshotMode {
     SnapshotModeEnabled,
@@ -308,6 +394,15 @@ pub enum Error {
         our_version: u8,
     },
 
+    #[error(
+        "Could not convert replication offset persisted at {} to the current encoding: {source}",
+        path.display(),
+    )]
+    ReplicationOffsetMigrationFailed {
+        path: PathBuf,
+        source: ReadySetError,
+    },
+
     #[error(transparent)]
     Io(#[from] io::Error),
 }
@@ -329,7 +424,9 @@ impl Error {
             // Could *maybe* try to slice up the IO errors here, but for now it's simpler to just
             // assume all IO errors are permanent
             Error::Io(_) => true,
-            Error::BadDbFormat | Error::SerdeVersionMismatch { .. } => false,
+            Error::BadDbFormat
+            | Error::SerdeVersionMismatch { .. }
+            | Error::ReplicationOffsetMigrationFailed { .. } => false,
         }
     }
 }
@@ -352,6 +449,13 @@ struct PersistentMeta<'a> {
     /// The latest replication offset that has been written to the base table backed by this
     /// [`PersistentState`]. Corresponds to [`PersistentState::replication_offset`]
     replication_offset: Option<Cow<'a, ReplicationOffset>>,
+
+    /// The version of the encoding used for [`replication_offset`](Self::replication_offset).
+    /// Metadata written before offsets were versioned deserializes with a version of `0`. This is
+    /// compared against [`REPLICATION_OFFSET_VERSION`] at startup, and if it's older the offset
+    /// is converted to the current encoding
+    #[serde(default)]
+    replication_offset_version: u8,
 }
 
 #[derive(Debug, Clone)]
@@ -436,6 +540,39 @@ impl PersistentStateHandle {
         RwLockWriteGuard::map(self.inner.write(), |i| &mut i.db)
     }
 
+    /// Call `f` with every record in the state, reading them in batches and releasing the lock on
+    /// the state between batches, so that writes to it aren't blocked for the whole scan. Records
+    /// written while the scan is in progress may or may not be seen.
+    pub fn for_each_record(&self, mut f: impl FnMut(Vec<DfValue>)) {
+        const BATCH_SIZE: usize = 1024;
+
+        let mut last_key: Option<Box<[u8]>> = None;
+        loop {
+            let inner = self.inner();
+            let db = &inner.db;
+            let cf = db.cf_handle(&inner.indices[0].column_family).unwrap();
+            let mut iter = db.raw_iterator_cf(cf);
+            match &last_key {
+                Some(key) => {
+                    iter.seek(key);
+                    if iter.key() == Some(&**key) {
+                        iter.next();
+                    }
+                }
+                None => iter.seek_to_first(),
+            }
+
+            for _ in 0..BATCH_SIZE {
+                let (Some(key), Some(value)) = (iter.key(), iter.value()) else {
+                    return;
+                };
+                f(deserialize_row(value));
+                last_key = Some(key.into());
+                iter.next();
+            }
+        }
+    }
+
     /// Perform a lookup for multiple equal keys at once. The results are returned in the order of
     /// the original keys.
     pub fn lookup_multi<'a>(
@@ -1292,7 +1429,13 @@ impl PersistentState {
             }
         };
 
-        let meta = increment_epoch(&db)?;
+        let mut meta = increment_epoch(&db)?;
+        migrate_replication_offset(&db, &mut meta).map_err(|source| {
+            Error::ReplicationOffsetMigrationFailed {
+                path: path.clone(),
+                source,
+            }
+        })?;
         let indices = meta.get_indices(&unique_keys);
 
         // If there are more column families than indices (+1 to account for the default column
@@ -1495,6 +1638,7 @@ impl PersistentState {
                 .collect(),
             epoch: self.epoch,
             replication_offset: self.replication_offset().map(Cow::Borrowed),
+            replication_offset_version: REPLICATION_OFFSET_VERSION,
         }
     }
 
@@ -2610,6 +2754,29 @@ mod tests {
         assert_eq!(state.cloned_records(), vec![first, second]);
     }
 
+    #[test]
+    fn persistent_state_handle_for_each_record() {
+        let mut state = setup_persistent("persistent_state_handle_for_each_record", None);
+        state.add_key(Index::new(IndexType::HashMap, vec![0]), None);
+        // More than one batch's worth of rows
+        let rows = (0..2500)
+            .map(|i| vec![DfValue::from(i), "Cat".into()])
+            .collect::<Vec<_>>();
+        state
+            .process_records(&mut rows.clone().into(), None, None)
+            .unwrap();
+
+        let mut records = vec![];
+        state
+            .read_handle()
+            .for_each_record(|record| records.push(record));
+        assert_eq!(records.len(), rows.len());
+        records.sort();
+        let mut expected = rows;
+        expected.sort();
+        assert_eq!(records, expected);
+    }
+
     #[test]
     #[cfg(not(windows))]
     fn persistent_state_drop() {
@@ -2684,6 +2851,7 @@ mod tests {
         let replication_offset = ReplicationOffset {
             offset: 12,
             replication_log_name: "binlog".to_owned(),
+            gtid_set: None,
         };
         state
             .process_records(&mut records, None, Some(replication_offset.clone()))
@@ -2692,6 +2860,58 @@ mod tests {
         assert_eq!(result, Some(&replication_offset));
     }
 
+    #[test]
+    fn untagged_replication_offset_is_migrated() {
+        let (_dir, name) = get_tmp_path();
+        let params = PersistenceParameters {
+            mode: DurabilityMode::Permanent,
+            ..Default::default()
+        };
+        let replication_offset = ReplicationOffset {
+            offset: (6 << 123) + (3 << 64) + 1234,
+            replication_log_name: "binlog".to_owned(),
+            gtid_set: None,
+        };
+        {
+            let mut state =
+                PersistentState::new(name.clone(), Vec::<Box<[usize]>>::new(), &params).unwrap();
+            state.add_key(Index::new(IndexType::HashMap, vec![0]), None);
+            state
+                .process_records(
+                    &mut vec![vec![1.into(), "A".into()]].into(),
+                    None,
+                    Some(replication_offset.clone()),
+                )
+                .unwrap();
+
+            // Simulate metadata written before replication offsets were versioned
+            let mut meta = state.meta();
+            meta.replication_offset_version = 0;
+            state.db.handle().save_meta(&meta);
+        }
+
+        let path = PathBuf::from(format!("{name}.db"));
+        assert_eq!(
+            read_persisted_replication_offset(&path).unwrap(),
+            PersistedReplicationOffset {
+                offset: Some(replication_offset.clone()),
+                version: 0,
+            }
+        );
+
+        let state = PersistentState::new(name, Vec::<Box<[usize]>>::new(), &params).unwrap();
+        assert_eq!(state.replication_offset(), Some(&replication_offset));
+        drop(state);
+
+        assert_eq!(
+            read_persisted_replication_offset(&path).unwrap(),
+            PersistedReplicationOffset {
+                offset: Some(replication_offset),
+                version: REPLICATION_OFFSET_VERSION,
+            }
+        );
+    }
+
     #[test]
     #[allow(clippy::op_ref)]
     fn persistent_state_prefix_transform() {
@@ -2750,6 +2970,7 @@ mod tests {
                 Some(ReplicationOffset {
                     offset: 1,
                     replication_log_name: String::new(),
+                    gtid_set: None,
                 }),
             )
             .unwrap();
@@ -2768,6 +2989,7 @@ mod tests {
                 Some(ReplicationOffset {
                     offset: 2,
                     replication_log_name: String::new(),
+                    gtid_set: None,
                 }),
             )
             .unwrap();
@@ -2781,6 +3003,7 @@ mod tests {
             Some(ReplicationOffset {
                 offset: 2,
                 replication_log_name: String::new(),
+                gtid_set: None,
             }),
         )
         .unwrap();
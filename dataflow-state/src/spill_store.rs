@@ -0,0 +1,394 @@
+//! On-disk overflow for the state of partially materialized readers
+//!
+//! A [`SpillStore`] holds the rows of keys that were evicted from a reader to stay within the
+//! memory limit, in a [RocksDB] database in a temporary directory that is deleted when the store is
+//! dropped. Rather than upquerying for an evicted key when it's next read, the reader's domain
+//! [reads](SpillStore::read) its rows back out of the store, which is slower than reading from
+//! memory but much cheaper than recomputing the results.
+//!
+//! All reads from and writes to the database happen on a thread owned by the store, so that disk
+//! I/O never blocks the domain. The store keeps the set of spilled keys in memory, and gives each
+//! spilled key a new generation, so that a read which completes after the key was written to,
+//! evicted again or spilled again can be told apart from one that's still valid (see
+//! [`SpillStore::finish_read`]).
+//!
+//! Writes to a key while it's spilled are not applied to the store - instead, the spilled rows for
+//! that key must be [discarded](SpillStore::remove), so that the key is recomputed with an upquery
+//! the next time it's read.
+//!
+//! [RocksDB]: https://rocksdb.org/
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use bincode::Options;
+use readyset_data::DfValue;
+use readyset_errors::{internal_err, ReadySetResult};
+use rocksdb::DB;
+use tempfile::TempDir;
+use tracing::warn;
+
+/// Called with the generation and rows of a key read from a [`SpillStore`]
+pub type SpillReadCallback = Box<dyn FnOnce(u64, ReadySetResult<Vec<Vec<DfValue>>>) + Send>;
+
+/// An operation on the database of a [`SpillStore`], run on its I/O thread
+enum Op {
+    Put { key: Vec<u8>, value: Vec<u8> },
+    Delete { key: Vec<u8> },
+    Read {
+        key: Vec<u8>,
+        generation: u64,
+        reply: SpillReadCallback,
+    },
+}
+
+/// Run the operations received on `ops` against `db`, until the [`SpillStore`] is dropped, then
+/// close the database and delete its directory
+fn run_io_thread(db: DB, dir: TempDir, ops: mpsc::Receiver<Op>) {
+    for op in ops {
+        match op {
+            Op::Put { key, value } => {
+                if let Err(error) = db.put(key, value) {
+                    // A later read of the key will fail, and the key will be upqueried instead
+                    warn!(%error, "Write to reader spill store failed");
+                }
+            }
+            Op::Delete { key } => {
+                if let Err(error) = db.delete(key) {
+                    warn!(%error, "Delete from reader spill store failed");
+                }
+            }
+            Op::Read {
+                key,
+                generation,
+                reply,
+            } => {
+                let rows = db
+                    .get_pinned(&key)
+                    .map_err(|e| internal_err!("Read from reader spill store failed: {e}"))
+                    .and_then(|value| {
+                        let value = value.ok_or_else(|| {
+                            internal_err!("Spilled key missing from reader spill store")
+                        })?;
+                        Ok(bincode::options().deserialize(&value)?)
+                    });
+                reply(generation, rows);
+            }
+        }
+    }
+
+    // The database must be closed before its directory is deleted
+    drop(db);
+    drop(dir);
+}
+
+/// A key in a [`SpillStore`]
+struct SpilledKey {
+    /// Incremented every time any key is spilled, so that a spilled key can be told apart from the
+    /// same key spilled again later
+    generation: u64,
+    /// The size, in bytes, of the serialized rows of the key
+    size: u64,
+    /// Whether a read of the key has been sent to the I/O thread, but not finished yet
+    reading: bool,
+}
+
+/// On-disk storage for the rows of keys evicted from a reader. See [the module
+/// documentation](self) for more information.
+pub struct SpillStore {
+    /// The keys in the store, indexed by the serialized key.
+    ///
+    /// Keeping the (much smaller) keys in memory lets us tell whether a key is spilled without
+    /// reading from disk, which matters since almost every miss and write to a hole checks.
+    keys: HashMap<Vec<u8>, SpilledKey>,
+    /// The total size, in bytes, of all the rows in the store
+    size: u64,
+    /// The maximum value of [`Self::size`], or `None` for unlimited
+    max_bytes: Option<u64>,
+    /// The generation to give the next key spilled
+    next_generation: u64,
+    /// Sends operations to the thread that owns the database. Dropping this stops the thread,
+    /// which then deletes the database.
+    ops: mpsc::Sender<Op>,
+}
+
+impl SpillStore {
+    /// Create a new, empty, spill store in a new directory prefixed with `name` inside of `dir`,
+    /// which can hold up to `max_bytes` bytes of rows (or unlimited if `None`)
+    pub fn new(dir: &Path, name: &str, max_bytes: Option<u64>) -> ReadySetResult<Self> {
+        fs::create_dir_all(dir)?;
+        let tmpdir = tempfile::Builder::new().prefix(name).tempdir_in(dir)?;
+
+        let mut opts = rocksdb::Options::default();
+        opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+        opts.create_if_missing(true);
+        let db = DB::open(&opts, tmpdir.path())
+            .map_err(|e| internal_err!("Could not open reader spill store: {e}"))?;
+
+        let (ops, ops_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name(format!("spill-{name}"))
+            .spawn(move || run_io_thread(db, tmpdir, ops_rx))?;
+
+        Ok(Self {
+            keys: HashMap::new(),
+            size: 0,
+            max_bytes,
+            next_generation: 0,
+            ops,
+        })
+    }
+
+    fn serialize_key(key: &[DfValue]) -> ReadySetResult<Vec<u8>> {
+        Ok(bincode::options().serialize(key)?)
+    }
+
+    fn send(&self, op: Op) -> ReadySetResult<()> {
+        self.ops
+            .send(op)
+            .map_err(|_| internal_err!("Reader spill store thread exited"))
+    }
+
+    /// Returns the number of keys in the store
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if there are no keys in the store
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the total size, in bytes, of the rows in the store
+    pub fn size_bytes(&self) -> u64 {
+        self.size
+    }
+
+    /// Write the rows of `key`, which is being evicted from memory, to the store.
+    ///
+    /// Returns `false`, without writing anything, if the rows would make the store exceed its
+    /// maximum size.
+    pub fn spill<'a, I>(&mut self, key: &[DfValue], rows: I) -> ReadySetResult<bool>
+    where
+        I: IntoIterator<Item = &'a [DfValue]>,
+    {
+        let key = Self::serialize_key(key)?;
+        let rows = rows.into_iter().collect::<Vec<_>>();
+        let value = bincode::options().serialize(&rows)?;
+        let value_size = value.len() as u64;
+
+        let previous_size = self.keys.get(&key).map_or(0, |k| k.size);
+        let new_size = self.size - previous_size + value_size;
+        if self.max_bytes.map_or(false, |max| new_size > max) {
+            return Ok(false);
+        }
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        self.send(Op::Put {
+            key: key.clone(),
+            value,
+        })?;
+        self.keys.insert(
+            key,
+            SpilledKey {
+                generation,
+                size: value_size,
+                reading: false,
+            },
+        );
+        self.size = new_size;
+        Ok(true)
+    }
+
+    /// Returns true if there are rows for `key` in the store
+    pub fn contains(&self, key: &[DfValue]) -> ReadySetResult<bool> {
+        if self.is_empty() {
+            return Ok(false);
+        }
+        Ok(self.keys.contains_key(&Self::serialize_key(key)?))
+    }
+
+    /// Start reading the rows for `key` from the store on its I/O thread, calling `reply` with the
+    /// generation of the key and its rows once they've been read. The key stays in the store until
+    /// the read is [finished](SpillStore::finish_read).
+    ///
+    /// Returns `false`, without calling `reply`, if `key` isn't in the store. Returns `true`
+    /// without starting another read if a read of `key` is already in progress.
+    pub fn read(&mut self, key: &[DfValue], reply: SpillReadCallback) -> ReadySetResult<bool> {
+        if self.is_empty() {
+            return Ok(false);
+        }
+        let key = Self::serialize_key(key)?;
+        let Some(spilled) = self.keys.get_mut(&key) else {
+            return Ok(false);
+        };
+        if !spilled.reading {
+            spilled.reading = true;
+            let generation = spilled.generation;
+            self.send(Op::Read {
+                key,
+                generation,
+                reply,
+            })?;
+        }
+        Ok(true)
+    }
+
+    /// Finish a read of `key` started by [`SpillStore::read`] which returned rows for
+    /// `generation`, removing the key from the store.
+    ///
+    /// Returns `false`, leaving the store as is, if the key was removed or spilled again since the
+    /// read started, in which case the rows that were read are out of date and must be discarded.
+    pub fn finish_read(&mut self, key: &[DfValue], generation: u64) -> ReadySetResult<bool> {
+        let key = Self::serialize_key(key)?;
+        match self.keys.get(&key) {
+            Some(spilled) if spilled.generation == generation => {
+                self.remove_serialized(key)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Discard the rows for `key` from the store, if any. Any read of `key` that's in progress will
+    /// be [rejected](SpillStore::finish_read) when it finishes.
+    pub fn remove(&mut self, key: &[DfValue]) -> ReadySetResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        self.remove_serialized(Self::serialize_key(key)?)
+    }
+
+    fn remove_serialized(&mut self, key: Vec<u8>) -> ReadySetResult<()> {
+        if let Some(spilled) = self.keys.remove(&key) {
+            self.size -= spilled.size;
+            self.send(Op::Delete { key })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn rows(rows: &[Vec<DfValue>]) -> impl Iterator<Item = &[DfValue]> {
+        rows.iter().map(|row| row.as_slice())
+    }
+
+    /// Read `key` from `store`, and wait for the read to finish
+    fn read(store: &mut SpillStore, key: &[DfValue]) -> Option<(u64, Vec<Vec<DfValue>>)> {
+        let (tx, rx) = mpsc::channel();
+        let reading = store
+            .read(
+                key,
+                Box::new(move |generation, rows| {
+                    tx.send((generation, rows.unwrap())).unwrap();
+                }),
+            )
+            .unwrap();
+        reading.then(|| rx.recv_timeout(Duration::from_secs(10)).unwrap())
+    }
+
+    #[test]
+    fn spill_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SpillStore::new(dir.path(), "spill_and_read", None).unwrap();
+        let key = vec![DfValue::from(1)];
+        let spilled = vec![
+            vec![DfValue::from(1), DfValue::from("a")],
+            vec![DfValue::from(1), DfValue::from("b")],
+        ];
+
+        assert!(store.spill(&key, rows(&spilled)).unwrap());
+        assert!(store.contains(&key).unwrap());
+        assert!(!store.contains(&[DfValue::from(2)]).unwrap());
+        assert!(store.size_bytes() > 0);
+
+        let (generation, read_rows) = read(&mut store, &key).unwrap();
+        assert_eq!(read_rows, spilled);
+        // The key stays in the store until the read is finished
+        assert!(store.contains(&key).unwrap());
+        assert!(store.finish_read(&key, generation).unwrap());
+        assert!(read(&mut store, &key).is_none());
+        assert!(store.is_empty());
+        assert_eq!(store.size_bytes(), 0);
+    }
+
+    #[test]
+    fn remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SpillStore::new(dir.path(), "remove", None).unwrap();
+        let key = vec![DfValue::from(1), DfValue::from(2)];
+        store
+            .spill(&key, rows(&[vec![DfValue::from(1), DfValue::from(2)]]))
+            .unwrap();
+
+        store.remove(&key).unwrap();
+        assert!(!store.contains(&key).unwrap());
+        assert!(read(&mut store, &key).is_none());
+    }
+
+    #[test]
+    fn reads_are_rejected_after_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SpillStore::new(dir.path(), "reads_are_rejected", None).unwrap();
+        let key = vec![DfValue::from(1)];
+        store
+            .spill(&key, rows(&[vec![DfValue::from(1), DfValue::from("a")]]))
+            .unwrap();
+
+        // A write to the key while it's being read discards it
+        let (generation, _) = read(&mut store, &key).unwrap();
+        store.remove(&key).unwrap();
+        assert!(!store.finish_read(&key, generation).unwrap());
+
+        // As does spilling it again
+        store
+            .spill(&key, rows(&[vec![DfValue::from(1), DfValue::from("b")]]))
+            .unwrap();
+        let (generation, _) = read(&mut store, &key).unwrap();
+        store
+            .spill(&key, rows(&[vec![DfValue::from(1), DfValue::from("c")]]))
+            .unwrap();
+        assert!(!store.finish_read(&key, generation).unwrap());
+
+        let (generation, read_rows) = read(&mut store, &key).unwrap();
+        assert_eq!(read_rows, vec![vec![DfValue::from(1), DfValue::from("c")]]);
+        assert!(store.finish_read(&key, generation).unwrap());
+    }
+
+    #[test]
+    fn respects_max_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SpillStore::new(dir.path(), "respects_max_bytes", Some(64)).unwrap();
+        let big_row = vec![vec![DfValue::from("x".repeat(128))]];
+
+        assert!(!store.spill(&[DfValue::from(1)], rows(&big_row)).unwrap());
+        assert!(store.is_empty());
+        assert!(store
+            .spill(&[DfValue::from(2)], rows(&[vec![DfValue::from(2)]]))
+            .unwrap());
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn deletes_directory_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SpillStore::new(dir.path(), "deletes_directory_on_drop", None).unwrap();
+        drop(store);
+
+        // The directory is deleted by the I/O thread once it sees the store was dropped
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while fs::read_dir(dir.path()).unwrap().count() > 0 {
+            assert!(Instant::now() < deadline, "spill store directory not deleted");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
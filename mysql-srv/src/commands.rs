@@ -1,3 +1,5 @@
+use std::fmt;
+
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take, take_until};
 use nom::combinator::{map, map_res, opt, rest};
@@ -17,12 +19,17 @@ pub struct ClientHandshake<'a> {
     pub password: &'a [u8],
     pub database: Option<&'a str>,
     pub auth_plugin_name: Option<&'a str>,
+    /// The connection attributes sent by the client, if it connected with `CLIENT_CONNECT_ATTRS`,
+    /// as pairs of names and values
+    pub connect_attrs: Vec<(&'a str, &'a str)>,
+    /// The zstd compression level to use, if the client negotiated zstd compression
+    pub zstd_compression_level: Option<u8>,
 }
 
 /// Parse a "length-encoded integer" as specified by the [mysql binary protocol documentation][docs]
 ///
 /// [docs]: https://dev.mysql.com/doc/internals/en/integer.html#length-encoded-integer
-fn lenenc_int(i: &[u8]) -> IResult<&[u8], i64> {
+pub(crate) fn lenenc_int(i: &[u8]) -> IResult<&[u8], i64> {
     let (i, first_byte) = le_u8(i)?;
     match first_byte {
         b @ 0x00..=0xfb => Ok((i, b.into())),
@@ -52,6 +59,41 @@ fn null_terminated_string(i: &[u8]) -> IResult<&[u8], &str> {
     Ok((i, res))
 }
 
+fn lenenc_bytes(i: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (i, len) = lenenc_int(i)?;
+    take(len as usize)(i)
+}
+
+/// Parse the connection attributes sent with a handshake response or `COM_CHANGE_USER`: the total
+/// length of the attributes, followed by the name and value of each attribute. Attributes whose
+/// name or value isn't valid UTF-8 are skipped, rather than keeping the client from connecting.
+fn connect_attrs(i: &[u8]) -> IResult<&[u8], Vec<(&str, &str)>> {
+    let (i, len) = lenenc_int(i)?;
+    let (i, mut attrs_bytes) = take(len as usize)(i)?;
+    let mut attrs = Vec::new();
+    while !attrs_bytes.is_empty() {
+        let (rest, name) = lenenc_bytes(attrs_bytes)?;
+        let (rest, value) = lenenc_bytes(rest)?;
+        if let (Ok(name), Ok(value)) = (std::str::from_utf8(name), std::str::from_utf8(value)) {
+            attrs.push((name, value));
+        }
+        attrs_bytes = rest;
+    }
+    Ok((i, attrs))
+}
+
+/// Returns true if `i` is an `SSLRequest` - the first 32 bytes of a handshake response with
+/// `CLIENT_SSL` set, which the client sends instead of the full handshake response to upgrade the
+/// connection to TLS before authenticating.
+///
+/// <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_ssl_request.html>
+pub fn is_ssl_request(i: &[u8]) -> bool {
+    i.len() == 32
+        && le_u32::<_, ()>(i).map_or(false, |(_, capabilities)| {
+            CapabilityFlags::from_bits_truncate(capabilities).contains(CapabilityFlags::CLIENT_SSL)
+        })
+}
+
 /// <https://dev.mysql.com/doc/internals/en/connection-phase-packets.html#packet-Protocol::HandshakeResponse41>
 pub fn client_handshake(i: &[u8]) -> IResult<&[u8], ClientHandshake<'_>> {
     let (i, capabilities) = map(le_u32, CapabilityFlags::from_bits_truncate)(i)?;
@@ -82,6 +124,19 @@ pub fn client_handshake(i: &[u8]) -> IResult<&[u8], ClientHandshake<'_>> {
         (i, None)
     };
 
+    let (i, connect_attrs) = if capabilities.contains(CapabilityFlags::CLIENT_CONNECT_ATTRS) {
+        connect_attrs(i)?
+    } else {
+        (i, vec![])
+    };
+
+    let (i, zstd_compression_level) =
+        if capabilities.contains(CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM) {
+            map(le_u8, Some)(i)?
+        } else {
+            (i, None)
+        };
+
     Ok((
         i,
         ClientHandshake {
@@ -92,10 +147,37 @@ pub fn client_handshake(i: &[u8]) -> IResult<&[u8], ClientHandshake<'_>> {
             password,
             database,
             auth_plugin_name,
+            connect_attrs,
+            zstd_compression_level,
         },
     ))
 }
 
+/// The contents of a `COM_CHANGE_USER` packet
+///
+/// <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_com_change_user.html>
+#[derive(PartialEq, Eq)]
+pub struct ChangeUser<'a> {
+    pub username: &'a str,
+    pub password: &'a [u8],
+    pub database: Option<&'a str>,
+    pub auth_plugin_name: Option<&'a str>,
+    /// The connection attributes sent by the client, if it connected with `CLIENT_CONNECT_ATTRS`
+    pub connect_attrs: Vec<(&'a str, &'a str)>,
+}
+
+// Written out by hand so that the password is never logged
+impl<'a> fmt::Debug for ChangeUser<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeUser")
+            .field("username", &self.username)
+            .field("database", &self.database)
+            .field("auth_plugin_name", &self.auth_plugin_name)
+            .field("connect_attrs", &self.connect_attrs)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Command<'a> {
     Query(&'a [u8]),
@@ -104,9 +186,13 @@ pub enum Command<'a> {
     ResetStmtData(u32),
     Prepare(&'a [u8]),
     Init(&'a [u8]),
-    ComSetOption(&'a [u8]),
+    /// `COM_SET_OPTION`, with the option being set: 0 to enable multiple statements per query
+    /// (`MYSQL_OPTION_MULTI_STATEMENTS_ON`), or 1 to disable them
+    /// (`MYSQL_OPTION_MULTI_STATEMENTS_OFF`)
+    ComSetOption(u16),
     Execute {
         stmt: u32,
+        flags: u8,
         params: &'a [u8],
     },
     SendLongData {
@@ -116,13 +202,24 @@ pub enum Command<'a> {
     },
     Ping,
     Quit,
+    /// `COM_RESET_CONNECTION`
+    ResetConnection,
+    /// `COM_CHANGE_USER`
+    ChangeUser(ChangeUser<'a>),
 }
 
 pub fn execute(i: &[u8]) -> IResult<&[u8], Command<'_>> {
     let (i, stmt) = le_u32(i)?;
-    let (i, _flags) = take(1u8)(i)?;
+    let (i, flags) = le_u8(i)?;
     let (i, _iterations) = le_u32(i)?;
-    Ok((&[], Command::Execute { stmt, params: i }))
+    Ok((
+        &[],
+        Command::Execute {
+            stmt,
+            flags,
+            params: i,
+        },
+    ))
 }
 
 pub fn send_long_data(i: &[u8]) -> IResult<&[u8], Command<'_>> {
@@ -138,6 +235,34 @@ pub fn send_long_data(i: &[u8]) -> IResult<&[u8], Command<'_>> {
     ))
 }
 
+/// Parse the body of a `COM_CHANGE_USER` packet. We always advertise `CLIENT_SECURE_CONNECTION`
+/// and `CLIENT_PLUGIN_AUTH`, so the password is always length-prefixed, and the character set and
+/// auth plugin name follow the database if the client sent them, followed by the connection
+/// attributes if the client connected with `CLIENT_CONNECT_ATTRS`.
+pub fn change_user(i: &[u8]) -> IResult<&[u8], Command<'_>> {
+    let (i, username) = null_terminated_string(i)?;
+    let (i, password_length) = le_u8(i)?;
+    let (i, password) = take(password_length)(i)?;
+    let (i, database) = null_terminated_string(i)?;
+    let (i, _charset) = opt(le_u16)(i)?;
+    let (i, auth_plugin_name) = opt(null_terminated_string)(i)?;
+    let (i, connect_attrs) = if i.is_empty() {
+        (i, vec![])
+    } else {
+        connect_attrs(i)?
+    };
+    Ok((
+        i,
+        Command::ChangeUser(ChangeUser {
+            username,
+            password,
+            database: Some(database).filter(|database| !database.is_empty()),
+            auth_plugin_name,
+            connect_attrs,
+        }),
+    ))
+}
+
 pub fn parse(i: &[u8]) -> IResult<&[u8], Command<'_>> {
     alt((
         map(
@@ -153,7 +278,7 @@ pub fn parse(i: &[u8]) -> IResult<&[u8], Command<'_>> {
             Command::Init,
         ),
         map(
-            preceded(tag(&[CommandByte::COM_SET_OPTION as u8]), rest),
+            preceded(tag(&[CommandByte::COM_SET_OPTION as u8]), le_u16),
             Command::ComSetOption,
         ),
         map(
@@ -175,6 +300,10 @@ pub fn parse(i: &[u8]) -> IResult<&[u8], Command<'_>> {
         ),
         map(tag(&[CommandByte::COM_QUIT as u8]), |_| Command::Quit),
         map(tag(&[CommandByte::COM_PING as u8]), |_| Command::Ping),
+        map(tag(&[CommandByte::COM_RESET_CONNECTION as u8]), |_| {
+            Command::ResetConnection
+        }),
+        preceded(tag(&[CommandByte::COM_CHANGE_USER as u8]), change_user),
     ))(i)
 }
 
@@ -215,6 +344,116 @@ mod tests {
         assert_eq!(handshake.maxps, 16777216);
     }
 
+    #[test]
+    fn it_parses_zstd_compression_level() {
+        let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+            | CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | CapabilityFlags::CLIENT_PLUGIN_AUTH
+            | CapabilityFlags::CLIENT_CONNECT_ATTRS
+            | CapabilityFlags::CLIENT_ZSTD_COMPRESSION_ALGORITHM;
+        let mut data = capabilities.bits().to_le_bytes().to_vec();
+        data.extend_from_slice(&16777216u32.to_le_bytes());
+        data.push(UTF8_GENERAL_CI as u8);
+        data.extend_from_slice(&[0; 23]);
+        data.extend_from_slice(b"jon\0");
+        data.push(0);
+        data.extend_from_slice(b"mysql_native_password\0");
+        data.extend_from_slice(&[3, 1, b'a', 0]);
+        data.push(7);
+
+        let (rest, handshake) = client_handshake(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(handshake.username, "jon");
+        assert_eq!(handshake.auth_plugin_name, Some("mysql_native_password"));
+        assert_eq!(handshake.connect_attrs, vec![("a", "")]);
+        assert_eq!(handshake.zstd_compression_level, Some(7));
+    }
+
+    #[test]
+    fn it_parses_connect_attrs() {
+        let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+            | CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | CapabilityFlags::CLIENT_CONNECT_ATTRS;
+        let mut data = capabilities.bits().to_le_bytes().to_vec();
+        data.extend_from_slice(&16777216u32.to_le_bytes());
+        data.push(UTF8_GENERAL_CI as u8);
+        data.extend_from_slice(&[0; 23]);
+        data.extend_from_slice(b"jon\0");
+        data.push(0);
+        let mut attrs = vec![12];
+        attrs.extend_from_slice(b"_client_name");
+        attrs.push(8);
+        attrs.extend_from_slice(b"libmysql");
+        attrs.push(12);
+        attrs.extend_from_slice(b"program_name");
+        attrs.push(5);
+        attrs.extend_from_slice(b"mysql");
+        data.push(attrs.len() as u8);
+        data.extend_from_slice(&attrs);
+
+        let (rest, handshake) = client_handshake(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            handshake.connect_attrs,
+            vec![("_client_name", "libmysql"), ("program_name", "mysql")]
+        );
+    }
+
+    #[test]
+    fn it_skips_connect_attrs_that_arent_utf8() {
+        let capabilities = CapabilityFlags::CLIENT_PROTOCOL_41
+            | CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | CapabilityFlags::CLIENT_CONNECT_ATTRS;
+        let mut data = capabilities.bits().to_le_bytes().to_vec();
+        data.extend_from_slice(&16777216u32.to_le_bytes());
+        data.push(UTF8_GENERAL_CI as u8);
+        data.extend_from_slice(&[0; 23]);
+        data.extend_from_slice(b"jon\0");
+        data.push(0);
+        let mut attrs = vec![4];
+        attrs.extend_from_slice(b"host");
+        attrs.push(2);
+        attrs.extend_from_slice(&[0xc3, 0x28]);
+        attrs.push(12);
+        attrs.extend_from_slice(b"program_name");
+        attrs.push(5);
+        attrs.extend_from_slice(b"mysql");
+        data.push(attrs.len() as u8);
+        data.extend_from_slice(&attrs);
+
+        let (rest, handshake) = client_handshake(&data).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(handshake.username, "jon");
+        assert_eq!(handshake.connect_attrs, vec![("program_name", "mysql")]);
+
+        let mut change_user = b"\x11jon\0\0\0\x21\x00plugin\0".to_vec();
+        change_user.push(attrs.len() as u8);
+        change_user.extend_from_slice(&attrs);
+        let (_, cmd) = parse(&change_user).unwrap();
+        let Command::ChangeUser(change_user) = cmd else {
+            panic!("expected COM_CHANGE_USER, got {cmd:?}");
+        };
+        assert_eq!(change_user.connect_attrs, vec![("program_name", "mysql")]);
+    }
+
+    #[test]
+    fn it_recognizes_ssl_request() {
+        let mut ssl_request = vec![0x00; 32];
+        ssl_request[..4].copy_from_slice(
+            &(CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_SSL)
+                .bits()
+                .to_le_bytes(),
+        );
+        assert!(is_ssl_request(&ssl_request));
+
+        let mut full_handshake = ssl_request.clone();
+        full_handshake.extend_from_slice(b"jon\0\0");
+        assert!(!is_ssl_request(&full_handshake));
+
+        ssl_request[..4].copy_from_slice(&CapabilityFlags::CLIENT_PROTOCOL_41.bits().to_le_bytes());
+        assert!(!is_ssl_request(&ssl_request));
+    }
+
     #[tokio::test]
     async fn it_parses_request() {
         let data = &[
@@ -251,4 +490,55 @@ mod tests {
             Command::ListFields(&b"select @@version_comment limit 1"[..])
         );
     }
+
+    #[tokio::test]
+    async fn it_parses_set_option() {
+        let data = &[0x03, 0x00, 0x00, 0x00, 0x1b, 0x01, 0x00];
+        let r = Cursor::new(&data[..]);
+        let mut pr = PacketReader::new(r);
+        let (_, p) = pr.next().await.unwrap().unwrap();
+        let (_, cmd) = parse(&p).unwrap();
+        assert_eq!(cmd, Command::ComSetOption(1));
+    }
+
+    #[tokio::test]
+    async fn it_parses_change_user() {
+        let mut data = vec![0x11];
+        data.extend_from_slice(b"jon\0");
+        data.push(3);
+        data.extend_from_slice(b"pwd");
+        data.extend_from_slice(b"db\0");
+        data.extend_from_slice(&[0x21, 0x00]);
+        data.extend_from_slice(b"mysql_native_password\0");
+        let (_, cmd) = parse(&data).unwrap();
+        assert_eq!(
+            cmd,
+            Command::ChangeUser(ChangeUser {
+                username: "jon",
+                password: b"pwd",
+                database: Some("db"),
+                auth_plugin_name: Some("mysql_native_password"),
+                connect_attrs: vec![],
+            })
+        );
+        assert!(!format!("{cmd:?}").contains("pwd"));
+
+        let (_, cmd) = parse(b"\x11jon\0\0\0").unwrap();
+        assert_eq!(
+            cmd,
+            Command::ChangeUser(ChangeUser {
+                username: "jon",
+                password: b"",
+                database: None,
+                auth_plugin_name: None,
+                connect_attrs: vec![],
+            })
+        );
+
+        let (_, cmd) = parse(b"\x11jon\0\0\0\x21\x00plugin\0\x04\x01a\x01b").unwrap();
+        let Command::ChangeUser(change_user) = cmd else {
+            panic!("expected COM_CHANGE_USER, got {cmd:?}");
+        };
+        assert_eq!(change_user.connect_attrs, vec![("a", "b")]);
+    }
 }
@@ -0,0 +1,309 @@
+//! The MySQL compressed protocol. If the client negotiates compression in the initial handshake,
+//! every packet after the server's OK packet is sent as the payload of a compressed packet, with
+//! its own header:
+//!
+//! - 3 bytes: the length of the (compressed) payload
+//! - 1 byte: the compressed sequence id
+//! - 3 bytes: the length of the payload before compression, or 0 if it was sent uncompressed
+//!
+//! <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_compression.html>
+
+use std::io::{self, IoSlice, Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use metrics::counter;
+use readyset_client_metrics::recorded;
+
+const U24_MAX: usize = 16_777_215;
+
+/// The length of a compressed packet header
+const HEADER_LEN: usize = 7;
+
+/// Payloads shorter than this are sent uncompressed, since compressing them isn't worth it. This
+/// is the same threshold MySQL uses.
+const MIN_COMPRESS_LENGTH: usize = 50;
+
+/// Writes that compress by less than this percentage of their length aren't worth compressing
+const MIN_SAVED_PERCENT: usize = 10;
+
+/// After a write that wasn't worth compressing, this many more writes are sent uncompressed
+/// without trying to compress them, since the data sent to a client tends to compress similarly
+/// from one write to the next
+const SKIPPED_AFTER_INCOMPRESSIBLE: u32 = 8;
+
+/// How to compress replies to clients that negotiate the compressed protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionOptions {
+    /// Writes to the client shorter than this many bytes are sent uncompressed. Packets are
+    /// compressed each time they're flushed to the client, and the rows of a result set are
+    /// flushed in batches, so this skips compressing small result sets, and the last batch of rows
+    /// of a large result set if it's small.
+    pub min_length: usize,
+    /// The zlib compression level, from 0 (fastest) to 9 (smallest). Clients that negotiate zstd
+    /// compression choose the zstd compression level themselves.
+    pub zlib_level: u32,
+}
+
+impl Default for CompressionOptions {
+    fn default() -> Self {
+        Self {
+            min_length: 1024,
+            zlib_level: 6,
+        }
+    }
+}
+
+/// A compression algorithm negotiated with the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// zlib at the given compression level, negotiated with `CLIENT_COMPRESS`
+    Zlib(u32),
+    /// zstd at the given compression level, negotiated with `CLIENT_ZSTD_COMPRESSION_ALGORITHM`
+    Zstd(i32),
+}
+
+impl Compression {
+    fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Zlib(level) => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::new(level));
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Compression::Zstd(level) => zstd::bulk::compress(data, level),
+        }
+    }
+
+    fn decompress(self, data: &[u8], len: usize) -> io::Result<Vec<u8>> {
+        let decompressed = match self {
+            Compression::Zlib(_) => {
+                let mut decompressed = Vec::with_capacity(len);
+                ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+                decompressed
+            }
+            Compression::Zstd(_) => zstd::bulk::decompress(data, len)?,
+        };
+        if decompressed.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "compressed packet decompressed to {} bytes, but expected {len}",
+                    decompressed.len()
+                ),
+            ));
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Compresses data written to the client into compressed packets
+pub struct Compressor {
+    compression: Compression,
+    /// Writes shorter than this are sent uncompressed, see [`CompressionOptions::min_length`]
+    min_length: usize,
+    /// The number of writes to send uncompressed (that are long enough to compress) before trying
+    /// to compress again, after a write didn't compress well
+    skip: u32,
+    /// The sequence id of the next compressed packet
+    pub seq: u8,
+}
+
+impl Compressor {
+    pub fn new(compression: Compression, min_length: usize) -> Self {
+        Self {
+            compression,
+            min_length,
+            skip: 0,
+            seq: 0,
+        }
+    }
+
+    /// Encode all of `slices`, which are being flushed to the client together, as a sequence of
+    /// compressed packets. Short writes are sent uncompressed, as are writes following one that
+    /// didn't compress well.
+    pub fn compress(&mut self, slices: &[IoSlice<'_>]) -> io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(slices.iter().map(|s| s.len()).sum());
+        for slice in slices {
+            data.extend_from_slice(slice);
+        }
+        let compress = if data.len() < self.min_length.max(MIN_COMPRESS_LENGTH) {
+            counter!(recorded::MYSQL_COMPRESSION_SKIPPED_WRITES, 1, "reason" => "short");
+            false
+        } else if self.skip > 0 {
+            counter!(recorded::MYSQL_COMPRESSION_SKIPPED_WRITES, 1, "reason" => "incompressible");
+            self.skip -= 1;
+            false
+        } else {
+            true
+        };
+
+        let mut packets = Vec::with_capacity(data.len() + HEADER_LEN);
+        let mut bytes_saved = 0;
+        for chunk in data.chunks(U24_MAX) {
+            let compressed = if compress && chunk.len() >= MIN_COMPRESS_LENGTH {
+                Some(self.compression.compress(chunk)?).filter(|c| c.len() < chunk.len())
+            } else {
+                None
+            };
+            let (payload, uncompressed_len) = match &compressed {
+                Some(compressed) => {
+                    bytes_saved += chunk.len() - compressed.len();
+                    (&compressed[..], chunk.len())
+                }
+                None => (chunk, 0),
+            };
+
+            packets.extend_from_slice(&(payload.len() as u32).to_le_bytes()[..3]);
+            packets.push(self.seq);
+            packets.extend_from_slice(&(uncompressed_len as u32).to_le_bytes()[..3]);
+            packets.extend_from_slice(payload);
+            self.seq = self.seq.wrapping_add(1);
+        }
+        if compress && bytes_saved * 100 < data.len() * MIN_SAVED_PERCENT {
+            self.skip = SKIPPED_AFTER_INCOMPRESSIBLE;
+        }
+        if bytes_saved > 0 {
+            counter!(recorded::MYSQL_COMPRESSION_BYTES_SAVED, bytes_saved as u64);
+        }
+        Ok(packets)
+    }
+}
+
+/// Decompresses compressed packets read from the client
+pub struct Decompressor {
+    compression: Compression,
+    /// Bytes read from the client which don't yet make up a full compressed packet
+    pub raw: Vec<u8>,
+    /// Decompressed bytes which haven't been read yet
+    pub decompressed: Vec<u8>,
+    /// The sequence id of the last compressed packet read
+    pub seq: u8,
+}
+
+impl Decompressor {
+    /// Create a new [`Decompressor`], with `raw` bytes already read from the client
+    pub fn new(compression: Compression, raw: Vec<u8>) -> Self {
+        Self {
+            compression,
+            raw,
+            decompressed: Vec::new(),
+            seq: 0,
+        }
+    }
+
+    /// Decompress the next compressed packet in `raw`, if it's been read in full, returning
+    /// whether there was one
+    pub fn decompress_next(&mut self) -> io::Result<bool> {
+        let Some(header) = self.raw.get(..HEADER_LEN) else {
+            return Ok(false);
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+        let seq = header[3];
+        let uncompressed_len = u32::from_le_bytes([header[4], header[5], header[6], 0]) as usize;
+        let Some(payload) = self.raw.get(HEADER_LEN..HEADER_LEN + len) else {
+            return Ok(false);
+        };
+
+        if uncompressed_len == 0 {
+            self.decompressed.extend_from_slice(payload);
+        } else {
+            let decompressed = self.compression.decompress(payload, uncompressed_len)?;
+            self.decompressed.extend_from_slice(&decompressed);
+        }
+        self.seq = seq;
+        self.raw.drain(..HEADER_LEN + len);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(compression: Compression, data: &[u8]) {
+        let mut compressor = Compressor::new(compression, 0);
+        compressor.seq = 3;
+        let packets = compressor.compress(&[IoSlice::new(data)]).unwrap();
+
+        let mut decompressor = Decompressor::new(compression, packets);
+        while decompressor.decompress_next().unwrap() {}
+        assert!(decompressor.raw.is_empty());
+        assert_eq!(decompressor.decompressed, data);
+        assert_eq!(decompressor.seq, compressor.seq.wrapping_sub(1));
+    }
+
+    #[test]
+    fn round_trips_zlib() {
+        round_trip(Compression::Zlib(6), b"short");
+        round_trip(Compression::Zlib(6), &[7; 1000]);
+        round_trip(Compression::Zlib(0), &[7; 1000]);
+    }
+
+    #[test]
+    fn round_trips_zstd() {
+        round_trip(Compression::Zstd(3), b"short");
+        round_trip(Compression::Zstd(3), &[7; 1000]);
+    }
+
+    #[test]
+    fn sends_short_payloads_uncompressed() {
+        let mut compressor = Compressor::new(Compression::Zlib(6), 0);
+        let packets = compressor
+            .compress(&[IoSlice::new(b"ab"), IoSlice::new(b"c")])
+            .unwrap();
+        assert_eq!(packets, [3, 0, 0, 0, 0, 0, 0, b'a', b'b', b'c']);
+        assert_eq!(compressor.seq, 1);
+    }
+
+    #[test]
+    fn sends_writes_shorter_than_min_length_uncompressed() {
+        let mut compressor = Compressor::new(Compression::Zlib(6), 200);
+        let packets = compressor.compress(&[IoSlice::new(&[1; 100])]).unwrap();
+        assert_eq!(packets.len(), HEADER_LEN + 100);
+        assert_eq!(packets[4..HEADER_LEN], [0, 0, 0]);
+
+        let packets = compressor.compress(&[IoSlice::new(&[1; 200])]).unwrap();
+        assert!(packets.len() < HEADER_LEN + 200);
+    }
+
+    #[test]
+    fn skips_compressing_after_incompressible_write() {
+        // Pseudorandom bytes, which zlib can't compress
+        let mut state = 1u32;
+        let incompressible = (0..200)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect::<Vec<_>>();
+        let mut compressor = Compressor::new(Compression::Zlib(6), 0);
+        compressor
+            .compress(&[IoSlice::new(&incompressible)])
+            .unwrap();
+        assert_eq!(compressor.skip, SKIPPED_AFTER_INCOMPRESSIBLE);
+
+        // Even compressible writes are sent uncompressed for a while
+        for _ in 0..SKIPPED_AFTER_INCOMPRESSIBLE {
+            let packets = compressor.compress(&[IoSlice::new(&[1; 200])]).unwrap();
+            assert_eq!(packets[4..HEADER_LEN], [0, 0, 0]);
+        }
+        let packets = compressor.compress(&[IoSlice::new(&[1; 200])]).unwrap();
+        assert!(packets.len() < HEADER_LEN + 200);
+        assert_eq!(compressor.skip, 0);
+    }
+
+    #[test]
+    fn waits_for_full_packet() {
+        let mut compressor = Compressor::new(Compression::Zlib(6), 0);
+        let packets = compressor.compress(&[IoSlice::new(&[1; 100])]).unwrap();
+
+        let mut decompressor = Decompressor::new(Compression::Zlib(6), packets[..10].to_vec());
+        assert!(!decompressor.decompress_next().unwrap());
+        decompressor.raw.extend_from_slice(&packets[10..]);
+        assert!(decompressor.decompress_next().unwrap());
+        assert_eq!(decompressor.decompressed, [1; 100]);
+    }
+}
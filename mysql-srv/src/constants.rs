@@ -53,8 +53,17 @@ pub const CAN_HANDLE_EXPIRED_PASSWORDS: u32 = 0x00400000;
 pub const SESSION_TRACK: u32 = 0x00800000;
 /// Can send OK after a Text Resultset.
 pub const DEPRECATE_EOF: u32 = 0x01000000;
+/// Can use zstd compression protocol
+pub const ZSTD_COMPRESSION_ALGORITHM: u32 = 0x04000000;
+/// Can send query attributes with COM_QUERY and COM_STMT_EXECUTE
+pub const QUERY_ATTRIBUTES: u32 = 0x08000000;
 /// Client supports plugin authentication
 pub const CLIENT_PLUGIN_AUTH: u32 = 0x00080000;
 
 pub const SSL_VERIFY_SERVER_CERT: u32 = 0x40000000;
 pub const REMEMBER_OPTIONS: u32 = 0x80000000;
+
+/// `COM_SET_OPTION` option enabling multiple statements per query
+pub const MYSQL_OPTION_MULTI_STATEMENTS_ON: u16 = 0;
+/// `COM_SET_OPTION` option disabling multiple statements per query
+pub const MYSQL_OPTION_MULTI_STATEMENTS_OFF: u16 = 1;
@@ -4761,3 +4761,27 @@ impl ErrorKind {
         }
     }
 }
+
+impl ErrorKind {
+    /// Returns the error kind for the given error code, or `None` if it isn't one of the codes
+    /// listed here, which only go up to those of MySQL 5.7. Unlike the [`From`] impl, this never
+    /// panics, so it can be used for error codes returned by an arbitrary server.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use mysql_srv::ErrorKind;
+    ///
+    /// assert_eq!(
+    ///     ErrorKind::from_code(1146),
+    ///     Some(ErrorKind::ER_NO_SUCH_TABLE)
+    /// );
+    /// // ER_INVALID_JSON_TEXT, added in MySQL 5.7.8
+    /// assert_eq!(ErrorKind::from_code(3140), None);
+    /// ```
+    pub fn from_code(code: u16) -> Option<Self> {
+        (ErrorKind::ER_HASHCHK as u16..=ErrorKind::ER_SLAVE_HAS_MORE_GTIDS_THAN_MASTER as u16)
+            .contains(&code)
+            .then(|| code.into())
+    }
+}
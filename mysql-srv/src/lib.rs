@@ -123,7 +123,7 @@
 //!                 let _guard = rt.handle().enter();
 //!                 tokio::net::TcpStream::from_std(s).unwrap()
 //!             };
-//!             rt.block_on(MySqlIntermediary::run_on_tcp(Backend, s, false))
+//!             rt.block_on(MySqlIntermediary::run_on_tcp(Backend, s, None))
 //!                 .unwrap();
 //!         }
 //!     });
@@ -164,27 +164,40 @@ use std::io;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use constants::{CLIENT_PLUGIN_AUTH, PROTOCOL_41, RESERVED, SECURE_CONNECTION};
+use constants::{
+    CLIENT_PLUGIN_AUTH, COMPRESS, CONNECT_ATTRS, FOUND_ROWS, MULTI_RESULTS, MULTI_STATEMENTS,
+    MYSQL_OPTION_MULTI_STATEMENTS_OFF, MYSQL_OPTION_MULTI_STATEMENTS_ON, PROTOCOL_41,
+    QUERY_ATTRIBUTES, RESERVED, SECURE_CONNECTION, SESSION_TRACK, SSL, ZSTD_COMPRESSION_ALGORITHM,
+};
 use error::{other_error, OtherErrorKind};
 use mysql_common::constants::CapabilityFlags;
 use readyset_data::DfType;
+use readyset_tracing::statement_logging::Component;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net;
-use tracing::{debug, info, trace};
+use tracing::{debug, info, trace, warn};
 use writers::write_err;
 
-use crate::authentication::{generate_auth_data, hash_password, AUTH_PLUGIN_NAME};
+use crate::authentication::{generate_auth_data, hash_password, AuthData, AUTH_PLUGIN_NAME};
+use crate::compression::Compression;
+pub use crate::compression::CompressionOptions;
 pub use crate::myc::constants::{ColumnFlags, ColumnType, StatusFlags};
+pub use crate::tls::{tls_config, ClientStream, TlsConfig};
 pub use crate::writers::prepare_column_definitions;
 
 mod authentication;
 mod commands;
+mod compression;
 mod constants;
 pub mod error;
 mod errorcodes;
 mod packet;
 mod params;
+mod query_attributes;
 mod resultset;
+mod session_state;
+mod statements;
+mod tls;
 mod value;
 mod writers;
 
@@ -228,7 +241,11 @@ impl From<&mysql_async::Column> for Column {
 pub use crate::error::MsqlSrvError;
 pub use crate::errorcodes::ErrorKind;
 pub use crate::params::{ParamParser, ParamValue, Params};
-pub use crate::resultset::{InitWriter, QueryResultWriter, RowWriter, StatementMetaWriter};
+pub use crate::query_attributes::QueryAttribute;
+pub use crate::resultset::{
+    EncodedRow, InitWriter, MultiResultWriter, QueryResultWriter, RowWriter, StatementMetaWriter,
+};
+pub use crate::session_state::SessionStateChange;
 pub use crate::value::{ToMySqlValue, Value, ValueInner};
 
 /// Implementors of this trait can be used to drive a MySQL-compatible database backend.
@@ -270,11 +287,72 @@ pub trait MySqlShim<W: AsyncWrite + Unpin + Send> {
     ///
     /// Results should be returned using the given
     /// [`QueryResultWriter`](struct.QueryResultWriter.html).
+    ///
+    /// If the client has enabled multiple statements per query, this is called once for each
+    /// statement in the query, in order, until the results of one of them are an error.
     async fn on_query(&mut self, query: &str, results: QueryResultWriter<'_, W>) -> io::Result<()>;
 
     /// Called when client switches database.
     async fn on_init(&mut self, _: &str, _: Option<InitWriter<'_, W>>) -> io::Result<()>;
 
+    /// Called when the client enables or disables support for multiple statements per query with
+    /// `COM_SET_OPTION`. Splitting queries into statements is handled by the
+    /// [`MySqlIntermediary`](struct.MySqlIntermediary.html), so this is purely informational.
+    async fn on_set_option(&mut self, _multi_statements: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called when the client resets its session with `COM_RESET_CONNECTION`, or changes user with
+    /// `COM_CHANGE_USER`. Prepared statements, session variables and any open transaction should
+    /// be discarded, leaving the connection as it was when the client connected, except that the
+    /// current database is kept. Prepared statement schemas cached by the
+    /// [`MySqlIntermediary`](struct.MySqlIntermediary.html) are discarded before this is called.
+    ///
+    /// When changing user, [`on_init`](MySqlShim::on_init) is called afterwards with the database
+    /// the client asked for, if any.
+    async fn on_reset(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the client has authenticated, if it connected with `CLIENT_CONNECT_ATTRS`, with
+    /// the connection attributes it sent, by name. These identify the client, with attributes set
+    /// by the client library such as `_client_name` and `_client_version`, and attributes set by
+    /// the application such as `program_name`.
+    ///
+    /// When the client changes user with `COM_CHANGE_USER`, this is called again with the
+    /// attributes it sent along with the command, if any.
+    async fn on_connection_attributes(
+        &mut self,
+        _attributes: &HashMap<String, String>,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once the client has authenticated, if it connected with `CLIENT_FOUND_ROWS`, asking
+    /// for the affected row count of an `UPDATE` to be the number of rows it matched rather than
+    /// the number of rows it changed.
+    async fn on_client_found_rows(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Return true to advertise `CLIENT_QUERY_ATTRIBUTES` to clients, so that they send query
+    /// attributes to [`on_query_attributes`](MySqlShim::on_query_attributes). Clients expect the
+    /// attributes to reach the database, so a shim that proxies queries to another server should
+    /// only do so if it forwards them.
+    fn supports_query_attributes(&self) -> bool {
+        false
+    }
+
+    /// Called before every [`on_query`](MySqlShim::on_query) and
+    /// [`on_execute`](MySqlShim::on_execute) if the shim
+    /// [supports query attributes](MySqlShim::supports_query_attributes) and the client connected
+    /// with `CLIENT_QUERY_ATTRIBUTES`, with the query attributes it sent along with the query or
+    /// execution (which may be none). If the query contains multiple statements, the attributes
+    /// apply to all of them.
+    async fn on_query_attributes(&mut self, _attributes: &[QueryAttribute<'_>]) -> io::Result<()> {
+        Ok(())
+    }
+
     /// Retrieve the password for the user with the given username, if any.
     ///
     /// If the user doesn't exist, return [`None`].
@@ -304,24 +382,54 @@ pub struct MySqlIntermediary<B, R: AsyncRead + Unpin, W: AsyncWrite + Unpin> {
     writer: packet::PacketWriter<W>,
     /// A cache of schemas per statement id
     schema_cache: HashMap<u32, CachedSchema>,
-    /// Whether to log statements received from a client
-    enable_statement_logging: bool,
+    /// Whether the client connected with `CLIENT_FOUND_ROWS`
+    client_found_rows: bool,
+    /// Whether the client has enabled multiple statements per query, either by connecting with
+    /// `CLIENT_MULTI_STATEMENTS` or with `COM_SET_OPTION`
+    multi_statements: bool,
+    /// Whether the shim supports query attributes and the client connected with
+    /// `CLIENT_QUERY_ATTRIBUTES`, and so sends query attributes with every `COM_QUERY` and
+    /// `COM_STMT_EXECUTE`
+    query_attributes: bool,
+    /// Whether the client connected with `CLIENT_SESSION_TRACK`, and so accepts changes to its
+    /// session state in OK packets
+    session_track: bool,
+    /// The connection attributes the client sent in the initial handshake, if it connected with
+    /// `CLIENT_CONNECT_ATTRS`
+    connection_attributes: HashMap<String, String>,
+    /// The challenge data sent to the client in the initial handshake, which the client also
+    /// hashes its password with when it changes user with `COM_CHANGE_USER`
+    auth_data: AuthData,
+    /// If set, the client may upgrade the connection to TLS during the initial handshake
+    tls: Option<tls::TlsUpgrade>,
+    /// How to compress replies, if the client negotiates the compressed protocol
+    compression_options: CompressionOptions,
 }
 
-impl<B: MySqlShim<net::tcp::OwnedWriteHalf> + Send>
-    MySqlIntermediary<B, net::TcpStream, net::TcpStream>
-{
+impl<B: MySqlShim<ClientStream> + Send> MySqlIntermediary<B, ClientStream, ClientStream> {
     /// Create a new server over a TCP stream and process client commands until the client
-    /// disconnects or an error occurs. See also
+    /// disconnects or an error occurs. If `tls` is set, clients may (or, if it requires TLS, must)
+    /// upgrade the connection to TLS before authenticating. Replies to clients that negotiate the
+    /// compressed protocol are compressed according to `compression`. See also
     /// [`MySqlIntermediary::run_on`](struct.MySqlIntermediary.html#method.run_on).
     pub async fn run_on_tcp(
         shim: B,
         stream: net::TcpStream,
-        enable_statement_logging: bool,
+        tls: Option<TlsConfig>,
+        compression: CompressionOptions,
     ) -> Result<(), io::Error> {
         stream.set_nodelay(true)?;
-        let (reader, writer) = stream.into_split();
-        MySqlIntermediary::run_on(shim, reader, writer, enable_statement_logging).await
+        let (tls, reader, writer) = match tls {
+            Some(config) => {
+                let (upgrade, reader, writer) = tls::TlsUpgrade::new(config, stream);
+                (Some(upgrade), reader, writer)
+            }
+            None => {
+                let (reader, writer) = ClientStream::split(stream);
+                (None, reader, writer)
+            }
+        };
+        MySqlIntermediary::run_with_options(shim, reader, writer, tls, compression).await
     }
 }
 
@@ -331,12 +439,8 @@ impl<B: MySqlShim<S> + Send, S: AsyncRead + AsyncWrite + Clone + Unpin + Send>
     /// Create a new server over a two-way stream and process client commands until the client
     /// disconnects or an error occurs. See also
     /// [`MySqlIntermediary::run_on`](struct.MySqlIntermediary.html#method.run_on).
-    pub async fn run_on_stream(
-        shim: B,
-        stream: S,
-        enable_statement_logging: bool,
-    ) -> Result<(), io::Error> {
-        MySqlIntermediary::run_on(shim, stream.clone(), stream, enable_statement_logging).await
+    pub async fn run_on_stream(shim: B, stream: S) -> Result<(), io::Error> {
+        MySqlIntermediary::run_on(shim, stream.clone(), stream).await
     }
 }
 
@@ -349,25 +453,57 @@ where
     write_err(error_kind, msg, &mut w).await
 }
 
+/// Collect the connection attributes sent by a client by name. If the client sends an attribute
+/// more than once, the last value it sent is kept.
+fn connection_attributes(attrs: &[(&str, &str)]) -> HashMap<String, String> {
+    attrs
+        .iter()
+        .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
 #[derive(Default)]
 struct StatementData {
+    /// Parameter values sent with `COM_STMT_SEND_LONG_DATA` since the statement was last executed
+    /// or reset, by parameter index
     long_data: HashMap<u16, Vec<u8>>,
+    /// Set if long data was sent for a parameter the statement doesn't have. As in MySQL, which
+    /// can't respond to `COM_STMT_SEND_LONG_DATA`, the error is reported by the next execution.
+    invalid_long_data: bool,
     bound_types: Vec<(myc::constants::ColumnType, bool)>,
+    /// The types and names of the query attributes last sent when executing the statement, if the
+    /// client connected with `CLIENT_QUERY_ATTRIBUTES`
+    attribute_types: Vec<(myc::constants::ColumnType, bool, String)>,
     params: u16,
 }
 
-const CAPABILITIES: u32 = PROTOCOL_41 | SECURE_CONNECTION | RESERVED | CLIENT_PLUGIN_AUTH;
+const CAPABILITIES: u32 = PROTOCOL_41
+    | SECURE_CONNECTION
+    | RESERVED
+    | CLIENT_PLUGIN_AUTH
+    | FOUND_ROWS
+    | MULTI_STATEMENTS
+    | MULTI_RESULTS
+    | COMPRESS
+    | ZSTD_COMPRESSION_ALGORITHM
+    | SESSION_TRACK
+    | CONNECT_ATTRS;
 
 impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
     MySqlIntermediary<B, R, W>
 {
     /// Create a new server over two one-way channels and process client commands until the client
     /// disconnects or an error occurs.
-    pub async fn run_on(
+    pub async fn run_on(shim: B, reader: R, writer: W) -> Result<(), io::Error> {
+        Self::run_with_options(shim, reader, writer, None, CompressionOptions::default()).await
+    }
+
+    async fn run_with_options(
         shim: B,
         reader: R,
         writer: W,
-        enable_statement_logging: bool,
+        tls: Option<tls::TlsUpgrade>,
+        compression_options: CompressionOptions,
     ) -> Result<(), io::Error> {
         let r = packet::PacketReader::new(reader);
         let w = packet::PacketWriter::new(writer);
@@ -376,9 +512,25 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
             reader: r,
             writer: w,
             schema_cache: HashMap::new(),
-            enable_statement_logging,
+            client_found_rows: false,
+            multi_statements: false,
+            query_attributes: false,
+            session_track: false,
+            connection_attributes: HashMap::new(),
+            auth_data: generate_auth_data()
+                .map_err(|_| other_error(OtherErrorKind::AuthDataErr))?,
+            tls,
+            compression_options,
         };
         if let (true, database) = mi.init().await? {
+            if !mi.connection_attributes.is_empty() {
+                mi.shim
+                    .on_connection_attributes(&mi.connection_attributes)
+                    .await?;
+            }
+            if mi.client_found_rows {
+                mi.shim.on_client_found_rows().await?;
+            }
             if let Some(database) = database {
                 mi.shim.on_init(&database, None).await?;
             }
@@ -398,8 +550,14 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
     /// whether authentication was successful, and a database name if one was specified by the
     /// client in the handshake response.
     async fn init(&mut self) -> Result<(bool, Option<String>), io::Error> {
-        let auth_data =
-            generate_auth_data().map_err(|_| other_error(OtherErrorKind::AuthDataErr))?;
+        let auth_data = self.auth_data;
+        let mut capabilities = CAPABILITIES;
+        if self.tls.is_some() {
+            capabilities |= SSL;
+        }
+        if self.shim.supports_query_attributes() {
+            capabilities |= QUERY_ATTRIBUTES;
+        }
 
         let mut init_packet = Vec::with_capacity(
             1 + 16 + 4 + 8 + 1 + 2 + 1 + 2 + 2 + 1 + 6 + 4 + 12 + 1 + AUTH_PLUGIN_NAME.len() + 1,
@@ -409,10 +567,10 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
         init_packet.extend_from_slice(&[0x08, 0x00, 0x00, 0x00]); // TODO: connection ID
         init_packet.extend_from_slice(&auth_data[..8]);
         init_packet.push(0);
-        init_packet.extend_from_slice(&CAPABILITIES.to_le_bytes()[..2]);
+        init_packet.extend_from_slice(&capabilities.to_le_bytes()[..2]);
         init_packet.extend_from_slice(&[0x21]); // UTF8_GENERAL_CI
         init_packet.extend_from_slice(&[0x00, 0x00]); // status flags
-        init_packet.extend_from_slice(&CAPABILITIES.to_le_bytes()[2..]);
+        init_packet.extend_from_slice(&capabilities.to_le_bytes()[2..]);
         init_packet.extend_from_slice(&[auth_data.len() as u8]);
         init_packet.extend_from_slice(&[0x00; 10][..]); // filler
         init_packet.extend_from_slice(&auth_data[8..]);
@@ -423,12 +581,26 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
         self.writer.write_packet(&init_packet).await?;
         self.writer.flush().await?;
 
-        let (seq, handshake_bytes) = self.reader.next().await?.ok_or_else(|| {
+        let peer_terminated = || {
             io::Error::new(
                 io::ErrorKind::ConnectionAborted,
                 "peer terminated connection",
             )
-        })?;
+        };
+        let mut packet = self.reader.next().await?.ok_or_else(peer_terminated)?;
+        let tls_required = self.tls.as_ref().map_or(false, |tls| tls.required());
+        let upgraded = commands::is_ssl_request(&packet.1);
+        if upgraded {
+            let tls = self.tls.take().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "client requested TLS, but TLS is not enabled",
+                )
+            })?;
+            tls.upgrade(self.reader.take_buffered()).await?;
+            packet = self.reader.next().await?.ok_or_else(peer_terminated)?;
+        }
+        let (seq, handshake_bytes) = packet;
         let handshake = commands::client_handshake(&handshake_bytes)
             .map_err(|e| match e {
                 nom::Err::Incomplete(_) => io::Error::new(
@@ -454,12 +626,90 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
 
         self.writer.set_seq(seq + 1);
 
+        if tls_required && !upgraded {
+            // As in MySQL for a user that requires a client certificate
+            writers::write_err(
+                ErrorKind::ER_ACCESS_DENIED_ERROR,
+                b"Access denied: connections must use TLS with a client certificate",
+                &mut self.writer,
+            )
+            .await?;
+            return Ok((false, None));
+        }
+
+        self.client_found_rows = handshake
+            .capabilities
+            .contains(CapabilityFlags::CLIENT_FOUND_ROWS);
+        self.multi_statements = handshake
+            .capabilities
+            .contains(CapabilityFlags::CLIENT_MULTI_STATEMENTS);
+        self.query_attributes = capabilities & QUERY_ATTRIBUTES != 0
+            && handshake
+                .capabilities
+                .contains(CapabilityFlags::CLIENT_QUERY_ATTRIBUTES);
+        self.session_track = handshake
+            .capabilities
+            .contains(CapabilityFlags::CLIENT_SESSION_TRACK);
+        self.connection_attributes = connection_attributes(&handshake.connect_attrs);
         let username = handshake.username.to_owned();
         let password = handshake.password.to_vec();
         let database = handshake.database.map(String::from);
         let client_auth_plugin = handshake.auth_plugin_name.map(|s| s.to_owned());
+        let secure_connection = handshake
+            .capabilities
+            .contains(CapabilityFlags::CLIENT_SECURE_CONNECTION);
+        // As in MySQL, zlib takes precedence if the client asks for both
+        let compression = if handshake
+            .capabilities
+            .contains(CapabilityFlags::CLIENT_COMPRESS)
+        {
+            Some(Compression::Zlib(self.compression_options.zlib_level))
+        } else {
+            handshake
+                .zstd_compression_level
+                .map(|level| Compression::Zstd(level.into()))
+        };
 
-        let handshake_password = if client_auth_plugin.iter().all(|apn| apn != AUTH_PLUGIN_NAME)
+        let auth_success = self
+            .authenticate(
+                &username,
+                password,
+                client_auth_plugin.as_deref(),
+                secure_connection,
+            )
+            .await?;
+        if auth_success {
+            writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
+            self.writer.flush().await?;
+
+            // Everything after the OK packet is compressed
+            if let Some(compression) = compression {
+                self.reader.enable_compression(compression);
+                self.writer
+                    .enable_compression(compression, self.compression_options.min_length);
+            }
+        }
+
+        Ok((auth_success, database))
+    }
+
+    /// Authenticate `username` with the `password` they sent, hashed with the challenge data sent
+    /// in the initial handshake using `client_auth_plugin`, returning whether authentication was
+    /// successful. If the client used the wrong auth plugin (or sent no password), ask it to
+    /// switch to the one we support and use the password it sends back instead.
+    ///
+    /// If authentication fails, an error is sent to the client, but on success it's up to the
+    /// caller to reply with an OK packet.
+    async fn authenticate(
+        &mut self,
+        username: &str,
+        password: Vec<u8>,
+        client_auth_plugin: Option<&str>,
+        secure_connection: bool,
+    ) -> Result<bool, io::Error> {
+        let auth_data = self.auth_data;
+
+        let handshake_password = if client_auth_plugin != Some(AUTH_PLUGIN_NAME)
             // Some clients (at the very least certain versions of PHP's MySQL PDO library) send an
             // empty password response in the initial handshake, even if the auth plugin is set and
             // correct. We want to send a switch-authentication request in that case too
@@ -467,10 +717,7 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
         {
             // Authentication mismatch - try to switch auth plugins
 
-            if !handshake
-                .capabilities
-                .contains(CapabilityFlags::CLIENT_SECURE_CONNECTION)
-            {
+            if !secure_connection {
                 debug!("Client does not support SECURE_CONNECTION, returning authentication error");
                 writers::write_err(
                     ErrorKind::ER_NOT_SUPPORTED_AUTH_MODE,
@@ -479,7 +726,7 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
                     &mut self.writer,
                 )
                 .await?;
-                return Ok((false, database));
+                return Ok(false);
             }
 
             debug!(
@@ -515,7 +762,7 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
         let auth_success = !self.shim.require_authentication()
             || self
                 .shim
-                .password_for_username(&username)
+                .password_for_username(username)
                 .map_or(false, |password| {
                     let expected = hash_password(&password, &auth_data);
                     let actual = handshake_password.as_slice();
@@ -525,7 +772,6 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
 
         if auth_success {
             debug!(%username, "Successfully authenticated client");
-            writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
         } else {
             debug!(%username, ?client_auth_plugin, "Received incorrect password");
             writers::write_err(
@@ -534,10 +780,10 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
                 &mut self.writer,
             )
             .await?;
+            self.writer.flush().await?;
         }
-        self.writer.flush().await?;
 
-        Ok((auth_success, database))
+        Ok(auth_success)
     }
 
     async fn run(mut self) -> Result<(), io::Error> {
@@ -554,7 +800,7 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
                 })?
                 .1;
             // These other variants are logged by the readyset-mysql `Backend`.
-            if self.enable_statement_logging
+            if Component::MySqlFrontend.is_enabled()
                 && !matches!(
                     cmd,
                     Command::Query(_)
@@ -567,14 +813,41 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
             }
             match cmd {
                 Command::Query(q) => {
-                    let w = QueryResultWriter::new(&mut self.writer, false);
-                    self.shim
-                        .on_query(
-                            ::std::str::from_utf8(q)
-                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
-                            w,
-                        )
-                        .await?;
+                    let q = if self.query_attributes {
+                        let (q, attributes) = query_attributes::split_query(q)?;
+                        self.shim.on_query_attributes(&attributes).await?;
+                        q
+                    } else {
+                        q
+                    };
+                    let query = ::std::str::from_utf8(q)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                    let statements = if self.multi_statements {
+                        statements::split(query)
+                    } else {
+                        vec![]
+                    };
+                    if statements.len() > 1 {
+                        // The results of each statement are followed by those of the next, until
+                        // one of them fails
+                        let last = statements.len() - 1;
+                        for (i, statement) in statements.into_iter().enumerate() {
+                            let mut failed = false;
+                            let w = QueryResultWriter::for_statement(
+                                &mut self.writer,
+                                i < last,
+                                &mut failed,
+                                self.session_track,
+                            );
+                            self.shim.on_query(statement, w).await?;
+                            if failed {
+                                break;
+                            }
+                        }
+                    } else {
+                        let w = QueryResultWriter::new(&mut self.writer, false, self.session_track);
+                        self.shim.on_query(query, w).await?;
+                    }
                 }
                 Command::Prepare(q) => {
                     let w = StatementMetaWriter {
@@ -590,48 +863,71 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
                         )
                         .await?;
                 }
-                Command::ResetStmtData(stmt) => {
-                    stmts
-                        .get_mut(&stmt)
-                        .ok_or_else(|| {
-                            io::Error::new(
-                                io::ErrorKind::InvalidData,
-                                format!("got reset data packet for unknown statement {}", stmt),
+                Command::ResetStmtData(stmt) => match stmts.get_mut(&stmt) {
+                    Some(state) => {
+                        state.long_data.clear();
+                        state.invalid_long_data = false;
+                        writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty())
+                            .await?;
+                    }
+                    None => {
+                        writers::write_err(
+                            ErrorKind::ER_UNKNOWN_STMT_HANDLER,
+                            format!(
+                                "Unknown prepared statement handler ({stmt}) given to \
+                                 mysqld_stmt_reset"
                             )
-                        })?
-                        .long_data
-                        .clear();
-                    writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
-                }
-                Command::Execute { stmt, params } => {
+                            .as_bytes(),
+                            &mut self.writer,
+                        )
+                        .await?;
+                    }
+                },
+                Command::Execute {
+                    stmt,
+                    flags,
+                    params,
+                } => {
                     let state = stmts.get_mut(&stmt).ok_or_else(|| {
                         io::Error::new(
                             io::ErrorKind::InvalidData,
                             format!("asked to execute unknown statement {}", stmt),
                         )
                     })?;
-                    {
-                        let params = params::ParamParser::new(params, state);
-                        let w = QueryResultWriter::new(&mut self.writer, true);
+                    if state.invalid_long_data {
+                        writers::write_err(
+                            ErrorKind::ER_WRONG_ARGUMENTS,
+                            b"Incorrect arguments to mysqld_stmt_send_long_data",
+                            &mut self.writer,
+                        )
+                        .await?;
+                    } else {
+                        let params = if self.query_attributes {
+                            let (params, attributes) =
+                                query_attributes::split_execute(params, flags, state)?;
+                            self.shim.on_query_attributes(&attributes).await?;
+                            params
+                        } else {
+                            params.into()
+                        };
+                        let params = params::ParamParser::new(&params, state);
+                        let w = QueryResultWriter::new(&mut self.writer, true, self.session_track);
                         self.shim
                             .on_execute(stmt, params, w, &mut self.schema_cache)
                             .await?;
                     }
                     state.long_data.clear();
+                    state.invalid_long_data = false;
                 }
                 Command::SendLongData { stmt, param, data } => {
-                    stmts
-                        .get_mut(&stmt)
-                        .ok_or_else(|| {
-                            io::Error::new(
-                                io::ErrorKind::InvalidData,
-                                format!("got long data packet for unknown statement {}", stmt),
-                            )
-                        })?
-                        .long_data
-                        .entry(param)
-                        .or_insert_with(Vec::new)
-                        .extend(data);
+                    // The client doesn't expect a response, even if there's an error
+                    match stmts.get_mut(&stmt) {
+                        Some(state) if param < state.params => {
+                            state.long_data.entry(param).or_default().extend(data);
+                        }
+                        Some(state) => state.invalid_long_data = true,
+                        None => warn!(stmt, "Got long data for unknown statement"),
+                    }
                 }
                 Command::Close(stmt) => {
                     self.shim.on_close(stmt).await;
@@ -651,30 +947,78 @@ impl<B: MySqlShim<W> + Send, R: AsyncRead + Unpin, W: AsyncWrite + Unpin + Send>
                 }
                 Command::Init(schema) => {
                     debug!(schema = %String::from_utf8_lossy(schema), "Handling COM_INIT_DB");
+                    let schema = ::std::str::from_utf8(schema)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
                     let w = InitWriter {
                         writer: &mut self.writer,
+                        session_state: if self.session_track {
+                            vec![SessionStateChange::Schema(schema.to_owned())]
+                        } else {
+                            vec![]
+                        },
                     };
-                    self.shim
-                        .on_init(
-                            ::std::str::from_utf8(schema)
-                                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
-                            Some(w),
-                        )
-                        .await?;
+                    self.shim.on_init(schema, Some(w)).await?;
                 }
                 Command::Ping => {
                     writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
                     self.writer.flush().await?;
                 }
-                Command::ComSetOption(_) => {
-                    // ReadySet already support multi-statement support for the MySQL protocol, so
-                    // we can simply respond with ok. We parse an incoming query as multiple single
-                    // statements, so failure with any one will be forwarded to the underlying
-                    // database as a single statement, meaning that the underlying database does
-                    // not need to have multi-statement support enabled for this connection.
+                Command::ComSetOption(option) => {
+                    // We split queries into statements ourselves and hand them to the shim one at
+                    // a time, so the upstream database (if any) doesn't need multiple statements
+                    // enabled for this connection.
+                    let multi_statements = match option {
+                        MYSQL_OPTION_MULTI_STATEMENTS_ON => true,
+                        MYSQL_OPTION_MULTI_STATEMENTS_OFF => false,
+                        _ => {
+                            writers::write_err(
+                                ErrorKind::ER_UNKNOWN_COM_ERROR,
+                                format!("Unknown COM_SET_OPTION option {option}").as_bytes(),
+                                &mut self.writer,
+                            )
+                            .await?;
+                            self.writer.flush().await?;
+                            continue;
+                        }
+                    };
+                    self.multi_statements = multi_statements;
+                    self.shim.on_set_option(multi_statements).await?;
                     writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
                     self.writer.flush().await?;
                 }
+                Command::ResetConnection => {
+                    stmts.clear();
+                    self.schema_cache.clear();
+                    self.shim.on_reset().await?;
+                    writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
+                }
+                Command::ChangeUser(change_user) => {
+                    let username = change_user.username.to_owned();
+                    let password = change_user.password.to_vec();
+                    let database = change_user.database.map(String::from);
+                    let client_auth_plugin = change_user.auth_plugin_name.map(String::from);
+                    let attributes = connection_attributes(&change_user.connect_attrs);
+                    debug!(%username, "Handling COM_CHANGE_USER");
+                    // As in MySQL, the connection is closed if the new user can't be authenticated
+                    if !self
+                        .authenticate(&username, password, client_auth_plugin.as_deref(), true)
+                        .await?
+                    {
+                        break;
+                    }
+
+                    stmts.clear();
+                    self.schema_cache.clear();
+                    self.shim.on_reset().await?;
+                    if !attributes.is_empty() {
+                        self.shim.on_connection_attributes(&attributes).await?;
+                        self.connection_attributes = attributes;
+                    }
+                    if let Some(database) = database {
+                        self.shim.on_init(&database, None).await?;
+                    }
+                    writers::write_ok_packet(&mut self.writer, 0, 0, StatusFlags::empty()).await?;
+                }
                 Command::Quit => {
                     break;
                 }
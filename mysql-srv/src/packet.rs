@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+use crate::compression::{Compression, Compressor, Decompressor};
 use crate::error::{other_error, OtherErrorKind};
 use crate::resultset::{MAX_POOL_ROWS, MAX_POOL_ROW_CAPACITY};
 
@@ -15,6 +16,9 @@ pub struct PacketWriter<W> {
 
     /// Reusable packets
     preallocated: Vec<QueuedPacket>,
+
+    /// Set once the client has negotiated the compressed protocol
+    compressor: Option<Compressor>,
 }
 
 /// Type for packets being enqueued in the packet writer.
@@ -23,6 +27,9 @@ enum QueuedPacket {
     Raw(Arc<[u8]>),
     /// Packets constructed with headers are written as two IoSlices, the header and the body.
     WithHeader([u8; 4], Vec<u8>),
+    /// Packets whose body is shared with the caller, such as pre-encoded rows, are written like
+    /// `WithHeader` packets without copying the body.
+    Shared([u8; 4], Arc<[u8]>),
 }
 
 /// A helper function that performes a vector write to completion, since
@@ -55,6 +62,18 @@ async fn write_all_vectored<'a, W: AsyncWrite + Unpin>(
     }
 }
 
+/// Write all of `slices` to `w`, as compressed packets if `compressor` is set
+async fn write_all_packets<'a, W: AsyncWrite + Unpin>(
+    w: &'a mut W,
+    compressor: Option<&mut Compressor>,
+    slices: &'a mut [IoSlice<'a>],
+) -> io::Result<()> {
+    match compressor {
+        Some(compressor) => w.write_all(&compressor.compress(slices)?).await,
+        None => write_all_vectored(w, slices).await,
+    }
+}
+
 // Gets an IoSlice to each of the packets currently enqueued in `queue`.
 fn queued_packet_slices(queue: &[QueuedPacket]) -> Vec<IoSlice<'_>> {
     if queue.is_empty() {
@@ -67,6 +86,10 @@ fn queued_packet_slices(queue: &[QueuedPacket]) -> Vec<IoSlice<'_>> {
             slices.push(IoSlice::new(hdr));
             slices.push(IoSlice::new(pack));
         }
+        QueuedPacket::Shared(hdr, pack) => {
+            slices.push(IoSlice::new(hdr));
+            slices.push(IoSlice::new(pack));
+        }
         QueuedPacket::Raw(r) => {
             slices.push(IoSlice::new(r));
         }
@@ -82,11 +105,23 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
             w,
             queue: Vec::new(),
             preallocated: Vec::new(),
+            compressor: None,
         }
     }
 
     pub fn set_seq(&mut self, seq: u8) {
         self.seq = seq;
+        // Like MySQL, start the compressed sequence ids of a reply from the same sequence id as the
+        // packets they contain
+        if let Some(compressor) = &mut self.compressor {
+            compressor.seq = seq;
+        }
+    }
+
+    /// Compress all packets written from now on with `compression`, except for writes shorter than
+    /// `min_length` bytes
+    pub fn enable_compression(&mut self, compression: Compression, min_length: usize) {
+        self.compressor = Some(Compressor::new(compression, min_length));
     }
 
     /// Flushes the writer. This function *must* be called before dropping the internal writer
@@ -117,6 +152,19 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
         self.queue.push(QueuedPacket::WithHeader(hdr, packet));
     }
 
+    /// Push a new packet to the outgoing packet list, without copying its body. Packets too large
+    /// to be sent in one piece are split up, which does copy them.
+    pub fn enqueue_shared_packet(&mut self, packet: Arc<[u8]>) {
+        if packet.len() >= U24_MAX {
+            return self.enqueue_packet(packet.to_vec());
+        }
+
+        let mut hdr = (packet.len() as u32).to_le_bytes();
+        hdr[3] = self.seq;
+        self.seq = self.seq.wrapping_add(1);
+        self.queue.push(QueuedPacket::Shared(hdr, packet));
+    }
+
     /// Enqueues raw bytes to be written on the wire.
     pub async fn enqueue_raw(&mut self, packet: Arc<[u8]>) -> Result<(), tokio::io::Error> {
         self.queue.push(QueuedPacket::Raw(packet));
@@ -131,7 +179,7 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
     pub async fn write_queued_packets(&mut self) -> Result<(), tokio::io::Error> {
         let mut slices = queued_packet_slices(&self.queue);
         if !slices.is_empty() {
-            write_all_vectored(&mut self.w, &mut slices).await?;
+            write_all_packets(&mut self.w, self.compressor.as_mut(), &mut slices).await?;
             self.return_queued_to_pool();
         }
 
@@ -172,7 +220,7 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
             }
         }
 
-        write_all_vectored(&mut self.w, &mut slices).await?;
+        write_all_packets(&mut self.w, self.compressor.as_mut(), &mut slices).await?;
         self.return_queued_to_pool();
 
         Ok(())
@@ -207,7 +255,7 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
             IoSlice::new(packet),
         ]);
 
-        write_all_vectored(&mut self.w, &mut slices).await?;
+        write_all_packets(&mut self.w, self.compressor.as_mut(), &mut slices).await?;
 
         self.seq = self.seq.wrapping_add(1);
         Ok(())
@@ -216,7 +264,7 @@ impl<W: AsyncWrite + Unpin> PacketWriter<W> {
     pub fn get_buffer(&mut self) -> Vec<u8> {
         while let Some(p) = self.preallocated.pop() {
             match p {
-                QueuedPacket::Raw(_) => {}
+                QueuedPacket::Raw(_) | QueuedPacket::Shared(..) => {}
                 QueuedPacket::WithHeader(_, mut vec) => {
                     vec.clear();
                     return vec;
@@ -232,6 +280,8 @@ pub struct PacketReader<R> {
     start: usize,
     remaining: usize,
     r: R,
+    /// Set once the client has negotiated the compressed protocol
+    decompressor: Option<Decompressor>,
 }
 
 impl<R> PacketReader<R> {
@@ -241,8 +291,25 @@ impl<R> PacketReader<R> {
             start: 0,
             remaining: 0,
             r,
+            decompressor: None,
         }
     }
+
+    /// Take any bytes that have been read from the underlying reader but not yet returned as part
+    /// of a packet
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        let buffered = self.bytes.split_off(self.bytes.len() - self.remaining);
+        self.bytes.clear();
+        self.start = 0;
+        self.remaining = 0;
+        buffered
+    }
+
+    /// Decompress all packets read from now on with `compression`
+    pub fn enable_compression(&mut self, compression: Compression) {
+        let buffered = self.take_buffered();
+        self.decompressor = Some(Decompressor::new(compression, buffered));
+    }
 }
 
 impl<R: AsyncRead + Unpin> PacketReader<R> {
@@ -266,9 +333,12 @@ impl<R: AsyncRead + Unpin> PacketReader<R> {
                     unsafe { ::std::slice::from_raw_parts(bytes.as_ptr(), bytes.len()) }
                 };
                 match packet(bytes) {
-                    Ok((rest, p)) => {
+                    Ok((rest, (seq, p))) => {
                         self.remaining = rest.len();
-                        return Ok(Some(p));
+                        // With compression, MySQL only tracks the sequence ids of the compressed
+                        // packets, and ignores those of the packets they contain
+                        let seq = self.decompressor.as_ref().map_or(seq, |d| d.seq);
+                        return Ok(Some((seq, p)));
                     }
                     Err(nom::Err::Incomplete(_)) | Err(nom::Err::Error(_)) => {}
                     Err(nom::Err::Failure(ctx)) => {
@@ -294,7 +364,7 @@ impl<R: AsyncRead + Unpin> PacketReader<R> {
                         length: new_len,
                     })
                 })?;
-                self.r.read(buf).await?
+                self.read_some(buf).await?
             };
             self.bytes.truncate(end + read);
             self.remaining = self.bytes.len();
@@ -311,6 +381,35 @@ impl<R: AsyncRead + Unpin> PacketReader<R> {
             }
         }
     }
+
+    /// Read some bytes from the client into `buf`, decompressing them if the client negotiated
+    /// compression, and returning the number of bytes read (or 0 at EOF)
+    async fn read_some(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(decompressor) = &mut self.decompressor else {
+            return self.r.read(buf).await;
+        };
+
+        while decompressor.decompressed.is_empty() {
+            if decompressor.decompress_next()? {
+                continue;
+            }
+            decompressor.raw.reserve(4096);
+            if self.r.read_buf(&mut decompressor.raw).await? == 0 {
+                if decompressor.raw.is_empty() {
+                    return Ok(0);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("{} unhandled compressed bytes", decompressor.raw.len()),
+                ));
+            }
+        }
+
+        let len = buf.len().min(decompressor.decompressed.len());
+        buf[..len].copy_from_slice(&decompressor.decompressed[..len]);
+        decompressor.decompressed.drain(..len);
+        Ok(len)
+    }
 }
 
 pub fn fullpacket(i: &[u8]) -> nom::IResult<&[u8], (u8, &[u8])> {
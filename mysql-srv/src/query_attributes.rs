@@ -0,0 +1,253 @@
+//! Query attributes, which clients that negotiate `CLIENT_QUERY_ATTRIBUTES` (MySQL 8.0.23+) send
+//! with every `COM_QUERY` and `COM_STMT_EXECUTE`, as named parameters that aren't bound to any
+//! placeholder in the query. APM tools use them to tag queries with, for example, trace ids.
+//!
+//! Attributes are sent in the same block as the parameters of a `COM_STMT_EXECUTE`, after the
+//! statement's own parameters, and each parameter's type is followed by its name.
+//!
+//! <https://dev.mysql.com/doc/refman/8.0/en/query-attributes.html>
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+use nom::bytes::complete::take;
+use nom::number::complete::le_u8;
+
+use crate::commands::lenenc_int;
+use crate::myc::constants::ColumnType;
+use crate::{MsqlSrvError, StatementData, Value};
+
+/// Set in the flags of a `COM_STMT_EXECUTE` if the parameter count is sent even though the
+/// statement has no parameters, because the client is sending query attributes
+const PARAMETER_COUNT_AVAILABLE: u8 = 0x08;
+
+/// A query attribute sent by the client with a query or statement execution
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryAttribute<'a> {
+    /// The name of the attribute
+    pub name: String,
+    /// The value of the attribute
+    pub value: Value<'a>,
+}
+
+fn parse_error<E>(_: E) -> MsqlSrvError {
+    MsqlSrvError::IndexingError
+}
+
+fn is_null(nullmap: &[u8], i: usize) -> bool {
+    nullmap
+        .get(i / 8)
+        .map_or(false, |byte| byte & (1 << (i % 8)) != 0)
+}
+
+/// Parse the types (and names) of `count` parameters
+fn parse_types(
+    mut i: &[u8],
+    count: usize,
+) -> Result<(&[u8], Vec<(ColumnType, bool, String)>), MsqlSrvError> {
+    let mut types = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (rest, coltype) = le_u8(i).map_err(parse_error)?;
+        let (rest, flag) = le_u8(rest).map_err(parse_error)?;
+        let (rest, name_len) = lenenc_int(rest).map_err(parse_error)?;
+        let (rest, name) = take(name_len as usize)(rest).map_err(parse_error)?;
+        types.push((
+            ColumnType::try_from(coltype)?,
+            flag & 128 != 0,
+            std::str::from_utf8(name)?.to_owned(),
+        ));
+        i = rest;
+    }
+    Ok((i, types))
+}
+
+/// Parse the query attributes at the start of a `COM_QUERY`, returning the query that follows
+/// them along with the attributes
+pub(crate) fn split_query(i: &[u8]) -> Result<(&[u8], Vec<QueryAttribute<'_>>), MsqlSrvError> {
+    let (i, count) = lenenc_int(i).map_err(parse_error)?;
+    // Always 1
+    let (i, _parameter_set_count) = lenenc_int(i).map_err(parse_error)?;
+    let count = count as usize;
+    if count == 0 {
+        return Ok((i, vec![]));
+    }
+
+    let (i, nullmap) = take((count + 7) / 8)(i).map_err(parse_error)?;
+    // Always 1, since types are sent with every query
+    let (i, _new_params_bind_flag) = le_u8(i).map_err(parse_error)?;
+    let (mut i, types) = parse_types(i, count)?;
+    let mut attributes = Vec::with_capacity(count);
+    for (n, (coltype, unsigned, name)) in types.into_iter().enumerate() {
+        let value = if is_null(nullmap, n) {
+            Value::null()
+        } else {
+            Value::parse_from(&mut i, coltype, unsigned)?
+        };
+        attributes.push(QueryAttribute { name, value });
+    }
+    Ok((i, attributes))
+}
+
+/// Split the parameter block of a `COM_STMT_EXECUTE` for `stmt` into the statement's own
+/// parameters, encoded as they would have been by a client that didn't negotiate
+/// `CLIENT_QUERY_ATTRIBUTES` (for [`ParamParser`](crate::ParamParser)), and the query attributes
+/// that follow them.
+pub(crate) fn split_execute<'a>(
+    params: &'a [u8],
+    flags: u8,
+    stmt: &mut StatementData,
+) -> Result<(Cow<'a, [u8]>, Vec<QueryAttribute<'a>>), MsqlSrvError> {
+    let num_params = stmt.params as usize;
+    if num_params == 0 && flags & PARAMETER_COUNT_AVAILABLE == 0 {
+        return Ok((Cow::Borrowed(params), vec![]));
+    }
+
+    let (i, count) = lenenc_int(params).map_err(parse_error)?;
+    let count = count as usize;
+    if count < num_params {
+        return Err(MsqlSrvError::IndexingError);
+    }
+    if count == 0 {
+        return Ok((Cow::Borrowed(i), vec![]));
+    }
+    let (i, nullmap) = take((count + 7) / 8)(i).map_err(parse_error)?;
+    let (mut i, new_params_bind_flag) = le_u8(i).map_err(parse_error)?;
+    if new_params_bind_flag != 0 {
+        let (rest, mut types) = parse_types(i, count)?;
+        stmt.attribute_types = types.split_off(num_params);
+        stmt.bound_types = types
+            .into_iter()
+            .map(|(coltype, unsigned, _)| (coltype, unsigned))
+            .collect();
+        i = rest;
+    } else if stmt.bound_types.len() != num_params
+        || stmt.attribute_types.len() != count - num_params
+    {
+        // The client is reusing types it never sent
+        return Err(MsqlSrvError::IndexingError);
+    }
+
+    // Skip over the values of the statement's own parameters, to find where the attributes start
+    let values = i;
+    for (n, (coltype, unsigned)) in stmt.bound_types.iter().enumerate() {
+        if !is_null(nullmap, n) && !stmt.long_data.contains_key(&(n as u16)) {
+            Value::parse_from(&mut i, *coltype, *unsigned)?;
+        }
+    }
+    let param_values = &values[..values.len() - i.len()];
+
+    let mut attributes = Vec::with_capacity(count - num_params);
+    for (n, (coltype, unsigned, name)) in stmt.attribute_types.iter().enumerate() {
+        let value = if is_null(nullmap, num_params + n) {
+            Value::null()
+        } else {
+            Value::parse_from(&mut i, *coltype, *unsigned)?
+        };
+        attributes.push(QueryAttribute {
+            name: name.clone(),
+            value,
+        });
+    }
+
+    if num_params == 0 {
+        return Ok((Cow::Borrowed(&[]), attributes));
+    }
+    let mut params = vec![0; (num_params + 7) / 8];
+    for n in (0..num_params).filter(|n| is_null(nullmap, *n)) {
+        params[n / 8] |= 1 << (n % 8);
+    }
+    params.push(1);
+    for (coltype, unsigned) in &stmt.bound_types {
+        params.push(*coltype as u8);
+        params.push(if *unsigned { 128 } else { 0 });
+    }
+    params.extend_from_slice(param_values);
+    Ok((Cow::Owned(params), attributes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ValueInner;
+
+    #[test]
+    fn splits_query() {
+        // 1 attribute, in 1 set, which isn't null
+        let mut packet = vec![1, 1, 0];
+        // New params bind flag
+        packet.push(1);
+        // MYSQL_TYPE_VAR_STRING, named "traceparent"
+        packet.extend_from_slice(&[0xfd, 0, 11]);
+        packet.extend_from_slice(b"traceparent");
+        packet.push(3);
+        packet.extend_from_slice(b"abc");
+        packet.extend_from_slice(b"SELECT 1");
+
+        let (query, attributes) = split_query(&packet).unwrap();
+        assert_eq!(query, b"SELECT 1");
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].name, "traceparent");
+        assert_eq!(attributes[0].value.into_inner(), ValueInner::Bytes(b"abc"));
+    }
+
+    #[test]
+    fn splits_query_without_attributes() {
+        let (query, attributes) = split_query(b"\x00\x01SELECT 1").unwrap();
+        assert_eq!(query, b"SELECT 1");
+        assert!(attributes.is_empty());
+    }
+
+    #[test]
+    fn splits_execute() {
+        let mut stmt = StatementData {
+            params: 1,
+            ..Default::default()
+        };
+        // 2 parameters (1 of the statement's and 1 attribute), neither of which are null
+        let mut packet = vec![2, 0];
+        // New params bind flag
+        packet.push(1);
+        // MYSQL_TYPE_LONGLONG, unnamed
+        packet.extend_from_slice(&[8, 0, 0]);
+        // MYSQL_TYPE_VAR_STRING, named "a"
+        packet.extend_from_slice(&[0xfd, 0, 1, b'a']);
+        packet.extend_from_slice(&42i64.to_le_bytes());
+        packet.extend_from_slice(b"\x01b");
+
+        let (params, attributes) = split_execute(&packet, 0, &mut stmt).unwrap();
+        let mut expected = vec![0, 1, 8, 0];
+        expected.extend_from_slice(&42i64.to_le_bytes());
+        assert_eq!(&params[..], &expected[..]);
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].name, "a");
+        assert_eq!(attributes[0].value.into_inner(), ValueInner::Bytes(b"b"));
+        assert_eq!(
+            stmt.bound_types,
+            vec![(ColumnType::MYSQL_TYPE_LONGLONG, false)]
+        );
+
+        // Executing again without binding new types reuses the types from before. This time the
+        // attribute is null.
+        let mut packet = vec![2, 0b10, 0];
+        packet.extend_from_slice(&7i64.to_le_bytes());
+        let (_, attributes) = split_execute(&packet, 0, &mut stmt).unwrap();
+        assert!(attributes[0].value.is_null());
+    }
+
+    #[test]
+    fn splits_execute_without_parameters() {
+        let mut stmt = StatementData::default();
+        let (params, attributes) =
+            split_execute(&[0], PARAMETER_COUNT_AVAILABLE, &mut stmt).unwrap();
+        assert!(params.is_empty());
+        assert!(attributes.is_empty());
+    }
+
+    #[test]
+    fn skips_execute_without_parameter_count() {
+        let mut stmt = StatementData::default();
+        let (params, attributes) = split_execute(b"", 0, &mut stmt).unwrap();
+        assert!(params.is_empty());
+        assert!(attributes.is_empty());
+    }
+}
@@ -3,26 +3,40 @@ use std::collections::HashMap;
 use std::io;
 use std::sync::Arc;
 
+use futures::{FutureExt, Stream, StreamExt};
 use tokio::io::AsyncWrite;
 
+use crate::error::{other_error, OtherErrorKind};
 use crate::myc::constants::{ColumnFlags, StatusFlags};
 use crate::packet::PacketWriter;
 use crate::value::ToMySqlValue;
-use crate::{writers, Column, ErrorKind, StatementData};
+use crate::{writers, Column, ErrorKind, SessionStateChange, StatementData};
 
 pub(crate) const DEFAULT_ROW_CAPACITY: usize = 4096;
 pub(crate) const MAX_POOL_ROW_CAPACITY: usize = DEFAULT_ROW_CAPACITY * 4;
 pub(crate) const MAX_POOL_ROWS: usize = 4096;
+/// The number of bytes of row data a [`RowWriter`] queues up before sending it to the client
+const MAX_QUEUED_ROW_BYTES: usize = 1 << 20;
 
 /// Convenience type for responding to a client `USE <db>` command.
 pub struct InitWriter<'a, W: AsyncWrite + Unpin> {
     pub(crate) writer: &'a mut PacketWriter<W>,
+    /// The change to the current schema, if the client connected with `CLIENT_SESSION_TRACK`
+    pub(crate) session_state: Vec<SessionStateChange>,
 }
 
 impl<'a, W: AsyncWrite + Unpin + 'a> InitWriter<'a, W> {
     /// Tell client that database context has been changed
     pub async fn ok(self) -> io::Result<()> {
-        writers::write_ok_packet(self.writer, 0, 0, StatusFlags::empty()).await
+        writers::write_ok_packet_with_warnings(
+            self.writer,
+            0,
+            0,
+            0,
+            StatusFlags::empty(),
+            &self.session_state,
+        )
+        .await
     }
 
     /// Tell client that there was a problem changing the database context.
@@ -35,6 +49,20 @@ impl<'a, W: AsyncWrite + Unpin + 'a> InitWriter<'a, W> {
     {
         writers::write_err(kind, msg.borrow(), self.writer).await
     }
+
+    /// Tell client that there was a problem changing the database context, with an arbitrary error
+    /// code and SQLSTATE, such as those of an error returned by an upstream server.
+    pub async fn error_with_sqlstate<E>(
+        self,
+        code: u16,
+        sqlstate: &[u8; 5],
+        msg: &E,
+    ) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        writers::write_err_with_sqlstate(code, sqlstate, msg.borrow(), self.writer).await
+    }
 }
 
 /// Convenience type for responding to a client `PREPARE` command.
@@ -81,6 +109,20 @@ impl<'a, W: AsyncWrite + Unpin + 'a> StatementMetaWriter<'a, W> {
     {
         writers::write_err(kind, msg.borrow(), self.writer).await
     }
+
+    /// Reply to the client's `PREPARE` with an error with an arbitrary error code and SQLSTATE,
+    /// such as those of an error returned by an upstream server.
+    pub async fn error_with_sqlstate<E>(
+        self,
+        code: u16,
+        sqlstate: &[u8; 5],
+        msg: &E,
+    ) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        writers::write_err_with_sqlstate(code, sqlstate, msg.borrow(), self.writer).await
+    }
 }
 
 #[derive(Debug)]
@@ -88,7 +130,9 @@ enum Finalizer {
     Ok {
         rows: u64,
         last_insert_id: u64,
+        warnings: u16,
         status_flags: Option<StatusFlags>,
+        session_state: Vec<SessionStateChange>,
     },
     Eof {
         status_flags: Option<StatusFlags>,
@@ -118,25 +162,57 @@ pub struct QueryResultWriter<'a, W: AsyncWrite + Unpin> {
     pub(crate) is_bin: bool,
     pub(crate) writer: &'a mut PacketWriter<W>,
     last_end: Option<Finalizer>,
+    /// Set when replying to one of several statements in a query, other than the last one, so
+    /// that the client expects the results of the statements after it
+    more_statements: bool,
+    /// Set to `true` if the reply is an error, when replying to one of several statements in a
+    /// query, since an error ends the reply to the whole query
+    failed: Option<&'a mut bool>,
+    /// Whether the client connected with `CLIENT_SESSION_TRACK`, and so accepts session state
+    /// changes in OK packets
+    session_track: bool,
 }
 
 impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
-    pub(crate) fn new(writer: &'a mut PacketWriter<W>, is_bin: bool) -> Self {
+    pub(crate) fn new(writer: &'a mut PacketWriter<W>, is_bin: bool, session_track: bool) -> Self {
         QueryResultWriter {
             is_bin,
             writer,
             last_end: None,
+            more_statements: false,
+            failed: None,
+            session_track,
+        }
+    }
+
+    /// Create a writer for the results of one of several statements in a text query, with
+    /// `more_statements` set unless it's the last statement. `failed` is set to `true` if the
+    /// statement's results end with an error.
+    pub(crate) fn for_statement(
+        writer: &'a mut PacketWriter<W>,
+        more_statements: bool,
+        failed: &'a mut bool,
+        session_track: bool,
+    ) -> Self {
+        QueryResultWriter {
+            is_bin: false,
+            writer,
+            last_end: None,
+            more_statements,
+            failed: Some(failed),
+            session_track,
+        }
+    }
+
+    fn set_failed(&mut self) {
+        if let Some(failed) = self.failed.as_mut() {
+            **failed = true;
         }
     }
 
     async fn finalize(&mut self, more_exists: bool) -> io::Result<()> {
         let mut status = match self.last_end {
-            Some(Finalizer::Ok {
-                rows: _,
-                last_insert_id: _,
-                status_flags,
-            })
-            | Some(Finalizer::Eof { status_flags }) => {
+            Some(Finalizer::Ok { status_flags, .. }) | Some(Finalizer::Eof { status_flags }) => {
                 if let Some(sf) = status_flags {
                     sf
                 } else {
@@ -153,8 +229,24 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
             Some(Finalizer::Ok {
                 rows,
                 last_insert_id,
+                warnings,
+                session_state,
                 ..
-            }) => writers::write_ok_packet(self.writer, rows, last_insert_id, status).await,
+            }) => {
+                writers::write_ok_packet_with_warnings(
+                    self.writer,
+                    rows,
+                    last_insert_id,
+                    warnings,
+                    status,
+                    if self.session_track {
+                        &session_state
+                    } else {
+                        &[]
+                    },
+                )
+                .await
+            }
             Some(Finalizer::Eof { .. }) => writers::write_eof_packet(self.writer, status).await,
         }
     }
@@ -199,7 +291,9 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
         self.last_end = Some(Finalizer::Ok {
             rows,
             last_insert_id,
+            warnings: 0,
             status_flags,
+            session_state: vec![],
         });
         Ok(self)
     }
@@ -219,6 +313,43 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
             .await
     }
 
+    /// Like [`completed`](struct.QueryResultWriter.html#method.completed), but also tells the
+    /// client that the query produced `warnings` warnings, such as when relaying the result of a
+    /// query run against an upstream server.
+    pub async fn completed_with_warnings(
+        mut self,
+        rows: u64,
+        last_insert_id: u64,
+        warnings: u16,
+        status_flags: Option<StatusFlags>,
+    ) -> io::Result<()> {
+        self.completed_with_session_state(rows, last_insert_id, warnings, status_flags, vec![])
+            .await
+    }
+
+    /// Like [`completed_with_warnings`](Self::completed_with_warnings), but also tells the client
+    /// about the changes to its session state made by the query, such as when relaying `SET` or
+    /// `USE` statements run against an upstream server. The changes are only sent if the client
+    /// connected with `CLIENT_SESSION_TRACK`.
+    pub async fn completed_with_session_state(
+        mut self,
+        rows: u64,
+        last_insert_id: u64,
+        warnings: u16,
+        status_flags: Option<StatusFlags>,
+        session_state: Vec<SessionStateChange>,
+    ) -> io::Result<()> {
+        self.finalize(true).await?;
+        self.last_end = Some(Finalizer::Ok {
+            rows,
+            last_insert_id,
+            warnings,
+            status_flags,
+            session_state,
+        });
+        self.no_more_results().await
+    }
+
     /// Reply to the client's query with an error.
     ///
     /// This also calls `no_more_results` implicitly.
@@ -227,14 +358,62 @@ impl<'a, W: AsyncWrite + Unpin> QueryResultWriter<'a, W> {
         E: Borrow<[u8]> + ?Sized,
     {
         self.finalize(true).await?;
+        self.set_failed();
         writers::write_err(kind, msg.borrow(), self.writer).await?;
         self.no_more_results().await
     }
 
+    /// Reply to the client's query with an error with an arbitrary error code and SQLSTATE, such
+    /// as those of an error returned by an upstream server.
+    ///
+    /// This also calls `no_more_results` implicitly.
+    pub async fn error_with_sqlstate<E>(
+        mut self,
+        code: u16,
+        sqlstate: &[u8; 5],
+        msg: &E,
+    ) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        self.finalize(true).await?;
+        self.set_failed();
+        writers::write_err_with_sqlstate(code, sqlstate, msg.borrow(), self.writer).await?;
+        self.no_more_results().await
+    }
+
     /// Send the last bits of the last resultset to the client, and indicate that there are no more
     /// resultsets coming.
+    ///
+    /// When replying to one of several statements in a query, this only ends the results of that
+    /// statement, and the client is told to expect the results of the next one.
     pub async fn no_more_results(mut self) -> io::Result<()> {
-        self.finalize(false).await
+        let more_statements = self.more_statements;
+        self.finalize(more_statements).await
+    }
+
+    /// Reply to the client's query with a resultset with the given `columns`, made up of the rows
+    /// produced by `rows`, which are sent to the client as they're produced.
+    ///
+    /// See [`RowWriter::write_stream`](struct.RowWriter.html#method.write_stream).
+    pub async fn stream<S, R, E>(self, columns: &'a [Column], rows: S) -> io::Result<()>
+    where
+        S: Stream<Item = R> + Unpin,
+        R: AsRef<[E]>,
+        E: ToMySqlValue,
+    {
+        let mut rw = self.start(columns).await?;
+        rw.write_stream(rows).await?;
+        rw.finish().await
+    }
+
+    /// Reply to the client's query with a sequence of results, using a
+    /// [`MultiResultWriter`](struct.MultiResultWriter.html).
+    pub fn multi_results(self) -> MultiResultWriter<'a, W> {
+        MultiResultWriter {
+            result: Some(self),
+            written_any: false,
+        }
     }
 }
 
@@ -249,6 +428,102 @@ impl<'a, W: AsyncWrite + Unpin> Drop for QueryResultWriter<'a, W> {
     }
 }
 
+/// Convenience type for replying to a query with a sequence of results, each of which is either an
+/// OK packet or a resultset, such as the response to a `CALL` statement.
+///
+/// Each result is completed before the method writing it returns, and `SERVER_MORE_RESULTS_EXISTS`
+/// is set on every result but the last, so there is no need to sequence
+/// [`QueryResultWriter::complete_one`](struct.QueryResultWriter.html#method.complete_one) and
+/// [`RowWriter::finish_one`](struct.RowWriter.html#method.finish_one) by hand. Once all results
+/// have been written, call [`finish`](struct.MultiResultWriter.html#method.finish), or call
+/// [`error`](struct.MultiResultWriter.html#method.error) to end the response with an error
+/// instead.
+#[must_use]
+pub struct MultiResultWriter<'a, W: AsyncWrite + Unpin> {
+    /// Only `None` if writing a result failed
+    result: Option<QueryResultWriter<'a, W>>,
+    /// Whether any results have been written yet
+    written_any: bool,
+}
+
+impl<'a, W: AsyncWrite + Unpin + 'a> MultiResultWriter<'a, W> {
+    fn take(&mut self) -> io::Result<QueryResultWriter<'a, W>> {
+        self.result
+            .take()
+            .ok_or_else(|| other_error(OtherErrorKind::QueryResultWriterErr))
+    }
+
+    /// Write a result indicating that `rows` rows were affected by a query. `last_insert_id` may be
+    /// given to communicate an identifier for a client's most recent insertion.
+    pub async fn ok(
+        &mut self,
+        rows: u64,
+        last_insert_id: u64,
+        status_flags: Option<StatusFlags>,
+    ) -> io::Result<()> {
+        let result = self.take()?;
+        self.result = Some(
+            result
+                .complete_one(rows, last_insert_id, status_flags)
+                .await?,
+        );
+        self.written_any = true;
+        Ok(())
+    }
+
+    /// Write a resultset conforming to the given `columns`, containing each of `rows`.
+    ///
+    /// As for [`RowWriter::write_row`](struct.RowWriter.html#method.write_row), every row must
+    /// conform to `columns`.
+    pub async fn rows<I, R, E>(&mut self, columns: &'a [Column], rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: IntoIterator<Item = E>,
+        E: ToMySqlValue,
+    {
+        let mut writer = self.take()?.start(columns).await?;
+        for row in rows {
+            writer.write_row(row).await?;
+        }
+        self.result = Some(writer.finish_one().await?);
+        self.written_any = true;
+        Ok(())
+    }
+
+    /// End the response with an error, in place of any further results.
+    pub async fn error<E>(mut self, kind: ErrorKind, msg: &E) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        self.take()?.error(kind, msg).await
+    }
+
+    /// End the response with an error with an arbitrary error code and SQLSTATE, in place of any
+    /// further results.
+    pub async fn error_with_sqlstate<E>(
+        mut self,
+        code: u16,
+        sqlstate: &[u8; 5],
+        msg: &E,
+    ) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        self.take()?.error_with_sqlstate(code, sqlstate, msg).await
+    }
+
+    /// Indicate to the client that no more results are coming. If no results were written, reply
+    /// with an OK packet indicating that no rows were affected.
+    pub async fn finish(mut self) -> io::Result<()> {
+        let result = self.take()?;
+        if self.written_any {
+            result.no_more_results().await
+        } else {
+            result.completed(0, 0, None).await
+        }
+    }
+}
+
 /// Convenience type for sending rows of a resultset to a client.
 ///
 /// Rows can either be written out one column at a time (using
@@ -281,6 +556,8 @@ pub struct RowWriter<'a, W: AsyncWrite + Unpin> {
     last_status_flags: Option<StatusFlags>,
     /// A buffer to hold row data
     row_data: Option<Vec<u8>>,
+    /// The number of bytes of row data queued since rows were last sent to the client
+    queued_bytes: usize,
 }
 
 impl<'a, W> RowWriter<'a, W>
@@ -292,7 +569,7 @@ where
         columns: &'a [Column],
         cached_column_def: Option<Arc<[u8]>>,
     ) -> io::Result<RowWriter<'a, W>> {
-        let bitmap_len = (columns.len() + 7 + 2) / 8;
+        let bitmap_len = bitmap_len(columns);
         let mut rw = RowWriter {
             result,
             columns,
@@ -306,6 +583,7 @@ where
             last_status_flags: None,
 
             row_data: None,
+            queued_bytes: 0,
         };
         rw.start().await?;
         Ok(rw)
@@ -411,16 +689,27 @@ where
             ));
         }
 
+        self.col = 0;
+
         if let Some(packet) = self.row_data.take() {
+            let len = packet.len();
             self.result.writer.enqueue_packet(packet);
+            self.row_queued(len).await?;
         }
 
-        self.col = 0;
+        Ok(())
+    }
 
-        if self.result.writer.queue_len() > MAX_POOL_ROWS {
+    /// Account for a row of `len` bytes having been queued, and send the queued rows to the client
+    /// once they add up to [`MAX_QUEUED_ROW_BYTES`] or [`MAX_POOL_ROWS`] rows
+    async fn row_queued(&mut self, len: usize) -> io::Result<()> {
+        self.queued_bytes += len;
+        if self.queued_bytes > MAX_QUEUED_ROW_BYTES
+            || self.result.writer.queue_len() > MAX_POOL_ROWS
+        {
             self.result.writer.flush().await?;
+            self.queued_bytes = 0;
         }
-
         Ok(())
     }
 
@@ -442,6 +731,155 @@ where
         }
         self.end_row().await
     }
+
+    /// Write each of `rows` as a part of this resultset.
+    ///
+    /// This is equivalent to calling [`write_row`](struct.RowWriter.html#method.write_row) for
+    /// each row, but encodes every row in a single pass over its values, which makes it
+    /// considerably cheaper for large resultsets.
+    ///
+    /// Note that every row *must* conform to the column specification provided to
+    /// [`QueryResultWriter::start`](struct.QueryResultWriter.html#method.start). If one does not,
+    /// this method will return an error indicating that an invalid value type or specification was
+    /// provided, and the rows before it will have been written.
+    pub async fn write_rows<I, R, E>(&mut self, rows: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = R>,
+        R: AsRef<[E]>,
+        E: ToMySqlValue,
+    {
+        if self.columns.is_empty() {
+            self.col += rows.into_iter().count();
+            return Ok(());
+        }
+        if self.col != 0 {
+            // End the row that was being written one column at a time
+            self.end_row().await?;
+        }
+
+        for row in rows {
+            let row_data = self.encode_row(row.as_ref())?;
+            let len = row_data.len();
+            self.result.writer.enqueue_packet(row_data);
+            self.row_queued(len).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write each row produced by `rows` as a part of this resultset, as they're produced.
+    ///
+    /// As with [`write_rows`](struct.RowWriter.html#method.write_rows), queued rows are sent to the
+    /// client once they add up to a given number of bytes, but they're also sent whenever the next
+    /// row isn't ready yet, so that writing to the client overlaps with producing rows. Since the
+    /// next row is only requested once the rows before it have been queued, and writes to the
+    /// client wait for the client to read them, a slow client slows down producing rows rather
+    /// than rows piling up in memory.
+    ///
+    /// Note that every row *must* conform to the column specification provided to
+    /// [`QueryResultWriter::start`](struct.QueryResultWriter.html#method.start). If one does not,
+    /// this method will return an error indicating that an invalid value type or specification was
+    /// provided, and the rows before it will have been written.
+    pub async fn write_stream<S, R, E>(&mut self, mut rows: S) -> io::Result<()>
+    where
+        S: Stream<Item = R> + Unpin,
+        R: AsRef<[E]>,
+        E: ToMySqlValue,
+    {
+        if self.columns.is_empty() {
+            self.col += rows.count().await;
+            return Ok(());
+        }
+        if self.col != 0 {
+            // End the row that was being written one column at a time
+            self.end_row().await?;
+        }
+
+        loop {
+            let row = match rows.next().now_or_never() {
+                Some(row) => row,
+                None => {
+                    // Send what we have while we wait for the next row
+                    if self.result.writer.queue_len() > 0 {
+                        self.result.writer.flush().await?;
+                        self.queued_bytes = 0;
+                    }
+                    rows.next().await
+                }
+            };
+            let Some(row) = row else {
+                break;
+            };
+
+            let row_data = self.encode_row(row.as_ref())?;
+            let len = row_data.len();
+            self.result.writer.enqueue_packet(row_data);
+            self.row_queued(len).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Encode `row` as a row packet
+    fn encode_row<E>(&mut self, row: &[E]) -> io::Result<Vec<u8>>
+    where
+        E: ToMySqlValue,
+    {
+        let mut row_data = self.result.writer.get_buffer();
+        row_data.reserve(DEFAULT_ROW_CAPACITY);
+        encode_row(row_data, self.columns, self.result.is_bin, row)
+    }
+
+    /// Write a row that was encoded ahead of time as a part of this resultset, without encoding or
+    /// copying it again.
+    ///
+    /// This is the cheapest way to send a row that's sent often, such as a row of the result of a
+    /// query that's cached, since encoding rows accounts for much of the cost of sending them.
+    ///
+    /// The row must have been encoded for the same protocol as this resultset, as returned by
+    /// [`is_binary`](Self::is_binary), or this method returns an error. It must also have been
+    /// encoded with the same column specification provided to
+    /// [`QueryResultWriter::start`](struct.QueryResultWriter.html#method.start), which is not
+    /// checked beyond the number of columns.
+    pub async fn write_encoded_row(&mut self, row: &EncodedRow) -> io::Result<()> {
+        if self.columns.is_empty() {
+            self.col += 1;
+            return Ok(());
+        }
+        if row.is_bin != self.result.is_bin {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                if row.is_bin {
+                    "row was encoded for the binary protocol, but the resultset is text"
+                } else {
+                    "row was encoded for the text protocol, but the resultset is binary"
+                },
+            ));
+        }
+        if row.columns != self.columns.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                if row.columns > self.columns.len() {
+                    "row has more columns than specification"
+                } else {
+                    "row has fewer columns than specification"
+                },
+            ));
+        }
+        if self.col != 0 {
+            // End the row that was being written one column at a time
+            self.end_row().await?;
+        }
+
+        self.result.writer.enqueue_shared_packet(row.data.clone());
+        self.row_queued(row.data.len()).await
+    }
+
+    /// Returns whether rows of this resultset are sent with the binary protocol (in reply to
+    /// `COM_STMT_EXECUTE`) rather than the text protocol (in reply to `COM_QUERY`)
+    pub fn is_binary(&self) -> bool {
+        self.result.is_bin
+    }
 }
 
 impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
@@ -457,7 +895,9 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
             self.result.last_end = Some(Finalizer::Ok {
                 rows: self.col as u64,
                 last_insert_id: 0,
+                warnings: 0,
                 status_flags: self.last_status_flags.take(),
+                session_state: vec![],
             });
             Ok(())
         } else {
@@ -485,6 +925,22 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
         self.result.error(kind, msg).await
     }
 
+    /// Reply to the client's query with an error with an arbitrary error code and SQLSTATE, such
+    /// as those of an error returned by an upstream server.
+    ///
+    /// This also calls `no_more_results` implicitly.
+    pub async fn error_with_sqlstate<E>(
+        self,
+        code: u16,
+        sqlstate: &[u8; 5],
+        msg: &E,
+    ) -> io::Result<()>
+    where
+        E: Borrow<[u8]> + ?Sized,
+    {
+        self.result.error_with_sqlstate(code, sqlstate, msg).await
+    }
+
     /// Indicate to the client that no more rows are coming.
     pub async fn finish(self) -> io::Result<()> {
         self.finish_one().await?.no_more_results().await
@@ -499,3 +955,110 @@ impl<'a, W: AsyncWrite + Unpin + 'a> RowWriter<'a, W> {
         Ok(self.result)
     }
 }
+
+/// The length of the null bitmap of a binary row with `columns`
+fn bitmap_len(columns: &[Column]) -> usize {
+    (columns.len() + 7 + 2) / 8
+}
+
+/// Encode `row` as a row packet for a resultset with `columns` into `row_data`, with the binary
+/// protocol if `is_bin` is set or the text protocol otherwise
+fn encode_row<E>(
+    mut row_data: Vec<u8>,
+    columns: &[Column],
+    is_bin: bool,
+    row: &[E],
+) -> io::Result<Vec<u8>>
+where
+    E: ToMySqlValue,
+{
+    if row.len() != columns.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            if row.len() > columns.len() {
+                "row has more columns than specification"
+            } else {
+                "row has fewer columns than specification"
+            },
+        ));
+    }
+
+    if is_bin {
+        row_data.push(0x00);
+        let bitmap_idx = row_data.len();
+        row_data.resize(bitmap_idx + bitmap_len(columns), 0);
+        for (i, (v, c)) in row.iter().zip(columns).enumerate() {
+            if !v.is_null() {
+                v.to_mysql_bin(&mut row_data, c)?;
+            } else if c.colflags.contains(ColumnFlags::NOT_NULL_FLAG) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "given NULL value for NOT NULL column",
+                ));
+            } else {
+                // See `write_col` for the layout of the null bitmap
+                row_data[bitmap_idx + (i + 2) / 8] |= 1u8 << ((i + 2) % 8);
+            }
+        }
+    } else {
+        for v in row {
+            v.to_mysql_text(&mut row_data)?;
+        }
+    }
+    Ok(row_data)
+}
+
+/// A row encoded ahead of time for a resultset with a given column specification, which can be
+/// written to any number of resultsets with the same column specification with
+/// [`RowWriter::write_encoded_row`] without being encoded again.
+///
+/// Rows are encoded differently for the binary protocol, used to reply to prepared statements, and
+/// the text protocol, used to reply to queries, so a row must be encoded for the protocol of the
+/// resultsets it's written to.
+#[derive(Debug, Clone)]
+pub struct EncodedRow {
+    is_bin: bool,
+    /// The number of columns in the row
+    columns: usize,
+    data: Arc<[u8]>,
+}
+
+impl EncodedRow {
+    /// Encode `row` for the binary protocol, for resultsets with `columns`
+    pub fn binary<E>(columns: &[Column], row: &[E]) -> io::Result<Self>
+    where
+        E: ToMySqlValue,
+    {
+        Self::encode(columns, true, row)
+    }
+
+    /// Encode `row` for the text protocol, for resultsets with `columns`
+    pub fn text<E>(columns: &[Column], row: &[E]) -> io::Result<Self>
+    where
+        E: ToMySqlValue,
+    {
+        Self::encode(columns, false, row)
+    }
+
+    fn encode<E>(columns: &[Column], is_bin: bool, row: &[E]) -> io::Result<Self>
+    where
+        E: ToMySqlValue,
+    {
+        let data = encode_row(Vec::new(), columns, is_bin, row)?;
+        Ok(EncodedRow {
+            is_bin,
+            columns: columns.len(),
+            data: data.into(),
+        })
+    }
+
+    /// Returns whether this row was encoded for the binary protocol
+    pub fn is_binary(&self) -> bool {
+        self.is_bin
+    }
+
+    /// Returns the encoded row, as it's sent in a row packet
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+}
@@ -0,0 +1,139 @@
+//! Session state tracking. Clients that negotiate `CLIENT_SESSION_TRACK` accept a list of changes
+//! to the state of their session at the end of each OK packet, which drivers such as
+//! mysql-connector-j use to keep their view of the session (the current schema, system variables,
+//! GTIDs of committed transactions) consistent with the server's without querying it.
+//!
+//! <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_basic_ok_packet.html>
+
+use std::io;
+
+use crate::myc::io::WriteMysqlExt;
+
+/// `SESSION_TRACK_SYSTEM_VARIABLES`
+const SYSTEM_VARIABLES: u8 = 0x00;
+/// `SESSION_TRACK_SCHEMA`
+const SCHEMA: u8 = 0x01;
+/// `SESSION_TRACK_STATE_CHANGE`
+const STATE_CHANGE: u8 = 0x02;
+/// `SESSION_TRACK_GTIDS`
+const GTIDS: u8 = 0x03;
+
+/// A change to the state of a client's session, sent along with the OK packet for the statement
+/// that caused it. Changes are only sent to clients that connected with `CLIENT_SESSION_TRACK`,
+/// and are silently dropped otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionStateChange {
+    /// A tracked system variable was set to a new value
+    SystemVariable {
+        /// The name of the variable
+        name: String,
+        /// The new value of the variable
+        value: String,
+    },
+    /// The current schema changed
+    Schema(String),
+    /// Whether the state of the session changed in some way that isn't tracked otherwise, such as
+    /// by setting a user-defined variable or creating a temporary table
+    StateChange(bool),
+    /// The GTIDs of the transactions committed by the statement, as a GTID set
+    Gtids(String),
+    /// Changes that are already encoded as the session state info of an OK packet, such as the
+    /// changes reported by an upstream server for a statement proxied to it, which are sent as is
+    Encoded(Vec<u8>),
+}
+
+impl SessionStateChange {
+    fn encode(&self, buf: &mut Vec<u8>) -> io::Result<()> {
+        if let SessionStateChange::Encoded(encoded) = self {
+            buf.extend_from_slice(encoded);
+            return Ok(());
+        }
+
+        let mut data = Vec::new();
+        let ty = match self {
+            SessionStateChange::SystemVariable { name, value } => {
+                data.write_lenenc_str(name.as_bytes())?;
+                data.write_lenenc_str(value.as_bytes())?;
+                SYSTEM_VARIABLES
+            }
+            SessionStateChange::Schema(schema) => {
+                data.write_lenenc_str(schema.as_bytes())?;
+                SCHEMA
+            }
+            SessionStateChange::StateChange(changed) => {
+                data.write_lenenc_str(if *changed { b"1" } else { b"0" })?;
+                STATE_CHANGE
+            }
+            SessionStateChange::Gtids(gtids) => {
+                // The encoding specification, of which there is only one
+                data.push(0);
+                data.write_lenenc_str(gtids.as_bytes())?;
+                GTIDS
+            }
+            SessionStateChange::Encoded(_) => unreachable!("Encoded changes are written as is"),
+        };
+        buf.push(ty);
+        buf.write_lenenc_str(&data)?;
+        Ok(())
+    }
+}
+
+/// Encode `changes` as the session state info of an OK packet
+pub(crate) fn encode(changes: &[SessionStateChange]) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for change in changes {
+        change.encode(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_changes() {
+        let encoded = encode(&[
+            SessionStateChange::Schema("db".into()),
+            SessionStateChange::SystemVariable {
+                name: "autocommit".into(),
+                value: "OFF".into(),
+            },
+            SessionStateChange::StateChange(true),
+        ])
+        .unwrap();
+
+        let mut expected = vec![SCHEMA, 3, 2];
+        expected.extend_from_slice(b"db");
+        expected.extend_from_slice(&[SYSTEM_VARIABLES, 15, 10]);
+        expected.extend_from_slice(b"autocommit");
+        expected.push(3);
+        expected.extend_from_slice(b"OFF");
+        expected.extend_from_slice(&[STATE_CHANGE, 2, 1, b'1']);
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn encodes_gtids() {
+        let gtids = "3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5";
+        let encoded = encode(&[SessionStateChange::Gtids(gtids.into())]).unwrap();
+
+        let mut expected = vec![GTIDS, gtids.len() as u8 + 2, 0, gtids.len() as u8];
+        expected.extend_from_slice(gtids.as_bytes());
+        assert_eq!(encoded, expected);
+    }
+
+    #[test]
+    fn encodes_encoded_changes_as_is() {
+        let upstream = encode(&[SessionStateChange::Schema("db".into())]).unwrap();
+        let encoded = encode(&[
+            SessionStateChange::Encoded(upstream.clone()),
+            SessionStateChange::StateChange(true),
+        ])
+        .unwrap();
+
+        let mut expected = upstream;
+        expected.extend_from_slice(&[STATE_CHANGE, 2, 1, b'1']);
+        assert_eq!(encoded, expected);
+    }
+}
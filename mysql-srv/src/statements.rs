@@ -0,0 +1,158 @@
+//! Splitting a query sent by a client with `CLIENT_MULTI_STATEMENTS` enabled into the individual
+//! statements it contains.
+
+/// Split `query` into the statements it contains, which are separated by semicolons. Semicolons
+/// within string literals, quoted identifiers and comments don't separate statements. Each
+/// statement is trimmed of surrounding whitespace, and empty statements are left out.
+///
+/// This doesn't know about compound statements, so if `query` creates a stored program (whose body
+/// may contain semicolons, as in a `CREATE TRIGGER ... BEGIN ... END`), it isn't split at all, and
+/// is returned whole as a single statement.
+pub(crate) fn split(query: &str) -> Vec<&str> {
+    let bytes = query.as_bytes();
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                i += 1;
+                while i < bytes.len() {
+                    if bytes[i] == b'\\' && quote != b'`' {
+                        i += 1;
+                    } else if bytes[i] == quote {
+                        // A doubled quote is an escaped quote, which the next iteration skips
+                        if bytes.get(i + 1) != Some(&quote) {
+                            break;
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'#' => i = line_end(bytes, i),
+            b'-' if bytes[i..].starts_with(b"--")
+                && bytes.get(i + 2).map_or(true, u8::is_ascii_whitespace) =>
+            {
+                i = line_end(bytes, i)
+            }
+            b'/' if bytes[i..].starts_with(b"/*") => {
+                i = bytes[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 1);
+            }
+            b';' => {
+                statements.push(&query[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    statements.push(&query[start.min(query.len())..]);
+
+    let statements: Vec<&str> = statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .collect();
+    if statements
+        .iter()
+        .any(|statement| creates_stored_program(statement))
+    {
+        return vec![query.trim()];
+    }
+    statements
+}
+
+/// Returns whether `statement` is a `CREATE PROCEDURE`, `FUNCTION`, `TRIGGER` or `EVENT`, possibly
+/// with a `DEFINER`
+fn creates_stored_program(statement: &str) -> bool {
+    let mut words = statement
+        .split(|c: char| c.is_ascii_whitespace() || c == '=')
+        .filter(|word| !word.is_empty());
+    if !words
+        .next()
+        .map_or(false, |word| word.eq_ignore_ascii_case("CREATE"))
+    {
+        return false;
+    }
+    while let Some(word) = words.next() {
+        if word.eq_ignore_ascii_case("DEFINER") {
+            words.next();
+        } else if !["OR", "REPLACE", "AGGREGATE"]
+            .iter()
+            .any(|skipped| word.eq_ignore_ascii_case(skipped))
+        {
+            return ["PROCEDURE", "FUNCTION", "TRIGGER", "EVENT"]
+                .iter()
+                .any(|kind| word.eq_ignore_ascii_case(kind));
+        }
+    }
+    false
+}
+
+/// Returns the index of the newline ending the line containing `i`, or the end of `bytes`
+fn line_end(bytes: &[u8], i: usize) -> usize {
+    bytes[i..]
+        .iter()
+        .position(|b| *b == b'\n')
+        .map_or(bytes.len(), |end| i + end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_statement() {
+        assert_eq!(split("SELECT 1"), vec!["SELECT 1"]);
+        assert_eq!(split(" SELECT 1; "), vec!["SELECT 1"]);
+        assert_eq!(split(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn multiple_statements() {
+        assert_eq!(
+            split("SELECT 1;UPDATE t SET x = 1;\n;  DELETE FROM t"),
+            vec!["SELECT 1", "UPDATE t SET x = 1", "DELETE FROM t"]
+        );
+    }
+
+    #[test]
+    fn quoted_semicolons() {
+        assert_eq!(
+            split(r#"SELECT 'a;b', "c;\";d", `e;f`, 'g'';h'; SELECT 2"#),
+            vec![r#"SELECT 'a;b', "c;\";d", `e;f`, 'g'';h'"#, "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn commented_semicolons() {
+        assert_eq!(
+            split("SELECT 1 -- a;b\n; SELECT 2 # c;d\n; SELECT /* e;f */ 3; SELECT 4--5;"),
+            vec![
+                "SELECT 1 -- a;b",
+                "SELECT 2 # c;d",
+                "SELECT /* e;f */ 3",
+                "SELECT 4--5"
+            ]
+        );
+    }
+
+    #[test]
+    fn stored_programs_are_not_split() {
+        let trigger = "CREATE DEFINER=`root`@`%` TRIGGER t BEFORE INSERT ON x FOR EACH ROW \
+                       BEGIN SET NEW.a = 1; SET NEW.b = 2; END";
+        assert_eq!(split(trigger), vec![trigger]);
+
+        let procedure = "CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 2; END; CALL p()";
+        assert_eq!(split(procedure), vec![procedure]);
+
+        assert_eq!(
+            split("CREATE TABLE function (x int); SELECT 1"),
+            vec!["CREATE TABLE function (x int)", "SELECT 1"]
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Upgrading client connections to TLS. If TLS is enabled, the server advertises `CLIENT_SSL` in
+//! the initial handshake, and a client can reply with an `SSLRequest` (the first 32 bytes of its
+//! handshake response) instead of the full handshake response, then perform a TLS handshake and
+//! send the full handshake response over the encrypted connection. If clients must present a
+//! certificate, a client that sends its handshake response in plaintext is denied access.
+//!
+//! <https://dev.mysql.com/doc/dev/mysql-server/latest/page_protocol_connection_phase_packets_protocol_ssl_request.html>
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, IoSlice};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+fn invalid_data<E: fmt::Display>(path: &Path, error: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {error}", path.display()),
+    )
+}
+
+/// Load every PEM-encoded certificate in the file at `path`
+fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))?;
+    if certs.is_empty() {
+        return Err(invalid_data(path, "no certificates found"));
+    }
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+/// Load the first PEM-encoded private key in the file at `path`
+fn load_key(path: &Path) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(
+                rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::ECKey(key),
+            ) => return Ok(PrivateKey(key)),
+            Some(_) => {}
+            None => return Err(invalid_data(path, "no private key found")),
+        }
+    }
+}
+
+/// How the server accepts TLS connections from clients, built by [`tls_config`]
+#[derive(Clone)]
+pub struct TlsConfig {
+    acceptor: TlsAcceptor,
+    /// Whether clients have to connect over TLS, which they do if they must present a client
+    /// certificate
+    required: bool,
+}
+
+/// Build a [`TlsConfig`] for accepting TLS connections from clients, with the PEM-encoded
+/// certificate chain at `cert_path` and private key at `key_path`.
+///
+/// If `client_ca_path` is set, clients must connect over TLS and present a certificate signed by
+/// one of the PEM-encoded CA certificates in that file. Otherwise, clients may also connect in
+/// plaintext.
+pub fn tls_config(
+    cert_path: &Path,
+    key_path: &Path,
+    client_ca_path: Option<&Path>,
+) -> io::Result<TlsConfig> {
+    let certs = load_certs(cert_path)?;
+    let key = load_key(key_path)?;
+
+    let config = ServerConfig::builder().with_safe_defaults();
+    let config = match client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots.add(&cert).map_err(|e| invalid_data(path, e))?;
+            }
+            config.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => config.with_no_client_auth(),
+    };
+    let config = config
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid_data(key_path, e))?;
+
+    Ok(TlsConfig {
+        acceptor: TlsAcceptor::from(Arc::new(config)),
+        required: client_ca_path.is_some(),
+    })
+}
+
+/// A TCP stream with bytes in front of it that were already read from the socket. When reading
+/// the `SSLRequest`, we may also read the start of the TLS handshake the client sent right after
+/// it, which the TLS handshake has to read first.
+struct Prefixed {
+    prefix: Vec<u8>,
+    stream: TcpStream,
+}
+
+impl AsyncRead for Prefixed {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.prefix.is_empty() {
+            return Pin::new(&mut self.stream).poll_read(cx, buf);
+        }
+        let len = self.prefix.len().min(buf.remaining());
+        buf.put_slice(&self.prefix[..len]);
+        self.prefix.drain(..len);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Prefixed {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+enum Stream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<Prefixed>>),
+    /// The TLS handshake is in progress
+    Upgrading,
+}
+
+fn upgrading_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "connection is being upgraded to TLS")
+}
+
+fn wrong_half_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "wrong half of a split connection")
+}
+
+/// One side of a connection to a client, which is either the read or the write half of a
+/// connection that stays in plaintext, or a handle to a connection the client may upgrade to TLS
+/// partway through the connection phase. The halves of a connection that stays in plaintext are
+/// split from each other, so that reading from and writing to it don't contend on a lock.
+pub struct ClientStream(Half);
+
+enum Half {
+    Read(OwnedReadHalf),
+    Write(OwnedWriteHalf),
+    Upgradable(Arc<Mutex<Stream>>),
+}
+
+impl ClientStream {
+    /// Split a connection that will stay in plaintext into its read and write halves
+    pub(crate) fn split(stream: TcpStream) -> (Self, Self) {
+        let (reader, writer) = stream.into_split();
+        (Self(Half::Read(reader)), Self(Half::Write(writer)))
+    }
+}
+
+/// Evaluate `$e` with `$stream` bound to the (pinned) stream the upgradable connection `$inner`
+/// currently uses
+macro_rules! with_stream {
+    ($inner:expr, $stream:ident => $e:expr) => {
+        match &mut *$inner.lock().expect("poisoned") {
+            Stream::Plain($stream) => {
+                let $stream = Pin::new($stream);
+                $e
+            }
+            Stream::Tls($stream) => {
+                let $stream = Pin::new($stream);
+                $e
+            }
+            Stream::Upgrading => Poll::Ready(Err(upgrading_error())),
+        }
+    };
+}
+
+/// Evaluate `$e` with `$stream` bound to the (pinned) write half or upgradable connection of
+/// `$self`
+macro_rules! with_writer {
+    ($self:ident, $stream:ident => $e:expr) => {
+        match &mut $self.0 {
+            Half::Write($stream) => {
+                let $stream = Pin::new($stream);
+                $e
+            }
+            Half::Upgradable(inner) => with_stream!(inner, $stream => $e),
+            Half::Read(_) => Poll::Ready(Err(wrong_half_error())),
+        }
+    };
+}
+
+impl AsyncRead for ClientStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.0 {
+            Half::Read(stream) => Pin::new(stream).poll_read(cx, buf),
+            Half::Upgradable(inner) => with_stream!(inner, stream => stream.poll_read(cx, buf)),
+            Half::Write(_) => Poll::Ready(Err(wrong_half_error())),
+        }
+    }
+}
+
+impl AsyncWrite for ClientStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        with_writer!(self, stream => stream.poll_write(cx, buf))
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        with_writer!(self, stream => stream.poll_write_vectored(cx, bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match &self.0 {
+            Half::Write(stream) => stream.is_write_vectored(),
+            Half::Upgradable(inner) => match &*inner.lock().expect("poisoned") {
+                Stream::Plain(stream) => stream.is_write_vectored(),
+                Stream::Tls(stream) => stream.is_write_vectored(),
+                Stream::Upgrading => false,
+            },
+            Half::Read(_) => false,
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        with_writer!(self, stream => stream.poll_flush(cx))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        with_writer!(self, stream => stream.poll_shutdown(cx))
+    }
+}
+
+/// A connection that the client may upgrade to TLS, along with the acceptor to perform the TLS
+/// handshake with
+pub(crate) struct TlsUpgrade {
+    acceptor: TlsAcceptor,
+    required: bool,
+    stream: Arc<Mutex<Stream>>,
+}
+
+impl TlsUpgrade {
+    /// Let the client upgrade `stream` to TLS as configured by `config`, returning the upgrade
+    /// along with a handle to read from the connection and another to write to it
+    pub(crate) fn new(config: TlsConfig, stream: TcpStream) -> (Self, ClientStream, ClientStream) {
+        let stream = Arc::new(Mutex::new(Stream::Plain(stream)));
+        let upgrade = Self {
+            acceptor: config.acceptor,
+            required: config.required,
+            stream: stream.clone(),
+        };
+        (
+            upgrade,
+            ClientStream(Half::Upgradable(stream.clone())),
+            ClientStream(Half::Upgradable(stream)),
+        )
+    }
+
+    /// Returns true if the client has to upgrade the connection to TLS
+    pub(crate) fn required(&self) -> bool {
+        self.required
+    }
+
+    /// Perform the TLS handshake with the client, once it's sent an `SSLRequest`. `buffered` is
+    /// any data that's already been read from the connection after the `SSLRequest`.
+    pub(crate) async fn upgrade(self, buffered: Vec<u8>) -> io::Result<()> {
+        let stream = {
+            let mut inner = self.stream.lock().expect("poisoned");
+            match std::mem::replace(&mut *inner, Stream::Upgrading) {
+                Stream::Plain(stream) => stream,
+                stream => {
+                    *inner = stream;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "connection has already been upgraded to TLS",
+                    ));
+                }
+            }
+        };
+
+        let stream = self
+            .acceptor
+            .accept(Prefixed {
+                prefix: buffered,
+                stream,
+            })
+            .await?;
+        *self.stream.lock().expect("poisoned") = Stream::Tls(Box::new(stream));
+        Ok(())
+    }
+}
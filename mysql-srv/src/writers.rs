@@ -7,7 +7,7 @@ use tokio::io::AsyncWrite;
 use crate::myc::constants::StatusFlags;
 use crate::myc::io::WriteMysqlExt;
 use crate::packet::PacketWriter;
-use crate::{Column, ErrorKind};
+use crate::{session_state, Column, ErrorKind, SessionStateChange};
 
 pub(crate) async fn write_eof_packet<W: AsyncWrite + Unpin>(
     w: &mut PacketWriter<W>,
@@ -24,15 +24,38 @@ pub(crate) async fn write_ok_packet<W: AsyncWrite + Unpin>(
     rows: u64,
     last_insert_id: u64,
     s: StatusFlags,
+) -> io::Result<()> {
+    write_ok_packet_with_warnings(w, rows, last_insert_id, 0, s, &[]).await
+}
+
+/// Write an OK packet. If `session_state` is non-empty, it's sent as the session state info of
+/// the packet, which is only valid if the client connected with `CLIENT_SESSION_TRACK`.
+/// `SERVER_SESSION_STATE_CHANGED` is set in the status flags if and only if it is, whatever `s`
+/// says (such as when relaying the status flags of an upstream server).
+pub(crate) async fn write_ok_packet_with_warnings<W: AsyncWrite + Unpin>(
+    w: &mut PacketWriter<W>,
+    rows: u64,
+    last_insert_id: u64,
+    warnings: u16,
+    mut s: StatusFlags,
+    session_state: &[SessionStateChange],
 ) -> io::Result<()> {
     const MAX_OK_PACKET_LEN: usize = 1 + 9 + 9 + 2 + 2;
     let mut buf = w.get_buffer();
     buf.reserve(MAX_OK_PACKET_LEN);
+    s.set(
+        StatusFlags::SERVER_SESSION_STATE_CHANGED,
+        !session_state.is_empty(),
+    );
     buf.write_u8(0x00)?; // OK packet type
     buf.write_lenenc_int(rows)?;
     buf.write_lenenc_int(last_insert_id)?;
     buf.write_u16::<LittleEndian>(s.bits())?;
-    buf.write_all(&[0x00, 0x00])?; // no warnings
+    buf.write_u16::<LittleEndian>(warnings)?;
+    if !session_state.is_empty() {
+        buf.write_lenenc_str(b"")?; // info
+        buf.write_lenenc_str(&session_state::encode(session_state)?)?;
+    }
     w.enqueue_packet(buf);
     Ok(())
 }
@@ -41,13 +64,24 @@ pub async fn write_err<W: AsyncWrite + Unpin>(
     err: ErrorKind,
     msg: &[u8],
     w: &mut PacketWriter<W>,
+) -> io::Result<()> {
+    write_err_with_sqlstate(err as u16, err.sqlstate(), msg, w).await
+}
+
+/// Write an error packet with an arbitrary error code and SQLSTATE, such as one returned by an
+/// upstream server, which may not correspond to any [`ErrorKind`]
+pub async fn write_err_with_sqlstate<W: AsyncWrite + Unpin>(
+    code: u16,
+    sqlstate: &[u8; 5],
+    msg: &[u8],
+    w: &mut PacketWriter<W>,
 ) -> io::Result<()> {
     let mut buf = w.get_buffer();
     buf.reserve(4 + 5 + msg.len());
     buf.write_u8(0xFF)?;
-    buf.write_u16::<LittleEndian>(err as u16)?;
+    buf.write_u16::<LittleEndian>(code)?;
     buf.write_u8(b'#')?;
-    buf.write_all(err.sqlstate())?;
+    buf.write_all(sqlstate)?;
     buf.write_all(msg)?;
     w.write_packet(&buf).await
 }
@@ -305,9 +305,38 @@ impl MySqlTime {
             .max(0)
     }
 
+    /// Rounds this [`MySqlTime`] to the given number of fractional second `digits` (at most 6),
+    /// rounding halves away from zero, as MySQL does when storing a value in a `TIME` column with
+    /// a lower precision.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use mysql_time::MySqlTime;
+    ///
+    /// let mysql_time = MySqlTime::from_hmsus(false, 2, 23, 58, 829513); // -02:23:58.829513
+    /// assert_eq!(
+    ///     mysql_time.round_subseconds(3),
+    ///     MySqlTime::from_hmsus(false, 2, 23, 58, 830000)
+    /// );
+    /// assert_eq!(
+    ///     mysql_time.round_subseconds(0),
+    ///     MySqlTime::from_hmsus(false, 2, 23, 59, 0)
+    /// );
+    /// ```
+    pub fn round_subseconds(&self, digits: u8) -> MySqlTime {
+        let unit = 1000 * 10_i64.pow(6 - u32::from(digits.min(6)));
+        let rounded = (self.nanos.abs() + unit / 2) / unit * unit;
+        MySqlTime::new(Duration::nanoseconds(rounded * self.nanos.signum()))
+    }
+
     fn duration(&self) -> Duration {
         Duration::nanoseconds(self.nanos)
     }
+
+    fn total_microseconds(&self) -> i64 {
+        self.nanos / 1000
+    }
 }
 
 impl Default for MySqlTime {
@@ -357,12 +386,9 @@ impl PartialOrd for MySqlTime {
 
 impl Ord for MySqlTime {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.is_positive()
-            .cmp(&other.is_positive())
-            .then(self.hour().cmp(&other.hour()))
-            .then(self.minutes().cmp(&other.minutes()))
-            .then(self.seconds().cmp(&other.seconds()))
-            .then(self.microseconds().cmp(&other.microseconds()))
+        // Comparing the components one by one would order negative times backwards, so compare
+        // the signed number of microseconds instead (which agrees with `PartialEq`)
+        self.total_microseconds().cmp(&other.total_microseconds())
     }
 }
 
@@ -809,6 +835,38 @@ mod tests {
         assert!(mysql_time1 < mysql_time3)
     }
 
+    #[test]
+    fn ord_negative() {
+        let minus_two_hours = MySqlTime::from_hmsus(false, 2, 0, 0, 0);
+        let minus_one_hour = MySqlTime::from_hmsus(false, 1, 0, 0, 0);
+        let minus_one_us = MySqlTime::from_microseconds(-1);
+        let zero = MySqlTime::from_microseconds(0);
+        let one_hour = MySqlTime::from_hmsus(true, 1, 0, 0, 0);
+
+        assert!(minus_two_hours < minus_one_hour);
+        assert!(minus_one_hour < minus_one_us);
+        assert!(minus_one_us < zero);
+        assert!(zero < one_hour);
+    }
+
+    #[test]
+    fn round_subseconds() {
+        let mysql_time = MySqlTime::from_hmsus(true, 10, 59, 59, 999_500);
+        assert_time!(mysql_time.round_subseconds(6), true, 10, 59, 59, 999_500);
+        assert_time!(mysql_time.round_subseconds(3), true, 11, 0, 0, 0);
+        assert_time!(mysql_time.round_subseconds(4), true, 10, 59, 59, 999_500);
+
+        let mysql_time = MySqlTime::from_hmsus(false, 0, 0, 1, 250_000);
+        assert_time!(mysql_time.round_subseconds(1), false, 0, 0, 1, 300_000);
+        assert_time!(mysql_time.round_subseconds(0), false, 0, 0, 1, 0);
+
+        // Rounding never goes past the edges of the range
+        assert_eq!(
+            MySqlTime::max_value().round_subseconds(0),
+            MySqlTime::max_value()
+        );
+    }
+
     #[proptest]
     fn sub(
         #[strategy(arbitrary_duration())] duration1: Duration,
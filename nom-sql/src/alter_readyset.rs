@@ -0,0 +1,216 @@
+use std::fmt;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::u64;
+use nom::combinator::{map, opt};
+use nom::sequence::{preceded, tuple};
+use nom_locate::LocatedSpan;
+use readyset_util::fmt::fmt_with;
+use serde::{Deserialize, Serialize};
+
+use crate::common::statement_terminator;
+use crate::table::relation;
+use crate::whitespace::whitespace1;
+use crate::{Dialect, NomSqlResult, Relation, SqlIdentifier};
+
+/// ALTER READYSET statements
+///
+/// This is a non-standard ReadySet-specific extension to SQL
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum AlterReadysetStatement {
+    /// Drop all caches and re-create them from their `CREATE CACHE` statements, against the
+    /// current schema, in a single migration. Restoring caches against any other schema is not
+    /// supported.
+    RestoreCaches,
+    /// Emit structured log events for every execution of the query with the given id, for a
+    /// bounded duration
+    TraceQuery {
+        /// The id of the query to trace, as shown by `SHOW PROXIED QUERIES` or `SHOW CACHES`
+        id: SqlIdentifier,
+        /// How long to trace the query for, in seconds. If not specified, a default duration is
+        /// used.
+        duration_secs: Option<u64>,
+    },
+    /// Drop the rows of a single replicated table and copy them from the upstream database again,
+    /// without restarting replication for the other tables
+    ResnapshotTable {
+        /// The table to resnapshot
+        table: Relation,
+    },
+}
+
+impl AlterReadysetStatement {
+    pub fn display(&self, dialect: Dialect) -> impl fmt::Display + Copy + '_ {
+        fmt_with(move |f| {
+            write!(f, "ALTER READYSET ")?;
+            match self {
+                AlterReadysetStatement::RestoreCaches => write!(f, "RESTORE CACHES"),
+                AlterReadysetStatement::TraceQuery { id, duration_secs } => {
+                    write!(f, "TRACE QUERY {id}")?;
+                    if let Some(duration_secs) = duration_secs {
+                        write!(f, " FOR {duration_secs} SECONDS")?;
+                    }
+                    Ok(())
+                }
+                AlterReadysetStatement::ResnapshotTable { table } => {
+                    write!(f, "RESNAPSHOT TABLE {}", table.display(dialect))
+                }
+            }
+        })
+    }
+}
+
+fn trace_query(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], AlterReadysetStatement> {
+    move |i| {
+        let (i, _) = tag_no_case("trace")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, _) = tag_no_case("query")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, id) = dialect.identifier()(i)?;
+        let (i, duration_secs) = opt(preceded(
+            tuple((whitespace1, tag_no_case("for"), whitespace1)),
+            |i| {
+                let (i, secs) = u64(i)?;
+                let (i, _) = whitespace1(i)?;
+                let (i, _) = alt((tag_no_case("seconds"), tag_no_case("second")))(i)?;
+                Ok((i, secs))
+            },
+        ))(i)?;
+        Ok((i, AlterReadysetStatement::TraceQuery { id, duration_secs }))
+    }
+}
+
+fn resnapshot_table(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], AlterReadysetStatement> {
+    move |i| {
+        let (i, _) = tag_no_case("resnapshot")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, _) = tag_no_case("table")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, table) = relation(dialect)(i)?;
+        Ok((i, AlterReadysetStatement::ResnapshotTable { table }))
+    }
+}
+
+pub(crate) fn alter_readyset_statement(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], AlterReadysetStatement> {
+    move |i| {
+        let (i, _) = tag_no_case("alter")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, _) = tag_no_case("readyset")(i)?;
+        let (i, _) = whitespace1(i)?;
+        let (i, stmt) = alt((
+            map(
+                tuple((tag_no_case("restore"), whitespace1, tag_no_case("caches"))),
+                |_| AlterReadysetStatement::RestoreCaches,
+            ),
+            trace_query(dialect),
+            resnapshot_table(dialect),
+        ))(i)?;
+        let (i, _) = statement_terminator(i)?;
+        Ok((i, stmt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_caches() {
+        assert_eq!(
+            alter_readyset_statement(Dialect::MySQL)(LocatedSpan::new(
+                b"alter readyset restore caches;"
+            ))
+            .unwrap()
+            .1,
+            AlterReadysetStatement::RestoreCaches
+        );
+    }
+
+    #[test]
+    fn trace_query() {
+        assert_eq!(
+            alter_readyset_statement(Dialect::MySQL)(LocatedSpan::new(
+                b"ALTER READYSET TRACE QUERY q_0123456789abcdef FOR 30 SECONDS"
+            ))
+            .unwrap()
+            .1,
+            AlterReadysetStatement::TraceQuery {
+                id: "q_0123456789abcdef".into(),
+                duration_secs: Some(30),
+            }
+        );
+        assert_eq!(
+            alter_readyset_statement(Dialect::PostgreSQL)(LocatedSpan::new(
+                b"alter readyset trace query q_0123456789abcdef;"
+            ))
+            .unwrap()
+            .1,
+            AlterReadysetStatement::TraceQuery {
+                id: "q_0123456789abcdef".into(),
+                duration_secs: None,
+            }
+        );
+    }
+
+    #[test]
+    fn resnapshot_table() {
+        assert_eq!(
+            alter_readyset_statement(Dialect::MySQL)(LocatedSpan::new(
+                b"ALTER READYSET RESNAPSHOT TABLE `db`.`t1`"
+            ))
+            .unwrap()
+            .1,
+            AlterReadysetStatement::ResnapshotTable {
+                table: Relation {
+                    schema: Some("db".into()),
+                    name: "t1".into(),
+                },
+            }
+        );
+        assert_eq!(
+            alter_readyset_statement(Dialect::PostgreSQL)(LocatedSpan::new(
+                b"alter readyset resnapshot table t1;"
+            ))
+            .unwrap()
+            .1,
+            AlterReadysetStatement::ResnapshotTable { table: "t1".into() }
+        );
+    }
+
+    #[test]
+    fn restore_caches_display() {
+        assert_eq!(
+            AlterReadysetStatement::RestoreCaches
+                .display(Dialect::MySQL)
+                .to_string(),
+            "ALTER READYSET RESTORE CACHES"
+        );
+        assert_eq!(
+            AlterReadysetStatement::TraceQuery {
+                id: "q_0123456789abcdef".into(),
+                duration_secs: Some(1),
+            }
+            .display(Dialect::MySQL)
+            .to_string(),
+            "ALTER READYSET TRACE QUERY q_0123456789abcdef FOR 1 SECONDS"
+        );
+        assert_eq!(
+            AlterReadysetStatement::ResnapshotTable {
+                table: Relation {
+                    schema: Some("public".into()),
+                    name: "t1".into(),
+                },
+            }
+            .display(Dialect::PostgreSQL)
+            .to_string(),
+            r#"ALTER READYSET RESNAPSHOT TABLE "public"."t1""#
+        );
+    }
+}
@@ -16,15 +16,15 @@ use crate::select::LimitClause;
 use crate::set::Variable;
 use crate::transaction::{CommitStatement, RollbackStatement, StartTransactionStatement};
 use crate::{
-    AlterColumnOperation, AlterTableDefinition, AlterTableStatement, CacheInner, CaseWhenBranch,
-    Column, ColumnConstraint, ColumnSpecification, CommonTableExpr, CompoundSelectStatement,
-    CreateCacheStatement, CreateTableStatement, CreateViewStatement, DeleteStatement,
-    DropAllCachesStatement, DropCacheStatement, DropTableStatement, DropViewStatement,
-    ExplainStatement, Expr, FieldDefinitionExpr, FieldReference, FunctionExpr, GroupByClause,
-    InValue, InsertStatement, JoinClause, JoinConstraint, JoinRightSide, Literal, OrderClause,
-    Relation, SelectSpecification, SelectStatement, SetNames, SetPostgresParameter, SetStatement,
-    SetVariables, ShowStatement, SqlIdentifier, SqlQuery, SqlType, TableExpr, TableExprInner,
-    TableKey, UpdateStatement, UseStatement,
+    AlterColumnOperation, AlterReadysetStatement, AlterTableDefinition, AlterTableStatement,
+    CacheInner, CaseWhenBranch, Column, ColumnConstraint, ColumnSpecification, CommonTableExpr,
+    CompoundSelectStatement, CreateCacheStatement, CreateTableStatement, CreateViewStatement,
+    DeleteStatement, DropAllCachesStatement, DropCacheStatement, DropTableStatement,
+    DropViewStatement, ExplainStatement, Expr, FieldDefinitionExpr, FieldReference, FunctionExpr,
+    GroupByClause, InValue, InsertStatement, JoinClause, JoinConstraint, JoinRightSide, Literal,
+    OrderClause, Relation, SelectSpecification, SelectStatement, SetNames, SetPostgresParameter,
+    SetStatement, SetVariables, ShowStatement, SqlIdentifier, SqlQuery, SqlType, TableExpr,
+    TableExprInner, TableKey, UpdateStatement, UseStatement,
 };
 
 /// Each method of the `Visitor` trait is a hook to be potentially overridden when recursively
@@ -396,6 +396,13 @@ pub trait Visitor<'ast>: Sized {
         Ok(())
     }
 
+    fn visit_alter_readyset_statement(
+        &mut self,
+        _alter_readyset_statement: &'ast AlterReadysetStatement,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     fn visit_sql_query(&mut self, sql_query: &'ast SqlQuery) -> Result<(), Self::Error> {
         walk_sql_query(self, sql_query)
     }
@@ -850,7 +857,9 @@ pub fn walk_column_constraint<'a, V: Visitor<'a>>(
     column_constraint: &'a ColumnConstraint,
 ) -> Result<(), V::Error> {
     match column_constraint {
-        ColumnConstraint::DefaultValue(expr) => visitor.visit_expr(expr),
+        ColumnConstraint::DefaultValue(expr) | ColumnConstraint::GeneratedAs { expr, .. } => {
+            visitor.visit_expr(expr)
+        }
         ColumnConstraint::Null
         | ColumnConstraint::NotNull
         | ColumnConstraint::CharacterSet(_)
@@ -858,7 +867,8 @@ pub fn walk_column_constraint<'a, V: Visitor<'a>>(
         | ColumnConstraint::AutoIncrement
         | ColumnConstraint::PrimaryKey
         | ColumnConstraint::Unique
-        | ColumnConstraint::OnUpdateCurrentTimestamp => Ok(()),
+        | ColumnConstraint::OnUpdateCurrentTimestamp
+        | ColumnConstraint::Invisible => Ok(()),
     }
 }
 
@@ -1111,6 +1121,7 @@ pub fn walk_sql_query<'a, V: Visitor<'a>>(
         SqlQuery::Use(statement) => visitor.visit_use_statement(statement),
         SqlQuery::Show(statement) => visitor.visit_show_statement(statement),
         SqlQuery::Explain(statement) => visitor.visit_explain_statement(statement),
+        SqlQuery::AlterReadySet(statement) => visitor.visit_alter_readyset_statement(statement),
     }
 }
 
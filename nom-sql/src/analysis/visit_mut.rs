@@ -16,15 +16,15 @@ use crate::select::LimitClause;
 use crate::set::Variable;
 use crate::transaction::{CommitStatement, RollbackStatement, StartTransactionStatement};
 use crate::{
-    AlterColumnOperation, AlterTableDefinition, AlterTableStatement, CacheInner, CaseWhenBranch,
-    Column, ColumnConstraint, ColumnSpecification, CommonTableExpr, CompoundSelectStatement,
-    CreateCacheStatement, CreateTableStatement, CreateViewStatement, DeleteStatement,
-    DropAllCachesStatement, DropCacheStatement, DropTableStatement, DropViewStatement,
-    ExplainStatement, Expr, FieldDefinitionExpr, FieldReference, FunctionExpr, GroupByClause,
-    InValue, InsertStatement, JoinClause, JoinConstraint, JoinRightSide, Literal, OrderClause,
-    Relation, SelectSpecification, SelectStatement, SetNames, SetPostgresParameter, SetStatement,
-    SetVariables, ShowStatement, SqlIdentifier, SqlQuery, SqlType, TableExpr, TableExprInner,
-    TableKey, UpdateStatement, UseStatement,
+    AlterColumnOperation, AlterReadysetStatement, AlterTableDefinition, AlterTableStatement,
+    CacheInner, CaseWhenBranch, Column, ColumnConstraint, ColumnSpecification, CommonTableExpr,
+    CompoundSelectStatement, CreateCacheStatement, CreateTableStatement, CreateViewStatement,
+    DeleteStatement, DropAllCachesStatement, DropCacheStatement, DropTableStatement,
+    DropViewStatement, ExplainStatement, Expr, FieldDefinitionExpr, FieldReference, FunctionExpr,
+    GroupByClause, InValue, InsertStatement, JoinClause, JoinConstraint, JoinRightSide, Literal,
+    OrderClause, Relation, SelectSpecification, SelectStatement, SetNames, SetPostgresParameter,
+    SetStatement, SetVariables, ShowStatement, SqlIdentifier, SqlQuery, SqlType, TableExpr,
+    TableExprInner, TableKey, UpdateStatement, UseStatement,
 };
 
 /// Each method of the `VisitorMut` trait is a hook to be potentially overridden when recursively
@@ -411,6 +411,13 @@ pub trait VisitorMut<'ast>: Sized {
         Ok(())
     }
 
+    fn visit_alter_readyset_statement(
+        &mut self,
+        _alter_readyset_statement: &'ast mut AlterReadysetStatement,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     fn visit_sql_query(&mut self, sql_query: &'ast mut SqlQuery) -> Result<(), Self::Error> {
         walk_sql_query(self, sql_query)
     }
@@ -867,7 +874,9 @@ pub fn walk_column_constraint<'a, V: VisitorMut<'a>>(
     column_constraint: &'a mut ColumnConstraint,
 ) -> Result<(), V::Error> {
     match column_constraint {
-        ColumnConstraint::DefaultValue(expr) => visitor.visit_expr(expr),
+        ColumnConstraint::DefaultValue(expr) | ColumnConstraint::GeneratedAs { expr, .. } => {
+            visitor.visit_expr(expr)
+        }
         ColumnConstraint::Null
         | ColumnConstraint::NotNull
         | ColumnConstraint::CharacterSet(_)
@@ -875,7 +884,8 @@ pub fn walk_column_constraint<'a, V: VisitorMut<'a>>(
         | ColumnConstraint::AutoIncrement
         | ColumnConstraint::PrimaryKey
         | ColumnConstraint::Unique
-        | ColumnConstraint::OnUpdateCurrentTimestamp => Ok(()),
+        | ColumnConstraint::OnUpdateCurrentTimestamp
+        | ColumnConstraint::Invisible => Ok(()),
     }
 }
 
@@ -1128,6 +1138,7 @@ pub fn walk_sql_query<'a, V: VisitorMut<'a>>(
         SqlQuery::Use(statement) => visitor.visit_use_statement(statement),
         SqlQuery::Show(statement) => visitor.visit_show_statement(statement),
         SqlQuery::Explain(statement) => visitor.visit_explain_statement(statement),
+        SqlQuery::AlterReadySet(statement) => visitor.visit_alter_readyset_statement(statement),
     }
 }
 
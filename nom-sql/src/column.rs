@@ -2,10 +2,10 @@ use std::cmp::Ordering;
 use std::{fmt, str};
 
 use nom::branch::alt;
-use nom::bytes::complete::{tag, tag_no_case};
-use nom::combinator::{map, opt};
+use nom::bytes::complete::{tag, tag_no_case, take_until};
+use nom::combinator::{map, opt, verify};
 use nom::multi::many0;
-use nom::sequence::{delimited, preceded, tuple};
+use nom::sequence::{delimited, preceded, terminated, tuple};
 use nom_locate::LocatedSpan;
 use readyset_util::fmt::fmt_with;
 use serde::{Deserialize, Serialize};
@@ -13,7 +13,7 @@ use serde::{Deserialize, Serialize};
 use crate::common::{column_identifier_no_alias, parse_comment};
 use crate::expression::expression;
 use crate::sql_type::type_identifier;
-use crate::whitespace::{whitespace0, whitespace1};
+use crate::whitespace::{is_versioned_invisible, whitespace0, whitespace1};
 use crate::{Dialect, Expr, Literal, NomSqlResult, Relation, SqlIdentifier, SqlType};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
@@ -89,6 +89,16 @@ pub enum ColumnConstraint {
     /// NOTE(grfn): Yes, this really is its own special thing, not just an expression - see
     /// <https://dev.mysql.com/doc/refman/8.0/en/timestamp-initialization.html>
     OnUpdateCurrentTimestamp,
+    /// MySQL invisible columns, which are omitted from `SELECT *` - see
+    /// <https://dev.mysql.com/doc/refman/8.0/en/invisible-columns.html>
+    Invisible,
+    /// MySQL generated columns, whose values are computed from an expression - see
+    /// <https://dev.mysql.com/doc/refman/8.0/en/create-table-generated-columns.html>
+    GeneratedAs {
+        expr: Expr,
+        /// True for `STORED` columns, and false for `VIRTUAL` columns (the default)
+        stored: bool,
+    },
 }
 
 impl ColumnConstraint {
@@ -97,12 +107,26 @@ impl ColumnConstraint {
             Self::Null => write!(f, "NULL"),
             Self::NotNull => write!(f, "NOT NULL"),
             Self::CharacterSet(charset) => write!(f, "CHARACTER SET {}", charset),
+            // Postgres collation names are case sensitive, and can contain characters like `.`
+            Self::Collation(collation) if dialect == Dialect::PostgreSQL => {
+                write!(f, "COLLATE {}", dialect.quote_identifier(collation))
+            }
             Self::Collation(collation) => write!(f, "COLLATE {}", collation),
             Self::DefaultValue(expr) => write!(f, "DEFAULT {}", expr.display(dialect)),
             Self::AutoIncrement => write!(f, "AUTO_INCREMENT"),
             Self::PrimaryKey => write!(f, "PRIMARY KEY"),
             Self::Unique => write!(f, "UNIQUE"),
             Self::OnUpdateCurrentTimestamp => write!(f, "ON UPDATE CURRENT_TIMESTAMP"),
+            // Like MySQL, wrap the attribute in a versioned comment so that it's ignored by
+            // versions which don't support invisible columns
+            Self::Invisible if dialect == Dialect::MySQL => write!(f, "/*!80023 INVISIBLE */"),
+            Self::Invisible => write!(f, "INVISIBLE"),
+            Self::GeneratedAs { expr, stored } => write!(
+                f,
+                "GENERATED ALWAYS AS ({}) {}",
+                expr.display(dialect),
+                if *stored { "STORED" } else { "VIRTUAL" }
+            ),
         })
     }
 }
@@ -145,6 +169,37 @@ impl ColumnSpecification {
         })
     }
 
+    /// Returns true if this column is invisible, meaning it's omitted from `SELECT *`
+    pub fn is_invisible(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|c| matches!(c, ColumnConstraint::Invisible))
+    }
+
+    /// Returns true if this is a generated column, whose values are computed by the upstream
+    /// database rather than written directly
+    pub fn is_generated(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|c| matches!(c, ColumnConstraint::GeneratedAs { .. }))
+    }
+
+    /// Returns the name of this column's character set, if it was specified with `CHARACTER SET`
+    pub fn character_set(&self) -> Option<&str> {
+        self.constraints.iter().find_map(|c| match c {
+            ColumnConstraint::CharacterSet(charset) => Some(charset.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the name of this column's collation, if it was specified with `COLLATE`
+    pub fn collation(&self) -> Option<&str> {
+        self.constraints.iter().find_map(|c| match c {
+            ColumnConstraint::Collation(collation) => Some(collation.as_str()),
+            _ => None,
+        })
+    }
+
     pub fn display(&self, dialect: Dialect) -> impl fmt::Display + Copy + '_ {
         fmt_with(move |f| {
             write!(
@@ -181,6 +236,43 @@ fn default(
     }
 }
 
+fn generated_as(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], ColumnConstraint> {
+    move |i| {
+        let (i, _) = whitespace0(i)?;
+        let (i, _) = opt(tuple((
+            tag_no_case("generated"),
+            whitespace1,
+            tag_no_case("always"),
+            whitespace1,
+        )))(i)?;
+        let (i, _) = tag_no_case("as")(i)?;
+        let (i, _) = whitespace0(i)?;
+        let (i, expr) = delimited(
+            terminated(tag("("), whitespace0),
+            expression(dialect),
+            preceded(whitespace0, tag(")")),
+        )(i)?;
+        let (i, stored) = opt(preceded(
+            whitespace0,
+            alt((
+                map(tag_no_case("stored"), |_| true),
+                map(tag_no_case("virtual"), |_| false),
+            )),
+        ))(i)?;
+        let (i, _) = whitespace0(i)?;
+
+        Ok((
+            i,
+            ColumnConstraint::GeneratedAs {
+                expr,
+                stored: stored.unwrap_or(false),
+            },
+        ))
+    }
+}
+
 pub fn on_update_current_timestamp(i: LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], ColumnConstraint> {
     let (i, _) = tag_no_case("on")(i)?;
     let (i, _) = whitespace1(i)?;
@@ -224,6 +316,20 @@ pub fn column_constraint(
             ),
             |_| ColumnConstraint::Unique,
         );
+        let invisible = map(
+            delimited(
+                whitespace0,
+                alt((
+                    tag_no_case("invisible"),
+                    verify(
+                        delimited(tag("/*"), take_until("*/"), tag("*/")),
+                        |comment: &LocatedSpan<&[u8]>| is_versioned_invisible(comment.fragment()),
+                    ),
+                )),
+                whitespace0,
+            ),
+            |_| ColumnConstraint::Invisible,
+        );
         let character_set = map(
             preceded(
                 delimited(whitespace0, tag_no_case("character set"), whitespace1),
@@ -255,6 +361,8 @@ pub fn column_constraint(
             character_set,
             collate,
             on_update_current_timestamp,
+            invisible,
+            generated_as(dialect),
         ))(i)
     }
 }
@@ -336,6 +444,71 @@ mod tests {
             assert_eq!(res, String::from_utf8(input.to_vec()).unwrap());
         }
 
+        #[test]
+        fn invisible() {
+            let input = b"`my_row_id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT INVISIBLE";
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(input))
+                .unwrap()
+                .1;
+            assert!(cspec.is_invisible());
+            let res = cspec.display(Dialect::MySQL).to_string();
+            assert_eq!(
+                res,
+                "`my_row_id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT /*!80023 INVISIBLE */"
+            );
+        }
+
+        #[test]
+        fn versioned_invisible_comment() {
+            let input =
+                b"`my_row_id` BIGINT UNSIGNED NOT NULL AUTO_INCREMENT /*!80023 INVISIBLE */";
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(input))
+                .unwrap()
+                .1;
+            assert!(cspec.is_invisible());
+            let res = cspec.display(Dialect::MySQL).to_string();
+            assert_eq!(res, String::from_utf8(input.to_vec()).unwrap());
+
+            // Other versioned comments are still skipped
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(
+                b"`a` INT /*!50100 some comment */ NOT NULL".as_slice(),
+            ))
+            .unwrap()
+            .1;
+            assert!(!cspec.is_invisible());
+            assert_eq!(cspec.constraints, vec![ColumnConstraint::NotNull]);
+        }
+
+        #[test]
+        fn generated_columns() {
+            let input =
+                b"`full_name` varchar(255) GENERATED ALWAYS AS (concat(`first`, `last`)) STORED";
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(input))
+                .unwrap()
+                .1;
+            assert!(cspec.is_generated());
+            assert!(matches!(
+                cspec.constraints[..],
+                [ColumnConstraint::GeneratedAs { stored: true, .. }]
+            ));
+            let res = cspec.display(Dialect::MySQL).to_string();
+            assert_eq!(res, String::from_utf8(input.to_vec()).unwrap());
+
+            // `GENERATED ALWAYS` and `VIRTUAL` are both optional
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(
+                b"`c` int AS (`a` + 1) NOT NULL",
+            ))
+            .unwrap()
+            .1;
+            assert!(matches!(
+                cspec.constraints[..],
+                [
+                    ColumnConstraint::GeneratedAs { stored: false, .. },
+                    ColumnConstraint::NotNull
+                ]
+            ));
+        }
+
         #[test]
         fn default_booleans() {
             let input = b"`c` bool DEFAULT FALSE";
@@ -358,6 +531,18 @@ mod tests {
                 ColumnConstraint::DefaultValue(Expr::Literal(Literal::Boolean(true)))
             ));
         }
+
+        #[test]
+        fn character_set_and_collation() {
+            let input = b"`c` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL";
+            let cspec = column_specification(Dialect::MySQL)(LocatedSpan::new(input))
+                .unwrap()
+                .1;
+            assert_eq!(cspec.character_set(), Some("utf8mb4"));
+            assert_eq!(cspec.collation(), Some("utf8mb4_bin"));
+            let res = cspec.display(Dialect::MySQL).to_string();
+            assert_eq!(res, String::from_utf8(input.to_vec()).unwrap());
+        }
     }
 
     mod postgres {
@@ -416,5 +601,17 @@ mod tests {
                 }
             );
         }
+
+        #[test]
+        fn collation_round_trip() {
+            let input = b"\"c\" TEXT COLLATE \"en_US.utf8\"";
+            let cspec = column_specification(Dialect::PostgreSQL)(LocatedSpan::new(input))
+                .unwrap()
+                .1;
+            assert_eq!(cspec.character_set(), None);
+            assert_eq!(cspec.collation(), Some("en_US.utf8"));
+            let res = cspec.display(Dialect::PostgreSQL).to_string();
+            assert_eq!(res, String::from_utf8(input.to_vec()).unwrap());
+        }
     }
 }
@@ -6,9 +6,9 @@ use itertools::Itertools;
 use nom::branch::alt;
 use nom::bytes::complete::{is_not, tag, tag_no_case};
 use nom::character::complete::digit1;
-use nom::combinator::{map, map_res, opt};
-use nom::multi::{separated_list0, separated_list1};
-use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::combinator::{eof, map, map_res, opt};
+use nom::multi::{many0, separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom_locate::LocatedSpan;
 use readyset_util::fmt::fmt_with;
 use serde::{Deserialize, Serialize};
@@ -237,6 +237,23 @@ impl CreateCacheStatement {
     }
 }
 
+/// Parse a sequence of [`CreateCacheStatement`]s, such as the output of `SHOW READYSET CREATE
+/// CACHES`, making up the entirety of the input
+pub fn create_cached_queries(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], Vec<CreateCacheStatement>> {
+    move |i| {
+        terminated(
+            many0(delimited(
+                whitespace0,
+                create_cached_query(dialect),
+                opt(statement_terminator),
+            )),
+            pair(whitespace0, eof),
+        )(i)
+    }
+}
+
 // MySQL grammar element for index column definition (§13.1.18, index_col_name)
 #[allow(clippy::type_complexity)]
 pub fn index_col_name(
@@ -1585,6 +1602,36 @@ mod tests {
             );
         }
 
+        #[test]
+        fn create_cached_queries_round_trip() {
+            let stmts = test_parse!(
+                create_cached_queries(Dialect::MySQL),
+                b"CREATE CACHE foo FROM SELECT id FROM users WHERE name = ?;
+                  CREATE CACHE ALWAYS `bar` FROM SELECT * FROM posts;\n"
+            );
+            assert_eq!(stmts.len(), 2);
+            assert_eq!(stmts[0].name, Some("foo".into()));
+            assert_eq!(stmts[1].name, Some("bar".into()));
+            assert!(stmts[1].always);
+
+            let displayed = stmts
+                .iter()
+                .map(|stmt| format!("{};", stmt.display(Dialect::MySQL)))
+                .join("\n");
+            assert_eq!(
+                test_parse!(create_cached_queries(Dialect::MySQL), displayed.as_bytes()),
+                stmts
+            );
+        }
+
+        #[test]
+        fn create_cached_queries_rejects_other_statements() {
+            create_cached_queries(Dialect::MySQL)(LocatedSpan::new(
+                b"CREATE CACHE foo FROM SELECT id FROM users; DROP CACHE foo;",
+            ))
+            .unwrap_err();
+        }
+
         #[test]
         fn lobsters_indexes() {
             let qstring = "CREATE TABLE `comments` (
@@ -15,6 +15,7 @@ use nom_locate::LocatedSpan;
 pub use self::alter::{
     AlterColumnOperation, AlterTableDefinition, AlterTableStatement, ReplicaIdentity,
 };
+pub use self::alter_readyset::AlterReadysetStatement;
 pub use self::column::{Column, ColumnConstraint, ColumnSpecification};
 pub use self::common::{FieldDefinitionExpr, FieldReference, IndexType, TableKey};
 pub use self::compound_select::{CompoundSelectOperator, CompoundSelectStatement};
@@ -40,6 +41,7 @@ pub use self::literal::{
 };
 pub use self::order::{OrderClause, OrderType};
 pub use self::parser::*;
+pub use self::rename::{RenameTableOperation, RenameTableStatement};
 pub use self::select::{CommonTableExpr, GroupByClause, JoinClause, LimitClause, SelectStatement};
 pub use self::set::{
     PostgresParameterScope, PostgresParameterValue, PostgresParameterValueInner, SetNames,
@@ -48,7 +50,7 @@ pub use self::set::{
 };
 pub use self::show::ShowStatement;
 pub use self::sql_identifier::SqlIdentifier;
-pub use self::sql_type::{EnumVariants, SqlType, SqlTypeArbitraryOptions};
+pub use self::sql_type::{EnumVariants, GeometryType, SqlType, SqlTypeArbitraryOptions};
 pub use self::table::{replicator_table_list, Relation, TableExpr, TableExprInner};
 pub use self::update::UpdateStatement;
 pub use self::use_statement::UseStatement;
@@ -60,6 +62,7 @@ mod dialect;
 mod macros;
 
 mod alter;
+mod alter_readyset;
 pub mod analysis;
 mod column;
 mod common;
@@ -310,6 +310,7 @@ impl Literal {
             SqlType::Serial => any::<i32>().prop_map(Self::from).boxed(),
             SqlType::BigSerial => any::<i64>().prop_map(Self::from).boxed(),
             SqlType::Array(_) => unimplemented!("Arrays aren't implemented yet"),
+            SqlType::Geometry(_) => unimplemented!("Geometry types aren't implemented yet"),
             SqlType::Other(ty) => {
                 unimplemented!("Other({}) isn't implemented yet", ty.display_unquoted())
             }
@@ -8,10 +8,11 @@ use readyset_util::redacted::Sensitive;
 use serde::{Deserialize, Serialize};
 
 use crate::alter::{alter_table_statement, AlterTableStatement};
+use crate::alter_readyset::{alter_readyset_statement, AlterReadysetStatement};
 use crate::compound_select::{compound_selection, CompoundSelectStatement};
 use crate::create::{
-    create_cached_query, create_table, key_specification, view_creation, CreateCacheStatement,
-    CreateTableStatement, CreateViewStatement,
+    create_cached_queries, create_cached_query, create_table, key_specification, view_creation,
+    CreateCacheStatement, CreateTableStatement, CreateViewStatement,
 };
 use crate::delete::{deletion, DeleteStatement};
 use crate::drop::{
@@ -44,6 +45,7 @@ pub enum SqlQuery {
     DropCache(DropCacheStatement),
     DropAllCaches(DropAllCachesStatement),
     AlterTable(AlterTableStatement),
+    AlterReadySet(AlterReadysetStatement),
     Insert(InsertStatement),
     CompoundSelect(CompoundSelectStatement),
     Select(SelectStatement),
@@ -77,6 +79,7 @@ impl SqlQuery {
             Self::Update(update) => write!(f, "{}", update.display(dialect)),
             Self::Set(set) => write!(f, "{}", set.display(dialect)),
             Self::AlterTable(alter) => write!(f, "{}", alter.display(dialect)),
+            Self::AlterReadySet(alter) => write!(f, "{}", alter.display(dialect)),
             Self::CompoundSelect(compound) => write!(f, "{}", compound.display(dialect)),
             Self::StartTransaction(tx) => write!(f, "{}", tx),
             Self::Commit(commit) => write!(f, "{}", commit),
@@ -114,6 +117,7 @@ impl SqlQuery {
             Self::Update(_) => "UPDATE",
             Self::Set(_) => "SET",
             Self::AlterTable(_) => "ALTER TABLE",
+            Self::AlterReadySet(_) => "ALTER READYSET",
             Self::CompoundSelect(_) => "SELECT",
             Self::StartTransaction(_) => "START TRANSACTION",
             Self::Commit(_) => "COMMIT",
@@ -146,9 +150,15 @@ pub fn sql_query(dialect: Dialect) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResul
             map(updating(dialect), SqlQuery::Update),
             map(set(dialect), SqlQuery::Set),
             map(view_creation(dialect), SqlQuery::CreateView),
-            map(create_cached_query(dialect), SqlQuery::CreateCache),
-            map(drop_cached_query(dialect), SqlQuery::DropCache),
-            map(drop_all_caches, SqlQuery::DropAllCaches),
+            // ReadySet-specific extensions to SQL. These are grouped into their own `alt` since
+            // `alt` only supports up to 21 alternatives
+            alt((
+                map(create_cached_query(dialect), SqlQuery::CreateCache),
+                map(drop_cached_query(dialect), SqlQuery::DropCache),
+                map(drop_all_caches, SqlQuery::DropAllCaches),
+                map(alter_readyset_statement(dialect), SqlQuery::AlterReadySet),
+                map(explain_statement, SqlQuery::Explain),
+            )),
             map(alter_table_statement(dialect), SqlQuery::AlterTable),
             map(start_transaction(dialect), SqlQuery::StartTransaction),
             map(commit(dialect), SqlQuery::Commit),
@@ -156,7 +166,6 @@ pub fn sql_query(dialect: Dialect) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResul
             map(rename_table(dialect), SqlQuery::RenameTable),
             map(use_statement(dialect), SqlQuery::Use),
             map(show(dialect), SqlQuery::Show),
-            map(explain_statement, SqlQuery::Explain),
         ))(i)
     }
 }
@@ -196,6 +205,11 @@ export_parser!(
     parse_create_cache_bytes,
     parse_create_cache
 );
+export_parser!(
+    create_cached_queries -> Vec<CreateCacheStatement>,
+    parse_create_caches_bytes,
+    parse_create_caches
+);
 export_parser!(
     alter_table_statement -> AlterTableStatement,
     parse_alter_table_bytes,
@@ -1778,6 +1778,19 @@ mod tests {
         use crate::table::Relation;
         use crate::{BinaryOperator, Expr, FunctionExpr, InValue};
 
+        #[test]
+        fn sql_calc_found_rows_is_not_an_alias() {
+            // `SQL_CALC_FOUND_ROWS` changes what `FOUND_ROWS()` returns afterwards, so queries
+            // using it must not be mistaken for a column with an alias
+            for qstr in [
+                "SELECT SQL_CALC_FOUND_ROWS id, name FROM users LIMIT 10",
+                "SELECT SQL_CALC_FOUND_ROWS * FROM users LIMIT 10",
+            ] {
+                let res = crate::parse_query(Dialect::MySQL, qstr);
+                assert!(res.is_err(), "!{:?}.is_err()", res);
+            }
+        }
+
         #[test]
         fn alias_generic_function() {
             let qstr = "SELECT id, coalesce(a, \"b\",c) AS created_day FROM users;";
@@ -23,6 +23,9 @@ pub enum ShowStatement {
     ReadySetStatus,
     ReadySetVersion,
     ReadySetTables,
+    ReadySetCreateCaches,
+    ReadySetCacheStats,
+    ReadySetMemory,
 }
 
 impl ShowStatement {
@@ -49,6 +52,9 @@ impl ShowStatement {
                 Self::ReadySetStatus => write!(f, "READYSET STATUS"),
                 Self::ReadySetVersion => write!(f, "READYSET VERSION"),
                 Self::ReadySetTables => write!(f, "READYSET TABLES"),
+                Self::ReadySetCreateCaches => write!(f, "READYSET CREATE CACHES"),
+                Self::ReadySetCacheStats => write!(f, "READYSET CACHE STATS"),
+                Self::ReadySetMemory => write!(f, "READYSET MEMORY"),
             }
         })
     }
@@ -109,6 +115,30 @@ pub fn show(dialect: Dialect) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u
                 ShowStatement::ReadySetTables,
                 tuple((tag_no_case("readyset"), whitespace1, tag_no_case("tables"))),
             ),
+            value(
+                ShowStatement::ReadySetCreateCaches,
+                tuple((
+                    tag_no_case("readyset"),
+                    whitespace1,
+                    tag_no_case("create"),
+                    whitespace1,
+                    tag_no_case("caches"),
+                )),
+            ),
+            value(
+                ShowStatement::ReadySetCacheStats,
+                tuple((
+                    tag_no_case("readyset"),
+                    whitespace1,
+                    tag_no_case("cache"),
+                    whitespace1,
+                    tag_no_case("stats"),
+                )),
+            ),
+            value(
+                ShowStatement::ReadySetMemory,
+                tuple((tag_no_case("readyset"), whitespace1, tag_no_case("memory"))),
+            ),
             map(show_tables(dialect), ShowStatement::Tables),
             value(ShowStatement::Events, tag_no_case("events")),
         ))(i)?;
@@ -373,4 +403,37 @@ mod tests {
         let res = test_parse!(show(Dialect::MySQL), b"SHOW READYSET TABLES");
         assert_eq!(res, ShowStatement::ReadySetTables);
     }
+
+    #[test]
+    fn show_readyset_create_caches() {
+        for &dialect in Dialect::ALL {
+            let res = test_parse!(show(dialect), b"SHOW READYSET CREATE CACHES");
+            assert_eq!(res, ShowStatement::ReadySetCreateCaches);
+            assert_eq!(
+                res.display(dialect).to_string(),
+                "SHOW READYSET CREATE CACHES"
+            );
+        }
+    }
+
+    #[test]
+    fn show_readyset_cache_stats() {
+        for &dialect in Dialect::ALL {
+            let res = test_parse!(show(dialect), b"SHOW READYSET CACHE STATS");
+            assert_eq!(res, ShowStatement::ReadySetCacheStats);
+            assert_eq!(
+                res.display(dialect).to_string(),
+                "SHOW READYSET CACHE STATS"
+            );
+        }
+    }
+
+    #[test]
+    fn show_readyset_memory() {
+        for &dialect in Dialect::ALL {
+            let res = test_parse!(show(dialect), b"SHOW READYSET MEMORY");
+            assert_eq!(res, ShowStatement::ReadySetMemory);
+            assert_eq!(res.display(dialect).to_string(), "SHOW READYSET MEMORY");
+        }
+    }
 }
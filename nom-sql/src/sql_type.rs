@@ -73,11 +73,55 @@ pub enum SqlType {
     Serial,
     BigSerial,
     Array(Box<SqlType>),
+    /// MySQL spatial types - see
+    /// <https://dev.mysql.com/doc/refman/8.0/en/spatial-type-overview.html>
+    Geometry(GeometryType),
 
     /// Any other named type
     Other(Relation),
 }
 
+/// The kinds of geometry values a MySQL spatial column can be declared to hold
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Eq,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+    test_strategy::Arbitrary,
+)]
+pub enum GeometryType {
+    /// `GEOMETRY`, which can hold a value of any of the other types
+    Geometry,
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    /// `GEOMETRYCOLLECTION`, which can also be spelled `GEOMCOLLECTION`
+    GeometryCollection,
+}
+
+impl fmt::Display for GeometryType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryType::Geometry => write!(f, "GEOMETRY"),
+            GeometryType::Point => write!(f, "POINT"),
+            GeometryType::LineString => write!(f, "LINESTRING"),
+            GeometryType::Polygon => write!(f, "POLYGON"),
+            GeometryType::MultiPoint => write!(f, "MULTIPOINT"),
+            GeometryType::MultiLineString => write!(f, "MULTILINESTRING"),
+            GeometryType::MultiPolygon => write!(f, "MULTIPOLYGON"),
+            GeometryType::GeometryCollection => write!(f, "GEOMETRYCOLLECTION"),
+        }
+    }
+}
+
 /// Options for generating arbitrary [`SqlType`]s
 #[derive(Debug, Clone, Copy)]
 pub struct SqlTypeArbitraryOptions {
@@ -145,6 +189,7 @@ impl Arbitrary for SqlType {
             any::<Option<u16>>().prop_map(VarBit).boxed(),
             Just(Serial).boxed(),
             Just(BigSerial).boxed(),
+            any::<GeometryType>().prop_map(Geometry).boxed(),
         ];
 
         if args.generate_arrays {
@@ -320,6 +365,7 @@ impl SqlType {
                 SqlType::Serial => write!(f, "SERIAL"),
                 SqlType::BigSerial => write!(f, "BIGSERIAL"),
                 SqlType::Array(ref t) => write!(f, "{}[]", t.display(dialect)),
+                SqlType::Geometry(ty) => write!(f, "{}", ty),
                 SqlType::Other(ref t) => write!(f, "{}", t.display(dialect)),
             }
         })
@@ -731,11 +777,44 @@ fn type_identifier_part3(
         alt((
             map(tag_no_case("citext"), |_| SqlType::Citext),
             map(tag("\"char\""), |_| SqlType::QuotedChar),
+            map(geometry_type(dialect), SqlType::Geometry),
             map(other_type(dialect), SqlType::Other),
         ))(i)
     }
 }
 
+/// Parse the name of one of MySQL's spatial types. PostgreSQL's geometric types (such as `point`
+/// and `polygon`) are a different thing altogether, so these are only parsed for MySQL.
+fn geometry_type(
+    dialect: Dialect,
+) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], GeometryType> {
+    move |i| match dialect {
+        // Longer names have to come before the names they start with
+        Dialect::MySQL => alt((
+            map(
+                alt((
+                    tag_no_case("geometrycollection"),
+                    tag_no_case("geomcollection"),
+                )),
+                |_| GeometryType::GeometryCollection,
+            ),
+            map(tag_no_case("geometry"), |_| GeometryType::Geometry),
+            map(tag_no_case("point"), |_| GeometryType::Point),
+            map(tag_no_case("linestring"), |_| GeometryType::LineString),
+            map(tag_no_case("polygon"), |_| GeometryType::Polygon),
+            map(tag_no_case("multipoint"), |_| GeometryType::MultiPoint),
+            map(tag_no_case("multilinestring"), |_| {
+                GeometryType::MultiLineString
+            }),
+            map(tag_no_case("multipolygon"), |_| GeometryType::MultiPolygon),
+        ))(i),
+        Dialect::PostgreSQL => Err(nom::Err::Error(ParseError::from_error_kind(
+            i,
+            ErrorKind::IsNot,
+        ))),
+    }
+}
+
 fn other_type(dialect: Dialect) -> impl Fn(LocatedSpan<&[u8]>) -> NomSqlResult<&[u8], Relation> {
     move |i| match dialect {
         Dialect::PostgreSQL => relation(dialect)(i),
@@ -892,6 +971,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn mysql_geometry_types() {
+        for (input, expected) in [
+            ("geometry", GeometryType::Geometry),
+            ("POINT", GeometryType::Point),
+            ("linestring", GeometryType::LineString),
+            ("polygon", GeometryType::Polygon),
+            ("multipoint", GeometryType::MultiPoint),
+            ("multilinestring", GeometryType::MultiLineString),
+            ("multipolygon", GeometryType::MultiPolygon),
+            ("geometrycollection", GeometryType::GeometryCollection),
+            ("geomcollection", GeometryType::GeometryCollection),
+        ] {
+            let res = test_parse!(type_identifier(Dialect::MySQL), input.as_bytes());
+            assert_eq!(res, SqlType::Geometry(expected));
+        }
+    }
+
+    #[test]
+    fn postgres_point_is_not_a_mysql_geometry() {
+        let res = test_parse!(type_identifier(Dialect::PostgreSQL), b"point");
+        assert_eq!(res, SqlType::Other("point".into()));
+    }
+
     #[test]
     fn boolean_bool() {
         let res = test_parse!(type_identifier(Dialect::PostgreSQL), b"boolean");
@@ -10,7 +10,7 @@ use std::ops::{Range, RangeFrom, RangeTo};
 use nom::branch::alt;
 use nom::bytes::complete::{tag, tag_no_case, take_until};
 use nom::character::complete::{line_ending, not_line_ending};
-use nom::combinator::{map, value};
+use nom::combinator::{map, value, verify};
 use nom::multi::{many0, many1};
 use nom::sequence::delimited;
 use nom::{
@@ -24,6 +24,9 @@ use crate::NomSqlResult;
 /// Recognizes a multiline comment of the form `/* ... */`, skipping the `/*` and `*/`,
 /// to return the comment content.
 ///
+/// MySQL's versioned comment wrapping the `INVISIBLE` column attribute (`/*!80023 INVISIBLE */`)
+/// is not recognized as a comment, since it's parsed as part of the column definition instead.
+///
 /// # Example
 ///
 /// ```
@@ -46,6 +49,7 @@ use crate::NomSqlResult;
 ///     parser("Z21c"),
 ///     Err(Err::Error(nom::error::Error::new("Z21c", ErrorKind::Tag)))
 /// );
+/// assert!(parser("/*!80023 INVISIBLE */").is_err());
 /// ```
 pub fn multiline_comment<I>(input: LocatedSpan<I>) -> NomSqlResult<I, I>
 where
@@ -58,12 +62,29 @@ where
         + Slice<RangeTo<usize>>
         + Copy,
 {
-    map(
-        delimited(tag_no_case("/*"), take_until("*/"), tag_no_case("*/")),
-        |input: LocatedSpan<I>| *input,
+    verify(
+        map(
+            delimited(tag_no_case("/*"), take_until("*/"), tag_no_case("*/")),
+            |input: LocatedSpan<I>| *input,
+        ),
+        |comment: &I| !is_versioned_invisible(comment.as_bytes()),
     )(input)
 }
 
+/// Returns true if `comment` is the content of a MySQL versioned comment wrapping the `INVISIBLE`
+/// column attribute, like the `!80023 INVISIBLE ` in `/*!80023 INVISIBLE */`
+pub(crate) fn is_versioned_invisible(comment: &[u8]) -> bool {
+    let Some(rest) = comment.strip_prefix(b"!") else {
+        return false;
+    };
+    let version_len = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+    version_len > 0
+        && rest
+            .get(version_len..)
+            .and_then(|attr| std::str::from_utf8(attr).ok())
+            .map_or(false, |attr| attr.trim().eq_ignore_ascii_case("invisible"))
+}
+
 /// Recognizes a EOL style comment of the form `# ...` (in case the start tag is '#'), skipping the
 /// `#` to return the comment content.
 ///
@@ -9,6 +9,7 @@ use crate::codec::{Codec, DecodeError, EncodeError};
 use crate::error::Error;
 use crate::message::FrontendMessage;
 use crate::response::Response;
+use crate::trace::TraceLevel;
 use crate::value::Value;
 
 const CHANNEL_INITIAL_CAPACITY: usize = 4096;
@@ -61,6 +62,12 @@ where
             .clear_statement_param_types(statement_name);
     }
 
+    /// Set the level messages sent and received on the channel are traced at, or disable tracing
+    /// if `None`.
+    pub(crate) fn set_trace_level(&mut self, level: Option<TraceLevel>) {
+        self.0.codec_mut().set_trace_level(level);
+    }
+
     /// Read a `FrontendMessage` from the channel.
     pub async fn next(&mut self) -> Option<Result<FrontendMessage, DecodeError>> {
         self.0.next().await
@@ -23,6 +23,7 @@ use crate::message::FrontendMessage::{self, *};
 use crate::message::SaslInitialResponse;
 use crate::message::StatementName::*;
 use crate::message::TransferFormat::{self, *};
+use crate::trace;
 use crate::value::Value;
 
 const ID_AUTHENTICATE: u8 = b'p';
@@ -48,10 +49,11 @@ const STARTUP_MESSAGE_DATABASE_PARAMETER: &str = "database";
 const STARTUP_MESSAGE_TERMINATOR: &str = "";
 const STARTUP_MESSAGE_USER_PARAMETER: &str = "user";
 
-const BOOL_TRUE_TEXT_REP: &str = "t";
 const HEADER_LENGTH: usize = 5;
 const LENGTH_NULL_SENTINEL: i32 = -1;
 const NUL_BYTE: u8 = b'\0';
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const TIME_FORMAT: &str = "%H:%M:%S%.f";
 const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
 const TIMESTAMP_TZ_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f %:z";
 
@@ -60,6 +62,22 @@ impl<R: IntoIterator<Item: TryInto<Value, Error = BackendError>>> Decoder for Co
     type Error = Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<FrontendMessage>, Error> {
+        let start_len = src.len();
+        let msg = self.decode_message(src)?;
+        if let Some(trace) = msg
+            .as_ref()
+            .and_then(|msg| trace::frontend_message(self.trace_level, msg))
+        {
+            // Messages other than the startup messages start with a message type byte
+            let has_id = !self.is_starting_up;
+            trace::log(true, start_len - src.len() - usize::from(has_id), &trace);
+        }
+        Ok(msg)
+    }
+}
+
+impl<R: IntoIterator<Item: TryInto<Value, Error = BackendError>>> Codec<R> {
+    fn decode_message(&mut self, src: &mut BytesMut) -> Result<Option<FrontendMessage>, Error> {
         let msg = {
             // Try to read a complete message from `src`. Otherwise return `Ok(None)` to indicate
             // that `src` does not yet contain a complete message.
@@ -360,9 +378,14 @@ fn get_binary_value(src: &mut Bytes, t: &Type) -> Result<Value, Error> {
         }
         _ => match *t {
             // Postgres does not allow interior 0 bytes, even though it is valid UTF-8
-            Type::BPCHAR | Type::VARCHAR | Type::TEXT | Type::NAME if buf.contains(&0) => {
+            Type::BPCHAR | Type::VARCHAR | Type::TEXT | Type::NAME | Type::UNKNOWN
+                if buf.contains(&0) =>
+            {
                 Err(Error::InvalidUtf8)
             }
+            // The binary representation of an unknown-typed value is the same as its text
+            // representation, so it gets the same treatment as in `get_text_value`
+            Type::UNKNOWN => Ok(Value::Text(str::from_utf8(buf)?.into())),
             Type::BOOL => Ok(Value::Bool(bool::from_sql(t, buf)?)),
             Type::VARCHAR => Ok(Value::VarChar(<&str>::from_sql(t, buf)?.into())),
             Type::BPCHAR => Ok(Value::BpChar(<&str>::from_sql(t, buf)?.into())),
@@ -416,6 +439,34 @@ fn get_bitvec_from_str(bit_str: &str) -> Result<BitVec, Error> {
     Ok(bits)
 }
 
+/// Parse the text representation of a boolean the way postgres does, accepting any unambiguous
+/// prefix of `true`, `false`, `yes` or `no`, as well as `on`, `off`, `1` and `0`, in any case
+fn get_bool_from_str(bool_str: &str) -> Result<bool, Error> {
+    let lower = bool_str.to_ascii_lowercase();
+    let is_prefix_of = |word: &str| !lower.is_empty() && word.starts_with(lower.as_str());
+    if is_prefix_of("true") || is_prefix_of("yes") || lower == "on" || lower == "1" {
+        Ok(true)
+    } else if is_prefix_of("false") || is_prefix_of("no") || lower == "off" || lower == "0" {
+        Ok(false)
+    } else {
+        Err(Error::InvalidTextBoolValue(bool_str.to_owned()))
+    }
+}
+
+/// Parse the text representation of a timestamp without a time zone. As well as the format we
+/// send timestamps in, this accepts a `T` separating the date and time (as in ISO 8601), and a
+/// date on its own, which means midnight on that date.
+fn get_timestamp_from_str(timestamp_str: &str) -> Result<NaiveDateTime, Error> {
+    NaiveDateTime::parse_from_str(timestamp_str, TIMESTAMP_FORMAT)
+        .or_else(|_| NaiveDateTime::parse_from_str(timestamp_str, "%Y-%m-%dT%H:%M:%S%.f"))
+        .or_else(|e| {
+            NaiveDate::parse_from_str(timestamp_str, DATE_FORMAT)
+                .map(|date| date.and_hms(0, 0, 0))
+                .map_err(|_| e)
+        })
+        .map_err(Error::from)
+}
+
 fn get_text_value(src: &mut Bytes, t: &Type) -> Result<Value, Error> {
     let len = get_i32(src)?;
     if len == LENGTH_NULL_SENTINEL {
@@ -424,33 +475,44 @@ fn get_text_value(src: &mut Bytes, t: &Type) -> Result<Value, Error> {
 
     let text = BytesStr::try_from(src.split_to(usize::try_from(len)?))?;
     let text_str: &str = text.borrow();
+    // Like postgres, ignore whitespace surrounding the text representation of non-string values
+    let trimmed = text_str.trim();
     match *t {
-        Type::BOOL => Ok(Value::Bool(text_str == BOOL_TRUE_TEXT_REP)),
+        Type::BOOL => get_bool_from_str(trimmed).map(Value::Bool),
         Type::VARCHAR => Ok(Value::VarChar(text_str.into())),
         Type::NAME => Ok(Value::Name(text_str.into())),
         Type::BPCHAR => Ok(Value::BpChar(text_str.into())),
-        Type::INT4 => Ok(Value::Int(text_str.parse::<i32>()?)),
-        Type::INT8 => Ok(Value::BigInt(text_str.parse::<i64>()?)),
-        Type::INT2 => Ok(Value::SmallInt(text_str.parse::<i16>()?)),
+        Type::INT4 => Ok(Value::Int(trimmed.parse::<i32>()?)),
+        Type::INT8 => Ok(Value::BigInt(trimmed.parse::<i64>()?)),
+        Type::INT2 => Ok(Value::SmallInt(trimmed.parse::<i16>()?)),
         Type::CHAR => Ok(Value::Char(text_str.parse::<i8>()?)),
-        Type::OID => Ok(Value::Oid(text_str.parse::<u32>()?)),
+        Type::OID => Ok(Value::Oid(trimmed.parse::<u32>()?)),
         Type::FLOAT8 => {
             // TODO: Ensure all values are properly parsed, including +/-0 and +/-inf.
-            Ok(Value::Double(text_str.parse::<f64>()?))
+            Ok(Value::Double(trimmed.parse::<f64>()?))
         }
         Type::FLOAT4 => {
             // TODO: Ensure all values are properly parsed, including +/-0 and +/-inf.
-            Ok(Value::Float(text_str.parse::<f32>()?))
+            Ok(Value::Float(trimmed.parse::<f32>()?))
         }
-        Type::NUMERIC => Ok(Value::Numeric(Decimal::from_str(text_str)?)),
+        Type::NUMERIC => Ok(Value::Numeric(Decimal::from_str(trimmed)?)),
         Type::TEXT => Ok(Value::Text(text_str.into())),
+        // Parameters whose type was left unspecified (type oid 0) by both the frontend and the
+        // statement are kept as text, and coerced to the type they're used as once the statement
+        // is executed, which is how postgres resolves the type of unknown literals
+        Type::UNKNOWN => Ok(Value::Text(text_str.into())),
+        Type::DATE => Ok(Value::Date(NaiveDate::parse_from_str(
+            trimmed,
+            DATE_FORMAT,
+        )?)),
+        Type::TIME => Ok(Value::Time(NaiveTime::parse_from_str(
+            trimmed,
+            TIME_FORMAT,
+        )?)),
         Type::TIMESTAMP => {
-            // TODO: Does not correctly handle all valid timestamp representations. For example,
-            // 8601/SQL timestamp format is assumed; infinity/-infinity are not supported.
-            Ok(Value::Timestamp(NaiveDateTime::parse_from_str(
-                text_str,
-                TIMESTAMP_FORMAT,
-            )?))
+            // TODO: Does not correctly handle all valid timestamp representations; infinity and
+            // -infinity are not supported.
+            get_timestamp_from_str(trimmed).map(Value::Timestamp)
         }
         Type::TIMESTAMPTZ => Ok(Value::TimestampTz(DateTime::<FixedOffset>::parse_from_str(
             text_str,
@@ -1291,6 +1353,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_decode_text_bool_spellings() {
+        for (text, expected) in [
+            ("true", true),
+            ("TRUE", true),
+            ("  yes ", true),
+            ("y", true),
+            ("on", true),
+            ("1", true),
+            ("f", false),
+            ("False", false),
+            ("no", false),
+            ("off", false),
+            ("0", false),
+        ] {
+            let mut buf = BytesMut::new();
+            buf.put_i32(text.len() as _);
+            buf.extend_from_slice(text.as_bytes());
+            assert_eq!(
+                get_text_value(&mut buf.freeze(), &Type::BOOL).unwrap(),
+                DataValue::Bool(expected),
+                "{text}"
+            );
+        }
+
+        for text in ["", "o", "of", "maybe", "2"] {
+            let mut buf = BytesMut::new();
+            buf.put_i32(text.len() as _);
+            buf.extend_from_slice(text.as_bytes());
+            get_text_value(&mut buf.freeze(), &Type::BOOL).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_decode_text_unknown() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(2); // size
+        buf.extend_from_slice(b"42"); // value
+        assert_eq!(
+            get_text_value(&mut buf.freeze(), &Type::UNKNOWN).unwrap(),
+            DataValue::Text("42".into())
+        );
+    }
+
+    #[test]
+    fn test_decode_binary_unknown() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(10); // size
+        buf.extend_from_slice(b"2020-01-02"); // value
+        assert_eq!(
+            get_binary_value(&mut buf.freeze(), &Type::UNKNOWN).unwrap(),
+            DataValue::Text("2020-01-02".into())
+        );
+
+        let mut buf = BytesMut::new();
+        buf.put_i32(3); // size
+        buf.extend_from_slice(b"a\0b"); // value
+        get_binary_value(&mut buf.freeze(), &Type::UNKNOWN).unwrap_err();
+    }
+
+    #[test]
+    fn test_decode_text_int_with_whitespace() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(5); // size
+        buf.extend_from_slice(b" 123 "); // value
+        assert_eq!(
+            get_text_value(&mut buf.freeze(), &Type::INT4).unwrap(),
+            DataValue::Int(123)
+        );
+    }
+
     #[test]
     fn test_decode_text_char() {
         let mut buf = BytesMut::new();
@@ -1414,6 +1547,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_decode_text_timestamp_other_formats() {
+        for (text, expected) in [
+            (
+                "2020-01-02T03:04:05",
+                NaiveDate::from_ymd(2020, 1, 2).and_hms(3, 4, 5),
+            ),
+            (
+                "2020-01-02",
+                NaiveDate::from_ymd(2020, 1, 2).and_hms(0, 0, 0),
+            ),
+        ] {
+            let mut buf = BytesMut::new();
+            buf.put_i32(text.len() as _);
+            buf.extend_from_slice(text.as_bytes());
+            assert_eq!(
+                get_text_value(&mut buf.freeze(), &Type::TIMESTAMP).unwrap(),
+                DataValue::Timestamp(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_text_date() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(10); // size
+        buf.extend_from_slice(b"2020-01-02"); // value
+        assert_eq!(
+            get_text_value(&mut buf.freeze(), &Type::DATE).unwrap(),
+            DataValue::Date(NaiveDate::from_ymd(2020, 1, 2))
+        );
+    }
+
+    #[test]
+    fn test_decode_text_time() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(11); // size
+        buf.extend_from_slice(b"03:04:05.66"); // value
+        assert_eq!(
+            get_text_value(&mut buf.freeze(), &Type::TIME).unwrap(),
+            DataValue::Time(NaiveTime::from_hms_milli(3, 4, 5, 660))
+        );
+    }
+
     #[test]
     fn test_decode_text_bytes() {
         let mut buf = BytesMut::new();
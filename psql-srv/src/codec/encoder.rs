@@ -13,6 +13,7 @@ use crate::message::CommandCompleteTag::*;
 use crate::message::ErrorSeverity;
 use crate::message::TransferFormat::{self, *};
 use crate::scram::{SCRAM_SHA_256_AUTHENTICATION_METHOD, SCRAM_SHA_256_SSL_AUTHENTICATION_METHOD};
+use crate::trace;
 use crate::value::Value;
 
 const ID_AUTHENTICATION_REQUEST: u8 = b'R';
@@ -44,6 +45,14 @@ const ERROR_RESPONSE_C_FIELD: u8 = b'C';
 const ERROR_RESPONSE_M_FIELD: u8 = b'M';
 const ERROR_RESPONSE_S_FIELD: u8 = b'S';
 const ERROR_RESPONSE_V_FIELD: u8 = b'V';
+const ERROR_RESPONSE_DETAIL_FIELD: u8 = b'D';
+const ERROR_RESPONSE_HINT_FIELD: u8 = b'H';
+const ERROR_RESPONSE_POSITION_FIELD: u8 = b'P';
+const ERROR_RESPONSE_SCHEMA_FIELD: u8 = b's';
+const ERROR_RESPONSE_TABLE_FIELD: u8 = b't';
+const ERROR_RESPONSE_COLUMN_FIELD: u8 = b'c';
+const ERROR_RESPONSE_DATATYPE_FIELD: u8 = b'd';
+const ERROR_RESPONSE_CONSTRAINT_FIELD: u8 = b'n';
 const ERROR_RESPONSE_SEVERITY_ERROR: &str = "ERROR";
 const ERROR_RESPONSE_SEVERITY_FATAL: &str = "FATAL";
 const ERROR_RESPONSE_SEVERITY_PANIC: &str = "PANIC";
@@ -69,11 +78,18 @@ where
 
     fn encode(&mut self, message: BackendMessage<R>, dst: &mut BytesMut) -> Result<(), Error> {
         let start_ofs = dst.len();
+        let trace = trace::backend_message(self.trace_level, &message);
+        // Every message but SSLResponse starts with a message type byte
+        let has_id = !matches!(message, SSLResponse { .. });
         encode(message, dst).map_err(|e| {
             // On an encoding error, remove any partially encoded data.
             dst.truncate(start_ofs);
             e
-        })
+        })?;
+        if let Some(trace) = trace {
+            trace::log(false, dst.len() - start_ofs - usize::from(has_id), &trace);
+        }
+        Ok(())
     }
 }
 
@@ -224,6 +240,7 @@ where
             severity,
             sqlstate,
             message,
+            fields,
         } => {
             let severity = match severity {
                 ErrorSeverity::Error => ERROR_RESPONSE_SEVERITY_ERROR,
@@ -240,6 +257,22 @@ where
             put_str(sqlstate.code(), dst);
             put_u8(ERROR_RESPONSE_M_FIELD, dst);
             put_str(&message, dst);
+            let position = fields.position.map(|p| p.to_string());
+            for (field, value) in [
+                (ERROR_RESPONSE_DETAIL_FIELD, &fields.detail),
+                (ERROR_RESPONSE_HINT_FIELD, &fields.hint),
+                (ERROR_RESPONSE_POSITION_FIELD, &position),
+                (ERROR_RESPONSE_SCHEMA_FIELD, &fields.schema),
+                (ERROR_RESPONSE_TABLE_FIELD, &fields.table),
+                (ERROR_RESPONSE_COLUMN_FIELD, &fields.column),
+                (ERROR_RESPONSE_DATATYPE_FIELD, &fields.datatype),
+                (ERROR_RESPONSE_CONSTRAINT_FIELD, &fields.constraint),
+            ] {
+                if let Some(value) = value {
+                    put_u8(field, dst);
+                    put_str(value, dst);
+                }
+            }
             put_u8(ERROR_RESPONSE_TERMINATOR, dst);
         }
 
@@ -600,6 +633,7 @@ mod tests {
     use uuid::Uuid;
 
     use super::*;
+    use crate::error::ErrorFields;
     use crate::message::{FieldDescription, SqlState};
     use crate::value::Value as DataValue;
 
@@ -857,6 +891,7 @@ mod tests {
                     severity: ErrorSeverity::Error,
                     sqlstate: SqlState::FEATURE_NOT_SUPPORTED,
                     message: "unsupported kringle".to_string(),
+                    fields: Box::default(),
                 },
                 &mut buf,
             )
@@ -876,6 +911,50 @@ mod tests {
         assert_eq!(buf, exp);
     }
 
+    #[test]
+    fn test_encode_error_response_with_fields() {
+        let mut codec = Codec::<Vec<Value>>::new();
+        let mut buf = BytesMut::new();
+        codec
+            .encode(
+                ErrorResponse {
+                    severity: ErrorSeverity::Error,
+                    sqlstate: SqlState::UNIQUE_VIOLATION,
+                    message: "duplicate key".to_string(),
+                    fields: Box::new(ErrorFields {
+                        detail: Some("Key exists".to_string()),
+                        position: Some(12),
+                        table: Some("t".to_string()),
+                        constraint: Some("t_pkey".to_string()),
+                        ..Default::default()
+                    }),
+                },
+                &mut buf,
+            )
+            .unwrap();
+        let mut exp = BytesMut::new();
+        exp.put_u8(b'E'); // message id
+        exp.put_i32(4 + 1 + 6 + 1 + 6 + 1 + 6 + 1 + 14 + 1 + 11 + 1 + 3 + 1 + 2 + 1 + 7 + 1); // message length
+        exp.put_u8(b'S'); // field id
+        exp.extend_from_slice(b"ERROR\0");
+        exp.put_u8(b'V'); // field id
+        exp.extend_from_slice(b"ERROR\0");
+        exp.put_u8(b'C'); // field id
+        exp.extend_from_slice(b"23505\0");
+        exp.put_u8(b'M'); // field id
+        exp.extend_from_slice(b"duplicate key\0");
+        exp.put_u8(b'D'); // field id
+        exp.extend_from_slice(b"Key exists\0");
+        exp.put_u8(b'P'); // field id
+        exp.extend_from_slice(b"12\0");
+        exp.put_u8(b't'); // field id
+        exp.extend_from_slice(b"t\0");
+        exp.put_u8(b'n'); // field id
+        exp.extend_from_slice(b"t_pkey\0");
+        exp.put_u8(b'\0'); // terminator
+        assert_eq!(buf, exp);
+    }
+
     #[test]
     fn test_encode_error_response_after_encoding_failure() {
         struct UnserializableValue;
@@ -916,6 +995,7 @@ mod tests {
                     severity: ErrorSeverity::Error,
                     sqlstate: SqlState::FEATURE_NOT_SUPPORTED,
                     message: "unsupported kringle".to_string(),
+                    fields: Box::default(),
                 },
                 &mut buf,
             )
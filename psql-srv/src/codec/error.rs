@@ -31,6 +31,9 @@ pub enum DecodeError {
     #[error("invalid integer: {0}")]
     InvalidInteger(#[from] TryFromIntError),
 
+    #[error("invalid text bool value: {0}")]
+    InvalidTextBoolValue(String),
+
     #[error("invalid text float value: {0}")]
     InvalidTextFloatValue(#[from] ParseFloatError),
 
@@ -10,6 +10,7 @@ pub use error::{DecodeError, EncodeError};
 use postgres_types::Type;
 
 use crate::error::Error;
+use crate::trace::TraceLevel;
 use crate::value::Value;
 
 /// A [`Decoder`] implementation that deserializes `FrontendMessage` and [`Encoder`] implementation
@@ -20,6 +21,8 @@ use crate::value::Value;
 pub struct Codec<R> {
     is_starting_up: bool,
     statement_param_types: HashMap<String, Vec<Type>>,
+    /// The level messages are traced at, if tracing is enabled for this connection
+    trace_level: Option<TraceLevel>,
     _unused: PhantomData<R>,
 }
 
@@ -28,6 +31,7 @@ impl<R: IntoIterator<Item: TryInto<Value, Error = Error>>> Codec<R> {
         Codec {
             is_starting_up: true,
             statement_param_types: HashMap::new(),
+            trace_level: None,
             _unused: PhantomData,
         }
     }
@@ -51,4 +55,9 @@ impl<R: IntoIterator<Item: TryInto<Value, Error = Error>>> Codec<R> {
     pub fn clear_statement_param_types(&mut self, statement_name: &str) {
         self.statement_param_types.remove(statement_name);
     }
+
+    /// Set the level messages are traced at, or disable tracing if `None`
+    pub(crate) fn set_trace_level(&mut self, level: Option<TraceLevel>) {
+        self.trace_level = level;
+    }
 }
@@ -1,6 +1,6 @@
 use std::num::TryFromIntError;
 
-use postgres::error::SqlState;
+use postgres::error::{DbError, ErrorPosition, SqlState};
 use postgres_types::Type;
 use thiserror::Error;
 
@@ -66,10 +66,82 @@ pub enum Error {
 
     #[error(transparent)]
     PostgresError(#[from] tokio_postgres::error::Error),
+
+    /// An error with a specific SQLSTATE, and optionally any of the other fields of a PostgreSQL
+    /// error, to report to the client as is
+    #[error("{message}")]
+    Database {
+        sqlstate: SqlState,
+        message: String,
+        fields: Box<ErrorFields>,
+    },
+}
+
+/// The optional fields of a PostgreSQL error response, beyond its severity, SQLSTATE, and message.
+///
+/// See the [PostgreSQL documentation][docs] for what each field means.
+///
+/// [docs]: https://www.postgresql.org/docs/current/protocol-error-fields.html
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ErrorFields {
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// The 1-based position in the query string, in characters, of the cause of the error
+    pub position: Option<u32>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub datatype: Option<String>,
+    pub constraint: Option<String>,
+}
+
+impl From<&DbError> for ErrorFields {
+    fn from(error: &DbError) -> Self {
+        ErrorFields {
+            detail: error.detail().map(|s| s.to_owned()),
+            hint: error.hint().map(|s| s.to_owned()),
+            position: match error.position() {
+                Some(ErrorPosition::Original(position)) => Some(*position),
+                // Positions in internally generated queries don't refer to the client's query
+                Some(ErrorPosition::Internal { .. }) | None => None,
+            },
+            schema: error.schema().map(|s| s.to_owned()),
+            table: error.table().map(|s| s.to_owned()),
+            column: error.column().map(|s| s.to_owned()),
+            datatype: error.datatype().map(|s| s.to_owned()),
+            constraint: error.constraint().map(|s| s.to_owned()),
+        }
+    }
 }
 
 impl<R> From<Error> for BackendMessage<R> {
     fn from(error: Error) -> Self {
+        // Errors from the upstream database and errors with explicit fields are sent along with
+        // all their fields, so that clients see exactly what they would from PostgreSQL
+        if let Error::PostgresError(db_error) = &error {
+            if let Some(db_error) = db_error.as_db_error() {
+                return BackendMessage::ErrorResponse {
+                    severity: ErrorSeverity::Error,
+                    sqlstate: db_error.code().clone(),
+                    message: db_error.message().to_owned(),
+                    fields: Box::new(db_error.into()),
+                };
+            }
+        }
+        if let Error::Database {
+            sqlstate,
+            message,
+            fields,
+        } = error
+        {
+            return BackendMessage::ErrorResponse {
+                severity: ErrorSeverity::Error,
+                sqlstate,
+                message,
+                fields,
+            };
+        }
+
         let sqlstate = match error {
             Error::AuthenticationFailure { .. } => SqlState::INVALID_PASSWORD,
             Error::NoUserSpecified => SqlState::INVALID_PASSWORD,
@@ -90,12 +162,14 @@ impl<R> From<Error> for BackendMessage<R> {
             Error::UnsupportedType(_) => SqlState::FEATURE_NOT_SUPPORTED,
             Error::Scram(_) => SqlState::PROTOCOL_VIOLATION,
             Error::PostgresError(ref e) => e.code().cloned().unwrap_or(SqlState::INTERNAL_ERROR),
+            Error::Database { ref sqlstate, .. } => sqlstate.clone(),
         };
 
         BackendMessage::ErrorResponse {
             severity: ErrorSeverity::Error,
             sqlstate,
             message: error.to_string(),
+            fields: Box::default(),
         }
     }
 }
@@ -23,6 +23,7 @@ mod protocol;
 mod response;
 mod runner;
 mod scram;
+mod trace;
 pub mod util;
 mod value;
 
@@ -38,7 +39,7 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_native_tls::TlsAcceptor;
 
 pub use crate::bytes::BytesStr;
-pub use crate::error::Error;
+pub use crate::error::{Error, ErrorFields};
 pub use crate::value::Value;
 
 pub enum CredentialsNeeded {
@@ -171,16 +172,14 @@ pub enum QueryResponse<R> {
 /// * `channel` - A bytestream channel connected to a PostgreSQL frontend. Requests sent by the
 ///   frontend on this channel will be forwarded to `backend`, and the `backend`'s responses will be
 ///   returned to the frontend. When `channel` is closed by the frontend, `run_backend` returns.
-/// * `enable_statement_logging` - Whether to log statements received from the client.
 /// * `tls_acceptor` - An object that performs a TLS handshake and creates a `TlsStream` or returns
 ///   an error.
 pub async fn run_backend<B: Backend>(
     backend: B,
     channel: tokio::net::TcpStream,
-    enable_statement_logging: bool,
     tls_acceptor: Option<Arc<TlsAcceptor>>,
 ) {
-    runner::Runner::run(backend, channel, enable_statement_logging, tls_acceptor).await
+    runner::Runner::run(backend, channel, tls_acceptor).await
 }
 
 pub async fn send_immediate_err<B, C>(channel: C, error: Error) -> Result<(), Error>
@@ -7,7 +7,7 @@ use postgres::SimpleQueryRow;
 use postgres_types::Type;
 use tokio_postgres::OwnedField;
 
-use crate::error::Error;
+use crate::error::{Error, ErrorFields};
 use crate::message::TransferFormat;
 use crate::value::Value;
 
@@ -53,6 +53,7 @@ pub enum BackendMessage<R> {
         severity: ErrorSeverity,
         sqlstate: SqlState,
         message: String,
+        fields: Box<ErrorFields>,
     },
     ParameterDescription {
         parameter_data_types: Vec<Type>,
@@ -2,6 +2,7 @@ use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use bytes::Bytes;
 use postgres::SimpleQueryMessage;
 use postgres_protocol::Oid;
 use postgres_types::{Kind, Type};
@@ -25,7 +26,7 @@ use crate::scram::{
 };
 use crate::value::Value;
 use crate::QueryResponse::*;
-use crate::{Backend, Column, Credentials, PrepareResponse};
+use crate::{trace, Backend, Column, Credentials, PrepareResponse};
 
 const ATTTYPMOD_NONE: i32 = -1;
 const TRANSFER_FORMAT_PLACEHOLDER: TransferFormat = TransferFormat::Text;
@@ -597,6 +598,14 @@ impl Protocol {
                 // A request to directly execute a complete SQL statement, without creating a
                 // prepared statement.
                 Query { query } => {
+                    if let Some(level) = trace::parse_set_trace(query.borrow()) {
+                        channel.set_trace_level(level?);
+                        return Ok(Response::Messages(smallvec![
+                            PassThroughCommandComplete(Bytes::from_static(b"SET")),
+                            BackendMessage::ready_for_query_idle(),
+                        ]));
+                    }
+
                     let response = backend.on_query(query.borrow()).await?;
                     if let Select { schema, resultset } = response {
                         let mut field_descriptions = Vec::with_capacity(schema.len());
@@ -678,13 +687,30 @@ impl Protocol {
                 Parse {
                     prepared_statement_name,
                     query,
-                    ..
+                    parameter_data_types,
                 } => {
+                    // The upstream database would accept setting the trace level as a custom
+                    // parameter, so reject it rather than silently not tracing
+                    if trace::parse_set_trace(query.borrow()).is_some() {
+                        return Err(Error::Unsupported(
+                            "SET readyset.trace can only be run as a simple query".to_string(),
+                        ));
+                    }
                     let PrepareResponse {
                         prepared_statement_id,
-                        param_schema,
+                        mut param_schema,
                         row_schema,
                     } = backend.on_prepare(query.borrow()).await?;
+                    // As in postgres, the types the frontend specified for parameters take
+                    // precedence over the ones we inferred, since they're the types the frontend
+                    // will encode the parameter values as. Parameters left unspecified (with type
+                    // oid 0) keep their inferred type.
+                    for (param_type, specified) in param_schema.iter_mut().zip(parameter_data_types)
+                    {
+                        if specified != Type::UNKNOWN {
+                            *param_type = specified;
+                        }
+                    }
                     channel.set_statement_param_types(
                         prepared_statement_name.borrow() as &str,
                         param_schema.clone(),
@@ -1492,6 +1518,53 @@ mod tests {
         block_on(protocol.on_request(request, &mut backend, &mut channel)).unwrap_err();
     }
 
+    #[test]
+    fn set_trace() {
+        let mut protocol = Protocol::new();
+        let mut backend = Backend::new();
+        let mut channel = Channel::<NullBytestream, Vec<Value>>::new(NullBytestream);
+
+        let startup_request = FrontendMessage::StartupMessage {
+            protocol_version: 12345,
+            user: Some(bytes_str("user_name")),
+            database: Some(bytes_str("database_name")),
+        };
+        block_on(protocol.on_request(startup_request, &mut backend, &mut channel)).unwrap();
+
+        // Setting the trace level is handled without passing the query to the backend.
+        let request = FrontendMessage::Query {
+            query: bytes_str("SET readyset.trace = debug1;"),
+        };
+        match block_on(protocol.on_request(request, &mut backend, &mut channel)).unwrap() {
+            Response::Messages(messages) => assert_eq!(
+                messages.as_slice(),
+                [
+                    PassThroughCommandComplete(Bytes::from_static(b"SET")),
+                    BackendMessage::ready_for_query_idle(),
+                ]
+            ),
+            _ => panic!(),
+        }
+        assert_eq!(backend.last_query, None);
+
+        let request = FrontendMessage::Query {
+            query: bytes_str("SET readyset.trace = verbose"),
+        };
+        block_on(protocol.on_request(request, &mut backend, &mut channel)).unwrap_err();
+
+        // It can't be set with the extended query protocol
+        let request = FrontendMessage::Parse {
+            prepared_statement_name: bytes_str("set_trace"),
+            query: bytes_str("SET readyset.trace = debug1"),
+            parameter_data_types: vec![],
+        };
+        assert!(matches!(
+            block_on(protocol.on_request(request, &mut backend, &mut channel)),
+            Err(Error::Unsupported(_))
+        ));
+        assert_eq!(backend.last_prepare, None);
+    }
+
     #[test]
     fn query_write() {
         let mut protocol = Protocol::new();
@@ -1572,6 +1645,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_with_specified_param_types() {
+        let mut protocol = Protocol::new();
+        let mut backend = Backend::new();
+        let mut channel = Channel::<NullBytestream, Vec<Value>>::new(NullBytestream);
+
+        let startup_request = FrontendMessage::StartupMessage {
+            protocol_version: 12345,
+            user: Some(bytes_str("user_name")),
+            database: Some(bytes_str("database_name")),
+        };
+        block_on(protocol.on_request(startup_request, &mut backend, &mut channel)).unwrap();
+
+        // Parameter types specified by the frontend replace the inferred ones, except where
+        // they're left unspecified.
+        let request = FrontendMessage::Parse {
+            prepared_statement_name: bytes_str("prepared1"),
+            query: bytes_str("SELECT * FROM test WHERE x = $1 AND y = $2;"),
+            parameter_data_types: vec![Type::UNKNOWN, Type::TEXT],
+        };
+        block_on(protocol.on_request(request, &mut backend, &mut channel)).unwrap();
+        assert_eq!(
+            protocol
+                .prepared_statements
+                .get("prepared1")
+                .unwrap()
+                .param_schema,
+            vec![Type::FLOAT8, Type::TEXT]
+        );
+    }
+
     #[test]
     fn parse_error() {
         let mut protocol = Protocol::new();
@@ -2268,7 +2372,8 @@ mod tests {
             Response::Message(ErrorResponse {
                 severity: ErrorSeverity::Error,
                 sqlstate: SqlState::INTERNAL_ERROR,
-                message
+                message,
+                ..
             }) if message == "internal error: error requested"
         ));
     }
@@ -2288,7 +2393,8 @@ mod tests {
                     ErrorResponse {
                         severity: ErrorSeverity::Error,
                         sqlstate: SqlState::INTERNAL_ERROR,
-                        message: "internal error: error requested".to_string()
+                        message: "internal error: error requested".to_string(),
+                        fields: Box::default(),
                     },
                     BackendMessage::ready_for_query_idle()
                 ]
@@ -2309,7 +2415,8 @@ mod tests {
             Response::Message(ErrorResponse {
                 severity: ErrorSeverity::Error,
                 sqlstate: SqlState::INTERNAL_ERROR,
-                message
+                message,
+                ..
             }) if message == "internal error: error requested"
         ));
         assert_eq!(protocol.state, State::Error);
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use readyset_tracing::statement_logging::Component;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_native_tls::TlsAcceptor;
 use tracing::{error, info};
@@ -18,8 +19,6 @@ pub struct Runner<B: Backend, C> {
     channel: Channel<C, B::Row>,
     /// Handles Postgres protocol messages and maintains protocol state
     protocol: Protocol,
-    /// Whether to log statements received from the client
-    enable_statement_logging: bool,
 }
 
 /// Indicates whether the client is initiating a TLS connection, or the client has closed the
@@ -38,7 +37,6 @@ impl<B: Backend> Runner<B, tokio::net::TcpStream> {
     pub async fn run(
         backend: B,
         byte_channel: tokio::net::TcpStream,
-        enable_statement_logging: bool,
         tls_acceptor: Option<Arc<TlsAcceptor>>,
     ) {
         let mut protocol = Protocol::new();
@@ -49,7 +47,6 @@ impl<B: Backend> Runner<B, tokio::net::TcpStream> {
             backend,
             channel: Channel::new(byte_channel),
             protocol,
-            enable_statement_logging,
         };
 
         // Connection has closed or is waiting for tls handshake
@@ -75,7 +72,6 @@ impl<B: Backend> Runner<B, tokio::net::TcpStream> {
                         backend,
                         channel: Channel::new(stream),
                         protocol,
-                        enable_statement_logging,
                     };
                     // Run loop again. Warn client if we get an unexpected RestartWithTls status.
                     if matches!(runner.main_loop().await, MainLoopStatus::RestartWithTls) {
@@ -100,7 +96,7 @@ impl<B: Backend, C: AsyncRead + AsyncWrite + Unpin> Runner<B, C> {
         request: Result<FrontendMessage, codec::DecodeError>,
     ) -> Result<(), Error> {
         let request = request?;
-        if self.enable_statement_logging {
+        if Component::PostgreSqlFrontend.is_enabled() {
             info!(target: "client_statement", "{:?}", request);
         }
         if request == FrontendMessage::Flush {
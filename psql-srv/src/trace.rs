@@ -0,0 +1,416 @@
+//! Per-connection tracing of protocol messages, enabled by running
+//! `SET readyset.trace = <level>`.
+//!
+//! Each message sent or received is logged (to the `client_protocol` target) in the same format
+//! as libpq's [`PQtrace`][pqtrace]: the direction (`F` for messages from the frontend, `B` for
+//! messages from the backend), the length of the message, the name of the message, and its
+//! fields, separated by tabs. Values in bound parameters and data rows aren't logged.
+//!
+//! The level a connection is traced at follows the semantics of Postgres' [`log_min_messages`]:
+//! a message is traced if its level is the same as or more severe than the connection's. Error
+//! responses are at the level of their severity, data rows are at `DEBUG2`, and every other
+//! message is at `DEBUG1`. Tracing is disabled with `SET readyset.trace = off`.
+//!
+//! The trace level can only be set with a simple query, since it's set without involving the
+//! backend; setting it with the extended query protocol is rejected.
+//!
+//! [pqtrace]: https://www.postgresql.org/docs/current/libpq-control.html#LIBPQ-PQTRACE
+//! [`log_min_messages`]: https://www.postgresql.org/docs/current/runtime-config-logging.html#GUC-LOG-MIN-MESSAGES
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use postgres::error::SqlState;
+use tracing::info;
+
+use crate::error::Error;
+use crate::message::{
+    BackendMessage, CommandCompleteTag, ErrorSeverity, FrontendMessage, StatementName,
+    TransferFormat,
+};
+
+/// The name of the configuration parameter that sets the trace level of a connection
+const TRACE_PARAMETER: &str = "readyset.trace";
+
+/// The level of a traced message, or of the messages a connection is traced at, ordered from
+/// least to most severe in the same order as Postgres' `log_min_messages`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum TraceLevel {
+    Debug5,
+    Debug4,
+    Debug3,
+    Debug2,
+    Debug1,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Log,
+    Fatal,
+    Panic,
+}
+
+/// The value of the `readyset.trace` parameter: either a [`TraceLevel`], or `None` if tracing is
+/// disabled
+struct TraceSetting(Option<TraceLevel>);
+
+impl FromStr for TraceSetting {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let level = match s.to_ascii_lowercase().as_str() {
+            "off" | "false" => return Ok(TraceSetting(None)),
+            // Tracing every message except data rows
+            "on" | "true" | "debug1" => TraceLevel::Debug1,
+            "debug5" => TraceLevel::Debug5,
+            "debug4" => TraceLevel::Debug4,
+            "debug3" => TraceLevel::Debug3,
+            // Tracing every message. Like in Postgres, `debug` is an alias for `debug2`.
+            "debug" | "debug2" => TraceLevel::Debug2,
+            "info" => TraceLevel::Info,
+            "notice" => TraceLevel::Notice,
+            "warning" => TraceLevel::Warning,
+            "error" => TraceLevel::Error,
+            "log" => TraceLevel::Log,
+            "fatal" => TraceLevel::Fatal,
+            "panic" => TraceLevel::Panic,
+            _ => return Err(()),
+        };
+        Ok(TraceSetting(Some(level)))
+    }
+}
+
+/// Strip `prefix` from the start of `s`, ignoring case
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let start = s.get(..prefix.len())?;
+    start
+        .eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+/// Strip the keyword `keyword` and the whitespace after it from the start of `s`, ignoring case
+fn strip_keyword<'a>(s: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = strip_prefix_ignore_case(s, keyword)?;
+    rest.starts_with(char::is_whitespace)
+        .then(|| rest.trim_start())
+}
+
+/// If `query` is a `SET readyset.trace` statement, returns the trace level it sets, or `None` if
+/// it disables tracing. Returns an error if the value isn't a valid trace level.
+pub(crate) fn parse_set_trace(query: &str) -> Option<Result<Option<TraceLevel>, Error>> {
+    let query = query.trim().trim_end_matches(';').trim_end();
+    let rest = strip_keyword(query, "set")?;
+    let rest = strip_keyword(rest, "session").unwrap_or(rest);
+    let rest = strip_prefix_ignore_case(rest, TRACE_PARAMETER)?;
+    let rest = rest.trim_start();
+    let value = match rest.strip_prefix('=') {
+        Some(value) => value,
+        None => strip_keyword(rest, "to")?,
+    };
+    let value = value.trim().trim_matches(|c| c == '\'' || c == '"');
+
+    Some(
+        value
+            .parse::<TraceSetting>()
+            .map(|TraceSetting(level)| level)
+            .map_err(|()| Error::Database {
+                sqlstate: SqlState::INVALID_PARAMETER_VALUE,
+                message: format!("invalid value for parameter \"{TRACE_PARAMETER}\": \"{value}\""),
+                fields: Box::default(),
+            }),
+    )
+}
+
+fn statement_name(name: &StatementName) -> String {
+    match name {
+        StatementName::Portal(name) => format!(" P {:?}", name.as_str()),
+        StatementName::PreparedStatement(name) => format!(" S {:?}", name.as_str()),
+    }
+}
+
+/// Returns the name and fields of `message`, as they should be traced, if it should be traced at
+/// `level`
+pub(crate) fn frontend_message(
+    level: Option<TraceLevel>,
+    message: &FrontendMessage,
+) -> Option<String> {
+    if level? > TraceLevel::Debug1 {
+        return None;
+    }
+
+    let mut trace = String::new();
+    // Writing to a `String` can't fail
+    let _ = match message {
+        FrontendMessage::Authenticate { .. } => write!(trace, "PasswordMessage\t"),
+        FrontendMessage::Bind {
+            portal_name,
+            prepared_statement_name,
+            params,
+            result_transfer_formats,
+        } => write!(
+            trace,
+            "Bind\t {:?} {:?} {} {}",
+            portal_name.as_str(),
+            prepared_statement_name.as_str(),
+            params.len(),
+            result_transfer_formats.len()
+        ),
+        FrontendMessage::Close { name } => write!(trace, "Close\t{}", statement_name(name)),
+        FrontendMessage::Describe { name } => write!(trace, "Describe\t{}", statement_name(name)),
+        FrontendMessage::Execute { portal_name, limit } => {
+            write!(trace, "Execute\t {:?} {limit}", portal_name.as_str())
+        }
+        FrontendMessage::Parse {
+            prepared_statement_name,
+            query,
+            parameter_data_types,
+        } => {
+            let _ = write!(
+                trace,
+                "Parse\t {:?} {:?} {}",
+                prepared_statement_name.as_str(),
+                query.as_str(),
+                parameter_data_types.len()
+            );
+            parameter_data_types
+                .iter()
+                .try_for_each(|ty| write!(trace, " {}", ty.oid()))
+        }
+        FrontendMessage::Query { query } => {
+            write!(trace, "Query\t {:?}", query.as_str())
+        }
+        FrontendMessage::SSLRequest => write!(trace, "SSLRequest\t"),
+        FrontendMessage::StartupMessage {
+            protocol_version,
+            user,
+            database,
+        } => {
+            let _ = write!(trace, "StartupMessage\t {protocol_version}");
+            if let Some(user) = user {
+                let _ = write!(trace, " \"user\" {:?}", user.as_str());
+            }
+            match database {
+                Some(database) => {
+                    write!(trace, " \"database\" {:?}", database.as_str())
+                }
+                None => Ok(()),
+            }
+        }
+        FrontendMessage::SaslResponse { .. } => write!(trace, "SASLResponse\t"),
+        FrontendMessage::Sync => write!(trace, "Sync\t"),
+        FrontendMessage::Flush => write!(trace, "Flush\t"),
+        FrontendMessage::Terminate => write!(trace, "Terminate\t"),
+    };
+    Some(trace)
+}
+
+fn backend_message_level<R>(message: &BackendMessage<R>) -> TraceLevel {
+    match message {
+        BackendMessage::ErrorResponse { severity, .. } => match severity {
+            ErrorSeverity::Error => TraceLevel::Error,
+            ErrorSeverity::Fatal => TraceLevel::Fatal,
+            ErrorSeverity::Panic => TraceLevel::Panic,
+        },
+        BackendMessage::DataRow { .. } | BackendMessage::PassThroughDataRow(_) => {
+            TraceLevel::Debug2
+        }
+        _ => TraceLevel::Debug1,
+    }
+}
+
+/// Returns the name and fields of `message`, as they should be traced, if it should be traced at
+/// `level`
+pub(crate) fn backend_message<R>(
+    level: Option<TraceLevel>,
+    message: &BackendMessage<R>,
+) -> Option<String> {
+    if backend_message_level(message) < level? {
+        return None;
+    }
+
+    let mut trace = String::new();
+    // Writing to a `String` can't fail
+    let _ = match message {
+        BackendMessage::AuthenticationCleartextPassword => {
+            write!(trace, "AuthenticationCleartextPassword\t")
+        }
+        BackendMessage::AuthenticationSasl { .. } => write!(trace, "AuthenticationSASL\t"),
+        BackendMessage::AuthenticationSaslContinue { .. } => {
+            write!(trace, "AuthenticationSASLContinue\t")
+        }
+        BackendMessage::AuthenticationSaslFinal { .. } => {
+            write!(trace, "AuthenticationSASLFinal\t")
+        }
+        BackendMessage::AuthenticationOk => write!(trace, "AuthenticationOk\t"),
+        BackendMessage::BindComplete => write!(trace, "BindComplete\t"),
+        BackendMessage::CloseComplete => write!(trace, "CloseComplete\t"),
+        BackendMessage::CommandComplete { tag } => {
+            let tag = match tag {
+                CommandCompleteTag::Delete(n) => format!("DELETE {n}"),
+                CommandCompleteTag::Empty => String::new(),
+                CommandCompleteTag::Insert(n) => format!("INSERT 0 {n}"),
+                CommandCompleteTag::Select(n) => format!("SELECT {n}"),
+                CommandCompleteTag::Update(n) => format!("UPDATE {n}"),
+            };
+            write!(trace, "CommandComplete\t {tag:?}")
+        }
+        BackendMessage::PassThroughCommandComplete(tag) => write!(
+            trace,
+            "CommandComplete\t {:?}",
+            String::from_utf8_lossy(tag)
+        ),
+        BackendMessage::DataRow { .. } => write!(trace, "DataRow\t"),
+        BackendMessage::PassThroughDataRow(row) => write!(trace, "DataRow\t {}", row.len()),
+        BackendMessage::ErrorResponse {
+            severity,
+            sqlstate,
+            message,
+            ..
+        } => {
+            let severity = match severity {
+                ErrorSeverity::Error => "ERROR",
+                ErrorSeverity::Fatal => "FATAL",
+                ErrorSeverity::Panic => "PANIC",
+            };
+            write!(
+                trace,
+                "ErrorResponse\t S {severity:?} C {:?} M {message:?}",
+                sqlstate.code()
+            )
+        }
+        BackendMessage::ParameterDescription {
+            parameter_data_types,
+        } => {
+            let _ = write!(
+                trace,
+                "ParameterDescription\t {}",
+                parameter_data_types.len()
+            );
+            parameter_data_types
+                .iter()
+                .try_for_each(|ty| write!(trace, " {}", ty.oid()))
+        }
+        BackendMessage::ParameterStatus {
+            parameter_name,
+            parameter_value,
+        } => write!(
+            trace,
+            "ParameterStatus\t {parameter_name:?} {parameter_value:?}"
+        ),
+        BackendMessage::ParseComplete => write!(trace, "ParseComplete\t"),
+        BackendMessage::ReadyForQuery { status } => {
+            write!(trace, "ReadyForQuery\t {}", *status as char)
+        }
+        BackendMessage::RowDescription { field_descriptions } => {
+            let _ = write!(trace, "RowDescription\t {}", field_descriptions.len());
+            field_descriptions.iter().try_for_each(|field| {
+                write!(
+                    trace,
+                    " {:?} {} {} {} {} {} {}",
+                    field.field_name,
+                    field.table_id,
+                    field.col_id,
+                    field.data_type.oid(),
+                    field.data_type_size,
+                    field.type_modifier,
+                    match field.transfer_format {
+                        TransferFormat::Text => 0,
+                        TransferFormat::Binary => 1,
+                    }
+                )
+            })
+        }
+        BackendMessage::PassThroughRowDescription(fields) => {
+            let _ = write!(trace, "RowDescription\t {}", fields.len());
+            fields.iter().try_for_each(|field| {
+                write!(
+                    trace,
+                    " {:?} {} {} {} {} {} {}",
+                    field.name(),
+                    field.table_oid(),
+                    field.column_id(),
+                    field.type_oid(),
+                    field.type_size(),
+                    field.type_modifier(),
+                    field.format()
+                )
+            })
+        }
+        BackendMessage::SSLResponse { byte } => write!(trace, "SSLResponse\t {}", *byte as char),
+    };
+    Some(trace)
+}
+
+/// Log a traced message. `from_frontend` is the direction of the message, and `length` is the
+/// length of the message as given in its header, which doesn't include the message type byte.
+pub(crate) fn log(from_frontend: bool, length: usize, trace: &str) {
+    let direction = if from_frontend { 'F' } else { 'B' };
+    info!(target: "client_protocol", "{direction}\t{length}\t{trace}");
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::bytes::BytesStr;
+
+    #[test]
+    fn parses_set_trace() {
+        assert_eq!(
+            parse_set_trace("SET readyset.trace = debug1")
+                .unwrap()
+                .unwrap(),
+            Some(TraceLevel::Debug1)
+        );
+        assert_eq!(
+            parse_set_trace("set session READYSET.TRACE to 'ERROR';")
+                .unwrap()
+                .unwrap(),
+            Some(TraceLevel::Error)
+        );
+        assert_eq!(
+            parse_set_trace("SET readyset.trace TO off")
+                .unwrap()
+                .unwrap(),
+            None
+        );
+        assert!(parse_set_trace("SET readyset.trace = loud")
+            .unwrap()
+            .is_err());
+        assert!(parse_set_trace("SET search_path = public").is_none());
+        assert!(parse_set_trace("SELECT 1").is_none());
+    }
+
+    #[test]
+    fn traces_messages_at_or_above_level() {
+        let query = FrontendMessage::Query {
+            query: BytesStr::try_from(Bytes::from_static(b"SELECT 1")).unwrap(),
+        };
+        assert_eq!(
+            frontend_message(Some(TraceLevel::Debug1), &query).unwrap(),
+            "Query\t \"SELECT 1\""
+        );
+        assert!(frontend_message(Some(TraceLevel::Info), &query).is_none());
+        assert!(frontend_message(None, &query).is_none());
+
+        let ready = BackendMessage::<Vec<crate::Value>>::ready_for_query_idle();
+        assert_eq!(
+            backend_message(Some(TraceLevel::Debug2), &ready).unwrap(),
+            "ReadyForQuery\t I"
+        );
+        assert!(backend_message(Some(TraceLevel::Error), &ready).is_none());
+
+        let error = BackendMessage::<Vec<crate::Value>>::ErrorResponse {
+            severity: ErrorSeverity::Error,
+            sqlstate: SqlState::SYNTAX_ERROR,
+            message: "oops".to_owned(),
+            fields: Box::default(),
+        };
+        assert_eq!(
+            backend_message(Some(TraceLevel::Error), &error).unwrap(),
+            "ErrorResponse\t S \"ERROR\" C \"42601\" M \"oops\""
+        );
+        assert!(backend_message(Some(TraceLevel::Fatal), &error).is_none());
+    }
+}
@@ -86,7 +86,7 @@ async fn run_server(backend: ScramSha256Backend) -> u16 {
             .send(listener.local_addr().unwrap().port())
             .unwrap();
         let (socket, _) = listener.accept().await.unwrap();
-        run_backend(backend, socket, false, tls_acceptor).await;
+        run_backend(backend, socket, tls_acceptor).await;
     });
     recv_port.await.unwrap()
 }
@@ -119,7 +119,7 @@ where
             .send(listener.local_addr().unwrap().port())
             .unwrap();
         let (socket, _) = listener.accept().await.unwrap();
-        run_backend(ErrorBackend(error_pos), socket, false, None).await;
+        run_backend(ErrorBackend(error_pos), socket, None).await;
     });
     let client = tokio::spawn(async move {
         let port = recv_port.await.unwrap();
@@ -177,7 +177,7 @@ async fn prepare_error_sync() {
             .send(listener.local_addr().unwrap().port())
             .unwrap();
         let (socket, _) = listener.accept().await.unwrap();
-        run_backend(ErrorBackend(ErrorPosition::Execute), socket, false, None).await;
+        run_backend(ErrorBackend(ErrorPosition::Execute), socket, None).await;
     });
     let port = recv_port.await.unwrap();
     tokio::task::spawn_blocking(move || {
@@ -92,7 +92,7 @@ async fn connect() {
             .send(listener.local_addr().unwrap().port())
             .unwrap();
         let (socket, _) = listener.accept().await.unwrap();
-        run_backend(TestBackend, socket, false, tls_acceptor).await;
+        run_backend(TestBackend, socket, tls_acceptor).await;
     });
 
     let port = recv_port.await.unwrap();
@@ -167,6 +167,7 @@ fn value_of_type(typ: &SqlType) -> DfValue {
         }
         SqlType::VarBit(_) => DfValue::from(BitVec::new()),
         SqlType::Array(_) => unimplemented!(),
+        SqlType::Geometry(_) => unimplemented!(),
         SqlType::Other(_) => unimplemented!(),
     }
 }
@@ -299,6 +300,7 @@ fn random_value_of_type(typ: &SqlType) -> DfValue {
         SqlType::Serial => (rng.gen::<u32>() + 1).into(),
         SqlType::BigSerial => (rng.gen::<u64>() + 1).into(),
         SqlType::Array(_) => unimplemented!(),
+        SqlType::Geometry(_) => unimplemented!(),
         SqlType::Other(_) => unimplemented!(),
     }
 }
@@ -412,6 +414,7 @@ fn unique_value_of_type(typ: &SqlType, idx: u32) -> DfValue {
         SqlType::Serial => (idx + 1).into(),
         SqlType::BigSerial => ((idx + 1) as u64).into(),
         SqlType::Array(_) => unimplemented!(),
+        SqlType::Geometry(_) => unimplemented!(),
         SqlType::Other(_) => unimplemented!(),
     }
 }
@@ -1861,6 +1864,11 @@ pub enum QueryOperation {
     Filter(Filter),
     Distinct,
     Join(JoinOperator),
+    /// Join a table in the query to itself, under a fresh alias
+    SelfJoin(JoinOperator),
+    /// Join a new table to a table in the query twice, on different columns, once under its own
+    /// name and once under a fresh alias
+    JoinTableTwice(JoinOperator),
     ProjectLiteral,
     #[weight(u32::from(!args.in_subquery))]
     SingleParameter,
@@ -2025,6 +2033,8 @@ lazy_static! {
             .map(QueryOperation::ColumnAggregate)
             .chain(iter::once(QueryOperation::Distinct))
             .chain(JOIN_OPERATORS.iter().cloned().map(QueryOperation::Join))
+            .chain(iter::once(QueryOperation::SelfJoin(JoinOperator::InnerJoin)))
+            .chain(iter::once(QueryOperation::JoinTableTwice(JoinOperator::InnerJoin)))
             .chain(iter::once(QueryOperation::ProjectLiteral))
             .chain(iter::once(QueryOperation::SingleParameter))
             .chain(iter::once(QueryOperation::InParameter { num_values: 3 }))
@@ -2059,6 +2069,44 @@ fn query_has_aggregate(query: &SelectStatement) -> bool {
     })
 }
 
+/// Returns an `ON` constraint for a join, comparing `left_column` in `left_table` to
+/// `right_column` in `right_table` (either of which may be an alias) for equality
+fn join_on_columns(
+    left_table: Relation,
+    left_column: ColumnName,
+    right_table: Relation,
+    right_column: ColumnName,
+) -> JoinConstraint {
+    JoinConstraint::On(Expr::BinaryOp {
+        op: BinaryOperator::Equal,
+        lhs: Box::new(Expr::Column(Column {
+            table: Some(left_table),
+            ..left_column.into()
+        })),
+        rhs: Box::new(Expr::Column(Column {
+            table: Some(right_table),
+            ..right_column.into()
+        })),
+    })
+}
+
+/// Add `column` in `table` (which may be an alias) to the fields projected by `query`, under a
+/// fresh alias
+fn project_column(
+    state: &mut QueryState<'_>,
+    query: &mut SelectStatement,
+    table: Relation,
+    column: ColumnName,
+) {
+    query.fields.push(FieldDefinitionExpr::Expr {
+        expr: Expr::Column(Column {
+            table: Some(table),
+            ..column.into()
+        }),
+        alias: Some(state.fresh_alias()),
+    });
+}
+
 fn column_in_query(state: &mut QueryState<'_>, query: &mut SelectStatement) -> Column {
     match query
         .tables
@@ -2294,6 +2342,105 @@ impl QueryOperation {
                 });
             }
 
+            QueryOperation::SelfJoin(operator) => {
+                let table = state.some_table_in_query_mut(query);
+                let table_name = table.name.clone();
+                let left_join_key = table.some_column_with_type(SqlType::Int(None));
+                let right_join_key =
+                    table.some_column_with_type_different_than(SqlType::Int(None), &left_join_key);
+                let projected = table.fresh_column();
+
+                if query.tables.is_empty() {
+                    query
+                        .tables
+                        .push(TableExpr::from(Relation::from(table_name.clone())));
+                }
+
+                // The table is referenced under its own name on the left-hand side of the join,
+                // and under the alias on the right-hand side
+                let alias = Relation {
+                    schema: None,
+                    name: state.fresh_alias(),
+                };
+                query.join.push(JoinClause {
+                    operator: *operator,
+                    right: JoinRightSide::Table(TableExpr {
+                        inner: TableExprInner::Table(table_name.clone().into()),
+                        alias: Some(alias.name.clone()),
+                    }),
+                    constraint: join_on_columns(
+                        table_name.clone().into(),
+                        left_join_key,
+                        alias.clone(),
+                        right_join_key,
+                    ),
+                });
+
+                project_column(state, query, table_name.into(), projected.clone());
+                project_column(state, query, alias, projected);
+            }
+
+            QueryOperation::JoinTableTwice(operator) => {
+                let left_table = state.some_table_in_query_mut(query);
+                let left_table_name = left_table.name.clone();
+                let first_left_key = left_table.some_column_with_type(SqlType::Int(None));
+                let second_left_key = left_table
+                    .some_column_with_type_different_than(SqlType::Int(None), &first_left_key);
+
+                if query.tables.is_empty() {
+                    query
+                        .tables
+                        .push(TableExpr::from(Relation::from(left_table_name.clone())));
+                }
+
+                let right_table = state.fresh_table_mut();
+                let right_table_name = right_table.name.clone();
+                let first_right_key = right_table.some_column_with_type(SqlType::Int(None));
+                let second_right_key = right_table
+                    .some_column_with_type_different_than(SqlType::Int(None), &first_right_key);
+                let right_projected = right_table.fresh_column();
+
+                // The right table is reached through a different pair of columns each time, so
+                // the two references to it can match different rows
+                let alias = Relation {
+                    schema: None,
+                    name: state.fresh_alias(),
+                };
+                query.join.push(JoinClause {
+                    operator: *operator,
+                    right: JoinRightSide::Table(TableExpr::from(Relation::from(
+                        right_table_name.clone(),
+                    ))),
+                    constraint: join_on_columns(
+                        left_table_name.clone().into(),
+                        first_left_key,
+                        right_table_name.clone().into(),
+                        first_right_key,
+                    ),
+                });
+                query.join.push(JoinClause {
+                    operator: *operator,
+                    right: JoinRightSide::Table(TableExpr {
+                        inner: TableExprInner::Table(right_table_name.clone().into()),
+                        alias: Some(alias.name.clone()),
+                    }),
+                    constraint: join_on_columns(
+                        left_table_name.into(),
+                        second_left_key,
+                        alias.clone(),
+                        second_right_key,
+                    ),
+                });
+
+                project_column(
+                    state,
+                    query,
+                    right_table_name.into(),
+                    right_projected.clone(),
+                );
+                project_column(state, query, alias, right_projected);
+            }
+
             QueryOperation::ProjectLiteral => {
                 let alias = state.fresh_alias();
                 query.fields.push(FieldDefinitionExpr::Expr {
@@ -2559,6 +2706,8 @@ impl QueryOperation {
 /// | joins                                   | Joins, with all [`JoinOperator`]s       |
 /// | inner_join                              | `INNER JOIN`s                           |
 /// | left_join                               | `LEFT JOIN`s                            |
+/// | self_join                               | `INNER JOIN`s of a table to itself      |
+/// | join_table_twice                        | Two `INNER JOIN`s to the same table     |
 /// | single_parameter / single_param / param | A single query parameter                |
 /// | range_param                             | A range query parameter                 |
 /// | multiple_parameters / params            | Multiple query parameters               |
@@ -2679,6 +2828,8 @@ impl FromStr for Operations {
             "joins" => Ok(JOIN_OPERATORS.iter().cloned().map(Join).collect()),
             "inner_join" => Ok(vec![Join(JoinOperator::InnerJoin)].into()),
             "left_join" => Ok(vec![Join(JoinOperator::LeftJoin)].into()),
+            "self_join" => Ok(vec![SelfJoin(JoinOperator::InnerJoin)].into()),
+            "join_table_twice" => Ok(vec![JoinTableTwice(JoinOperator::InnerJoin)].into()),
             "single_parameter" | "single_param" | "param" => Ok(vec![SingleParameter].into()),
             "multiple_parameters" | "params" => Ok(vec![MultipleParameters].into()),
             "range_param" => Ok(vec![RangeParameter].into()),
@@ -3282,6 +3433,59 @@ mod tests {
         }
     }
 
+    /// Returns the names of the tables joined to in `query`, and the aliases they're joined under
+    fn joined_tables(query: &SelectStatement) -> Vec<(SqlIdentifier, Option<SqlIdentifier>)> {
+        query
+            .join
+            .iter()
+            .map(|jc| match &jc.right {
+                JoinRightSide::Table(TableExpr {
+                    inner: TableExprInner::Table(table),
+                    alias,
+                }) => (table.name.clone(), alias.clone()),
+                right => unreachable!("Unexpected join right side: {:?}", right),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn self_join() {
+        let query = generate_query(vec![QueryOperation::SelfJoin(JoinOperator::InnerJoin)]);
+        eprintln!("query: {}", query.display(nom_sql::Dialect::MySQL));
+        assert_eq!(query.tables.len(), 1);
+        let table = query.tables[0].inner.as_table().unwrap().name.clone();
+        let joined = joined_tables(&query);
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0].0, table);
+        let alias = joined[0].1.clone().unwrap();
+        assert_ne!(alias, table);
+
+        let JoinConstraint::On(Expr::BinaryOp { lhs, rhs, .. }) = &query.join[0].constraint else {
+            unreachable!("Unexpected constraint: {:?}", query.join[0].constraint);
+        };
+        match (lhs.as_ref(), rhs.as_ref()) {
+            (Expr::Column(left), Expr::Column(right)) => {
+                assert_eq!(left.table.as_ref().unwrap().name, table);
+                assert_eq!(right.table.as_ref().unwrap().name, alias);
+                assert_ne!(left.name, right.name);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn join_table_twice() {
+        let query = generate_query(vec![QueryOperation::JoinTableTwice(JoinOperator::LeftJoin)]);
+        eprintln!("query: {}", query.display(nom_sql::Dialect::MySQL));
+        assert_eq!(query.tables.len(), 1);
+        let joined = joined_tables(&query);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined[0].0, joined[1].0);
+        assert_eq!(joined[0].1, None);
+        assert!(joined[1].1.is_some());
+        assert_ne!(query.join[0].constraint, query.join[1].constraint);
+    }
+
     mod parse_num_operations {
         use super::*;
 
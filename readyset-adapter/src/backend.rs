@@ -77,15 +77,18 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use chrono::FixedOffset;
 use futures::future::{self, OptionFuture};
 use mysql_common::row::convert::{FromRow, FromRowError};
 use nom_sql::{
-    CacheInner, CreateCacheStatement, DeleteStatement, Dialect, DropCacheStatement,
-    InsertStatement, Relation, SelectStatement, SetStatement, ShowStatement, SqlIdentifier,
-    SqlQuery, UpdateStatement, UseStatement,
+    AlterReadysetStatement, CacheInner, CreateCacheStatement, DeleteStatement, Dialect,
+    DropCacheStatement, InsertStatement, Relation, SelectStatement, SetStatement, ShowStatement,
+    SqlIdentifier, SqlQuery, UpdateStatement, UseStatement,
 };
 use readyset_client::consistency::Timestamp;
+use readyset_client::internal::IndexType;
 use readyset_client::query::*;
+use readyset_client::replication::ReplicationOffset;
 use readyset_client::results::Results;
 use readyset_client::{ColumnSchema, ViewCreateRequest};
 pub use readyset_client_metrics::QueryDestination;
@@ -98,11 +101,12 @@ use readyset_util::redacted::Sensitive;
 use readyset_version::READYSET_VERSION;
 use timestamp_service::client::{TimestampClient, WriteId, WriteKey};
 use tokio::sync::mpsc::UnboundedSender;
-use tracing::{error, instrument, trace, warn};
+use tracing::{debug, error, instrument, trace, warn};
 
 use crate::backend::noria_connector::ExecuteSelectContext;
-use crate::query_handler::SetBehavior;
+use crate::query_handler::{SessionContext, SetBehavior};
 use crate::query_status_cache::QueryStatusCache;
+use crate::result_cache::{cacheable_tables, ResultCache, ResultCacheInvalidation, ResultCacheKey};
 use crate::upstream_database::NoriaCompare;
 pub use crate::upstream_database::UpstreamPrepare;
 use crate::{rewrite, QueryHandler, UpstreamDatabase, UpstreamDestination};
@@ -265,6 +269,7 @@ pub struct BackendBuilder {
     query_max_failure_seconds: u64,
     fallback_recovery_seconds: u64,
     telemetry_sender: Option<TelemetrySender>,
+    read_after_write_window: Option<Duration>,
 }
 
 impl Default for BackendBuilder {
@@ -285,6 +290,7 @@ impl Default for BackendBuilder {
             query_max_failure_seconds: (i64::MAX / 1000) as u64,
             fallback_recovery_seconds: 0,
             telemetry_sender: None,
+            read_after_write_window: None,
         }
     }
 }
@@ -314,13 +320,20 @@ impl BackendBuilder {
             users: self.users,
             query_log_sender: self.query_log_sender,
             last_query: None,
+            client: None,
             state: BackendState {
                 proxy_state,
                 parsed_query_cache: HashMap::new(),
-                prepared_statements: Vec::new(),
+                prepared_statements: HashMap::new(),
+                next_prepared_id: 0,
                 query_status_cache,
                 ticket: self.ticket,
                 timestamp_client: self.timestamp_client,
+                session_context: SessionContext::default(),
+                result_cache: None,
+                transaction_result_cache_invalidations: Vec::new(),
+                read_after_write: None,
+                now_utc_offset_read_at: None,
             },
             settings: BackendSettings {
                 slowlog: self.slowlog,
@@ -333,6 +346,7 @@ impl BackendBuilder {
                 query_max_failure_duration: Duration::new(self.query_max_failure_seconds, 0),
                 query_log_ad_hoc_queries: self.query_log_ad_hoc_queries,
                 fallback_recovery_duration: Duration::new(self.fallback_recovery_seconds, 0),
+                read_after_write_window: self.read_after_write_window,
             },
             telemetry_sender: self.telemetry_sender,
             _query_handler: PhantomData,
@@ -414,6 +428,15 @@ impl BackendBuilder {
         self.telemetry_sender = Some(telemetry_sender);
         self
     }
+
+    /// After a connection makes a write to the upstream database, proxy that connection's reads
+    /// to the upstream database until `window` has passed or ReadySet has replicated past the
+    /// write, whichever comes first. If `None`, reads are served from ReadySet straight after
+    /// writes.
+    pub fn read_after_write_window(mut self, window: Option<Duration>) -> Self {
+        self.read_after_write_window = window;
+        self
+    }
 }
 
 /// A [`CachedPreparedStatement`] stores the data needed for an immediate
@@ -442,6 +465,8 @@ where
     /// If statement was successfully rewritten, will store all information necessary to install
     /// the view in readyset
     view_request: Option<ViewCreateRequest>,
+    /// The results in the result cache that executing the statement upstream may change
+    result_cache_invalidation: ResultCacheInvalidation,
 }
 
 impl<DB> CachedPreparedStatement<DB>
@@ -491,6 +516,10 @@ where
     /// have been handled using this connection (Backend) yet.
     last_query: Option<QueryInfo>,
 
+    /// The application or client library the client identified itself as, if any, which queries
+    /// on this connection are logged and recorded in metrics with
+    client: Option<Arc<str>>,
+
     /// Encapsulates the inner state of this [`Backend`]
     state: BackendState<DB>,
     /// The settings with which the [`Backend`] was started
@@ -513,7 +542,9 @@ where
     // a cache of all previously parsed queries
     parsed_query_cache: HashMap<String, SqlQuery>,
     // all queries previously prepared on noria or upstream, mapped by their ID.
-    prepared_statements: Vec<CachedPreparedStatement<DB>>,
+    prepared_statements: HashMap<u32, CachedPreparedStatement<DB>>,
+    /// The ID to give the next prepared statement
+    next_prepared_id: u32,
     /// Current RYW ticket. `None` if RYW is not enabled. This `ticket` will
     /// be updated as the client makes writes so as to be an accurate low watermark timestamp
     /// required to make RYW-consistent reads. On reads, the client will pass in this ticket to be
@@ -523,6 +554,96 @@ where
     /// is responsible for creating accurate RYW timestamps/tickets based on writes made by the
     /// Backend client.
     timestamp_client: Option<TimestampClient>,
+    /// The session variables set by the client on this connection
+    session_context: SessionContext,
+    /// Cache for the results of ad hoc `SELECT` queries that are proxied upstream, if enabled
+    result_cache: Option<ResultCache<DB::CachedReadResult>>,
+    /// The result cache invalidations for writes made in the current transaction, with the schema
+    /// search path they were made with. Other connections can cache results from before the writes
+    /// are committed, so these are applied again once the transaction is committed.
+    transaction_result_cache_invalidations: Vec<(ResultCacheInvalidation, Vec<SqlIdentifier>)>,
+    /// Set while this connection's reads are pinned to the upstream database after a write, per
+    /// [`BackendBuilder::read_after_write_window`]
+    read_after_write: Option<ReadAfterWrite>,
+    /// When the offset from UTC of the time zone of the upstream session was last read, for
+    /// binding the current time to `NOW()` in cached queries. `None` if it hasn't been read
+    /// since the client last ran a `SET` statement (which may have changed the time zone).
+    now_utc_offset_read_at: Option<Instant>,
+}
+
+/// Tracks a write made by a connection whose reads are pinned to the upstream database until
+/// ReadySet has replicated the write
+#[derive(Debug)]
+struct ReadAfterWrite {
+    /// When to stop pinning reads, even if ReadySet hasn't replicated the write yet
+    until: Instant,
+    /// The upstream database's replication offset as of the write. `None` if it hasn't been read
+    /// from the upstream database yet, which happens at the next read after the write since the
+    /// results of the write borrow the upstream connection, and `Some(None)` if the upstream
+    /// database couldn't report it.
+    offset: Option<Option<ReplicationOffset>>,
+}
+
+impl<DB> BackendState<DB>
+where
+    DB: UpstreamDatabase,
+{
+    /// Returns the result cache, if it's enabled and the results of proxied queries can currently
+    /// be read from it.
+    ///
+    /// Results can't be read from the cache inside of transactions, which might have made writes
+    /// that the cached results don't reflect, or after unsupported `SET` statements, which might
+    /// have changed the results of queries in ways we don't know about.
+    fn result_cache(&self) -> Option<&ResultCache<DB::CachedReadResult>> {
+        self.result_cache
+            .as_ref()
+            .filter(|_| self.proxy_state == ProxyState::Fallback && self.read_after_write.is_none())
+    }
+
+    /// Remove the results from the result cache that a statement this connection executed on the
+    /// upstream database with `schema_search_path` may have changed, per `invalidation`.
+    fn invalidate_result_cache(
+        &mut self,
+        invalidation: ResultCacheInvalidation,
+        schema_search_path: &[SqlIdentifier],
+    ) {
+        let Some(result_cache) = &self.result_cache else {
+            return;
+        };
+        if invalidation == ResultCacheInvalidation::None {
+            return;
+        }
+        result_cache.invalidate(&invalidation, schema_search_path);
+        if matches!(
+            self.proxy_state,
+            ProxyState::InTransaction | ProxyState::AutocommitOff
+        ) {
+            self.transaction_result_cache_invalidations
+                .push((invalidation, schema_search_path.to_vec()));
+        }
+    }
+
+    /// Apply the result cache invalidations for the writes made in the current transaction again
+    /// if it was `committed`, or forget them if it was rolled back.
+    fn end_result_cache_transaction(&mut self, committed: bool) {
+        let invalidations = std::mem::take(&mut self.transaction_result_cache_invalidations);
+        if let (true, Some(result_cache)) = (committed, &self.result_cache) {
+            for (invalidation, schema_search_path) in &invalidations {
+                result_cache.invalidate(invalidation, schema_search_path);
+            }
+        }
+    }
+
+    /// Pin this connection's reads to the upstream database after it has made a write, for up to
+    /// `window`. Does nothing if `window` is `None`.
+    fn pin_reads_after_write(&mut self, window: Option<Duration>) {
+        if let Some(window) = window {
+            self.read_after_write = Some(ReadAfterWrite {
+                until: Instant::now() + window,
+                offset: None,
+            });
+        }
+    }
 }
 
 /// Settings that have no state and are constant for a given [`Backend`]
@@ -546,6 +667,9 @@ struct BackendSettings {
     /// repeatedly failed for query_max_failure_duration.
     fallback_recovery_duration: Duration,
     fail_invalidated_queries: bool,
+    /// How long to pin a connection's reads to the upstream database after it makes a write, if
+    /// at all
+    read_after_write_window: Option<Duration>,
 }
 
 /// QueryInfo holds information regarding the last query that was sent along this connection
@@ -554,6 +678,8 @@ struct BackendSettings {
 pub struct QueryInfo {
     pub destination: QueryDestination,
     pub noria_error: String,
+    /// The type of the index that was read from, if the query was executed against ReadySet
+    pub index_type: Option<IndexType>,
 }
 
 impl FromRow for QueryInfo {
@@ -572,6 +698,8 @@ impl FromRow for QueryInfo {
                     res.noria_error = std::str::from_utf8(d)
                         .map_err(|_| FromRowError(row.clone()))?
                         .to_string();
+                } else if c.name_str() == "ReadySet_index" {
+                    res.index_type = dest.parse().ok();
                 } else {
                     return Err(FromRowError(row.clone()));
                 }
@@ -733,18 +861,39 @@ where
             .unwrap_or_else(|| DB::DEFAULT_DB_VERSION.to_string())
     }
 
-    /// The identifier of the last prepared statement (which is always the last in the vector)
+    /// The identifier of the last prepared statement
     pub fn last_prepared_id(&self) -> u32 {
-        (self.state.prepared_statements.len() - 1)
-            .try_into()
-            .expect("Too many prepared statements")
+        self.state.next_prepared_id - 1
     }
 
     /// The identifier we can reserve for the next prepared statement
     pub fn next_prepared_id(&self) -> u32 {
-        (self.state.prepared_statements.len())
-            .try_into()
-            .expect("Too many prepared statements")
+        self.state.next_prepared_id
+    }
+
+    /// Discard the prepared statement identified by `id`, along with the statements prepared for
+    /// it in ReadySet and the upstream database. Does nothing if there is no such statement.
+    pub async fn remove_statement(&mut self, id: u32) -> Result<(), DB::Error> {
+        let Some(statement) = self.state.prepared_statements.remove(&id) else {
+            return Ok(());
+        };
+        let (noria_prep, upstream_prep) = match statement.prep {
+            PrepareResult::Noria(noria_prep) => (Some(noria_prep), None),
+            PrepareResult::Upstream(upstream_prep) => (None, Some(upstream_prep)),
+            PrepareResult::Both(noria_prep, upstream_prep) => {
+                (Some(noria_prep), Some(upstream_prep))
+            }
+        };
+        if let Some(noria_prep) = noria_prep {
+            self.noria
+                .remove_prepared_statement(noria_prep.statement_id());
+        }
+        if let (Some(upstream_prep), Some(upstream)) = (upstream_prep, &mut self.upstream) {
+            upstream
+                .remove_statement(upstream_prep.statement_id)
+                .await?;
+        }
+        Ok(())
     }
 
     /// Switch the active database for this backend to the given named database.
@@ -766,6 +915,37 @@ where
         Ok(())
     }
 
+    /// Returns the schema search path currently in use, which for MySQL contains just the current
+    /// database, if any
+    pub fn schema_search_path(&self) -> &[SqlIdentifier] {
+        self.noria.schema_search_path()
+    }
+
+    /// Reset this connection's session to the state it was in when the client connected, for
+    /// clients that reset their session (such as MySQL clients sending `COM_RESET_CONNECTION`).
+    ///
+    /// All prepared statements, session variables and any open transaction are discarded, and the
+    /// upstream connection (if any) is reset. The schema search path is kept, but the upstream
+    /// connection reverts to the database it connected to.
+    pub async fn reset(&mut self) -> Result<(), DB::Error> {
+        if let Some(upstream) = &mut self.upstream {
+            upstream.reset().await?;
+        }
+        self.noria.clear_prepared_statements();
+        self.last_query = None;
+        self.state.prepared_statements.clear();
+        self.state.next_prepared_id = 0;
+        self.state.proxy_state = if self.upstream.is_some() {
+            ProxyState::Fallback
+        } else {
+            ProxyState::Never
+        };
+        self.state.session_context = SessionContext::default();
+        self.state.read_after_write = None;
+        self.state.now_utc_offset_read_at = None;
+        Ok(())
+    }
+
     /// Executes query on the upstream database, for when it cannot be parsed or executed by noria.
     /// Returns the query result, or an error if fallback is not configured
     #[instrument(skip_all)]
@@ -787,6 +967,38 @@ where
         result.map(QueryResult::Upstream)
     }
 
+    /// Executes an ad hoc `SELECT` query on the upstream database, for when it won't be executed
+    /// by ReadySet, reading its results from `result_cache` if they're cacheable.
+    #[instrument(skip_all)]
+    async fn query_fallback_select<'a>(
+        upstream: Option<&'a mut DB>,
+        result_cache: Option<&ResultCache<DB::CachedReadResult>>,
+        schema_search_path: &[SqlIdentifier],
+        query: &'a str,
+        stmt: &SelectStatement,
+        event: &mut QueryExecutionEvent,
+    ) -> Result<QueryResult<'a, DB>, DB::Error> {
+        let Some((result_cache, tables)) =
+            result_cache.and_then(|cache| Some((cache, cacheable_tables(stmt)?)))
+        else {
+            return Self::query_fallback(upstream, query, event).await;
+        };
+        let upstream = upstream.ok_or_else(|| {
+            ReadySetError::Internal("This case requires an upstream connector".to_string())
+        })?;
+        let key = ResultCacheKey::new(query, schema_search_path);
+        let _t = event.start_upstream_timer();
+        let result = upstream
+            .query_with_result_cache(query, result_cache, key, &tables)
+            .await;
+        drop(_t);
+        event.destination = Some(match &result {
+            Ok((_, destination)) => *destination,
+            Err(_) => QueryDestination::Upstream,
+        });
+        result.map(|(result, _)| QueryResult::Upstream(result))
+    }
+
     /// Prepares query on the mysql_backend, if present, when it cannot be parsed or prepared by
     /// noria.
     pub async fn prepare_fallback(
@@ -837,12 +1049,13 @@ where
         self.last_query = destination.map(|d| QueryInfo {
             destination: d,
             noria_error: String::new(),
+            index_type: None,
         });
 
         // Update noria migration state for query
         match &noria_res {
             Some(Ok(noria_connector::PrepareResult::Select(res))) => {
-                let mut state = MigrationState::Successful;
+                let mut unsupported_reason = None;
 
                 let (schema, params) = match res {
                     SelectPrepareResult::Schema(SelectPrepareResultInner {
@@ -868,24 +1081,33 @@ where
                                     query = %Sensitive(&select_meta.stmt.display(DB::sql_dialect())),
                                     "Query compare failed"
                                 );
-                                state = MigrationState::Unsupported;
+                                unsupported_reason =
+                                    Some(format!("Schema differs from upstream: {e}"));
                             }
                         } else if self.settings.fail_invalidated_queries {
                             internal!("Cannot compare schema for borrowed query");
                         } else {
                             warn!("Cannot compare schema for borrowed query");
-                            state = MigrationState::Unsupported;
+                            unsupported_reason =
+                                Some("Cannot compare schema for borrowed query".to_owned());
                         }
                     }
                 }
 
-                self.state.query_status_cache.update_query_migration_state(
-                    &ViewCreateRequest::new(
-                        select_meta.rewritten.clone(),
-                        self.noria.schema_search_path().to_owned(),
-                    ),
-                    state,
+                let view_request = ViewCreateRequest::new(
+                    select_meta.rewritten.clone(),
+                    self.noria.schema_search_path().to_owned(),
                 );
+                match unsupported_reason {
+                    Some(reason) => self
+                        .state
+                        .query_status_cache
+                        .update_query_unsupported(&view_request, reason),
+                    None => self
+                        .state
+                        .query_status_cache
+                        .update_query_migration_state(&view_request, MigrationState::Successful),
+                }
             }
             Some(Err(e)) => {
                 if e.caused_by_view_not_found() {
@@ -898,12 +1120,12 @@ where
                         MigrationState::Pending,
                     );
                 } else if e.caused_by_unsupported() {
-                    self.state.query_status_cache.update_query_migration_state(
+                    self.state.query_status_cache.update_query_unsupported(
                         &ViewCreateRequest::new(
                             select_meta.rewritten.clone(),
                             self.noria.schema_search_path().to_owned(),
                         ),
-                        MigrationState::Unsupported,
+                        e.to_string(),
                     );
                 } else {
                     error!(
@@ -956,6 +1178,7 @@ where
             self.last_query = Some(QueryInfo {
                 destination: QueryDestination::Upstream,
                 noria_error: String::new(),
+                index_type: None,
             });
             res
         } else {
@@ -970,6 +1193,7 @@ where
             self.last_query = Some(QueryInfo {
                 destination: QueryDestination::Readyset,
                 noria_error: String::new(),
+                index_type: None,
             });
             Ok(PrepareResult::Noria(res))
         }
@@ -1022,7 +1246,12 @@ where
         stmt: &nom_sql::SelectStatement,
     ) -> Option<(nom_sql::SelectStatement, bool)> {
         let mut rewritten = stmt.clone();
-        if rewrite::process_query(&mut rewritten, self.noria.server_supports_pagination()).is_err()
+        if rewrite::process_query(
+            &mut rewritten,
+            self.noria.server_supports_pagination(),
+            self.noria.now_parameter_granularity(),
+        )
+        .is_err()
         {
             None
         } else {
@@ -1046,6 +1275,9 @@ where
         }
 
         match self.parse_query(query) {
+            Ok(query) if self.has_fallback() && Handler::requires_fallback(&query) => {
+                PrepareMeta::Proxy
+            }
             Ok(SqlQuery::Select(stmt)) => self.plan_prepare_select(stmt),
             Ok(
                 query @ SqlQuery::Insert(_)
@@ -1095,6 +1327,7 @@ where
                 self.last_query = Some(QueryInfo {
                     destination: QueryDestination::Upstream,
                     noria_error: String::new(),
+                    index_type: None,
                 });
 
                 res
@@ -1114,6 +1347,7 @@ where
     pub async fn prepare(&mut self, query: &str) -> Result<&PrepareResult<DB>, DB::Error> {
         self.last_query = None;
         let mut query_event = QueryExecutionEvent::new(EventType::Prepare);
+        query_event.client = self.client.clone();
 
         let meta = self.plan_prepare(query).await;
         let res = self.do_prepare(&meta, query, &mut query_event).await?;
@@ -1154,6 +1388,28 @@ where
         }
         query_event.query_id = id;
 
+        let result_cache_invalidation = match &parsed_query {
+            Some(parsed) => ResultCacheInvalidation::for_query(parsed),
+            None => ResultCacheInvalidation::for_unparsed(query),
+        };
+
+        if let Some(query_id) = id {
+            if self.state.query_status_cache.is_traced(&query_id) {
+                debug!(
+                    target: QUERY_TRACE_TARGET,
+                    %query_id,
+                    ?migration_state,
+                    always,
+                    prepared_against = match &res {
+                        PrepareResult::Noria(_) => "readyset",
+                        PrepareResult::Upstream(_) => "upstream",
+                        PrepareResult::Both(..) => "both",
+                    },
+                    "traced query prepared"
+                );
+            }
+        }
+
         let cache_entry = CachedPreparedStatement {
             query_id: id,
             prep: res,
@@ -1162,11 +1418,17 @@ where
             parsed_query,
             view_request,
             always,
+            result_cache_invalidation,
         };
 
-        self.state.prepared_statements.push(cache_entry);
-
-        Ok(&self.state.prepared_statements.last().unwrap().prep)
+        let id = self.state.next_prepared_id;
+        self.state.next_prepared_id += 1;
+        Ok(&self
+            .state
+            .prepared_statements
+            .entry(id)
+            .or_insert(cache_entry)
+            .prep)
     }
 
     /// Executes a prepared statement on ReadySet
@@ -1342,7 +1604,7 @@ where
         // Linear scan, but we shouldn't be doing it often, right?
         self.state
             .prepared_statements
-            .iter_mut()
+            .values_mut()
             .filter_map(
                 |CachedPreparedStatement {
                      prep,
@@ -1376,15 +1638,40 @@ where
         params: &[DfValue],
     ) -> Result<QueryResult<'_, DB>, DB::Error> {
         self.last_query = None;
+        if matches!(
+            self.state.prepared_statements.get(&id),
+            Some(CachedPreparedStatement {
+                prep: PrepareResult::Both(..),
+                ..
+            })
+        ) {
+            self.refresh_read_after_write().await;
+            self.refresh_now_utc_offset().await;
+        }
         let cached_statement = self
             .state
             .prepared_statements
-            .get_mut(id as usize)
+            .get_mut(&id)
             .ok_or(PreparedStatementMissing { statement_id: id })?;
 
         let mut event = QueryExecutionEvent::new(EventType::Execute);
+        event.client = self.client.clone();
         event.query = cached_statement.parsed_query.clone();
         event.query_id = cached_statement.query_id;
+        if let Some(query_id) = cached_statement.query_id {
+            self.state.query_status_cache.record_request(|| query_id);
+        }
+
+        // Read before the results borrow `noria`
+        let result_cache_invalidation = (self.state.result_cache.is_some()
+            && !matches!(cached_statement.prep, PrepareResult::Noria(_))
+            && cached_statement.result_cache_invalidation != ResultCacheInvalidation::None)
+            .then(|| {
+                (
+                    cached_statement.result_cache_invalidation.clone(),
+                    self.noria.schema_search_path().to_vec(),
+                )
+            });
 
         let upstream = &mut self.upstream;
         let noria = &mut self.noria;
@@ -1430,7 +1717,9 @@ where
                 } else if always_readyset {
                     false
                 } else {
-                    is_recovering || self.state.proxy_state.should_proxy()
+                    is_recovering
+                        || self.state.proxy_state.should_proxy()
+                        || self.state.read_after_write.is_some()
                 }
             }
         };
@@ -1478,13 +1767,27 @@ where
                 //
                 // Must exist or we would not have executed the query against ReadySet.
                 #[allow(clippy::unwrap_used)]
-                self.state.query_status_cache.update_query_migration_state(
+                self.state.query_status_cache.update_query_unsupported(
                     cached_statement.view_request.as_ref().unwrap(),
-                    MigrationState::Unsupported,
+                    e.to_string(),
                 );
             }
         }
 
+        // Includes writes we couldn't parse, such as `REPLACE` or `CALL`
+        let proxied_write = matches!(cached_statement.prep, PrepareResult::Upstream(_))
+            && cached_statement.result_cache_invalidation != ResultCacheInvalidation::None;
+        if proxied_write && result.is_ok() {
+            self.state
+                .pin_reads_after_write(self.settings.read_after_write_window);
+        }
+        if let (true, Some((invalidation, schema_search_path))) =
+            (result.is_ok(), result_cache_invalidation)
+        {
+            self.state
+                .invalidate_result_cache(invalidation, &schema_search_path);
+        }
+
         self.last_query = event.destination.map(|d| QueryInfo {
             destination: d,
             noria_error: event
@@ -1492,8 +1795,14 @@ where
                 .as_ref()
                 .map(|e| e.to_string())
                 .unwrap_or_default(),
+            index_type: event.index_type,
         });
-        log_query(self.query_log_sender.as_ref(), event, self.settings.slowlog);
+        log_query(
+            self.query_log_sender.as_ref(),
+            self.state.query_status_cache,
+            event,
+            self.settings.slowlog,
+        );
 
         result
     }
@@ -1541,7 +1850,7 @@ where
     /// Generates response to the `EXPLAIN LAST STATEMENT` query
     #[instrument(skip_all)]
     fn explain_last_statement(&self) -> ReadySetResult<noria_connector::QueryResult<'static>> {
-        let (destination, error) = self
+        let (destination, error, index_type) = self
             .last_query
             .as_ref()
             .map(|info| {
@@ -1551,13 +1860,17 @@ where
                         s if s.is_empty() => "ok".to_string(),
                         s => s.clone(),
                     },
+                    info.index_type
+                        .map(|index_type| index_type.to_string())
+                        .unwrap_or_else(|| "none".to_string()),
                 )
             })
-            .unwrap_or_else(|| ("unknown".to_string(), "ok".to_string()));
+            .unwrap_or_else(|| ("unknown".to_string(), "ok".to_string(), "none".to_string()));
 
         Ok(noria_connector::QueryResult::Meta(vec![
             ("Query_destination", destination).into(),
             ("ReadySet_error", error).into(),
+            ("ReadySet_index", index_type).into(),
         ]))
     }
 
@@ -1583,7 +1896,11 @@ where
             }
         }
         // Now migrate the new query
-        rewrite::process_query(&mut stmt, self.noria.server_supports_pagination())?;
+        rewrite::process_query(
+            &mut stmt,
+            self.noria.server_supports_pagination(),
+            self.noria.now_parameter_granularity(),
+        )?;
         self.noria
             .handle_create_cached_query(name, &stmt, override_schema_search_path, always)
             .await?;
@@ -1623,7 +1940,7 @@ where
     async fn drop_all_caches(&mut self) -> ReadySetResult<noria_connector::QueryResult<'static>> {
         self.noria.drop_all_caches().await?;
         self.state.query_status_cache.clear();
-        self.state.prepared_statements.iter_mut().for_each(
+        self.state.prepared_statements.values_mut().for_each(
             |CachedPreparedStatement {
                  prep,
                  migration_state,
@@ -1638,6 +1955,66 @@ where
         Ok(noria_connector::QueryResult::Empty)
     }
 
+    /// Forwards an `ALTER READYSET RESNAPSHOT TABLE` request to noria
+    #[instrument(skip(self))]
+    async fn resnapshot_table(
+        &mut self,
+        table: &Relation,
+    ) -> ReadySetResult<noria_connector::QueryResult<'static>> {
+        self.noria.resnapshot_table(table).await?;
+        Ok(noria_connector::QueryResult::Empty)
+    }
+
+    /// Forwards an `ALTER READYSET RESTORE CACHES` request to noria
+    #[instrument(skip(self))]
+    async fn restore_all_caches(
+        &mut self,
+    ) -> ReadySetResult<noria_connector::QueryResult<'static>> {
+        self.noria.restore_all_caches().await?;
+        // The caches keep their names, but prepared statements may hold on to view handles for the
+        // caches we just dropped, so force them to be re-prepared against ReadySet
+        self.state.prepared_statements.values_mut().for_each(
+            |CachedPreparedStatement {
+                 migration_state, ..
+             }| {
+                if *migration_state == MigrationState::Successful {
+                    *migration_state = MigrationState::Pending;
+                }
+            },
+        );
+        Ok(noria_connector::QueryResult::Empty)
+    }
+
+    /// Responds to an `ALTER READYSET TRACE QUERY` request by enabling tracing for the query with
+    /// the given id, both in the adapter and in the readers of its cache, if it has one
+    #[instrument(skip(self))]
+    async fn trace_query(
+        &mut self,
+        id: &SqlIdentifier,
+        duration_secs: Option<u64>,
+    ) -> ReadySetResult<noria_connector::QueryResult<'static>> {
+        let query_id = self
+            .state
+            .query_status_cache
+            .query_id(id.as_str())
+            .ok_or_else(|| ReadySetError::NoQueryForId { id: id.to_string() })?;
+        let duration = duration_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_QUERY_TRACE_DURATION);
+        self.state
+            .query_status_cache
+            .trace_query(query_id, duration);
+        let cached = self.noria.trace_query(query_id, duration).await?;
+        debug!(
+            target: QUERY_TRACE_TARGET,
+            %query_id,
+            ?duration,
+            cached,
+            "tracing query"
+        );
+        Ok(noria_connector::QueryResult::Empty)
+    }
+
     /// Responds to a `SHOW PROXIED QUERIES` query
     #[instrument(skip(self))]
     async fn show_proxied_queries(
@@ -1663,12 +2040,18 @@ where
                 create_dummy_column("query id"),
                 create_dummy_column("proxied query"),
                 create_dummy_column("readyset supported"),
+                create_dummy_column("unsupported reason"),
+                create_dummy_column("requests per second"),
+                create_dummy_column("cache suggestion"),
             ]),
 
             columns: Cow::Owned(vec![
                 "query id".into(),
                 "proxied query".into(),
                 "readyset supported".into(),
+                "unsupported reason".into(),
+                "requests per second".into(),
+                "cache suggestion".into(),
             ]),
         };
 
@@ -1682,10 +2065,41 @@ where
                 }
                 .to_string();
 
+                let requests_per_second = self
+                    .state
+                    .query_status_cache
+                    .requests_per_second(&id)
+                    .map(|rps| DfValue::from(format!("{rps:.2}")))
+                    .unwrap_or(DfValue::None);
+
+                // Only suggest caching queries that a dry run showed we can cache, but that
+                // aren't cached yet. Queries are referred to by id, so that the suggestion can be
+                // run as-is no matter how the query was rewritten
+                let cache_suggestion = match status.migration_state {
+                    MigrationState::DryRunSucceeded => DfValue::from(
+                        CreateCacheStatement {
+                            name: None,
+                            inner: Ok(CacheInner::Id(id.to_string().into())),
+                            always: false,
+                        }
+                        .display(DB::sql_dialect())
+                        .to_string(),
+                    ),
+                    MigrationState::Pending
+                    | MigrationState::Successful
+                    | MigrationState::Unsupported => DfValue::None,
+                };
+
                 vec![
                     DfValue::from(id.to_string()),
                     DfValue::from(query.display(DB::sql_dialect()).to_string()),
                     DfValue::from(s),
+                    status
+                        .unsupported_reason
+                        .map(DfValue::from)
+                        .unwrap_or(DfValue::None),
+                    requests_per_second,
+                    cache_suggestion,
                 ]
             })
             .collect::<Vec<_>>();
@@ -1761,6 +2175,15 @@ where
             }
             SqlQuery::DropCache(DropCacheStatement { name }) => self.drop_cached_query(name).await,
             SqlQuery::DropAllCaches(_) => self.drop_all_caches().await,
+            SqlQuery::AlterReadySet(AlterReadysetStatement::RestoreCaches) => {
+                self.restore_all_caches().await
+            }
+            SqlQuery::AlterReadySet(AlterReadysetStatement::ResnapshotTable { table }) => {
+                self.resnapshot_table(table).await
+            }
+            SqlQuery::AlterReadySet(AlterReadysetStatement::TraceQuery { id, duration_secs }) => {
+                self.trace_query(id, *duration_secs).await
+            }
             SqlQuery::Show(ShowStatement::CachedQueries(query_id)) => {
                 // Log a telemetry event
                 if let Some(ref telemetry_sender) = self.telemetry_sender {
@@ -1776,6 +2199,11 @@ where
             SqlQuery::Show(ShowStatement::ReadySetStatus) => self.noria.readyset_status().await,
             SqlQuery::Show(ShowStatement::ReadySetVersion) => readyset_version(),
             SqlQuery::Show(ShowStatement::ReadySetTables) => self.noria.table_statuses().await,
+            SqlQuery::Show(ShowStatement::ReadySetCreateCaches) => {
+                self.noria.create_cache_statements().await
+            }
+            SqlQuery::Show(ShowStatement::ReadySetCacheStats) => self.noria.cache_stats().await,
+            SqlQuery::Show(ShowStatement::ReadySetMemory) => self.noria.memory_usage().await,
             SqlQuery::Show(ShowStatement::ProxiedQueries(q_id)) => {
                 // Log a telemetry event
                 if let Some(ref telemetry_sender) = self.telemetry_sender {
@@ -1816,6 +2244,7 @@ where
             migration_state: MigrationState::Unsupported,
             execution_info: None,
             always: false,
+            unsupported_reason: None,
         });
         let original_status = status.clone();
         let did_work = if let Some(ref mut i) = status.execution_info {
@@ -1847,7 +2276,15 @@ where
                     &status.execution_info.unwrap().last_transition_time,
                 );
             }
-            return Self::query_fallback(upstream, original_query, event).await;
+            return Self::query_fallback_select(
+                upstream,
+                state.result_cache(),
+                noria.schema_search_path(),
+                original_query,
+                &original_stmt,
+                event,
+            )
+            .await;
         }
 
         let noria_res = {
@@ -1928,31 +2365,110 @@ where
     /// supplied select statement by rewriting it.
     /// Returns whether noria should try the select, along with the query status if it was obtained
     /// during processing.
+    /// If this connection's reads are pinned to the upstream database after a write, unpin them
+    /// once the read-after-write window has passed or ReadySet has replicated past the write.
+    ///
+    /// Does nothing within transactions, whose reads are proxied anyway, so that the upstream
+    /// database's replication offset isn't read until the writes made in the transaction have been
+    /// committed.
+    async fn refresh_read_after_write(&mut self) {
+        if self.state.proxy_state.should_proxy() {
+            return;
+        }
+        let Some(pin) = self.state.read_after_write.as_mut() else {
+            return;
+        };
+        if pin.until <= Instant::now() {
+            self.state.read_after_write = None;
+            return;
+        }
+
+        if pin.offset.is_none() {
+            let offset = match self.upstream.as_mut() {
+                Some(upstream) => upstream.replication_offset().await.unwrap_or_else(|error| {
+                    debug!(%error, "Could not read replication offset from upstream database");
+                    None
+                }),
+                None => None,
+            };
+            pin.offset = Some(offset);
+        }
+        // If the upstream database can't tell us its replication offset, all we can do is wait
+        // for the window to pass
+        let Some(Some(offset)) = pin.offset.clone() else {
+            return;
+        };
+        match self.noria.replication_offsets().await {
+            Ok(offsets) if offsets.has_replicated(&offset) => {
+                trace!(%offset, "Write replicated, no longer pinning reads to upstream");
+                self.state.read_after_write = None;
+            }
+            Ok(_) => {}
+            Err(error) => warn!(%error, "Could not read replication offsets from ReadySet"),
+        }
+    }
+
+    /// If queries comparing columns with `NOW()` are cached on this connection, read the offset
+    /// from UTC of the time zone of the upstream session (which the current time is bound to
+    /// `NOW()` in) if it hasn't been read in the last [`NOW_UTC_OFFSET_REFRESH_INTERVAL`], or since
+    /// the client last ran a `SET` statement.
+    ///
+    /// Without an upstream database, the current time is bound in UTC.
+    async fn refresh_now_utc_offset(&mut self) {
+        if self.noria.now_parameter_granularity().is_none()
+            || self.state.now_utc_offset_read_at.map_or(false, |read_at| {
+                read_at.elapsed() < NOW_UTC_OFFSET_REFRESH_INTERVAL
+            })
+        {
+            return;
+        }
+        let Some(upstream) = self.upstream.as_mut() else {
+            return;
+        };
+
+        match upstream.utc_offset().await {
+            Ok(Some(offset)) => match FixedOffset::east_opt(offset) {
+                Some(offset) => self.noria.set_now_utc_offset(offset),
+                None => warn!(%offset, "Upstream database reported an invalid UTC offset"),
+            },
+            Ok(None) => {}
+            Err(error) => warn!(%error, "Could not read UTC offset from upstream database"),
+        }
+        self.state.now_utc_offset_read_at = Some(Instant::now());
+    }
+
     fn noria_should_try_select(&self, q: &mut ViewCreateRequest) -> (bool, Option<QueryStatus>) {
         let mut status = None;
-        let should_try =
-            if rewrite::process_query(&mut q.statement, self.noria.server_supports_pagination())
-                .is_ok()
-            {
-                let s = self.state.query_status_cache.query_status(q);
-                let should_try = if self.state.proxy_state.should_proxy() {
+        let should_try = if rewrite::process_query(
+            &mut q.statement,
+            self.noria.server_supports_pagination(),
+            self.noria.now_parameter_granularity(),
+        )
+        .is_ok()
+        {
+            let s = self.state.query_status_cache.query_status(q);
+            self.state
+                .query_status_cache
+                .record_request(|| QueryId::from_view_create_request(q));
+            let should_try =
+                if self.state.proxy_state.should_proxy() || self.state.read_after_write.is_some() {
                     s.always
                 } else {
                     true
                 };
-                status = Some(s);
-                should_try
-            } else {
-                warn!(
-                    // FIXME(ENG-2499): Use correct dialect.
-                    statement = %Sensitive(&q.statement.display(nom_sql::Dialect::MySQL)),
-                    "This statement could not be rewritten by ReadySet"
-                );
-                matches!(
-                    self.state.proxy_state,
-                    ProxyState::Never | ProxyState::Fallback
-                )
-            };
+            status = Some(s);
+            should_try
+        } else {
+            warn!(
+                // FIXME(ENG-2499): Use correct dialect.
+                statement = %Sensitive(&q.statement.display(nom_sql::Dialect::MySQL)),
+                "This statement could not be rewritten by ReadySet"
+            );
+            matches!(
+                self.state.proxy_state,
+                ProxyState::Never | ProxyState::Fallback
+            ) && self.state.read_after_write.is_none()
+        };
 
         (should_try, status)
     }
@@ -1962,6 +2478,9 @@ where
     /// If we have an upstream then we will pass valid set statements across to that upstream.
     /// If no upstream is present we will ignore the statement
     /// Disallowed set statements always produce an error
+    ///
+    /// Returns whether the statement only sets variables handled by ReadySet, in which case it
+    /// shouldn't be passed to the upstream.
     fn handle_set(
         noria: &mut NoriaConnector,
         upstream: Option<&mut &mut DB>,
@@ -1970,7 +2489,10 @@ where
         query: &str,
         set: &SetStatement,
         event: &mut QueryExecutionEvent,
-    ) -> Result<(), DB::Error> {
+    ) -> Result<bool, DB::Error> {
+        // The statement may have changed the time zone of the upstream session
+        state.now_utc_offset_read_at = None;
+
         match Handler::handle_set_statement(set) {
             SetBehavior::Unsupported => {
                 warn!(
@@ -2021,9 +2543,16 @@ where
                 trace!(?search_path, "Setting search_path");
                 noria.set_schema_search_path(search_path);
             }
+            SetBehavior::SetNowParameterGranularity(granularity) => {
+                trace!(?granularity, "Setting NOW() parameter granularity");
+                noria.set_now_parameter_granularity(granularity);
+                return Ok(true);
+            }
         }
 
-        Ok(())
+        Handler::update_session_context(set, &mut state.session_context);
+
+        Ok(false)
     }
 
     #[instrument(level = "trace", skip_all)]
@@ -2037,15 +2566,20 @@ where
         state: &mut BackendState<DB>,
     ) -> Result<QueryResult<'a, DB>, DB::Error> {
         match &query {
-            SqlQuery::Set(s) => Self::handle_set(
-                noria,
-                upstream.as_mut(),
-                settings,
-                state,
-                raw_query,
-                s,
-                event,
-            )?,
+            SqlQuery::Set(s) => {
+                if Self::handle_set(
+                    noria,
+                    upstream.as_mut(),
+                    settings,
+                    state,
+                    raw_query,
+                    s,
+                    event,
+                )? {
+                    event.destination = Some(QueryDestination::Readyset);
+                    return Ok(QueryResult::Noria(noria_connector::QueryResult::Empty));
+                }
+            }
             SqlQuery::Use(UseStatement { database }) => {
                 noria.set_schema_search_path(vec![database.clone()])
             }
@@ -2095,6 +2629,13 @@ where
                             upstream.query(raw_query).await
                         };
 
+                        if query_result.is_ok() {
+                            state.pin_reads_after_write(settings.read_after_write_window);
+                            state.invalidate_result_cache(
+                                ResultCacheInvalidation::Tables(vec![t]),
+                                noria.schema_search_path(),
+                            );
+                        }
                         query_result.map(QueryResult::Upstream)
                     }
 
@@ -2107,27 +2648,53 @@ where
                     | SqlQuery::AlterTable(_)
                     | SqlQuery::Use(_) => {
                         event.sql_type = SqlQueryType::Other;
-                        upstream.query(raw_query).await.map(QueryResult::Upstream)
+                        let result = upstream.query(raw_query).await;
+                        if result.is_ok() {
+                            state.invalidate_result_cache(
+                                ResultCacheInvalidation::for_query(&query),
+                                noria.schema_search_path(),
+                            );
+                        }
+                        result.map(QueryResult::Upstream)
                     }
                     SqlQuery::RenameTable(_) => {
                         unsupported!("{} not yet supported", query.query_type());
                     }
                     SqlQuery::Set(_) | SqlQuery::CompoundSelect(_) | SqlQuery::Show(_) => {
                         event.sql_type = SqlQueryType::Other;
-                        upstream.query(raw_query).await.map(QueryResult::Upstream)
+                        let result = upstream.query(raw_query).await;
+                        // Turning autocommit back on commits the implicit transaction
+                        if result.is_ok() && state.proxy_state.is_fallback() {
+                            state.end_result_cache_transaction(true);
+                        }
+                        result.map(QueryResult::Upstream)
                     }
 
                     SqlQuery::StartTransaction(_) | SqlQuery::Commit(_) | SqlQuery::Rollback(_) => {
-                        Self::handle_transaction_boundaries(
+                        let result = Self::handle_transaction_boundaries(
                             Some(upstream),
                             &mut state.proxy_state,
                             &query,
                         )
-                        .await
+                        .await;
+                        // Writes made in the transaction aren't replicated until it's committed, so
+                        // restart the read-after-write window
+                        if result.is_ok()
+                            && matches!(query, SqlQuery::Commit(_))
+                            && state.read_after_write.is_some()
+                        {
+                            state.pin_reads_after_write(settings.read_after_write_window);
+                        }
+                        if result.is_ok() && !matches!(query, SqlQuery::StartTransaction(_)) {
+                            state
+                                .end_result_cache_transaction(matches!(query, SqlQuery::Commit(_)));
+                        }
+                        result
                     }
                     SqlQuery::CreateCache(_)
                     | SqlQuery::DropCache(_)
                     | SqlQuery::DropAllCaches(_)
+                    | SqlQuery::AlterReadySet(_)
                     | SqlQuery::Explain(_) => {
                         unreachable!("path returns prior")
                     }
@@ -2179,7 +2746,9 @@ where
     #[inline]
     pub async fn query<'a>(&'a mut self, query: &'a str) -> Result<QueryResult<'a, DB>, DB::Error> {
         let mut event = QueryExecutionEvent::new(EventType::Query);
+        event.client = self.client.clone();
         let query_log_sender = self.query_log_sender.clone();
+        let query_status_cache = self.state.query_status_cache;
         let slowlog = self.settings.slowlog;
 
         let parse_result = {
@@ -2201,9 +2770,17 @@ where
                 let fallback_res =
                     Self::query_fallback(self.upstream.as_mut(), query, &mut event).await;
                 if fallback_res.is_ok() {
+                    let invalidation = ResultCacheInvalidation::for_unparsed(query);
+                    if invalidation != ResultCacheInvalidation::None {
+                        self.state
+                            .pin_reads_after_write(self.settings.read_after_write_window);
+                    }
+                    self.state
+                        .invalidate_result_cache(invalidation, self.noria.schema_search_path());
                     self.state.query_status_cache.insert(query);
 
                     let (id, _) = self.state.query_status_cache.insert(query);
+                    self.state.query_status_cache.record_request(|| id);
                     if let Some(ref telemetry_sender) = self.telemetry_sender {
                         if let Err(e) = telemetry_sender
                             .send_event_with_payload(
@@ -2259,7 +2836,18 @@ where
             Ok(ref parsed_query) if Handler::requires_fallback(parsed_query) => {
                 if self.has_fallback() {
                     // Query requires a fallback and we can send it to fallback
-                    Self::query_fallback(self.upstream.as_mut(), query, &mut event).await
+                    let result =
+                        Self::query_fallback(self.upstream.as_mut(), query, &mut event).await;
+                    if result.is_ok() {
+                        let invalidation = ResultCacheInvalidation::for_query(parsed_query);
+                        if invalidation != ResultCacheInvalidation::None {
+                            self.state
+                                .pin_reads_after_write(self.settings.read_after_write_window);
+                        }
+                        self.state
+                            .invalidate_result_cache(invalidation, self.noria.schema_search_path());
+                    }
+                    result
                 } else {
                     // Query requires a fallback, but none is available
                     Handler::default_response(parsed_query)
@@ -2268,6 +2856,8 @@ where
                 }
             }
             Ok(SqlQuery::Select(stmt)) => {
+                self.refresh_read_after_write().await;
+                self.refresh_now_utc_offset().await;
                 let mut view_request = ViewCreateRequest::new(
                     stmt.clone(),
                     self.noria.schema_search_path().to_owned(),
@@ -2292,11 +2882,29 @@ where
                     )
                     .await
                 } else {
-                    Self::query_fallback(self.upstream.as_mut(), query, &mut event).await
+                    Self::query_fallback_select(
+                        self.upstream.as_mut(),
+                        self.state.result_cache(),
+                        self.noria.schema_search_path(),
+                        query,
+                        &stmt,
+                        &mut event,
+                    )
+                    .await
                 }
             }
-            Ok(_) if self.state.proxy_state.should_proxy() => {
-                Self::query_fallback(self.upstream.as_mut(), query, &mut event).await
+            Ok(parsed_query) if self.state.proxy_state.should_proxy() => {
+                let result = Self::query_fallback(self.upstream.as_mut(), query, &mut event).await;
+                if result.is_ok() {
+                    let invalidation = ResultCacheInvalidation::for_query(&parsed_query);
+                    if invalidation != ResultCacheInvalidation::None {
+                        self.state
+                            .pin_reads_after_write(self.settings.read_after_write_window);
+                    }
+                    self.state
+                        .invalidate_result_cache(invalidation, self.noria.schema_search_path());
+                }
+                result
             }
             Ok(parsed_query) => {
                 Self::query_adhoc_non_select(
@@ -2319,18 +2927,55 @@ where
                 .as_ref()
                 .map(|e| e.to_string())
                 .unwrap_or_default(),
+            index_type: event.index_type,
         });
 
-        log_query(query_log_sender.as_ref(), event, slowlog);
+        log_query(
+            query_log_sender.as_ref(),
+            query_status_cache,
+            event,
+            slowlog,
+        );
 
         result
     }
 
+    /// Use `result_cache` to cache the results of ad hoc `SELECT` queries that are proxied to the
+    /// upstream database
+    pub fn with_result_cache(
+        mut self,
+        result_cache: Option<ResultCache<DB::CachedReadResult>>,
+    ) -> Self {
+        self.state.result_cache = result_cache;
+        self
+    }
+
     /// Whether or not we have fallback enabled.
     pub fn has_fallback(&self) -> bool {
         self.upstream.is_some()
     }
 
+    /// Have the upstream database, if any, report the number of rows matched by `UPDATE` statements
+    /// rather than the number of rows they changed. See
+    /// [`UpstreamDatabase::set_client_found_rows`].
+    pub async fn set_client_found_rows(&mut self) -> Result<(), DB::Error> {
+        if let Some(upstream) = &mut self.upstream {
+            upstream.set_client_found_rows().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the application or client library the client identified itself as, if any
+    pub fn client(&self) -> Option<&str> {
+        self.client.as_deref()
+    }
+
+    /// Set the application or client library the client identified itself as, which queries on
+    /// this connection are logged and recorded in metrics with from now on
+    pub fn set_client(&mut self, client: Option<Arc<str>>) {
+        self.client = client;
+    }
+
     /// If we are using fallback, this will return the database that was in the original connection
     /// string, if it exists, otherwise it will return None. If we are not using fallback this will
     /// always return None.
@@ -2346,12 +2991,26 @@ where
         &self.state.ticket
     }
 
+    /// Returns the session variables set by the client on this connection
+    pub fn session_context(&self) -> &SessionContext {
+        &self.state.session_context
+    }
+
+    /// Returns a mutable reference to the session variables set by the client on this connection,
+    /// for recording options set outside of SQL statements, such as by protocol commands
+    pub fn session_context_mut(&mut self) -> &mut SessionContext {
+        &mut self.state.session_context
+    }
+
     fn parse_query(&mut self, query: &str) -> ReadySetResult<SqlQuery> {
-        match self.state.parsed_query_cache.entry(query.to_owned()) {
+        // The same query text can parse differently depending on the session variables, so we
+        // cache parsed queries by their normalized text
+        let normalized = Handler::normalize_query_text(query, &self.state.session_context);
+        match self.state.parsed_query_cache.entry(normalized.into_owned()) {
             Entry::Occupied(entry) => Ok(entry.get().clone()),
             Entry::Vacant(entry) => {
                 trace!(%query, "Parsing query");
-                match nom_sql::parse_query(self.settings.dialect, query) {
+                match nom_sql::parse_query(self.settings.dialect, entry.key()) {
                     Ok(parsed_query) => Ok(entry.insert(parsed_query).clone()),
                     Err(_) => Err(ReadySetError::UnparseableQuery {
                         query: query.to_string(),
@@ -2375,15 +3034,45 @@ where
     }
 }
 
+/// How long to trace a query for if `ALTER READYSET TRACE QUERY` doesn't specify a duration
+const DEFAULT_QUERY_TRACE_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// How often to read the offset from UTC of the time zone of the upstream session again, so that
+/// changes to it (such as for daylight saving time) are picked up. See
+/// [`Backend::refresh_now_utc_offset`].
+const NOW_UTC_OFFSET_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Offloads recording query metrics to a separate thread. Sends a
 /// message over a mpsc channel.
 fn log_query(
     sender: Option<&UnboundedSender<QueryExecutionEvent>>,
+    query_status_cache: &QueryStatusCache,
     event: QueryExecutionEvent,
     slowlog: bool,
 ) {
     const SLOW_DURATION: std::time::Duration = std::time::Duration::from_millis(5);
 
+    if let Some(query_id) = event.query_id {
+        if query_status_cache.is_traced(&query_id) {
+            debug!(
+                target: QUERY_TRACE_TARGET,
+                %query_id,
+                client = ?event.client,
+                event_type = ?event.event,
+                query_type = ?event.sql_type,
+                destination = ?event.destination,
+                execution_path = ?event.execution_path(),
+                num_keys = ?event.num_keys,
+                cache_misses = ?event.cache_misses,
+                parse_time = ?event.parse_duration,
+                readyset_time = ?event.readyset_duration,
+                upstream_time = ?event.upstream_duration,
+                readyset_error = ?event.noria_error.as_ref().map(|e| e.to_string()),
+                "traced query executed"
+            );
+        }
+    }
+
     if slowlog
         && (event.upstream_duration.unwrap_or_default() > SLOW_DURATION
             || event.readyset_duration.unwrap_or_default() > SLOW_DURATION)
@@ -2392,6 +3081,7 @@ fn log_query(
             warn!(
                 // FIXME(ENG-2499): Use correct dialect.
                 query = %Sensitive(&query.display(nom_sql::Dialect::MySQL)),
+                client = ?event.client,
                 readyset_time = ?event.readyset_duration,
                 upstream_time = ?event.upstream_duration,
                 "slow query"
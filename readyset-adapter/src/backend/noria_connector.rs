@@ -2,8 +2,15 @@ use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
 use std::sync::{atomic, Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
 
+use chrono::FixedOffset;
+use futures::stream::{self, BoxStream};
+use futures::{future, Stream, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use nom_sql::analysis::visit::Visitor;
 use nom_sql::{
@@ -12,7 +19,9 @@ use nom_sql::{
 };
 use readyset_client::consistency::Timestamp;
 use readyset_client::internal::LocalNodeIndex;
+use readyset_client::query::QueryId;
 use readyset_client::recipe::changelist::{Change, ChangeList, IntoChanges};
+use readyset_client::replication::ReplicationOffsets;
 use readyset_client::results::{ResultIterator, Results};
 use readyset_client::{
     ColumnSchema, ReadQuery, ReaderAddress, ReaderHandle, ReadySetHandle, SchemaType, Table,
@@ -30,6 +39,7 @@ use readyset_util::redacted::{Sensitive, REDACT_SENSITIVE};
 use tracing::{error, info, instrument, trace, warn};
 
 use crate::backend::SelectSchema;
+use crate::cache_warmer::KeyStatistics;
 use crate::rewrite::{self, ProcessedQueryParams};
 use crate::utils;
 
@@ -210,6 +220,28 @@ impl<N: Into<SqlIdentifier>, V: Into<String>> From<(N, V)> for MetaVariable {
     }
 }
 
+/// The number of rows to read from a reader at a time when reading the results of a select over the
+/// network, so that large result sets don't have to be held in memory all at once
+const READ_PAGE_SIZE: usize = 4096;
+
+/// The rows of the results of a select, read from a reader one page at a time as they're written
+/// to the client
+pub struct ResultPages(BoxStream<'static, ReadySetResult<Vec<Vec<DfValue>>>>);
+
+impl fmt::Debug for ResultPages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResultPages").finish_non_exhaustive()
+    }
+}
+
+impl Stream for ResultPages {
+    type Item = ReadySetResult<Vec<Vec<DfValue>>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.poll_next_unpin(cx)
+    }
+}
+
 #[derive(Debug)]
 #[allow(clippy::large_enum_variant)]
 pub enum QueryResult<'a> {
@@ -222,6 +254,12 @@ pub enum QueryResult<'a> {
         rows: ResultIterator,
         schema: SelectSchema<'a>,
     },
+    /// The results of a select read from a reader over the network, whose pages of rows are only
+    /// read as they're written to the client
+    SelectPages {
+        pages: ResultPages,
+        schema: SelectSchema<'a>,
+    },
     Update {
         num_rows_updated: u64,
         last_inserted_id: u64,
@@ -266,6 +304,10 @@ impl<'a> QueryResult<'a> {
                 schema: schema.into_owned(),
                 rows,
             },
+            QueryResult::SelectPages { schema, pages } => QueryResult::SelectPages {
+                schema: schema.into_owned(),
+                pages,
+            },
             // Have to manually pass each variant to convince rustc that the
             // returned type is really owned
             QueryResult::Empty => QueryResult::Empty,
@@ -377,6 +419,9 @@ impl ViewCache {
     }
 }
 
+/// The number of most frequently read keys of each cache to show in `SHOW READYSET CACHE STATS`
+const CACHE_STATS_MAX_KEYS: usize = 10;
+
 pub struct NoriaConnector {
     inner: NoriaBackend,
     auto_increments: Arc<RwLock<HashMap<Relation, atomic::AtomicUsize>>>,
@@ -409,6 +454,18 @@ pub struct NoriaConnector {
     /// supports a multi-element schema search path, the concept of "currently connected database"
     /// in MySQL can be thought of as a schema search path that only has one element.
     schema_search_path: Vec<SqlIdentifier>,
+
+    /// If set, the keys each cache is read with are recorded here, so that the caches can be
+    /// warmed up with them after a restart. See [`crate::cache_warmer`].
+    key_statistics: Option<KeyStatistics>,
+
+    /// If set, comparisons with `NOW()` in cached queries are replaced with parameters bound to
+    /// the current time rounded down to this granularity. See [`rewrite::parametrize_now`].
+    now_parameter_granularity: Option<Duration>,
+
+    /// The offset from UTC of the time zone of the client's session on the upstream database,
+    /// which the current time bound to parameters replacing `NOW()` is given in
+    now_utc_offset: FixedOffset,
 }
 
 mod request_handler {
@@ -523,9 +580,25 @@ impl NoriaConnector {
             dialect,
             parse_dialect,
             schema_search_path,
+            key_statistics: None,
+            now_parameter_granularity: None,
+            now_utc_offset: FixedOffset::east(0),
         }
     }
 
+    /// Record the keys each cache is read with in the given [`KeyStatistics`]
+    pub fn with_key_statistics(mut self, key_statistics: Option<KeyStatistics>) -> Self {
+        self.key_statistics = key_statistics;
+        self
+    }
+
+    /// Cache queries comparing columns with `NOW()` by binding `NOW()` to the current time rounded
+    /// down to the given granularity, rather than proxying them
+    pub fn with_now_parameter_granularity(mut self, granularity: Option<Duration>) -> Self {
+        self.now_parameter_granularity = granularity;
+        self
+    }
+
     pub(crate) async fn graphviz(
         &mut self,
         simplified: bool,
@@ -609,6 +682,67 @@ impl NoriaConnector {
         ))
     }
 
+    /// Returns the `CREATE CACHE` statements for all caches, one per row, in a form that can be
+    /// executed as-is (for example after being saved to a file that's later passed to
+    /// `--cache-statements-file`)
+    pub(crate) async fn create_cache_statements(&mut self) -> ReadySetResult<QueryResult<'static>> {
+        let statements = noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.cache_statements()
+        )?;
+
+        let select_schema = SelectSchema {
+            use_bogo: false,
+            schema: Cow::Owned(vec![ColumnSchema {
+                column: nom_sql::Column {
+                    name: "statement".into(),
+                    table: None,
+                },
+                column_type: DfType::DEFAULT_TEXT,
+                base: None,
+            }]),
+            columns: Cow::Owned(vec!["statement".into()]),
+        };
+        let data = statements
+            .into_iter()
+            .map(|stmt| {
+                vec![DfValue::from(format!(
+                    "{};",
+                    stmt.display(self.parse_dialect)
+                ))]
+            })
+            .collect::<Vec<_>>();
+        Ok(QueryResult::from_owned(
+            select_schema,
+            vec![Results::new(data)],
+        ))
+    }
+
+    /// Discard all statements prepared on this connection
+    pub(crate) fn clear_prepared_statements(&mut self) {
+        self.prepared_statement_cache.clear();
+    }
+
+    /// Discard the statement prepared on this connection with the given id
+    pub(crate) fn remove_prepared_statement(&mut self, statement_id: StatementID) {
+        self.prepared_statement_cache.remove(&statement_id);
+    }
+
+    pub(crate) fn now_parameter_granularity(&self) -> Option<Duration> {
+        self.now_parameter_granularity
+    }
+
+    /// Change the granularity of the current time bound to `NOW()` in cached queries for this
+    /// connection, or stop caching queries comparing columns with `NOW()` if `None`. See
+    /// [`NoriaConnector::with_now_parameter_granularity`].
+    pub(crate) fn set_now_parameter_granularity(&mut self, granularity: Option<Duration>) {
+        self.now_parameter_granularity = granularity;
+    }
+
+    pub(crate) fn set_now_utc_offset(&mut self, offset: FixedOffset) {
+        self.now_utc_offset = offset;
+    }
+
     pub(crate) fn server_supports_pagination(&self) -> bool {
         self.inner
             .inner
@@ -1011,6 +1145,146 @@ impl NoriaConnector {
         Ok(QueryResult::from_owned(schema, vec![Results::new(data)]))
     }
 
+    /// Returns the replication offsets of the schema and of each table
+    pub(crate) async fn replication_offsets(&mut self) -> ReadySetResult<ReplicationOffsets> {
+        noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.replication_offsets()
+        )
+    }
+
+    pub(crate) async fn cache_stats(&mut self) -> ReadySetResult<QueryResult<'static>> {
+        let stats = noria_await!(
+            self.inner.get_mut()?,
+            self.inner
+                .get_mut()?
+                .noria
+                .cache_stats(CACHE_STATS_MAX_KEYS)
+        )?;
+
+        let columns = [
+            ("name", DfType::DEFAULT_TEXT),
+            ("hits", DfType::UnsignedBigInt),
+            ("misses", DfType::UnsignedBigInt),
+            ("evictions", DfType::UnsignedBigInt),
+            ("evicted keys", DfType::UnsignedBigInt),
+            ("evicted bytes", DfType::UnsignedBigInt),
+            ("most read keys", DfType::DEFAULT_TEXT),
+        ];
+        let schema = SelectSchema {
+            use_bogo: false,
+            schema: Cow::Owned(
+                columns
+                    .iter()
+                    .map(|(name, column_type)| ColumnSchema {
+                        column: nom_sql::Column {
+                            name: (*name).into(),
+                            table: None,
+                        },
+                        column_type: column_type.clone(),
+                        base: None,
+                    })
+                    .collect(),
+            ),
+            columns: Cow::Owned(columns.iter().map(|(name, _)| (*name).into()).collect()),
+        };
+
+        let data = stats
+            .into_iter()
+            .map(|(cache, stats)| {
+                let most_read_keys = stats
+                    .keys
+                    .iter()
+                    .map(|key| {
+                        format!(
+                            "({}): {} hits, {} misses, {} evictions",
+                            key.key.iter().join(", "),
+                            key.hits,
+                            key.misses,
+                            key.evictions
+                        )
+                    })
+                    .join("; ");
+                vec![
+                    cache.display(self.parse_dialect).to_string().into(),
+                    stats.hits.into(),
+                    stats.misses.into(),
+                    stats.evictions.into(),
+                    stats.evicted_keys.into(),
+                    stats.evicted_bytes.into(),
+                    most_read_keys.into(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        Ok(QueryResult::from_owned(schema, vec![Results::new(data)]))
+    }
+
+    /// Returns the memory used by each cache, followed by rows for the totals and the configured
+    /// memory budget and cache quota. Columns that don't apply to a row are NULL.
+    pub(crate) async fn memory_usage(&mut self) -> ReadySetResult<QueryResult<'static>> {
+        let usage = noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.memory_usage()
+        )?;
+
+        let columns = [
+            ("name", DfType::DEFAULT_TEXT),
+            ("reader bytes", DfType::UnsignedBigInt),
+            ("state bytes", DfType::UnsignedBigInt),
+            ("total bytes", DfType::UnsignedBigInt),
+        ];
+        let schema = SelectSchema {
+            use_bogo: false,
+            schema: Cow::Owned(
+                columns
+                    .iter()
+                    .map(|(name, column_type)| ColumnSchema {
+                        column: nom_sql::Column {
+                            name: (*name).into(),
+                            table: None,
+                        },
+                        column_type: column_type.clone(),
+                        base: None,
+                    })
+                    .collect(),
+            ),
+            columns: Cow::Owned(columns.iter().map(|(name, _)| (*name).into()).collect()),
+        };
+
+        let total_row = |name: &str, total: Option<u64>| {
+            vec![
+                name.into(),
+                DfValue::None,
+                DfValue::None,
+                total.map(DfValue::from).unwrap_or(DfValue::None),
+            ]
+        };
+        let mut data = usage
+            .caches
+            .iter()
+            .map(|(cache, cache_usage)| {
+                vec![
+                    cache.display(self.parse_dialect).to_string().into(),
+                    cache_usage.reader_bytes.into(),
+                    cache_usage.state_bytes.into(),
+                    cache_usage.total_bytes().into(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        data.push(vec![
+            "All caches".into(),
+            usage.reader_bytes.into(),
+            usage.state_bytes.into(),
+            usage.in_memory_bytes().into(),
+        ]);
+        data.push(total_row("Base tables", Some(usage.base_table_bytes)));
+        data.push(total_row("Memory budget", usage.memory_budget));
+        data.push(total_row("Cache memory quota", usage.cache_memory_quota));
+
+        Ok(QueryResult::from_owned(schema, vec![Results::new(data)]))
+    }
+
     /// Set the schema search path
     pub fn set_schema_search_path(&mut self, search_path: Vec<SqlIdentifier>) {
         self.schema_search_path = search_path;
@@ -1151,6 +1425,50 @@ impl NoriaConnector {
         Ok(())
     }
 
+    /// Make a request to ReadySet to drop and re-create all cached queries. Since the names of the
+    /// caches don't change, only the view handles we have for them are invalidated.
+    pub async fn restore_all_caches(&mut self) -> ReadySetResult<()> {
+        let dialect = self.dialect;
+        noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.restore_all_queries(dialect)
+        )?;
+        self.inner.get_mut()?.views.clear();
+        Ok(())
+    }
+
+    /// Make a request to ReadySet to drop the rows of `table` and copy them from the upstream
+    /// database again. Tables named without a schema are looked up in the first schema in the
+    /// schema search path.
+    pub async fn resnapshot_table(&mut self, table: &Relation) -> ReadySetResult<()> {
+        let mut table = table.clone();
+        if table.schema.is_none() {
+            table.schema = Some(
+                self.schema_search_path
+                    .first()
+                    .ok_or_else(|| ReadySetError::TableNotFound {
+                        name: table.name.to_string(),
+                        schema: None,
+                    })?
+                    .clone(),
+            );
+        }
+        noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.resnapshot_table(&table)
+        )
+    }
+
+    /// Make a request to ReadySet to log every write and replay to the readers of the cache for the
+    /// query with the given id for `duration`. Returns `false` if the query isn't cached.
+    pub async fn trace_query(&mut self, id: QueryId, duration: Duration) -> ReadySetResult<bool> {
+        let name = Relation::from(id.to_string());
+        noria_await!(
+            self.inner.get_mut()?,
+            self.inner.get_mut()?.noria.trace_reader(&name, duration)
+        )
+    }
+
     pub fn view_create_request_from_name(&self, name: &Relation) -> Option<ViewCreateRequest> {
         self.view_cache.view_create_request_from_name(name)
     }
@@ -1436,8 +1754,11 @@ impl NoriaConnector {
             .collect();
 
         trace!("select::collapse where-in clauses");
-        let processed_query_params =
-            rewrite::process_query(&mut statement, self.server_supports_pagination())?;
+        let processed_query_params = rewrite::process_query(
+            &mut statement,
+            self.server_supports_pagination(),
+            self.now_parameter_granularity(),
+        )?;
 
         // check if we already have this query prepared
         trace!("select::access view");
@@ -1533,8 +1854,11 @@ impl NoriaConnector {
                 create_if_missing,
             } => {
                 verify_no_placeholders(&statement)?;
-                let processed_query_params =
-                    rewrite::process_query(&mut statement, self.server_supports_pagination())?;
+                let processed_query_params = rewrite::process_query(
+                    &mut statement,
+                    self.server_supports_pagination(),
+                    self.now_parameter_granularity(),
+                )?;
                 let name = self
                     .get_view(&statement, false, create_if_missing, None)
                     .await?;
@@ -1562,12 +1886,23 @@ impl NoriaConnector {
             self.read_request_handler.as_mut(),
             event,
             self.dialect,
+            self.now_utc_offset,
         )
         .await;
 
-        if let Err(e) = res.as_ref() {
-            if e.is_networking_related() || e.caused_by_view_destroyed() {
-                self.failed_views.insert(qname.into_owned());
+        match res.as_ref() {
+            Ok(_) => {
+                if let Some(key_statistics) = &self.key_statistics {
+                    if let Ok(keys) = processed_query_params.make_keys(params, self.now_utc_offset)
+                    {
+                        key_statistics.record(&qname, keys.iter().map(|key| key.as_ref()));
+                    }
+                }
+            }
+            Err(e) => {
+                if e.is_networking_related() || e.caused_by_view_destroyed() {
+                    self.failed_views.insert(qname.into_owned());
+                }
             }
         }
 
@@ -1622,9 +1957,10 @@ fn build_view_query<'a>(
     ticket: Option<Timestamp>,
     read_behavior: ReadBehavior,
     dialect: Dialect,
+    now_utc_offset: FixedOffset,
 ) -> ReadySetResult<Option<(&'a mut ReaderHandle, ViewQuery)>> {
     let (limit, offset) = processed_query_params.limit_offset_params(params)?;
-    let raw_keys = processed_query_params.make_keys(params)?;
+    let raw_keys = processed_query_params.make_keys(params, now_utc_offset)?;
 
     getter.build_view_query(
         raw_keys,
@@ -1649,6 +1985,7 @@ async fn do_read<'a>(
     read_request_handler: Option<&'a mut ReadRequestHandler>,
     event: &mut readyset_client_metrics::QueryExecutionEvent,
     dialect: Dialect,
+    now_utc_offset: FixedOffset,
 ) -> ReadySetResult<QueryResult<'a>> {
     let (reader_handle, vq) = match build_view_query(
         getter,
@@ -1657,14 +1994,37 @@ async fn do_read<'a>(
         ticket,
         read_behavior,
         dialect,
+        now_utc_offset,
     )? {
         Some(res) => res,
         None => return Err(ReadySetError::NoCacheForQuery),
     };
 
     event.num_keys = Some(vq.key_comparisons.len() as _);
+    event.index_type = reader_handle.index_type();
+
+    let Some(rh) = read_request_handler else {
+        // Read the results over the network one page at a time, so that they don't all have to be
+        // held in memory at once. The first page is read before returning, so that misses and
+        // errors can still fall back to the upstream database.
+        #[allow(clippy::unwrap_used)] // READ_PAGE_SIZE is nonzero
+        let page_size = NonZeroUsize::new(READ_PAGE_SIZE).unwrap();
+        let mut pages = reader_handle.raw_lookup_stream(vq, page_size).boxed();
+        let first_page = pages.try_next().await?.unwrap_or_default();
+
+        trace!("select::complete");
+
+        return Ok(QueryResult::SelectPages {
+            pages: ResultPages(
+                stream::once(future::ready(Ok(first_page)))
+                    .chain(pages)
+                    .boxed(),
+            ),
+            schema: select_schema(reader_handle),
+        });
+    };
 
-    let data = if let Some(rh) = read_request_handler {
+    let data = {
         let request = readyset_client::Tagged::from(ReadQuery::Normal {
             target: ReaderAddress {
                 node: *reader_handle.node(),
@@ -1697,28 +2057,29 @@ async fn do_read<'a>(
         } else {
             reader_handle.raw_lookup(vq).await?
         }
-    } else {
-        reader_handle.raw_lookup(vq).await?
     };
 
     event.cache_misses = data.total_stats().map(|s| s.cache_misses);
 
     trace!("select::complete");
 
-    Ok(QueryResult::from_iter(
-        SelectSchema {
-            // TODO(vlad): looks like poor `use_bogo` is unused except in js? Should just remove it.
-            use_bogo: false,
-            schema: Cow::Borrowed(
-                reader_handle
-                    .schema()
-                    .unwrap()
-                    .schema(SchemaType::ReturnedSchema),
-            ), /* Safe because we already unwrapped above */
-            columns: Cow::Borrowed(reader_handle.columns()),
-        },
-        data,
-    ))
+    Ok(QueryResult::from_iter(select_schema(reader_handle), data))
+}
+
+/// The schema of the results of a select read from `reader_handle`, which must have been checked
+/// to have one when building the view query
+fn select_schema(reader_handle: &ReaderHandle) -> SelectSchema<'_> {
+    SelectSchema {
+        // TODO(vlad): looks like poor `use_bogo` is unused except in js? Should just remove it.
+        use_bogo: false,
+        schema: Cow::Borrowed(
+            reader_handle
+                .schema()
+                .unwrap()
+                .schema(SchemaType::ReturnedSchema),
+        ),
+        columns: Cow::Borrowed(reader_handle.columns()),
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,308 @@
+//! Warm-up of caches after the adapter starts, or a cache is created, by executing each cache for
+//! the keys it was most recently read with.
+//!
+//! [`KeyStatistics`] records the keys each cache is read with, and can optionally be persisted to
+//! a file so that it survives restarts. The [`CacheWarmer`] polls the controller for caches whose
+//! reader it hasn't seen yet, and reads each of them with their recorded keys, so that the first
+//! requests for those keys don't all pay the latency of an upquery at the same time.
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use nom_sql::Relation;
+use readyset_client::ReadySetHandle;
+use readyset_data::{DfValue, Dialect};
+use readyset_util::shutdown::ShutdownReceiver;
+use tokio::io::AsyncWriteExt;
+use tokio::select;
+use tracing::{debug, info, instrument, warn};
+
+/// The keys each cache was most recently read with, shared between all the connections to the
+/// adapter.
+///
+/// Cloning a [`KeyStatistics`] returns a handle to the same statistics. The keys of each cache are
+/// kept in a separate shard of the map, so connections reading different caches don't contend
+/// with each other.
+///
+/// The keys are the parameter values of the queries run against each cache, so they're only
+/// written to disk if the operator asks for them to be (with [`KeyStatistics::save`]). Keys can't
+/// be hashed or redacted in the saved file, since warming up a cache after a restart needs the
+/// values themselves, so the file is created readable only by its owner.
+#[derive(Clone)]
+pub struct KeyStatistics {
+    /// The most recently read keys of each cache, most recent first
+    keys: Arc<DashMap<Relation, VecDeque<Vec<DfValue>>>>,
+    /// The maximum number of keys to remember for each cache
+    max_keys_per_cache: usize,
+}
+
+impl KeyStatistics {
+    /// Create a new, empty, [`KeyStatistics`] which remembers up to `max_keys_per_cache` keys for
+    /// each cache
+    pub fn new(max_keys_per_cache: usize) -> Self {
+        Self {
+            keys: Default::default(),
+            max_keys_per_cache,
+        }
+    }
+
+    /// Load the statistics saved to `path` by [`KeyStatistics::save`], if it exists. Caches which
+    /// already have keys recorded keep them rather than the saved ones.
+    pub async fn load(&self, path: &Path) -> io::Result<()> {
+        let contents = match tokio::fs::read(path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let saved: Vec<(Relation, Vec<Vec<DfValue>>)> = serde_json::from_slice(&contents)?;
+        for (cache, cache_keys) in saved {
+            self.keys.entry(cache).or_insert_with(|| {
+                cache_keys
+                    .into_iter()
+                    .take(self.max_keys_per_cache)
+                    .collect()
+            });
+        }
+        Ok(())
+    }
+
+    /// Save the statistics to `path`, replacing its contents atomically. The file is only
+    /// readable by its owner, since it contains the parameters of queries.
+    pub async fn save(&self, path: &Path) -> io::Result<()> {
+        let saved = self
+            .keys
+            .iter()
+            .map(|entry| {
+                (
+                    entry.key().clone(),
+                    entry.value().iter().cloned().collect::<Vec<_>>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let contents = serde_json::to_vec(&saved)?;
+
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options.open(&tmp_path).await?;
+        file.write_all(&contents).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp_path, path).await
+    }
+
+    /// Record that `cache` was read with the given keys
+    pub fn record<'a, I>(&self, cache: &Relation, keys: I)
+    where
+        I: IntoIterator<Item = &'a [DfValue]>,
+    {
+        if self.max_keys_per_cache == 0 {
+            return;
+        }
+
+        let mut cache_keys = self.keys.entry(cache.clone()).or_default();
+        for key in keys {
+            if let Some(pos) = cache_keys.iter().position(|k| k.as_slice() == key) {
+                if let Some(key) = cache_keys.remove(pos) {
+                    cache_keys.push_front(key);
+                }
+            } else {
+                if cache_keys.len() >= self.max_keys_per_cache {
+                    cache_keys.pop_back();
+                }
+                cache_keys.push_front(key.to_vec());
+            }
+        }
+    }
+
+    /// Returns the keys `cache` was most recently read with, most recent first
+    pub fn keys(&self, cache: &Relation) -> Vec<Vec<DfValue>> {
+        self.keys
+            .get(cache)
+            .map(|keys| keys.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Polls the controller for caches that haven't been warmed up yet, and reads each of them with the
+/// keys recorded in [`KeyStatistics`]
+pub struct CacheWarmer {
+    /// The handle to the controller used to list and read caches
+    controller: ReadySetHandle,
+    /// The keys to warm each cache up with
+    key_statistics: KeyStatistics,
+    /// The file to load [`Self::key_statistics`] from on startup and save it to after each poll,
+    /// if the keys should persist across restarts
+    path: Option<PathBuf>,
+    /// The interval between subsequent polls of the controller for caches
+    poll_interval: Duration,
+    /// Dialect to use to coerce keys to the types of each cache's key columns
+    dialect: Dialect,
+    /// The index of the reader node of each cache that we've already warmed up. A cache whose
+    /// reader node changes (such as because it was dropped and created again) is warmed up again.
+    warmed: HashMap<Relation, usize>,
+    /// Receiver to return the shutdown signal on
+    shutdown_recv: ShutdownReceiver,
+}
+
+impl CacheWarmer {
+    pub fn new(
+        controller: ReadySetHandle,
+        key_statistics: KeyStatistics,
+        path: Option<PathBuf>,
+        poll_interval: Duration,
+        dialect: Dialect,
+        shutdown_recv: ShutdownReceiver,
+    ) -> Self {
+        Self {
+            controller,
+            key_statistics,
+            path,
+            poll_interval,
+            dialect,
+            warmed: HashMap::new(),
+            shutdown_recv,
+        }
+    }
+
+    #[instrument(level = "info", name = "cache_warmer", skip(self))]
+    pub async fn run(&mut self) {
+        if let Some(path) = &self.path {
+            if let Err(error) = self.key_statistics.load(path).await {
+                warn!(
+                    %error,
+                    path = %path.display(),
+                    "Could not load cache key statistics; caches will only be warmed up with \
+                     keys read since startup"
+                );
+            }
+        }
+
+        let mut interval = tokio::time::interval(self.poll_interval);
+        loop {
+            select! {
+                // See `ViewsSynchronizer::run` for why this is biased
+                biased;
+                _ = self.shutdown_recv.recv() => {
+                    info!("Cache warmer shutting down after shut down signal received");
+                    break;
+                }
+                _ = interval.tick() => self.poll().await,
+            }
+        }
+
+        self.save().await;
+    }
+
+    async fn poll(&mut self) {
+        match self.controller.views().await {
+            Ok(views) => {
+                for (cache, reader) in &views {
+                    if self.warmed.get(cache) == Some(&reader.index()) {
+                        continue;
+                    }
+                    self.warm(cache).await;
+                    self.warmed.insert(cache.clone(), reader.index());
+                }
+                self.warmed.retain(|cache, _| views.contains_key(cache));
+            }
+            Err(error) => debug!(%error, "Could not list caches to warm up"),
+        }
+
+        self.save().await;
+    }
+
+    /// Save the key statistics to [`Self::path`], if set
+    async fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(error) = self.key_statistics.save(path).await {
+                warn!(%error, path = %path.display(), "Could not save cache key statistics");
+            }
+        }
+    }
+
+    /// Read `cache` with each of the keys it was most recently read with, waiting for the results
+    /// to be filled in
+    async fn warm(&mut self, cache: &Relation) {
+        let keys = self.key_statistics.keys(cache);
+        if keys.is_empty() {
+            return;
+        }
+
+        let num_keys = keys.len();
+        let result = async {
+            let mut view = self.controller.view(cache.clone()).await?;
+            let raw_keys = keys.into_iter().map(Cow::Owned).collect();
+            match view.build_view_query(raw_keys, None, None, None, true, self.dialect)? {
+                Some((handle, query)) => handle.raw_lookup(query).await.map(|_| ()),
+                None => Ok(()),
+            }
+        }
+        .await;
+
+        match result {
+            Ok(()) => info!(cache = %cache.display_unquoted(), num_keys, "Warmed up cache"),
+            Err(error) => {
+                warn!(%error, cache = %cache.display_unquoted(), "Could not warm up cache")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_most_recent_keys() {
+        let statistics = KeyStatistics::new(2);
+        let cache = Relation::from("q");
+        statistics.record(&cache, [&[DfValue::from(1)][..]]);
+        statistics.record(&cache, [&[DfValue::from(2)][..], &[DfValue::from(1)][..]]);
+        assert_eq!(
+            statistics.keys(&cache),
+            vec![vec![DfValue::from(1)], vec![DfValue::from(2)]]
+        );
+
+        statistics.record(&cache, [&[DfValue::from(3)][..]]);
+        assert_eq!(
+            statistics.keys(&cache),
+            vec![vec![DfValue::from(3)], vec![DfValue::from(1)]]
+        );
+        assert!(statistics.keys(&"other".into()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key_statistics.json");
+        let empty = KeyStatistics::new(10);
+        empty.load(&path).await.unwrap();
+        assert!(empty.keys(&"q".into()).is_empty());
+
+        let statistics = KeyStatistics::new(10);
+        let key = vec![DfValue::from(1), DfValue::from("a")];
+        statistics.record(&"q".into(), [key.as_slice()]);
+        statistics.save(&path).await.unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let loaded = KeyStatistics::new(10);
+        loaded.record(&"r".into(), [&[DfValue::from(2)][..]]);
+        loaded.load(&path).await.unwrap();
+        assert_eq!(loaded.keys(&"q".into()), vec![key]);
+        assert_eq!(loaded.keys(&"r".into()), vec![vec![DfValue::from(2)]]);
+    }
+}
@@ -1,9 +1,11 @@
+use std::collections::BTreeMap;
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
 
 use anyhow::anyhow;
 use futures::TryFutureExt;
@@ -13,8 +15,12 @@ use hyper::service::make_service_fn;
 use hyper::{self, Body, Method, Request, Response};
 use metrics_exporter_prometheus::PrometheusHandle;
 use readyset_client::query::DeniedQuery;
+use readyset_client::replication::ReplicationSourceHealth;
+use readyset_client::status::SnapshotStatus;
+use readyset_client::ReadySetHandle;
 use readyset_client_metrics::recorded;
 use readyset_sql_passes::anonymize::Anonymizer;
+use readyset_tracing::statement_logging::{self, Component};
 use readyset_util::shutdown::ShutdownReceiver;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::Sender;
@@ -43,6 +49,13 @@ pub struct NoriaAdapterHttpRouter {
     /// Used to retrieve the prometheus scrape's render as a String when servicing
     /// HTTP requests on /metrics.
     pub prometheus_handle: Option<PrometheusHandle>,
+
+    /// Used to check whether the ReadySet server has finished snapshotting and is replicating
+    /// when servicing HTTP requests on /readyz.
+    pub controller: ReadySetHandle,
+    /// The maximum replication lag for the adapter to be considered ready on /readyz, or `None`
+    /// to not check the lag.
+    pub max_replication_lag: Option<Duration>,
 }
 
 impl NoriaAdapterHttpRouter {
@@ -112,6 +125,56 @@ impl Service<Request<Body>> for NoriaAdapterHttpRouter {
     ///
     ///   `curl -X GET <adapter>:<adapter-port>/health`
     ///
+    /// ## Liveness Check
+    ///
+    /// Return 200 code as long as the adapter process is running, for use as a Kubernetes liveness
+    /// probe.
+    ///
+    /// * **URL**
+    ///
+    ///   `/healthz`
+    ///
+    /// * **Method:**
+    ///
+    ///   `GET`
+    ///
+    /// * **Success Response:**
+    ///
+    ///     * **Code:** 200 <br /> **Content:** `"ok"`
+    ///
+    /// * **Sample Call:**
+    ///
+    ///   `curl -X GET <adapter>:<adapter-port>/healthz`
+    ///
+    /// ## Readiness Check
+    ///
+    /// Whether the adapter is ready to serve traffic, for use as a Kubernetes readiness probe. The
+    /// adapter is ready once it's listening for connections, the ReadySet server has finished
+    /// snapshotting, the replicator is connected to each upstream database, and replication is no
+    /// further behind each upstream database than `--readiness-max-replication-lag-secs`, and has
+    /// received an event from it in that time.
+    ///
+    /// * **URL**
+    ///
+    ///   `/readyz`
+    ///
+    /// * **Method:**
+    ///
+    ///   `GET`
+    ///
+    /// * **Success Response:**
+    ///
+    ///     * **Code:** 200 <br /> **Content:** `"ready"`
+    ///
+    /// * **Error Response:**
+    ///
+    ///     * **Code:** 503 Service Unavailable <br /> **Content:** Each check that failed, one per
+    ///       line, such as `"snapshot is in progress"`
+    ///
+    /// * **Sample Call:**
+    ///
+    ///   `curl -X GET <adapter>:<adapter-port>/readyz`
+    ///
     /// ## Allow List
     ///
     /// List of SQL queries that will be handled by ReadySet as opposed to being passed through to
@@ -168,6 +231,45 @@ impl Service<Request<Body>> for NoriaAdapterHttpRouter {
     ///
     ///   `curl -X GET <adapter>:<adapter-port>/deny-list`
     ///
+    /// ## Statement Logging
+    ///
+    /// Whether each component of ReadySet (`replicator`, `mysql_frontend` and
+    /// `postgresql_frontend`) is logging the statements it receives. A `POST` with a JSON object
+    /// mapping components to booleans enables or disables statement logging for those components,
+    /// leaving the others unchanged.
+    ///
+    /// The replicator runs in the process of the ReadySet server that's the leader, so its status
+    /// is read and changed via the controller rather than in the adapter's process.
+    ///
+    /// * **URL**
+    ///
+    ///   `/statement-logging`
+    ///
+    /// * **Method:**
+    ///
+    ///   `GET` | `POST`
+    ///
+    /// * **Success Response:**
+    ///
+    ///   Whether each component is logging statements, as a JSON Object.
+    ///
+    ///     * **Code:** 200 <br /> **Content:** `{ "replicator": false, "mysql_frontend": true,
+    ///       "postgresql_frontend": false }`
+    ///
+    /// * **Error Response:**
+    ///
+    ///     * **Code:** 400 Bad Request <br /> **Content:** `"body must be a JSON object mapping
+    ///       components to booleans"`
+    ///
+    ///   OR
+    ///
+    ///     * **Code:** 503 Service Unavailable <br /> **Content:** `"Could not reach the
+    ///       controller: <error>"`
+    ///
+    /// * **Sample Call:**
+    ///
+    ///   `curl -X POST <adapter>:<adapter-port>/statement-logging -d '{"mysql_frontend": true}'`
+    ///
     /// ## Prometheus
     ///
     /// Endpoint for Prometheus metric API calls.
@@ -284,6 +386,33 @@ impl Service<Request<Body>> for NoriaAdapterHttpRouter {
                     Ok(res.unwrap())
                 })
             }
+            (&Method::GET, "/statement-logging") => {
+                let controller = self.controller.clone();
+                Box::pin(async move {
+                    Ok(statement_logging_response(res, controller, None)
+                        .await
+                        .unwrap())
+                })
+            }
+            (&Method::POST, "/statement-logging") => {
+                let controller = self.controller.clone();
+                Box::pin(async move {
+                    let body = hyper::body::to_bytes(req.into_body()).await?;
+                    let res = match serde_json::from_slice::<BTreeMap<Component, bool>>(&body) {
+                        Ok(mut changes) => {
+                            let replicator = changes.remove(&Component::Replicator);
+                            statement_logging::update(&changes);
+                            statement_logging_response(res, controller, replicator).await
+                        }
+                        Err(_) => res.status(400).header(CONTENT_TYPE, "text/plain").body(
+                            hyper::Body::from(
+                                "body must be a JSON object mapping components to booleans",
+                            ),
+                        ),
+                    };
+                    Ok(res.unwrap())
+                })
+            }
             (&Method::GET, "/health") => {
                 let state = self.health_reporter.health().state;
                 Box::pin(async move {
@@ -302,6 +431,29 @@ impl Service<Request<Body>> for NoriaAdapterHttpRouter {
                     Ok(res.unwrap())
                 })
             }
+            (&Method::GET, "/healthz") => Box::pin(async move {
+                Ok(res
+                    .status(200)
+                    .header(CONTENT_TYPE, "text/plain")
+                    .body(hyper::Body::from("ok"))
+                    .unwrap())
+            }),
+            (&Method::GET, "/readyz") => {
+                let health_reporter = self.health_reporter.clone();
+                let controller = self.controller.clone();
+                let max_replication_lag = self.max_replication_lag;
+                Box::pin(async move {
+                    let failures =
+                        readiness_failures(&health_reporter, controller, max_replication_lag).await;
+                    let res = res.header(CONTENT_TYPE, "text/plain");
+                    let res = if failures.is_empty() {
+                        res.status(200).body(hyper::Body::from("ready"))
+                    } else {
+                        res.status(503).body(hyper::Body::from(failures.join("\n")))
+                    };
+                    Ok(res.unwrap())
+                })
+            }
             (&Method::GET, "/metrics") => {
                 let body = self.prometheus_handle.as_ref().map(|x| x.render());
                 let res = res.header(CONTENT_TYPE, "text/plain");
@@ -324,3 +476,165 @@ impl Service<Request<Body>> for NoriaAdapterHttpRouter {
         }
     }
 }
+
+/// Respond with whether each component is logging statements, after enabling or disabling
+/// statement logging for the replicator via the controller if `replicator` is `Some`
+async fn statement_logging_response(
+    res: hyper::http::response::Builder,
+    mut controller: ReadySetHandle,
+    replicator: Option<bool>,
+) -> hyper::http::Result<Response<Body>> {
+    let mut statuses = statement_logging::statuses();
+    match controller.replicator_statement_logging(replicator).await {
+        Ok(enabled) => {
+            statuses.insert(Component::Replicator, enabled);
+        }
+        Err(error) => {
+            return res
+                .status(503)
+                .header(CONTENT_TYPE, "text/plain")
+                .body(hyper::Body::from(format!(
+                    "Could not reach the controller: {error}"
+                )))
+        }
+    }
+    // Serializing a map of unit enum variants to booleans can't fail
+    let json = serde_json::to_string(&statuses).unwrap();
+    res.header(CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(json))
+}
+
+/// Returns a description of each reason the adapter isn't ready to serve traffic, which is empty
+/// if the adapter is ready
+async fn readiness_failures(
+    health_reporter: &AdapterHealthReporter,
+    mut controller: ReadySetHandle,
+    max_replication_lag: Option<Duration>,
+) -> Vec<String> {
+    let mut failures = vec![];
+
+    let state = health_reporter.health().state;
+    if state != State::Healthy {
+        failures.push(format!("adapter is in {state} state"));
+    }
+
+    match controller.status().await {
+        Ok(status) if status.snapshot_status == SnapshotStatus::Completed => {}
+        Ok(_) => failures.push("snapshot is in progress".to_owned()),
+        Err(error) => failures.push(format!("could not get status of ReadySet server: {error}")),
+    }
+
+    match controller.replication_health().await {
+        Ok(sources) => {
+            let now = SystemTime::now();
+            failures.extend(sources.into_iter().filter_map(|(source, health)| {
+                replication_failure(source.as_deref(), &health, max_replication_lag, now)
+            }));
+        }
+        Err(error) => failures.push(format!("could not get replication health: {error}")),
+    }
+
+    failures
+}
+
+/// Returns why replication from `source` keeps the adapter from being ready as of `now`, if it
+/// does. Besides checking the lag as of the last replication event, replication is considered
+/// behind if no events, including the heartbeats upstream databases send when they have no new
+/// writes, have been received for longer than `max_replication_lag`, since the lag we last saw
+/// doesn't change if replication stalls.
+fn replication_failure(
+    source: Option<&str>,
+    health: &ReplicationSourceHealth,
+    max_replication_lag: Option<Duration>,
+    now: SystemTime,
+) -> Option<String> {
+    let source = match source {
+        Some(name) => format!("upstream database {name}"),
+        None => "upstream database".to_owned(),
+    };
+    if !health.connected {
+        return Some(format!("replication from {source} is not connected"));
+    }
+    let max = max_replication_lag?;
+    if let Some(lag) = health.lag.filter(|lag| *lag > max) {
+        return Some(format!(
+            "replication from {source} is {}s behind, more than the maximum of {}s",
+            lag.as_secs(),
+            max.as_secs()
+        ));
+    }
+    let since_last_event = health
+        .last_event
+        .and_then(|last_event| now.duration_since(last_event).ok())
+        .unwrap_or_default();
+    (since_last_event > max).then(|| {
+        format!(
+            "no replication events received from {source} in {}s, more than the maximum lag of \
+             {}s",
+            since_last_event.as_secs(),
+            max.as_secs()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_LAG: Option<Duration> = Some(Duration::from_secs(30));
+
+    fn health(lag: u64, since_last_event: u64, now: SystemTime) -> ReplicationSourceHealth {
+        ReplicationSourceHealth {
+            connected: true,
+            lag: Some(Duration::from_secs(lag)),
+            last_event: Some(now - Duration::from_secs(since_last_event)),
+        }
+    }
+
+    #[test]
+    fn replication_ready_when_caught_up() {
+        let now = SystemTime::now();
+        assert_eq!(
+            replication_failure(None, &health(1, 1, now), MAX_LAG, now),
+            None
+        );
+    }
+
+    #[test]
+    fn replication_not_ready_when_disconnected() {
+        let now = SystemTime::now();
+        let health = ReplicationSourceHealth {
+            connected: false,
+            ..health(0, 0, now)
+        };
+        assert_eq!(
+            replication_failure(Some("replica"), &health, MAX_LAG, now).unwrap(),
+            "replication from upstream database replica is not connected"
+        );
+        // Regardless of whether the lag is checked
+        assert!(replication_failure(None, &health, None, now).is_some());
+    }
+
+    #[test]
+    fn replication_not_ready_when_lagging() {
+        let now = SystemTime::now();
+        assert_eq!(
+            replication_failure(None, &health(31, 0, now), MAX_LAG, now).unwrap(),
+            "replication from upstream database is 31s behind, more than the maximum of 30s"
+        );
+        assert_eq!(
+            replication_failure(None, &health(31, 0, now), None, now),
+            None
+        );
+    }
+
+    #[test]
+    fn replication_not_ready_when_stalled() {
+        let now = SystemTime::now();
+        assert_eq!(
+            replication_failure(None, &health(0, 45, now), MAX_LAG, now).unwrap(),
+            "no replication events received from upstream database in 45s, more than the maximum \
+             lag of 30s"
+        );
+    }
+}
@@ -9,19 +9,24 @@
 #![deny(unreachable_pub)]
 
 pub mod backend;
+pub mod cache_warmer;
 pub mod fallback_cache;
 pub mod http_router;
 pub mod migration_handler;
+pub mod params;
 pub mod proxied_queries_reporter;
 mod query_handler;
 pub mod query_status_cache;
+pub mod result_cache;
 pub mod rewrite;
 pub mod upstream_database;
 mod utils;
 pub mod views_synchronizer;
 
 pub use crate::backend::{Backend, BackendBuilder};
-pub use crate::query_handler::{QueryHandler, SetBehavior};
+pub use crate::query_handler::{
+    QueryHandler, SessionContext, SetBehavior, NOW_PARAMETER_GRANULARITY_VARIABLE,
+};
 pub use crate::upstream_database::{
     UpstreamConfig, UpstreamDatabase, UpstreamDestination, UpstreamPrepare,
 };
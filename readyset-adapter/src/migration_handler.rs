@@ -274,7 +274,7 @@ where
 
                 self.start_time.remove(view_request);
                 self.query_status_cache
-                    .update_query_migration_state(view_request, MigrationState::Unsupported);
+                    .update_query_unsupported(view_request, e.to_string());
             }
             // Errors that were not caused by unsupported may be transient, do nothing
             // so we may retry the migration on this query.
@@ -287,8 +287,10 @@ where
                 );
                 if Instant::now() - *self.start_time.get(view_request).unwrap() > self.max_retry {
                     // Query failed for long enough, it is unsupported.
-                    self.query_status_cache
-                        .update_query_migration_state(view_request, MigrationState::Unsupported);
+                    self.query_status_cache.update_query_unsupported(
+                        view_request,
+                        format!("Migration kept failing for over {:?}: {e}", self.max_retry),
+                    );
                 }
             }
         }
@@ -330,7 +332,7 @@ where
             Err(e) if e.caused_by_unsupported() => {
                 self.start_time.remove(view_request);
                 self.query_status_cache
-                    .update_query_migration_state(view_request, MigrationState::Unsupported);
+                    .update_query_unsupported(view_request, e.to_string());
             }
             _ => {} // Leave it as pending.
         }
@@ -0,0 +1,148 @@
+//! Conversion of the parameters of prepared statements, as decoded from the wire protocol of one
+//! of the frontends, into [`DfValue`]s.
+//!
+//! Each frontend maps the parameter values it decodes onto a [`ParamValue`], along with the type
+//! the parameter was sent as (or [`DfType::Unknown`] if the protocol doesn't tell us), then calls
+//! [`convert_param`]. Keeping the conversion itself here means that both frontends accept (and
+//! reject) the same parameter values, and convert them the same way.
+use std::str::{self, FromStr};
+use std::sync::Arc;
+
+use bit_vec::BitVec;
+use readyset_data::{DfType, DfValue};
+use readyset_errors::{ReadySetError, ReadySetResult};
+use rust_decimal::Decimal;
+
+/// A single prepared statement parameter value, as decoded from the wire protocol of a frontend
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue<'a> {
+    /// `NULL`
+    Null,
+    /// A boolean
+    Bool(bool),
+    /// A signed integer
+    Int(i64),
+    /// An unsigned integer
+    UnsignedInt(u64),
+    /// A single-precision float
+    Float(f32),
+    /// A double-precision float
+    Double(f64),
+    /// An exact decimal number
+    Numeric(Decimal),
+    /// A string
+    Text(&'a str),
+    /// Raw bytes, which are interpreted according to the type of the parameter
+    Bytes(&'a [u8]),
+    /// A value which the frontend has already converted, for types (such as dates and times) that
+    /// only one of the protocols has a native representation for
+    Value(DfValue),
+}
+
+/// Convert `value`, a parameter sent as type `ty`, to a [`DfValue`].
+///
+/// The type only decides how to interpret values whose representation on the wire depends on it
+/// (such as decimals, bits and binary strings, which the MySQL binary protocol sends as raw
+/// bytes). Coercing parameters to the types of the columns they're compared with, or inserted
+/// into, happens later, once we know which statement they're executed against.
+pub fn convert_param(value: ParamValue<'_>, ty: &DfType) -> ReadySetResult<DfValue> {
+    let mk_err = |src_type: &str, details: String| ReadySetError::DfValueConversionError {
+        src_type: src_type.to_owned(),
+        target_type: ty.to_string(),
+        details,
+    };
+
+    match value {
+        ParamValue::Null => Ok(DfValue::None),
+        ParamValue::Bool(b) => Ok(b.into()),
+        ParamValue::Int(i) => Ok(i.into()),
+        ParamValue::UnsignedInt(i) => Ok(i.into()),
+        ParamValue::Float(f) => DfValue::try_from(f)
+            .map_err(|_| mk_err("f32", format!("Non-finite parameter value `{f}`"))),
+        ParamValue::Double(f) => DfValue::try_from(f)
+            .map_err(|_| mk_err("f64", format!("Non-finite parameter value `{f}`"))),
+        ParamValue::Numeric(d) => Ok(d.into()),
+        ParamValue::Text(s) => match ty {
+            DfType::Numeric { .. } => Decimal::from_str(s.trim())
+                .map(DfValue::from)
+                .map_err(|e| mk_err("Text", e.to_string())),
+            _ => Ok(s.into()),
+        },
+        ParamValue::Bytes(b) => match ty {
+            // Bits are sent as a big-endian bitmap, padded on the left to a whole number of bytes
+            DfType::Bit(_) | DfType::VarBit(_) => Ok(BitVec::from_bytes(b).into()),
+            DfType::Blob | DfType::Binary(_) | DfType::VarBinary(_) => {
+                Ok(DfValue::ByteArray(Arc::new(b.to_vec())))
+            }
+            DfType::Numeric { .. } => {
+                let s = str::from_utf8(b).map_err(|e| mk_err("Bytes", e.to_string()))?;
+                convert_param(ParamValue::Text(s), ty)
+            }
+            // Parameters of any other type are strings, so keep them as text as long as they're
+            // valid UTF-8
+            _ => Ok(b.into()),
+        },
+        ParamValue::Value(v) => Ok(v),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsigned_integers() {
+        assert_eq!(
+            convert_param(ParamValue::UnsignedInt(u64::MAX), &DfType::Unknown).unwrap(),
+            DfValue::UnsignedInt(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        convert_param(ParamValue::Double(f64::NAN), &DfType::Double).unwrap_err();
+        convert_param(ParamValue::Float(f32::INFINITY), &DfType::Float).unwrap_err();
+    }
+
+    #[test]
+    fn decimals() {
+        let expected = DfValue::from(Decimal::new(-12345, 3));
+        assert_eq!(
+            convert_param(ParamValue::Bytes(b"-12.345"), &DfType::DEFAULT_NUMERIC).unwrap(),
+            expected
+        );
+        assert_eq!(
+            convert_param(ParamValue::Text("-12.345"), &DfType::DEFAULT_NUMERIC).unwrap(),
+            expected
+        );
+        assert_eq!(
+            convert_param(
+                ParamValue::Numeric(Decimal::new(-12345, 3)),
+                &DfType::Unknown
+            )
+            .unwrap(),
+            expected
+        );
+        convert_param(ParamValue::Bytes(b"abc"), &DfType::DEFAULT_NUMERIC).unwrap_err();
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(
+            convert_param(ParamValue::Bytes(&[0b101, 0xff]), &DfType::Bit(16)).unwrap(),
+            DfValue::from(BitVec::from_bytes(&[0b101, 0xff]))
+        );
+        assert_eq!(
+            convert_param(ParamValue::Bytes(b"abc"), &DfType::Blob).unwrap(),
+            DfValue::ByteArray(Arc::new(b"abc".to_vec()))
+        );
+        assert_eq!(
+            convert_param(ParamValue::Bytes(b"abc"), &DfType::Unknown).unwrap(),
+            DfValue::from("abc")
+        );
+        assert_eq!(
+            convert_param(ParamValue::Bytes(&[0xff]), &DfType::Unknown).unwrap(),
+            DfValue::ByteArray(Arc::new(vec![0xff]))
+        );
+    }
+}
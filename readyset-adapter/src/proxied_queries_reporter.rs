@@ -109,6 +109,7 @@ mod tests {
                 migration_state: MigrationState::Pending,
                 execution_info: None,
                 always: false,
+                unsupported_reason: None,
             },
         };
         proxied_queries_reporter.report_query(&mut init_q).await;
@@ -127,6 +128,7 @@ mod tests {
                 migration_state: MigrationState::Successful,
                 execution_info: None,
                 always: false,
+                unsupported_reason: None,
             },
         };
         proxied_queries_reporter.report_query(&mut updated_q).await;
@@ -1,8 +1,19 @@
+use std::borrow::Cow;
+use std::time::Duration;
+
 use nom_sql::{SqlIdentifier, SqlQuery};
 use readyset_errors::ReadySetResult;
 
 use crate::backend::noria_connector;
 
+/// The name of the variable clients `SET` to change whether (and with what granularity) queries
+/// on their connection comparing columns with `NOW()` are cached, overriding the adapter's
+/// `--now-parameter-granularity-secs` option. Setting it to a number of seconds caches such
+/// queries with that granularity, and setting it to anything else (such as `OFF`) proxies them.
+///
+/// This variable is handled by ReadySet, and isn't set on the upstream database.
+pub const NOW_PARAMETER_GRANULARITY_VARIABLE: &str = "readyset_now_parameter_granularity";
+
 /// Classification for how we should be handling a SQL `SET` statement.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SetBehavior {
@@ -14,6 +25,30 @@ pub enum SetBehavior {
     SetAutocommit(bool),
     /// This `SET` statement represents the current schema search path being changed
     SetSearchPath(Vec<SqlIdentifier>),
+    /// This `SET` statement sets [`NOW_PARAMETER_GRANULARITY_VARIABLE`], to the given granularity
+    /// or to `None` to stop caching queries comparing columns with `NOW()`
+    SetNowParameterGranularity(Option<Duration>),
+}
+
+/// The values of the session variables and connection options set by a client which affect the
+/// semantics of its queries, tracked per connection.
+///
+/// A value is `None` if the client hasn't set it on this connection (in which case the upstream
+/// database's default applies), or has set it to an expression we can't evaluate. These are
+/// recorded whether or not ReadySet supports the value the
+/// client set, so that a [`QueryHandler`] or protocol shim can decide how to handle queries under
+/// unsupported semantics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionContext {
+    /// The value of `sql_mode`, as set by the client
+    pub sql_mode: Option<String>,
+    /// The value of `time_zone`, as set by the client
+    pub time_zone: Option<String>,
+    /// Whether the client has enabled `autocommit`
+    pub autocommit: Option<bool>,
+    /// Whether the client has enabled multiple statements per query, which MySQL clients do with
+    /// `COM_SET_OPTION`
+    pub multi_statements: Option<bool>,
 }
 
 impl SetBehavior {
@@ -43,4 +78,20 @@ pub trait QueryHandler: Sized + Send {
     ///
     /// See the documentation of [`SetStatement`] for more information.
     fn handle_set_statement(stmt: &nom_sql::SetStatement) -> SetBehavior;
+
+    /// Record the values of any of the session variables tracked in [`SessionContext`] which are
+    /// set by the given SET statement.
+    ///
+    /// This is called for every SET statement the [`Backend`] doesn't reject.
+    ///
+    /// [`Backend`]: crate::Backend
+    fn update_session_context(_stmt: &nom_sql::SetStatement, _context: &mut SessionContext) {}
+
+    /// Rewrite the text of `query`, sent by a client whose session variables are those in
+    /// `context`, into the syntax the parser expects for the upstream database's dialect. This
+    /// allows clients to change how their queries are lexed (for example, with MySQL's
+    /// `ANSI_QUOTES` SQL mode) and still have them parsed correctly.
+    fn normalize_query_text<'a>(query: &'a str, _context: &SessionContext) -> Cow<'a, str> {
+        Cow::Borrowed(query)
+    }
 }
@@ -1,10 +1,17 @@
 //! The query status cache provides a thread-safe window into an adapter's
 //! knowledge about queries, currently the migration status of a query in
 //! ReadySet.
+//!
+//! The migration states of the queries in the cache can be persisted to a file with
+//! [`QueryStatusPersister`], so that after the adapter restarts, queries that were already
+//! migrated are served from ReadySet straight away rather than being migrated again.
 use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::anyhow;
 use clap::ValueEnum;
@@ -12,7 +19,23 @@ use dashmap::DashMap;
 use readyset_client::query::*;
 use readyset_client::ViewCreateRequest;
 use readyset_util::hash::hash;
-use tracing::error;
+use readyset_util::shutdown::ShutdownReceiver;
+use serde::{Deserialize, Serialize};
+use tokio::select;
+use tracing::{error, info, instrument, warn};
+
+/// Only one in this many requests is recorded by [`QueryStatusCache::record_request`], so that
+/// estimating request rates doesn't mean hashing every query that's requested
+const REQUEST_SAMPLE_INTERVAL: u64 = 16;
+
+/// The maximum number of queries to estimate request rates for. Requests for other queries aren't
+/// recorded once this many are.
+const MAX_REQUEST_RATES: usize = 10_000;
+
+/// How long a query stays unsupported across restarts. Unsupported statuses that were first saved
+/// longer ago than this aren't loaded by [`QueryStatusCache::load`], so that queries which a newer
+/// version of ReadySet supports are eventually migrated again.
+const PERSISTED_UNSUPPORTED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
 
 /// A metadata cache for all queries that have been processed by this
 /// adapter. Thread-safe.
@@ -30,6 +53,23 @@ pub struct QueryStatusCache {
     /// different id formats in the future.
     ids: DashMap<QueryId, Query, ahash::RandomState>,
 
+    /// A thread-safe hash map from the ids of queries which have had tracing enabled with `ALTER
+    /// READYSET TRACE QUERY` to the time at which tracing should stop.
+    traced: DashMap<QueryId, Instant, ahash::RandomState>,
+
+    /// A thread-safe hash map from query ids to the time at which each query was first requested
+    /// and the (estimated) number of times it has been requested since, used to estimate request
+    /// rates. Holds at most [`MAX_REQUEST_RATES`] queries.
+    requests: DashMap<QueryId, (Instant, u64), ahash::RandomState>,
+
+    /// The number of requests passed to [`Self::record_request`], used to sample them
+    requests_seen: AtomicU64,
+
+    /// A thread-safe hash map from unsupported queries to the time at which they were first
+    /// saved by [`Self::save`], which is persisted along with their status so that they can
+    /// expire after [`PERSISTED_UNSUPPORTED_TTL`]
+    unsupported_since: DashMap<Arc<ViewCreateRequest>, SystemTime, ahash::RandomState>,
+
     /// Holds the current style of migration, whether async or explicit, which may change the
     /// behavior of some internal methods.
     style: MigrationStyle,
@@ -124,6 +164,10 @@ impl QueryStatusCache {
             statuses: DashMap::default(),
             failed_parses: DashMap::default(),
             ids: DashMap::default(),
+            traced: DashMap::default(),
+            requests: DashMap::default(),
+            requests_seen: AtomicU64::new(0),
+            unsupported_since: DashMap::default(),
             style: MigrationStyle::InRequestPath,
             automatic_placeholder_inlining: false,
         }
@@ -344,6 +388,7 @@ impl QueryStatusCache {
                             migration_state: m,
                             execution_info: None,
                             always: false,
+                            unsupported_reason: None,
                         },
                     );
                 }
@@ -352,6 +397,32 @@ impl QueryStatusCache {
         })
     }
 
+    /// Marks the query as [`MigrationState::Unsupported`], recording `reason` as the reason
+    /// ReadySet can't cache it. If the query was already unsupported, its existing reason is kept.
+    pub fn update_query_unsupported<Q>(&self, q: &Q, reason: String)
+    where
+        Q: QueryStatusKey,
+    {
+        q.with_mut_status(self, |s| match s {
+            Some(mut s) if s.migration_state != MigrationState::Unsupported => {
+                s.migration_state = MigrationState::Unsupported;
+                s.unsupported_reason = Some(reason);
+            }
+            None => {
+                self.insert_with_status(
+                    q.clone(),
+                    QueryStatus {
+                        migration_state: MigrationState::Unsupported,
+                        execution_info: None,
+                        always: false,
+                        unsupported_reason: Some(reason),
+                    },
+                );
+            }
+            _ => {}
+        })
+    }
+
     /// Updates the query's always flag, indicating whether the query should be served from
     /// ReadySet regardless of autocommit state.
     /// Will not apply the always flag to unsupported queries, or try to insert a query if it has
@@ -379,6 +450,7 @@ impl QueryStatusCache {
             Some(mut s) if s.migration_state != MigrationState::Unsupported => {
                 s.migration_state = status.migration_state;
                 s.execution_info = status.execution_info;
+                s.unsupported_reason = status.unsupported_reason;
             }
             Some(mut s) => {
                 s.execution_info = status.execution_info;
@@ -389,6 +461,79 @@ impl QueryStatusCache {
         })
     }
 
+    /// Load the migration states saved to `path` by [`QueryStatusCache::save`] into the cache,
+    /// returning the number of queries loaded. Does nothing if `path` doesn't exist.
+    ///
+    /// Queries that are already in the cache keep their current status, and queries that were
+    /// saved as unsupported more than [`PERSISTED_UNSUPPORTED_TTL`] ago aren't loaded.
+    pub fn load(&self, path: &Path) -> io::Result<usize> {
+        let contents = match std::fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        let saved: Vec<PersistedQueryStatus> = serde_json::from_slice(&contents)?;
+        let now = SystemTime::now();
+        let mut loaded = 0;
+        for persisted in saved {
+            if self.statuses.contains_key(&persisted.query) {
+                continue;
+            }
+            if persisted.migration_state == MigrationState::Unsupported {
+                let Some(since) = persisted.unsupported_since.filter(|since| {
+                    now.duration_since(*since)
+                        .map_or(true, |age| age < PERSISTED_UNSUPPORTED_TTL)
+                }) else {
+                    continue;
+                };
+                self.unsupported_since
+                    .insert(persisted.query.clone(), since);
+            }
+            self.insert_with_status(
+                persisted.query,
+                QueryStatus {
+                    migration_state: persisted.migration_state,
+                    execution_info: None,
+                    always: persisted.always,
+                    unsupported_reason: persisted.unsupported_reason,
+                },
+            );
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    /// Save the migration states of the parsed queries in the cache whose migration has been
+    /// processed to `path`, replacing its contents atomically.
+    ///
+    /// Queries that are still pending migration aren't saved, since they'll be processed again
+    /// anyway, and neither are queries that failed to parse, which are always unsupported.
+    pub async fn save(&self, path: &Path) -> io::Result<()> {
+        let contents = self.persisted_statuses()?;
+        write_atomically(path, contents).await
+    }
+
+    /// Serialize the statuses saved by [`Self::save`]
+    fn persisted_statuses(&self) -> io::Result<Vec<u8>> {
+        let now = SystemTime::now();
+        let saved = self
+            .statuses
+            .iter()
+            .filter(|r| !r.is_pending())
+            .map(|r| PersistedQueryStatus {
+                query: r.key().clone(),
+                migration_state: r.migration_state,
+                always: r.always,
+                unsupported_reason: r.unsupported_reason.clone(),
+                unsupported_since: r
+                    .is_unsupported()
+                    .then(|| *self.unsupported_since.entry(r.key().clone()).or_insert(now)),
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_vec(&saved)?)
+    }
+
     /// Clear all queries currently marked as successful from the cache.
     pub fn clear(&self) {
         self.statuses
@@ -479,9 +624,153 @@ impl QueryStatusCache {
 
     /// Returns a query given a query hash
     pub fn query(&self, id: &str) -> Option<Query> {
-        let id = QueryId::new(u64::from_str_radix(id.strip_prefix("q_")?, 16).ok()?);
+        let id = self.query_id(id)?;
         self.ids.get(&id).map(|r| (*r.value()).clone())
     }
+
+    /// Returns the [`QueryId`] for the given query hash, if it is the id of a known query
+    pub fn query_id(&self, id: &str) -> Option<QueryId> {
+        let id = QueryId::new(u64::from_str_radix(id.strip_prefix("q_")?, 16).ok()?);
+        self.ids.contains_key(&id).then_some(id)
+    }
+
+    /// Enables tracing of every execution of the query with the given id for the given duration,
+    /// replacing any previous duration for that query
+    pub fn trace_query(&self, id: QueryId, duration: Duration) {
+        self.traced.insert(id, Instant::now() + duration);
+    }
+
+    /// Records that a query was requested, for estimating its request rate.
+    ///
+    /// Only one in every [`REQUEST_SAMPLE_INTERVAL`] requests is recorded (counting for that many
+    /// requests), and `id` is only called to get the id of the query for those, so callers that
+    /// have to hash the query to get its id only do so for the sampled requests.
+    pub fn record_request<F>(&self, id: F)
+    where
+        F: FnOnce() -> QueryId,
+    {
+        if self.requests_seen.fetch_add(1, Ordering::Relaxed) % REQUEST_SAMPLE_INTERVAL != 0 {
+            return;
+        }
+
+        let id = id();
+        if let Some(mut requests) = self.requests.get_mut(&id) {
+            requests.1 += REQUEST_SAMPLE_INTERVAL;
+        } else if self.requests.len() < MAX_REQUEST_RATES {
+            self.requests
+                .entry(id)
+                .or_insert_with(|| (Instant::now(), 0))
+                .1 += REQUEST_SAMPLE_INTERVAL;
+        }
+    }
+
+    /// Returns the estimated number of requests per second for the query with the given id,
+    /// averaged over the time since it was first requested, or `None` if it has never been
+    /// requested
+    pub fn requests_per_second(&self, id: &QueryId) -> Option<f64> {
+        self.requests.get(id).map(|r| {
+            let (first_requested, count) = *r;
+            // Avoid wildly overestimating the rate of queries that were only just requested
+            count as f64 / first_requested.elapsed().as_secs_f64().max(1.0)
+        })
+    }
+
+    /// Returns true if tracing is currently enabled for the query with the given id. Queries whose
+    /// tracing duration has elapsed are removed.
+    pub fn is_traced(&self, id: &QueryId) -> bool {
+        if self.traced.is_empty() {
+            return false;
+        }
+
+        let now = Instant::now();
+        self.traced.remove_if(id, |_, until| *until <= now);
+        self.traced.contains_key(id)
+    }
+}
+
+/// The status of a single query, as saved by [`QueryStatusCache::save`]
+#[derive(Debug, Serialize, Deserialize)]
+struct PersistedQueryStatus {
+    query: Arc<ViewCreateRequest>,
+    migration_state: MigrationState,
+    always: bool,
+    unsupported_reason: Option<String>,
+    /// When the query was first saved as unsupported, if it is
+    #[serde(default)]
+    unsupported_since: Option<SystemTime>,
+}
+
+/// Write `contents` to a temporary file next to `path`, then rename it over `path`, so that
+/// `path` is never left partially written
+async fn write_atomically(path: &Path, contents: Vec<u8>) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    tokio::fs::write(&tmp_path, contents).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+/// Periodically saves the migration states in a [`QueryStatusCache`] to a file, so that they can
+/// be loaded with [`QueryStatusCache::load`] when the adapter restarts
+pub struct QueryStatusPersister {
+    /// The cache whose migration states are saved
+    query_status_cache: &'static QueryStatusCache,
+    /// The file to save the migration states to
+    path: PathBuf,
+    /// The interval between subsequent saves
+    save_interval: Duration,
+    /// Receiver to return the shutdown signal on
+    shutdown_recv: ShutdownReceiver,
+    /// The contents of the last successful save, so that the file is only rewritten when the
+    /// migration states change
+    last_saved: Option<Vec<u8>>,
+}
+
+impl QueryStatusPersister {
+    pub fn new(
+        query_status_cache: &'static QueryStatusCache,
+        path: PathBuf,
+        save_interval: Duration,
+        shutdown_recv: ShutdownReceiver,
+    ) -> Self {
+        Self {
+            query_status_cache,
+            path,
+            save_interval,
+            shutdown_recv,
+            last_saved: None,
+        }
+    }
+
+    #[instrument(level = "info", name = "query_status_persister", skip(self))]
+    pub async fn run(&mut self) {
+        let mut interval = tokio::time::interval(self.save_interval);
+        loop {
+            select! {
+                // See `ViewsSynchronizer::run` for why this is biased
+                biased;
+                _ = self.shutdown_recv.recv() => {
+                    info!("Query status persister shutting down after shut down signal received");
+                    break;
+                }
+                _ = interval.tick() => self.save().await,
+            }
+        }
+
+        self.save().await;
+    }
+
+    async fn save(&mut self) {
+        let res = match self.query_status_cache.persisted_statuses() {
+            Ok(contents) if self.last_saved.as_ref() == Some(&contents) => Ok(()),
+            Ok(contents) => write_atomically(&self.path, contents.clone())
+                .await
+                .map(|()| self.last_saved = Some(contents)),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = res {
+            warn!(%error, path = %self.path.display(), "Could not save query statuses");
+        }
+    }
 }
 
 /// MigrationStyle is used to communicate which style of managing migrations we have configured.
@@ -672,4 +961,145 @@ mod tests {
         cache.clear();
         assert_eq!(cache.allow_list().len(), 0);
     }
+
+    #[test]
+    fn trace_query_expires() {
+        let cache = QueryStatusCache::new();
+        let q = ViewCreateRequest::new(select_statement("SELECT * FROM t1").unwrap(), vec![]);
+        let (id, _) = cache.query_migration_state(&q);
+
+        assert_eq!(cache.query_id(&id.to_string()), Some(id));
+        assert_eq!(cache.query_id("q_0000000000000000"), None);
+        assert!(!cache.is_traced(&id));
+
+        cache.trace_query(id, Duration::from_secs(60));
+        assert!(cache.is_traced(&id));
+
+        cache.trace_query(id, Duration::ZERO);
+        assert!(!cache.is_traced(&id));
+        assert!(cache.traced.is_empty());
+    }
+
+    #[test]
+    fn unsupported_reason_is_kept() {
+        let cache = QueryStatusCache::new();
+        let query = ViewCreateRequest::new(select_statement("SELECT * FROM t1").unwrap(), vec![]);
+
+        cache.update_query_unsupported(&query, "first".to_owned());
+        cache.update_query_unsupported(&query, "second".to_owned());
+
+        let status = cache.query_status(&query);
+        assert!(status.is_unsupported());
+        assert_eq!(status.unsupported_reason.as_deref(), Some("first"));
+        assert_eq!(cache.deny_list().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("query_statuses.json");
+        let cache = QueryStatusCache::new();
+        assert_eq!(cache.load(&path).unwrap(), 0);
+
+        let successful =
+            ViewCreateRequest::new(select_statement("SELECT * FROM t1").unwrap(), vec![]);
+        let unsupported =
+            ViewCreateRequest::new(select_statement("SELECT * FROM t2").unwrap(), vec![]);
+        let pending = ViewCreateRequest::new(select_statement("SELECT * FROM t3").unwrap(), vec![]);
+        cache.update_query_migration_state(&successful, MigrationState::Successful);
+        cache.always_attempt_readyset(&successful, true);
+        cache.update_query_unsupported(&unsupported, "reason".to_owned());
+        cache.insert(pending.clone());
+        cache.insert("SELECT".to_owned());
+        cache.save(&path).await.unwrap();
+
+        let loaded = QueryStatusCache::new();
+        assert_eq!(loaded.load(&path).unwrap(), 2);
+        assert_eq!(
+            loaded.query_status(&successful),
+            cache.query_status(&successful)
+        );
+        assert!(loaded.query_status(&successful).always);
+        assert_eq!(
+            loaded
+                .query_status(&unsupported)
+                .unsupported_reason
+                .as_deref(),
+            Some("reason")
+        );
+        assert_eq!(loaded.allow_list().len(), 1);
+        assert_eq!(loaded.deny_list().len(), 1);
+        assert!(!loaded.statuses.contains_key(&pending));
+    }
+
+    #[tokio::test]
+    async fn expired_unsupported_statuses_are_not_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("query_statuses.json");
+        let cache = QueryStatusCache::new();
+        let fresh = ViewCreateRequest::new(select_statement("SELECT * FROM t1").unwrap(), vec![]);
+        let expired = ViewCreateRequest::new(select_statement("SELECT * FROM t2").unwrap(), vec![]);
+        cache.update_query_unsupported(&fresh, "reason".to_owned());
+        cache.update_query_unsupported(&expired, "reason".to_owned());
+        cache.unsupported_since.insert(
+            Arc::new(expired.clone()),
+            SystemTime::now() - PERSISTED_UNSUPPORTED_TTL,
+        );
+        cache.save(&path).await.unwrap();
+
+        let loaded = QueryStatusCache::new();
+        assert_eq!(loaded.load(&path).unwrap(), 1);
+        assert!(loaded.query_status(&fresh).is_unsupported());
+        assert!(!loaded.statuses.contains_key(&expired));
+
+        // The time a query was first saved as unsupported is kept across restarts
+        let since = *loaded.unsupported_since.get(&fresh).unwrap();
+        loaded.save(&path).await.unwrap();
+        let reloaded = QueryStatusCache::new();
+        reloaded.load(&path).unwrap();
+        assert_eq!(*reloaded.unsupported_since.get(&fresh).unwrap(), since);
+    }
+
+    #[test]
+    fn requests_per_second() {
+        let cache = QueryStatusCache::new();
+        let q = ViewCreateRequest::new(select_statement("SELECT * FROM t1").unwrap(), vec![]);
+        let (id, _) = cache.query_migration_state(&q);
+        assert_eq!(cache.requests_per_second(&id), None);
+
+        for _ in 0..5 * REQUEST_SAMPLE_INTERVAL {
+            cache.record_request(|| id);
+        }
+        let rate = cache.requests_per_second(&id).unwrap();
+        assert!(
+            rate > 0.0 && rate <= (5 * REQUEST_SAMPLE_INTERVAL) as f64,
+            "rate = {rate}"
+        );
+    }
+
+    #[test]
+    fn requests_are_sampled() {
+        let cache = QueryStatusCache::new();
+        let mut hashed = 0;
+        for _ in 0..REQUEST_SAMPLE_INTERVAL * 3 {
+            cache.record_request(|| {
+                hashed += 1;
+                QueryId::new(1)
+            });
+        }
+        assert_eq!(hashed, 3);
+        assert_eq!(
+            cache.requests.get(&QueryId::new(1)).unwrap().1,
+            REQUEST_SAMPLE_INTERVAL * 3
+        );
+    }
+
+    #[test]
+    fn request_rates_are_bounded() {
+        let cache = QueryStatusCache::new();
+        for i in 0..(MAX_REQUEST_RATES as u64 + 1) * REQUEST_SAMPLE_INTERVAL {
+            cache.record_request(|| QueryId::new(i));
+        }
+        assert_eq!(cache.requests.len(), MAX_REQUEST_RATES);
+    }
 }
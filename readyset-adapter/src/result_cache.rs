@@ -0,0 +1,888 @@
+//! The result cache holds the results of ad hoc, non-parameterized `SELECT` queries that are
+//! proxied to the upstream database, in the adapter's memory, as a middle ground between caching a
+//! query in ReadySet with `CREATE CACHE` and proxying every execution of it.
+//!
+//! The cache is bounded by an (estimated) number of bytes, evicting the least recently used
+//! results once it's full. Results are invalidated by the [`ResultCacheInvalidator`] once a write
+//! to any of the tables a query reads from is replicated to ReadySet, so queries can only be cached
+//! if every table they read from is replicated. Writes made through the adapter itself invalidate
+//! the results they affect as soon as they're executed (see [`ResultCacheInvalidation`]), but since
+//! other writes are only seen once they've been replicated, results can be stale for up to the
+//! replication lag plus the invalidator's polling interval.
+//!
+//! Results are stored separately for each upstream user, and only read by the user whose
+//! privileges the upstream database checked when executing the query.
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use nom_sql::analysis::visit::{self, Visitor};
+use nom_sql::{
+    Column, DeleteStatement, FunctionExpr, InsertStatement, Literal, Relation, SelectStatement,
+    SqlIdentifier, SqlQuery, TableExpr, TableExprInner, UpdateStatement, Variable,
+};
+use parking_lot::Mutex;
+use readyset_client::replication::ReplicationOffsets;
+use readyset_client::ReadySetHandle;
+use readyset_util::shutdown::ShutdownReceiver;
+use tokio::select;
+use tracing::{debug, info, instrument, warn};
+
+/// Functions whose results can differ between executions of a query even if the tables it reads
+/// from don't change, which prevent the query's results from being cached
+const NONDETERMINISTIC_FUNCTIONS: &[&str] = &[
+    "clock_timestamp",
+    "connection_id",
+    "curdate",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "currval",
+    "curtime",
+    "database",
+    "found_rows",
+    "gen_random_uuid",
+    "get_lock",
+    "is_free_lock",
+    "is_used_lock",
+    "last_insert_id",
+    "localtime",
+    "localtimestamp",
+    "nextval",
+    "now",
+    "pg_backend_pid",
+    "rand",
+    "random",
+    "release_lock",
+    "row_count",
+    "schema",
+    "session_user",
+    "sleep",
+    "statement_timestamp",
+    "sysdate",
+    "system_user",
+    "timeofday",
+    "transaction_timestamp",
+    "unix_timestamp",
+    "user",
+    "utc_date",
+    "utc_time",
+    "utc_timestamp",
+    "uuid",
+    "uuid_short",
+    "version",
+];
+
+fn is_nondeterministic(name: &str) -> bool {
+    NONDETERMINISTIC_FUNCTIONS
+        .iter()
+        .any(|f| f.eq_ignore_ascii_case(name))
+}
+
+/// Collects the tables a query reads from, failing if the query's results can't be cached
+#[derive(Default)]
+struct CacheableTables {
+    tables: Vec<Relation>,
+    ctes: HashSet<SqlIdentifier>,
+}
+
+impl<'ast> Visitor<'ast> for CacheableTables {
+    type Error = ();
+
+    fn visit_table_expr(&mut self, table_expr: &'ast TableExpr) -> Result<(), Self::Error> {
+        match &table_expr.inner {
+            TableExprInner::Table(table) => {
+                if !self.tables.contains(table) {
+                    self.tables.push(table.clone())
+                }
+                Ok(())
+            }
+            TableExprInner::Subquery(sq) => self.visit_select_statement(sq),
+        }
+    }
+
+    fn visit_select_statement(
+        &mut self,
+        select_statement: &'ast SelectStatement,
+    ) -> Result<(), Self::Error> {
+        self.ctes
+            .extend(select_statement.ctes.iter().map(|cte| cte.name.clone()));
+        visit::walk_select_statement(self, select_statement)
+    }
+
+    fn visit_column(&mut self, column: &'ast Column) -> Result<(), Self::Error> {
+        // Functions like `CURRENT_TIMESTAMP` can be called without parentheses, in which case
+        // they're parsed as columns
+        if column.table.is_none() && is_nondeterministic(&column.name) {
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn visit_variable(&mut self, _variable: &'ast Variable) -> Result<(), Self::Error> {
+        Err(())
+    }
+
+    fn visit_literal(&mut self, literal: &'ast Literal) -> Result<(), Self::Error> {
+        if matches!(literal, Literal::Placeholder(_)) {
+            return Err(());
+        }
+        Ok(())
+    }
+
+    fn visit_function_expr(
+        &mut self,
+        function_expr: &'ast FunctionExpr,
+    ) -> Result<(), Self::Error> {
+        if let FunctionExpr::Call { name, .. } = function_expr {
+            if is_nondeterministic(name) {
+                return Err(());
+            }
+        }
+        visit::walk_function_expr(self, function_expr)
+    }
+}
+
+/// If the results of `stmt` can be stored in a [`ResultCache`], returns the tables it reads from.
+///
+/// The results of a query can be cached if they only depend on the contents of the tables it
+/// reads from, so queries with placeholders, which refer to variables, or which call
+/// nondeterministic functions such as `NOW()` can't be cached. Queries that don't read from any
+/// tables aren't cached either.
+pub fn cacheable_tables(stmt: &SelectStatement) -> Option<Vec<Relation>> {
+    let mut visitor = CacheableTables::default();
+    visitor.visit_select_statement(stmt).ok()?;
+    let ctes = visitor.ctes;
+    let tables = visitor
+        .tables
+        .into_iter()
+        .filter(|t| t.schema.is_some() || !ctes.contains(&t.name))
+        .collect::<Vec<_>>();
+    (!tables.is_empty()).then_some(tables)
+}
+
+/// Statements which can't write to any tables, or change the results of any queries, when they're
+/// executed on the upstream database, by their first keyword
+const READ_ONLY_STATEMENTS: &[&str] = &[
+    "begin", "commit", "desc", "describe", "explain", "rollback", "select", "set", "show", "start",
+    "use",
+];
+
+/// The results in a [`ResultCache`] that a statement may have changed, once it's been executed on
+/// the upstream database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultCacheInvalidation {
+    /// The statement can't have changed the results of any queries
+    None,
+    /// The statement may have written to these tables, which are resolved against the schema
+    /// search path in the same way as the tables in a query
+    Tables(Vec<Relation>),
+    /// The statement may have changed the results of any query, such as by changing the schema,
+    /// or the privileges of a user
+    All,
+}
+
+impl ResultCacheInvalidation {
+    /// Returns the results that `query` may have changed
+    pub fn for_query(query: &SqlQuery) -> Self {
+        match query {
+            SqlQuery::Insert(InsertStatement { table, .. })
+            | SqlQuery::Update(UpdateStatement { table, .. })
+            | SqlQuery::Delete(DeleteStatement { table, .. }) => Self::Tables(vec![table.clone()]),
+            SqlQuery::Select(_)
+            | SqlQuery::CompoundSelect(_)
+            | SqlQuery::Show(_)
+            | SqlQuery::Explain(_)
+            | SqlQuery::Set(_)
+            | SqlQuery::Use(_)
+            | SqlQuery::StartTransaction(_)
+            | SqlQuery::Commit(_)
+            | SqlQuery::Rollback(_)
+            | SqlQuery::CreateCache(_)
+            | SqlQuery::DropCache(_)
+            | SqlQuery::DropAllCaches(_)
+            | SqlQuery::AlterReadySet(_) => Self::None,
+            SqlQuery::CreateTable(_)
+            | SqlQuery::CreateView(_)
+            | SqlQuery::AlterTable(_)
+            | SqlQuery::DropTable(_)
+            | SqlQuery::DropView(_)
+            | SqlQuery::RenameTable(_) => Self::All,
+        }
+    }
+
+    /// Returns the results that `query`, which we couldn't parse, may have changed, going by its
+    /// first keyword
+    pub fn for_unparsed(query: &str) -> Self {
+        let keyword = query
+            .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        if READ_ONLY_STATEMENTS
+            .iter()
+            .any(|stmt| stmt.eq_ignore_ascii_case(keyword))
+        {
+            Self::None
+        } else {
+            Self::All
+        }
+    }
+}
+
+/// The key a query's results are stored under in a [`ResultCache`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResultCacheKey {
+    /// The text of the query
+    query: String,
+    /// The schema search path the query was executed with, which determines the tables it reads
+    /// from
+    schema_search_path: Vec<SqlIdentifier>,
+    /// The upstream user the query was executed as, whose privileges allowed it to read the
+    /// results
+    user: Option<String>,
+}
+
+impl ResultCacheKey {
+    pub fn new(query: &str, schema_search_path: &[SqlIdentifier]) -> Self {
+        Self {
+            query: query.to_owned(),
+            schema_search_path: schema_search_path.to_vec(),
+            user: None,
+        }
+    }
+
+    /// Store the results under this key only for `user`, so that they're never read by users who
+    /// might not be allowed to read the tables the query reads from
+    pub fn for_user(self, user: Option<&str>) -> Self {
+        Self {
+            user: user.map(str::to_owned),
+            ..self
+        }
+    }
+}
+
+struct CachedResult<R> {
+    result: R,
+    /// The (resolved) tables the query reads from
+    tables: Vec<Relation>,
+    size_bytes: usize,
+    /// The value of [`Inner::tick`] when this result was last read, used to evict the least
+    /// recently used results
+    last_used: u64,
+}
+
+struct Inner<R> {
+    results: HashMap<ResultCacheKey, CachedResult<R>>,
+    /// The keys of all cached results, by the tick they were last read at
+    lru: BTreeMap<u64, ResultCacheKey>,
+    /// The keys of the cached results which read from each table
+    by_table: HashMap<Relation, HashSet<ResultCacheKey>>,
+    /// The tables which are replicated by ReadySet, and so can be invalidated. `None` if we don't
+    /// (yet) know which tables are replicated, in which case nothing can be cached.
+    replicated_tables: Option<HashSet<Relation>>,
+    /// The epoch at which each table was last invalidated
+    invalidated_at: HashMap<Relation, u64>,
+    /// The epoch at which the entire cache was last cleared
+    cleared_at: u64,
+    /// Incremented every time results are invalidated, to detect results that were read from the
+    /// upstream database before an invalidation but inserted after it
+    epoch: u64,
+    tick: u64,
+    size_bytes: usize,
+    max_bytes: usize,
+}
+
+impl<R> Inner<R> {
+    /// Resolve `table` against `schema_search_path`, returning the first replicated table with its
+    /// name, in the same way ReadySet resolves the tables in a query
+    fn resolve(&self, table: &Relation, schema_search_path: &[SqlIdentifier]) -> Option<Relation> {
+        let replicated_tables = self.replicated_tables.as_ref()?;
+        match &table.schema {
+            Some(_) => replicated_tables.contains(table).then(|| table.clone()),
+            None => schema_search_path
+                .iter()
+                .map(|schema| Relation {
+                    schema: Some(schema.clone()),
+                    name: table.name.clone(),
+                })
+                .find(|table| replicated_tables.contains(table)),
+        }
+    }
+
+    fn remove(&mut self, key: &ResultCacheKey) {
+        if let Some(cached) = self.results.remove(key) {
+            self.lru.remove(&cached.last_used);
+            for table in &cached.tables {
+                if let Some(keys) = self.by_table.get_mut(table) {
+                    keys.remove(key);
+                    if keys.is_empty() {
+                        self.by_table.remove(table);
+                    }
+                }
+            }
+            self.size_bytes -= cached.size_bytes;
+        }
+    }
+
+    fn invalidate(&mut self, table: &Relation) {
+        self.epoch += 1;
+        self.invalidated_at.insert(table.clone(), self.epoch);
+        for key in self.by_table.remove(table).unwrap_or_default() {
+            self.remove(&key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.epoch += 1;
+        self.cleared_at = self.epoch;
+        self.invalidated_at.clear();
+        self.results.clear();
+        self.lru.clear();
+        self.by_table.clear();
+        self.size_bytes = 0;
+    }
+}
+
+/// A thread-safe, memory-bounded LRU cache of the results of proxied queries. See [the module
+/// documentation](self) for more information.
+pub struct ResultCache<R> {
+    inner: Arc<Mutex<Inner<R>>>,
+}
+
+impl<R> Clone for ResultCache<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<R> std::fmt::Debug for ResultCache<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.lock();
+        f.debug_struct("ResultCache")
+            .field("len", &inner.results.len())
+            .field("size_bytes", &inner.size_bytes)
+            .field("max_bytes", &inner.max_bytes)
+            .finish()
+    }
+}
+
+impl<R: Clone> ResultCache<R> {
+    /// Create a new, empty [`ResultCache`] which holds at most `max_bytes` worth of results
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                results: HashMap::new(),
+                lru: BTreeMap::new(),
+                by_table: HashMap::new(),
+                replicated_tables: None,
+                invalidated_at: HashMap::new(),
+                cleared_at: 0,
+                epoch: 0,
+                tick: 0,
+                size_bytes: 0,
+                max_bytes,
+            })),
+        }
+    }
+
+    /// Returns the cached results for `key`, if any
+    pub fn get(&self, key: &ResultCacheKey) -> Option<R> {
+        let mut inner = self.inner.lock();
+        inner.tick += 1;
+        let tick = inner.tick;
+        let cached = inner.results.get_mut(key)?;
+        let last_used = std::mem::replace(&mut cached.last_used, tick);
+        let result = cached.result.clone();
+        inner.lru.remove(&last_used);
+        inner.lru.insert(tick, key.clone());
+        Some(result)
+    }
+
+    /// Returns the current epoch of the cache, which must be read before executing a query whose
+    /// results will be passed to [`insert`](Self::insert)
+    pub fn epoch(&self) -> u64 {
+        self.inner.lock().epoch
+    }
+
+    /// Store `result`, which is estimated to take up `size_bytes` of memory, as the results of the
+    /// query with the given `key`, which reads from `tables`.
+    ///
+    /// `epoch` is the [`epoch`](Self::epoch) of the cache from before the query was executed. If
+    /// any of `tables` have been invalidated since then, or any of them aren't replicated, the
+    /// results aren't stored.
+    pub fn insert(
+        &self,
+        key: ResultCacheKey,
+        tables: &[Relation],
+        epoch: u64,
+        result: R,
+        size_bytes: usize,
+    ) {
+        let size_bytes = size_bytes + key.query.len();
+        let mut inner = self.inner.lock();
+        if size_bytes > inner.max_bytes || inner.cleared_at > epoch {
+            return;
+        }
+        let Some(tables) = tables
+            .iter()
+            .map(|table| inner.resolve(table, &key.schema_search_path))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        if tables.iter().any(|table| {
+            inner
+                .invalidated_at
+                .get(table)
+                .map_or(false, |e| *e > epoch)
+        }) {
+            return;
+        }
+
+        inner.remove(&key);
+        while inner.size_bytes + size_bytes > inner.max_bytes {
+            let Some((_, lru_key)) = inner.lru.pop_first() else {
+                break;
+            };
+            inner.remove(&lru_key);
+        }
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        for table in &tables {
+            inner
+                .by_table
+                .entry(table.clone())
+                .or_default()
+                .insert(key.clone());
+        }
+        inner.lru.insert(tick, key.clone());
+        inner.size_bytes += size_bytes;
+        inner.results.insert(
+            key,
+            CachedResult {
+                result,
+                tables,
+                size_bytes,
+                last_used: tick,
+            },
+        );
+    }
+
+    /// Remove the cached results that a statement executed with the given schema search path may
+    /// have changed, per `invalidation`. Unqualified tables are invalidated in every schema in the
+    /// search path, since the statement might not have resolved them to a replicated table.
+    pub fn invalidate(
+        &self,
+        invalidation: &ResultCacheInvalidation,
+        schema_search_path: &[SqlIdentifier],
+    ) {
+        match invalidation {
+            ResultCacheInvalidation::None => {}
+            ResultCacheInvalidation::Tables(tables) => {
+                let mut inner = self.inner.lock();
+                for table in tables {
+                    match &table.schema {
+                        Some(_) => inner.invalidate(table),
+                        None => {
+                            for schema in schema_search_path {
+                                inner.invalidate(&Relation {
+                                    schema: Some(schema.clone()),
+                                    name: table.name.clone(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            ResultCacheInvalidation::All => self.clear(),
+        }
+    }
+
+    /// Remove all cached results which read from any of `tables`
+    pub fn invalidate_tables<'a, I>(&self, tables: I)
+    where
+        I: IntoIterator<Item = &'a Relation>,
+    {
+        let mut inner = self.inner.lock();
+        for table in tables {
+            inner.invalidate(table);
+        }
+    }
+
+    /// Set the tables which are replicated by ReadySet, and can therefore be read from by cached
+    /// queries, or `None` to stop caching any results until the replicated tables are known
+    /// again. Cached results which read from a table that's no longer replicated are removed.
+    pub fn set_replicated_tables(&self, replicated_tables: Option<HashSet<Relation>>) {
+        let mut inner = self.inner.lock();
+        let removed: Vec<Relation> = match (&inner.replicated_tables, &replicated_tables) {
+            (Some(old), Some(new)) => old.difference(new).cloned().collect(),
+            (Some(old), None) => old.iter().cloned().collect(),
+            (None, _) => vec![],
+        };
+        for table in &removed {
+            inner.invalidate(table);
+        }
+        inner.replicated_tables = replicated_tables;
+    }
+
+    /// Remove all cached results
+    pub fn clear(&self) {
+        self.inner.lock().clear()
+    }
+
+    /// Returns the number of cached results
+    pub fn len(&self) -> usize {
+        self.inner.lock().results.len()
+    }
+
+    /// Returns true if there are no cached results
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the estimated number of bytes taken up by all cached results
+    pub fn size_bytes(&self) -> usize {
+        self.inner.lock().size_bytes
+    }
+
+    /// Returns the maximum estimated number of bytes that the cached results can take up, which is
+    /// also the size of the largest result that can be cached
+    pub fn max_bytes(&self) -> usize {
+        self.inner.lock().max_bytes
+    }
+}
+
+/// Invalidates the results in a [`ResultCache`] as writes to the tables they read from are
+/// replicated to ReadySet, by periodically polling the replication offsets of all tables from the
+/// controller.
+pub struct ResultCacheInvalidator<R> {
+    /// The handle used to query the controller for replication offsets
+    controller: ReadySetHandle,
+    cache: ResultCache<R>,
+    /// The interval between subsequent pollings of the controller for replication offsets
+    poll_interval: Duration,
+    /// The replication offsets we saw on the last successful poll
+    offsets: Option<ReplicationOffsets>,
+    /// Receiver to return the shutdown signal on
+    shutdown_recv: ShutdownReceiver,
+}
+
+impl<R: Clone> ResultCacheInvalidator<R> {
+    pub fn new(
+        controller: ReadySetHandle,
+        cache: ResultCache<R>,
+        poll_interval: Duration,
+        shutdown_recv: ShutdownReceiver,
+    ) -> Self {
+        ResultCacheInvalidator {
+            controller,
+            cache,
+            poll_interval,
+            offsets: None,
+            shutdown_recv,
+        }
+    }
+
+    #[instrument(level = "info", name = "result_cache_invalidator", skip(self))]
+    pub async fn run(&mut self) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        loop {
+            select! {
+                // See the comment in `ViewsSynchronizer::run` for why we use `biased` here
+                biased;
+                _ = self.shutdown_recv.recv() => {
+                    info!("Result cache invalidator shutting down after shut down signal received");
+                    break;
+                }
+                _ = interval.tick() => self.poll().await,
+            }
+        }
+    }
+
+    async fn poll(&mut self) {
+        let offsets = match self.controller.replication_offsets().await {
+            Ok(offsets) => offsets,
+            Err(error) => {
+                // We can't tell which tables have been written to, so stop caching anything until
+                // we can again
+                warn!(%error, "Could not get replication offsets from leader");
+                self.cache.set_replicated_tables(None);
+                self.cache.clear();
+                self.offsets = None;
+                return;
+            }
+        };
+
+        match &self.offsets {
+            Some(old) if old.schema == offsets.schema => {
+                let changed = offsets
+                    .tables
+                    .iter()
+                    .filter(|(table, offset)| old.tables.get(*table) != Some(*offset))
+                    .map(|(table, _)| table);
+                self.cache.invalidate_tables(changed);
+            }
+            _ => {
+                // The schema might have changed, which can change the results of any query
+                debug!("Schema replication offset changed, clearing result cache");
+                self.cache.clear();
+            }
+        }
+
+        self.cache.set_replicated_tables(Some(
+            offsets
+                .tables
+                .iter()
+                .filter(|(_, offset)| offset.is_some())
+                .map(|(table, _)| table.clone())
+                .collect(),
+        ));
+        self.offsets = Some(offsets);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom_sql::{parse_query, Dialect, SqlQuery};
+
+    use super::*;
+
+    fn select_statement(s: &str) -> SelectStatement {
+        match parse_query(Dialect::MySQL, s) {
+            Ok(SqlQuery::Select(s)) => s,
+            _ => panic!("Invalid SELECT statement"),
+        }
+    }
+
+    fn cacheable(s: &str) -> Option<Vec<Relation>> {
+        cacheable_tables(&select_statement(s))
+    }
+
+    fn table(schema: &str, name: &str) -> Relation {
+        Relation {
+            schema: Some(schema.into()),
+            name: name.into(),
+        }
+    }
+
+    fn cache(max_bytes: usize) -> ResultCache<u32> {
+        let cache = ResultCache::new(max_bytes);
+        cache.set_replicated_tables(Some(
+            [table("db", "t1"), table("db", "t2")].into_iter().collect(),
+        ));
+        cache
+    }
+
+    fn key(query: &str) -> ResultCacheKey {
+        ResultCacheKey::new(query, &["db".into()])
+    }
+
+    #[test]
+    fn cacheable_queries() {
+        assert_eq!(
+            cacheable("SELECT * FROM t1 JOIN t2 ON t1.x = t2.x WHERE t1.y = 1"),
+            Some(vec!["t1".into(), "t2".into()])
+        );
+        assert_eq!(
+            cacheable("SELECT a.x FROM db.t1 AS a WHERE a.x IN (SELECT x FROM t2)"),
+            Some(vec![table("db", "t1"), "t2".into()])
+        );
+        assert_eq!(
+            cacheable("WITH c AS (SELECT x FROM t1) SELECT * FROM c"),
+            Some(vec!["t1".into()])
+        );
+        assert_eq!(
+            cacheable("SELECT coalesce(x, 0) FROM t1"),
+            Some(vec!["t1".into()])
+        );
+    }
+
+    #[test]
+    fn uncacheable_queries() {
+        assert_eq!(cacheable("SELECT 1"), None);
+        assert_eq!(cacheable("SELECT * FROM t1 WHERE x = ?"), None);
+        assert_eq!(cacheable("SELECT * FROM t1 WHERE x = @var"), None);
+        assert_eq!(cacheable("SELECT * FROM t1 WHERE created_at < NOW()"), None);
+        assert_eq!(cacheable("SELECT rand(), x FROM t1"), None);
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        assert_eq!(cache.get(&key("q1")), Some(1));
+        assert_eq!(
+            cache.get(&ResultCacheKey::new("q1", &["other".into()])),
+            None
+        );
+        assert_eq!(cache.size_bytes(), 10 + "q1".len());
+    }
+
+    #[test]
+    fn unreplicated_tables_are_not_cached() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t3".into()], epoch, 1, 10);
+        cache.insert(key("q2"), &[table("other", "t1")], epoch, 2, 10);
+        assert!(cache.is_empty());
+
+        let cache = ResultCache::new(1024);
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn invalidate_tables() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        cache.insert(key("q2"), &["t1".into(), "t2".into()], epoch, 2, 10);
+        cache.insert(key("q3"), &[table("db", "t2")], epoch, 3, 10);
+
+        cache.invalidate_tables(&[table("db", "t1")]);
+        assert_eq!(cache.get(&key("q1")), None);
+        assert_eq!(cache.get(&key("q2")), None);
+        assert_eq!(cache.get(&key("q3")), Some(3));
+        assert_eq!(cache.size_bytes(), 10 + "q3".len());
+    }
+
+    #[test]
+    fn results_read_before_invalidation_are_not_cached() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.invalidate_tables(&[table("db", "t1")]);
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        cache.insert(key("q2"), &["t2".into()], epoch, 2, 10);
+        assert_eq!(cache.get(&key("q1")), None);
+        assert_eq!(cache.get(&key("q2")), Some(2));
+
+        let epoch = cache.epoch();
+        cache.clear();
+        cache.insert(key("q2"), &["t2".into()], epoch, 2, 10);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache(3 * 12);
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        cache.insert(key("q2"), &["t1".into()], epoch, 2, 10);
+        cache.insert(key("q3"), &["t1".into()], epoch, 3, 10);
+        assert_eq!(cache.get(&key("q1")), Some(1));
+
+        cache.insert(key("q4"), &["t1".into()], epoch, 4, 10);
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(&key("q2")), None);
+        assert_eq!(cache.get(&key("q1")), Some(1));
+        assert_eq!(cache.get(&key("q3")), Some(3));
+        assert_eq!(cache.get(&key("q4")), Some(4));
+
+        // Results bigger than the entire cache aren't stored
+        cache.insert(key("q5"), &["t1".into()], epoch, 5, 1024);
+        assert_eq!(cache.get(&key("q5")), None);
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn results_are_per_user() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.insert(
+            key("q1").for_user(Some("alice")),
+            &["t1".into()],
+            epoch,
+            1,
+            10,
+        );
+        assert_eq!(cache.get(&key("q1").for_user(Some("alice"))), Some(1));
+        assert_eq!(cache.get(&key("q1").for_user(Some("bob"))), None);
+        assert_eq!(cache.get(&key("q1")), None);
+    }
+
+    #[test]
+    fn invalidate_writes() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        let insert_all = || {
+            cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+            cache.insert(key("q2"), &["t2".into()], epoch, 2, 10);
+        };
+
+        insert_all();
+        let write = ResultCacheInvalidation::for_query(
+            &parse_query(Dialect::MySQL, "UPDATE t1 SET x = 1").unwrap(),
+        );
+        assert_eq!(write, ResultCacheInvalidation::Tables(vec!["t1".into()]));
+        cache.invalidate(&write, &["other".into(), "db".into()]);
+        assert_eq!(cache.get(&key("q1")), None);
+        assert_eq!(cache.get(&key("q2")), Some(2));
+
+        // Results read before the write can't be stored after it
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        assert_eq!(cache.get(&key("q1")), None);
+
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        cache.invalidate(&ResultCacheInvalidation::None, &["db".into()]);
+        assert_eq!(cache.len(), 2);
+        cache.invalidate(&ResultCacheInvalidation::All, &["db".into()]);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn invalidation_for_statements() {
+        let for_query =
+            |s: &str| ResultCacheInvalidation::for_query(&parse_query(Dialect::MySQL, s).unwrap());
+        assert_eq!(
+            for_query("DELETE FROM db.t2 WHERE x = 1"),
+            ResultCacheInvalidation::Tables(vec![table("db", "t2")])
+        );
+        assert_eq!(
+            for_query("INSERT INTO t1 (x) VALUES (1)"),
+            ResultCacheInvalidation::Tables(vec!["t1".into()])
+        );
+        assert_eq!(for_query("SELECT * FROM t1"), ResultCacheInvalidation::None);
+        assert_eq!(for_query("DROP TABLE t1"), ResultCacheInvalidation::All);
+
+        assert_eq!(
+            ResultCacheInvalidation::for_unparsed("  select x from t1 lock in share mode"),
+            ResultCacheInvalidation::None
+        );
+        assert_eq!(
+            ResultCacheInvalidation::for_unparsed("(SELECT 1) UNION (SELECT 2)"),
+            ResultCacheInvalidation::None
+        );
+        for write in [
+            "REPLACE INTO t1 VALUES (1)",
+            "CALL do_writes()",
+            "LOAD DATA INFILE 'x' INTO TABLE t1",
+            "GRANT SELECT ON db.* TO bob",
+            "REVOKE SELECT ON db.* FROM bob",
+            "TRUNCATE t1",
+        ] {
+            assert_eq!(
+                ResultCacheInvalidation::for_unparsed(write),
+                ResultCacheInvalidation::All,
+                "{write}"
+            );
+        }
+    }
+
+    #[test]
+    fn tables_no_longer_replicated_are_invalidated() {
+        let cache = cache(1024);
+        let epoch = cache.epoch();
+        cache.insert(key("q1"), &["t1".into()], epoch, 1, 10);
+        cache.insert(key("q2"), &["t2".into()], epoch, 2, 10);
+        cache.set_replicated_tables(Some([table("db", "t2")].into_iter().collect()));
+        assert_eq!(cache.get(&key("q1")), None);
+        assert_eq!(cache.get(&key("q2")), Some(2));
+    }
+}
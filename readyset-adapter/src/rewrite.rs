@@ -2,12 +2,15 @@ use std::borrow::Cow;
 use std::cmp::max;
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Debug;
+use std::time::Duration;
 use std::{iter, mem};
 
+use chrono::{FixedOffset, TimeZone, Utc};
 use itertools::{Either, Itertools};
 use nom_sql::analysis::visit_mut::{self, VisitorMut};
 use nom_sql::{
-    BinaryOperator, Expr, InValue, ItemPlaceholder, LimitClause, Literal, SelectStatement,
+    BinaryOperator, Expr, FunctionExpr, InValue, ItemPlaceholder, LimitClause, Literal,
+    SelectStatement,
 };
 use readyset_data::{DfType, DfValue};
 use readyset_errors::{invalid_err, unsupported, ReadySetError, ReadySetResult};
@@ -25,6 +28,11 @@ pub struct ProcessedQueryParams {
     reordered_placeholders: Option<Vec<usize>>,
     rewritten_in_conditions: Vec<RewrittenIn>,
     auto_parameters: Vec<(usize, Literal)>,
+    /// The indices of the parameters that calls to `NOW()` were replaced with by
+    /// [`parametrize_now`], which are bound to the current time when making keys
+    now_parameters: Vec<usize>,
+    /// The granularity the current time is rounded down to when binding [`Self::now_parameters`]
+    now_parameter_granularity: Duration,
     pagination_parameters: AdapterPaginationParams,
 }
 
@@ -60,11 +68,20 @@ fn use_fallback_pagination(server_supports_pagination: bool, limit_clause: &Limi
 ///   therefore cannot guarantee that the rewritten query is free of user PII.
 /// - Collapses 'WHERE <expr> IN ?, ... ?' to 'WHERE <expr> = ?'
 /// - Removes `OFFSET ?` if there isn't a `LIMIT`
+/// - If `now_parameter_granularity` is set, replaces comparisons of columns with `NOW()` (or
+///   `CURRENT_TIMESTAMP`) with placeholders, which are bound to the current time rounded down to
+///   that granularity when the query is executed. See [`parametrize_now`].
 pub fn process_query(
     query: &mut SelectStatement,
     server_supports_pagination: bool,
+    now_parameter_granularity: Option<Duration>,
 ) -> ReadySetResult<ProcessedQueryParams> {
     let reordered_placeholders = reorder_numbered_placeholders(query);
+    let now_parameters = if now_parameter_granularity.is_some() {
+        parametrize_now(query)
+    } else {
+        vec![]
+    };
 
     let limit_clause = mem::take(&mut query.limit_clause);
 
@@ -83,6 +100,8 @@ pub fn process_query(
         reordered_placeholders,
         rewritten_in_conditions,
         auto_parameters,
+        now_parameters,
+        now_parameter_granularity: now_parameter_granularity.unwrap_or_default(),
         pagination_parameters: AdapterPaginationParams {
             limit_clause,
             force_paginate_in_adapter,
@@ -164,9 +183,15 @@ impl ProcessedQueryParams {
     pub(crate) fn make_keys<'param, T>(
         &self,
         params: &'param [T],
+        now_utc_offset: FixedOffset,
     ) -> ReadySetResult<Vec<Cow<'param, [T]>>>
     where
-        T: Clone + TryFrom<Literal, Error = ReadySetError> + Debug + Default + PartialEq,
+        T: Clone
+            + TryFrom<Literal, Error = ReadySetError>
+            + From<DfValue>
+            + Debug
+            + Default
+            + PartialEq,
     {
         let params = if let Some(order_map) = &self.reordered_placeholders {
             Cow::Owned(reorder_params(params, order_map)?)
@@ -174,6 +199,18 @@ impl ProcessedQueryParams {
             Cow::Borrowed(params)
         };
 
+        let params = if self.now_parameters.is_empty() {
+            params
+        } else {
+            let now = T::from(current_time(self.now_parameter_granularity, now_utc_offset));
+            let now_parameters = self
+                .now_parameters
+                .iter()
+                .map(|i| (*i, now.clone()))
+                .collect::<Vec<_>>();
+            Cow::Owned(splice_auto_parameters(params.as_ref(), &now_parameters).into_owned())
+        };
+
         let mut params = params.as_ref();
 
         let AdapterPaginationParams {
@@ -694,6 +731,121 @@ pub fn auto_parametrize_query(query: &mut SelectStatement) -> Vec<(usize, Litera
     visitor.out
 }
 
+/// The names of the functions that [`parametrize_now`] replaces with parameters
+const NOW_FUNCTIONS: &[&str] = &["now", "current_timestamp", "localtimestamp"];
+
+fn is_now_call(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Call(FunctionExpr::Call { name, arguments })
+            if arguments.is_empty()
+                && NOW_FUNCTIONS.iter().any(|f| name.eq_ignore_ascii_case(f))
+    )
+}
+
+#[derive(Default)]
+struct ParametrizeNowVisitor {
+    out: Vec<usize>,
+    in_where_clause: bool,
+    param_index: usize,
+    query_depth: u8,
+}
+
+impl<'ast> VisitorMut<'ast> for ParametrizeNowVisitor {
+    type Error = !;
+
+    fn visit_literal(&mut self, literal: &'ast mut Literal) -> Result<(), Self::Error> {
+        if matches!(literal, Literal::Placeholder(_)) {
+            self.param_index += 1;
+        }
+        Ok(())
+    }
+
+    fn visit_select_statement(
+        &mut self,
+        select_statement: &'ast mut SelectStatement,
+    ) -> Result<(), Self::Error> {
+        let was_in_where_clause = mem::replace(&mut self.in_where_clause, false);
+        self.query_depth = self.query_depth.saturating_add(1);
+        visit_mut::walk_select_statement(self, select_statement)?;
+        self.query_depth = self.query_depth.saturating_sub(1);
+        self.in_where_clause = was_in_where_clause;
+        Ok(())
+    }
+
+    fn visit_where_clause(&mut self, expression: &'ast mut Expr) -> Result<(), Self::Error> {
+        // As with auto-parametrization, parameters are only supported in the WHERE clause of the
+        // top-level query
+        self.in_where_clause = self.query_depth <= 1;
+        self.visit_expr(expression)?;
+        self.in_where_clause = false;
+        Ok(())
+    }
+
+    fn visit_expr(&mut self, expression: &'ast mut Expr) -> Result<(), Self::Error> {
+        if self.in_where_clause {
+            if let Expr::BinaryOp { lhs, op, rhs } = expression {
+                if (*op == BinaryOperator::Equal || op.is_ordering_comparison())
+                    && matches!(**rhs, Expr::Column(_))
+                    && is_now_call(lhs)
+                {
+                    // For `NOW() < col`, flip the comparison to `col > NOW()` first
+                    mem::swap(lhs, rhs);
+                    *op = op.flip_ordering_comparison().unwrap_or_else(|op| op);
+                }
+                if (*op == BinaryOperator::Equal || op.is_ordering_comparison())
+                    && matches!(**lhs, Expr::Column(_))
+                    && is_now_call(rhs)
+                {
+                    **rhs = Expr::Literal(Literal::Placeholder(ItemPlaceholder::QuestionMark));
+                    self.out.push(self.param_index);
+                    self.param_index += 1;
+                    return Ok(());
+                }
+            }
+        }
+
+        visit_mut::walk_expr(self, expression)
+    }
+}
+
+/// Replace calls to `NOW()` (or `CURRENT_TIMESTAMP` or `LOCALTIMESTAMP`) that a column is compared
+/// with in the `WHERE` clause of the given query with parameters, and return the indices of those
+/// parameters in the parameter list.
+///
+/// This allows queries like `SELECT * FROM t WHERE expires_at > NOW()` to be cached, with the
+/// parameters bound to the current time when the query is executed (see
+/// [`ProcessedQueryParams::make_keys`]). Since the current time is rounded down to a configurable
+/// granularity, results are shared between executions within the same interval, and are up to
+/// that granularity stale.
+pub fn parametrize_now(query: &mut SelectStatement) -> Vec<usize> {
+    let mut visitor = ParametrizeNowVisitor::default();
+    #[allow(clippy::unwrap_used)] // error is !, which can never be returned
+    visitor.visit_select_statement(query).unwrap();
+    visitor.out
+}
+
+/// Returns the current time in the time zone `utc_offset` from UTC, rounded down to a multiple of
+/// `granularity` since the Unix epoch. If `granularity` is less than a second, the time isn't
+/// rounded.
+///
+/// The time is returned without a time zone, as the upstream database's `NOW()` is when it's
+/// compared with a column without one (which in MySQL is every date and time column).
+fn current_time(granularity: Duration, utc_offset: FixedOffset) -> DfValue {
+    let now = Utc::now().with_timezone(&utc_offset);
+    let granularity = granularity.as_secs() as i64;
+    let now = if granularity > 0 {
+        let secs = now.timestamp();
+        utc_offset
+            .timestamp_opt(secs - secs.rem_euclid(granularity), 0)
+            .single()
+            .unwrap_or(now)
+    } else {
+        now
+    };
+    DfValue::from(now.naive_local())
+}
+
 /// Splice the given list of extracted parameters, which should be a tuple of (placeholder position,
 /// value) as returned by [`auto_parametrize_query`] into the given list of parameters supplied by
 /// the user, by interleaving them into the params based on the placeholder position.
@@ -1254,10 +1406,10 @@ mod tests {
             params: Vec<DfValue>,
         ) -> (Vec<Vec<DfValue>>, SelectStatement) {
             let mut query = parse_select_statement(query);
-            let processed = process_query(&mut query, false).unwrap();
+            let processed = process_query(&mut query, false, None).unwrap();
             (
                 processed
-                    .make_keys(&params)
+                    .make_keys(&params, FixedOffset::east(0))
                     .unwrap()
                     .into_iter()
                     .map(|c| c.to_vec())
@@ -1274,7 +1426,7 @@ mod tests {
                 "SELECT id FROM users WHERE credit_card_number = $1 AND id = $2",
             );
 
-            process_query(&mut query, false).expect("Should be able to rewrite query");
+            process_query(&mut query, false, None).expect("Should be able to rewrite query");
             assert_eq!(
                 query.display(nom_sql::Dialect::MySQL).to_string(),
                 expected.display(nom_sql::Dialect::MySQL).to_string()
@@ -1288,7 +1440,52 @@ mod tests {
             );
             let expected =
                 parse_select_statement("SELECT id + 3 FROM users WHERE credit_card_number = $1");
-            process_query(&mut query, false).expect("Should be able to rewrite query");
+            process_query(&mut query, false, None).expect("Should be able to rewrite query");
+            assert_eq!(query, expected);
+        }
+
+        #[test]
+        fn now_parameters() {
+            let mut query = parse_select_statement(
+                "SELECT id FROM t WHERE x = ? AND NOW() < expires_at AND y = 4",
+            );
+            let processed =
+                process_query(&mut query, false, Some(Duration::from_secs(60))).unwrap();
+            assert_eq!(
+                query,
+                parse_select_statement(
+                    "SELECT id FROM t WHERE x = $1 AND expires_at > $2 AND y = 4"
+                )
+            );
+
+            let keys = processed
+                .make_keys(&[DfValue::from(1)], FixedOffset::east(0))
+                .unwrap();
+            assert_eq!(keys.len(), 1);
+            assert_eq!(keys[0][0], DfValue::from(1));
+            let now = match &keys[0][1] {
+                DfValue::TimestampTz(ts) => ts.to_chrono(),
+                key => panic!("Expected a timestamp, got {key:?}"),
+            };
+            assert_eq!(now.timestamp() % 60, 0);
+
+            // The current time is bound in the time zone of the upstream session
+            let keys = processed
+                .make_keys(&[DfValue::from(1)], FixedOffset::east(5 * 3600))
+                .unwrap();
+            match &keys[0][1] {
+                DfValue::TimestampTz(ts) => {
+                    let offset = ts.to_chrono().timestamp() - now.timestamp();
+                    // Allow for the minute having ticked over between the two calls
+                    assert!(offset == 5 * 3600 || offset == 5 * 3600 + 60, "{offset}");
+                }
+                key => panic!("Expected a timestamp, got {key:?}"),
+            }
+
+            // Without a granularity, `NOW()` is left alone
+            let mut query = parse_select_statement("SELECT id FROM t WHERE expires_at > NOW()");
+            let expected = query.clone();
+            process_query(&mut query, false, None).unwrap();
             assert_eq!(query, expected);
         }
 
@@ -1461,7 +1658,7 @@ mod tests {
         #[test]
         fn correct_offset_limit() {
             let get_lim_off = |q: &str, p: &[DfValue]| -> (Option<usize>, Option<usize>) {
-                let proc = process_query(&mut parse_select_statement(q), false).unwrap();
+                let proc = process_query(&mut parse_select_statement(q), false, None).unwrap();
                 proc.limit_offset_params(p).unwrap()
             };
 
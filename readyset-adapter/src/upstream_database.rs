@@ -3,13 +3,15 @@ use std::fmt::Debug;
 
 use async_trait::async_trait;
 pub use database_utils::UpstreamConfig;
-use nom_sql::SqlIdentifier;
+use nom_sql::{Relation, SqlIdentifier};
+use readyset_client::replication::ReplicationOffset;
 use readyset_client::ColumnSchema;
 use readyset_client_metrics::QueryDestination;
 use readyset_data::DfValue;
 use readyset_errors::ReadySetError;
 
 use crate::fallback_cache::FallbackCache;
+use crate::result_cache::{ResultCache, ResultCacheKey};
 
 /// Information about a statement that has been prepared in an [`UpstreamDatabase`]
 pub struct UpstreamPrepare<DB: UpstreamDatabase> {
@@ -108,6 +110,13 @@ pub trait UpstreamDatabase: Sized + Send {
     /// Resets the connection with the upstream database
     async fn reset(&mut self) -> Result<(), Self::Error>;
 
+    /// Have the upstream database report the number of rows matched by `UPDATE` statements rather
+    /// than the number of rows they changed, as for a MySQL client that connected with
+    /// `CLIENT_FOUND_ROWS`. Does nothing by default.
+    async fn set_client_found_rows(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Returns the SQL dialect for which to format queries.
     fn sql_dialect() -> nom_sql::Dialect;
 
@@ -148,11 +157,41 @@ pub trait UpstreamDatabase: Sized + Send {
         params: &[DfValue],
     ) -> Result<Self::QueryResult<'a>, Self::Error>;
 
+    /// Discard a statement that was prepared earlier with [`prepare`](UpstreamDatabase::prepare),
+    /// releasing anything held for it in the upstream database. Does nothing by default.
+    async fn remove_statement(&mut self, _statement_id: u32) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Execute a raw, un-prepared query
     async fn query<'a, S>(&'a mut self, query: S) -> Result<Self::QueryResult<'a>, Self::Error>
     where
         S: AsRef<str> + Send + Sync + 'a;
 
+    /// Execute a raw, un-prepared read query which reads from `tables`, returning its results from
+    /// `cache` if they're stored there under `key`, and otherwise storing them in `cache` once
+    /// they've been read from the upstream database. Returns where the results were read from
+    /// alongside them.
+    ///
+    /// Implementations must scope `key` to the user the query is executed as (see
+    /// [`ResultCacheKey::for_user`]), so cached results are only returned to users the upstream
+    /// database allowed to read them.
+    ///
+    /// The default implementation, for upstream databases which can't read the results of a query
+    /// into a [`CachedReadResult`](UpstreamDatabase::CachedReadResult), ignores the cache and
+    /// executes the query with [`query`](UpstreamDatabase::query).
+    async fn query_with_result_cache<'a>(
+        &'a mut self,
+        query: &'a str,
+        _cache: &ResultCache<Self::CachedReadResult>,
+        _key: ResultCacheKey,
+        _tables: &[Relation],
+    ) -> Result<(Self::QueryResult<'a>, QueryDestination), Self::Error> {
+        let result = self.query(query).await?;
+        let destination = result.destination();
+        Ok((result, destination))
+    }
+
     /// Execute a raw, un-prepared write query, constructing and returning a RYW ticket for the
     /// write
     // TODO: newtype RYW ticket, not just String
@@ -172,6 +211,20 @@ pub trait UpstreamDatabase: Sized + Send {
     /// Handle rolling back the ongoing transaction for this connection to the upstream db.
     async fn rollback<'a>(&'a mut self) -> Result<Self::QueryResult<'a>, Self::Error>;
 
+    /// Returns the upstream database's current position in its replication log, which ReadySet
+    /// will have reached once it has replicated every write made so far, or `None` if the upstream
+    /// database can't report it. Returns `None` by default.
+    async fn replication_offset(&mut self) -> Result<Option<ReplicationOffset>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Returns the current offset from UTC, in seconds, of the time zone of the session on the
+    /// upstream database, which the upstream database gives the current time in, or `None` if the
+    /// upstream database can't report it. Returns `None` by default.
+    async fn utc_offset(&mut self) -> Result<Option<i32>, Self::Error> {
+        Ok(None)
+    }
+
     /// Return schema dump from the upstream database, for inclusion in a query analysis bundle.
     async fn schema_dump(&mut self) -> Result<Vec<u8>, anyhow::Error>;
 
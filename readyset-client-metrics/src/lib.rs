@@ -5,6 +5,7 @@ use std::time::{Duration, Instant};
 
 use metrics::SharedString;
 use nom_sql::SqlQuery;
+use readyset_client::internal::IndexType;
 use readyset_client::query::QueryId;
 use readyset_errors::ReadySetError;
 use serde::Serialize;
@@ -44,6 +45,12 @@ pub struct QueryExecutionEvent {
 
     /// Number of cache misses which occurred as part of a query
     pub cache_misses: Option<u64>,
+
+    /// The type of the index that was read from, if the query was executed against ReadySet
+    pub index_type: Option<IndexType>,
+
+    /// The application or client library that issued the query, if the client identified itself
+    pub client: Option<Arc<str>>,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Clone, Copy, Default)]
@@ -53,6 +60,7 @@ pub enum QueryDestination {
     ReadysetThenUpstream,
     Upstream,
     Both,
+    ResultCache,
     #[cfg(feature = "fallback_cache")]
     FallbackCache,
 }
@@ -65,6 +73,7 @@ impl TryFrom<&str> for QueryDestination {
             "readyset_then_upstream" => Ok(QueryDestination::ReadysetThenUpstream),
             "upstream" => Ok(QueryDestination::Upstream),
             "both" => Ok(QueryDestination::Both),
+            "result_cache" => Ok(QueryDestination::ResultCache),
             #[cfg(feature = "fallback_cache")]
             "fallback_cache" => Ok(QueryDestination::FallbackCache),
             _ => Err(ReadySetError::Internal(
@@ -81,6 +90,7 @@ impl fmt::Display for QueryDestination {
             QueryDestination::ReadysetThenUpstream => "readyset_then_upstream",
             QueryDestination::Upstream => "upstream",
             QueryDestination::Both => "both",
+            QueryDestination::ResultCache => "result_cache",
             #[cfg(feature = "fallback_cache")]
             QueryDestination::FallbackCache => "fallback_cache",
         };
@@ -88,6 +98,28 @@ impl fmt::Display for QueryDestination {
     }
 }
 
+/// The path taken to produce the results of a query, used to segment query latencies.
+#[derive(Copy, Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExecutionPath {
+    /// The query was served by ReadySet without any cache misses
+    CacheHit,
+    /// The query was served by ReadySet, but required an upquery for at least one key
+    Upquery,
+    /// The query was proxied to the upstream database, either directly or after failing to
+    /// execute on ReadySet
+    Proxied,
+}
+
+impl From<ExecutionPath> for SharedString {
+    fn from(path: ExecutionPath) -> Self {
+        match path {
+            ExecutionPath::CacheHit => SharedString::const_str("cache_hit"),
+            ExecutionPath::Upquery => SharedString::const_str("upquery"),
+            ExecutionPath::Proxied => SharedString::const_str("proxied"),
+        }
+    }
+}
+
 #[derive(Copy, Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EventType {
     Prepare,
@@ -162,6 +194,36 @@ impl QueryExecutionEvent {
             destination: None,
             cache_misses: None,
             num_keys: None,
+            index_type: None,
+            client: None,
+        }
+    }
+
+    /// Returns the path taken to produce the results of this query, if it was executed against
+    /// either ReadySet or the upstream database (but not both)
+    pub fn execution_path(&self) -> Option<ExecutionPath> {
+        match self.destination? {
+            QueryDestination::Readyset if self.cache_misses.unwrap_or(0) > 0 => {
+                Some(ExecutionPath::Upquery)
+            }
+            QueryDestination::Readyset => Some(ExecutionPath::CacheHit),
+            QueryDestination::ReadysetThenUpstream | QueryDestination::Upstream => {
+                Some(ExecutionPath::Proxied)
+            }
+            QueryDestination::Both | QueryDestination::ResultCache => None,
+            #[cfg(feature = "fallback_cache")]
+            QueryDestination::FallbackCache => None,
+        }
+    }
+
+    /// Returns the total time spent executing this query, across ReadySet and the upstream
+    /// database
+    pub fn execution_duration(&self) -> Option<Duration> {
+        match (self.readyset_duration, self.upstream_duration) {
+            (None, None) => None,
+            (readyset, upstream) => {
+                Some(readyset.unwrap_or_default() + upstream.unwrap_or_default())
+            }
         }
     }
 
@@ -203,3 +265,95 @@ impl<'a> Drop for QueryExecutionTimerHandle<'a> {
         self.duration.replace(self.start.elapsed());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(
+        destination: Option<QueryDestination>,
+        cache_misses: Option<u64>,
+    ) -> QueryExecutionEvent {
+        let mut event = QueryExecutionEvent::new(EventType::Execute);
+        event.destination = destination;
+        event.cache_misses = cache_misses;
+        event
+    }
+
+    mod execution_path {
+        use super::*;
+
+        #[test]
+        fn readyset_without_misses_is_cache_hit() {
+            assert_eq!(
+                event(Some(QueryDestination::Readyset), None).execution_path(),
+                Some(ExecutionPath::CacheHit)
+            );
+            assert_eq!(
+                event(Some(QueryDestination::Readyset), Some(0)).execution_path(),
+                Some(ExecutionPath::CacheHit)
+            );
+        }
+
+        #[test]
+        fn readyset_with_misses_is_upquery() {
+            assert_eq!(
+                event(Some(QueryDestination::Readyset), Some(2)).execution_path(),
+                Some(ExecutionPath::Upquery)
+            );
+        }
+
+        #[test]
+        fn upstream_is_proxied() {
+            assert_eq!(
+                event(Some(QueryDestination::Upstream), None).execution_path(),
+                Some(ExecutionPath::Proxied)
+            );
+            assert_eq!(
+                event(Some(QueryDestination::ReadysetThenUpstream), Some(1)).execution_path(),
+                Some(ExecutionPath::Proxied)
+            );
+        }
+
+        #[test]
+        fn no_path_for_other_destinations() {
+            assert_eq!(event(None, None).execution_path(), None);
+            assert_eq!(
+                event(Some(QueryDestination::Both), None).execution_path(),
+                None
+            );
+            assert_eq!(
+                event(Some(QueryDestination::ResultCache), None).execution_path(),
+                None
+            );
+        }
+    }
+
+    mod execution_duration {
+        use super::*;
+
+        #[test]
+        fn none_without_durations() {
+            assert_eq!(event(None, None).execution_duration(), None);
+        }
+
+        #[test]
+        fn single_duration() {
+            let mut ev = event(Some(QueryDestination::Readyset), None);
+            ev.readyset_duration = Some(Duration::from_millis(3));
+            assert_eq!(ev.execution_duration(), Some(Duration::from_millis(3)));
+
+            let mut ev = event(Some(QueryDestination::Upstream), None);
+            ev.upstream_duration = Some(Duration::from_millis(5));
+            assert_eq!(ev.execution_duration(), Some(Duration::from_millis(5)));
+        }
+
+        #[test]
+        fn sums_readyset_and_upstream() {
+            let mut ev = event(Some(QueryDestination::ReadysetThenUpstream), None);
+            ev.readyset_duration = Some(Duration::from_millis(3));
+            ev.upstream_duration = Some(Duration::from_millis(5));
+            assert_eq!(ev.execution_duration(), Some(Duration::from_millis(8)));
+        }
+    }
+}
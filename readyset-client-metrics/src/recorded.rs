@@ -9,6 +9,7 @@
 /// | database_type | The database type being executed. Must be a [`DatabaseType`] |
 /// | query_type | SqlQueryType, whether the query was a read or write. |
 /// | event_type | EventType, whether the query was a prepare, execute, or query.  |
+/// | client | The application or client library that issued the query, if it identified itself. |
 ///
 /// [`DatabaseType`]: crate::DatabaseType
 pub const QUERY_LOG_EXECUTION_TIME: &str = "query-log.execution_time";
@@ -21,14 +22,31 @@ pub const QUERY_LOG_EXECUTION_TIME: &str = "query-log.execution_time";
 /// | query | The query text being executed. |
 /// | query_type | SqlQueryType, whether the query was a read or write. |
 /// | event_type | EventType, whether the query was a prepare, execute, or query.  |
+/// | client | The application or client library that issued the query, if it identified itself. |
 pub const QUERY_LOG_PARSE_TIME: &str = "query-log.parse_time";
 
+/// Histogram: The total time in seconds spent executing a query, across ReadySet and the
+/// upstream database, segmented by the path taken to produce its results.
+///
+/// | Tag | Description |
+/// | --- | ----------- |
+/// | query | The query text being executed. |
+/// | query_id | The ReadySet query id, if the query has one. |
+/// | query_type | SqlQueryType, whether the query was a read or write. |
+/// | event_type | EventType, whether the query was a prepare, execute, or query.  |
+/// | path | The [`ExecutionPath`] taken: `cache_hit`, `upquery`, or `proxied`. |
+/// | client | The application or client library that issued the query, if it identified itself. |
+///
+/// [`ExecutionPath`]: crate::ExecutionPath
+pub const QUERY_LOG_EXECUTION_PATH_TIME: &str = "query-log.execution_path_time";
+
 /// Counter: The number of individual keys read for a query. This will be greater than the number of
 /// times the query was executed in the case of `IN` queries.
 ///
 /// | Tag | Description |
 /// | --- | ----------- |
 /// | query | The query text being executed. |
+/// | client | The application or client library that issued the query, if it identified itself. |
 pub const QUERY_LOG_TOTAL_KEYS_READ: &str = "query-log.total_keys_read";
 
 /// Counter: The number of cache misses which occurred, potentially multiple from a single query.
@@ -36,6 +54,7 @@ pub const QUERY_LOG_TOTAL_KEYS_READ: &str = "query-log.total_keys_read";
 /// | Tag | Description |
 /// | --- | ----------- |
 /// | query | The query text being executed. |
+/// | client | The application or client library that issued the query, if it identified itself. |
 pub const QUERY_LOG_TOTAL_CACHE_MISSES: &str = "query-log.total_cache_misses";
 
 /// Counter: The number of queries which encountered at least one cache miss.
@@ -43,6 +62,7 @@ pub const QUERY_LOG_TOTAL_CACHE_MISSES: &str = "query-log.total_cache_misses";
 /// | Tag | Description |
 /// | --- | ----------- |
 /// | query | The query text being executed. |
+/// | client | The application or client library that issued the query, if it identified itself. |
 pub const QUERY_LOG_QUERY_CACHE_MISSED: &str = "query-log.query_cache_missed";
 
 /// Counter: The number of successful queries (dry runs/real) processed by the migration handler.
@@ -62,3 +82,16 @@ pub const ADAPTER_EXTERNAL_REQUESTS: &str = "noria-client.external_requests";
 
 /// Gauge: The number of currently connected SQL clients
 pub const CONNECTED_CLIENTS: &str = "noria-client.connected_clients";
+
+/// Counter: The number of bytes saved by compressing the data sent to MySQL clients that
+/// negotiated the compressed protocol.
+pub const MYSQL_COMPRESSION_BYTES_SAVED: &str = "noria-client.mysql_compression.bytes_saved";
+
+/// Counter: The number of writes to MySQL clients that negotiated the compressed protocol which
+/// were sent uncompressed.
+///
+/// | Tag | Description |
+/// | --- | ----------- |
+/// | reason | `short` if the write was shorter than the minimum length to compress, or
+/// `incompressible` if a recent write to the client didn't compress well. |
+pub const MYSQL_COMPRESSION_SKIPPED_WRITES: &str = "noria-client.mysql_compression.skipped_writes";
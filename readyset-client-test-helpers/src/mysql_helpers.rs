@@ -3,7 +3,7 @@ use std::fmt::Display;
 
 use async_trait::async_trait;
 use mysql_async::prelude::Queryable;
-use mysql_srv::MySqlIntermediary;
+use mysql_srv::{CompressionOptions, MySqlIntermediary};
 use readyset_adapter::backend::QueryInfo;
 use readyset_mysql::{Backend, MySqlQueryHandler, MySqlUpstream};
 use tokio::net::TcpStream;
@@ -87,12 +87,10 @@ impl Adapter for MySQLAdapter {
         s: TcpStream,
     ) {
         MySqlIntermediary::run_on_tcp(
-            Backend {
-                noria: backend,
-                enable_statement_logging: false,
-            },
+            Backend::new(backend),
             s,
-            false,
+            None,
+            CompressionOptions::default(),
         )
         .await
         .unwrap()
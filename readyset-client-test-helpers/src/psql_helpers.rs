@@ -70,7 +70,7 @@ impl Adapter for PostgreSQLAdapter {
     }
 
     async fn run_backend(backend: Backend<Self::Upstream, Self::Handler>, s: TcpStream) {
-        psql_srv::run_backend(readyset_psql::Backend::new(backend), s, false, None).await
+        psql_srv::run_backend(readyset_psql::Backend::new(backend), s, None).await
     }
 }
 
@@ -90,9 +90,11 @@ pub async fn last_query_info(conn: &Client) -> QueryInfo {
 
     let destination = QueryDestination::try_from(row.get("Query_destination").unwrap()).unwrap();
     let noria_error = row.get("ReadySet_error").unwrap().to_owned();
+    let index_type = row.get("ReadySet_index").and_then(|s| s.parse().ok());
 
     QueryInfo {
         destination,
         noria_error,
+        index_type,
     }
 }
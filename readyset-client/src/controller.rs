@@ -8,7 +8,7 @@ use std::time::{Duration, Instant};
 
 use futures_util::future;
 use hyper::client::HttpConnector;
-use nom_sql::{Relation, SelectStatement};
+use nom_sql::{CreateCacheStatement, Relation, SelectStatement};
 use parking_lot::RwLock;
 use petgraph::graph::NodeIndex;
 use readyset_errors::{
@@ -28,9 +28,13 @@ use crate::debug::stats;
 use crate::metrics::MetricsDump;
 use crate::recipe::changelist::ChangeList;
 use crate::recipe::ExtendRecipeSpec;
-use crate::replication::ReplicationOffsets;
+use crate::replication::{
+    ReplicationCaptureRequest, ReplicationOffsets, ReplicationSourceHealth, TableChecksum,
+    TableConsistency,
+};
 use crate::status::ReadySetStatus;
 use crate::table::{Table, TableBuilder, TableRpc};
+use crate::typed::{FromRow, IntoParams, TypedView};
 use crate::view::{View, ViewBuilder, ViewRpc};
 use crate::{NodeSize, ReplicationOffset, TableStatus, ViewCreateRequest, ViewFilter, ViewRequest};
 
@@ -346,6 +350,14 @@ impl ReadySetHandle {
         self.simple_get_request("verbose_views").await
     }
 
+    /// Returns the `CREATE CACHE` statements for all caches, ordered by name. Running these
+    /// statements against a fresh deployment re-creates the same set of caches.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub async fn cache_statements(&mut self) -> ReadySetResult<Vec<CreateCacheStatement>> {
+        self.simple_get_request("cache_statements").await
+    }
+
     /// For each of the given list of queries, determine whether that query (or a semantically
     /// equivalent query) has been created as a `View`.
     ///
@@ -375,6 +387,23 @@ impl ReadySetHandle {
         self.request_view(request)
     }
 
+    /// Obtain a [`TypedView`] for executing the cached query with the given name, with parameters
+    /// of type `P` and rows of type `R`.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub async fn typed_view<P, R, I>(
+        &mut self,
+        name: I,
+        dialect: dataflow_expression::Dialect,
+    ) -> ReadySetResult<TypedView<P, R>>
+    where
+        P: IntoParams,
+        R: FromRow,
+        I: Into<Relation>,
+    {
+        Ok(TypedView::new(self.view(name).await?, dialect))
+    }
+
     /// Obtain a `View` from the given pool of workers, that allows you to query the given external
     /// view.
     ///
@@ -523,6 +552,27 @@ impl ReadySetHandle {
         self.rpc("get_statistics", (), self.request_timeout)
     }
 
+    /// Get statistics about the reads of, and evictions from, each cache, including at most
+    /// `max_keys` of the most frequently read keys of each cache.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn cache_stats(
+        &mut self,
+        max_keys: usize,
+    ) -> impl Future<Output = ReadySetResult<BTreeMap<Relation, stats::CacheStats>>> + '_ {
+        self.rpc("cache_stats", max_keys, self.request_timeout)
+    }
+
+    /// Get the approximate memory used by base tables, materialized state, and the readers of
+    /// each cache, along with the configured memory budget and per-cache quota.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn memory_usage(
+        &mut self,
+    ) -> impl Future<Output = ReadySetResult<stats::MemoryUsage>> + '_ {
+        self.rpc("memory_usage", (), self.request_timeout)
+    }
+
     /// Flush all partial state, evicting all rows present.
     ///
     /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
@@ -604,6 +654,107 @@ impl ReadySetHandle {
         self.rpc("remove_all_queries", (), self.migration_timeout)
     }
 
+    /// Drop all caches and re-create them from their `CREATE CACHE` statements against the current
+    /// schema, as part of a single migration, using the given [`Dialect`] for expression
+    /// evaluation semantics. Caches can't be re-created against any schema other than the current
+    /// one.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    ///
+    /// [`Dialect`]: dataflow_expression::Dialect
+    pub fn restore_all_queries(
+        &mut self,
+        dialect: dataflow_expression::Dialect,
+    ) -> impl Future<Output = ReadySetResult<()>> + '_ {
+        self.rpc("restore_all_queries", dialect, self.migration_timeout)
+    }
+
+    /// Ask the replicator to drop the rows of `table` and copy them from the upstream database
+    /// again, without restarting replication for any other table. Returns once the request has
+    /// been made, before the table has been resnapshotted.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn resnapshot_table(
+        &mut self,
+        table: &Relation,
+    ) -> impl Future<Output = ReadySetResult<()>> + '_ {
+        self.rpc("resnapshot_table", table, self.request_timeout)
+    }
+
+    /// Log every write and replay to the readers of the cache named `name` for `duration`, for
+    /// `ALTER READYSET TRACE QUERY`. Returns `false` if there is no cache named `name`.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn trace_reader(
+        &mut self,
+        name: &Relation,
+        duration: Duration,
+    ) -> impl Future<Output = ReadySetResult<bool>> + '_ {
+        self.rpc("trace_reader", (name, duration), self.request_timeout)
+    }
+
+    /// Ask the replicator to capture the replication events it receives from the upstream database
+    /// to a file, for the duration given in `request`. Returns once the capture has started.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn capture_replication_events(
+        &mut self,
+        request: &ReplicationCaptureRequest,
+    ) -> impl Future<Output = ReadySetResult<()>> + '_ {
+        self.rpc("capture_replication_events", request, self.request_timeout)
+    }
+
+    /// Enable or disable statement logging for the replicator, which runs in the leader's process,
+    /// if `enabled` is `Some`, and return whether the replicator is logging statements.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn replicator_statement_logging(
+        &mut self,
+        enabled: Option<bool>,
+    ) -> impl Future<Output = ReadySetResult<bool>> + '_ {
+        self.rpc(
+            "replicator_statement_logging",
+            enabled,
+            self.request_timeout,
+        )
+    }
+
+    /// Compute a checksum of the rows of the base table `table`, which doesn't depend on the order
+    /// of the rows.
+    ///
+    /// Every row of the table is read to compute the checksum, so this can take a while for large
+    /// tables.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn table_checksum(
+        &mut self,
+        table: &Relation,
+    ) -> impl Future<Output = ReadySetResult<TableChecksum>> + '_ {
+        self.rpc("table_checksum", table, self.request_timeout)
+    }
+
+    /// Returns the result of the most recent consistency check of each replicated table, if
+    /// consistency checks are enabled with `--consistency-check-interval-secs`.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn consistency_check(
+        &mut self,
+    ) -> impl Future<Output = ReadySetResult<BTreeMap<Relation, TableConsistency>>> + '_ {
+        self.rpc("consistency_check", (), self.request_timeout)
+    }
+
+    /// Returns the health of replication from each upstream database, keyed by the name of the
+    /// additional upstream database, or `None` for the primary upstream database. Empty if
+    /// ReadySet isn't replicating from an upstream database.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn replication_health(
+        &mut self,
+    ) -> impl Future<Output = ReadySetResult<BTreeMap<Option<String>, ReplicationSourceHealth>>> + '_
+    {
+        self.rpc("replication_health", (), self.request_timeout)
+    }
+
     /// Set the replication offset for the schema, which is stored with the recipe.
     ///
     /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
@@ -618,6 +769,49 @@ impl ReadySetHandle {
         )
     }
 
+    /// Set the replication offset for the schema of the additional upstream database named
+    /// `source`, which is stored with the recipe alongside the schema offset of the primary
+    /// upstream. Passing `None` clears it.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn set_source_schema_replication_offset(
+        &mut self,
+        source: &str,
+        replication_offset: Option<&ReplicationOffset>,
+    ) -> impl Future<Output = ReadySetResult<()>> + '_ {
+        self.rpc(
+            "set_source_schema_replication_offset",
+            (source, replication_offset),
+            self.request_timeout,
+        )
+    }
+
+    /// Get the set of tables the replicator has stopped replicating because of a destructive schema
+    /// change, which is stored with the recipe.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn quarantined_tables(
+        &mut self,
+    ) -> impl Future<Output = ReadySetResult<HashSet<Relation>>> + '_ {
+        self.rpc("quarantined_tables", (), self.request_timeout)
+    }
+
+    /// Record whether the replicator has stopped replicating `table` because of a destructive
+    /// schema change.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn set_table_quarantined(
+        &mut self,
+        table: &Relation,
+        quarantined: bool,
+    ) -> impl Future<Output = ReadySetResult<()>> + '_ {
+        self.rpc(
+            "set_table_quarantined",
+            (table, quarantined),
+            self.request_timeout,
+        )
+    }
+
     /// Fetch a graphviz description of the dataflow graph.
     ///
     /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
@@ -1,6 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
+use nom_sql::Relation;
 use petgraph::graph::NodeIndex;
+use readyset_data::DfValue;
 use serde::{Deserialize, Serialize};
 
 use crate::internal::*;
@@ -44,6 +46,124 @@ pub struct NodeStats {
     pub probe_result: HashMap<String, String>,
 }
 
+/// Statistics about the reads of, and evictions from, a single key in a cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyStats {
+    /// The values of the key
+    pub key: Vec<DfValue>,
+    /// The number of times the key was read from the cache without missing
+    pub hits: u64,
+    /// The number of times the key was read from the cache and missed
+    pub misses: u64,
+    /// The number of times the key was evicted from the cache
+    pub evictions: u64,
+}
+
+/// Statistics about the reads of, and evictions from, the reader of a cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// The total number of key lookups into the cache that hit
+    pub hits: u64,
+    /// The total number of key lookups into the cache that missed
+    pub misses: u64,
+    /// The number of times keys were evicted from the cache to free memory
+    pub evictions: u64,
+    /// The total number of keys evicted from the cache, either to free memory or because the
+    /// key was evicted upstream of the cache
+    pub evicted_keys: u64,
+    /// The total number of bytes freed by evicting keys from the cache
+    pub evicted_bytes: u64,
+    /// Statistics about the most frequently read keys in the cache, most frequently read first
+    pub keys: Vec<KeyStats>,
+}
+
+impl CacheStats {
+    /// Add the statistics of another shard or replica of the same cache to these statistics,
+    /// keeping at most `max_keys` keys
+    pub fn merge(&mut self, other: CacheStats, max_keys: usize) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.evictions += other.evictions;
+        self.evicted_keys += other.evicted_keys;
+        self.evicted_bytes += other.evicted_bytes;
+        // Shards of a cache never share keys, but replicas of the same shard do
+        for key_stats in other.keys {
+            match self.keys.iter_mut().find(|k| k.key == key_stats.key) {
+                Some(existing) => {
+                    existing.hits += key_stats.hits;
+                    existing.misses += key_stats.misses;
+                    existing.evictions += key_stats.evictions;
+                }
+                None => self.keys.push(key_stats),
+            }
+        }
+        self.keys
+            .sort_by(|a, b| (b.hits + b.misses).cmp(&(a.hits + a.misses)));
+        self.keys.truncate(max_keys);
+    }
+}
+
+/// The approximate memory used by the dataflow state of a single cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheMemoryUsage {
+    /// The size of the cache's reader, in bytes
+    pub reader_bytes: u64,
+    /// The total size of the materialized state of the non-base-table nodes the cache reads from,
+    /// in bytes. State shared with other caches is counted towards each of them.
+    pub state_bytes: u64,
+}
+
+impl CacheMemoryUsage {
+    /// Returns the total size of the cache's reader and state, in bytes
+    pub fn total_bytes(&self) -> u64 {
+        self.reader_bytes + self.state_bytes
+    }
+}
+
+/// The approximate memory used by the dataflow state, broken down by the kind of state and by
+/// cache, along with the configured limits on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// The total size of all base tables, in bytes
+    pub base_table_bytes: u64,
+    /// The total size of the materialized state of all nodes other than base tables and readers,
+    /// in bytes
+    pub state_bytes: u64,
+    /// The total size of all readers, in bytes
+    pub reader_bytes: u64,
+    /// The memory used by each cache
+    pub caches: BTreeMap<Relation, CacheMemoryUsage>,
+    /// The budget, in bytes, for the total size of materialized state and readers that new caches
+    /// are admitted against, if any
+    pub memory_budget: Option<u64>,
+    /// The maximum size, in bytes, of the reader of each cache, if any
+    pub cache_memory_quota: Option<u64>,
+}
+
+impl MemoryUsage {
+    /// Returns the total size of materialized state and readers, which is what's counted against
+    /// [`Self::memory_budget`]
+    pub fn in_memory_bytes(&self) -> u64 {
+        self.state_bytes + self.reader_bytes
+    }
+
+    /// Returns the memory we expect a new cache to use: its quota if there is one, or otherwise
+    /// the average memory used by the existing caches
+    pub fn projected_cache_bytes(&self) -> u64 {
+        match self.cache_memory_quota {
+            Some(quota) => quota,
+            None if self.caches.is_empty() => 0,
+            None => {
+                self.caches
+                    .values()
+                    .map(CacheMemoryUsage::total_bytes)
+                    .sum::<u64>()
+                    / self.caches.len() as u64
+            }
+        }
+    }
+}
+
 /// Statistics about the Soup data-flow.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GraphStats {
@@ -57,3 +177,48 @@ impl Deref for GraphStats {
         &self.domains
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(reader_bytes: u64, state_bytes: u64) -> CacheMemoryUsage {
+        CacheMemoryUsage {
+            reader_bytes,
+            state_bytes,
+        }
+    }
+
+    #[test]
+    fn in_memory_bytes_excludes_base_tables() {
+        let usage = MemoryUsage {
+            base_table_bytes: 1000,
+            state_bytes: 20,
+            reader_bytes: 3,
+            ..Default::default()
+        };
+        assert_eq!(usage.in_memory_bytes(), 23);
+    }
+
+    #[test]
+    fn projected_cache_bytes_uses_quota() {
+        let usage = MemoryUsage {
+            caches: [("q".into(), cache(100, 100))].into_iter().collect(),
+            cache_memory_quota: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(usage.projected_cache_bytes(), 50);
+    }
+
+    #[test]
+    fn projected_cache_bytes_averages_existing_caches() {
+        let usage = MemoryUsage {
+            caches: [("q1".into(), cache(10, 20)), ("q2".into(), cache(30, 0))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(usage.projected_cache_bytes(), 30);
+        assert_eq!(MemoryUsage::default().projected_cache_bytes(), 0);
+    }
+}
@@ -1,4 +1,6 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
 
 use nom_sql::BinaryOperator;
 use serde::{Deserialize, Serialize};
@@ -82,6 +84,27 @@ impl IndexType {
     }
 }
 
+impl fmt::Display for IndexType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexType::HashMap => write!(f, "HashMap"),
+            IndexType::BTreeMap => write!(f, "BTreeMap"),
+        }
+    }
+}
+
+impl FromStr for IndexType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HashMap" => Ok(IndexType::HashMap),
+            "BTreeMap" => Ok(IndexType::BTreeMap),
+            _ => Err(format!("Invalid index type: {s}")),
+        }
+    }
+}
+
 /// A description of an index used on a relation
 #[derive(Debug, Clone, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize, Hash)]
 pub struct Index {
@@ -256,6 +256,7 @@ pub mod metrics;
 pub mod query;
 pub mod status;
 mod table;
+pub mod typed;
 mod view;
 use std::convert::TryFrom;
 use std::default::Default;
@@ -364,8 +365,8 @@ pub use crate::table::{
     TableReplicationStatus, TableRequest, TableStatus,
 };
 pub use crate::view::{
-    KeyComparison, LookupResult, ReadQuery, ReadReply, ReadReplyBatch, ReadReplyStats, SchemaType,
-    View, ViewCreateRequest, ViewQuery,
+    KeyComparison, LookupResult, ReadPage, ReadQuery, ReadReply, ReadReplyBatch, ReadReplyStats,
+    SchemaType, View, ViewCreateRequest, ViewQuery,
 };
 
 pub mod builders {
@@ -435,6 +436,13 @@ pub enum KeyCount {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NodeMaterializedSize(usize);
 
+impl NodeMaterializedSize {
+    /// Returns the size in bytes
+    pub fn bytes(self) -> usize {
+        self.0
+    }
+}
+
 impl Display for KeyCount {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
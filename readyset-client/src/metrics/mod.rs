@@ -263,6 +263,30 @@ pub mod recorded {
     /// within a domain.
     pub const READER_STATE_SIZE_BYTES: &str = "reader_state_size_bytes";
 
+    /// Counter: The number of key lookups into a reader that hit. Updated at the domain each time
+    /// its state sizes are updated.
+    ///
+    /// | Tag | Description |
+    /// | --- | ----------- |
+    /// | cache_name | The name of the cache the reader belongs to. |
+    pub const READER_KEY_HITS: &str = "reader.key_hits";
+
+    /// Counter: The number of key lookups into a reader that missed. Updated at the domain each
+    /// time its state sizes are updated.
+    ///
+    /// | Tag | Description |
+    /// | --- | ----------- |
+    /// | cache_name | The name of the cache the reader belongs to. |
+    pub const READER_KEY_MISSES: &str = "reader.key_misses";
+
+    /// Counter: The number of keys evicted from a reader. Updated at the domain each time its
+    /// state sizes are updated.
+    ///
+    /// | Tag | Description |
+    /// | --- | ----------- |
+    /// | cache_name | The name of the cache the reader belongs to. |
+    pub const READER_EVICTED_KEYS: &str = "reader.evicted_keys";
+
     /// Gauge: The sum of the amount of bytes used to store a node's base tables
     /// on disk.
     pub const ESTIMATED_BASE_TABLE_SIZE_BYTES: &str = "base_tables_estimated_size_bytes";
@@ -386,12 +410,65 @@ pub mod recorded {
     /// log.
     pub const REPLICATOR_FAILURE: &str = "replicator.update_failure";
 
+    /// Counter: Number of times replication could not be resumed because the replication offset
+    /// it needed had been purged from the upstream database
+    pub const REPLICATOR_OFFSET_PURGED: &str = "replicator.offset_purged";
+
+    /// Counter: Number of corrupt or unrecognized events read from the replication log, each of
+    /// which causes the replicator to request the log again
+    pub const REPLICATOR_INVALID_EVENT: &str = "replicator.invalid_event";
+
+    /// Counter: Number of destructive schema changes to replicated tables that the replicator
+    /// didn't apply, quarantining the tables they were made to instead. Incremented with the
+    /// label `table_name`.
+    pub const REPLICATOR_DDL_QUARANTINED: &str = "replicator.ddl_quarantined";
+
+    /// Gauge: Number of seconds between the time the upstream database executed the statement
+    /// whose replication event was read last and the time it was read. Set to 0 when the upstream
+    /// database tells us it has no new events.
+    pub const REPLICATOR_LAG: &str = "replicator.lag_seconds";
+
+    /// Counter: Number of times MySQL replication switched to a different server to read the
+    /// binlog from, out of the primary and the replicas given by `--replication-source-candidates`.
+    /// Incremented with the labels `from` and `to` (the host of each server).
+    pub const REPLICATOR_SOURCE_SWITCHES: &str = "replicator.source_switches";
+
+    /// Gauge: Number of bytes of WAL the upstream Postgres database is retaining for the
+    /// replication slot, between the slot's restart LSN and the current WAL position.
+    pub const REPLICATOR_SLOT_RETAINED_WAL: &str = "replicator.slot_retained_wal_bytes";
+
+    /// Counter: Number of rows inserted, updated or deleted in a replicated table, as read from the
+    /// MySQL binlog. Incremented with the labels `table_name` (the table's schema and name, such
+    /// as `db.users`) and `operation` (one of `insert`, `update` or `delete`).
+    pub const REPLICATOR_TABLE_ROWS: &str = "replicator.table_rows";
+
+    /// Counter: Number of bytes of MySQL binlog row events decoded for a replicated table.
+    /// Incremented with the label `table_name`.
+    pub const REPLICATOR_TABLE_BYTES: &str = "replicator.table_bytes";
+
+    /// Histogram: The time in microseconds it took to convert the rows of a single MySQL binlog
+    /// row event for a replicated table to ReadySet table operations. Recorded with the label
+    /// `table_name`.
+    pub const REPLICATOR_TABLE_CONVERSION_TIME: &str = "replicator.table_conversion_time_us";
+
     /// Counter: Number of tables that failed to replicate and are ignored
     pub const TABLE_FAILED_TO_REPLICATE: &str = "replicator.table_failed";
 
     /// Counter: Number of replication actions performed successfully.
     pub const REPLICATOR_SUCCESS: &str = "replicator.update_success";
 
+    /// Gauge: Whether a replicated table has diverged from the upstream database, according to the
+    /// most recent consistency check of the table. Set to 1 if the checksums of the table's rows
+    /// have differed in two checks in a row, and 0 otherwise. Recorded with the label
+    /// `table_name`.
+    pub const REPLICATOR_CONSISTENCY_CHECK_DIVERGED: &str = "replicator.consistency_check.diverged";
+
+    /// Counter: Number of consistency checks of a replicated table that found its checksum in
+    /// ReadySet to differ from its checksum in the upstream database. Incremented with the label
+    /// `table_name`.
+    pub const REPLICATOR_CONSISTENCY_CHECK_MISMATCHES: &str =
+        "replicator.consistency_check.mismatches";
+
     /// Gauge: Indicates whether a server is the leader. Set to 1 when the
     /// server is leader, 0 for follower.
     pub const CONTROLLER_IS_LEADER: &str = "controller.is_leader";
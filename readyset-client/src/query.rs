@@ -19,6 +19,10 @@ use serde::{Deserialize, Serialize, Serializer};
 
 use crate::ViewCreateRequest;
 
+/// The `tracing` target for the structured events emitted for queries traced with `ALTER READYSET
+/// TRACE QUERY`, both by the adapter and by the readers of the query's cache
+pub const QUERY_TRACE_TARGET: &str = "readyset_query_trace";
+
 /// A QueryId is a string with the prefix `q_` and the suffix of the hash of the query
 #[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[repr(transparent)]
@@ -181,6 +185,8 @@ pub struct QueryStatus {
     pub execution_info: Option<ExecutionInfo>,
     /// If we should always cache the query (never proxy to upstream)
     pub always: bool,
+    /// If the query is unsupported, the reason ReadySet can't cache it, if known
+    pub unsupported_reason: Option<String>,
 }
 
 impl QueryStatus {
@@ -191,6 +197,10 @@ impl QueryStatus {
             migration_state: MigrationState::default_for_query(query),
             execution_info: None,
             always: false,
+            unsupported_reason: match query {
+                Query::Parsed(_) => None,
+                Query::ParseFailed(_) => Some("Query failed to parse".to_owned()),
+            },
         }
     }
 
@@ -200,6 +210,7 @@ impl QueryStatus {
             migration_state,
             execution_info: None,
             always: false,
+            unsupported_reason: None,
         }
     }
 
@@ -2,14 +2,31 @@
 
 use std::borrow::Borrow;
 use std::cmp::{min_by_key, Ordering};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime};
 
 use nom_sql::Relation;
+use readyset_data::DfValue;
 use readyset_errors::{ReadySetError, ReadySetResult};
 use serde::{Deserialize, Serialize};
 
+mod gtid;
+
+pub use gtid::GtidSet;
+
+/// The current version of the encoding used for [`ReplicationOffset`]s which are persisted to disk.
+///
+/// Persisted offsets are tagged with the version of the encoding they were written with, so that
+/// offsets written by an older version of ReadySet can be converted on startup (see
+/// [`ReplicationOffset::migrate`]) rather than forcing a full resnapshot. Version `0` refers to
+/// offsets that were persisted before they were tagged with a version.
+///
+/// Version `2` added the [`gtid_set`](ReplicationOffset::gtid_set) of MySQL offsets.
+pub const REPLICATION_OFFSET_VERSION: u8 = 2;
+
 /// Enum representing whether a base table node was already initialized (and has a replication
 /// offset assigned), or if it is still pending initialization.
 #[derive(Serialize, Deserialize)]
@@ -35,6 +52,12 @@ pub struct ReplicationOffset {
     /// The name of the replication log that this offset is within. [`ReplicationOffset`]s with
     /// different log names are not comparable
     pub replication_log_name: String,
+
+    /// For MySQL, the set of GTIDs executed as of this offset (in the format of
+    /// `@@gtid_executed`), if GTIDs are enabled on the upstream database. This allows resuming
+    /// replication with GTID auto-positioning. Not taken into account when comparing offsets.
+    #[serde(default)]
+    pub gtid_set: Option<String>,
 }
 
 impl fmt::Display for ReplicationOffset {
@@ -70,6 +93,36 @@ impl PartialOrd for ReplicationOffset {
 }
 
 impl ReplicationOffset {
+    /// Construct an offset for the given position within a MySQL binary log file, named like
+    /// `binlog.000001`.
+    ///
+    /// We use the binlog basefile name as the [`replication_log_name`][Self::replication_log_name],
+    /// and we use the binlog suffix len for the top 5 bits of the offset, which can be as big as 31
+    /// digits in theory, but we only allow up to 17 decimal digits, which is more than enough for
+    /// the binlog spec. This is required to be able to properly format the integer back to string,
+    /// including any leading zeroes. The following 59 bits are used for the numerical value of the
+    /// suffix, finally the last 64 bits of the offset are the actual binlog offset.
+    pub fn from_binlog_position(binlog_file: &str, position: u64) -> ReadySetResult<Self> {
+        let invalid =
+            |what| ReadySetError::ReplicationFailed(format!("Invalid binlog {what} {binlog_file}"));
+        let (basename, suffix) = binlog_file
+            .rsplit_once('.')
+            .ok_or_else(|| invalid("name"))?;
+
+        let suffix_len = suffix.len() as u128;
+        if suffix_len > 17 {
+            // 17 digit decimal number is the most we can fit into 59 bits
+            return Err(invalid("suffix"));
+        }
+        let suffix = suffix.parse::<u128>().map_err(|_| invalid("suffix"))?;
+
+        Ok(ReplicationOffset {
+            offset: (suffix_len << 123) + (suffix << 64) + (position as u128),
+            replication_log_name: basename.to_string(),
+            gtid_set: None,
+        })
+    }
+
     /// Try to mutate `other` to take the maximum of its offset and the offset of
     /// `self`. If `other` is `None`, will assign it to `Some(self.clone)`.
     ///
@@ -85,7 +138,8 @@ impl ReplicationOffset {
             }
 
             if self.offset > other.offset {
-                other.offset = self.offset
+                other.offset = self.offset;
+                other.gtid_set = self.gtid_set.clone();
             }
         } else {
             *other = Some(self.clone())
@@ -93,6 +147,32 @@ impl ReplicationOffset {
 
         Ok(())
     }
+
+    /// Convert an offset which was persisted using the given encoding `version` into an offset
+    /// using the current encoding, [`REPLICATION_OFFSET_VERSION`].
+    ///
+    /// Returns an error if `version` is newer than the current version, since that means the
+    /// offset was written by a newer version of ReadySet and we don't know how to interpret it.
+    pub fn migrate(self, version: u8) -> ReadySetResult<Self> {
+        match version {
+            // Untagged offsets use the same encoding as version 1: MySQL offsets are bit-packed
+            // binlog file suffixes and positions (see the `TryFrom<&BinlogPosition>` impl in the
+            // replicators crate), and PostgreSQL offsets are LSNs, so all we need to do is tag
+            // them.
+            //
+            // Neither version recorded a GTID set, which deserializes to `None`, so resuming from
+            // a converted MySQL offset falls back to positioning by binlog file and offset.
+            0 | 1 => Ok(Self {
+                gtid_set: None,
+                ..self
+            }),
+            REPLICATION_OFFSET_VERSION => Ok(self),
+            _ => Err(ReadySetError::UnknownReplicationOffsetVersion {
+                version,
+                current: REPLICATION_OFFSET_VERSION,
+            }),
+        }
+    }
 }
 
 /// Set of replication offsets for the entire system
@@ -106,6 +186,11 @@ pub struct ReplicationOffsets {
     ///
     /// A table with [`None`] as its replication offset has not yet been snapshotted successfully
     pub tables: HashMap<Relation, Option<ReplicationOffset>>,
+
+    /// Replication offsets for the schema of each additional upstream database we replicate from,
+    /// by the name of the upstream. See [`ReplicationOffsets::for_source`].
+    #[serde(default)]
+    pub sources: HashMap<String, ReplicationOffset>,
 }
 
 impl ReplicationOffsets {
@@ -115,6 +200,56 @@ impl ReplicationOffsets {
         Self {
             schema,
             tables: HashMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Narrow this set of replication offsets down to those of a single upstream database: the
+    /// tables for which `replicated_from` returns true, and the schema offset of the additional
+    /// upstream named `source`, or of the primary upstream if `source` is `None`.
+    ///
+    /// Each upstream writes to its own set of tables and keeps track of its own position in the
+    /// schema, so offsets from different upstreams (which need not even share a replication log)
+    /// are never compared with each other.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use readyset_client::replication::{ReplicationOffset, ReplicationOffsets};
+    ///
+    /// let offset = |offset| ReplicationOffset {
+    ///     replication_log_name: "binlog".to_string(),
+    ///     offset,
+    ///     gtid_set: None,
+    /// };
+    ///
+    /// let mut replication_offsets = ReplicationOffsets::with_schema_offset(Some(offset(1)));
+    /// replication_offsets
+    ///     .tables
+    ///     .insert("t1".into(), Some(offset(2)));
+    /// replication_offsets
+    ///     .tables
+    ///     .insert("t2".into(), Some(offset(3)));
+    /// replication_offsets.sources.insert("t2".into(), offset(4));
+    ///
+    /// let source = replication_offsets.for_source(Some("t2"), |table| table.name.as_str() == "t2");
+    /// assert_eq!(source.schema, Some(offset(4)));
+    /// assert_eq!(source.tables.len(), 1);
+    /// assert!(source.has_table(&"t2".into()));
+    /// ```
+    pub fn for_source<F>(mut self, source: Option<&str>, replicated_from: F) -> Self
+    where
+        F: Fn(&Relation) -> bool,
+    {
+        let schema = match source {
+            Some(source) => self.sources.remove(source),
+            None => self.schema,
+        };
+        self.tables.retain(|table, _| replicated_from(table));
+        Self {
+            schema,
+            tables: self.tables,
+            sources: HashMap::new(),
         }
     }
 
@@ -157,6 +292,7 @@ impl ReplicationOffsets {
     ///     Some(ReplicationOffset {
     ///         replication_log_name: "binlog".to_string(),
     ///         offset: 1,
+    ///         gtid_set: None,
     ///     }),
     /// );
     /// assert!(replication_offsets.has_table(&"table_1".into()));
@@ -170,6 +306,60 @@ impl ReplicationOffsets {
         self.tables.get(table).iter().any(|o| o.is_some())
     }
 
+    /// Returns true if every table has been replicated at least up to `offset`.
+    ///
+    /// If `offset` and a table's offset both carry a MySQL GTID set, the table has been replicated
+    /// up to `offset` once every GTID in `offset` is in the table's set, which holds whichever
+    /// server in the replication topology either offset was read from. Otherwise, offsets are
+    /// only comparable within a single replication log, so tables in other replication logs, such
+    /// as those replicated from an additional upstream database, are ignored; as are tables whose
+    /// GTID set has no transactions from the same servers as `offset`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use readyset_client::replication::{ReplicationOffset, ReplicationOffsets};
+    ///
+    /// let offset = |replication_log_name: &str, offset| ReplicationOffset {
+    ///     replication_log_name: replication_log_name.to_string(),
+    ///     offset,
+    ///     gtid_set: None,
+    /// };
+    ///
+    /// let mut replication_offsets = ReplicationOffsets::default();
+    /// replication_offsets
+    ///     .tables
+    ///     .insert("t1".into(), Some(offset("binlog", 2)));
+    /// replication_offsets
+    ///     .tables
+    ///     .insert("t2".into(), Some(offset("other", 1)));
+    /// replication_offsets.tables.insert("t3".into(), None);
+    ///
+    /// assert!(replication_offsets.has_replicated(&offset("binlog", 2)));
+    /// assert!(!replication_offsets.has_replicated(&offset("binlog", 3)));
+    /// ```
+    pub fn has_replicated(&self, offset: &ReplicationOffset) -> bool {
+        let gtid_set = |offset: &ReplicationOffset| {
+            offset
+                .gtid_set
+                .as_deref()
+                .and_then(|gtid_set| gtid_set.parse::<GtidSet>().ok())
+        };
+        let written = gtid_set(offset);
+        self.tables
+            .values()
+            .flatten()
+            .all(|table| match (&written, gtid_set(table)) {
+                (Some(written), Some(replicated)) => {
+                    !written.shares_source(&replicated) || written.is_subset(&replicated)
+                }
+                _ => {
+                    table.replication_log_name != offset.replication_log_name
+                        || table.offset >= offset.offset
+                }
+            })
+    }
+
     /// If all replication offsets are present (the schema and all tables), returns the minimum of
     /// all replication offsets, from which streaming replication can successfully continue.
     /// Otherwise, returns `Ok(None)`.
@@ -242,6 +432,111 @@ impl ReplicationOffsets {
     }
 }
 
+/// How the values in rows are redacted when capturing replication events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureRedaction {
+    /// Capture values as they are
+    #[default]
+    None,
+    /// Replace text and byte array values with a hash of the value
+    Text,
+    /// Replace text, byte array and integer values with a hash of the value, and every other value
+    /// with NULL
+    All,
+}
+
+/// A request to capture the replication events received from the upstream database to a file,
+/// for reproducing replication bugs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationCaptureRequest {
+    /// The name of the file to write the events to, within the directory configured with
+    /// `--replication-capture-dir` on the host running the replicator. Any existing file with
+    /// this name is overwritten.
+    pub file_name: String,
+    /// How long to capture events for
+    pub duration: Duration,
+    /// How the values in rows are redacted
+    pub redaction: CaptureRedaction,
+}
+
+/// The number of consistency checks in a row a table's checksums must differ in for the table to
+/// be considered to have diverged from the upstream database
+pub const DIVERGED_AFTER_MISMATCHES: u32 = 1;
+
+/// A checksum of the rows of a table which doesn't depend on the order of the rows, used to check
+/// that a table in ReadySet has the same rows as the upstream table it's replicated from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableChecksum {
+    /// The number of rows in the table
+    pub rows: u64,
+    /// The wrapping sum of the hashes of each row
+    pub checksum: u64,
+}
+
+impl TableChecksum {
+    /// Add `row` to the checksum. Text values are hashed by their contents, ignoring their
+    /// collation.
+    pub fn add_row<'a, I>(&mut self, row: I)
+    where
+        I: IntoIterator<Item = &'a DfValue>,
+    {
+        let mut hasher = DefaultHasher::new();
+        for value in row {
+            match value {
+                DfValue::Text(_) | DfValue::TinyText(_) => <&str>::try_from(value)
+                    .unwrap_or_default()
+                    .hash(&mut hasher),
+                _ => value.hash(&mut hasher),
+            }
+        }
+        self.rows += 1;
+        self.checksum = self.checksum.wrapping_add(hasher.finish());
+    }
+
+    /// Add the rows checksummed by `other` to this checksum, such as to combine the checksums of
+    /// the shards of a table
+    pub fn combine(&mut self, other: TableChecksum) {
+        self.rows += other.rows;
+        self.checksum = self.checksum.wrapping_add(other.checksum);
+    }
+}
+
+/// The result of the most recent consistency check of a replicated table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableConsistency {
+    /// The checksum of the table's rows in ReadySet
+    pub readyset: TableChecksum,
+    /// The checksum of the table's rows in the upstream database
+    pub upstream: TableChecksum,
+    /// The number of checks in a row the checksums have differed in
+    pub consecutive_mismatches: u32,
+    /// When the table was checked, in seconds since the Unix epoch
+    pub checked_at: u64,
+}
+
+impl TableConsistency {
+    /// Returns true if the table's checksums have differed in enough checks in a row for the table
+    /// to be considered to have diverged from the upstream database
+    pub fn diverged(&self) -> bool {
+        self.consecutive_mismatches >= DIVERGED_AFTER_MISMATCHES
+    }
+}
+
+/// The health of replication from a single upstream database
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplicationSourceHealth {
+    /// Whether the replicator is connected to the upstream database and receiving replication
+    /// events from it
+    pub connected: bool,
+    /// How far behind the upstream database replication was, as of the last replication event we
+    /// received, or `None` if the upstream database doesn't let us tell
+    pub lag: Option<Duration>,
+    /// When we last received a replication event from the upstream database, including the
+    /// heartbeats it sends when it has no new writes for us. `None` if we never have.
+    pub last_event: Option<SystemTime>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -255,6 +550,7 @@ mod tests {
                 schema: Some(ReplicationOffset {
                     offset: 1,
                     replication_log_name: "test".to_owned(),
+                    gtid_set: None,
                 }),
                 tables: HashMap::from([
                     (
@@ -262,6 +558,7 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 2,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                     (
@@ -269,9 +566,11 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 3,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                 ]),
+                sources: HashMap::new(),
             };
             let res = offsets.max_offset().unwrap().unwrap();
             assert_eq!(res.replication_log_name, "test");
@@ -284,6 +583,7 @@ mod tests {
                 schema: Some(ReplicationOffset {
                     offset: 1,
                     replication_log_name: "binlog".to_owned(),
+                    gtid_set: None,
                 }),
                 tables: HashMap::from([
                     (
@@ -291,6 +591,7 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 2,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                     (
@@ -298,9 +599,11 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 3,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                 ]),
+                sources: HashMap::new(),
             };
             let res = offsets.max_offset();
             res.unwrap_err();
@@ -316,6 +619,7 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 2,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                     (
@@ -323,9 +627,11 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 3,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                 ]),
+                sources: HashMap::new(),
             };
             let res = offsets.max_offset().unwrap();
             assert!(res.is_none());
@@ -337,6 +643,7 @@ mod tests {
                 schema: Some(ReplicationOffset {
                     offset: 1,
                     replication_log_name: "test".to_owned(),
+                    gtid_set: None,
                 }),
                 tables: HashMap::from([
                     (
@@ -344,13 +651,181 @@ mod tests {
                         Some(ReplicationOffset {
                             offset: 2,
                             replication_log_name: "test".to_owned(),
+                            gtid_set: None,
                         }),
                     ),
                     ("t2".into(), None),
                 ]),
+                sources: HashMap::new(),
             };
             let res = offsets.max_offset().unwrap();
             assert!(res.is_none());
         }
     }
+
+    mod try_max_into {
+        use super::*;
+
+        #[test]
+        fn takes_gtid_set_of_max_offset() {
+            let offset = |offset: u128, gtid_set: &str| ReplicationOffset {
+                offset,
+                replication_log_name: "binlog".to_owned(),
+                gtid_set: Some(gtid_set.to_owned()),
+            };
+
+            let mut max = Some(offset(2, "uuid:1-2"));
+            offset(1, "uuid:1").try_max_into(&mut max).unwrap();
+            assert_eq!(max, Some(offset(2, "uuid:1-2")));
+            offset(3, "uuid:1-3").try_max_into(&mut max).unwrap();
+            assert_eq!(max, Some(offset(3, "uuid:1-3")));
+        }
+
+        #[test]
+        fn missing_gtid_set_deserializes_to_none() {
+            let offset: ReplicationOffset =
+                serde_json::from_str(r#"{"offset":1,"replication_log_name":"binlog"}"#).unwrap();
+            assert_eq!(offset.gtid_set, None);
+        }
+    }
+
+    mod migrate {
+        use super::*;
+
+        #[test]
+        fn untagged_offsets_are_unchanged() {
+            let offset = ReplicationOffset {
+                offset: (6 << 123) + (3 << 64) + 1234,
+                replication_log_name: "binlog".to_owned(),
+                gtid_set: None,
+            };
+            assert_eq!(offset.clone().migrate(0).unwrap(), offset);
+        }
+
+        #[test]
+        fn version_1_offsets_have_no_gtid_set() {
+            let offset: ReplicationOffset =
+                serde_json::from_str(r#"{"offset":1,"replication_log_name":"binlog"}"#).unwrap();
+            let migrated = offset.clone().migrate(1).unwrap();
+            assert_eq!(migrated, offset);
+            assert_eq!(migrated.gtid_set, None);
+        }
+
+        #[test]
+        fn current_version_is_unchanged() {
+            let offset = ReplicationOffset {
+                offset: 12,
+                replication_log_name: "".to_owned(),
+                gtid_set: None,
+            };
+            assert_eq!(
+                offset.clone().migrate(REPLICATION_OFFSET_VERSION).unwrap(),
+                offset
+            );
+        }
+
+        #[test]
+        fn newer_version_fails() {
+            let offset = ReplicationOffset {
+                offset: 12,
+                replication_log_name: "binlog".to_owned(),
+                gtid_set: None,
+            };
+            offset.migrate(REPLICATION_OFFSET_VERSION + 1).unwrap_err();
+        }
+    }
+
+    mod has_replicated {
+        use super::*;
+
+        const UUID1: &str = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+        const UUID2: &str = "8d1ba2a6-2b0c-11ee-a6a1-0242ac110002";
+
+        fn offset(
+            replication_log_name: &str,
+            offset: u128,
+            gtid_set: Option<String>,
+        ) -> ReplicationOffset {
+            ReplicationOffset {
+                offset,
+                replication_log_name: replication_log_name.to_owned(),
+                gtid_set,
+            }
+        }
+
+        fn offsets(tables: Vec<ReplicationOffset>) -> ReplicationOffsets {
+            ReplicationOffsets {
+                schema: None,
+                tables: tables
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, offset)| (Relation::from(format!("t{i}").as_str()), Some(offset)))
+                    .collect(),
+            }
+        }
+
+        #[test]
+        fn compares_gtid_sets_across_servers() {
+            // Replicating from a replica, whose binlog has a different name and positions
+            let replicated = offsets(vec![
+                offset("replica-bin", 10, Some(format!("{UUID1}:1-5"))),
+                offset("replica-bin", 20, Some(format!("{UUID1}:1-7"))),
+            ]);
+
+            assert!(replicated.has_replicated(&offset(
+                "primary-bin",
+                1000,
+                Some(format!("{UUID1}:1-5"))
+            )));
+            assert!(!replicated.has_replicated(&offset(
+                "primary-bin",
+                1,
+                Some(format!("{UUID1}:1-6"))
+            )));
+            // Tables replicated from an unrelated upstream database are ignored
+            assert!(offsets(vec![offset(
+                "replica-bin",
+                10,
+                Some(format!("{UUID2}:1-5"))
+            )])
+            .has_replicated(&offset("primary-bin", 1, Some(format!("{UUID1}:1-6")))));
+        }
+
+        #[test]
+        fn compares_positions_without_gtid_sets() {
+            let replicated = offsets(vec![
+                offset("binlog", 10, Some(format!("{UUID1}:1-5"))),
+                offset("other", 1, None),
+            ]);
+            assert!(replicated.has_replicated(&offset("binlog", 10, None)));
+            assert!(!replicated.has_replicated(&offset("binlog", 11, None)));
+        }
+    }
+
+    #[test]
+    fn table_checksum_ignores_row_order() {
+        let rows = [
+            vec![DfValue::from(1), DfValue::from("a")],
+            vec![DfValue::from(2), DfValue::from("b")],
+            vec![DfValue::from(2), DfValue::from("b")],
+        ];
+        let mut forwards = TableChecksum::default();
+        for row in &rows {
+            forwards.add_row(row);
+        }
+
+        let mut shard1 = TableChecksum::default();
+        shard1.add_row(&rows[2]);
+        let mut shard2 = TableChecksum::default();
+        shard2.add_row(&rows[1]);
+        shard2.add_row(&rows[0]);
+        shard1.combine(shard2);
+        assert_eq!(forwards, shard1);
+        assert_eq!(forwards.rows, 3);
+
+        let mut missing_duplicate = TableChecksum::default();
+        missing_duplicate.add_row(&rows[0]);
+        missing_duplicate.add_row(&rows[1]);
+        assert_ne!(forwards, missing_duplicate);
+    }
 }
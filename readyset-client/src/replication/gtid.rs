@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use mysql_common::packets::{GnoInterval, Sid};
+use readyset_errors::{ReadySetError, ReadySetResult};
+
+/// A GTID source identifier (the `server_uuid` of the server a transaction originated on)
+type Uuid = [u8; 16];
+
+/// A set of MySQL global transaction identifiers, such as the value of `@@gtid_executed`.
+///
+/// A GTID is composed of the UUID of the server the transaction originated on and a transaction
+/// number (GNO) which is sequential within that server. A GTID set is represented as a list of
+/// ranges of transaction numbers for each source UUID, for example:
+///
+/// ```text
+/// 3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5:11,8d1ba2a6-2b0c-11ee-a6a1-0242ac110002:1-42
+/// ```
+///
+/// If the replication offsets persisted by ReadySet carry the set of transactions applied so far,
+/// the replicator can resume the binlog with GTID auto-positioning, rather than by file name and
+/// position, and writes made on any server in the replication topology can be checked against
+/// them. See <https://dev.mysql.com/doc/refman/8.0/en/replication-gtids-concepts.html>
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct GtidSet {
+    /// Sorted, non-overlapping, non-adjacent inclusive ranges of transaction numbers for each
+    /// source UUID
+    intervals: BTreeMap<Uuid, Vec<(u64, u64)>>,
+}
+
+impl GtidSet {
+    /// Parse the value of `@@gtid_executed` (or the `Executed_Gtid_Set` column of `SHOW MASTER
+    /// STATUS`), returning `None` if GTIDs are disabled or no transactions have been executed
+    pub fn from_executed(s: &str) -> ReadySetResult<Option<Self>> {
+        let set = s.parse::<GtidSet>()?;
+        Ok((!set.is_empty()).then_some(set))
+    }
+
+    /// Returns true if the set contains no transactions
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Returns true if every transaction in this set is also in `other`
+    pub fn is_subset(&self, other: &GtidSet) -> bool {
+        self.intervals.iter().all(|(sid, intervals)| {
+            let other_intervals = match other.intervals.get(sid) {
+                Some(other_intervals) => other_intervals,
+                None => return false,
+            };
+            intervals.iter().all(|&(start, end)| {
+                // Since intervals are non-adjacent, a contained interval must fall within a single
+                // interval of `other`
+                let idx = other_intervals.partition_point(|&(_, e)| e < start);
+                other_intervals
+                    .get(idx)
+                    .map_or(false, |&(s, e)| s <= start && end <= e)
+            })
+        })
+    }
+
+    /// Returns true if any transactions in this set originated on the same server as any
+    /// transactions in `other`
+    pub fn shares_source(&self, other: &GtidSet) -> bool {
+        self.intervals
+            .keys()
+            .any(|sid| other.intervals.contains_key(sid))
+    }
+
+    /// Add the transaction identified by `sid` and `gno` to the set
+    pub fn add(&mut self, sid: Uuid, gno: u64) {
+        self.add_interval(sid, gno, gno)
+    }
+
+    /// Add the inclusive range of transactions `start..=end` from `sid` to the set, merging it
+    /// with any ranges it overlaps or is adjacent to
+    fn add_interval(&mut self, sid: Uuid, start: u64, end: u64) {
+        let intervals = self.intervals.entry(sid).or_default();
+        let idx = intervals.partition_point(|&(_, e)| e.saturating_add(1) < start);
+        let mut merged = (start, end);
+        while let Some(&(s, e)) = intervals.get(idx) {
+            if s > merged.1.saturating_add(1) {
+                break;
+            }
+            merged = (merged.0.min(s), merged.1.max(e));
+            intervals.remove(idx);
+        }
+        intervals.insert(idx, merged);
+    }
+
+    /// Convert this set into the SID block sent as part of `COM_BINLOG_DUMP_GTID`
+    pub fn to_sids(&self) -> Vec<Sid<'static>> {
+        self.intervals
+            .iter()
+            .map(|(uuid, intervals)| {
+                intervals
+                    .iter()
+                    .fold(Sid::new(*uuid), |sid, &(start, end)| {
+                        // Intervals on the wire are half-open
+                        sid.with_interval(GnoInterval::new(start, end + 1))
+                    })
+            })
+            .collect()
+    }
+}
+
+fn parse_uuid(s: &str) -> Option<Uuid> {
+    let digits = s.chars().filter(|c| *c != '-').collect::<String>();
+    let mut uuid = Uuid::default();
+    hex::decode_to_slice(digits, &mut uuid).ok()?;
+    Some(uuid)
+}
+
+impl FromStr for GtidSet {
+    type Err = ReadySetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ReadySetError::ReplicationFailed(format!("Invalid GTID set {s}"));
+
+        let mut set = GtidSet::default();
+        // `@@gtid_executed` separates the sets for each source with a comma and a newline
+        for source in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let mut parts = source.split(':');
+            let uuid = parts
+                .next()
+                .and_then(|uuid| parse_uuid(uuid.trim()))
+                .ok_or_else(invalid)?;
+            for interval in parts {
+                let (start, end) = match interval.split_once('-') {
+                    Some((start, end)) => (start.parse(), end.parse()),
+                    None => (interval.parse(), interval.parse()),
+                };
+                let (start, end): (u64, u64) =
+                    (start.map_err(|_| invalid())?, end.map_err(|_| invalid())?);
+                if start == 0 || start > end {
+                    return Err(invalid());
+                }
+                set.add_interval(uuid, start, end);
+            }
+        }
+
+        Ok(set)
+    }
+}
+
+impl Display for GtidSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (uuid, intervals)) in self.intervals.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            let uuid = hex::encode(uuid);
+            write!(
+                f,
+                "{}-{}-{}-{}-{}",
+                &uuid[..8],
+                &uuid[8..12],
+                &uuid[12..16],
+                &uuid[16..20],
+                &uuid[20..]
+            )?;
+            for (start, end) in intervals {
+                if start == end {
+                    write!(f, ":{start}")?;
+                } else {
+                    write!(f, ":{start}-{end}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID1: &str = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+    const UUID2: &str = "8d1ba2a6-2b0c-11ee-a6a1-0242ac110002";
+
+    #[test]
+    fn parse_and_display() {
+        let s = format!("{UUID1}:1-5:11,{UUID2}:1-42");
+        let set = s.parse::<GtidSet>().unwrap();
+        assert_eq!(set.to_string(), s);
+    }
+
+    #[test]
+    fn parse_executed_gtid_set() {
+        // The format used by `SHOW MASTER STATUS`, with unmerged intervals and upper case UUIDs
+        let s = format!("{}:1-3:4-5,\n{UUID2}:7", UUID1.to_uppercase());
+        let set = GtidSet::from_executed(&s).unwrap().unwrap();
+        assert_eq!(set.to_string(), format!("{UUID1}:1-5,{UUID2}:7"));
+
+        assert_eq!(GtidSet::from_executed("").unwrap(), None);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        "not-a-uuid:1-5".parse::<GtidSet>().unwrap_err();
+        format!("{UUID1}:5-1").parse::<GtidSet>().unwrap_err();
+        format!("{UUID1}:0").parse::<GtidSet>().unwrap_err();
+    }
+
+    #[test]
+    fn add_merges_intervals() {
+        let mut set = format!("{UUID1}:1-3:7-9").parse::<GtidSet>().unwrap();
+        set.add(parse_uuid(UUID1).unwrap(), 5);
+        assert_eq!(set.to_string(), format!("{UUID1}:1-3:5:7-9"));
+        set.add(parse_uuid(UUID1).unwrap(), 4);
+        set.add(parse_uuid(UUID1).unwrap(), 6);
+        assert_eq!(set.to_string(), format!("{UUID1}:1-9"));
+        set.add(parse_uuid(UUID1).unwrap(), 3);
+        assert_eq!(set.to_string(), format!("{UUID1}:1-9"));
+        set.add(parse_uuid(UUID2).unwrap(), 1);
+        assert_eq!(set.to_string(), format!("{UUID1}:1-9,{UUID2}:1"));
+    }
+
+    #[test]
+    fn subset() {
+        let set = format!("{UUID1}:1-5:11").parse::<GtidSet>().unwrap();
+        assert!(GtidSet::default().is_subset(&set));
+        assert!(set.is_subset(&set));
+        assert!(format!("{UUID1}:2-4")
+            .parse::<GtidSet>()
+            .unwrap()
+            .is_subset(&set));
+        assert!(!format!("{UUID1}:1-6")
+            .parse::<GtidSet>()
+            .unwrap()
+            .is_subset(&set));
+        assert!(!format!("{UUID1}:1,{UUID2}:1")
+            .parse::<GtidSet>()
+            .unwrap()
+            .is_subset(&set));
+        assert!(!set.is_subset(&GtidSet::default()));
+    }
+}
@@ -103,6 +103,17 @@ pub enum TableOperation {
     /// Note that truncate operations are *not* currently performed in order within a single batch
     /// of table operations
     Truncate,
+    /// Delete every row whose value in `column` is less than `cutoff`, ignoring rows where that
+    /// value is NULL.
+    ///
+    /// Like truncate operations, these are processed before the rest of the operations in the same
+    /// batch.
+    DeleteExpired {
+        /// The index of the column to compare against `cutoff`
+        column: usize,
+        /// Rows with a value in `column` less than this are deleted
+        cutoff: DfValue,
+    },
     /// Set the replication offset for data written to this base table.
     ///
     /// Within a group of table operations, the largest replication offset will take precedence
@@ -125,21 +136,31 @@ impl TableOperation {
         }
     }
 
-    /// Construct an iterator over the shards this TableOperation should target.
+    /// Construct an iterator over the shards this TableOperation should target, for a table
+    /// sharded by the column `shard_col`.
+    ///
+    /// Operations identifying a row only by its key target the shard for the column at index
+    /// `shard_key_col` of the key, which is `shard_col`.
     ///
     /// ## Invariants
-    /// * `key_col` must be in the rows.
-    /// * the `key`s must have at least one element.
+    /// * `shard_col` must be in the rows.
+    /// * `shard_key_col` must be in the `key`s.
     #[inline]
-    pub fn shards(&self, key_col: usize, num_shards: usize) -> impl Iterator<Item = usize> {
+    pub fn shards(
+        &self,
+        shard_col: usize,
+        shard_key_col: usize,
+        num_shards: usize,
+    ) -> impl Iterator<Item = usize> {
         #[allow(clippy::indexing_slicing)]
         let key = match self {
-            TableOperation::Insert(row) => Some(&row[key_col]),
-            TableOperation::DeleteByKey { key } => Some(&key[0]),
-            TableOperation::DeleteRow { row } => Some(&row[key_col]),
-            TableOperation::Update { key, .. } => Some(&key[0]),
-            TableOperation::InsertOrUpdate { row, .. } => Some(&row[key_col]),
+            TableOperation::Insert(row) => Some(&row[shard_col]),
+            TableOperation::DeleteByKey { key } => Some(&key[shard_key_col]),
+            TableOperation::DeleteRow { row } => Some(&row[shard_col]),
+            TableOperation::Update { key, .. } => Some(&key[shard_key_col]),
+            TableOperation::InsertOrUpdate { row, .. } => Some(&row[shard_col]),
             TableOperation::Truncate
+            | TableOperation::DeleteExpired { .. }
             | TableOperation::SetReplicationOffset(_)
             | TableOperation::SetSnapshotMode(_) => None,
         };
@@ -151,6 +172,65 @@ impl TableOperation {
             Either::Right(0..num_shards)
         }
     }
+
+    /// If this operation changes the value of the column `shard_col` of an existing row such that
+    /// the row belongs in a different one of `num_shards` shards afterwards, returns it as a delete
+    /// of the existing row followed by an insert of the updated one, which can then each be sent
+    /// to their own shard. Any other operation is returned as is. `shard_key_col` is the index of
+    /// `shard_col` within the key of the table.
+    ///
+    /// Since the existing row isn't known here, only updates that set every column of the row can
+    /// be split up like this, and an error is returned for any other operation that would move a
+    /// row to a different shard.
+    pub fn split_shard_key_update(
+        self,
+        shard_col: usize,
+        shard_key_col: usize,
+        num_shards: usize,
+    ) -> ReadySetResult<impl Iterator<Item = TableOperation>> {
+        let moves_row = |old: Option<&DfValue>, modification: Option<&Modification>| match (
+            old,
+            modification,
+        ) {
+            (Some(old), Some(Modification::Set(new))) => {
+                crate::shard_by(old, num_shards) != crate::shard_by(new, num_shards)
+            }
+            (_, Some(Modification::Apply(..))) => true,
+            _ => false,
+        };
+
+        match self {
+            TableOperation::Update { update, key }
+                if moves_row(key.get(shard_key_col), update.get(shard_col)) =>
+            {
+                let row = update
+                    .into_iter()
+                    .map(|modification| match modification {
+                        Modification::Set(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>();
+                match row {
+                    Some(row) => Ok(Either::Right(
+                        [
+                            TableOperation::DeleteByKey { key },
+                            TableOperation::Insert(row),
+                        ]
+                        .into_iter(),
+                    )),
+                    None => unsupported!(
+                        "Updates that move a row to a different shard must set every column"
+                    ),
+                }
+            }
+            TableOperation::InsertOrUpdate { row, update }
+                if moves_row(row.get(shard_col), update.get(shard_col)) =>
+            {
+                unsupported!("Insert-or-update operations can't move a row to a different shard")
+            }
+            op => Ok(Either::Left(iter::once(op))),
+        }
+    }
 }
 
 impl From<Vec<DfValue>> for TableOperation {
@@ -306,6 +386,8 @@ pub struct TableBuilder {
     pub addr: LocalNodeIndex,
     pub key_is_primary: bool,
     pub key: Vec<usize>,
+    /// The column the table's state is sharded by, if it's sharded
+    pub shard_column: Option<usize>,
     pub dropped: VecMap<DfValue>,
 
     pub table_name: Relation,
@@ -361,6 +443,7 @@ impl TableBuilder {
             node: self.addr,
             key: self.key,
             key_is_primary: self.key_is_primary,
+            shard_column: self.shard_column,
             columns: self.columns,
             dropped: self.dropped,
             table_name: self.table_name,
@@ -369,6 +452,7 @@ impl TableBuilder {
             shards: conns,
             last_trace_sample: Instant::now(),
             request_timeout: self.table_request_timeout,
+            buffered: None,
         }
     }
 }
@@ -386,6 +470,7 @@ pub struct Table {
     pub node: LocalNodeIndex,
     key_is_primary: bool,
     key: Vec<usize>,
+    shard_column: Option<usize>,
     columns: Vec<SqlIdentifier>,
     dropped: VecMap<DfValue>,
     table_name: Relation,
@@ -394,6 +479,9 @@ pub struct Table {
     shard_addrs: Vec<SocketAddr>,
     last_trace_sample: Instant,
     request_timeout: Duration,
+    /// Operations performed on this table since [`Self::buffer_operations`] was called, which
+    /// haven't been sent yet
+    buffered: Option<Vec<TableOperation>>,
 }
 
 impl fmt::Debug for Table {
@@ -403,6 +491,7 @@ impl fmt::Debug for Table {
             .field("node", &self.node)
             .field("key_is_primary", &self.key_is_primary)
             .field("key", &self.key)
+            .field("shard_column", &self.shard_column)
             .field("columns", &self.columns)
             .field("dropped", &self.dropped)
             .field("table_name", &self.table_name)
@@ -478,6 +567,14 @@ impl Table {
                             ));
                         }
                     }
+                    TableOperation::DeleteExpired { column, .. } => {
+                        if *column >= self.columns.len() {
+                            return Err(ReadySetError::WrongColumnCount(
+                                self.columns.len(),
+                                column + 1,
+                            ));
+                        }
+                    }
                     TableOperation::SetReplicationOffset(_)
                     | TableOperation::SetSnapshotMode(_)
                     | TableOperation::Truncate => {}
@@ -487,7 +584,7 @@ impl Table {
         };
 
         if let Err(e) = immediate_err() {
-            return future::Either::Left(future::Either::Left(async move { Err(e) }));
+            return future::Either::Left(future::Either::Left(future::ready(Err(e))));
         }
 
         let nshards = self.shards.len();
@@ -501,24 +598,33 @@ impl Table {
                 ))
             }
             _ => {
-                let key_len = self.key.len();
-                let key_col = match self.key.first() {
-                    // If it's `None`, then it's empty.
+                let ncols = self.columns.len() + self.dropped.len();
+                let shard_col = match self.shard_column {
                     None => {
                         return future::Either::Right(future::Either::Left(future::Either::Left(
-                            future::Either::Left(
-                                async move { internal!("sharded base without a key") },
-                            ),
+                            future::Either::Left(async move {
+                                internal!("sharded base without a shard column")
+                            }),
                         )))
                     }
-                    Some(_) if key_len != 1 => {
+                    Some(col) if col >= ncols => {
                         return future::Either::Right(future::Either::Left(future::Either::Left(
                             future::Either::Right(async move {
-                                internal!("base sharded by complex key")
+                                internal!("base sharded by a column it doesn't have")
                             }),
                         )))
                     }
-                    Some(&k) => k,
+                    Some(col) => col,
+                };
+                // Tables are only sharded by a column of their key, so that rows with the same key
+                // are always in the same shard
+                let shard_key_col = match self.key.iter().position(|col| *col == shard_col) {
+                    Some(shard_key_col) => shard_key_col,
+                    None => {
+                        return future::Either::Left(future::Either::Left(future::ready(Err(
+                            internal_err!("base sharded by a column that isn't part of its key"),
+                        ))))
+                    }
                 };
 
                 let _guard = span.as_ref().map(Span::enter);
@@ -532,12 +638,22 @@ impl Table {
                         }))
                     }
                 };
-                for r in ops.drain(..) {
-                    for shard in r.shards(key_col, nshards) {
-                        // The `shard` index belongs to the range `0..nshards`,
-                        // so it's not out of bounds.
-                        #[allow(clippy::indexing_slicing)]
-                        shard_writes[shard].push(r.clone())
+                for op in ops.drain(..) {
+                    let split = match op.split_shard_key_update(shard_col, shard_key_col, nshards) {
+                        Ok(split) => split,
+                        Err(e) => {
+                            return future::Either::Left(future::Either::Left(future::ready(Err(
+                                e,
+                            ))))
+                        }
+                    };
+                    for r in split {
+                        for shard in r.shards(shard_col, shard_key_col, nshards) {
+                            // The `shard` index belongs to the range `0..nshards`,
+                            // so it's not out of bounds.
+                            #[allow(clippy::indexing_slicing)]
+                            shard_writes[shard].push(r.clone())
+                        }
                     }
                 }
 
@@ -823,6 +939,13 @@ impl Table {
     }
 
     async fn request(&mut self, r: TableRequest) -> ReadySetResult<()> {
+        let r = match (&mut self.buffered, r) {
+            (Some(buffered), TableRequest::TableOperations(ops)) => {
+                buffered.extend(ops);
+                return Ok(());
+            }
+            (_, r) => r,
+        };
         future::poll_fn(|cx| self.poll_ready(cx)).await?;
         self.call(r).await?;
 
@@ -972,6 +1095,15 @@ impl Table {
         .await
     }
 
+    /// Delete every row from this base table whose value in the column at index `column` is less
+    /// than `cutoff`. See [`TableOperation::DeleteExpired`].
+    pub async fn delete_expired(&mut self, column: usize, cutoff: DfValue) -> ReadySetResult<()> {
+        self.request_with_timeout(TableRequest::TableOperations(vec![
+            TableOperation::DeleteExpired { column, cutoff },
+        ]))
+        .await
+    }
+
     /// Updates the timestamp of the base table in the data flow graph.
     pub async fn update_timestamp(&mut self, t: consistency::Timestamp) -> ReadySetResult<()> {
         self.request_with_timeout(TableRequest::Timestamp(t)).await
@@ -1003,4 +1135,125 @@ impl Table {
         ]))
         .await
     }
+
+    /// Hold on to the operations performed on this table from now on, rather than sending them,
+    /// until [`Self::flush_operations`] is called.
+    ///
+    /// This can be used to make a series of writes visible to readers of the table all at once,
+    /// at the cost of holding all of them in memory.
+    pub fn buffer_operations(&mut self) {
+        self.buffered.get_or_insert_with(Vec::new);
+    }
+
+    /// Send all the operations performed since [`Self::buffer_operations`] was called in a single
+    /// batch, which is applied to each shard of the table atomically, and stop buffering
+    /// operations. Does nothing if operations aren't being buffered.
+    pub async fn flush_operations(&mut self) -> ReadySetResult<()> {
+        match self.buffered.take() {
+            Some(ops) if !ops.is_empty() => self.request(TableRequest::TableOperations(ops)).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod split_shard_key_update {
+        use super::*;
+
+        const SHARDS: usize = 2;
+
+        /// Returns two values of the shard column that belong in different shards
+        fn values_in_different_shards() -> (DfValue, DfValue) {
+            let first = DfValue::from(0);
+            let second = (1..)
+                .map(DfValue::from)
+                .find(|v| crate::shard_by(v, SHARDS) != crate::shard_by(&first, SHARDS))
+                .unwrap();
+            (first, second)
+        }
+
+        fn split(op: TableOperation) -> ReadySetResult<Vec<TableOperation>> {
+            // Rows are (id, tenant), keyed by (id, tenant) and sharded by tenant
+            Ok(op.split_shard_key_update(1, 1, SHARDS)?.collect())
+        }
+
+        #[test]
+        fn update_within_shard() {
+            let (tenant, _) = values_in_different_shards();
+            let op = TableOperation::Update {
+                key: vec![1.into(), tenant.clone()],
+                update: vec![Modification::Set(2.into()), Modification::Set(tenant)],
+            };
+            assert_eq!(split(op.clone()).unwrap(), vec![op]);
+        }
+
+        #[test]
+        fn update_not_changing_shard_column() {
+            let (tenant, _) = values_in_different_shards();
+            let op = TableOperation::Update {
+                key: vec![1.into(), tenant],
+                update: vec![Modification::Set(2.into()), Modification::None],
+            };
+            assert_eq!(split(op.clone()).unwrap(), vec![op]);
+        }
+
+        #[test]
+        fn update_moving_row_is_delete_and_insert() {
+            let (old, new) = values_in_different_shards();
+            let op = TableOperation::Update {
+                key: vec![1.into(), old.clone()],
+                update: vec![Modification::Set(1.into()), Modification::Set(new.clone())],
+            };
+            let split = split(op).unwrap();
+            assert_eq!(
+                split,
+                vec![
+                    TableOperation::DeleteByKey {
+                        key: vec![1.into(), old.clone()]
+                    },
+                    TableOperation::Insert(vec![1.into(), new.clone()]),
+                ]
+            );
+            assert_eq!(
+                split
+                    .iter()
+                    .map(|op| op.shards(1, 1, SHARDS).collect::<Vec<_>>())
+                    .collect::<Vec<_>>(),
+                vec![
+                    vec![crate::shard_by(&old, SHARDS)],
+                    vec![crate::shard_by(&new, SHARDS)]
+                ]
+            );
+        }
+
+        #[test]
+        fn partial_update_moving_row() {
+            let (old, new) = values_in_different_shards();
+            let op = TableOperation::Update {
+                key: vec![1.into(), old],
+                update: vec![Modification::None, Modification::Set(new)],
+            };
+            assert!(split(op).unwrap_err().is_unsupported());
+        }
+
+        #[test]
+        fn insert_or_update_moving_row() {
+            let (old, new) = values_in_different_shards();
+            let op = TableOperation::InsertOrUpdate {
+                row: vec![1.into(), old],
+                update: vec![Modification::None, Modification::Set(new)],
+            };
+            assert!(split(op).unwrap_err().is_unsupported());
+        }
+
+        #[test]
+        fn other_operations_are_unchanged() {
+            let (tenant, _) = values_in_different_shards();
+            let op = TableOperation::Insert(vec![1.into(), tenant]);
+            assert_eq!(split(op.clone()).unwrap(), vec![op]);
+        }
+    }
 }
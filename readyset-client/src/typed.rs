@@ -0,0 +1,245 @@
+//! A typed API for executing cached queries directly, without going through the MySQL or
+//! PostgreSQL wire protocols.
+//!
+//! A [`TypedView`] wraps the [`View`] for a cached query, and executes it with a tuple of Rust
+//! values for the query's parameters, converting each row of the results to a tuple of Rust values
+//! (or any other type implementing [`FromRow`]):
+//!
+//! ```no_run
+//! # use readyset_client::ReadySetHandle;
+//! # use readyset_client::typed::TypedView;
+//! # use readyset_errors::ReadySetResult;
+//! # async fn example(handle: &mut ReadySetHandle) -> ReadySetResult<()> {
+//! let mut view: TypedView<(i32,), (String, Option<i64>)> = handle
+//!     .typed_view("q_users_by_id", dataflow_expression::Dialect::DEFAULT_MYSQL)
+//!     .await?;
+//! for (name, age) in view.execute((1,)).await? {
+//!     println!("{name}: {age:?}");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use dataflow_expression::Dialect;
+use readyset_data::DfValue;
+use readyset_errors::{ReadySetError, ReadySetResult};
+use rust_decimal::Decimal;
+
+use crate::{SchemaType, View};
+
+/// Types that a single value in the results of a cached query can be converted to
+pub trait FromDfValue: Sized {
+    /// Convert `value` to `Self`, returning an error if it has the wrong type
+    fn from_df_value(value: &DfValue) -> ReadySetResult<Self>;
+}
+
+macro_rules! from_df_value_via_try_from {
+    ($($ty:ty),+) => {
+        $(impl FromDfValue for $ty {
+            fn from_df_value(value: &DfValue) -> ReadySetResult<Self> {
+                <$ty>::try_from(value)
+            }
+        })+
+    };
+}
+
+from_df_value_via_try_from!(
+    i8,
+    i16,
+    i32,
+    i64,
+    u8,
+    u16,
+    u32,
+    u64,
+    f32,
+    f64,
+    bool,
+    String,
+    Vec<u8>,
+    Decimal,
+    NaiveDate,
+    NaiveDateTime,
+    DateTime<FixedOffset>
+);
+
+impl FromDfValue for DfValue {
+    fn from_df_value(value: &DfValue) -> ReadySetResult<Self> {
+        Ok(value.clone())
+    }
+}
+
+/// `NULL` values are converted to `None`, and all other values to `Some`
+impl<T: FromDfValue> FromDfValue for Option<T> {
+    fn from_df_value(value: &DfValue) -> ReadySetResult<Self> {
+        if value.is_none() {
+            Ok(None)
+        } else {
+            T::from_df_value(value).map(Some)
+        }
+    }
+}
+
+/// Types that a row in the results of a cached query can be converted to
+pub trait FromRow: Sized {
+    /// Convert `row` to `Self`, returning an error if it has the wrong number of columns, or any of
+    /// its values have the wrong type
+    fn from_row(row: &[DfValue]) -> ReadySetResult<Self>;
+}
+
+impl FromRow for Vec<DfValue> {
+    fn from_row(row: &[DfValue]) -> ReadySetResult<Self> {
+        Ok(row.to_vec())
+    }
+}
+
+/// Types that can be used as the values of the parameters of a cached query
+pub trait IntoParams {
+    /// Convert `self` to the values of the query's parameters, in order
+    fn into_params(self) -> Vec<DfValue>;
+}
+
+impl IntoParams for Vec<DfValue> {
+    fn into_params(self) -> Vec<DfValue> {
+        self
+    }
+}
+
+impl IntoParams for () {
+    fn into_params(self) -> Vec<DfValue> {
+        vec![]
+    }
+}
+
+macro_rules! tuple_impls {
+    ($len:expr => $($name:ident $idx:tt),+) => {
+        impl<$($name: FromDfValue),+> FromRow for ($($name,)+) {
+            fn from_row(row: &[DfValue]) -> ReadySetResult<Self> {
+                if row.len() != $len {
+                    return Err(ReadySetError::WrongColumnCount($len, row.len()));
+                }
+                Ok(($($name::from_df_value(&row[$idx])?,)+))
+            }
+        }
+
+        impl<$($name: Into<DfValue>),+> IntoParams for ($($name,)+) {
+            fn into_params(self) -> Vec<DfValue> {
+                vec![$(self.$idx.into()),+]
+            }
+        }
+    };
+}
+
+tuple_impls!(1 => A 0);
+tuple_impls!(2 => A 0, B 1);
+tuple_impls!(3 => A 0, B 1, C 2);
+tuple_impls!(4 => A 0, B 1, C 2, D 3);
+tuple_impls!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple_impls!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_impls!(7 => A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_impls!(8 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+tuple_impls!(9 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+tuple_impls!(10 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+tuple_impls!(11 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+tuple_impls!(12 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+/// A cached query, which can be executed with parameters of type `P` to return rows of type `R`.
+///
+/// The parameters are the values of the placeholders in the query, in order, which are coerced to
+/// the types of the columns they're compared with. Queries whose parameters have to be rewritten
+/// before the lookup (such as `IN` lists, or placeholders in `LIMIT` and `OFFSET` clauses) aren't
+/// supported, since that happens in the adapter.
+pub struct TypedView<P, R> {
+    view: View,
+    dialect: Dialect,
+    _types: PhantomData<fn(P) -> R>,
+}
+
+impl<P, R> TypedView<P, R>
+where
+    P: IntoParams,
+    R: FromRow,
+{
+    /// Create a new [`TypedView`] for executing `view`, using the semantics of `dialect` to coerce
+    /// the parameters to the types of the query's key columns
+    pub fn new(view: View, dialect: Dialect) -> Self {
+        Self {
+            view,
+            dialect,
+            _types: PhantomData,
+        }
+    }
+
+    /// Execute the query with the given parameters, waiting for the results if they aren't yet
+    /// cached, and convert each of the rows it returns
+    pub async fn execute(&mut self, params: P) -> ReadySetResult<Vec<R>> {
+        let params = params.into_params();
+        let raw_keys = if params.is_empty() {
+            vec![]
+        } else {
+            vec![Cow::Owned(params)]
+        };
+        let (handle, query) = self
+            .view
+            .build_view_query(raw_keys, None, None, None, true, self.dialect)?
+            .ok_or(ReadySetError::NoCacheForQuery)?;
+        // Rows can have hidden columns after the ones returned to the client (such as the key
+        // columns of the reader), which aren't part of the row type
+        let returned_cols = handle
+            .schema()
+            .map(|schema| schema.schema(SchemaType::ReturnedSchema).len());
+        handle
+            .raw_lookup(query)
+            .await?
+            .into_iter()
+            .map(|row| match returned_cols {
+                Some(cols) if cols < row.len() => R::from_row(&row[..cols]),
+                _ => R::from_row(&row),
+            })
+            .collect()
+    }
+
+    /// Returns the underlying [`View`]
+    pub fn into_inner(self) -> View {
+        self.view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_to_tuples() {
+        let row = vec![DfValue::from(1), DfValue::from("a"), DfValue::None];
+        assert_eq!(
+            <(i32, String, Option<i64>)>::from_row(&row).unwrap(),
+            (1, "a".to_owned(), None)
+        );
+        assert_eq!(
+            <(i64, Option<String>, DfValue)>::from_row(&row).unwrap(),
+            (1, Some("a".to_owned()), DfValue::None)
+        );
+    }
+
+    #[test]
+    fn rows_with_wrong_shape() {
+        let row = vec![DfValue::from(1), DfValue::from("a")];
+        <(i32,)>::from_row(&row).unwrap_err();
+        <(i32, String, String)>::from_row(&row).unwrap_err();
+        <(i32, i32)>::from_row(&row).unwrap_err();
+        <(i32, String)>::from_row(&[DfValue::None, DfValue::from("a")]).unwrap_err();
+    }
+
+    #[test]
+    fn tuples_to_params() {
+        assert_eq!(().into_params(), vec![]);
+        assert_eq!(
+            (1i64, "a", None::<i32>).into_params(),
+            vec![DfValue::from(1i64), DfValue::from("a"), DfValue::None]
+        );
+    }
+}
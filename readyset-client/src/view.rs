@@ -6,6 +6,7 @@ use std::fmt;
 use std::future::Future;
 use std::hash::{Hash, Hasher};
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::ops::{Bound, Range, RangeBounds};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
@@ -16,7 +17,7 @@ use async_bincode::{AsyncBincodeStream, AsyncDestination};
 use dataflow_expression::{BinaryOperator as DfBinaryOperator, Dialect, Expr as DfExpr};
 use futures_util::future::TryFutureExt;
 use futures_util::stream::futures_unordered::FuturesUnordered;
-use futures_util::stream::{StreamExt, TryStreamExt};
+use futures_util::stream::{self, Stream, StreamExt, TryStreamExt};
 use futures_util::{future, ready};
 use nom_sql::{
     BinaryOperator, Column, ColumnConstraint, ColumnSpecification, ItemPlaceholder, Literal,
@@ -26,7 +27,7 @@ use petgraph::graph::NodeIndex;
 use proptest::arbitrary::Arbitrary;
 use rand::prelude::IteratorRandom;
 use rand::thread_rng;
-use readyset_data::{DfType, DfValue};
+use readyset_data::{Collation, DfType, DfValue};
 use readyset_errors::{
     internal, internal_err, rpc_err, unsupported, view_err, ReadySetError, ReadySetResult,
 };
@@ -51,6 +52,7 @@ pub(crate) mod results;
 
 use self::results::{ResultIterator, Results};
 use crate::consistency::Timestamp;
+use crate::internal::IndexType;
 use crate::{ReaderAddress, Tagged, Tagger};
 
 type Transport = AsyncBincodeStream<
@@ -169,6 +171,17 @@ impl ColumnSchema {
         table: Relation,
         dialect: Dialect,
     ) -> ReadySetResult<Self> {
+        let mut column_type = DfType::from_sql_type(
+            &spec.sql_type,
+            dialect,
+            |_| None, /* Custom types not allowed for inserts via the adapter */
+        )?;
+        if let Some(collation) = spec
+            .collation()
+            .and_then(|name| Collation::from_upstream_name(name, dialect))
+        {
+            column_type = column_type.with_collation(collation);
+        }
         Ok(Self {
             base: Some(ColumnBase {
                 column: spec.column.name.clone(),
@@ -176,13 +189,21 @@ impl ColumnSchema {
                 constraints: spec.constraints,
             }),
             column: spec.column,
-            column_type: DfType::from_sql_type(
-                &spec.sql_type,
-                dialect,
-                |_| None, /* Custom types not allowed for inserts via the adapter */
-            )?,
+            column_type,
         })
     }
+
+    /// Returns the name of the collation of this column's base column in the upstream database,
+    /// if it has one
+    pub fn collation(&self) -> Option<&str> {
+        self.base
+            .iter()
+            .flat_map(|b| &b.constraints)
+            .find_map(|c| match c {
+                ColumnConstraint::Collation(collation) => Some(collation.as_str()),
+                _ => None,
+            })
+    }
 }
 
 /// A `ViewSchema` is used to desribe the columns of a stored ReadySet
@@ -788,6 +809,31 @@ pub enum ReadQuery {
         /// View query to run
         query: ViewQuery,
     },
+    /// Read from a leaf view, looking up each of the keys in the query separately and returning
+    /// the results for each key
+    PerKey {
+        /// Where to read from
+        target: ReaderAddress,
+        /// View query to run, whose key comparisons must all be equality comparisons
+        query: ViewQuery,
+    },
+    /// Read from a leaf view, replying with at most `page_size` rows and keeping the rest of the
+    /// results at the reader, to be read with [`ReadQuery::NextPage`]
+    Paged {
+        /// Where to read from
+        target: ReaderAddress,
+        /// View query to run
+        query: ViewQuery,
+        /// The maximum number of rows to reply with
+        page_size: NonZeroUsize,
+    },
+    /// Read the next page of at most `page_size` rows of the results of a [`ReadQuery::Paged`]
+    NextPage {
+        /// The cursor returned with the previous page of the results
+        cursor: u64,
+        /// The maximum number of rows to reply with
+        page_size: NonZeroUsize,
+    },
     /// Read the size of a leaf view
     Size {
         /// Where to read from
@@ -851,10 +897,24 @@ impl ReadReplyStats {
     }
 }
 
+/// One page of the results of a [`ReadQuery::Paged`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ReadPage<D = ReadReplyBatch> {
+    /// The rows in this page
+    pub rows: D,
+    /// The cursor to read the next page of the results with, or `None` if this is the last page
+    pub cursor: Option<u64>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ReadReply<D = ReadReplyBatch> {
     /// A reply to a normal lookup request
     Normal(ReadySetResult<LookupResult<D>>),
+    /// A reply to a per-key lookup request, with the results for each key in the same order as
+    /// the request, or `None` for keys that missed in a non-blocking read
+    PerKey(ReadySetResult<Vec<Option<D>>>),
+    /// A reply to a paged lookup request, or `None` if the request was non-blocking and missed
+    Page(ReadySetResult<Option<ReadPage<D>>>),
     /// Read size of view
     Size(usize),
     // Read keys of view
@@ -887,6 +947,9 @@ pub struct ReaderHandleBuilder {
     /// entry for each key column at the reader.
     pub key_mapping: Vec<(ViewPlaceholder, KeyColumnIdx)>,
 
+    /// The type of the index on the reader, if it is indexed
+    pub index_type: Option<IndexType>,
+
     /// The amount of time before a view request RPC is terminated.
     pub view_request_timeout: Duration,
 }
@@ -943,6 +1006,7 @@ impl ReaderHandleBuilder {
         let columns = self.columns.clone();
         let schema = self.schema.clone();
         let key_mapping = self.key_mapping.clone();
+        let index_type = self.index_type;
 
         let mut addrs = Vec::with_capacity(shards.len());
         let mut conns = Vec::with_capacity(shards.len());
@@ -993,6 +1057,7 @@ impl ReaderHandleBuilder {
             schema,
             columns,
             key_mapping,
+            index_type,
             shard_addrs: addrs,
             shards: Vec1::try_from_vec(conns).map_err(|_| {
                 internal_err!(
@@ -1047,6 +1112,8 @@ pub struct ReaderHandle {
     /// (view_placeholder, key_column_index) pairs according to their mapping. Contains exactly
     /// one entry for each key column at the reader.
     key_mapping: Vec<(ViewPlaceholder, KeyColumnIdx)>,
+    /// The type of the index on the reader, if it is indexed
+    index_type: Option<IndexType>,
     shards: Vec1<ViewRpc>,
     shard_addrs: Vec<SocketAddr>,
 }
@@ -1119,6 +1186,46 @@ pub struct ViewQuery {
     pub timestamp: Option<Timestamp>,
 }
 
+/// Send `request`, a [`ReadQuery::Paged`] or [`ReadQuery::NextPage`], to a shard of a reader, and
+/// return the page of results it replies with
+async fn read_page(shard: &mut ViewRpc, request: ReadQuery) -> ReadySetResult<ReadPage> {
+    future::poll_fn(|cx| shard.poll_ready(cx))
+        .await
+        .map_err(rpc_err!("ReaderHandle::raw_lookup_stream"))?;
+    let reply = shard
+        .call(Instrumented::from(Tagged::from(request)))
+        .await
+        .map_err(rpc_err!("ReaderHandle::raw_lookup_stream"))?;
+    let ReadReply::Page(page) = reply.v else {
+        internal!("Unexpected response type from reader service");
+    };
+    page?.ok_or(ReadySetError::ReaderMissingKey)
+}
+
+/// Read the results of `request`, a [`ReadQuery::Paged`], from a shard of a reader one page at a
+/// time, only requesting each page once the previous one has been returned
+fn read_pages(
+    shard: ViewRpc,
+    request: ReadQuery,
+    page_size: NonZeroUsize,
+) -> impl Stream<Item = ReadySetResult<Vec<Vec<DfValue>>>> + Send {
+    stream::try_unfold(
+        (shard, Some(request)),
+        move |(mut shard, request)| async move {
+            let Some(request) = request else {
+                return Ok(None);
+            };
+            let page = read_page(&mut shard, request).await?;
+            let next = page
+                .cursor
+                .map(|cursor| ReadQuery::NextPage { cursor, page_size });
+            ReadySetResult::Ok(Some((Vec::from(page.rows), (shard, next))))
+        },
+    )
+    // The last page can be empty if the one before it was full
+    .try_filter(|rows| future::ready(!rows.is_empty()))
+}
+
 // TODO(andrew): consolidate From impls once RYW fully adopted
 impl From<(Vec<KeyComparison>, bool, Option<Timestamp>)> for ViewQuery {
     fn from(
@@ -1393,6 +1500,11 @@ impl ReaderHandle {
         &self.key_mapping
     }
 
+    /// Returns the type of the index on the reader node, if it is indexed
+    pub fn index_type(&self) -> Option<IndexType> {
+        self.index_type
+    }
+
     /// Get the current keys of this view. For debugging only.
     #[instrument(level = "info", skip(self))]
     pub async fn keys(&mut self) -> ReadySetResult<Vec<Vec<DfValue>>> {
@@ -1449,6 +1561,80 @@ impl ReaderHandle {
         }
     }
 
+    /// Issue a raw `ViewQuery` against this view, and return the results as a stream of batches of
+    /// at most `batch_size` rows rather than all at once.
+    ///
+    /// Each shard of the reader replies with one batch at a time, keeping the rest of its results
+    /// until the next batch is requested, which only happens once the stream is polled for it.
+    /// The batches of each shard are returned in turn, so only one batch is read at a time. If the
+    /// stream is dropped before it's finished, the reader drops the rest of the results once
+    /// they've gone unread for a while.
+    ///
+    /// If the query is non-blocking and misses, the stream returns
+    /// [`ReadySetError::ReaderMissingKey`], as [`raw_lookup`](Self::raw_lookup) does.
+    pub fn raw_lookup_stream(
+        &mut self,
+        mut query: ViewQuery,
+        batch_size: NonZeroUsize,
+    ) -> impl Stream<Item = ReadySetResult<Vec<Vec<DfValue>>>> + Send + 'static {
+        let mut shard_queries = vec![Vec::new(); self.shards.len()];
+        for comparison in query.key_comparisons.drain(..) {
+            for shard in comparison.shard_keys(self.shards.len()) {
+                #[allow(clippy::indexing_slicing)]
+                // We built `shard_queries` to be the correct length, so it's safe to access
+                // it by index in this case.
+                shard_queries[shard].push(comparison.clone());
+            }
+        }
+
+        let node = self.node;
+        let shard_pages = self
+            .shards
+            .iter()
+            .enumerate()
+            .zip(shard_queries)
+            .filter(|(_, key_comparisons)| !key_comparisons.is_empty())
+            .map(|((shardi, shard), key_comparisons)| {
+                let request = ReadQuery::Paged {
+                    target: ReaderAddress {
+                        node,
+                        name: self.name.clone(),
+                        shard: shardi,
+                    },
+                    query: ViewQuery {
+                        key_comparisons,
+                        ..query.clone()
+                    },
+                    page_size: batch_size,
+                };
+                read_pages(shard.clone(), request, batch_size)
+            })
+            .collect::<Vec<_>>();
+
+        stream::iter(shard_pages)
+            .flatten()
+            .map_err(move |e| view_err(node, e))
+    }
+
+    /// Retrieve the query results for the given parameter value, as a stream of batches of at most
+    /// `batch_size` rows.
+    ///
+    /// See [`ReaderHandle::raw_lookup_stream`] for how the batches are read.
+    pub fn lookup_stream(
+        &mut self,
+        key: &[DfValue],
+        block: bool,
+        batch_size: NonZeroUsize,
+    ) -> ReadySetResult<impl Stream<Item = ReadySetResult<Vec<Vec<DfValue>>>> + Send + 'static>
+    {
+        let key = Vec1::try_from_vec(key.into())
+            .map_err(|_| view_err(self.node, ReadySetError::EmptyKey))?;
+        Ok(self.raw_lookup_stream(
+            (vec![KeyComparison::Equal(key)], block, None).into(),
+            batch_size,
+        ))
+    }
+
     /// Retrieve the query results for the given parameter value.
     ///
     /// The method will block if the results are not yet available only when `block` is `true`.
@@ -1469,6 +1655,76 @@ impl ReaderHandle {
         self.raw_lookup((key_comparisons, block, None).into()).await
     }
 
+    /// Retrieve the query results for each of the given parameter values separately, in the same
+    /// order as `keys`.
+    ///
+    /// The keys are sent to each shard of the reader in a single request, which looks each of them
+    /// up separately so that the results for each key can be told apart. If `block` is false, keys
+    /// that miss are returned as `None` (and backfilled asynchronously) rather than causing the
+    /// whole lookup to miss, as they would with [`multi_lookup`](Self::multi_lookup).
+    pub async fn bulk_lookup(
+        &mut self,
+        keys: Vec<Vec<DfValue>>,
+        block: bool,
+    ) -> ReadySetResult<Vec<Option<Vec<Vec<DfValue>>>>> {
+        let num_keys = keys.len();
+        let num_shards = self.shards.len();
+        // The indices in `keys`, and the key comparisons, of the keys to send to each shard
+        let mut shard_keys = vec![(Vec::new(), Vec::new()); num_shards];
+        for (i, key) in keys.into_iter().enumerate() {
+            let key = Vec1::try_from_vec(key)
+                .map_err(|_| view_err(self.node, ReadySetError::EmptyKey))?;
+            #[allow(clippy::indexing_slicing)]
+            // `shard_by` always returns a shard less than `num_shards`
+            let (idxs, key_comparisons) = &mut shard_keys[crate::shard_by(key.first(), num_shards)];
+            idxs.push(i);
+            key_comparisons.push(KeyComparison::Equal(key));
+        }
+
+        future::poll_fn(|cx| self.poll_ready(cx)).await?;
+        let node = self.node;
+        let mut requests = FuturesUnordered::new();
+        for ((shardi, shard), (idxs, key_comparisons)) in
+            self.shards.iter_mut().enumerate().zip(shard_keys)
+        {
+            if key_comparisons.is_empty() {
+                // poll_ready reserves a sender slot which we have to release
+                // we do that by dropping the old handle and replacing it with a clone
+                // https://github.com/tokio-rs/tokio/issues/898
+                *shard = shard.clone();
+                continue;
+            }
+            let request = Instrumented::from(Tagged::from(ReadQuery::PerKey {
+                target: ReaderAddress {
+                    node,
+                    name: self.name.clone(),
+                    shard: shardi,
+                },
+                query: (key_comparisons, block, None).into(),
+            }));
+            requests.push(shard.call(request).map_ok(move |reply| (idxs, reply)));
+        }
+
+        let mut results = vec![None; num_keys];
+        while let Some((idxs, reply)) = requests
+            .try_next()
+            .await
+            .map_err(rpc_err!("ReaderHandle::bulk_lookup"))?
+        {
+            let ReadReply::PerKey(shard_results) = reply.v else {
+                internal!("Unexpected response type from reader service");
+            };
+            let shard_results = shard_results.map_err(|e| view_err(node, e))?;
+            for (i, rows) in idxs.into_iter().zip(shard_results) {
+                #[allow(clippy::indexing_slicing)] // `idxs` are all indices in `keys`
+                let result = &mut results[i];
+                *result = rows.map(Vec::from);
+            }
+        }
+
+        Ok(results)
+    }
+
     /// Retrieve the query results for the given parameter value.
     ///
     /// The method will block if the results are not yet available or do not have a timestamp
@@ -1565,7 +1821,7 @@ impl ReaderHandle {
                              idx: &PlaceholderIdx,
                              key_type: &DfType|
              -> ReadySetResult<_> {
-                Ok(match key_remap {
+                let value = match key_remap {
                     Some(remap) => {
                         match remap.get(idx).ok_or_else(|| {
                             internal_err!("Key remapping for ReusedReaderHandle is missing indices")
@@ -1589,7 +1845,10 @@ impl ReaderHandle {
                         }
                     }
                     None => key[*idx - 1].coerce_to(key_type, &DfType::Unknown)?,
-                })
+                };
+                // Both upstreams compare time values in keys at microsecond precision, whatever
+                // the precision of the column (which only applies to values when they're stored)
+                Ok(value.round_subseconds(6))
             };
 
             raw_keys
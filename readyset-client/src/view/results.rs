@@ -344,6 +344,18 @@ impl StreamingIterator for OwnedResultIterator {
 
     #[inline(always)]
     fn advance(&mut self) {
+        // Each row is only returned once, so drop the row we're moving past rather than keeping
+        // the whole resultset in memory until we're done with all of it
+        if let Some(row) = self.row {
+            if let Some(values) = self
+                .data
+                .get_mut(self.set)
+                .and_then(|s| s.results.get_mut(*row))
+            {
+                *values = Vec::new();
+            }
+        }
+
         let row = match self.row.as_mut() {
             Some(row) => {
                 row.inc();
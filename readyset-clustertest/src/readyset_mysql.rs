@@ -1,7 +1,8 @@
 use ::readyset_client::metrics::{recorded, DumpedMetricValue};
 use ::readyset_client::query::QueryId;
 use ::readyset_client::{get_metric, ViewCreateRequest};
-use mysql_async::prelude::Queryable;
+use mysql_async::prelude::{FromRow, Queryable};
+use mysql_async::FromRowError;
 use readyset_adapter::backend::QueryInfo;
 use readyset_client_metrics::QueryDestination;
 use readyset_util::eventually;
@@ -21,6 +22,20 @@ fn readyset_mysql(name: &str) -> DeploymentBuilder {
         .deploy_adapter()
 }
 
+/// The id, query text, and support status of a row of `SHOW PROXIED QUERIES`, ignoring the
+/// columns (like the request rate) that change from one execution to the next
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProxiedQuery(String, String, String);
+
+impl FromRow for ProxiedQuery {
+    fn from_row_opt(row: mysql_async::Row) -> Result<Self, FromRowError> {
+        match (row.get_opt(0), row.get_opt(1), row.get_opt(2)) {
+            (Some(Ok(id)), Some(Ok(query)), Some(Ok(supported))) => Ok(Self(id, query, supported)),
+            _ => Err(FromRowError(row)),
+        }
+    }
+}
+
 async fn last_statement_destination(conn: &mut mysql_async::Conn) -> QueryDestination {
     conn.query_first::<QueryInfo, _>("EXPLAIN LAST STATEMENT")
         .await
@@ -499,12 +514,12 @@ async fn dry_run_evaluates_support() {
     )))
     .to_string();
     let mut results = EventuallyConsistentResults::new();
-    results.write(&[(
+    results.write(&[ProxiedQuery(
         query_id.clone(),
         "SELECT * FROM `t1` WHERE (`uid` = $1)".to_string(),
         "pending".to_string(),
     )]);
-    results.write(&[(
+    results.write(&[ProxiedQuery(
         query_id,
         "SELECT * FROM `t1` WHERE (`uid` = $1)".to_string(),
         "yes".to_string(),
@@ -578,12 +593,12 @@ async fn proxied_queries_filtering() {
     )))
     .to_string();
     let mut results = EventuallyConsistentResults::new();
-    results.write(&[(
+    results.write(&[ProxiedQuery(
         query_id.clone(),
         "SELECT * FROM `t1` WHERE (`uid` = $1)".to_string(),
         "pending".to_string(),
     )]);
-    results.write(&[(
+    results.write(&[ProxiedQuery(
         query_id.clone(),
         "SELECT * FROM `t1` WHERE (`uid` = $1)".to_string(),
         "yes".to_string(),
@@ -605,7 +620,7 @@ async fn proxied_queries_filtering() {
     let proxied_queries = adapter
         .as_mysql_conn()
         .unwrap()
-        .query::<(String, String, String), _>(query)
+        .query::<ProxiedQuery, _>(query)
         .await
         .unwrap();
 
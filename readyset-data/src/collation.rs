@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 use strum_macros::{EnumCount, FromRepr};
 use test_strategy::Arbitrary;
 
+use crate::dialect::SqlEngine;
+use crate::Dialect;
+
 /// Description for how string values should be compared against each other for ordering and
 /// equality.
 ///
@@ -90,6 +93,30 @@ impl Collation {
         }
     }
 
+    /// Returns the collation that compares strings the same way as the collation named `name` in
+    /// an upstream database of the given `dialect`, or `None` if there isn't one.
+    ///
+    /// Only whether a collation is case sensitive is taken into account, so eg MySQL's accent
+    /// insensitive collations compare strings as if they were only case insensitive.
+    pub fn from_upstream_name(name: &str, dialect: Dialect) -> Option<Self> {
+        match dialect.engine() {
+            SqlEngine::MySQL => {
+                let name = name.to_ascii_lowercase();
+                if name.ends_with("_ci") {
+                    Some(Self::Citext)
+                } else if name.ends_with("_cs") || name.ends_with("_bin") || name == "binary" {
+                    Some(Self::Utf8)
+                } else {
+                    None
+                }
+            }
+            // Postgres collations are case sensitive, unless they're nondeterministic ICU
+            // collations, which can't be told apart by name. Case insensitive columns use the
+            // `citext` type instead, which mustn't lose its collation.
+            SqlEngine::PostgreSQL => None,
+        }
+    }
+
     /// Returns `true` if the collation is [`Utf8`].
     ///
     /// [`Utf8`]: Collation::Utf8
@@ -116,6 +143,28 @@ mod tests {
         assert!(Collation::COUNT <= 16)
     }
 
+    #[test]
+    fn from_upstream_name() {
+        for (name, collation) in [
+            ("utf8mb4_0900_ai_ci", Some(Collation::Citext)),
+            ("LATIN1_SWEDISH_CI", Some(Collation::Citext)),
+            ("utf8mb4_0900_as_cs", Some(Collation::Utf8)),
+            ("utf8mb4_bin", Some(Collation::Utf8)),
+            ("binary", Some(Collation::Utf8)),
+            ("utf8mb4_unknown", None),
+        ] {
+            assert_eq!(
+                Collation::from_upstream_name(name, Dialect::DEFAULT_MYSQL),
+                collation,
+                "{name}"
+            );
+        }
+        assert_eq!(
+            Collation::from_upstream_name("en_US.utf8", Dialect::DEFAULT_POSTGRESQL),
+            None
+        );
+    }
+
     #[proptest]
     fn hash_matches_eq(collation: Collation, s1: String, s2: String) {
         if collation.compare_strs(&s1, &s2) == Ordering::Equal {
@@ -2,6 +2,7 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::sync::Arc;
 
+use bit_vec::BitVec;
 use readyset_errors::{ReadySetError, ReadySetResult};
 use rust_decimal::Decimal;
 
@@ -178,11 +179,32 @@ where
             Ok(DfValue::from(r#enum::apply_enum_limits(idx, variants)))
         }
 
+        DfType::Bit(len) => {
+            // Like MySQL's `BIT(n)` columns and PostgreSQL's casts from integers to `bit(n)`, take
+            // the rightmost `n` bits of the value in two's complement, sign-extending if there
+            // aren't enough
+            let (bits, negative) = match i64::try_from(val) {
+                Ok(v) => (v as u64, v < 0),
+                Err(_) => (u64::try_from(val).map_err(|_| err())?, false),
+            };
+            Ok(DfValue::from(
+                (0..len)
+                    .rev()
+                    .map(|i| {
+                        if i < 64 {
+                            (bits >> i) & 1 == 1
+                        } else {
+                            negative
+                        }
+                    })
+                    .collect::<BitVec>(),
+            ))
+        }
+
         DfType::Unknown
         | DfType::MacAddr
         | DfType::Inet
         | DfType::Uuid
-        | DfType::Bit(_)
         | DfType::VarBit(_)
         | DfType::Array(_) => Err(ReadySetError::DfValueConversionError {
             src_type: from_ty.to_string(),
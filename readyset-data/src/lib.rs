@@ -24,7 +24,7 @@ use readyset_errors::{internal, invalid_err, unsupported, ReadySetError, ReadySe
 use readyset_util::arbitrary::{arbitrary_decimal, arbitrary_duration};
 use readyset_util::redacted::Sensitive;
 use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde_json::Value as JsonValue;
 use test_strategy::Arbitrary;
 use tokio_postgres::types::{to_sql_checked, FromSql, IsNull, Kind, ToSql, Type};
@@ -169,6 +169,16 @@ impl fmt::Display for DfValue {
 /// The format for times when parsed as text
 pub const TIME_FORMAT: &str = "%H:%M:%S";
 
+/// Convert a time to a number in the form `hhmmss.ffffff`, the way MySQL does when a time is used
+/// in a numeric context
+fn time_as_decimal(t: &MySqlTime) -> Decimal {
+    let hhmmss =
+        i64::from(t.hour()) * 1_00_00 + i64::from(t.minutes()) * 1_00 + i64::from(t.seconds());
+    let mut d = Decimal::new(hhmmss * 1_000_000 + i64::from(t.microseconds()), 6);
+    d.set_sign_negative(!t.is_positive());
+    d
+}
+
 impl DfValue {
     /// Construct a new [`DfValue::Array`] containing an empty array
     pub fn empty_array() -> Self {
@@ -538,22 +548,96 @@ impl DfValue {
             DfValue::Float(f) => float::coerce_f64(f64::from(*f), to_ty, from_ty),
             DfValue::Double(f) => float::coerce_f64(*f, to_ty, from_ty),
             DfValue::Numeric(d) => float::coerce_decimal(d.as_ref(), to_ty, from_ty),
-            DfValue::Time(ts) => {
-                if let DfType::Text(collation) = to_ty {
+            DfValue::Time(ts) => match to_ty {
+                // TODO(ENG-1833): Use `subsecond_digits` value.
+                DfType::Time { .. } => Ok(self.clone()),
+                DfType::Text(collation) => {
                     Ok(DfValue::from_str_and_collation(&ts.to_string(), *collation))
-                } else {
-                    Err(mk_err())
                 }
-            }
+                DfType::Char(..)
+                | DfType::VarChar(..)
+                | DfType::Blob
+                | DfType::Binary(_)
+                | DfType::VarBinary(_) => {
+                    DfValue::from(ts.to_string()).coerce_to(to_ty, &DfType::Unknown)
+                }
+                // Like MySQL, times are converted to numbers in the form `hhmmss.ffffff`, with
+                // integers rounding off the fractional seconds
+                DfType::Bool
+                | DfType::TinyInt
+                | DfType::UnsignedTinyInt
+                | DfType::SmallInt
+                | DfType::UnsignedSmallInt
+                | DfType::Int
+                | DfType::UnsignedInt
+                | DfType::BigInt
+                | DfType::UnsignedBigInt => float::coerce_decimal(
+                    &time_as_decimal(ts)
+                        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero),
+                    to_ty,
+                    from_ty,
+                ),
+                DfType::Float | DfType::Double | DfType::Numeric { .. } => {
+                    float::coerce_decimal(&time_as_decimal(ts), to_ty, from_ty)
+                }
+                _ => Err(mk_err()),
+            },
             DfValue::BitVector(vec) => match to_ty {
                 DfType::VarBit(None) => Ok(self.clone()),
                 DfType::VarBit(max_size_opt) => match max_size_opt {
                     Some(max_size) if vec.len() > *max_size as usize => Err(mk_err()),
                     _ => Ok(self.clone()),
                 },
+                // Like an explicit cast in PostgreSQL, bits are truncated or zero-padded on the
+                // right to the length of the type
+                DfType::Bit(len) => {
+                    let mut vec = vec.as_ref().clone();
+                    vec.truncate(*len as usize);
+                    vec.grow((*len as usize).saturating_sub(vec.len()), false);
+                    Ok(DfValue::from(vec))
+                }
+                DfType::Text(..) | DfType::Char(..) | DfType::VarChar(..) => {
+                    DfValue::from(self.to_string()).coerce_to(to_ty, &DfType::Unknown)
+                }
+                // Like PostgreSQL, which can cast `bit(n)` to integers but not `bit varying`
+                DfType::Bool
+                | DfType::TinyInt
+                | DfType::UnsignedTinyInt
+                | DfType::SmallInt
+                | DfType::UnsignedSmallInt
+                | DfType::Int
+                | DfType::UnsignedInt
+                | DfType::BigInt
+                | DfType::UnsignedBigInt
+                    if vec.len() <= 64 && !matches!(from_ty, DfType::VarBit(_)) =>
+                {
+                    let val = vec
+                        .iter()
+                        .fold(0u64, |acc, bit| (acc << 1) | u64::from(bit));
+                    integer::coerce_integer(val, to_ty, from_ty)
+                }
                 _ => Err(mk_err()),
             },
-            DfValue::ByteArray(_) | DfValue::Max => Err(mk_err()),
+            DfValue::ByteArray(bytes) => match to_ty {
+                DfType::Blob => Ok(self.clone()),
+                DfType::VarBinary(len) => Ok(DfValue::ByteArray(Arc::new(
+                    bytes.iter().copied().take(*len as usize).collect(),
+                ))),
+                // Like MySQL, binary values are right-padded with zero bytes
+                DfType::Binary(len) => {
+                    let mut bytes = bytes.as_ref().clone();
+                    bytes.resize(*len as usize, 0);
+                    Ok(DfValue::ByteArray(Arc::new(bytes)))
+                }
+                DfType::Text(..) | DfType::Char(..) | DfType::VarChar(..) => {
+                    match str::from_utf8(bytes) {
+                        Ok(s) => DfValue::from(s).coerce_to(to_ty, &DfType::Unknown),
+                        Err(_) => Err(mk_err()),
+                    }
+                }
+                _ => Err(mk_err()),
+            },
+            DfValue::Max => Err(mk_err()),
             DfValue::PassThrough(ref p) => Err(ReadySetError::DfValueConversionError {
                 src_type: format!("PassThrough[{}]", p.ty),
                 target_type: to_ty.to_string(),
@@ -564,8 +648,14 @@ impl DfValue {
 
     /// Mutates the given DfType value to match its underlying database representation for the
     /// given column schema.
+    ///
+    /// Time values are rounded to the precision of the column, which is how both MySQL and
+    /// PostgreSQL store them. MySQL's `TIME_TRUNCATE_FRACTIONAL` SQL mode, which truncates
+    /// instead, is not supported.
     pub fn maybe_coerce_for_table_op(&mut self, col_ty: &DfType) -> ReadySetResult<()> {
-        if col_ty.is_enum() {
+        if let Some(subsecond_digits) = col_ty.subsecond_digits() {
+            *self = self.round_subseconds(subsecond_digits);
+        } else if col_ty.is_enum() {
             *self = self
                 .coerce_to(col_ty, &DfType::Unknown)
                 // There shouldn't be any cases where this coerce_to call returns an error, but if
@@ -579,6 +669,21 @@ impl DfValue {
         Ok(())
     }
 
+    /// Rounds the fractional seconds of time and timestamp values to the given number of
+    /// `subsecond_digits` (at most 6), rounding halves away from zero. Other values are returned
+    /// unchanged.
+    ///
+    /// Rounding to 6 digits normalizes values to the microsecond precision used by both MySQL
+    /// and PostgreSQL, without losing any precision those databases would keep.
+    pub fn round_subseconds(&self, subsecond_digits: u16) -> DfValue {
+        let digits = subsecond_digits.min(6) as u8;
+        match self {
+            DfValue::TimestampTz(ts) => DfValue::TimestampTz(ts.round_subseconds(digits)),
+            DfValue::Time(t) => DfValue::Time(t.round_subseconds(digits)),
+            _ => self.clone(),
+        }
+    }
+
     /// If `self` represents any integer value, returns the integer.
     ///
     /// The returned integer is in the range of [`i64::MIN`] through [`u64::MAX`].
@@ -3789,5 +3894,186 @@ mod tests {
                 DfValue::from(vec![DfValue::from(1u64)])
             );
         }
+
+        /// Coercing any value to any type either succeeds or returns an error - in particular, it
+        /// doesn't panic
+        #[proptest]
+        fn coercion_matrix(
+            value: DfValue,
+            sql_type: SqlType,
+            #[strategy(proptest::sample::select(vec![
+                Dialect::DEFAULT_MYSQL,
+                Dialect::DEFAULT_POSTGRESQL,
+            ]))]
+            dialect: Dialect,
+        ) {
+            let to_ty = DfType::from_sql_type(&sql_type, dialect, |_| None);
+            prop_assume!(to_ty.is_ok());
+            let to_ty = to_ty.unwrap();
+
+            let _ = value.coerce_to(&to_ty, &DfType::Unknown);
+            let _ = value.coerce_to(&to_ty, &value.infer_dataflow_type());
+        }
+
+        #[test]
+        fn time_to_numbers() {
+            let time = DfValue::from(MySqlTime::from_hmsus(false, 12, 34, 56, 500_000));
+            assert_eq!(
+                time.coerce_to(&DfType::BigInt, &DfType::Unknown).unwrap(),
+                DfValue::from(-123457)
+            );
+            assert_eq!(
+                time.coerce_to(&DfType::Double, &DfType::Unknown).unwrap(),
+                DfValue::Double(-123456.5)
+            );
+            assert_eq!(
+                time.coerce_to(&DfType::DEFAULT_NUMERIC, &DfType::Unknown)
+                    .unwrap(),
+                DfValue::from(Decimal::new(-123456500000, 6))
+            );
+            time.coerce_to(&DfType::SmallInt, &DfType::Unknown)
+                .unwrap_err();
+        }
+
+        #[test]
+        fn time_to_time_and_text() {
+            let time = DfValue::from(MySqlTime::from_hmsus(true, 1, 2, 3, 0));
+            assert_eq!(
+                time.coerce_to(
+                    &DfType::Time {
+                        subsecond_digits: 6
+                    },
+                    &DfType::Unknown
+                )
+                .unwrap(),
+                time
+            );
+            assert_eq!(
+                time.coerce_to(&DfType::VarChar(5, Collation::default()), &DfType::Unknown)
+                    .unwrap(),
+                DfValue::from("01:02")
+            );
+        }
+
+        #[test]
+        fn integers_to_bits() {
+            let bits = |s: &str| DfValue::from(s.chars().map(|c| c == '1').collect::<BitVec>());
+            assert_eq!(
+                DfValue::from(5).coerce_to(&DfType::Bit(4), &DfType::Unknown),
+                Ok(bits("0101"))
+            );
+            assert_eq!(
+                DfValue::from(13).coerce_to(&DfType::Bit(2), &DfType::Unknown),
+                Ok(bits("01"))
+            );
+            assert_eq!(
+                DfValue::from(-1).coerce_to(&DfType::Bit(4), &DfType::Unknown),
+                Ok(bits("1111"))
+            );
+            assert_eq!(
+                DfValue::from(-1).coerce_to(&DfType::Bit(66), &DfType::Unknown),
+                Ok(bits(&"1".repeat(66)))
+            );
+            assert_eq!(
+                DfValue::from(u64::MAX).coerce_to(&DfType::Bit(66), &DfType::Unknown),
+                Ok(bits(&format!("00{}", "1".repeat(64))))
+            );
+        }
+
+        #[test]
+        fn bits_to_other_types() {
+            let bits = DfValue::from(BitVec::from_iter([false, true, false, true]));
+            assert_eq!(
+                bits.coerce_to(&DfType::Int, &DfType::Unknown),
+                Ok(DfValue::from(5))
+            );
+            assert_eq!(
+                bits.coerce_to(&DfType::Int, &DfType::Bit(4)),
+                Ok(DfValue::from(5))
+            );
+            bits.coerce_to(&DfType::Int, &DfType::VarBit(None))
+                .unwrap_err();
+            assert_eq!(
+                bits.coerce_to(&DfType::DEFAULT_TEXT, &DfType::Unknown),
+                Ok(DfValue::from("0101"))
+            );
+            assert_eq!(
+                bits.coerce_to(&DfType::Bit(6), &DfType::Unknown),
+                Ok(DfValue::from(BitVec::from_iter([
+                    false, true, false, true, false, false
+                ])))
+            );
+            assert_eq!(
+                bits.coerce_to(&DfType::Bit(2), &DfType::Unknown),
+                Ok(DfValue::from(BitVec::from_iter([false, true])))
+            );
+        }
+
+        #[test]
+        fn text_to_bits() {
+            assert_eq!(
+                DfValue::from("0101").coerce_to(&DfType::Bit(4), &DfType::Unknown),
+                Ok(DfValue::from(BitVec::from_iter([false, true, false, true])))
+            );
+            assert_eq!(
+                DfValue::from("11").coerce_to(&DfType::VarBit(None), &DfType::Unknown),
+                Ok(DfValue::from(BitVec::from_iter([true, true])))
+            );
+            DfValue::from("012")
+                .coerce_to(&DfType::Bit(3), &DfType::Unknown)
+                .unwrap_err();
+        }
+
+        #[test]
+        fn byte_arrays() {
+            let bytes = DfValue::from(b"abc".to_vec());
+            assert_eq!(
+                bytes.coerce_to(&DfType::Binary(5), &DfType::Unknown),
+                Ok(DfValue::from(b"abc\0\0".to_vec()))
+            );
+            assert_eq!(
+                bytes.coerce_to(&DfType::VarBinary(2), &DfType::Unknown),
+                Ok(DfValue::from(b"ab".to_vec()))
+            );
+            assert_eq!(
+                bytes.coerce_to(&DfType::DEFAULT_TEXT, &DfType::Unknown),
+                Ok(DfValue::from("abc"))
+            );
+            DfValue::from(vec![0xffu8])
+                .coerce_to(&DfType::DEFAULT_TEXT, &DfType::Unknown)
+                .unwrap_err();
+        }
+    }
+
+    #[test]
+    fn table_op_rounds_subseconds() {
+        let mut ts =
+            DfValue::from(NaiveDate::from_ymd(2022, 2, 9).and_hms_micro(13, 14, 15, 500_000));
+        ts.maybe_coerce_for_table_op(&DfType::DateTime {
+            subsecond_digits: 0,
+        })
+        .unwrap();
+        assert_eq!(
+            ts,
+            DfValue::from(NaiveDate::from_ymd(2022, 2, 9).and_hms(13, 14, 16))
+        );
+
+        let mut time = DfValue::Time(MySqlTime::from_hmsus(false, 1, 2, 3, 456_789));
+        time.maybe_coerce_for_table_op(&DfType::Time {
+            subsecond_digits: 2,
+        })
+        .unwrap();
+        assert_eq!(
+            time,
+            DfValue::Time(MySqlTime::from_hmsus(false, 1, 2, 3, 460_000))
+        );
+
+        // Keys are normalized to microseconds
+        let ts =
+            DfValue::from(NaiveDate::from_ymd(2022, 2, 9).and_hms_nano(13, 14, 15, 123_456_500));
+        assert_eq!(
+            ts.round_subseconds(6),
+            DfValue::from(NaiveDate::from_ymd(2022, 2, 9).and_hms_micro(13, 14, 15, 123_457))
+        );
     }
 }
@@ -550,7 +550,16 @@ pub(crate) trait TextCoerce: Sized + Clone + Into<DfValue> {
                 }
             }
 
-            DfType::Bit(_) | DfType::VarBit(_) => Err(Self::coerce_err(to_ty, "Not allowed")),
+            DfType::Bit(_) | DfType::VarBit(_) => DfValue::from(
+                str.chars()
+                    .map(|c| match c {
+                        '0' => Ok(false),
+                        '1' => Ok(true),
+                        _ => Err(Self::coerce_err(to_ty, format!("{c:?} is not a valid bit"))),
+                    })
+                    .collect::<ReadySetResult<bit_vec::BitVec>>()?,
+            )
+            .coerce_to(to_ty, from_ty),
         }
     }
 }
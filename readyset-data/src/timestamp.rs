@@ -2,7 +2,7 @@ use std::fmt;
 use std::hash::Hash;
 use std::str::FromStr;
 
-use chrono::{Date, DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Timelike};
+use chrono::{Date, DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, Timelike};
 use proptest::arbitrary::Arbitrary;
 use readyset_errors::{ReadySetError, ReadySetResult};
 use serde::{Deserialize, Serialize};
@@ -256,6 +256,27 @@ impl TimestampTz {
         self.into()
     }
 
+    /// Rounds the fractional seconds of this timestamp to the given number of `digits` (at most
+    /// 6), rounding halves away from zero and carrying into the seconds if needed.
+    ///
+    /// This is what both MySQL and PostgreSQL do when storing a value in a column with a lower
+    /// precision. Unlike [`TimestampTz::subsecond_digits`], which only affects how the timestamp
+    /// is displayed, this changes the value itself, and with it how the timestamp compares.
+    pub fn round_subseconds(&self, digits: u8) -> TimestampTz {
+        let unit = 1000 * 10_i64.pow(6 - u32::from(digits.min(6)));
+        // Copy the datetime out first, since we can't take references into a packed struct
+        let datetime = self.datetime;
+        let nanos = i64::from(datetime.nanosecond());
+        let rounded = (nanos + unit / 2) / unit * unit;
+        let datetime = datetime
+            .checked_add_signed(Duration::nanoseconds(rounded - nanos))
+            .unwrap_or(datetime);
+        TimestampTz {
+            extra: self.extra,
+            datetime,
+        }
+    }
+
     // MySQL can cast a timestamp into a signed/unsigned integer
     // where the fields up to seconds are decimal digits. i.e.
     // +--------------------------------------------------------------+
@@ -471,6 +492,33 @@ mod tests {
     use super::*;
     use crate::{Collation, DfType};
 
+    #[test]
+    fn round_subseconds() {
+        let ts = TimestampTz::from(
+            chrono::NaiveDate::from_ymd(2022, 12, 31).and_hms_micro(23, 59, 59, 999_500),
+        );
+        assert_eq!(ts.round_subseconds(6), ts);
+        assert_eq!(
+            ts.round_subseconds(3),
+            TimestampTz::from(chrono::NaiveDate::from_ymd(2023, 1, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            ts.round_subseconds(4).to_chrono().naive_local(),
+            chrono::NaiveDate::from_ymd(2022, 12, 31).and_hms_micro(23, 59, 59, 999_500)
+        );
+
+        let ts = TimestampTz::from_str("2004-10-19 10:23:54.25+02").unwrap();
+        let rounded = ts.round_subseconds(1);
+        assert_eq!(
+            rounded.to_chrono(),
+            chrono::FixedOffset::east(2 * 60 * 60)
+                .ymd(2004, 10, 19)
+                .and_hms_milli(10, 23, 54, 300)
+        );
+        assert!(rounded.has_timezone());
+        assert_eq!(rounded.subsecond_digits(), ts.subsecond_digits());
+    }
+
     #[test]
     fn timestamp_coercion() {
         let ts =
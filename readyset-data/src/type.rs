@@ -277,6 +277,10 @@ impl DfType {
             VarBinary(len) => Self::VarBinary(len),
             Binary(len) => Self::Binary(len.unwrap_or(1)),
 
+            // We don't support any spatial functions, so spatial values are stored as the bytes of
+            // MySQL's internal geometry format (a 4-byte SRID followed by the WKB of the value)
+            Geometry(_) => Self::Blob,
+
             Bit(len) => Self::Bit(len.unwrap_or(1)),
             VarBit(len) => Self::VarBit(len),
 
@@ -463,6 +467,18 @@ impl DfType {
         matches!(self, Self::Text(..) | Self::VarChar(..) | Self::Char(..))
     }
 
+    /// Returns this type with its collation replaced by `collation`, if it's any `text` type, or
+    /// this type as is otherwise
+    #[must_use]
+    pub fn with_collation(self, collation: Collation) -> Self {
+        match self {
+            Self::Text(_) => Self::Text(collation),
+            Self::VarChar(len, _) => Self::VarChar(len, collation),
+            Self::Char(len, _) => Self::Char(len, collation),
+            ty => ty,
+        }
+    }
+
     /// Returns `true` if this is any IEEE 754 floating-point type.
     #[inline]
     pub fn is_any_float(&self) -> bool {
@@ -145,3 +145,118 @@ mod arrays {
         .await;
     }
 }
+
+mod casts {
+    use bit_vec::BitVec;
+    use proptest::prelude::*;
+    use proptest::strategy::ValueTree;
+    use proptest::test_runner::TestRunner;
+    use readyset_data::DfType;
+
+    use super::*;
+
+    /// Check that coercing generated values of type `from_ty` to each of `targets` agrees with
+    /// casting them from `from_sql` to the corresponding SQL type in postgres - either both
+    /// succeed with the same value, or both fail
+    async fn check_casts<S>(
+        values: S,
+        (from_sql, from_ty): (&str, DfType),
+        targets: &[(&str, DfType)],
+    ) where
+        S: Strategy,
+        S::Value: Into<DfValue>,
+    {
+        let (client, conn) = config().connect(NoTls).await.unwrap();
+        tokio::spawn(conn);
+
+        let values = proptest::collection::vec(values, 100)
+            .new_tree(&mut TestRunner::default())
+            .unwrap()
+            .current();
+
+        for value in values {
+            let value: DfValue = value.into();
+            for (to_sql, to_ty) in targets {
+                let upstream = client
+                    .query_one(
+                        format!("SELECT $1::text::{from_sql}::{to_sql}").as_str(),
+                        &[&value.to_string()],
+                    )
+                    .await
+                    .map(|row| row.get::<_, DfValue>(0));
+                let coerced = value.coerce_to(to_ty, &from_ty);
+
+                match (upstream, coerced) {
+                    (Ok(upstream), Ok(coerced)) => {
+                        assert_eq!(coerced, upstream, "CAST({value} AS {to_sql})")
+                    }
+                    (Err(_), Err(_)) => {}
+                    (upstream, coerced) => panic!(
+                        "CAST({value} AS {to_sql}): upstream returned {upstream:?}, but coercion \
+                         returned {coerced:?}"
+                    ),
+                }
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn cast_integers() {
+        check_casts(
+            any::<i64>(),
+            ("bigint", DfType::BigInt),
+            &[
+                ("smallint", DfType::SmallInt),
+                ("integer", DfType::Int),
+                ("bigint", DfType::BigInt),
+                ("real", DfType::Float),
+                ("double precision", DfType::Double),
+                ("numeric", DfType::DEFAULT_NUMERIC),
+                ("text", DfType::DEFAULT_TEXT),
+                ("bit(1)", DfType::Bit(1)),
+                ("bit(8)", DfType::Bit(8)),
+                ("bit(64)", DfType::Bit(64)),
+                ("bit(70)", DfType::Bit(70)),
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn cast_text() {
+        check_casts(
+            any::<i64>().prop_map(|n| n.to_string()),
+            ("text", DfType::DEFAULT_TEXT),
+            &[
+                ("smallint", DfType::SmallInt),
+                ("integer", DfType::Int),
+                ("bigint", DfType::BigInt),
+                ("real", DfType::Float),
+                ("double precision", DfType::Double),
+                ("numeric", DfType::DEFAULT_NUMERIC),
+                ("text", DfType::DEFAULT_TEXT),
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn cast_bits() {
+        check_casts(
+            proptest::collection::vec(any::<bool>(), 0..=16)
+                .prop_map(|bits| bits.into_iter().collect::<BitVec>()),
+            ("bit varying", DfType::VarBit(None)),
+            &[
+                ("integer", DfType::Int),
+                ("bigint", DfType::BigInt),
+                ("text", DfType::DEFAULT_TEXT),
+                ("bit(8)", DfType::Bit(8)),
+                ("bit varying", DfType::VarBit(None)),
+            ],
+        )
+        .await;
+    }
+}
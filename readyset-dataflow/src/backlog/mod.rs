@@ -6,13 +6,17 @@ use std::sync::Arc;
 use ahash::RandomState;
 use common::SizeOf;
 use dataflow_expression::{PostLookup, ReaderProcessing};
+use dataflow_state::{SpillReadCallback, SpillStore};
 use reader_map::EvictionStrategy;
 use readyset_client::consistency::Timestamp;
+use readyset_client::debug::stats::CacheStats;
 use readyset_client::results::SharedResults;
 use readyset_client::KeyComparison;
+use tracing::warn;
 use vec1::Vec1;
 
 pub use self::multir::LookupError;
+use self::stats::ReaderStats;
 use crate::prelude::*;
 
 /// The kind of reader update notification, currently the eviction epoch of the writer
@@ -137,6 +141,7 @@ fn new_inner(
     };
 
     let (notifier, receiver) = tokio::sync::broadcast::channel(1);
+    let stats = Arc::new(ReaderStats::default());
 
     let w = WriteHandle {
         partial: trigger.is_some(),
@@ -147,6 +152,8 @@ fn new_inner(
         mem_size: 0,
         notifier,
         eviction_epoch: 0,
+        stats: stats.clone(),
+        spill: None,
     };
 
     let r = SingleReadHandle {
@@ -156,6 +163,7 @@ fn new_inner(
         post_lookup: post_processing,
         receiver,
         eviction_epoch: 0,
+        stats,
     };
 
     (r, w)
@@ -163,6 +171,7 @@ fn new_inner(
 
 mod multir;
 mod multiw;
+mod stats;
 
 fn key_to_single(k: Key) -> Cow<DfValue> {
     assert_eq!(k.len(), 1);
@@ -183,6 +192,11 @@ pub(crate) struct WriteHandle {
     notifier: ReaderUpdatedSender,
     /// How many eviction rounds this handle had
     eviction_epoch: usize,
+    /// Statistics about the reads of and evictions from this reader, shared with its readers
+    stats: Arc<ReaderStats>,
+    /// If set, the rows of keys evicted to free memory are written here rather than discarded, so
+    /// that they can be filled back in by [`WriteHandle::fill_from_spill`] without an upquery
+    spill: Option<SpillStore>,
 }
 
 type Key<'a> = Cow<'a, [DfValue]>;
@@ -315,6 +329,102 @@ impl WriteHandle {
         self.partial
     }
 
+    /// Write the rows of keys evicted by [`WriteHandle::evict_bytes`] to `spill`, rather than
+    /// discarding them.
+    ///
+    /// Only point keys are spilled, so this should only be used for readers with a
+    /// [`IndexType::HashMap`] index.
+    pub(crate) fn set_spill_store(&mut self, spill: SpillStore) {
+        debug_assert_eq!(self.index.index_type, IndexType::HashMap);
+        self.spill = Some(spill);
+    }
+
+    /// Discard any spilled rows for the keys of `records`, which are being written to while their
+    /// keys are holes, so that those keys are upqueried rather than filled with stale rows from the
+    /// spill store.
+    ///
+    /// If the spilled rows can't be discarded, stops spilling altogether.
+    pub(crate) fn discard_spilled<'a, I>(&mut self, records: I)
+    where
+        I: IntoIterator<Item = &'a [DfValue]>,
+    {
+        let Some(spill) = self.spill.as_mut() else {
+            return;
+        };
+        if spill.is_empty() {
+            return;
+        }
+
+        let key_cols = self.index.columns.as_slice();
+        let contiguous = self.contiguous;
+        let result = records.into_iter().try_for_each(|rec| {
+            if contiguous {
+                spill.remove(&rec[key_cols[0]..(key_cols[0] + key_cols.len())])
+            } else {
+                spill.remove(&key_cols.iter().map(|c| rec[*c].clone()).collect::<Vec<_>>())
+            }
+        });
+        if let Err(error) = result {
+            warn!(%error, "Could not discard spilled rows, no longer spilling evicted keys");
+            self.spill = None;
+        }
+    }
+
+    /// Returns true if any keys evicted from this reader are in its spill store
+    pub(crate) fn has_spilled_keys(&self) -> bool {
+        self.spill.as_ref().map_or(false, |spill| !spill.is_empty())
+    }
+
+    /// If the rows for `key` were spilled to disk when it was evicted, start reading them back
+    /// and return `true`. `reply` is called, on another thread, with the generation of the
+    /// spilled key and its rows once they've been read, which should then be passed to
+    /// [`WriteHandle::fill_from_spill`]. Otherwise, return `false`.
+    pub(crate) fn read_spilled(
+        &mut self,
+        key: &KeyComparison,
+        reply: SpillReadCallback,
+    ) -> ReadySetResult<bool> {
+        let (Some(spill), KeyComparison::Equal(equal)) = (self.spill.as_mut(), key) else {
+            return Ok(false);
+        };
+        spill.read(equal.as_slice(), reply)
+    }
+
+    /// Fill the hole for `key` with `rows`, which were read from the spill store by
+    /// [`WriteHandle::read_spilled`] for the given `generation` of the key, and return `true`.
+    ///
+    /// If the key was written to, evicted, filled or spilled again since the read started, `rows`
+    /// are out of date, so the hole is left as is and this returns `false`.
+    ///
+    /// As with any other fill, the rows will be made visible to readers after the next call to
+    /// `swap()`.
+    pub(crate) fn fill_from_spill(
+        &mut self,
+        key: &KeyComparison,
+        generation: u64,
+        rows: Vec<Vec<DfValue>>,
+    ) -> ReadySetResult<bool> {
+        let (Some(spill), KeyComparison::Equal(equal)) = (self.spill.as_mut(), key) else {
+            return Ok(false);
+        };
+        if !spill.finish_read(equal.as_slice(), generation)? {
+            return Ok(false);
+        }
+
+        self.mut_with_key(equal.as_vec()).mark_filled()?;
+        self.add(rows.into_iter().map(Record::Positive));
+        Ok(true)
+    }
+
+    /// Give up on reading the rows of `key` back from the spill store, such as because the read
+    /// failed, so that the key is upqueried instead
+    pub(crate) fn discard_spilled_key(&mut self, key: &KeyComparison) -> ReadySetResult<()> {
+        if let (Some(spill), KeyComparison::Equal(equal)) = (self.spill.as_mut(), key) {
+            spill.remove(equal.as_slice())?;
+        }
+        Ok(())
+    }
+
     /// Attempt to evict `bytes` from state. This approximates the number of keys to evict,
     /// these keys may not have exactly `bytes` worth of state.
     pub(crate) fn evict_bytes(&mut self, bytes: usize) -> u64 {
@@ -326,7 +436,11 @@ impl WriteHandle {
                 self.mem_size
             );
 
-            bytes_to_be_freed += self.handle.evict(bytes as f64 / self.mem_size as f64);
+            let (keys_evicted, bytes_freed) = self
+                .handle
+                .evict(bytes as f64 / self.mem_size as f64, self.spill.as_mut());
+            self.stats.record_eviction(keys_evicted, bytes_freed);
+            bytes_to_be_freed += bytes_freed;
         }
 
         self.mem_size = self.mem_size.saturating_sub(bytes_to_be_freed as usize);
@@ -338,7 +452,13 @@ impl WriteHandle {
             invariant_eq!(len, self.index.len());
         }
         match key {
-            KeyComparison::Equal(k) => self.mut_with_key(k.as_vec()).mark_hole(),
+            KeyComparison::Equal(k) => {
+                // Writes to a hole are dropped, so any rows spilled for the key would go stale
+                if let Some(spill) = self.spill.as_mut() {
+                    spill.remove(k.as_slice())?;
+                }
+                self.mut_with_key(k.as_vec()).mark_hole()
+            }
             KeyComparison::Range((start, end)) => {
                 let start = start.clone();
                 let end = end.clone();
@@ -366,6 +486,14 @@ impl WriteHandle {
         Ok(())
     }
 
+    /// Evict `key` from the reader, by marking it as a hole, and record the eviction in the
+    /// reader's statistics
+    pub(crate) fn evict_key(&mut self, key: &KeyComparison) -> ReadySetResult<()> {
+        self.mark_hole(key)?;
+        self.stats.record_key_eviction(key);
+        Ok(())
+    }
+
     pub(crate) fn mark_filled(&mut self, key: KeyComparison) -> ReadySetResult<()> {
         if let Some(len) = key.len() {
             invariant_eq!(len, self.index.len());
@@ -374,6 +502,10 @@ impl WriteHandle {
         #[allow(clippy::unreachable)] // Documented invariant.
         let range = match (self.index.index_type, &key) {
             (IndexType::HashMap, KeyComparison::Equal(equal)) => {
+                // The key is being filled by a replay, so we no longer need any rows spilled for it
+                if let Some(spill) = self.spill.as_mut() {
+                    spill.remove(equal.as_slice())?;
+                }
                 return self.mut_with_key(equal.as_vec()).mark_filled();
             }
             (IndexType::HashMap, KeyComparison::Range(_)) => {
@@ -397,6 +529,12 @@ impl WriteHandle {
         Ok(())
     }
 
+    /// Returns statistics about the reads of and evictions from this reader, with at most
+    /// `max_keys` of its most frequently read keys
+    pub(crate) fn stats(&self, max_keys: usize) -> CacheStats {
+        self.stats.snapshot(max_keys)
+    }
+
     /// Increment the eviction epoch, and notify readers
     pub(crate) fn notify_readers_of_eviction(&mut self) -> ReadySetResult<()> {
         self.eviction_epoch += 1;
@@ -438,6 +576,9 @@ pub struct SingleReadHandle {
     receiver: ReaderUpdatedNotifier,
     /// Caches the eviction epoch of the associated [`WriteHandle`]
     eviction_epoch: usize,
+    /// Statistics about the reads of and evictions from this reader, shared with its
+    /// [`WriteHandle`]
+    stats: Arc<ReaderStats>,
 }
 
 impl Clone for SingleReadHandle {
@@ -449,6 +590,7 @@ impl Clone for SingleReadHandle {
             post_lookup: self.post_lookup.clone(),
             receiver: self.receiver.resubscribe(),
             eviction_epoch: self.eviction_epoch,
+            stats: self.stats.clone(),
         }
     }
 }
@@ -548,6 +690,18 @@ impl SingleReadHandle {
 
         self.eviction_epoch
     }
+
+    /// Record a lookup of `keys` in this reader, of which the keys in `misses` missed, in the
+    /// statistics returned by [`WriteHandle::stats`]. If `consistency_miss` is true, every key is
+    /// recorded as a miss.
+    pub fn record_lookup(
+        &self,
+        keys: &[KeyComparison],
+        misses: &[Cow<KeyComparison>],
+        consistency_miss: bool,
+    ) {
+        self.stats.record_lookup(keys, misses, consistency_miss)
+    }
 }
 
 #[cfg(test)]
@@ -732,6 +886,87 @@ mod tests {
         }
     }
 
+    mod spill {
+        use super::*;
+
+        fn spilling_reader(dir: &std::path::Path) -> (SingleReadHandle, WriteHandle) {
+            let (r, mut w) = new_partial(
+                2,
+                Index::hash_map(vec![0]),
+                |_: &mut dyn Iterator<Item = KeyComparison>| true,
+                EvictionKind::Random,
+                ReaderProcessing::default(),
+            );
+            w.set_spill_store(SpillStore::new(dir, "reader", None).unwrap());
+            w.swap();
+
+            w.mark_filled(vec1![DfValue::from(1)].into()).unwrap();
+            w.add(vec![Record::Positive(vec![1.into(), "a".into()])]);
+            w.swap();
+            w.evict_bytes(usize::MAX);
+            w.swap();
+            assert!(r.get(&[1.into()]).err().unwrap().is_miss());
+
+            (r, w)
+        }
+
+        /// Read `key` back from the spill store of `w`, and wait for the read to finish
+        fn read_spilled(
+            w: &mut WriteHandle,
+            key: &KeyComparison,
+        ) -> Option<(u64, Vec<Vec<DfValue>>)> {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let reading = w
+                .read_spilled(
+                    key,
+                    Box::new(move |generation, rows| {
+                        tx.send((generation, rows.unwrap())).unwrap();
+                    }),
+                )
+                .unwrap();
+            reading.then(|| rx.recv_timeout(std::time::Duration::from_secs(10)).unwrap())
+        }
+
+        #[test]
+        fn fill_from_spill() {
+            let dir = tempfile::tempdir().unwrap();
+            let (r, mut w) = spilling_reader(dir.path());
+            let key = KeyComparison::from(vec1![DfValue::from(1)]);
+
+            let (generation, rows) = read_spilled(&mut w, &key).unwrap();
+            assert!(w.fill_from_spill(&key, generation, rows).unwrap());
+            w.swap();
+            let rows = r.get(&[1.into()]).unwrap();
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0], vec![1.into(), "a".into()].into_boxed_slice());
+
+            assert!(read_spilled(&mut w, &key).is_none());
+        }
+
+        #[test]
+        fn writes_during_read_discard_spilled_rows() {
+            let dir = tempfile::tempdir().unwrap();
+            let (r, mut w) = spilling_reader(dir.path());
+            let key = KeyComparison::from(vec1![DfValue::from(1)]);
+
+            let (generation, rows) = read_spilled(&mut w, &key).unwrap();
+            // A write to the key arrives before the read is finished
+            w.discard_spilled([&[DfValue::from(1), DfValue::from("b")][..]]);
+            assert!(!w.fill_from_spill(&key, generation, rows).unwrap());
+            w.swap();
+            assert!(r.get(&[1.into()]).err().unwrap().is_miss());
+        }
+
+        #[test]
+        fn writes_discard_spilled_rows() {
+            let dir = tempfile::tempdir().unwrap();
+            let (_r, mut w) = spilling_reader(dir.path());
+
+            w.discard_spilled([&[DfValue::from(1), DfValue::from("b")][..]]);
+            assert!(read_spilled(&mut w, &vec1![DfValue::from(1)].into()).is_none());
+        }
+    }
+
     mod mark_filled {
         use super::*;
 
@@ -2,7 +2,10 @@ use std::ops::{Bound, RangeBounds};
 
 use ahash::RandomState;
 use dataflow_expression::PreInsertion;
+use dataflow_state::SpillStore;
+use reader_map::refs::Values;
 use readyset_client::consistency::Timestamp;
+use tracing::warn;
 
 use super::{key_to_single, Key};
 use crate::prelude::*;
@@ -88,19 +91,36 @@ impl Handle {
     }
 
     /// Evict keys that were selected by the assigned eviction strategy from the state, and return
-    /// the number of bytes freed. The amount of keys evicted will be ceil(len() * ratio)
-    pub fn evict(&mut self, ratio: f64) -> u64 {
+    /// the number of keys evicted and the number of bytes freed. The amount of keys evicted will be
+    /// ceil(len() * ratio).
+    ///
+    /// If `spill` is set, the rows of each evicted key are written to it, so that they can be read
+    /// back later without an upquery.
+    pub fn evict(&mut self, ratio: f64, mut spill: Option<&mut SpillStore>) -> (u64, u64) {
         let base_value_size = self.base_value_size() as u64;
-        match *self {
+        let mut keys_evicted = 0;
+        let mut spill_key = |key: &[DfValue], rows: &Values<Box<[DfValue]>>| {
+            if let Some(spill) = spill.as_deref_mut() {
+                if let Err(error) = spill.spill(key, rows.iter().map(|r| &r[..])) {
+                    warn!(%error, "Could not spill evicted key to disk");
+                }
+            }
+        };
+        let bytes_freed = match *self {
             Handle::Single(ref mut h) => h.evict_keys(ratio, |k, v| {
+                keys_evicted += 1;
+                spill_key(std::slice::from_ref(k), v);
                 // Each row's state is composed of: The key, the set of Values in the row (DfValues)
                 // and the bytes required to hold the Row data structure.
                 k.deep_size_of() + v.iter().map(|r| r.deep_size_of()).sum::<u64>() + base_value_size
             }),
             Handle::Many(ref mut h) => h.evict_keys(ratio, |k, v| {
+                keys_evicted += 1;
+                spill_key(k, v);
                 k.deep_size_of() + v.iter().map(|r| r.deep_size_of()).sum::<u64>() + base_value_size
             }),
-        }
+        };
+        (keys_evicted, bytes_freed)
     }
 
     pub fn refresh(&mut self) {
@@ -0,0 +1,267 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use readyset_client::debug::stats::{CacheStats, KeyStats};
+use readyset_client::KeyComparison;
+use readyset_data::DfValue;
+
+/// The maximum number of distinct keys to keep statistics for in a single reader. Once twice this
+/// many keys are tracked, the least frequently read keys are forgotten.
+const MAX_TRACKED_KEYS: usize = 1024;
+
+/// The number of shards the per-key statistics are split into, so that lookups of keys in
+/// different shards don't contend on the same lock
+const SHARDS: usize = 16;
+
+/// Counts of reads of and evictions from a single key, which can be incremented while holding only
+/// a read lock on the key's shard
+#[derive(Default)]
+struct KeyCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl KeyCounters {
+    fn reads(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed) + self.misses.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, missed: bool) {
+        if missed {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+type Shard = RwLock<HashMap<Vec<DfValue>, KeyCounters>>;
+
+/// Forget all but the `max_keys` most frequently read keys in `keys`. Keys read exactly as often
+/// as the least frequently read key that's kept are kept too, as long as there's room for them.
+fn forget_infrequent_keys(keys: &mut HashMap<Vec<DfValue>, KeyCounters>, max_keys: usize) {
+    if keys.len() <= max_keys {
+        return;
+    }
+    let mut reads = keys.values().map(KeyCounters::reads).collect::<Vec<_>>();
+    reads.sort_unstable_by(|a, b| b.cmp(a));
+    let min_reads = reads[max_keys - 1];
+    let mut tied = reads[..max_keys]
+        .iter()
+        .filter(|reads| **reads == min_reads)
+        .count();
+    keys.retain(|_, counters| {
+        let reads = counters.reads();
+        if reads == min_reads && tied > 0 {
+            tied -= 1;
+            true
+        } else {
+            reads > min_reads
+        }
+    });
+}
+
+/// Counts of hits, misses and evictions for the keys of a single reader, shared between its
+/// [`WriteHandle`](super::WriteHandle) and all of its
+/// [`SingleReadHandle`](super::SingleReadHandle)s
+#[derive(Default)]
+pub(crate) struct ReaderStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    evicted_keys: AtomicU64,
+    evicted_bytes: AtomicU64,
+    /// Hasher used to pick the shard of [`Self::keys`] each key belongs to
+    hasher: ahash::RandomState,
+    /// The counts of each point key, split into shards by the hash of the key
+    keys: [Shard; SHARDS],
+}
+
+impl ReaderStats {
+    // The statistics are only ever incremented, so they're still consistent if another thread
+    // panicked while holding a lock on a shard
+    fn read(shard: &Shard) -> RwLockReadGuard<'_, HashMap<Vec<DfValue>, KeyCounters>> {
+        shard.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(shard: &Shard) -> RwLockWriteGuard<'_, HashMap<Vec<DfValue>, KeyCounters>> {
+        shard.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn shard(&self, key: &[DfValue]) -> &Shard {
+        let mut hasher = self.hasher.build_hasher();
+        key.hash(&mut hasher);
+        &self.keys[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Record a read of `key`, only taking a write lock on its shard if the key isn't tracked yet
+    fn record_key(&self, key: &[DfValue], missed: bool) {
+        let shard = self.shard(key);
+        if let Some(counters) = Self::read(shard).get(key) {
+            counters.record(missed);
+            return;
+        }
+
+        let mut keys = Self::write(shard);
+        if !keys.contains_key(key) && keys.len() >= MAX_TRACKED_KEYS * 2 / SHARDS {
+            forget_infrequent_keys(&mut keys, MAX_TRACKED_KEYS / SHARDS);
+        }
+        keys.entry(key.to_vec()).or_default().record(missed);
+    }
+
+    /// Record a lookup of `keys` in the reader, of which the keys in `misses` missed. If
+    /// `consistency_miss` is true the reader hadn't caught up with the timestamp of the lookup, so
+    /// every key is recorded as a miss.
+    pub(crate) fn record_lookup(
+        &self,
+        keys: &[KeyComparison],
+        misses: &[Cow<KeyComparison>],
+        consistency_miss: bool,
+    ) {
+        for key in keys {
+            let missed = consistency_miss || misses.iter().any(|miss| miss.as_ref() == key);
+            if missed {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+            } else {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+            }
+
+            // Only keep per-key statistics for point lookups, since ranges can overlap
+            if let KeyComparison::Equal(key) = key {
+                self.record_key(key.as_slice(), missed);
+            }
+        }
+    }
+
+    /// Record that `keys` keys totalling `bytes` bytes were evicted from the reader to free memory
+    pub(crate) fn record_eviction(&self, keys: u64, bytes: u64) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+        self.evicted_keys.fetch_add(keys, Ordering::Relaxed);
+        self.evicted_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Record that `key` was evicted from the reader
+    pub(crate) fn record_key_eviction(&self, key: &KeyComparison) {
+        self.evicted_keys.fetch_add(1, Ordering::Relaxed);
+        if let KeyComparison::Equal(key) = key {
+            let key = key.as_slice();
+            if let Some(counters) = Self::read(self.shard(key)).get(key) {
+                counters.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the statistics of the reader, with at most `max_keys` of the most frequently read
+    /// keys
+    pub(crate) fn snapshot(&self, max_keys: usize) -> CacheStats {
+        let mut keys = Vec::new();
+        if max_keys > 0 {
+            for shard in &self.keys {
+                keys.extend(Self::read(shard).iter().map(|(key, counters)| KeyStats {
+                    key: key.clone(),
+                    hits: counters.hits.load(Ordering::Relaxed),
+                    misses: counters.misses.load(Ordering::Relaxed),
+                    evictions: counters.evictions.load(Ordering::Relaxed),
+                }));
+            }
+            keys.sort_by(|a, b| (b.hits + b.misses).cmp(&(a.hits + a.misses)));
+            keys.truncate(max_keys);
+        }
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            evicted_keys: self.evicted_keys.load(Ordering::Relaxed),
+            evicted_bytes: self.evicted_bytes.load(Ordering::Relaxed),
+            keys,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vec1::vec1;
+
+    use super::*;
+
+    #[test]
+    fn records_hits_misses_and_evictions() {
+        let stats = ReaderStats::default();
+        let one = KeyComparison::Equal(vec1![DfValue::from(1)]);
+        let two = KeyComparison::Equal(vec1![DfValue::from(2)]);
+
+        stats.record_lookup(&[one.clone(), two.clone()], &[Cow::Borrowed(&two)], false);
+        stats.record_lookup(&[one.clone()], &[], false);
+        stats.record_key_eviction(&one);
+        stats.record_eviction(3, 100);
+
+        let snapshot = stats.snapshot(1);
+        assert_eq!(snapshot.hits, 2);
+        assert_eq!(snapshot.misses, 1);
+        assert_eq!(snapshot.evictions, 1);
+        assert_eq!(snapshot.evicted_keys, 4);
+        assert_eq!(snapshot.evicted_bytes, 100);
+        assert_eq!(
+            snapshot.keys,
+            vec![KeyStats {
+                key: vec![DfValue::from(1)],
+                hits: 2,
+                misses: 0,
+                evictions: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn forgets_infrequent_keys() {
+        let stats = ReaderStats::default();
+        let frequent = KeyComparison::Equal(vec1![DfValue::from(-1)]);
+        stats.record_lookup(&[frequent.clone(), frequent.clone()], &[], false);
+        for i in 0..(MAX_TRACKED_KEYS * 2 + 1) {
+            stats.record_lookup(
+                &[KeyComparison::Equal(vec1![DfValue::from(i as i64)])],
+                &[],
+                false,
+            );
+        }
+
+        let snapshot = stats.snapshot(usize::MAX);
+        assert!(snapshot.keys.len() <= MAX_TRACKED_KEYS * 2);
+        assert_eq!(snapshot.keys[0].key, vec![DfValue::from(-1)]);
+    }
+
+    #[test]
+    fn consistency_miss_counts_as_miss() {
+        let stats = ReaderStats::default();
+        stats.record_lookup(&[KeyComparison::Equal(vec1![DfValue::from(1)])], &[], true);
+
+        let snapshot = stats.snapshot(1);
+        assert_eq!(snapshot.hits, 0);
+        assert_eq!(snapshot.misses, 1);
+        assert_eq!(snapshot.keys[0].misses, 1);
+    }
+
+    #[test]
+    fn forgetting_keeps_tied_keys_up_to_limit() {
+        let mut keys = (0..10)
+            .map(|i| {
+                let counters = KeyCounters::default();
+                // Keys 0, 1 and 2 were read twice, and the rest once
+                counters
+                    .hits
+                    .store(if i < 3 { 2 } else { 1 }, Ordering::Relaxed);
+                (vec![DfValue::from(i)], counters)
+            })
+            .collect::<HashMap<_, _>>();
+        forget_infrequent_keys(&mut keys, 5);
+
+        assert_eq!(keys.len(), 5);
+        for i in 0..3 {
+            assert!(keys.contains_key(&vec![DfValue::from(i)]));
+        }
+    }
+}
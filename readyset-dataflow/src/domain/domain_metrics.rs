@@ -11,6 +11,8 @@ use metrics::{
     register_counter, register_gauge, register_histogram, Counter, Gauge, Histogram, Label,
     SharedString,
 };
+use nom_sql::Relation;
+use readyset_client::debug::stats::CacheStats;
 use readyset_client::internal::ReplicaAddress;
 use readyset_client::metrics::recorded;
 use strum::{EnumCount, IntoEnumIterator};
@@ -48,6 +50,8 @@ pub(super) struct DomainMetrics {
     chuncked_replay_time: NodeMap<(Counter, Histogram)>,
     base_table_lookups: NodeMap<Counter>,
     node_state_size: NodeMap<Gauge>,
+    /// The hit, miss and evicted key counters of each reader node
+    reader_stats: NodeMap<(Counter, Counter, Counter)>,
 }
 
 impl DomainMetrics {
@@ -97,6 +101,7 @@ impl DomainMetrics {
             reader_replay_request_time: Default::default(),
             base_table_lookups: Default::default(),
             node_state_size: Default::default(),
+            reader_stats: Default::default(),
             shard,
             index,
         }
@@ -354,4 +359,27 @@ impl DomainMetrics {
             self.node_state_size.insert(node, gauge);
         }
     }
+
+    pub(super) fn set_reader_stats(
+        &mut self,
+        node: LocalNodeIndex,
+        name: &Relation,
+        stats: &CacheStats,
+    ) {
+        if !self.reader_stats.contains_key(node) {
+            let cache_name = name.display_unquoted().to_string();
+            let counters = (
+                register_counter!(recorded::READER_KEY_HITS, "cache_name" => cache_name.clone()),
+                register_counter!(recorded::READER_KEY_MISSES, "cache_name" => cache_name.clone()),
+                register_counter!(recorded::READER_EVICTED_KEYS, "cache_name" => cache_name),
+            );
+            self.reader_stats.insert(node, counters);
+        }
+
+        if let Some((hits, misses, evicted_keys)) = self.reader_stats.get(node) {
+            hits.absolute(stats.hits);
+            misses.absolute(stats.misses);
+            evicted_keys.absolute(stats.evicted_keys);
+        }
+    }
 }
@@ -6,13 +6,15 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::fmt::Debug;
 use std::ops::Bound;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::{cell, cmp, mem, process, time};
 
 use ahash::RandomState;
 use dataflow_state::{
-    EvictBytesResult, MaterializedNodeState, PointKey, RangeKey, RangeLookupResult,
+    EvictBytesResult, MaterializedNodeState, PersistentStateHandle, PointKey, RangeKey,
+    RangeLookupResult, SpillStore,
 };
 use failpoint_macros::failpoint;
 use futures_util::future::FutureExt;
@@ -22,7 +24,7 @@ pub use internal::{DomainIndex, ReplicaAddress};
 use merging_interval_tree::IntervalTreeSet;
 use petgraph::graph::NodeIndex;
 use readyset_client::internal::Index;
-use readyset_client::replication::ReplicationOffsetState;
+use readyset_client::replication::{ReplicationOffsetState, TableChecksum};
 use readyset_client::{channel, internal, KeyComparison, KeyCount, ReaderAddress};
 use readyset_errors::{internal, internal_err, ReadySetError, ReadySetResult};
 use readyset_util::futures::abort_on_panic;
@@ -30,14 +32,14 @@ use readyset_util::redacted::Sensitive;
 use readyset_util::Indices;
 use serde::{Deserialize, Serialize};
 use timekeeper::{RealTime, SimpleTracker, ThreadTime, Timer, TimerSet};
-use tokio::sync::mpsc::Sender;
+use tokio::sync::mpsc::{Sender, UnboundedSender};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::{debug, error, trace, warn, Instrument};
 use vec1::Vec1;
 
 pub(crate) use self::replay_paths::ReplayPath;
 use self::replay_paths::{Destination, ReplayPathSpec, ReplayPaths, Target};
-use crate::node::special::EgressTx;
+use crate::node::special::{Base, EgressTx};
 use crate::node::{NodeProcessingResult, ProcessEnv};
 use crate::payload::{
     MaterializedState, PrepareStateKind, PrettyReplayPath, ReplayPieceContext, SourceSelection,
@@ -62,6 +64,27 @@ pub struct Config {
 
     #[serde(default)]
     pub eviction_kind: crate::EvictionKind,
+
+    /// If set, partially materialized readers with hash indexes write the rows of keys evicted to
+    /// free memory to an on-disk store in this directory, and fill those keys back in from there
+    /// when they're next read instead of upquerying for them.
+    #[serde(default)]
+    pub reader_spill_dir: Option<PathBuf>,
+
+    /// The maximum number of bytes of rows each reader can spill to disk, or `None` for unlimited.
+    #[serde(default)]
+    pub reader_spill_max_bytes: Option<u64>,
+
+    /// The maximum size, in bytes, of each partially materialized reader. Readers over their
+    /// quota are evicted from back down to it whenever state sizes are updated.
+    #[serde(default)]
+    pub cache_memory_quota: Option<usize>,
+
+    /// The budget, in bytes, for the total size of materialized state and readers. This isn't
+    /// enforced by domains, but the controller refuses to create caches that it expects would
+    /// exceed it.
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
 }
 
 const BATCH_SIZE: usize = 256;
@@ -419,6 +442,7 @@ impl DomainBuilder {
             timed_purges: Default::default(),
 
             delayed_for_self: Default::default(),
+            remote_self_tx: None,
 
             state_size,
             total_time: Timer::new(),
@@ -436,6 +460,9 @@ impl DomainBuilder {
             metrics: domain_metrics::DomainMetrics::new(address),
 
             eviction_kind: self.config.eviction_kind,
+            reader_spill_dir: self.config.reader_spill_dir,
+            reader_spill_max_bytes: self.config.reader_spill_max_bytes,
+            cache_memory_quota: self.config.cache_memory_quota,
             remapped_keys: Default::default(),
 
             init_state_tx,
@@ -560,6 +587,43 @@ impl RemappedKeys {
     }
 }
 
+/// The rows of a base table to compute the checksum of
+enum ChecksumRows {
+    /// Read from the table's persistent state
+    Persistent(PersistentStateHandle),
+    /// Copied out of the table's state up front, for states that can't be read from another thread
+    Cloned(Vec<Vec<DfValue>>),
+}
+
+/// Computes a checksum of the rows of a base table, as returned by [`Domain::table_checksum_task`]
+pub struct TableChecksumTask {
+    base: Base,
+    dropped: Vec<usize>,
+    rows: ChecksumRows,
+}
+
+impl TableChecksumTask {
+    /// Compute the checksum of the rows of the table as they would be read by a replay, skipping
+    /// the values of any columns that have been dropped
+    pub fn run(self) -> TableChecksum {
+        let mut checksum = TableChecksum::default();
+        let mut add_row = |mut row: Vec<DfValue>| {
+            self.base.fix(&mut row);
+            checksum.add_row(
+                row.iter()
+                    .enumerate()
+                    .filter(|(i, _)| !self.dropped.contains(i))
+                    .map(|(_, value)| value),
+            );
+        };
+        match self.rows {
+            ChecksumRows::Persistent(handle) => handle.for_each_record(add_row),
+            ChecksumRows::Cloned(rows) => rows.into_iter().for_each(add_row),
+        }
+        checksum
+    }
+}
+
 pub struct Domain {
     index: DomainIndex,
     shard: Option<usize>,
@@ -617,6 +681,10 @@ pub struct Domain {
     channel_coordinator: Arc<ChannelCoordinator>,
 
     delayed_for_self: VecDeque<Box<Packet>>,
+    /// Sends packets to this domain from other threads, such as the rows of keys read back from
+    /// the spill stores of readers. Created the first time it's needed by
+    /// [`Domain::remote_self_sender`].
+    remote_self_tx: Option<UnboundedSender<Box<Packet>>>,
 
     state_size: Arc<AtomicUsize>,
     total_time: Timer<SimpleTracker, RealTime>,
@@ -640,6 +708,12 @@ pub struct Domain {
 
     metrics: domain_metrics::DomainMetrics,
     eviction_kind: crate::EvictionKind,
+    /// See [`Config::reader_spill_dir`]
+    reader_spill_dir: Option<PathBuf>,
+    /// See [`Config::reader_spill_max_bytes`]
+    reader_spill_max_bytes: Option<u64>,
+    /// See [`Config::cache_memory_quota`]
+    cache_memory_quota: Option<usize>,
 
     /// This channel is used to notify the replica that a base node has its persistent state
     /// initialized.
@@ -709,6 +783,38 @@ impl Domain {
             .collect()
     }
 
+    /// Prepare to compute a checksum of the rows of the base table `node`, per
+    /// [`DomainRequest::RequestTableChecksum`]. The returned task only reads the table's rows once
+    /// it's [run](TableChecksumTask::run), which can be done on another thread.
+    pub fn table_checksum_task(&self, node: LocalNodeIndex) -> ReadySetResult<TableChecksumTask> {
+        let n = self
+            .nodes
+            .get(node)
+            .ok_or_else(|| ReadySetError::NoSuchNode(node.id()))?
+            .borrow();
+        let base = n
+            .get_base()
+            .ok_or_else(|| internal_err!("asked for the checksum of non-base node"))?;
+        let rows = match self
+            .state
+            .get(node)
+            .ok_or_else(|| internal_err!("base node {node} has no state"))?
+        {
+            MaterializedNodeState::Persistent(state) => {
+                ChecksumRows::Persistent(state.read_handle())
+            }
+            MaterializedNodeState::PersistentReadHandle(handle) => {
+                ChecksumRows::Persistent(handle.clone())
+            }
+            state => ChecksumRows::Cloned(state.cloned_records()),
+        };
+        Ok(TableChecksumTask {
+            base: base.clone(),
+            dropped: base.get_dropped().keys().collect(),
+            rows,
+        })
+    }
+
     /// Initiate a replay for a miss represented by the given keys and column indices in the given
     /// node.
     ///
@@ -1626,7 +1732,30 @@ impl Domain {
                         #[allow(clippy::unwrap_used)] // checked it was a reader above
                         let r = n.as_mut_reader().unwrap();
 
-                        let (r_part, w_part) = backlog::new_partial(
+                        let spill = match &self.reader_spill_dir {
+                            Some(dir) if index.index_type == IndexType::HashMap => {
+                                let spill_name = format!(
+                                    "{}-{}-",
+                                    name.display_unquoted().to_string().replace(['.', '/'], "_"),
+                                    self.shard.unwrap_or(0)
+                                );
+                                match SpillStore::new(dir, &spill_name, self.reader_spill_max_bytes)
+                                {
+                                    Ok(spill) => Some(spill),
+                                    Err(error) => {
+                                        warn!(
+                                            %error,
+                                            name = %name.display_unquoted(),
+                                            "Could not create spill store for reader"
+                                        );
+                                        None
+                                    }
+                                }
+                            }
+                            _ => None,
+                        };
+
+                        let (r_part, mut w_part) = backlog::new_partial(
                             num_columns,
                             index,
                             move |misses: &mut dyn Iterator<Item = KeyComparison>| {
@@ -1661,6 +1790,9 @@ impl Domain {
                             self.eviction_kind,
                             r.reader_processing().clone(),
                         );
+                        if let Some(spill) = spill {
+                            w_part.set_spill_store(spill);
+                        }
 
                         let shard = *self.shard.as_ref().unwrap_or(&0);
                         // TODO(ENG-838): Don't recreate every single node on leader failure.
@@ -2179,6 +2311,19 @@ impl Domain {
             DomainRequest::RequestSnapshottingTables => {
                 Ok(Some(bincode::serialize(&self.snapshotting_base_nodes())?))
             }
+            DomainRequest::RequestTableChecksum { node } => Ok(Some(bincode::serialize(
+                &self.table_checksum_task(node)?.run(),
+            )?)),
+            DomainRequest::TraceReader { node, duration } => {
+                self.nodes
+                    .get(node)
+                    .ok_or_else(|| ReadySetError::NoSuchNode(node.id()))?
+                    .borrow_mut()
+                    .as_mut_reader()
+                    .ok_or_else(|| internal_err!("asked to trace non-reader node"))?
+                    .trace_until(time::Instant::now() + duration);
+                Ok(None)
+            }
             DomainRequest::RequestNodeSizes => {
                 let mut res = Vec::new();
                 for (local_index, node_ref) in self.nodes.iter() {
@@ -2198,6 +2343,17 @@ impl Domain {
                 }
                 Ok(Some(bincode::serialize(&res)?))
             }
+            DomainRequest::RequestReaderStats { max_keys } => {
+                let res = self
+                    .nodes
+                    .iter()
+                    .filter_map(|(local_index, node_ref)| {
+                        let wh = self.reader_write_handles.get(local_index)?;
+                        Some((node_ref.borrow().global_addr(), wh.stats(max_keys)))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Some(bincode::serialize(&res)?))
+            }
             DomainRequest::Packet(pkt) => {
                 self.handle_packet(Box::new(pkt), executor)?;
                 Ok(None)
@@ -2277,6 +2433,16 @@ impl Domain {
                 let start = time::Instant::now();
                 self.total_replay_time.start();
 
+                let spill_tx = if self
+                    .reader_write_handles
+                    .get(node)
+                    .map_or(false, |w| w.has_spilled_keys())
+                {
+                    Some(self.remote_self_sender()?)
+                } else {
+                    None
+                };
+
                 let mut n = self
                     .nodes
                     .get(node)
@@ -2302,6 +2468,40 @@ impl Domain {
                 // ensure that all writes have been applied
                 w.swap();
 
+                // read back any keys that were spilled to disk when they were evicted on the spill
+                // store's own thread, rather than upquerying for them. They're filled in once
+                // they've been read, when we get the `FillFromSpill` packet sent below.
+                if let Some(spill_tx) = spill_tx {
+                    let mut unspilled = Vec::with_capacity(keys.len());
+                    for key in keys.drain(..) {
+                        let spill_tx = spill_tx.clone();
+                        let reply_key = key.clone();
+                        let reply_cols = cols.clone();
+                        let reading = w.read_spilled(
+                            &key,
+                            Box::new(move |generation, rows| {
+                                let rows = rows
+                                    .map_err(
+                                        |error| warn!(%error, "Could not read back spilled key"),
+                                    )
+                                    .ok();
+                                // If the domain has gone away, there's nothing left to fill
+                                let _ = spill_tx.send(Box::new(Packet::FillFromSpill {
+                                    node,
+                                    cols: reply_cols,
+                                    key: reply_key,
+                                    generation,
+                                    rows,
+                                }));
+                            }),
+                        )?;
+                        if !reading {
+                            unspilled.push(key);
+                        }
+                    }
+                    keys = unspilled;
+                }
+
                 // don't request keys that have been filled since the request was sent
                 let mut keys = keys
                     .drain(..)
@@ -2337,6 +2537,45 @@ impl Domain {
                 self.total_replay_time.stop();
                 self.metrics.rec_reader_replay_time(node, start.elapsed());
             }
+            Packet::FillFromSpill {
+                node,
+                cols,
+                key,
+                generation,
+                rows,
+            } => {
+                if self
+                    .nodes
+                    .get(node)
+                    .map_or(true, |n| n.borrow().is_dropped())
+                {
+                    return Ok(());
+                }
+                let Some(w) = self.reader_write_handles.get_mut(node) else {
+                    return Ok(());
+                };
+
+                let filled = match rows {
+                    Some(rows) => w.fill_from_spill(&key, generation, rows)?,
+                    None => {
+                        w.discard_spilled_key(&key)?;
+                        false
+                    }
+                };
+                if filled {
+                    w.swap();
+                    w.notify_readers()?;
+                } else {
+                    // The key was written to while we were reading it, or couldn't be read, so
+                    // it has to be upqueried like any other miss
+                    self.delayed_for_self
+                        .push_back(Box::new(Packet::RequestReaderReplay {
+                            node,
+                            cols,
+                            keys: vec![key],
+                        }));
+                }
+            }
             Packet::RequestPartialReplay {
                 tag,
                 keys,
@@ -2373,6 +2612,32 @@ impl Domain {
         Ok(())
     }
 
+    /// Returns a sender for packets to this domain that can be used from other threads and tasks,
+    /// creating it if this is the first time it's needed
+    fn remote_self_sender(&mut self) -> ReadySetResult<UnboundedSender<Box<Packet>>> {
+        if let Some(tx) = &self.remote_self_tx {
+            return Ok(tx.clone());
+        }
+
+        let sender = self
+            .channel_coordinator
+            .builder_for(&self.address())?
+            .build_async()?;
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(
+            UnboundedReceiverStream::new(rx)
+                .map(Ok)
+                .forward(sender)
+                .map(|r| {
+                    if let Err(error) = r {
+                        error!(%error, "Domain went away while sending packets to itself");
+                    }
+                }),
+        );
+        self.remote_self_tx = Some(tx.clone());
+        Ok(tx)
+    }
+
     /// Timed purges happen when [`FrontierStrategy`] is not None, in which case all keys
     /// are purged from the node after a given amount of time
     fn handle_timed_purges(&mut self) -> ReadySetResult<()> {
@@ -2392,7 +2657,7 @@ impl Domain {
                 );
                 if let Some(wh) = self.reader_write_handles.get_mut(tp.view) {
                     for key in tp.keys {
-                        wh.mark_hole(&key)?;
+                        wh.evict_key(&key)?;
                     }
                     swap.insert(tp.view);
                 }
@@ -4018,6 +4283,8 @@ impl Domain {
                     // We are a reader, which has its own kind of state
                     let mut size = 0;
                     if let Some(wh) = self.reader_write_handles.get(local_index) {
+                        self.metrics
+                            .set_reader_stats(local_index, n.name(), &wh.stats(0));
                         if wh.is_partial() {
                             size = wh.deep_size_of();
                             reader_size += size;
@@ -4056,6 +4323,38 @@ impl Domain {
         // no response sent, as worker will read the atomic
     }
 
+    /// Evict from each partially materialized reader whose size exceeds
+    /// [`Config::cache_memory_quota`], until it's back within the quota
+    pub fn enforce_cache_memory_quota(&mut self, executor: &mut dyn Executor) {
+        let Some(quota) = self.cache_memory_quota else {
+            return;
+        };
+
+        let over_quota = self
+            .reader_write_handles
+            .iter()
+            .filter(|(_, wh)| wh.is_partial())
+            .filter_map(|(node, wh)| {
+                let size = wh.deep_size_of() as usize;
+                (size > quota).then_some((node, size - quota))
+            })
+            .collect::<Vec<_>>();
+        for (node, num_bytes) in over_quota {
+            debug!(%node, num_bytes, quota, "Reader exceeds cache memory quota, evicting");
+            // A reader we fail to evict from just stays over its quota until the next time we
+            // check, so log the error rather than failing the whole domain over it
+            if let Err(error) = self.handle_packet(
+                Box::new(Packet::Evict {
+                    node: Some(node),
+                    num_bytes,
+                }),
+                executor,
+            ) {
+                warn!(%node, %error, "Failed to evict from reader over cache memory quota");
+            }
+        }
+    }
+
     pub fn estimated_base_tables_size(&self) -> u64 {
         self.state
             .values()
@@ -63,7 +63,7 @@ pub use dataflow_state::{
     DurabilityMode, MaterializedNodeState, PersistenceParameters, PersistentState,
 };
 
-pub use crate::domain::{Domain, DomainBuilder, DomainIndex};
+pub use crate::domain::{Domain, DomainBuilder, DomainIndex, TableChecksumTask};
 pub use crate::node_map::NodeMap;
 pub use crate::payload::{DomainRequest, Packet, PacketDiscriminants};
 pub use crate::processing::LookupIndex;
@@ -2,7 +2,7 @@ use std::collections::{HashMap, HashSet};
 
 use nom_sql::{ColumnSpecification, Relation, SqlIdentifier};
 use readyset_client::consistency::Timestamp;
-use readyset_data::{DfType, Dialect};
+use readyset_data::{Collation, DfType, Dialect};
 use serde::{Deserialize, Serialize};
 
 use crate::ops;
@@ -57,11 +57,14 @@ impl Column {
     where
         F: Fn(Relation) -> Option<DfType>,
     {
-        Ok(Self::new(
-            spec.column.name,
-            DfType::from_sql_type(&spec.sql_type, dialect, resolve_type)?,
-            spec.column.table,
-        ))
+        let mut ty = DfType::from_sql_type(&spec.sql_type, dialect, resolve_type)?;
+        if let Some(collation) = spec
+            .collation()
+            .and_then(|name| Collation::from_upstream_name(name, dialect))
+        {
+            ty = ty.with_collation(collation);
+        }
+        Ok(Self::new(spec.column.name, ty, spec.column.table))
     }
 
     /// Column name
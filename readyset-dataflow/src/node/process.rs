@@ -10,6 +10,7 @@ use readyset_errors::ReadySetResult;
 use tracing::{debug_span, trace};
 
 use crate::node::special::base::{BaseWrite, SetSnapshotMode};
+use crate::node::special::Base;
 use crate::node::NodeType;
 use crate::prelude::*;
 use crate::processing::{MissLookupKey, MissReplayKey};
@@ -138,9 +139,12 @@ impl Node {
                 match m.take().map(|p| *p) {
                     Some(Packet::Input { inner, .. }) => {
                         let PacketData { dst, data, trace } = inner;
-                        let ops = data
+                        let ops: Vec<_> = data
                             .try_into()
                             .expect("Payload of Input packet was not of Input type");
+                        if let Some(state) = env.state.get_mut(addr) {
+                            Base::add_expiration_indices(&ops, state);
+                        }
 
                         let snapshot_mode = env
                             .state
@@ -213,7 +217,7 @@ impl Node {
             }
             NodeType::Reader(ref mut r) => {
                 if let Some(state) = env.reader_write_handles.get_mut(addr) {
-                    r.process(m, swap_reader, state);
+                    r.process(&self.name, m, swap_reader, state);
                 }
             }
             NodeType::Egress(None) => internal!("tried to process through taken egress"),
@@ -492,7 +496,7 @@ impl Node {
                         "Evicting keys from reader"
                     );
                     for k in keys {
-                        state.mark_hole(k)?;
+                        state.evict_key(k)?;
                     }
                     state.swap();
                     state.notify_readers_of_eviction()?;
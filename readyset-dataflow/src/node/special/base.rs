@@ -2,8 +2,9 @@ use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::ops::Bound;
 
-use dataflow_state::{MaterializedNodeState, PointKey, SnapshotMode};
+use dataflow_state::{MaterializedNodeState, PointKey, RangeKey, RangeLookupResult, SnapshotMode};
 use itertools::Itertools;
 use nom_sql::Relation;
 use readyset_client::replication::ReplicationOffset;
@@ -203,6 +204,14 @@ impl Base {
                     records.clear();
                     records.extend(db.cloned_records().into_iter().map(|r| Record::Negative(r)))
                 }
+                TableOperation::DeleteExpired { column, cutoff } => {
+                    records.retain(|r| !(r.is_positive() && is_expired(r, column, &cutoff)));
+                    records.extend(
+                        expired_rows(db, column, &cutoff)
+                            .into_iter()
+                            .map(Record::Negative),
+                    )
+                }
                 TableOperation::DeleteByKey { .. }
                 | TableOperation::InsertOrUpdate { .. }
                 | TableOperation::Update { .. } => {
@@ -218,6 +227,21 @@ impl Base {
         })
     }
 
+    /// Make sure `state` has a BTree index on the column of each [`TableOperation::DeleteExpired`]
+    /// in `ops`, so the expired rows can be found without scanning the whole table. Building the
+    /// index scans the table once, the first time rows are expired from it; adding an index that
+    /// already exists does nothing.
+    pub(in crate::node) fn add_expiration_indices(
+        ops: &[TableOperation],
+        state: &mut MaterializedNodeState,
+    ) {
+        for op in ops {
+            if let TableOperation::DeleteExpired { column, .. } = op {
+                state.add_key(Index::btree_map(vec![*column]), None);
+            }
+        }
+    }
+
     /// Compute the deltas required to apply the list of the provided `TableOperation` to the base
     /// table
     pub(in crate::node) fn process_ops(
@@ -266,10 +290,17 @@ impl Base {
         let mut replication_offset: Option<ReplicationOffset> = None;
         let mut set_snapshot_mode: Option<SetSnapshotMode> = None;
 
+        let mut expirations = vec![];
+
         while let Some(op) = ops.peek() {
-            // Process all of the `SetReplicationOffset` and `SetSnapshotMode` ops, then proceed to
-            // the keyed operations as usual
+            // Process all of the `SetReplicationOffset`, `SetSnapshotMode` and `DeleteExpired` ops,
+            // then proceed to the keyed operations as usual
             match op {
+                TableOperation::DeleteExpired { column, cutoff } => {
+                    expirations.push((*column, cutoff.clone()));
+                    ops.next();
+                    n_ops -= 1;
+                }
                 TableOperation::SetReplicationOffset(offset) => {
                     offset.try_max_into(&mut replication_offset)?;
                     ops.next();
@@ -314,6 +345,21 @@ impl Base {
         let mut touched_keys: HashMap<Vec<DfValue>, TouchedKey> = HashMap::new();
         let mut failed_log = FailedOpLogger::new(name);
 
+        if !truncated {
+            for (column, cutoff) in &expirations {
+                for row in expired_rows(db, *column, cutoff) {
+                    let key = row
+                        .cloned_indices(key_cols.to_vec())
+                        .map_err(|_| ReadySetError::InvalidRecordLength)?;
+                    // A row expired by more than one of the operations must only be deleted once,
+                    // and later operations in this batch on the same key must see it as deleted
+                    if touched_keys.insert(key, TouchedKey::Deleted).is_none() {
+                        results.push(Record::Negative(row));
+                    }
+                }
+            }
+        }
+
         for (key, ops) in &ops {
             // It is not enough to check the persisted value for the key, as it may have been
             // changed in previous iteration, therefore we have to check it was not
@@ -392,7 +438,8 @@ impl Base {
                     TableOperation::SetSnapshotMode(_)
                     | TableOperation::SetReplicationOffset(_)
                     | TableOperation::InsertOrUpdate { .. }
-                    | TableOperation::Truncate => {
+                    | TableOperation::Truncate
+                    | TableOperation::DeleteExpired { .. } => {
                         // This is unreachable, because all of those cases are handled above
                     }
                 }
@@ -473,7 +520,39 @@ fn key_val(i: usize, col: usize, r: &TableOperation) -> Option<&DfValue> {
         TableOperation::InsertOrUpdate { ref row, .. } => Some(&row[col]),
         TableOperation::SetReplicationOffset(_)
         | TableOperation::SetSnapshotMode(_)
-        | TableOperation::Truncate => None,
+        | TableOperation::Truncate
+        | TableOperation::DeleteExpired { .. } => None,
+    }
+}
+
+/// Returns true if the value of `row` in `column` is non-NULL and less than `cutoff`, meaning the
+/// row should be deleted by a [`TableOperation::DeleteExpired`]
+fn is_expired(row: &[DfValue], column: usize, cutoff: &DfValue) -> bool {
+    row.get(column)
+        .map_or(false, |value| !value.is_none() && value < cutoff)
+}
+
+/// Returns the rows of `db` to delete for a [`TableOperation::DeleteExpired`], looking them up in
+/// the BTree index on `column` so that only the expired rows are read, rather than the whole table.
+/// The index is added by [`Base::add_expiration_indices`].
+fn expired_rows(db: &MaterializedNodeState, column: usize, cutoff: &DfValue) -> Vec<Vec<DfValue>> {
+    let range = RangeKey::Single((Bound::Unbounded, Bound::Excluded(cutoff.clone())));
+    match db.lookup_range(&[column], &range) {
+        RangeLookupResult::Some(rows) => rows
+            .into_iter()
+            .filter(|row| is_expired(row, column, cutoff))
+            .map(|row| row.into_owned())
+            .collect(),
+        RangeLookupResult::Missing(_) => {
+            debug!(
+                column,
+                "Expired rows missing from index, scanning the table"
+            );
+            db.cloned_records()
+                .into_iter()
+                .filter(|row| is_expired(row, column, cutoff))
+                .collect()
+        }
     }
 }
 
@@ -541,6 +620,7 @@ fn apply_table_op_coercions(
         }
         TableOperation::DeleteByKey { key } => coerce_key(key),
         TableOperation::Truncate
+        | TableOperation::DeleteExpired { .. }
         | TableOperation::SetReplicationOffset(_)
         | TableOperation::SetSnapshotMode(_) => Ok(()),
     }
@@ -1126,5 +1206,70 @@ mod tests {
                 }
             );
         }
+
+        #[test]
+        fn delete_expired() {
+            let mut b = Base::new().with_primary_key([0]);
+            let ni = LocalNodeIndex::make(0u32);
+            let mut state = MaterializedNodeState::Persistent(
+                PersistentState::new(
+                    "delete_expired".into(),
+                    Vec::<Box<[usize]>>::new(),
+                    &PersistenceParameters::default(),
+                )
+                .unwrap(),
+            );
+
+            state.add_key(Index::hash_map(vec![0]), None);
+
+            let mut recs = vec![
+                Record::Positive(vec![1.into(), 10.into()]),
+                Record::Positive(vec![2.into(), 20.into()]),
+                Record::Positive(vec![3.into(), DfValue::None]),
+                Record::Positive(vec![4.into(), 40.into()]),
+            ]
+            .into();
+            state.process_records(&mut recs, None, None).unwrap();
+
+            let ops = vec![
+                TableOperation::Insert(vec![1.into(), 50.into()]),
+                TableOperation::DeleteExpired {
+                    column: 1,
+                    cutoff: 30.into(),
+                },
+            ];
+            Base::add_expiration_indices(&ops, &mut state);
+
+            let mut state_map = NodeMap::new();
+            state_map.insert(ni, state);
+
+            let table = Relation {
+                name: "test".into(),
+                schema: None,
+            };
+            let res = b
+                .process_ops(
+                    ni,
+                    &[],
+                    ops,
+                    &state_map,
+                    SnapshotMode::SnapshotModeDisabled,
+                    table,
+                )
+                .unwrap();
+            assert_eq!(
+                res,
+                BaseWrite {
+                    records: vec![
+                        Record::Negative(vec![1.into(), 10.into()]),
+                        Record::Negative(vec![2.into(), 20.into()]),
+                        Record::Positive(vec![1.into(), 50.into()]),
+                    ]
+                    .into(),
+                    replication_offset: None,
+                    set_snapshot_mode: None
+                }
+            );
+        }
     }
 }
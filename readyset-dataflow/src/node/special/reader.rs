@@ -1,12 +1,14 @@
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
 use dataflow_expression::ReaderProcessing;
 use failpoint_macros::failpoint;
 use metrics::histogram;
+use nom_sql::Relation;
 use readyset_client::metrics::recorded;
+use readyset_client::query::QUERY_TRACE_TARGET;
 use readyset_client::{KeyColumnIdx, ViewPlaceholder};
 use serde::{Deserialize, Serialize};
-use tracing::{trace, warn};
+use tracing::{debug, trace, warn};
 
 use crate::backlog;
 use crate::prelude::*;
@@ -25,6 +27,11 @@ pub struct Reader {
     ///
     /// The data is stored in this manner instead of in a Hashmap to support ordered iteration.
     placeholder_map: Vec<(ViewPlaceholder, KeyColumnIdx)>,
+
+    /// If the query this reader is for is being traced with `ALTER READYSET TRACE QUERY`, the time
+    /// at which tracing should stop. Tracing is temporary, so this isn't persisted.
+    #[serde(skip)]
+    traced_until: Option<Instant>,
 }
 
 impl Clone for Reader {
@@ -34,6 +41,7 @@ impl Clone for Reader {
             reader_processing: self.reader_processing.clone(),
             index: self.index.clone(),
             placeholder_map: self.placeholder_map.clone(),
+            traced_until: self.traced_until,
         }
     }
 }
@@ -45,6 +53,7 @@ impl Reader {
             reader_processing,
             index: None,
             placeholder_map: Default::default(),
+            traced_until: None,
         }
     }
 
@@ -60,6 +69,25 @@ impl Reader {
             reader_processing: self.reader_processing.clone(),
             index: self.index.clone(),
             placeholder_map: self.placeholder_map.clone(),
+            traced_until: self.traced_until,
+        }
+    }
+
+    /// Log every write and replay to this reader as a structured event until `until`
+    pub fn trace_until(&mut self, until: Instant) {
+        self.traced_until = Some(until);
+    }
+
+    /// Returns true if writes to this reader are currently being traced, clearing the tracing
+    /// deadline once it has elapsed
+    fn is_traced(&mut self) -> bool {
+        match self.traced_until {
+            Some(until) if until > Instant::now() => true,
+            Some(_) => {
+                self.traced_until = None;
+                false
+            }
+            None => false,
         }
     }
 
@@ -119,6 +147,7 @@ impl Reader {
     #[failpoint("reader-handle-packet")]
     pub(in crate::node) fn process(
         &mut self,
+        name: &Relation,
         m: &mut Option<Box<Packet>>,
         swap: bool,
         state: &mut backlog::WriteHandle,
@@ -137,11 +166,15 @@ impl Reader {
                 }
             },
         );
+        let traced = self.is_traced();
+        let received = if traced { m.mut_data().len() } else { 0 };
+
         // make sure we don't fill a partial materialization
         // hole with incomplete (i.e., non-replay) state.
         if m.is_regular() && state.is_partial() {
             let data = m.mut_data();
             trace!(?data, "reader received regular message");
+            state.discard_spilled(data.iter().map(|row| &row[..]));
             data.retain(|row| {
                 match state.contains_record(&row[..]) {
                     Ok(false) => {
@@ -199,7 +232,19 @@ impl Reader {
             });
         }
 
-        state.add(m.take_data());
+        let data = m.take_data();
+        if traced {
+            debug!(
+                target: QUERY_TRACE_TARGET,
+                query = %name.display_unquoted(),
+                replay = !m.is_regular(),
+                received,
+                applied = data.len(),
+                positive = data.iter().filter(|r| r.is_positive()).count(),
+                "traced query reader updated"
+            );
+        }
+        state.add(data);
 
         if swap {
             // TODO: avoid doing the pointer swap if we didn't modify anything (inc. ts)
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use std::fmt::{self, Display};
+use std::time::Duration;
 
 use dataflow_state::MaterializedNodeState;
 use itertools::Itertools;
@@ -351,10 +352,26 @@ pub enum DomainRequest {
     /// Request a list of base table nodes that are currently involved in snapshotting.
     RequestSnapshottingTables,
 
+    /// Request a checksum of the rows of the given base table node, not including any columns
+    /// that have been dropped. The worker computes it off of the domain's thread, via
+    /// [`Domain::table_checksum_task`](crate::Domain::table_checksum_task).
+    RequestTableChecksum { node: LocalNodeIndex },
+
+    /// Log every write and replay to the given reader node for the given duration, for `ALTER
+    /// READYSET TRACE QUERY`
+    TraceReader {
+        node: LocalNodeIndex,
+        duration: Duration,
+    },
+
     /// Request a map of node indexes to approximate key counts and materialized state size in
     /// bytes
     RequestNodeSizes,
 
+    /// Request the statistics about the reads of, and evictions from, each reader node in the
+    /// domain, with at most `max_keys` of the most frequently read keys of each
+    RequestReaderStats { max_keys: usize },
+
     /// Process the packet, as per usual
     Packet(Packet),
 
@@ -441,6 +458,19 @@ pub enum Packet {
         keys: Vec<KeyComparison>,
     },
 
+    /// The rows of a key that a reader spilled to disk when it was evicted, read back from the
+    /// reader's spill store so that the key can be filled without an upquery.
+    FillFromSpill {
+        node: LocalNodeIndex,
+        cols: Vec<usize>,
+        key: KeyComparison,
+        /// The generation of the key in the spill store when the rows were read
+        generation: u64,
+        /// The rows of the key, or `None` if they couldn't be read, in which case the key is
+        /// upqueried instead
+        rows: Option<Vec<Vec<DfValue>>>,
+    },
+
     /// A packet used solely to drive the event loop forward.
     Spin,
 
@@ -607,6 +637,7 @@ impl ToString for Packet {
             Packet::Input { .. } => "Input",
             Packet::Message { .. } => "Message",
             Packet::RequestReaderReplay { .. } => "RequestReaderReplay",
+            Packet::FillFromSpill { .. } => "FillFromSpill",
             Packet::RequestPartialReplay { .. } => "RequestPartialReplay",
             Packet::ReplayPiece { .. } => "ReplayPiece",
             Packet::EvictKeys { .. } => "EvictKeys",
@@ -627,6 +658,9 @@ impl fmt::Debug for Packet {
             Packet::RequestReaderReplay { ref keys, .. } => {
                 write!(f, "Packet::RequestReaderReplay({:?})", keys)
             }
+            Packet::FillFromSpill { ref key, .. } => {
+                write!(f, "Packet::FillFromSpill({:?})", key)
+            }
             Packet::RequestPartialReplay { ref tag, .. } => {
                 write!(f, "Packet::RequestPartialReplay({:?})", tag)
             }
@@ -287,6 +287,11 @@ pub enum ReadySetError {
     #[error("Reader not found")]
     ReaderNotFound,
 
+    /// The rest of the results of a paged read could not be found at the given worker, because
+    /// they were already read or went unread for too long.
+    #[error("Read cursor {0} not found")]
+    ReadCursorNotFound(u64),
+
     /// The request cannot be serviced because the server is shutting down.
     #[error("Server is shutting down")]
     ServerShuttingDown,
@@ -558,6 +563,19 @@ pub enum ReadySetError {
     )]
     ReplicationOffsetLogDifferent(String, String),
 
+    /// A persisted replication offset was tagged with an encoding version we don't know how to
+    /// convert from.
+    #[error(
+        "Unknown replication offset encoding version {version} (current version is {current}); \
+         was the offset written by a newer version of ReadySet?"
+    )]
+    UnknownReplicationOffsetVersion {
+        /// The version the offset was tagged with
+        version: u8,
+        /// The current replication offset encoding version
+        current: u8,
+    },
+
     /// An error that was encountered during snapshot/binlog/wal replication proccess
     #[error("Error during replication: {0}")]
     ReplicationFailed(String),
@@ -619,6 +637,107 @@ pub enum ReadySetError {
     #[error("Change in DDL requires partial resnapshot")]
     ResnapshotNeeded,
 
+    /// The replication offset we need to resume replication from is no longer available on the
+    /// upstream database, for example because the binlog file it refers to has been purged.
+    /// Replication can only continue after a full resnapshot.
+    #[error("Replication offset {offset} has been purged from the upstream database: {message}")]
+    ReplicationOffsetPurged {
+        /// The replication offset we attempted to resume replication from
+        offset: String,
+        /// The error message returned by the upstream database
+        message: String,
+    },
+
+    /// The upstream database's replication position is behind the replication offset we need to
+    /// resume replication from, for example because it was restored from a backup. Resuming
+    /// replication could apply transactions twice, or skip them altogether.
+    #[error(
+        "Upstream replication position {upstream_offset} is behind replication offset {offset}"
+    )]
+    ReplicationOffsetRewound {
+        /// The replication offset we attempted to resume replication from
+        offset: String,
+        /// The current replication position of the upstream database
+        upstream_offset: String,
+    },
+
+    /// The upstream database reported that the data we've replicated from it may be inconsistent
+    /// with its own. Replication can only continue after a full resnapshot.
+    #[error("Replicated data may be inconsistent with the upstream database: {0}")]
+    FullResnapshotNeeded(String),
+
+    /// Replication stopped to switch to a different server to read the MySQL binlog from, as
+    /// chosen from `--upstream-db-url` and `--replication-source-candidates`. Replication
+    /// restarts without a snapshot.
+    #[error("Switching to a different replication source")]
+    ReplicationSourceChanged,
+
+    /// Every replication event in the capture file being replayed has been read
+    #[error("Reached the end of the replication capture")]
+    ReplicationCaptureEnded,
+
+    /// We read an event from the replication log that we couldn't decode, or that we don't know
+    /// how to handle
+    #[error("Unsupported replication event: {0}")]
+    ReplicationUnsupportedEvent(String),
+
+    /// A value in a replicated row couldn't be converted to a ReadySet value
+    #[error(
+        "Could not convert value of column {column} in {}: {}",
+        table.display_unquoted(),
+        Sensitive(message)
+    )]
+    ReplicationConversionError {
+        /// The table the row belongs to
+        table: Relation,
+        /// The column the value belongs to
+        column: String,
+        /// A description of why the conversion failed
+        message: String,
+    },
+
+    /// The rows of a table we replicate can't be brought up to date from the replication log, so
+    /// the table has to be snapshotted again
+    #[error("{} needs to be resnapshotted: {reason}", table.display_unquoted())]
+    TableResnapshotNeeded {
+        /// The table to resnapshot, qualified with its schema
+        table: Relation,
+        /// Why the table can't be replicated from the replication log
+        reason: String,
+    },
+
+    /// A rows event in the MySQL binlog referred to a table that we haven't seen the
+    /// `TABLE_MAP_EVENT` for
+    #[error("No TABLE_MAP_EVENT found for table id {table_id} in {event}")]
+    ReplicationTableMapNotFound {
+        /// The type of the rows event
+        event: String,
+        /// The id of the table the rows event referred to
+        table_id: u64,
+    },
+
+    /// The connection to the upstream database was lost during replication
+    #[error("Lost connection to the upstream database during replication: {0}")]
+    ReplicationConnectionLost(String),
+
+    /// Replication is paused at a DDL statement changing a replicated table which couldn't be
+    /// parsed, as configured by `--ddl-conflict-policy pause`
+    #[error(
+        "Replication is paused at a DDL statement for {} that could not be parsed ({error}). Set \
+         --ddl-conflict-policy to skip, ignore or resnapshot to continue replicating.",
+        table.display_unquoted()
+    )]
+    ReplicationPausedAtDdl {
+        /// The table changed by the statement, qualified with its schema
+        table: Relation,
+        /// The error we got parsing the statement
+        error: String,
+    },
+
+    /// The user we replicate as is missing a permission required for replication
+    #[error("Missing permission required for replication: {0}")]
+    ReplicationPermissionMissing(String),
+
     #[error("Root certificate must be a valid DER or PEM encoded certificate")]
     InvalidRootCertificate,
 
@@ -677,6 +796,21 @@ pub enum ReadySetError {
     /// Error interacting with native_tls
     #[error("TLS error: {0}")]
     NativeTlsError(String),
+
+    /// A cache could not be created because the memory used by materialized state and readers,
+    /// plus the memory the new cache is expected to use, would exceed the configured budget.
+    #[error(
+        "Creating cache would exceed the memory budget: {used} bytes in use, {projected} bytes \
+         projected for the new cache, {budget} bytes budgeted"
+    )]
+    MemoryBudgetExceeded {
+        /// The memory currently used by materialized state and readers, in bytes
+        used: u64,
+        /// The memory the new cache is expected to use, in bytes
+        projected: u64,
+        /// The configured memory budget, in bytes
+        budget: u64,
+    },
 }
 
 impl ReadySetError {
@@ -814,6 +948,24 @@ impl ReadySetError {
     pub fn is_invalid_query(&self) -> bool {
         matches!(self, Self::InvalidQuery(..))
     }
+
+    /// Returns true if the error either *is* [`InvalidQuery`], or was *caused by*
+    /// [`InvalidQuery`]
+    pub fn caused_by_invalid_query(&self) -> bool {
+        self.any_cause(|e| e.is_invalid_query())
+    }
+
+    /// Returns true if the error either *is* [`NoSuchColumn`] or [`NonExistentColumn`], or was
+    /// *caused by* either of them
+    pub fn caused_by_missing_column(&self) -> bool {
+        self.any_cause(|e| matches!(e, Self::NoSuchColumn(..) | Self::NonExistentColumn { .. }))
+    }
+
+    /// Returns true if the error either *is* [`MemoryBudgetExceeded`], or was *caused by*
+    /// [`MemoryBudgetExceeded`]
+    pub fn caused_by_memory_budget_exceeded(&self) -> bool {
+        self.any_cause(|e| matches!(e, Self::MemoryBudgetExceeded { .. }))
+    }
 }
 
 /// Make a new [`ReadySetError::Internal`] with the provided format arguments.
@@ -21,6 +21,7 @@ use mysql_common::chrono::NaiveDateTime;
 use mysql_time::MySqlTime;
 use nom_sql::{Literal, SqlQuery};
 use pgsql::types::to_sql_checked;
+use readyset_client::internal::IndexType;
 use readyset_data::{DfValue, TIMESTAMP_FORMAT};
 use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
@@ -56,6 +57,10 @@ pub enum Conditional {
     /// Invert the ['Query'] result if no upstream connector is present. Pass becomes fail, fail
     /// becomes pass. Ignored for ['Statement'].
     InvertNoUpstream,
+    /// When running against ReadySet, check with `EXPLAIN LAST STATEMENT` that the [`Query`] was
+    /// executed against ReadySet (rather than proxied upstream), reading from an index of the
+    /// given type if specified. Ignored for [`Statement`].
+    Cacheable(Option<IndexType>),
 }
 
 impl Display for Conditional {
@@ -64,6 +69,8 @@ impl Display for Conditional {
             Conditional::SkipIf(engine) => write!(f, "skipif {}", engine),
             Conditional::OnlyIf(engine) => write!(f, "onlyif {}", engine),
             Conditional::InvertNoUpstream => write!(f, "invertupstream"),
+            Conditional::Cacheable(None) => write!(f, "cacheable"),
+            Conditional::Cacheable(Some(index_type)) => write!(f, "cacheable {}", index_type),
         }
     }
 }
@@ -330,12 +337,32 @@ impl pgsql::types::ToSql for Value {
         ty: &pgsql::types::Type,
         out: &mut bytes::BytesMut,
     ) -> Result<pgsql::types::IsNull, Box<dyn Error + Sync + Send>> {
+        use pgsql::types::Type;
+
+        // Values are encoded in the binary format, so need to be converted to the exact type of
+        // the parameter
         match self {
             Value::Text(x) => x.to_sql(ty, out),
-            Value::Integer(x) => x.to_sql(ty, out),
-            Value::Real(i, f) => (*i as f64 + ((*f as f64) / 1_000_000_000.0)).to_sql(ty, out),
+            Value::Integer(x) => match *ty {
+                Type::BOOL => (*x != 0).to_sql(ty, out),
+                Type::CHAR => i8::try_from(*x)?.to_sql(ty, out),
+                Type::INT2 => i16::try_from(*x)?.to_sql(ty, out),
+                Type::INT4 => i32::try_from(*x)?.to_sql(ty, out),
+                Type::NUMERIC => Decimal::from(*x).to_sql(ty, out),
+                _ => x.to_sql(ty, out),
+            },
+            Value::Real(i, f) => {
+                let x = *i as f64 + ((*f as f64) / 1_000_000_000.0);
+                match *ty {
+                    Type::FLOAT4 => (x as f32).to_sql(ty, out),
+                    _ => x.to_sql(ty, out),
+                }
+            }
             Value::Numeric(d) => d.to_sql(ty, out),
-            Value::Date(x) => x.to_sql(ty, out),
+            Value::Date(x) => match *ty {
+                Type::DATE => x.date().to_sql(ty, out),
+                _ => x.to_sql(ty, out),
+            },
             Value::Time(x) => NaiveTime::from(*x).to_sql(ty, out),
             Value::ByteArray(array) => array.to_sql(ty, out),
             Value::Null => None::<i8>.to_sql(ty, out),
@@ -806,8 +833,17 @@ pub enum Record {
     /// Sleep for the given number of milliseconds
     Sleep(u64),
 
-    /// Print a graphviz representation of the current query graph.
+    /// Print a graphviz representation of the current query graph, or write it to a file if
+    /// `--graphviz-dir` is passed.
     Graphviz,
+
+    /// Create a database with the given name (a schema, in PostgreSQL), which is dropped along
+    /// with the test database before the script is run.
+    CreateDatabase(String),
+
+    /// Switch to the database with the given name (by setting `search_path`, in PostgreSQL) for
+    /// the records that follow, or back to the test database if no name is given.
+    UseDatabase(Option<String>),
 }
 
 impl Display for Record {
@@ -821,6 +857,9 @@ impl Display for Record {
             }
             Record::Graphviz => f.write_str("graphviz\n"),
             Record::Sleep(msecs) => writeln!(f, "sleep {}", msecs),
+            Record::CreateDatabase(name) => writeln!(f, "create-database {}", name),
+            Record::UseDatabase(Some(name)) => writeln!(f, "use-database {}", name),
+            Record::UseDatabase(None) => f.write_str("use-database\n"),
         }
     }
 }
@@ -865,4 +904,42 @@ mod tests {
     fn compare_result_value() {
         assert!(Value::Integer(9) > Value::Integer(10));
     }
+
+    #[test]
+    fn pgsql_param_matches_type() {
+        use pgsql::types::{ToSql, Type};
+
+        let encode = |value: Value, ty: Type| {
+            let mut out = bytes::BytesMut::new();
+            value.to_sql_checked(&ty, &mut out).unwrap();
+            out.to_vec()
+        };
+
+        assert_eq!(encode(Value::Integer(1), Type::BOOL), [1]);
+        assert_eq!(
+            encode(Value::Integer(-2), Type::INT2),
+            (-2i16).to_be_bytes()
+        );
+        assert_eq!(
+            encode(Value::Integer(-2), Type::INT4),
+            (-2i32).to_be_bytes()
+        );
+        assert_eq!(
+            encode(Value::Integer(-2), Type::INT8),
+            (-2i64).to_be_bytes()
+        );
+        assert_eq!(
+            encode(Value::from(0.25f64), Type::FLOAT4),
+            0.25f32.to_be_bytes()
+        );
+        assert_eq!(
+            encode(Value::from(0.25f64), Type::FLOAT8),
+            0.25f64.to_be_bytes()
+        );
+
+        let mut out = bytes::BytesMut::new();
+        Value::Integer(i64::MAX)
+            .to_sql_checked(&Type::INT4, &mut out)
+            .unwrap_err();
+    }
 }
@@ -0,0 +1,613 @@
+//! Generation and running of wire protocol conformance scenarios
+//!
+//! Each scenario is a test script exercising one corner of the MySQL or PostgreSQL wire protocol:
+//! identifiers of the maximum length the database allows, every supported type as both a result
+//! column and a query parameter, and values whose length puts packets or messages on either side
+//! of the boundaries at which they're encoded differently or split up. The scenarios are run
+//! against ReadySet's `mysql-srv` or `psql-srv` using the same client library as `verify` for each
+//! database type, and the results are collected into a [`ConformanceReport`] per protocol.
+//!
+//! Each protocol is only exercised with that one client library (`mysql_async` for MySQL and
+//! `tokio-postgres` for PostgreSQL), so differences between client libraries in how they split up
+//! packets or encode parameters aren't covered.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+use clap::Parser;
+use console::style;
+use database_utils::DatabaseType;
+use futures::StreamExt;
+use itertools::Itertools;
+use mysql_time::MySqlTime;
+use readyset_logictest::ast::{Query, QueryParams, QueryResults, Record, Statement, Value};
+use readyset_logictest::runner::{NoriaOptions, RunOptions, TestScript};
+
+/// The maximum length of an identifier in MySQL
+const MYSQL_MAX_IDENTIFIER_LEN: usize = 64;
+
+/// The maximum length of an identifier in PostgreSQL (`NAMEDATALEN - 1`)
+const POSTGRESQL_MAX_IDENTIFIER_LEN: usize = 63;
+
+/// The maximum length of the payload of a single MySQL packet. Packets with a longer payload are
+/// split, and a payload of exactly this length is followed by an empty packet.
+const MYSQL_MAX_PAYLOAD_LEN: usize = 0xff_ffff;
+
+/// Results containing text values longer than this are hashed rather than written out in full
+const MAX_UNHASHED_TEXT_LEN: usize = 128;
+
+/// Generate wire protocol conformance scenarios, run them against ReadySet, and report which
+/// scenarios pass for each protocol. Scenarios are run with the same client library as `verify`.
+#[derive(Parser, Debug)]
+pub struct Conformance {
+    /// Database types to generate and run scenarios for
+    #[clap(
+        long,
+        value_enum,
+        default_values = ["mysql", "postgresql"],
+    )]
+    database_type: Vec<DatabaseType>,
+
+    /// Directory to write the generated scenarios to, as test scripts which can also be run with
+    /// `verify`. Defaults to a temporary directory.
+    #[clap(long, short = 'o')]
+    output: Option<PathBuf>,
+
+    /// Only generate the scenarios, without running them. Requires `--output`.
+    #[clap(long, requires = "output")]
+    generate_only: bool,
+
+    /// Also generate scenarios with values long enough to span MySQL's maximum packet length,
+    /// which take up tens of megabytes each
+    #[clap(long)]
+    large_packets: bool,
+
+    /// Write the conformance report to this file, in addition to printing it
+    #[clap(long)]
+    report: Option<PathBuf>,
+
+    /// Number of scenarios to run in parallel
+    #[clap(long, short = 't', default_value = "8")]
+    tasks: usize,
+}
+
+/// A single generated scenario
+#[derive(Debug, Clone)]
+struct Scenario {
+    /// The name of the scenario, which is unique for its database type
+    name: String,
+    /// The records of the test script for the scenario
+    records: Vec<Record>,
+    /// Parts of the scenario which aren't tested, and why, to be recorded in the report
+    unsupported: Vec<(&'static str, &'static str)>,
+}
+
+/// A value of a single SQL type, as written in SQL and as returned to the client
+struct TypeCase {
+    name: &'static str,
+    sql_type: &'static str,
+    literal: &'static str,
+    value: Value,
+    /// If the value can't be passed as a query parameter, why not
+    param_unsupported: Option<&'static str>,
+}
+
+impl TypeCase {
+    fn new(
+        name: &'static str,
+        sql_type: &'static str,
+        literal: &'static str,
+        value: Value,
+    ) -> Self {
+        Self {
+            name,
+            sql_type,
+            literal,
+            value,
+            param_unsupported: None,
+        }
+    }
+
+    fn result_only(mut self, reason: &'static str) -> Self {
+        self.param_unsupported = Some(reason);
+        self
+    }
+}
+
+/// The name of the protocol tested for `database_type`, and the client library it's tested with,
+/// as shown in the report
+fn protocol_name(database_type: DatabaseType) -> &'static str {
+    match database_type {
+        DatabaseType::MySQL => "mysql-srv (mysql_async)",
+        DatabaseType::PostgreSQL => "psql-srv (tokio-postgres)",
+    }
+}
+
+/// The placeholder for the `n`th (1-based) parameter of a query
+fn placeholder(database_type: DatabaseType, n: usize) -> String {
+    match database_type {
+        DatabaseType::MySQL => "?".to_owned(),
+        DatabaseType::PostgreSQL => format!("${n}"),
+    }
+}
+
+fn statement(command: String) -> Record {
+    Record::Statement(Statement::ok(command))
+}
+
+fn query(query: String, params: Vec<Value>, results: Vec<Value>) -> Record {
+    // Text that isn't printable ASCII, or is too long to be readable, is compared by hash
+    let hash = results.iter().any(|v| match v {
+        Value::Text(s) => {
+            s.len() > MAX_UNHASHED_TEXT_LEN || !s.bytes().all(|b| (0x20..0x7f).contains(&b))
+        }
+        _ => false,
+    });
+
+    Record::Query(Query {
+        label: None,
+        column_types: None,
+        sort_mode: None,
+        conditionals: vec![],
+        query,
+        results: if hash {
+            QueryResults::hash(&results)
+        } else {
+            QueryResults::Results(results)
+        },
+        params: QueryParams::PositionalParams(params),
+    })
+}
+
+/// A text value of `len` bytes, made of a repeating pattern so that truncated or misaligned values
+/// don't compare equal
+fn patterned_text(len: usize) -> String {
+    (0..len).map(|i| (b'a' + (i % 26) as u8) as char).collect()
+}
+
+fn type_cases(database_type: DatabaseType) -> Vec<TypeCase> {
+    let text = || Value::Text("conformance".to_owned());
+    let unicode = || Value::Text("ünïcødé ✓".to_owned());
+    let time = || Value::Time(MySqlTime::from_hmsus(true, 12, 34, 56, 0));
+    let null = || {
+        TypeCase::new("null", "INT", "NULL", Value::Null).result_only(
+            "NULL never compares equal to a parameter, so rows can't be looked up by it",
+        )
+    };
+
+    match database_type {
+        DatabaseType::MySQL => {
+            let datetime = || {
+                Value::Date(
+                    NaiveDate::from_ymd_opt(2023, 1, 2)
+                        .and_then(|d| d.and_hms_opt(3, 4, 5))
+                        .unwrap(),
+                )
+            };
+            vec![
+                TypeCase::new("tinyint", "TINYINT", "-128", Value::Integer(-128)),
+                TypeCase::new("smallint", "SMALLINT", "32767", Value::Integer(32767)),
+                TypeCase::new("int", "INT", "-2147483648", Value::Integer(-2147483648)),
+                TypeCase::new(
+                    "int_unsigned",
+                    "INT UNSIGNED",
+                    "4294967295",
+                    Value::Integer(4294967295),
+                ),
+                TypeCase::new(
+                    "bigint",
+                    "BIGINT",
+                    "9223372036854775807",
+                    Value::Integer(i64::MAX),
+                ),
+                TypeCase::new("float", "FLOAT", "0.25", Value::from(0.25f64)),
+                TypeCase::new("double", "DOUBLE", "1.5", Value::from(1.5f64)),
+                TypeCase::new("varchar", "VARCHAR(255)", "'conformance'", text()),
+                TypeCase::new("text", "TEXT", "'conformance'", text()),
+                TypeCase::new("text_unicode", "TEXT", "'ünïcødé ✓'", unicode()),
+                TypeCase::new("text_empty", "TEXT", "''", Value::Text(String::new())),
+                TypeCase::new("datetime", "DATETIME", "'2023-01-02 03:04:05'", datetime()),
+                TypeCase::new(
+                    "timestamp",
+                    "TIMESTAMP",
+                    "'2023-01-02 03:04:05'",
+                    datetime(),
+                ),
+                TypeCase::new("time", "TIME", "'12:34:56'", time()),
+                null(),
+            ]
+        }
+        DatabaseType::PostgreSQL => vec![
+            TypeCase::new("smallint", "SMALLINT", "-32768", Value::Integer(-32768)),
+            TypeCase::new("int", "INT", "-2147483648", Value::Integer(-2147483648)),
+            TypeCase::new(
+                "bigint",
+                "BIGINT",
+                "9223372036854775807",
+                Value::Integer(i64::MAX),
+            ),
+            TypeCase::new("real", "REAL", "0.25", Value::from(0.25f64)),
+            TypeCase::new("double", "DOUBLE PRECISION", "1.5", Value::from(1.5f64)),
+            TypeCase::new("text", "TEXT", "'conformance'", text()),
+            TypeCase::new("text_unicode", "TEXT", "'ünïcødé ✓'", unicode()),
+            TypeCase::new("text_empty", "TEXT", "''", Value::Text(String::new())),
+            TypeCase::new("boolean", "BOOLEAN", "TRUE", Value::Integer(1)),
+            TypeCase::new("time", "TIME", "'12:34:56'", time()),
+            null(),
+        ],
+    }
+}
+
+/// Scenarios reading each supported type as a result column, both with and without parameters
+/// (and so over both the text and binary protocols for MySQL), and passing it as a parameter
+fn type_scenarios(database_type: DatabaseType) -> Vec<Scenario> {
+    type_cases(database_type)
+        .into_iter()
+        .map(|case| {
+            let table = format!("types_{}", case.name);
+            let p1 = placeholder(database_type, 1);
+            let mut records = vec![
+                statement(format!(
+                    "CREATE TABLE {table} (id BIGINT PRIMARY KEY, c {})",
+                    case.sql_type
+                )),
+                statement(format!(
+                    "INSERT INTO {table} (id, c) VALUES (1, {})",
+                    case.literal
+                )),
+                query(
+                    format!("SELECT c FROM {table} WHERE id = 1"),
+                    vec![],
+                    vec![case.value.clone()],
+                ),
+                query(
+                    format!("SELECT c FROM {table} WHERE id = {p1}"),
+                    vec![Value::Integer(1)],
+                    vec![case.value.clone()],
+                ),
+            ];
+            let mut unsupported = vec![];
+            match case.param_unsupported {
+                None => records.push(query(
+                    format!("SELECT id FROM {table} WHERE c = {p1}"),
+                    vec![case.value],
+                    vec![Value::Integer(1)],
+                )),
+                Some(reason) => unsupported.push(("param", reason)),
+            }
+
+            Scenario {
+                name: format!("type_{}", case.name),
+                records,
+                unsupported,
+            }
+        })
+        .collect()
+}
+
+/// A scenario using a table, column and alias whose names are as long as the database allows
+fn max_length_identifier_scenario(database_type: DatabaseType) -> Scenario {
+    let max_len = match database_type {
+        DatabaseType::MySQL => MYSQL_MAX_IDENTIFIER_LEN,
+        DatabaseType::PostgreSQL => POSTGRESQL_MAX_IDENTIFIER_LEN,
+    };
+    let identifier = |prefix: char| {
+        std::iter::once(prefix)
+            .chain(std::iter::repeat('x').take(max_len - 1))
+            .collect::<String>()
+    };
+    let (table, column, alias) = (identifier('t'), identifier('c'), identifier('a'));
+    let p1 = placeholder(database_type, 1);
+
+    Scenario {
+        name: "max_length_identifiers".to_owned(),
+        records: vec![
+            statement(format!(
+                "CREATE TABLE {table} ({column} BIGINT PRIMARY KEY)"
+            )),
+            statement(format!("INSERT INTO {table} ({column}) VALUES (1)")),
+            query(
+                format!("SELECT {column} AS {alias} FROM {table} WHERE {column} = 1"),
+                vec![],
+                vec![Value::Integer(1)],
+            ),
+            query(
+                format!("SELECT {column} AS {alias} FROM {table} WHERE {column} = {p1}"),
+                vec![Value::Integer(1)],
+                vec![Value::Integer(1)],
+            ),
+        ],
+        unsupported: vec![],
+    }
+}
+
+/// The lengths of text values that put packets or messages on either side of the boundaries at
+/// which they're encoded differently or split up
+fn packet_boundary_lengths(database_type: DatabaseType, large_packets: bool) -> Vec<usize> {
+    match database_type {
+        DatabaseType::MySQL => {
+            // Length-encoded integers, which prefix each value in a row, take 1 byte below 251, 3
+            // bytes below 2^16 and 4 bytes below 2^24
+            let mut lengths = vec![250, 251, 252, 65535, 65536];
+            if large_packets {
+                // A value of `len` bytes with a 4 byte length prefix makes a row packet with a
+                // payload of `len + 4` bytes, so these put row packets on either side of the
+                // maximum payload length (and the query and parameter packets containing the
+                // value over it)
+                let boundary = MYSQL_MAX_PAYLOAD_LEN - 4;
+                lengths.extend([boundary - 1, boundary, boundary + 1, MYSQL_MAX_PAYLOAD_LEN]);
+            }
+            lengths
+        }
+        DatabaseType::PostgreSQL => {
+            // Messages are read from the socket in 8KiB chunks, growing the buffer as needed
+            let mut lengths = vec![8191, 8192, 8193, 65535, 65536, 65537];
+            if large_packets {
+                lengths.push(MYSQL_MAX_PAYLOAD_LEN + 1);
+            }
+            lengths
+        }
+    }
+}
+
+/// Scenarios writing and reading text values of each of [`packet_boundary_lengths`]
+fn packet_boundary_scenarios(database_type: DatabaseType, large_packets: bool) -> Vec<Scenario> {
+    let text_type = match database_type {
+        DatabaseType::MySQL => "LONGTEXT",
+        DatabaseType::PostgreSQL => "TEXT",
+    };
+    let p1 = placeholder(database_type, 1);
+
+    packet_boundary_lengths(database_type, large_packets)
+        .into_iter()
+        .map(|len| {
+            let table = format!("packets_{len}");
+            let text = patterned_text(len);
+            Scenario {
+                name: format!("packet_boundary_{len}"),
+                records: vec![
+                    statement(format!(
+                        "CREATE TABLE {table} (id BIGINT PRIMARY KEY, c {text_type})"
+                    )),
+                    statement(format!("INSERT INTO {table} (id, c) VALUES (1, '{text}')")),
+                    query(
+                        format!("SELECT c FROM {table} WHERE id = 1"),
+                        vec![],
+                        vec![Value::Text(text.clone())],
+                    ),
+                    query(
+                        format!("SELECT c FROM {table} WHERE id = {p1}"),
+                        vec![Value::Integer(1)],
+                        vec![Value::Text(text.clone())],
+                    ),
+                    query(
+                        format!("SELECT id FROM {table} WHERE c = {p1}"),
+                        vec![Value::Text(text)],
+                        vec![Value::Integer(1)],
+                    ),
+                ],
+                unsupported: vec![],
+            }
+        })
+        .collect()
+}
+
+/// Generate all the conformance scenarios for `database_type`
+fn scenarios(database_type: DatabaseType, large_packets: bool) -> Vec<Scenario> {
+    let mut scenarios = vec![max_length_identifier_scenario(database_type)];
+    scenarios.extend(type_scenarios(database_type));
+    scenarios.extend(packet_boundary_scenarios(database_type, large_packets));
+    scenarios
+}
+
+/// The outcome of a single scenario, or part of a scenario, in a [`ConformanceReport`]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    Passed,
+    Failed(String),
+    /// The scenario, or part of it, isn't tested, for the given reason
+    Unsupported(&'static str),
+}
+
+/// The results of running the conformance scenarios, grouped by protocol
+#[derive(Debug, Default)]
+pub struct ConformanceReport {
+    /// The outcome of each scenario for each protocol
+    results: BTreeMap<&'static str, Vec<(String, Outcome)>>,
+}
+
+impl ConformanceReport {
+    fn num_failures(&self) -> usize {
+        self.results
+            .values()
+            .flatten()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Failed(_)))
+            .count()
+    }
+}
+
+impl Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (protocol, results) in &self.results {
+            let count =
+                |pred: fn(&Outcome) -> bool| results.iter().filter(|(_, o)| pred(o)).count();
+            let passes = count(|o| *o == Outcome::Passed);
+            let unsupported = count(|o| matches!(o, Outcome::Unsupported(_)));
+            writeln!(
+                f,
+                "{protocol}: {passes}/{} scenarios passed, {unsupported} unsupported",
+                results.len() - unsupported
+            )?;
+            for (scenario, outcome) in results.iter().sorted() {
+                let (status, details) = match outcome {
+                    Outcome::Passed => ("pass", ""),
+                    Outcome::Failed(error) => ("FAIL", error.as_str()),
+                    Outcome::Unsupported(reason) => ("n/a ", *reason),
+                };
+                writeln!(f, "    {status}  {scenario}")?;
+                for line in details.lines() {
+                    writeln!(f, "              {line}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Conformance {
+    /// Write `scenarios` to test scripts in `dir`, returning the path of each script
+    fn write_scenarios(dir: &Path, scenarios: Vec<Scenario>) -> anyhow::Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir)?;
+        scenarios
+            .into_iter()
+            .map(|scenario| {
+                let path = dir.join(format!("{}.test", scenario.name));
+                let mut file = fs::File::create(&path)?;
+                TestScript::from(scenario.records).write_to(&mut file)?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    #[tokio::main]
+    pub async fn run(self) -> anyhow::Result<()> {
+        let tempdir;
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => {
+                tempdir = tempfile::tempdir()?;
+                tempdir.path().to_owned()
+            }
+        };
+
+        let mut report = ConformanceReport::default();
+        for &database_type in &self.database_type {
+            let dir = output.join(database_type.to_string());
+            let scenarios = scenarios(database_type, self.large_packets);
+            let unsupported = scenarios
+                .iter()
+                .flat_map(|scenario| {
+                    scenario.unsupported.iter().map(|(part, reason)| {
+                        (
+                            format!("{}/{part}", scenario.name),
+                            Outcome::Unsupported(*reason),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+            let paths = Self::write_scenarios(&dir, scenarios)
+                .with_context(|| format!("Writing scenarios to {}", dir.display()))?;
+            println!(
+                "==> {} {} scenarios for {} to {}",
+                style("Generated").bold(),
+                paths.len(),
+                protocol_name(database_type),
+                dir.display()
+            );
+            if self.generate_only {
+                continue;
+            }
+
+            let results = futures::stream::iter(paths)
+                .map(|path| async move {
+                    let name = path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    let result = async {
+                        let mut script = TestScript::open_file(path)?;
+                        script
+                            .run(
+                                RunOptions {
+                                    database_type,
+                                    ..Default::default()
+                                },
+                                NoriaOptions::default(),
+                            )
+                            .await
+                    }
+                    .await;
+                    let outcome = match result {
+                        Ok(()) => Outcome::Passed,
+                        Err(e) => Outcome::Failed(format!("{e:#}")),
+                    };
+                    (name, outcome)
+                })
+                .buffer_unordered(self.tasks.max(1))
+                .chain(futures::stream::iter(unsupported))
+                .collect::<Vec<_>>()
+                .await;
+            report.results.insert(protocol_name(database_type), results);
+        }
+
+        if self.generate_only {
+            return Ok(());
+        }
+
+        println!("\n{}\n{report}", style("Conformance report").bold());
+        if let Some(path) = &self.report {
+            fs::write(path, report.to_string())
+                .with_context(|| format!("Writing report to {}", path.display()))?;
+        }
+
+        let failures = report.num_failures();
+        if failures > 0 {
+            bail!(
+                "{failures} conformance scenario{} failed",
+                if failures == 1 { "" } else { "s" }
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use readyset_logictest::parser;
+
+    use super::*;
+
+    #[test]
+    fn scenarios_round_trip_through_parser() {
+        for database_type in [DatabaseType::MySQL, DatabaseType::PostgreSQL] {
+            for scenario in scenarios(database_type, false) {
+                let mut script = vec![];
+                TestScript::from(scenario.records.clone())
+                    .write_to(&mut script)
+                    .unwrap();
+                let records = parser::read_records(&script[..])
+                    .unwrap_or_else(|e| panic!("Parsing {}: {e}", scenario.name));
+                assert_eq!(records.len(), scenario.records.len(), "{}", scenario.name);
+            }
+        }
+    }
+
+    #[test]
+    fn packet_boundaries_straddle_max_payload() {
+        let lengths = packet_boundary_lengths(DatabaseType::MySQL, true);
+        assert!(lengths.contains(&(MYSQL_MAX_PAYLOAD_LEN - 4)));
+        assert!(!packet_boundary_lengths(DatabaseType::MySQL, false)
+            .iter()
+            .any(|len| *len > MYSQL_MAX_PAYLOAD_LEN - 5));
+    }
+
+    #[test]
+    fn unsupported_isnt_failure() {
+        let mut report = ConformanceReport::default();
+        report.results.insert(
+            protocol_name(DatabaseType::PostgreSQL),
+            vec![
+                ("type_null".to_owned(), Outcome::Passed),
+                ("type_null/param".to_owned(), Outcome::Unsupported("reason")),
+            ],
+        );
+        assert_eq!(report.num_failures(), 0);
+        assert!(report
+            .to_string()
+            .starts_with("psql-srv (tokio-postgres): 1/1 scenarios passed, 1 unsupported\n"));
+    }
+}
@@ -51,6 +51,9 @@ pub(crate) struct Seed {
     queries: Vec<Query>,
     generator: GeneratorState,
     hash_threshold: usize,
+    /// Whether the seed script contains a `graphviz` record, in which case one is added after
+    /// each set of generated query results, once the queries have been migrated
+    graphviz: bool,
     script: TestScript,
 }
 
@@ -65,6 +68,7 @@ impl TryFrom<PathBuf> for Seed {
         let mut tables = vec![];
         let mut queries = vec![];
         let mut hash_threshold = DEFAULT_HASH_THRESHOLD;
+        let mut graphviz = false;
 
         for record in script.records() {
             match record {
@@ -92,7 +96,8 @@ impl TryFrom<PathBuf> for Seed {
                     hash_threshold = *ht;
                 }
                 Record::Halt { .. } => break,
-                Record::Graphviz | Record::Sleep(_) => {}
+                Record::Graphviz => graphviz = true,
+                Record::Sleep(_) | Record::CreateDatabase(_) | Record::UseDatabase(_) => {}
             }
         }
 
@@ -105,6 +110,7 @@ impl TryFrom<PathBuf> for Seed {
             queries,
             generator,
             hash_threshold,
+            graphviz,
             script,
         })
     }
@@ -180,6 +186,7 @@ impl TryFrom<Vec<QuerySeed>> for Seed {
             queries,
             generator,
             hash_threshold: DEFAULT_HASH_THRESHOLD,
+            graphviz: false,
             script: records.into(),
         })
     }
@@ -349,8 +356,10 @@ impl Seed {
         let hash_threshold = self.hash_threshold;
         let queries = mem::take(&mut self.queries);
 
-        let new_entries =
-            new_entries.chain(run_queries(&queries, &mut conn, hash_threshold).await?);
+        let graphviz = self.graphviz.then_some(Record::Graphviz);
+        let new_entries = new_entries
+            .chain(run_queries(&queries, &mut conn, hash_threshold).await?)
+            .chain(graphviz.clone());
 
         if opts.include_deletes {
             let rows_to_delete = opts.rows_to_delete.unwrap_or(opts.rows_per_table / 2);
@@ -424,8 +433,11 @@ impl Seed {
                 })?;
             }
 
-            self.script
-                .extend(new_entries.chain(run_queries(&queries, &mut conn, hash_threshold).await?))
+            self.script.extend(
+                new_entries
+                    .chain(run_queries(&queries, &mut conn, hash_threshold).await?)
+                    .chain(graphviz),
+            )
         } else {
             self.script.extend(new_entries)
         }
@@ -26,12 +26,14 @@ use tokio::sync::Mutex;
 use walkdir::WalkDir;
 
 pub mod ast;
+pub mod conformance;
 pub mod from_query_log;
 pub mod generate;
 pub mod parser;
 pub mod permute;
 pub mod runner;
 
+use crate::conformance::Conformance;
 use crate::from_query_log::FromQueryLog;
 use crate::generate::Generate;
 use crate::permute::Permute;
@@ -58,6 +60,7 @@ enum Command {
     FromQueryLog(FromQueryLog),
     Fuzz(Fuzz),
     Permute(Permute),
+    Conformance(Conformance),
 }
 
 impl Command {
@@ -75,6 +78,7 @@ impl Command {
                 fuzz.run()
             }
             Self::Permute(permute) => permute.run(),
+            Self::Conformance(conformance) => conformance.run(),
         }
     }
 }
@@ -284,6 +288,11 @@ struct Verify {
     #[clap(long)]
     time: bool,
 
+    /// Write the dataflow graph for each `graphviz` record in a test script to a `.dot` file (and
+    /// an `.svg` file, if graphviz is installed) in this directory, rather than printing it
+    #[clap(long)]
+    graphviz_dir: Option<PathBuf>,
+
     /// Logging/tracing options
     #[clap(flatten)]
     tracing: readyset_tracing::Options,
@@ -538,6 +547,7 @@ impl From<&Verify> for RunOptions {
             upstream_database_url: verify.database_url().cloned(),
             replication_url: verify.replication_url.clone(),
             time: verify.time,
+            graphviz_dir: verify.graphviz_dir.clone(),
         }
     }
 }
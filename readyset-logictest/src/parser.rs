@@ -14,17 +14,18 @@ use anyhow::{anyhow, bail, Context};
 use chrono::NaiveDateTime;
 use mysql_time::MySqlTime;
 use nom::branch::alt;
-use nom::bytes::complete::tag;
+use nom::bytes::complete::{tag, take_while1};
 use nom::character::complete::{
     alphanumeric1, anychar, char, digit1, line_ending, not_line_ending, one_of, space0, space1,
 };
-use nom::combinator::{complete, eof, map, map_opt, map_parser, opt, peek, recognize};
+use nom::combinator::{complete, eof, map, map_opt, map_parser, map_res, opt, peek, recognize};
 use nom::multi::{count, many0, many1, many_till};
 use nom::sequence::{pair, preceded, terminated, tuple};
 use nom::IResult;
 use nom_locate::LocatedSpan;
 use nom_sql::to_nom_result;
 use nom_sql::whitespace::whitespace1;
+use readyset_client::internal::IndexType;
 use readyset_data::TIMESTAMP_PARSE_FORMAT;
 
 use crate::ast::*;
@@ -59,8 +60,21 @@ fn invert_no_upstream(i: &[u8]) -> IResult<&[u8], Conditional> {
     Ok((i, Conditional::InvertNoUpstream))
 }
 
+fn cacheable(i: &[u8]) -> IResult<&[u8], Conditional> {
+    let (i, _) = tag("cacheable")(i)?;
+    let (i, index_type) = opt(preceded(
+        space1,
+        map_res(alphanumeric1, |s: &[u8]| {
+            String::from_utf8_lossy(s).parse::<IndexType>()
+        }),
+    ))(i)?;
+    let (i, _) = opt(comment)(i)?;
+
+    Ok((i, Conditional::Cacheable(index_type)))
+}
+
 fn conditional(i: &[u8]) -> IResult<&[u8], Conditional> {
-    alt((skipif, onlyif, invert_no_upstream))(i)
+    alt((skipif, onlyif, invert_no_upstream, cacheable))(i)
 }
 
 fn conditionals(i: &[u8]) -> IResult<&[u8], Vec<Conditional>> {
@@ -332,6 +346,28 @@ fn sleep(i: &[u8]) -> IResult<&[u8], Record> {
     Ok((i, Record::Sleep(len)))
 }
 
+fn database_name(i: &[u8]) -> IResult<&[u8], String> {
+    let (i, _) = space1(i)?;
+    map_res(
+        take_while1(|c: u8| c.is_ascii_alphanumeric() || c == b'_'),
+        |name| std::str::from_utf8(name).map(str::to_owned),
+    )(i)
+}
+
+fn create_database(i: &[u8]) -> IResult<&[u8], Record> {
+    let (i, _) = tag("create-database")(i)?;
+    let (i, name) = database_name(i)?;
+    let (i, _) = line_ending(i)?;
+    Ok((i, Record::CreateDatabase(name)))
+}
+
+fn use_database(i: &[u8]) -> IResult<&[u8], Record> {
+    let (i, _) = tag("use-database")(i)?;
+    let (i, name) = opt(database_name)(i)?;
+    let (i, _) = line_ending(i)?;
+    Ok((i, Record::UseDatabase(name)))
+}
+
 fn halt(i: &[u8]) -> IResult<&[u8], Record> {
     let (i, conditionals) = conditionals(i)?;
     let (i, _) = tag("halt")(i)?;
@@ -349,6 +385,8 @@ pub fn record(i: &[u8]) -> IResult<&[u8], Record> {
             Record::Graphviz
         }),
         hash_threshold,
+        create_database,
+        use_database,
     ))(i)
 }
 
@@ -430,6 +468,32 @@ mod tests {
             conditional(b"invert_no_upstream").unwrap().1,
             Conditional::InvertNoUpstream
         );
+
+        assert_eq!(
+            conditional(b"cacheable").unwrap().1,
+            Conditional::Cacheable(None)
+        );
+
+        assert_eq!(
+            conditional(b"cacheable BTreeMap").unwrap().1,
+            Conditional::Cacheable(Some(IndexType::BTreeMap))
+        );
+    }
+
+    #[test]
+    fn parse_database_records() {
+        assert_eq!(
+            record(b"create-database other_db\n").unwrap().1,
+            Record::CreateDatabase("other_db".to_owned())
+        );
+        assert_eq!(
+            record(b"use-database other_db\n").unwrap().1,
+            Record::UseDatabase(Some("other_db".to_owned()))
+        );
+        assert_eq!(
+            record(b"use-database\n").unwrap().1,
+            Record::UseDatabase(None)
+        );
     }
 
     #[test]
@@ -31,6 +31,8 @@ const SMALL_OPERATIONS: &[&str] = &[
     "distinct",
     "inner_join",
     "left_join",
+    "self_join",
+    "join_table_twice",
     "single_parameter",
     "multiple_parameters",
     "range_param",
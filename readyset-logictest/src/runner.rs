@@ -13,17 +13,19 @@ use anyhow::{anyhow, bail, Context};
 use console::style;
 use database_utils::{DatabaseConnection, DatabaseType, DatabaseURL};
 use itertools::Itertools;
-use mysql_srv::MySqlIntermediary;
+use mysql_srv::{CompressionOptions, MySqlIntermediary};
 use nom_sql::{Dialect, Relation};
 use readyset_adapter::backend::noria_connector::ReadBehavior;
 use readyset_adapter::backend::{BackendBuilder, NoriaConnector};
 use readyset_adapter::query_status_cache::QueryStatusCache;
 use readyset_adapter::{UpstreamConfig, UpstreamDatabase};
 use readyset_client::consensus::{Authority, LocalAuthorityStore};
+use readyset_client::internal::IndexType;
 use readyset_client::{ReadySetHandle, ViewCreateRequest};
 use readyset_mysql::{MySqlQueryHandler, MySqlUpstream};
 use readyset_psql::{PostgreSqlQueryHandler, PostgreSqlUpstream};
 use readyset_server::{Builder, LocalAuthority, ReuseConfigType};
+use readyset_util::hash::hash;
 use readyset_util::shutdown::ShutdownSender;
 use tokio::time::sleep;
 use {mysql_async as mysql, tokio_postgres as pgsql};
@@ -76,6 +78,9 @@ pub struct RunOptions {
     pub replication_url: Option<String>,
     pub enable_reuse: bool,
     pub time: bool,
+    /// If set, write the dataflow graph for each `graphviz` record to a file in this directory,
+    /// rather than printing it
+    pub graphviz_dir: Option<PathBuf>,
 }
 
 impl Default for RunOptions {
@@ -86,6 +91,7 @@ impl Default for RunOptions {
             time: false,
             replication_url: None,
             database_type: DatabaseType::MySQL,
+            graphviz_dir: None,
         }
     }
 }
@@ -204,9 +210,44 @@ impl TestScript {
             .await
             .with_context(|| "creating database")?;
 
+        // In PostgreSQL, the databases created by the script are schemas in the test database,
+        // which were dropped along with it
+        if url.database_type() == DatabaseType::MySQL {
+            for name in self.created_databases() {
+                admin_conn
+                    .query_drop(format!("DROP DATABASE IF EXISTS {}", name))
+                    .await
+                    .with_context(|| format!("dropping database {}", name))?;
+            }
+        }
+
         Ok(())
     }
 
+    /// The names of the databases created by `create-database` records in the script
+    fn created_databases(&self) -> impl Iterator<Item = &str> {
+        self.records.iter().filter_map(|record| match record {
+            Record::CreateDatabase(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The name of the database (the schema, in PostgreSQL) that the script starts out running
+    /// in, if it's known
+    fn test_database(opts: &RunOptions) -> Option<String> {
+        match opts.database_type {
+            DatabaseType::PostgreSQL => Some("public".to_owned()),
+            DatabaseType::MySQL => match (&opts.upstream_database_url, &opts.replication_url) {
+                (Some(url), _) => url.db_name().map(str::to_owned),
+                (None, Some(url)) => url
+                    .parse::<DatabaseURL>()
+                    .ok()
+                    .and_then(|url| url.db_name().map(str::to_owned)),
+                (None, None) => None,
+            },
+        }
+    }
+
     /// Run the test script on ReadySet server
     pub async fn run_on_noria(
         &self,
@@ -243,8 +284,12 @@ impl TestScript {
         mut noria: Option<ReadySetHandle>,
     ) -> anyhow::Result<()> {
         let mut prev_was_statement = false;
+        let mut graphviz_records = 0;
 
         let is_readyset = noria.is_some();
+        // Without an upstream database, ReadySet creates databases implicitly along with the first
+        // table in them
+        let has_upstream = !is_readyset || opts.replication_url.is_some();
         let conditional_skip = |conditionals: &[Conditional]| {
             return conditionals.iter().any(|s| match s {
                 Conditional::SkipIf(c) if c == "readyset" => is_readyset,
@@ -299,6 +344,18 @@ impl TestScript {
                             if invert_result {
                                 return Err(anyhow!("Expected failure: {}", query.query));
                             }
+                            if is_readyset {
+                                if let Some(index_type) =
+                                    query.conditionals.iter().find_map(|c| match c {
+                                        Conditional::Cacheable(index_type) => Some(*index_type),
+                                        _ => None,
+                                    })
+                                {
+                                    self.check_cacheable(index_type, conn).await.with_context(
+                                        || format!("Checking query {} is cacheable", query.query),
+                                    )?;
+                                }
+                            }
                         }
                         Err(e) => {
                             if !invert_result {
@@ -320,9 +377,41 @@ impl TestScript {
                 Record::HashThreshold(_) => {}
                 Record::Halt { .. } => break,
                 Record::Sleep(msecs) => sleep(Duration::from_millis(*msecs)).await,
+                Record::CreateDatabase(name) => {
+                    if has_upstream {
+                        let stmt = match opts.database_type {
+                            DatabaseType::MySQL => format!("CREATE DATABASE {}", name),
+                            DatabaseType::PostgreSQL => format!("CREATE SCHEMA {}", name),
+                        };
+                        conn.query_drop(stmt)
+                            .await
+                            .with_context(|| format!("Creating database {}", name))?;
+                    }
+                }
+                Record::UseDatabase(name) => {
+                    let name = match name {
+                        Some(name) => name.clone(),
+                        None => Self::test_database(opts)
+                            .ok_or_else(|| anyhow!("Unknown test database to switch back to"))?,
+                    };
+                    let stmt = match opts.database_type {
+                        DatabaseType::MySQL => format!("USE {}", name),
+                        DatabaseType::PostgreSQL => format!("SET search_path = {}", name),
+                    };
+                    conn.query_drop(stmt)
+                        .await
+                        .with_context(|| format!("Switching to database {}", name))?;
+                }
                 Record::Graphviz => {
                     if let Some(noria) = &mut noria {
-                        println!("{}", noria.graphviz().await?);
+                        graphviz_records += 1;
+                        match &opts.graphviz_dir {
+                            Some(dir) => self
+                                .write_graphviz(noria, dir, graphviz_records)
+                                .await
+                                .context("Writing graphviz")?,
+                            None => println!("{}", noria.graphviz().await?),
+                        }
                     }
                 }
             }
@@ -330,6 +419,64 @@ impl TestScript {
         Ok(())
     }
 
+    /// Write the dataflow graph of `noria` to `<script name>-<path hash>-<n>.dot` in `dir`, and
+    /// render it to an `.svg` file alongside if the graphviz `dot` command is available.
+    ///
+    /// The hash of the script's full path keeps scripts with the same name in different
+    /// directories from overwriting each other's graphs.
+    async fn write_graphviz(
+        &self,
+        noria: &mut ReadySetHandle,
+        dir: &Path,
+        n: usize,
+    ) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("Creating {}", dir.display()))?;
+
+        let path = self
+            .path
+            .canonicalize()
+            .unwrap_or_else(|_| self.path.clone());
+        let dot_path = dir.join(format!("{}-{:016x}-{n}.dot", self.name(), hash(&path)));
+        tokio::fs::write(&dot_path, noria.graphviz().await?)
+            .await
+            .with_context(|| format!("Writing {}", dot_path.display()))?;
+        println!(
+            "{} {}",
+            style("  > Wrote graphviz to").bold(),
+            style(dot_path.display()).blue()
+        );
+
+        let svg_path = dot_path.with_extension("svg");
+        match tokio::process::Command::new("dot")
+            .arg("-Tsvg")
+            .arg("-o")
+            .arg(&svg_path)
+            .arg(&dot_path)
+            .status()
+            .await
+        {
+            Ok(status) if status.success() => println!(
+                "{} {}",
+                style("  > Rendered graphviz to").bold(),
+                style(svg_path.display()).blue()
+            ),
+            Ok(status) => eprintln!(
+                "{} {}",
+                style("  > Rendering graphviz failed:").yellow(),
+                status
+            ),
+            Err(error) => eprintln!(
+                "{} {}",
+                style("  > Could not run dot to render graphviz:").yellow(),
+                error
+            ),
+        }
+
+        Ok(())
+    }
+
     async fn run_statement(
         &self,
         stmt: &Statement,
@@ -444,6 +591,40 @@ impl TestScript {
         Ok(())
     }
 
+    /// Check, using `EXPLAIN LAST STATEMENT`, that the last query run on `conn` was executed
+    /// against ReadySet, reading from an index of type `index_type` if given
+    async fn check_cacheable(
+        &self,
+        index_type: Option<IndexType>,
+        conn: &mut DatabaseConnection,
+    ) -> anyhow::Result<()> {
+        let rows: Vec<Vec<Value>> = conn.query("EXPLAIN LAST STATEMENT").await?;
+        let (destination, error, index) = match rows.first().map(|row| row.as_slice()) {
+            Some([Value::Text(destination), Value::Text(error), Value::Text(index)]) => {
+                (destination, error, index)
+            }
+            _ => bail!("Unexpected result from EXPLAIN LAST STATEMENT: {:?}", rows),
+        };
+
+        if destination != "readyset" {
+            bail!(
+                "Query was executed against {} instead of ReadySet (ReadySet error: {})",
+                destination,
+                error
+            );
+        }
+        if let Some(index_type) = index_type {
+            if *index != index_type.to_string() {
+                bail!(
+                    "Query read from a {} index, but expected a {} index",
+                    index,
+                    index_type
+                );
+            }
+        }
+        Ok(())
+    }
+
     async fn start_noria_server(
         &self,
         run_opts: &RunOptions,
@@ -462,6 +643,17 @@ impl TestScript {
 
             if let Some(replication_url) = &run_opts.replication_url {
                 builder.set_replication_url(replication_url.to_owned());
+
+                // Replicate the tables in the databases created by the script, as well as those in
+                // the test database
+                if self.created_databases().next().is_some() {
+                    let tables = Self::test_database(run_opts)
+                        .into_iter()
+                        .chain(self.created_databases().map(str::to_owned))
+                        .map(|db| format!("{}.*", db))
+                        .join(", ");
+                    builder.set_replication_tables(tables);
+                }
             }
 
             builder.set_keep_prior_recipes(false);
@@ -566,12 +758,14 @@ impl TestScript {
 
             match database_type {
                 DatabaseType::MySQL => MySqlIntermediary::run_on_tcp(
-                    readyset_mysql::Backend {
-                        noria: make_backend!(MySqlUpstream, MySqlQueryHandler, Dialect::MySQL,),
-                        enable_statement_logging: false,
-                    },
+                    readyset_mysql::Backend::new(make_backend!(
+                        MySqlUpstream,
+                        MySqlQueryHandler,
+                        Dialect::MySQL,
+                    )),
                     s,
-                    false,
+                    None,
+                    CompressionOptions::default(),
                 )
                 .await
                 .unwrap(),
@@ -583,7 +777,6 @@ impl TestScript {
                             Dialect::PostgreSQL,
                         )),
                         s,
-                        false,
                         None,
                     )
                     .await
@@ -1,27 +1,28 @@
-use core::fmt;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::fmt::Formatter;
+use std::io::Write;
 use std::ops::{Deref, DerefMut};
 
 use async_trait::async_trait;
-use futures_util::StreamExt;
+use futures_util::{future, StreamExt};
 use itertools::{izip, Itertools};
 use mysql_async::consts::StatusFlags;
 use mysql_common::bigdecimal03::ToPrimitive;
+use mysql_common::io::WriteMysqlExt;
 use mysql_srv::{
     CachedSchema, Column, ColumnFlags, ColumnType, InitWriter, MsqlSrvError, MySqlShim,
-    QueryResultWriter, RowWriter, StatementMetaWriter,
+    QueryResultWriter, RowWriter, SessionStateChange, StatementMetaWriter, ToMySqlValue,
 };
 use readyset_adapter::backend::noria_connector::{
-    MetaVariable, SelectPrepareResult, SelectPrepareResultInner,
+    MetaVariable, ResultPages, SelectPrepareResult, SelectPrepareResultInner,
 };
 use readyset_adapter::backend::{
     noria_connector, QueryResult, SinglePrepareResult, UpstreamPrepare,
 };
 use readyset_data::{DfType, DfValue, DfValueKind};
 use readyset_errors::{internal, ReadySetError};
+use readyset_tracing::statement_logging::Component;
 use readyset_util::redacted::Sensitive;
 use streaming_iterator::StreamingIterator;
 use tokio::io::{self, AsyncWrite};
@@ -29,26 +30,38 @@ use tracing::{error, info, trace};
 use upstream::StatementMeta;
 
 use crate::constants::DEFAULT_CHARACTER_SET;
+use crate::error::upstream_sqlstate;
 use crate::schema::convert_column;
 use crate::upstream::{self, CachedReadResult, MySqlUpstream};
-use crate::value::mysql_value_to_dataflow_value;
+use crate::value::mysql_param_to_dataflow_value;
 use crate::{Error, MySqlQueryHandler};
 
-/// Helper struct to correctly transform a binary type value into its correct [`String`]
-/// representation.
+/// Helper struct to correctly write a binary type value as its hex string representation, encoded
+/// straight into the row being written rather than formatted as a [`String`] first.
 // TODO(fran): We can't keep using the `Display` impl of `DfValue`, since types such as binary or
 //  byte array have different display formats depending on the database (Postgres even has two
 //  possible display formats based on configuration).
 //  This is a temporary workaround, since the actual fix might involve a bigger effort.
 struct BinaryDisplay<'a>(&'a [u8]);
 
-impl<'a> fmt::Display for BinaryDisplay<'a> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "0x{}",
-            self.0.iter().map(|byte| format!("{:02x}", byte)).join("")
-        )
+impl<'a> ToMySqlValue for BinaryDisplay<'a> {
+    fn to_mysql_text<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        w.write_lenenc_int(2 + 2 * self.0.len() as u64)?;
+        w.write_all(b"0x")?;
+        for byte in self.0 {
+            w.write_all(&[
+                HEX_DIGITS[(byte >> 4) as usize],
+                HEX_DIGITS[(byte & 0xf) as usize],
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn to_mysql_bin<W: Write>(&self, w: &mut W, _: &Column) -> io::Result<()> {
+        // Strings are encoded the same way in the binary protocol as in the text protocol
+        self.to_mysql_text(w)
     }
 }
 
@@ -89,14 +102,14 @@ async fn write_column<W: AsyncWrite + Unpin>(
         }
         DfValue::Text(ref t) => {
             if ty.is_binary() {
-                rw.write_col(BinaryDisplay(c.as_bytes()?).to_string())
+                rw.write_col(BinaryDisplay(c.as_bytes()?))
             } else {
                 rw.write_col(t.as_str())
             }
         }
         DfValue::TinyText(ref t) => {
             if ty.is_binary() {
-                rw.write_col(BinaryDisplay(c.as_bytes()?).to_string())
+                rw.write_col(BinaryDisplay(c.as_bytes()?))
             } else {
                 rw.write_col(t.as_str())
             }
@@ -145,7 +158,7 @@ async fn write_column<W: AsyncWrite + Unpin>(
             _ => return Err(conv_error())?,
         },
         DfValue::Time(ref t) => rw.write_col(t),
-        DfValue::ByteArray(ref bytes) => rw.write_col(BinaryDisplay(bytes.as_ref()).to_string()),
+        DfValue::ByteArray(ref bytes) => rw.write_col(BinaryDisplay(bytes.as_ref())),
         // These types are PostgreSQL specific
         DfValue::Array(_) => {
             internal!("Cannot write MySQL column: MySQL does not support arrays")
@@ -160,6 +173,77 @@ async fn write_column<W: AsyncWrite + Unpin>(
     Ok(written?)
 }
 
+/// Write the rows read from ReadySet to `rw`, and finish the resultset.
+///
+/// Each row is encoded and queued as it's read from `rows`, and queued rows are sent to the client
+/// (waiting for the client to read them) once they add up to a given number of bytes, so sending
+/// rows to the client is interleaved with reading them. Since results that were returned by a
+/// reader over the network drop each row as soon as it's been read, only the rows that haven't
+/// been sent yet are kept in memory.
+async fn write_dataflow_rows<W, I>(
+    mut rw: RowWriter<'_, W>,
+    mut rows: I,
+    columns: &[Column],
+    column_types: &[DfType],
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    I: StreamingIterator<Item = [DfValue]>,
+{
+    while let Some(row) = rows.next() {
+        if let Err(e) = write_dataflow_row(&mut rw, row, columns, column_types).await {
+            return handle_column_write_err(e, rw).await;
+        }
+        rw.end_row().await?;
+    }
+    rw.finish().await
+}
+
+/// Writes rows of a result set read from ReadySet one page at a time, only reading each page once
+/// the one before it has been written
+async fn write_dataflow_pages<W>(
+    mut rw: RowWriter<'_, W>,
+    mut pages: ResultPages,
+    columns: &[Column],
+    column_types: &[DfType],
+) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(page) = pages.next().await {
+        let rows = match page {
+            Ok(rows) => rows,
+            Err(e) => {
+                let e = Error::from(e);
+                return rw.error(e.error_kind(), e.to_string().as_bytes()).await;
+            }
+        };
+        for row in rows {
+            if let Err(e) = write_dataflow_row(&mut rw, &row, columns, column_types).await {
+                return handle_column_write_err(e, rw).await;
+            }
+            rw.end_row().await?;
+        }
+    }
+    rw.finish().await
+}
+
+/// Writes the columns of a single row read from ReadySet, leaving the row to be ended by the caller
+async fn write_dataflow_row<W>(
+    rw: &mut RowWriter<'_, W>,
+    row: &[DfValue],
+    columns: &[Column],
+    column_types: &[DfType],
+) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+{
+    for (c, ty, val) in izip!(columns.iter(), column_types.iter(), row.iter()) {
+        write_column(rw, val, c, ty).await?;
+    }
+    Ok(())
+}
+
 async fn write_query_results<W: AsyncWrite + Unpin>(
     r: Result<(u64, u64), Error>,
     results: QueryResultWriter<'_, W>,
@@ -171,6 +255,11 @@ async fn write_query_results<W: AsyncWrite + Unpin>(
                 .completed(row_count, last_insert, status_flags)
                 .await
         }
+        Err(Error::MySql(mysql_async::Error::Server(e))) => {
+            results
+                .error_with_sqlstate(e.code, &upstream_sqlstate(&e), e.message.as_bytes())
+                .await
+        }
         Err(e) => {
             results
                 .error(e.error_kind(), e.to_string().as_bytes())
@@ -278,9 +367,23 @@ async fn write_meta_with_header<W: AsyncWrite + Unpin>(
 pub struct Backend {
     /// Handle to the backing noria client
     pub noria: readyset_adapter::Backend<MySqlUpstream, MySqlQueryHandler>,
-    /// Enables logging of statements received from the client. The `Backend` only logs Query,
-    /// Prepare and Execute statements.
-    pub enable_statement_logging: bool,
+    /// The connection attributes the client sent, by name
+    connection_attributes: HashMap<String, String>,
+}
+
+impl Backend {
+    pub fn new(noria: readyset_adapter::Backend<MySqlUpstream, MySqlQueryHandler>) -> Self {
+        Backend {
+            noria,
+            connection_attributes: HashMap::new(),
+        }
+    }
+
+    /// Returns the connection attributes the client sent when it connected, or when it last
+    /// changed user, by name
+    pub fn connection_attributes(&self) -> &HashMap<String, String> {
+        &self.connection_attributes
+    }
 }
 
 impl Deref for Backend {
@@ -320,11 +423,11 @@ macro_rules! convert_columns {
 macro_rules! handle_error {
     ($error: expr, $writer: expr) => {
         match $error {
-            Error::MySql(mysql_async::Error::Server(mysql_async::ServerError {
-                code,
-                message,
-                ..
-            })) => $writer.error(code.into(), message.as_bytes()).await,
+            Error::MySql(mysql_async::Error::Server(e)) => {
+                $writer
+                    .error_with_sqlstate(e.code, &upstream_sqlstate(&e), e.message.as_bytes())
+                    .await
+            }
             Error::MySql(mysql_async::Error::Driver(
                 mysql_async::DriverError::ConnectionClosed,
             )) => {
@@ -384,24 +487,25 @@ where
         noria_connector::QueryResult::MetaWithHeader(vars) => {
             write_meta_with_header(vars, writer).await
         }
-        noria_connector::QueryResult::Select { mut rows, schema } => {
+        noria_connector::QueryResult::Select { rows, schema } => {
             let mysql_schema = convert_columns!(schema.schema, writer);
-            let mut rw = writer.start(&mysql_schema).await?;
-            while let Some(row) = rows.next() {
-                for (coli, (val, c)) in row.iter().zip(&mysql_schema).enumerate() {
-                    let ty = schema
-                        .schema
-                        .get(coli)
-                        .map(|cs| cs.column_type.clone())
-                        .unwrap_or_default();
-
-                    if let Err(e) = write_column(&mut rw, val, c, &ty).await {
-                        return handle_column_write_err(e, rw).await;
-                    }
-                }
-                rw.end_row().await?;
-            }
-            rw.finish().await
+            let column_types = schema
+                .schema
+                .iter()
+                .map(|cs| cs.column_type.clone())
+                .collect::<Vec<_>>();
+            let rw = writer.start(&mysql_schema).await?;
+            write_dataflow_rows(rw, rows, &mysql_schema, &column_types).await
+        }
+        noria_connector::QueryResult::SelectPages { pages, schema } => {
+            let mysql_schema = convert_columns!(schema.schema, writer);
+            let column_types = schema
+                .schema
+                .iter()
+                .map(|cs| cs.column_type.clone())
+                .collect::<Vec<_>>();
+            let rw = writer.start(&mysql_schema).await?;
+            write_dataflow_pages(rw, pages, &mysql_schema, &column_types).await
         }
     }
 }
@@ -421,14 +525,26 @@ where
         upstream::QueryResult::WriteResult {
             num_rows_affected,
             last_inserted_id,
+            warnings,
             status_flags,
+            session_state,
         } => {
-            write_query_results(
-                Ok((num_rows_affected, last_inserted_id)),
-                writer,
-                Some(status_flags),
-            )
-            .await
+            // Relay the changes to the session made by `SET`, `USE`, commits, etc. to clients
+            // that track them
+            let session_state = if session_state.is_empty() {
+                vec![]
+            } else {
+                vec![SessionStateChange::Encoded(session_state)]
+            };
+            writer
+                .completed_with_session_state(
+                    num_rows_affected,
+                    last_inserted_id,
+                    warnings,
+                    Some(status_flags),
+                    session_state,
+                )
+                .await
         }
         upstream::QueryResult::ReadResult {
             mut stream,
@@ -436,16 +552,20 @@ where
         } => {
             let formatted_cols = columns.iter().map(|c| c.into()).collect::<Vec<_>>();
             let mut rw = writer.start(&formatted_cols).await?;
-            while let Some(row) = stream.next().await {
-                let row = match row {
-                    Ok(row) => row,
-                    Err(err) => return handle_error!(Error::MySql(err), rw),
-                };
-
-                for (i, _) in row.columns_ref().iter().enumerate() {
-                    rw.write_col(row.as_ref(i).expect("Must match column number"))?;
-                }
-                rw.end_row().await?;
+            // Send rows to the client as they're read from upstream, stopping at the first error
+            let mut upstream_error = None;
+            let rows = (&mut stream).scan(&mut upstream_error, |upstream_error, row| {
+                future::ready(match row {
+                    Ok(row) => Some(row.unwrap()),
+                    Err(err) => {
+                        **upstream_error = Some(err);
+                        None
+                    }
+                })
+            });
+            rw.write_stream(rows).await?;
+            if let Some(err) = upstream_error {
+                return handle_error!(Error::MySql(err), rw);
             }
 
             if let Some(status_flags) = stream.status_flags() {
@@ -502,8 +622,8 @@ where
         info: StatementMetaWriter<'_, W>,
         schema_cache: &mut HashMap<u32, CachedSchema>,
     ) -> io::Result<()> {
-        if self.enable_statement_logging {
-            info!(target: "client_statement", "Prepare: {query}");
+        if Component::MySqlFrontend.is_enabled() {
+            info!(target: "client_statement", client = ?self.client(), "Prepare: {query}");
         }
         use noria_connector::PrepareResult::*;
 
@@ -550,11 +670,10 @@ where
                 info.reply(self.last_prepared_id(), &params, &schema).await
             }
 
-            Err(Error::MySql(mysql_async::Error::Server(mysql_async::ServerError {
-                code,
-                message,
-                ..
-            }))) => info.error(code.into(), message.as_bytes()).await,
+            Err(Error::MySql(mysql_async::Error::Server(e))) => {
+                info.error_with_sqlstate(e.code, &upstream_sqlstate(&e), e.message.as_bytes())
+                    .await
+            }
             Err(Error::MySql(mysql_async::Error::Driver(
                 mysql_async::DriverError::ConnectionClosed,
             ))) => {
@@ -595,9 +714,7 @@ where
         // derived directly from ParamParser.
         let params_result = params
             .into_iter()
-            .flat_map(|p| {
-                p.map(|pval| mysql_value_to_dataflow_value(pval.value).map_err(Error::from))
-            })
+            .map(|p| -> Result<DfValue, Error> { Ok(mysql_param_to_dataflow_value(p?)?) })
             .collect::<Result<Vec<DfValue>, Error>>();
 
         let value_params = match params_result {
@@ -610,12 +727,17 @@ where
             }
         };
 
-        if self.enable_statement_logging {
-            info!(target: "client_statement", "Execute: {{id: {id}, params: {:?}}}", value_params)
+        if Component::MySqlFrontend.is_enabled() {
+            info!(
+                target: "client_statement",
+                client = ?self.client(),
+                "Execute: {{id: {id}, params: {:?}}}",
+                value_params
+            )
         }
 
         match self.execute(id, &value_params).await {
-            Ok(QueryResult::Noria(noria_connector::QueryResult::Select { mut rows, schema })) => {
+            Ok(QueryResult::Noria(noria_connector::QueryResult::Select { rows, schema })) => {
                 let CachedSchema {
                     mysql_schema,
                     column_types,
@@ -642,27 +764,18 @@ where
                     }
                 };
 
-                let mut rw = results
+                let rw = results
                     .start_with_cache(mysql_schema, preencoded_schema.clone())
                     .await?;
-                while let Some(row) = rows.next() {
-                    for (c, ty, val) in izip!(mysql_schema.iter(), column_types.iter(), row.iter())
-                    {
-                        if let Err(e) = write_column(&mut rw, val, c, ty).await {
-                            return handle_column_write_err(e, rw).await;
-                        };
-                    }
-                    rw.end_row().await?;
-                }
-                rw.finish().await
+                write_dataflow_rows(rw, rows, mysql_schema, column_types).await
             }
             execute_result => handle_query_result(execute_result, results).await,
         }
     }
 
     async fn on_init(&mut self, database: &str, w: Option<InitWriter<'_, W>>) -> io::Result<()> {
-        if self.enable_statement_logging {
-            info!(target: "client_statement", "database: {database}");
+        if Component::MySqlFrontend.is_enabled() {
+            info!(target: "client_statement", client = ?self.client(), "database: {database}");
         }
         match self.set_database(database).await {
             Ok(()) => {
@@ -674,11 +787,7 @@ where
             }
             Err(e) => {
                 if let Some(w) = w {
-                    w.error(
-                        mysql_srv::ErrorKind::ER_UNKNOWN_ERROR,
-                        e.to_string().as_bytes(),
-                    )
-                    .await
+                    handle_error!(e, w)
                 } else {
                     Ok(())
                 }
@@ -686,11 +795,66 @@ where
         }
     }
 
+    async fn on_set_option(&mut self, multi_statements: bool) -> io::Result<()> {
+        self.noria.session_context_mut().multi_statements = Some(multi_statements);
+        Ok(())
+    }
+
+    async fn on_reset(&mut self) -> io::Result<()> {
+        let database = self.schema_search_path().first().cloned();
+        self.reset()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        // Resetting the upstream connection switches it back to the database it connected to, but
+        // the client's current database is kept
+        if let Some(database) = database {
+            self.set_database(&database)
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+        Ok(())
+    }
+
+    async fn on_client_found_rows(&mut self) -> io::Result<()> {
+        self.set_client_found_rows()
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    async fn on_connection_attributes(
+        &mut self,
+        attributes: &HashMap<String, String>,
+    ) -> io::Result<()> {
+        // Queries are attributed to the application if it set `program_name`, or otherwise to the
+        // client library
+        let client = attributes
+            .get("program_name")
+            .or_else(|| attributes.get("_client_name"))
+            .map(|client| client.as_str().into());
+        self.noria.set_client(client);
+        self.connection_attributes = attributes.clone();
+
+        if Component::MySqlFrontend.is_enabled() {
+            let attributes = attributes
+                .iter()
+                .sorted()
+                .map(|(name, value)| format!("{name}: {value:?}"))
+                .collect::<Vec<_>>();
+            info!(
+                target: "client_statement",
+                client = ?self.client(),
+                "Connection attributes: {{{}}}",
+                attributes.join(", ")
+            );
+        }
+        Ok(())
+    }
+
     async fn on_close(&mut self, _: u32) {}
 
     async fn on_query(&mut self, query: &str, results: QueryResultWriter<'_, W>) -> io::Result<()> {
-        if self.enable_statement_logging {
-            info!(target: "client_statement", "Query: {query}");
+        if Component::MySqlFrontend.is_enabled() {
+            info!(target: "client_statement", client = ?self.client(), "Query: {query}");
         }
         let query_result = self.query(query).await;
         handle_query_result(query_result, results).await
@@ -732,6 +896,10 @@ async fn handle_column_write_err<W: AsyncWrite + Unpin>(
                 "upstream connection closed",
             ))
         }
+        Error::MySql(mysql_async::Error::Server(e)) => {
+            rw.error_with_sqlstate(e.code, &upstream_sqlstate(&e), e.message.as_bytes())
+                .await
+        }
         _ => rw.error(e.error_kind(), e.to_string().as_bytes()).await,
     }
 }
@@ -1,2 +1,27 @@
 /// The default character set to use when writing out column packets.
 pub static DEFAULT_CHARACTER_SET: u16 = mysql_async::consts::UTF8_GENERAL_CI;
+
+/// Returns the ID of the MySQL collation named `name`, to write out in column packets in place of
+/// [`DEFAULT_CHARACTER_SET`], or `None` if it isn't one of the commonly used collations we know
+/// about.
+pub fn collation_id(name: &str) -> Option<u16> {
+    Some(match name.to_ascii_lowercase().as_str() {
+        "latin1_swedish_ci" => 8,
+        "ascii_general_ci" => 11,
+        "utf8_general_ci" | "utf8mb3_general_ci" => 33,
+        "utf8mb4_general_ci" => 45,
+        "utf8mb4_bin" => 46,
+        "latin1_bin" => 47,
+        "latin1_general_ci" => 48,
+        "binary" => 63,
+        "ascii_bin" => 65,
+        "utf8_bin" | "utf8mb3_bin" => 83,
+        "utf8_unicode_ci" | "utf8mb3_unicode_ci" => 192,
+        "utf8mb4_unicode_ci" => 224,
+        "utf8mb4_unicode_520_ci" => 246,
+        "utf8mb4_0900_ai_ci" => 255,
+        "utf8mb4_0900_as_cs" => 278,
+        "utf8mb4_0900_bin" => 309,
+        _ => return None,
+    })
+}
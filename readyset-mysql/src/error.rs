@@ -41,7 +41,8 @@ impl Error {
         }
          */
         match self {
-            Self::MySql(mysql_async::Error::Server(e)) => e.code.into(),
+            Self::MySql(mysql_async::Error::Server(e)) => mysql_srv::ErrorKind::from_code(e.code)
+                .unwrap_or(mysql_srv::ErrorKind::ER_UNKNOWN_ERROR),
             Self::MySql(_) => {
                 // TODO(peter): We need to translate these to appropriate
                 // mysql error codes. Currently mysql_async is only used by fallback.
@@ -52,6 +53,16 @@ impl Error {
     }
 }
 
+/// Returns the SQLSTATE of an error returned by the upstream database, so that it can be passed
+/// through to the client verbatim. Falls back to the SQLSTATE for the error's code if the upstream
+/// didn't send a valid one, or to `HY000` (which MySQL itself uses for any error without a more
+/// specific SQLSTATE) for codes newer than those in [`mysql_srv::ErrorKind`].
+pub(crate) fn upstream_sqlstate(e: &mysql_async::ServerError) -> [u8; 5] {
+    e.state.as_bytes().try_into().unwrap_or_else(|_| {
+        *mysql_srv::ErrorKind::from_code(e.code).map_or(b"HY000", |kind| kind.sqlstate())
+    })
+}
+
 impl IsFatalError for Error {
     fn is_fatal(&self) -> bool {
         matches!(self, Self::MySql(e) if e.is_fatal())
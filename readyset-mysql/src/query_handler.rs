@@ -1,12 +1,18 @@
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::str::FromStr;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
-use nom_sql::{Column, Expr, FieldDefinitionExpr, Literal, SqlIdentifier, SqlQuery, VariableScope};
+use nom_sql::{
+    Column, Expr, FieldDefinitionExpr, FunctionExpr, Literal, SqlIdentifier, SqlQuery,
+    VariableScope,
+};
 use readyset_adapter::backend::noria_connector::QueryResult;
 use readyset_adapter::backend::SelectSchema;
-use readyset_adapter::{QueryHandler, SetBehavior};
+use readyset_adapter::{
+    QueryHandler, SessionContext, SetBehavior, NOW_PARAMETER_GRANULARITY_VARIABLE,
+};
 use readyset_client::results::Results;
 use readyset_client::ColumnSchema;
 use readyset_data::{DfType, DfValue};
@@ -24,11 +30,14 @@ const REQUIRED_SQL_MODES: [SqlMode; 3] = [
 ];
 
 /// The list of mysql `SQL_MODE`s that *may* be set by a client (because they don't affect query
-/// semantics)
-const ALLOWED_SQL_MODES: [SqlMode; 11] = [
+/// semantics, or only change how queries are lexed, which we account for in
+/// [`normalize_quotes`])
+const ALLOWED_SQL_MODES: [SqlMode; 13] = [
+    SqlMode::AnsiQuotes,
     SqlMode::ErrorForDivisionByZero, // deprecated
     SqlMode::IgnoreSpace,            // TODO: I think this is fine, but I'm not 100% sure
     SqlMode::NoAutoValueOnZero,
+    SqlMode::NoBackslashEscapes,
     SqlMode::NoDirInCreate,
     SqlMode::NoEngineSubstitution,
     SqlMode::NoZeroDate,
@@ -108,6 +117,94 @@ fn raw_sql_modes_to_list(sql_modes: &str) -> Result<Vec<SqlMode>, ReadySetError>
         .collect::<Result<Vec<SqlMode>, ReadySetError>>()
 }
 
+/// Rewrite `query`, written for a session with the `ANSI_QUOTES` and/or `NO_BACKSLASH_ESCAPES`
+/// SQL modes enabled, into the default MySQL syntax our parser expects.
+///
+/// With `ANSI_QUOTES`, double quotes delimit identifiers rather than strings, so double-quoted
+/// identifiers are quoted with backticks instead. With `NO_BACKSLASH_ESCAPES`, backslashes in
+/// string literals are ordinary characters, so they're escaped. Comments and backtick-quoted
+/// identifiers are left untouched.
+fn normalize_quotes(query: &str, ansi_quotes: bool, no_backslash_escapes: bool) -> Cow<'_, str> {
+    if !ansi_quotes && !no_backslash_escapes {
+        return Cow::Borrowed(query);
+    }
+
+    /// Returns the length of the prefix of `s` up to and including the first occurrence of `end`
+    /// after `start`, or the length of `s` if there isn't one
+    fn len_through(s: &str, start: usize, end: &str) -> usize {
+        s[start..]
+            .find(end)
+            .map_or(s.len(), |i| start + i + end.len())
+    }
+
+    let mut res = String::with_capacity(query.len());
+    let mut rest = query;
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("/*") {
+            len_through(rest, 2, "*/")
+        } else if c == '#' || rest.starts_with("--") {
+            len_through(rest, 1, "\n")
+        } else if c == '`' {
+            len_through(rest, 1, "`")
+        } else if c == '\'' || c == '"' {
+            let Some((token, len)) =
+                normalize_quoted(rest, ansi_quotes && c == '"', no_backslash_escapes)
+            else {
+                // An unterminated string or identifier is a syntax error either way
+                res.push_str(rest);
+                break;
+            };
+            res.push_str(&token);
+            rest = &rest[len..];
+            continue;
+        } else {
+            c.len_utf8()
+        };
+        res.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    Cow::Owned(res)
+}
+
+/// Rewrite the quoted string literal (or identifier, if `is_identifier`) at the start of `s` for
+/// [`normalize_quotes`], returning it along with its length in `s`, or `None` if it's unterminated
+fn normalize_quoted(
+    s: &str,
+    is_identifier: bool,
+    no_backslash_escapes: bool,
+) -> Option<(String, usize)> {
+    let mut chars = s.char_indices().peekable();
+    let (_, quote) = chars.next()?;
+    let delimiter = if is_identifier { '`' } else { quote };
+    let mut res = String::from(delimiter);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '`' if is_identifier => res.push_str("``"),
+            '\\' if !is_identifier && no_backslash_escapes => res.push_str("\\\\"),
+            '\\' if !is_identifier => {
+                res.push(c);
+                if let Some((_, escaped)) = chars.next() {
+                    res.push(escaped);
+                }
+            }
+            // A doubled quote stands for a single quote
+            _ if c == quote && chars.next_if(|(_, c)| *c == quote).is_some() => {
+                res.push(quote);
+                if !is_identifier {
+                    res.push(quote);
+                }
+            }
+            _ if c == quote => {
+                res.push(delimiter);
+                return Some((res, i + 1));
+            }
+            _ => res.push(c),
+        }
+    }
+    None
+}
+
 lazy_static! {
     /// The set of parameters that we can safely proxy upstream with *any* value, as we've
     /// determined that they don't change the semantics of queries in a way that would matter for us
@@ -813,15 +910,29 @@ lazy_static! {
     ]);
 }
 
+/// Returns true if `expr` calls `FOUND_ROWS()`
+fn calls_found_rows(expr: &Expr) -> bool {
+    match expr {
+        Expr::Call(FunctionExpr::Call { name, .. }) if name.eq_ignore_ascii_case("found_rows") => {
+            true
+        }
+        _ => expr.recursive_subexpressions().any(calls_found_rows),
+    }
+}
+
 /// MySQL flavor of [`QueryHandler`].
 pub struct MySqlQueryHandler;
 
 impl QueryHandler for MySqlQueryHandler {
     fn requires_fallback(query: &SqlQuery) -> bool {
-        // Currently any query with variables requires a fallback
+        // Currently any query with variables requires a fallback, as do queries calling
+        // `FOUND_ROWS()`, which must run on the upstream connection that ran the preceding
+        // `SQL_CALC_FOUND_ROWS` query (which ReadySet can't parse, so always proxies)
         match query {
             SqlQuery::Select(stmt) => stmt.fields.iter().any(|field| match field {
-                FieldDefinitionExpr::Expr { expr, .. } => expr.contains_vars(),
+                FieldDefinitionExpr::Expr { expr, .. } => {
+                    expr.contains_vars() || calls_found_rows(expr)
+                }
                 _ => false,
             }),
             _ => false,
@@ -877,6 +988,24 @@ impl QueryHandler for MySqlQueryHandler {
 
         match stmt {
             nom_sql::SetStatement::Variable(set) => {
+                if let Some((_, value)) = set.variables.iter().find(|(var, _)| {
+                    matches!(var.scope, VariableScope::Local | VariableScope::Session)
+                        && var
+                            .name
+                            .eq_ignore_ascii_case(NOW_PARAMETER_GRANULARITY_VARIABLE)
+                }) {
+                    // The variable doesn't exist upstream, so it can't be set along with others
+                    if set.variables.len() > 1 {
+                        return Unsupported;
+                    }
+                    let granularity = match value {
+                        Expr::Literal(Literal::UnsignedInteger(secs)) => Some(*secs),
+                        Expr::Literal(Literal::Integer(secs)) => u64::try_from(*secs).ok(),
+                        _ => None,
+                    };
+                    return SetNowParameterGranularity(granularity.map(Duration::from_secs));
+                }
+
                 if let Some(val) = set.variables.iter().find_map(|(var, val)| {
                     if var.name.as_str().eq_ignore_ascii_case("autocommit") {
                         Some(val)
@@ -884,9 +1013,7 @@ impl QueryHandler for MySqlQueryHandler {
                         None
                     }
                 }) {
-                    return SetAutocommit(
-                        matches!(val, Expr::Literal(Literal::UnsignedInteger(i)) if *i == 1),
-                    );
+                    return SetAutocommit(is_autocommit_on(val));
                 }
 
                 SetBehavior::proxy_if(set.variables.iter().all(|(variable, value)| {
@@ -933,6 +1060,46 @@ impl QueryHandler for MySqlQueryHandler {
             nom_sql::SetStatement::PostgresParameter(_) => Unsupported,
         }
     }
+
+    fn update_session_context(stmt: &nom_sql::SetStatement, context: &mut SessionContext) {
+        let nom_sql::SetStatement::Variable(set) = stmt else {
+            return;
+        };
+
+        for (variable, value) in &set.variables {
+            if matches!(variable.scope, VariableScope::User | VariableScope::Global) {
+                continue;
+            }
+            let string_value = match value {
+                Expr::Literal(Literal::String(s)) => Some(s.clone()),
+                _ => None,
+            };
+            match variable.name.to_ascii_lowercase().as_str() {
+                "sql_mode" => context.sql_mode = string_value,
+                "time_zone" => context.time_zone = string_value,
+                "autocommit" => context.autocommit = Some(is_autocommit_on(value)),
+                _ => {}
+            }
+        }
+    }
+
+    fn normalize_query_text<'a>(query: &'a str, context: &SessionContext) -> Cow<'a, str> {
+        let sql_modes = context
+            .sql_mode
+            .as_deref()
+            .and_then(|sql_mode| raw_sql_modes_to_list(sql_mode).ok())
+            .unwrap_or_default();
+        normalize_quotes(
+            query,
+            sql_modes.contains(&SqlMode::AnsiQuotes),
+            sql_modes.contains(&SqlMode::NoBackslashEscapes),
+        )
+    }
+}
+
+/// Returns true if `value`, assigned to the `autocommit` variable, turns autocommit on
+fn is_autocommit_on(value: &Expr) -> bool {
+    matches!(value, Expr::Literal(Literal::UnsignedInteger(i)) if *i == 1)
 }
 
 #[cfg(test)]
@@ -977,6 +1144,149 @@ mod tests {
         );
     }
 
+    #[test]
+    fn set_now_parameter_granularity() {
+        let set = |value| {
+            SetStatement::Variable(SetVariables {
+                variables: vec![(
+                    Variable {
+                        scope: VariableScope::Local,
+                        name: "readyset_now_parameter_granularity".into(),
+                    },
+                    value,
+                )],
+            })
+        };
+        assert_eq!(
+            MySqlQueryHandler::handle_set_statement(&set(Expr::Literal(Literal::UnsignedInteger(
+                60
+            )))),
+            SetBehavior::SetNowParameterGranularity(Some(Duration::from_secs(60)))
+        );
+        assert_eq!(
+            MySqlQueryHandler::handle_set_statement(&set(Expr::Column("OFF".into()))),
+            SetBehavior::SetNowParameterGranularity(None)
+        );
+    }
+
+    #[test]
+    fn update_session_context() {
+        let stmt = SetStatement::Variable(SetVariables {
+            variables: vec![
+                (
+                    Variable {
+                        scope: VariableScope::Session,
+                        name: "sql_mode".into(),
+                    },
+                    Expr::Literal(Literal::from("ANSI_QUOTES")),
+                ),
+                (
+                    Variable {
+                        scope: VariableScope::Local,
+                        name: "TIME_ZONE".into(),
+                    },
+                    Expr::Literal(Literal::from("+00:00")),
+                ),
+                (
+                    Variable {
+                        scope: VariableScope::Global,
+                        name: "autocommit".into(),
+                    },
+                    Expr::Literal(Literal::UnsignedInteger(0)),
+                ),
+            ],
+        });
+        let mut context = SessionContext::default();
+        MySqlQueryHandler::update_session_context(&stmt, &mut context);
+        assert_eq!(
+            context,
+            SessionContext {
+                sql_mode: Some("ANSI_QUOTES".into()),
+                time_zone: Some("+00:00".into()),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn supported_sql_mode_ansi_quotes() {
+        let m = "NO_ZERO_DATE,ONLY_FULL_GROUP_BY,NO_ZERO_IN_DATE,ANSI_QUOTES,NO_BACKSLASH_ESCAPES";
+        let stmt = SetStatement::Variable(SetVariables {
+            variables: vec![(
+                Variable {
+                    scope: VariableScope::Session,
+                    name: "sql_mode".into(),
+                },
+                Expr::Literal(Literal::from(m)),
+            )],
+        });
+        assert_eq!(
+            MySqlQueryHandler::handle_set_statement(&stmt),
+            SetBehavior::Proxy
+        );
+    }
+
+    #[test]
+    fn normalize_ansi_quotes() {
+        assert_eq!(
+            normalize_quotes(r#"SELECT "x", 'y"z' FROM "t""t" -- "c""#, true, false),
+            r#"SELECT `x`, 'y"z' FROM `t"t` -- "c""#
+        );
+        assert_eq!(
+            normalize_quotes(
+                r#"SELECT `"x"` /* "y" */ FROM t WHERE a = 'b\'c'"#,
+                true,
+                false
+            ),
+            r#"SELECT `"x"` /* "y" */ FROM t WHERE a = 'b\'c'"#
+        );
+    }
+
+    #[test]
+    fn normalize_no_backslash_escapes() {
+        assert_eq!(
+            normalize_quotes(r#"SELECT 'a\b', "c\'d", 'e''f' FROM t"#, false, true),
+            r#"SELECT 'a\\b', "c\\'d", 'e''f' FROM t"#
+        );
+        assert_eq!(
+            normalize_quotes(r#"SELECT 'a\b' FROM t"#, false, false),
+            r#"SELECT 'a\b' FROM t"#
+        );
+    }
+
+    #[test]
+    fn normalize_query_text_uses_sql_mode() {
+        let context = SessionContext {
+            sql_mode: Some("ANSI_QUOTES,ONLY_FULL_GROUP_BY".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            MySqlQueryHandler::normalize_query_text(r#"SELECT "x" FROM "t""#, &context),
+            "SELECT `x` FROM `t`"
+        );
+        assert_eq!(
+            MySqlQueryHandler::normalize_query_text(
+                r#"SELECT "x" FROM "t""#,
+                &SessionContext::default()
+            ),
+            r#"SELECT "x" FROM "t""#
+        );
+    }
+
+    #[test]
+    fn found_rows_requires_fallback() {
+        let query = |q: &str| nom_sql::parse_query(nom_sql::Dialect::MySQL, q).unwrap();
+        assert!(MySqlQueryHandler::requires_fallback(&query(
+            "SELECT FOUND_ROWS()"
+        )));
+        assert!(MySqlQueryHandler::requires_fallback(&query(
+            "SELECT found_rows() + 1 AS total"
+        )));
+        assert!(!MySqlQueryHandler::requires_fallback(&query(
+            "SELECT id FROM found_rows"
+        )));
+    }
+
     #[test]
     fn all_required_sql_modes_are_allowed() {
         for mode in REQUIRED_SQL_MODES {
@@ -5,7 +5,7 @@ use readyset_client::ColumnSchema;
 use readyset_data::DfType;
 use readyset_errors::{unsupported, ReadySetResult};
 
-use crate::constants::DEFAULT_CHARACTER_SET;
+use crate::constants::{collation_id, DEFAULT_CHARACTER_SET};
 
 /// Checks if `c1` is a subtype of `c2`.
 pub(crate) fn is_subtype(c1: mysql_srv::ColumnType, c2: mysql_srv::ColumnType) -> bool {
@@ -135,6 +135,15 @@ pub(crate) fn convert_column(col: &ColumnSchema) -> ReadySetResult<mysql_srv::Co
         _ => None,
     };
 
+    // Only string columns have a collation in MySQL
+    let character_set = match col.column_type {
+        DfType::Text(_) | DfType::VarChar(..) | DfType::Char(..) | DfType::Enum { .. } => col
+            .collation()
+            .and_then(collation_id)
+            .unwrap_or(DEFAULT_CHARACTER_SET),
+        _ => DEFAULT_CHARACTER_SET,
+    };
+
     Ok(mysql_srv::Column {
         table: col
             .column
@@ -150,7 +159,7 @@ pub(crate) fn convert_column(col: &ColumnSchema) -> ReadySetResult<mysql_srv::Co
         coltype,
         column_length,
         colflags,
-        character_set: DEFAULT_CHARACTER_SET,
+        character_set,
     })
 }
 
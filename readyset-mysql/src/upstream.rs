@@ -4,24 +4,26 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 #[cfg(feature = "fallback_cache")]
 use std::hash::{Hash, Hasher};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{ready, Context, Poll};
 
 use async_trait::async_trait;
-use futures_util::Stream;
-#[cfg(feature = "fallback_cache")]
-use futures_util::StreamExt;
+use futures_util::{Stream, StreamExt};
 use mysql_async::consts::{CapabilityFlags, StatusFlags};
 use mysql_async::prelude::Queryable;
 use mysql_async::{
     Column, Conn, Opts, OptsBuilder, ResultSetStream, Row, SslOpts, TxOpts, UrlError,
 };
-use nom_sql::SqlIdentifier;
+use nom_sql::{Relation, SqlIdentifier};
 use pin_project::pin_project;
 use readyset_adapter::fallback_cache::FallbackCache;
 #[cfg(feature = "fallback_cache")]
 use readyset_adapter::fallback_cache::FallbackCacheApi;
+use readyset_adapter::result_cache::{ResultCache, ResultCacheKey};
 use readyset_adapter::upstream_database::{NoriaCompare, UpstreamDestination};
 use readyset_adapter::{UpstreamConfig, UpstreamDatabase, UpstreamPrepare};
+use readyset_client::replication::ReplicationOffset;
 use readyset_client::ColumnSchema;
 use readyset_client_metrics::QueryDestination;
 use readyset_data::DfValue;
@@ -46,6 +48,65 @@ fn dt_to_value_params(dt: &[DfValue]) -> ReadySetResult<Vec<mysql_async::Value>>
 pub enum ReadResultStream<'a> {
     Text(#[pin] ResultSetStream<'a, 'a, 'static, Row, mysql_async::TextProtocol>),
     Binary(#[pin] ResultSetStream<'a, 'a, 'static, Row, mysql_async::BinaryProtocol>),
+    Caching(#[pin] CachingStream<'a>),
+}
+
+/// A [`ReadResultStream`] for the results of a query whose rows are stored in a [`ResultCache`]
+/// once they've all been streamed to the client. Rows stop being buffered as soon as they'd take
+/// up more memory than the cache can hold, or if the upstream returns an error.
+#[pin_project]
+#[derive(Debug)]
+pub struct CachingStream<'a> {
+    inner: Pin<Box<ReadResultStream<'a>>>,
+    columns: Arc<[Column]>,
+    cache: ResultCache<CachedReadResult>,
+    key: ResultCacheKey,
+    tables: Vec<Relation>,
+    /// The [`epoch`](ResultCache::epoch) of the cache from before the query was executed
+    epoch: u64,
+    /// The rows read so far, or `None` if they won't be cached
+    rows: Option<Vec<Row>>,
+    /// The estimated number of bytes taken up by `rows`
+    size_bytes: usize,
+    /// The [`max_bytes`](ResultCache::max_bytes) of the cache
+    max_bytes: usize,
+}
+
+impl<'a> Stream for CachingStream<'a> {
+    type Item = Result<Row, mysql_async::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let next = ready!(this.inner.as_mut().poll_next(cx));
+        match &next {
+            Some(Ok(row)) => {
+                *this.size_bytes += row_size_bytes(row);
+                if *this.size_bytes > *this.max_bytes {
+                    *this.rows = None;
+                } else if let Some(rows) = this.rows {
+                    rows.push(row.clone());
+                }
+            }
+            Some(Err(_)) => *this.rows = None,
+            None => {
+                if let Some(rows) = this.rows.take() {
+                    let result = CachedReadResult {
+                        data: rows,
+                        columns: this.columns.clone(),
+                        status_flags: this.inner.status_flags(),
+                    };
+                    this.cache.insert(
+                        this.key.clone(),
+                        this.tables,
+                        *this.epoch,
+                        result,
+                        *this.size_bytes,
+                    );
+                }
+            }
+        }
+        Poll::Ready(next)
+    }
 }
 
 impl<'a> From<ResultSetStream<'a, 'a, 'static, Row, mysql_async::TextProtocol>>
@@ -69,7 +130,12 @@ pub enum QueryResult<'a> {
     WriteResult {
         num_rows_affected: u64,
         last_inserted_id: u64,
+        /// The number of warnings the upstream server reported for the query
+        warnings: u16,
         status_flags: StatusFlags,
+        /// The changes to the state of the session the upstream server reported for the query,
+        /// encoded as the session state info of its OK packet
+        session_state: Vec<u8>,
     },
     ReadResult {
         stream: ReadResultStream<'a>,
@@ -104,13 +170,22 @@ pub struct CachedReadResult {
     pub status_flags: Option<StatusFlags>,
 }
 
+/// Returns an estimate of the number of bytes of memory taken up by `row`
+fn row_size_bytes(row: &Row) -> usize {
+    (0..row.len())
+        .map(|i| match row.as_ref(i) {
+            Some(mysql_async::Value::Bytes(bytes)) => bytes.len(),
+            _ => std::mem::size_of::<mysql_async::Value>(),
+        })
+        .sum()
+}
+
 impl<'a> From<CachedReadResult> for QueryResult<'a> {
     fn from(r: CachedReadResult) -> Self {
         QueryResult::CachedReadResult(r)
     }
 }
 
-#[cfg(feature = "fallback_cache")]
 impl<'a> QueryResult<'a> {
     /// Can convert a stream ReadResult into a CachedReadResult.
     async fn async_try_into(self) -> Result<CachedReadResult, Error> {
@@ -121,16 +196,8 @@ impl<'a> QueryResult<'a> {
             } => {
                 let mut rows = vec![];
                 while let Some(row) = stream.next().await {
-                    match row {
-                        Ok(row) => rows.push(row),
-                        Err(e) => {
-                            // TODO: Update to be more sophisticated than this hack.
-                            return Err(Error::ReadySet(internal_err!(
-                                "Found error from MySQL rather than row {}",
-                                e
-                            )));
-                        }
-                    }
+                    // Keep the upstream's error, so its error code is returned to the client
+                    rows.push(row.map_err(Error::MySql)?);
                 }
                 let status_flags = stream.status_flags();
                 Ok(CachedReadResult {
@@ -147,8 +214,17 @@ impl<'a> QueryResult<'a> {
 }
 
 /// A connector to an underlying mysql store. This is really just a wrapper for the mysql crate.
+///
+/// Capabilities can only be negotiated when connecting, so once the client has connected to us
+/// with capabilities the connection to the upstream wasn't opened with (see
+/// [`UpstreamDatabase::set_client_found_rows`]), that connection is closed and reopened when it's
+/// next used.
 pub struct MySqlUpstream {
-    conn: Conn,
+    /// The connection to the upstream server, if it's open
+    /// The options to open the connection with
+    opts: Opts,
+    /// The upstream server's version, as `(major, minor, patch)`
+    server_version: (u16, u16, u16),
     prepared_statements: HashMap<StatementID, mysql_async::Statement>,
     upstream_config: UpstreamConfig,
     #[cfg(feature = "fallback_cache")]
@@ -189,13 +265,11 @@ fn schema_column_match(schema: &[ColumnSchema], columns: &[Column]) -> Result<()
 impl<'a> Stream for ReadResultStream<'a> {
     type Item = Result<Row, mysql_async::Error>;
 
-    fn poll_next(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Option<Self::Item>> {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         match self.project() {
             ReadResultStreamProj::Text(s) => s.poll_next(cx),
             ReadResultStreamProj::Binary(s) => s.poll_next(cx),
+            ReadResultStreamProj::Caching(s) => s.poll_next(cx),
         }
     }
 }
@@ -205,6 +279,7 @@ impl<'a> ReadResultStream<'a> {
         match self {
             ReadResultStream::Text(s) => s.ok_packet().map(|o| o.status_flags()),
             ReadResultStream::Binary(s) => s.ok_packet().map(|o| o.status_flags()),
+            ReadResultStream::Caching(s) => s.inner.status_flags(),
         }
     }
 }
@@ -248,17 +323,20 @@ macro_rules! handle_query_result {
                 ReadySetError::Internal("The mysql_async result has no resultsets".to_string())
             })?;
 
+            let ok_packet = resultset.ok_packet().ok_or_else(|| {
+                ReadySetError::Internal("The mysql_async result has no ok packet".to_string())
+            })?;
+
             Ok(QueryResult::WriteResult {
                 num_rows_affected: resultset.affected_rows(),
-                last_inserted_id: resultset.last_insert_id().unwrap_or(1),
-                status_flags: resultset
-                    .ok_packet()
-                    .ok_or_else(|| {
-                        ReadySetError::Internal(
-                            "The mysql_async result has no ok packet".to_string(),
-                        )
-                    })?
-                    .status_flags(),
+                // The upstream reports a last insert ID of 0 for queries that didn't generate one
+                last_inserted_id: resultset.last_insert_id().unwrap_or(0),
+                warnings: ok_packet.warnings(),
+                status_flags: ok_packet.status_flags(),
+                session_state: ok_packet
+                    .session_state_info_ref()
+                    .map(<[u8]>::to_vec)
+                    .unwrap_or_default(),
             })
         }
     }};
@@ -269,14 +347,16 @@ impl MySqlUpstream {
         upstream_config: UpstreamConfig,
     ) -> Result<
         (
-            Conn,
+            Option<Conn>,
+            Opts,
+            (u16, u16, u16),
             HashMap<StatementID, mysql_async::Statement>,
             UpstreamConfig,
         ),
         Error,
     > {
-        // CLIENT_SESSION_TRACK is required for GTID information to be sent in OK packets on commits
-        // GTID information is used for RYW
+        // CLIENT_SESSION_TRACK is required for changes to the session state to be sent in OK
+        // packets, which are relayed to clients that track them. GTIDs of commits are used for RYW.
         let url = upstream_config
             .upstream_db_url
             .as_deref()
@@ -290,38 +370,57 @@ impl MySqlUpstream {
             opts = OptsBuilder::from_opts(opts).ssl_opts(ssl_opts).into();
         }
 
-        let span = info_span!(
-            "Connecting to MySQL upstream",
-            host = %opts.ip_or_hostname(),
-            port = %opts.tcp_port(),
-            user = %opts.user().unwrap_or("<NO USER>"),
-        );
-        span.in_scope(|| info!("Establishing connection"));
-        let conn = if cfg!(feature = "ryw") {
-            Conn::new(
-                OptsBuilder::from_opts(opts).add_capability(CapabilityFlags::CLIENT_SESSION_TRACK),
-            )
-            .instrument(span.clone())
-            .await?
-        } else {
-            Conn::new(OptsBuilder::from_opts(opts))
-                .instrument(span.clone())
-                .await?
-        };
-
-        // Check that the server version is supported.
-        let (major, minor, _) = conn.server_version();
-        if major < MIN_UPSTREAM_VERSION {
-            return Err(Error::ReadySet(ReadySetError::UnsupportedServerVersion {
-                major,
-                minor: minor.to_string(),
-                min: MIN_UPSTREAM_VERSION,
-            }));
-        }
+        opts = OptsBuilder::from_opts(opts)
+            .add_capability(CapabilityFlags::CLIENT_SESSION_TRACK)
+            .into();
 
-        span.in_scope(|| info!("Established connection to upstream"));
+        let mut conn = None;
+        let server_version = open(&mut conn, &opts).await?.server_version();
         let prepared_statements = HashMap::new();
-        Ok((conn, prepared_statements, upstream_config))
+
+        Ok((
+            conn,
+            opts,
+            server_version,
+            prepared_statements,
+            upstream_config,
+        ))
+    }
+
+    /// Returns the connection to the upstream server, opening it if it hasn't been yet
+    async fn conn(&mut self) -> Result<&mut Conn, Error> {
+        open(&mut self.conn, &self.opts).await
+    }
+}
+
+/// Returns the connection in `conn`, opening it with `opts` first if it hasn't been yet. Returns an
+/// error if the server a new connection is opened to has a version we don't support.
+async fn open<'a>(conn: &'a mut Option<Conn>, opts: &Opts) -> Result<&'a mut Conn, Error> {
+    match conn {
+        Some(conn) => Ok(conn),
+        None => {
+            let span = info_span!(
+                "Connecting to MySQL upstream",
+                host = %opts.ip_or_hostname(),
+                port = %opts.tcp_port(),
+                user = %opts.user().unwrap_or("<NO USER>"),
+            );
+            span.in_scope(|| info!("Establishing connection"));
+            let new_conn = Conn::new(opts.clone()).instrument(span.clone()).await?;
+
+            // Check that the server version is supported.
+            let (major, minor, _) = new_conn.server_version();
+            if major < MIN_UPSTREAM_VERSION {
+                return Err(Error::ReadySet(ReadySetError::UnsupportedServerVersion {
+                    major,
+                    minor: minor.to_string(),
+                    min: MIN_UPSTREAM_VERSION,
+                }));
+            }
+
+            span.in_scope(|| info!("Established connection to upstream"));
+            Ok(conn.insert(new_conn))
+        }
     }
 }
 
@@ -338,10 +437,12 @@ impl UpstreamDatabase for MySqlUpstream {
         upstream_config: UpstreamConfig,
         fallback_cache: Option<FallbackCache<Self::CachedReadResult>>,
     ) -> Result<Self, Error> {
-        let (conn, prepared_statements, upstream_config) =
+        let (conn, opts, server_version, prepared_statements, upstream_config) =
             Self::connect_inner(upstream_config).await?;
         Ok(Self {
             conn,
+            opts,
+            server_version,
             prepared_statements,
             upstream_config,
             fallback_cache,
@@ -353,10 +454,12 @@ impl UpstreamDatabase for MySqlUpstream {
         upstream_config: UpstreamConfig,
         _: Option<FallbackCache<Self::CachedReadResult>>,
     ) -> Result<Self, Error> {
-        let (conn, prepared_statements, upstream_config) =
+        let (conn, opts, server_version, prepared_statements, upstream_config) =
             Self::connect_inner(upstream_config).await?;
         Ok(Self {
             conn,
+            opts,
+            server_version,
             prepared_statements,
             upstream_config,
         })
@@ -371,7 +474,7 @@ impl UpstreamDatabase for MySqlUpstream {
     }
 
     fn database(&self) -> Option<&str> {
-        self.conn.opts().db_name()
+        self.opts.db_name()
     }
 
     fn version(&self) -> String {
@@ -379,50 +482,35 @@ impl UpstreamDatabase for MySqlUpstream {
         // clients will interpret the version numbers and use that to dictate which dialect they
         // send us. Anything after the version can be any text we desire. Additionally, the version
         // string must be null terminated.
-        let (major, minor, patch) = self.conn.server_version();
+        let (major, minor, patch) = self.server_version;
         format!("{major}.{minor}.{patch}-readyset\0")
     }
 
-    #[cfg(feature = "fallback_cache")]
     async fn reset(&mut self) -> Result<(), Error> {
-        let opts = self.conn.opts().clone();
-        let conn = Conn::new(opts).await?;
-        let prepared_statements = HashMap::new();
-        let upstream_config = self.upstream_config.clone();
-        let fallback_cache = if let Some(ref cache) = self.fallback_cache {
-            cache.clear().await;
-            Some(cache.clone())
-        } else {
-            None
+        let Some(conn) = self.conn.as_mut() else {
+            return Ok(());
         };
-        let old_self = std::mem::replace(
-            self,
-            Self {
-                conn,
-                prepared_statements,
-                upstream_config,
-                fallback_cache,
-            },
-        );
-        let _ = old_self.conn.disconnect().await as Result<(), _>;
+        self.prepared_statements.clear();
+        // Servers too old to support `COM_RESET_CONNECTION` get a new connection instead, which is
+        // opened when it's next used
+        if !conn.reset().await? {
+            if let Some(conn) = self.conn.take() {
+                let _ = conn.disconnect().await as Result<(), _>;
+            }
+        }
         Ok(())
     }
 
-    #[cfg(not(feature = "fallback_cache"))]
-    async fn reset(&mut self) -> Result<(), Error> {
-        let opts = self.conn.opts().clone();
-        let conn = Conn::new(opts).await?;
-        let prepared_statements = HashMap::new();
-        let upstream_config = self.upstream_config.clone();
-        let old_self = std::mem::replace(
-            self,
-            Self {
-                conn,
-                prepared_statements,
-                upstream_config,
-            },
-        );
-        let _ = old_self.conn.disconnect().await as Result<(), _>;
+    async fn set_client_found_rows(&mut self) -> Result<(), Error> {
+        self.opts = OptsBuilder::from_opts(self.opts.clone())
+            .add_capability(CapabilityFlags::CLIENT_FOUND_ROWS)
+            .into();
+        // Capabilities can only be negotiated when connecting, so the connection is reopened when
+        // it's next used
+        if let Some(conn) = self.conn.take() {
+            self.prepared_statements.clear();
+            let _ = conn.disconnect().await as Result<(), _>;
+        }
         Ok(())
     }
 
@@ -432,7 +520,7 @@ impl UpstreamDatabase for MySqlUpstream {
     where
         S: AsRef<str> + Send + Sync + 'a,
     {
-        let statement = self.conn.prep(query).await?;
+        let statement = self.conn().await?.prep(query).await?;
         self.prepared_statements
             .insert(statement.id(), statement.clone());
         Ok(UpstreamPrepare {
@@ -458,8 +546,8 @@ impl UpstreamDatabase for MySqlUpstream {
                 return Ok(query_r.into());
             }
             let params = dt_to_value_params(params)?;
-            let result = self
-                .conn
+            let result = open(&mut self.conn, &self.opts)
+                .await?
                 .exec_iter(
                     self.prepared_statements.get(&id).ok_or(Error::ReadySet(
                         ReadySetError::PreparedStatementMissing { statement_id: id },
@@ -478,8 +566,8 @@ impl UpstreamDatabase for MySqlUpstream {
             }
         } else {
             let params = dt_to_value_params(params)?;
-            let result = self
-                .conn
+            let result = open(&mut self.conn, &self.opts)
+                .await?
                 .exec_iter(
                     self.prepared_statements.get(&id).ok_or(Error::ReadySet(
                         ReadySetError::PreparedStatementMissing { statement_id: id },
@@ -498,8 +586,8 @@ impl UpstreamDatabase for MySqlUpstream {
         params: &[DfValue],
     ) -> Result<Self::QueryResult<'a>, Error> {
         let params = dt_to_value_params(params)?;
-        let result = self
-            .conn
+        let result = open(&mut self.conn, &self.opts)
+            .await?
             .exec_iter(
                 self.prepared_statements.get(&id).ok_or(Error::ReadySet(
                     ReadySetError::PreparedStatementMissing { statement_id: id },
@@ -520,7 +608,10 @@ impl UpstreamDatabase for MySqlUpstream {
                 return Ok(query_r.into());
             }
             let query_str = query.as_ref().to_owned();
-            let result = self.conn.query_iter(query).await?;
+            let result = open(&mut self.conn, &self.opts)
+                .await?
+                .query_iter(query)
+                .await?;
             let r = handle_query_result!(result);
             match r {
                 Ok(query_result @ QueryResult::ReadResult { .. }) => {
@@ -531,7 +622,7 @@ impl UpstreamDatabase for MySqlUpstream {
                 _ => r,
             }
         } else {
-            let result = self.conn.query_iter(query).await?;
+            let result = self.conn().await?.query_iter(query).await?;
             handle_query_result!(result)
         }
     }
@@ -541,10 +632,48 @@ impl UpstreamDatabase for MySqlUpstream {
     where
         S: AsRef<str> + Send + Sync + 'a,
     {
-        let result = self.conn.query_iter(query).await?;
+        let result = self.conn().await?.query_iter(query).await?;
         handle_query_result!(result)
     }
 
+    async fn query_with_result_cache<'a>(
+        &'a mut self,
+        query: &'a str,
+        cache: &ResultCache<CachedReadResult>,
+        key: ResultCacheKey,
+        tables: &[Relation],
+    ) -> Result<(Self::QueryResult<'a>, QueryDestination), Error> {
+        // The upstream checked that the user we're connected as can read the results, so they're
+        // only read from the cache by the same user
+        let key = key.for_user(self.opts.user());
+        if let Some(cached_result) = cache.get(&key) {
+            return Ok((cached_result.into(), QueryDestination::ResultCache));
+        }
+
+        let epoch = cache.epoch();
+        let result = self.conn().await?.query_iter(query).await?;
+        match handle_query_result!(result) {
+            Ok(QueryResult::ReadResult { stream, columns }) => {
+                let stream = ReadResultStream::Caching(CachingStream {
+                    inner: Box::pin(stream),
+                    columns: columns.clone(),
+                    cache: cache.clone(),
+                    key,
+                    tables: tables.to_vec(),
+                    epoch,
+                    rows: Some(vec![]),
+                    size_bytes: 0,
+                    max_bytes: cache.max_bytes(),
+                });
+                Ok((
+                    QueryResult::ReadResult { stream, columns },
+                    QueryDestination::Upstream,
+                ))
+            }
+            r => r.map(|r| (r, QueryDestination::Upstream)),
+        }
+    }
+
     /// Executes the given query on the mysql backend.
     async fn handle_ryw_write<'a, S>(
         &'a mut self,
@@ -553,7 +682,11 @@ impl UpstreamDatabase for MySqlUpstream {
     where
         S: AsRef<str> + Send + Sync + 'a,
     {
-        let mut transaction = self.conn.start_transaction(TxOpts::default()).await?;
+        let mut transaction = self
+            .conn()
+            .await?
+            .start_transaction(TxOpts::default())
+            .await?;
         transaction.query_drop(query).await.map_err(|e| {
             error!("Could not execute query in mysql : {:?}", e);
             e
@@ -561,6 +694,7 @@ impl UpstreamDatabase for MySqlUpstream {
 
         let affected_rows = transaction.affected_rows();
         let last_insert_id = transaction.last_insert_id();
+        let warnings = transaction.get_warnings();
         let status_flags = transaction.status();
         let txid = transaction.commit_returning_gtid().await.map_err(|e| {
             internal_err!(
@@ -572,44 +706,90 @@ impl UpstreamDatabase for MySqlUpstream {
             QueryResult::WriteResult {
                 num_rows_affected: affected_rows,
                 last_inserted_id: last_insert_id.unwrap_or(0),
+                warnings,
                 status_flags,
+                session_state: vec![],
             },
             txid,
         ))
     }
 
     async fn start_tx<'a>(&'a mut self) -> Result<Self::QueryResult<'a>, Error> {
-        self.conn.query_drop("START TRANSACTION").await?;
+        let conn = self.conn().await?;
+        conn.query_drop("START TRANSACTION").await?;
 
         Ok(QueryResult::Command {
-            status_flags: self.conn.status(),
+            status_flags: conn.status(),
         })
     }
 
     async fn commit<'a>(&'a mut self) -> Result<Self::QueryResult<'a>, Error> {
-        let result = self.conn.query_iter("COMMIT").await?;
+        let conn = self.conn().await?;
+        let result = conn.query_iter("COMMIT").await?;
         result.drop_result().await?;
 
         Ok(QueryResult::Command {
-            status_flags: self.conn.status(),
+            status_flags: conn.status(),
         })
     }
 
     async fn rollback<'a>(&'a mut self) -> Result<Self::QueryResult<'a>, Error> {
-        let result = self.conn.query_iter("ROLLBACK").await?;
+        let conn = self.conn().await?;
+        let result = conn.query_iter("ROLLBACK").await?;
         result.drop_result().await?;
 
         Ok(QueryResult::Command {
-            status_flags: self.conn.status(),
+            status_flags: conn.status(),
         })
     }
 
+    async fn replication_offset(&mut self) -> Result<Option<ReplicationOffset>, Error> {
+        // Requires the `REPLICATION CLIENT` privilege, and returns no rows if binary logging is
+        // disabled. `SHOW MASTER STATUS` was removed in MySQL 8.4 in favor of `SHOW BINARY LOG
+        // STATUS`, which older versions and MariaDB don't support.
+        let conn = self.conn().await?;
+        let status: Option<Row> = match conn.query_first("SHOW BINARY LOG STATUS").await {
+            Err(mysql_async::Error::Server(_)) => conn.query_first("SHOW MASTER STATUS").await?,
+            status => status?,
+        };
+        let Some(row) = status else {
+            return Ok(None);
+        };
+        let (Some(file), Some(position)) = (row.get::<String, _>(0), row.get::<u64, _>(1)) else {
+            return Ok(None);
+        };
+        // Only present if GTIDs are enabled, and empty if none have been executed yet
+        let gtid_set = row
+            .get_opt::<String, _>("Executed_Gtid_Set")
+            .and_then(|gtid_set| gtid_set.ok())
+            .filter(|gtid_set| !gtid_set.is_empty());
+        Ok(Some(ReplicationOffset {
+            gtid_set,
+            ..ReplicationOffset::from_binlog_position(&file, position)?
+        }))
+    }
+
+    async fn utc_offset(&mut self) -> Result<Option<i32>, Error> {
+        Ok(self
+            .conn()
+            .await?
+            .query_first("SELECT TIMESTAMPDIFF(SECOND, UTC_TIMESTAMP(), NOW())")
+            .await?)
+    }
+
     async fn schema_dump(&mut self) -> Result<Vec<u8>, anyhow::Error> {
-        let tables: Vec<String> = self.conn.query_iter("SHOW TABLES").await?.collect().await?;
+        let tables: Vec<String> = self
+            .conn()
+            .await?
+            .query_iter("SHOW TABLES")
+            .await?
+            .collect()
+            .await?;
         let mut dump = String::with_capacity(tables.len());
         for table in &tables {
             if let Some(create) = self
-                .conn
+                .conn()
+                .await?
                 .query_first(format!("SHOW CREATE TABLE `{}`", &table))
                 .await?
                 .map(|row: (String, String)| row.1)
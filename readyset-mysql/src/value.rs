@@ -1,18 +1,36 @@
 use std::convert::{TryFrom, TryInto};
 
 use mysql_common::chrono::{NaiveDate, NaiveDateTime};
-use mysql_srv::{Value, ValueInner};
-use readyset_data::DfValue;
+use mysql_srv::{ColumnType, ParamValue, ValueInner};
+use readyset_adapter::params::{self, convert_param};
+use readyset_data::{DfType, DfValue};
 use readyset_errors::{ReadySetError, ReadySetResult};
 
-pub(crate) fn mysql_value_to_dataflow_value(value: Value) -> ReadySetResult<DfValue> {
-    Ok(match value.into_inner() {
-        ValueInner::Null => DfValue::None,
-        ValueInner::Bytes(b) => DfValue::from(b),
-        ValueInner::Int(i) => i.into(),
-        ValueInner::UInt(i) => (i as i32).into(),
-        ValueInner::Double(f) => DfValue::try_from(f)?,
-        ValueInner::Datetime(_) => DfValue::TimestampTz(
+/// Returns the type to interpret the value of a parameter sent with the given column type as, for
+/// the column types whose values the binary protocol sends as raw bytes
+fn param_type(coltype: ColumnType) -> DfType {
+    match coltype {
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
+            DfType::DEFAULT_NUMERIC
+        }
+        ColumnType::MYSQL_TYPE_BIT => DfType::VarBit(None),
+        ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB => DfType::Blob,
+        _ => DfType::Unknown,
+    }
+}
+
+pub(crate) fn mysql_param_to_dataflow_value(param: ParamValue) -> ReadySetResult<DfValue> {
+    let value = param.value;
+    let param_value = match value.into_inner() {
+        ValueInner::Null => params::ParamValue::Null,
+        ValueInner::Bytes(b) => params::ParamValue::Bytes(b),
+        ValueInner::Int(i) => params::ParamValue::Int(i),
+        ValueInner::UInt(i) => params::ParamValue::UnsignedInt(i),
+        ValueInner::Double(f) => params::ParamValue::Double(f),
+        ValueInner::Datetime(_) => params::ParamValue::Value(DfValue::TimestampTz(
             NaiveDateTime::try_from(value)
                 .map_err(|e| ReadySetError::DfValueConversionError {
                     src_type: "ValueInner::Datetime".to_string(),
@@ -20,19 +38,17 @@ pub(crate) fn mysql_value_to_dataflow_value(value: Value) -> ReadySetResult<DfVa
                     details: format!("{:?}", e),
                 })?
                 .into(),
-        ),
+        )),
         ValueInner::Time(_) => {
-            DfValue::Time(
-                value
-                    .try_into()
-                    .map_err(|e| ReadySetError::DfValueConversionError {
-                        src_type: "ValueInner::Time".to_string(),
-                        target_type: "DfValue::Time".to_string(),
-                        details: format!("{:?}", e),
-                    })?,
-            )
+            params::ParamValue::Value(DfValue::Time(value.try_into().map_err(|e| {
+                ReadySetError::DfValueConversionError {
+                    src_type: "ValueInner::Time".to_string(),
+                    target_type: "DfValue::Time".to_string(),
+                    details: format!("{:?}", e),
+                }
+            })?))
         }
-        ValueInner::Date(_) => DfValue::TimestampTz(
+        ValueInner::Date(_) => params::ParamValue::Value(DfValue::TimestampTz(
             NaiveDate::try_from(value)
                 .map_err(|e| ReadySetError::DfValueConversionError {
                     src_type: "ValueInner::Date".to_string(),
@@ -40,6 +56,8 @@ pub(crate) fn mysql_value_to_dataflow_value(value: Value) -> ReadySetResult<DfVa
                     details: format!("{:?}", e),
                 })?
                 .into(),
-        ),
-    })
+        )),
+    };
+
+    convert_param(param_value, &param_type(param.coltype))
 }
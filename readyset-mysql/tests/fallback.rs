@@ -1,11 +1,15 @@
+use mysql_async::consts::CapabilityFlags;
 use mysql_async::prelude::*;
 use readyset_adapter::backend::UnsupportedSetMode;
+use readyset_adapter::http_router::NoriaAdapterHttpRouter;
+use readyset_adapter::query_status_cache::QueryStatusCache;
 use readyset_adapter::BackendBuilder;
 use readyset_client::query::QueryId;
 use readyset_client_metrics::QueryDestination;
 use readyset_client_test_helpers::mysql_helpers::{last_query_info, MySQLAdapter};
 use readyset_client_test_helpers::{self, sleep, TestBuilder};
 use readyset_server::Handle;
+use readyset_util::eventually;
 use readyset_util::hash::hash;
 use readyset_util::shutdown::ShutdownSender;
 use serial_test::serial;
@@ -218,6 +222,132 @@ async fn set_autocommit() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn upstream_errors_keep_code_and_sqlstate() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+
+    let server_error = |e: mysql_async::Error| match e {
+        mysql_async::Error::Server(e) => (e.code, e.state),
+        e => panic!("Expected a server error, got: {e}"),
+    };
+
+    assert_eq!(
+        server_error(
+            conn.query_drop("SELECT * FROM does_not_exist")
+                .await
+                .unwrap_err()
+        ),
+        (1146, "42S02".to_owned())
+    );
+    assert_eq!(
+        server_error(conn.prep("SELECT * FROM does_not_exist").await.unwrap_err()),
+        (1146, "42S02".to_owned())
+    );
+    // ER_INVALID_JSON_TEXT_IN_PARAM is too new to be listed in `mysql_srv::ErrorKind`
+    assert_eq!(
+        server_error(
+            conn.query_drop("SELECT CAST('{' AS JSON)")
+                .await
+                .unwrap_err()
+        ),
+        (3141, "22032".to_owned())
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn upstream_affected_rows_respect_client_found_rows() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(opts.clone()).await.unwrap();
+    conn.query_drop("CREATE TABLE found_rows_t (id int PRIMARY KEY, x int)")
+        .await
+        .unwrap();
+    conn.query_drop("INSERT INTO found_rows_t (id, x) VALUES (1, 1)")
+        .await
+        .unwrap();
+
+    // Without CLIENT_FOUND_ROWS, an update that doesn't change a row doesn't count it
+    conn.query_drop("UPDATE found_rows_t SET x = 1 WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(conn.affected_rows(), 0);
+
+    let mut found_rows_conn = mysql_async::Conn::new(
+        mysql_async::OptsBuilder::from_opts(opts)
+            .add_capability(mysql_async::consts::CapabilityFlags::CLIENT_FOUND_ROWS),
+    )
+    .await
+    .unwrap();
+    found_rows_conn
+        .query_drop("UPDATE found_rows_t SET x = 1 WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(found_rows_conn.affected_rows(), 1);
+    found_rows_conn
+        .exec_drop("UPDATE found_rows_t SET x = ? WHERE id = ?", (1, 1))
+        .await
+        .unwrap();
+    assert_eq!(found_rows_conn.affected_rows(), 1);
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn upstream_last_insert_id() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+    conn.query_drop("CREATE TABLE last_insert_id_t (id int AUTO_INCREMENT PRIMARY KEY, x int)")
+        .await
+        .unwrap();
+
+    conn.query_drop("INSERT INTO last_insert_id_t (x) VALUES (1)")
+        .await
+        .unwrap();
+    assert_eq!(conn.last_insert_id(), Some(1));
+    conn.exec_drop("INSERT INTO last_insert_id_t (x) VALUES (?)", (2,))
+        .await
+        .unwrap();
+    assert_eq!(conn.last_insert_id(), Some(2));
+
+    conn.query_drop("UPDATE last_insert_id_t SET x = 3 WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(conn.last_insert_id(), None);
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn upstream_warnings() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+    conn.query_drop("CREATE TABLE warnings_t (id int PRIMARY KEY)")
+        .await
+        .unwrap();
+    conn.query_drop("INSERT INTO warnings_t (id) VALUES (1)")
+        .await
+        .unwrap();
+    assert_eq!(conn.get_warnings(), 0);
+
+    // Ignoring the duplicate key leaves a warning
+    conn.query_drop("INSERT IGNORE INTO warnings_t (id) VALUES (1)")
+        .await
+        .unwrap();
+    assert_eq!(conn.get_warnings(), 1);
+    conn.exec_drop("INSERT IGNORE INTO warnings_t (id) VALUES (?)", (1,))
+        .await
+        .unwrap();
+    assert_eq!(conn.get_warnings(), 1);
+
+    shutdown_tx.shutdown().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 #[serial]
 async fn proxy_unsupported_sets() {
@@ -236,20 +366,19 @@ async fn proxy_unsupported_sets() {
 
     sleep().await;
 
-    conn.query_drop("SET @@SESSION.SQL_MODE = 'ANSI_QUOTES';")
+    conn.query_drop("SET @@SESSION.SQL_MODE = 'PIPES_AS_CONCAT';")
         .await
         .unwrap();
 
     // We should proxy the SET statement upstream, then all subsequent statements should go upstream
-    // (evidenced by the fact that `"x"` is interpreted as a column reference, per the ANSI_QUOTES
-    // SQL mode)
+    // (evidenced by the fact that `||` concatenates strings, per the PIPES_AS_CONCAT SQL mode)
     assert_eq!(
-        conn.query_first::<(i32,), _>("SELECT \"x\" FROM \"t\"")
+        conn.query_first::<(String,), _>("SELECT x || 'y' FROM t")
             .await
             .unwrap()
             .unwrap()
             .0,
-        1,
+        "1y",
     );
 
     assert_eq!(
@@ -278,7 +407,7 @@ async fn proxy_unsupported_sets_prep_exec() {
 
     sleep().await;
 
-    conn.query_drop("SET @@SESSION.SQL_MODE = 'ANSI_QUOTES';")
+    conn.query_drop("SET @@SESSION.SQL_MODE = 'PIPES_AS_CONCAT';")
         .await
         .unwrap();
 
@@ -292,6 +421,61 @@ async fn proxy_unsupported_sets_prep_exec() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn cache_now_in_session_time_zone() {
+    let (opts, _handle, shutdown_tx) = setup_with(
+        BackendBuilder::new()
+            .require_authentication(false)
+            .unsupported_set_mode(UnsupportedSetMode::Allow),
+    )
+    .await;
+    let mut conn = mysql_async::Conn::new(opts.clone()).await.unwrap();
+    conn.query_drop("SET readyset_now_parameter_granularity = 1")
+        .await
+        .unwrap();
+    // Far enough from UTC that binding `NOW()` in the wrong time zone would change the results
+    conn.query_drop("SET time_zone = '+05:00'").await.unwrap();
+
+    conn.query_drop("CREATE TABLE expiring (id int PRIMARY KEY, expires_at DATETIME)")
+        .await
+        .unwrap();
+    conn.query_drop(
+        "INSERT INTO expiring (id, expires_at) VALUES \
+         (1, NOW() - INTERVAL 1 HOUR), (2, NOW() + INTERVAL 1 HOUR)",
+    )
+    .await
+    .unwrap();
+    sleep().await;
+
+    conn.query_drop("CREATE CACHE FROM SELECT id FROM expiring WHERE expires_at > NOW()")
+        .await
+        .unwrap();
+    let ids: Vec<i32> = conn
+        .query("SELECT id FROM expiring WHERE expires_at > NOW()")
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![2]);
+    assert_eq!(
+        last_query_info(&mut conn).await.destination,
+        QueryDestination::Readyset
+    );
+
+    // Other connections don't cache queries calling `NOW()` unless they opt in
+    let mut other_conn = mysql_async::Conn::new(opts).await.unwrap();
+    let ids: Vec<i32> = other_conn
+        .query("SELECT id FROM expiring WHERE expires_at > NOW()")
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![2]);
+    assert_eq!(
+        last_query_info(&mut other_conn).await.destination,
+        QueryDestination::Upstream
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 #[serial]
 async fn prepare_in_tx_select_out() {
@@ -614,20 +798,19 @@ async fn proxy_mode_should_allow_commands() {
         .unwrap();
     sleep().await;
 
-    conn.query_drop("SET @@SESSION.SQL_MODE = 'ANSI_QUOTES';")
+    conn.query_drop("SET @@SESSION.SQL_MODE = 'PIPES_AS_CONCAT';")
         .await
         .unwrap();
 
     // We should proxy the SET statement upstream, then all subsequent statements should go upstream
-    // (evidenced by the fact that `"x"` is interpreted as a column reference, per the ANSI_QUOTES
-    // SQL mode)
+    // (evidenced by the fact that `||` concatenates strings, per the PIPES_AS_CONCAT SQL mode)
     assert_eq!(
-        conn.query_first::<(i32,), _>("SELECT \"x\" FROM \"t\"")
+        conn.query_first::<(String,), _>("SELECT x || 'y' FROM t")
             .await
             .unwrap()
             .unwrap()
             .0,
-        1,
+        "1y",
     );
 
     // We should still handle custom ReadySet commands directly, otherwise we will end up passing
@@ -709,6 +892,17 @@ async fn transaction_proxies() {
     shutdown_tx.shutdown().await;
 }
 
+/// A row of `SHOW PROXIED QUERIES`: the query id, query, whether ReadySet supports the query, the
+/// reason it's unsupported, its request rate, and the statement to cache it
+type ProxiedQueryRow = (
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
 #[tokio::test(flavor = "multi_thread")]
 #[serial]
 async fn valid_sql_parsing_failed_shows_proxied() {
@@ -722,13 +916,21 @@ async fn valid_sql_parsing_failed_shows_proxied() {
     let _ = conn.query_drop(q.clone()).await;
 
     let proxied_queries = conn
-        .query::<(String, String, String), _>("SHOW PROXIED QUERIES;")
+        .query::<ProxiedQueryRow, _>("SHOW PROXIED QUERIES;")
         .await
         .unwrap();
     let id = QueryId::new(hash(&q));
 
     assert!(
-        proxied_queries.contains(&(id.to_string(), q, "unsupported".to_owned())),
+        proxied_queries
+            .iter()
+            .any(|(query_id, query, supported, reason, _, suggestion)| {
+                *query_id == id.to_string()
+                    && *query == q
+                    && supported == "unsupported"
+                    && reason.as_deref() == Some("Query failed to parse")
+                    && suggestion.is_none()
+            }),
         "proxied_queries = {:?}",
         proxied_queries,
     );
@@ -745,7 +947,7 @@ async fn invalid_sql_parsing_failed_doesnt_show_proxied() {
     let q = "this isn't valid SQL".to_string();
     let _ = conn.query_drop(q.clone()).await;
     let proxied_queries = conn
-        .query::<(String, String, String), _>("SHOW PROXIED QUERIES;")
+        .query::<ProxiedQueryRow, _>("SHOW PROXIED QUERIES;")
         .await
         .unwrap();
 
@@ -783,6 +985,92 @@ async fn switch_database_with_use() {
     shutdown_tx.shutdown().await;
 }
 
+/// Returns the session state info of the OK packet `query` is answered with
+async fn session_state_info(conn: &mut mysql_async::Conn, query: &str) -> Vec<u8> {
+    let mut result = conn.query_iter(query).await.unwrap();
+    let stream = result
+        .stream_and_drop::<mysql_async::Row>()
+        .await
+        .unwrap()
+        .unwrap();
+    stream
+        .ok_packet()
+        .and_then(|ok| ok.session_state_info_ref())
+        .map(<[u8]>::to_vec)
+        .unwrap_or_default()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn proxied_session_state_changes() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(
+        mysql_async::OptsBuilder::from_opts(opts.clone())
+            .add_capability(CapabilityFlags::CLIENT_SESSION_TRACK),
+    )
+    .await
+    .unwrap();
+    conn.query_drop("DROP DATABASE IF EXISTS session_state")
+        .await
+        .unwrap();
+    conn.query_drop("CREATE DATABASE session_state")
+        .await
+        .unwrap();
+
+    // The upstream server tracks changes to the current schema by default. The change is
+    // SESSION_TRACK_SCHEMA, followed by its length and the length-encoded name of the schema.
+    let mut expected = vec![0x01, 14, 13];
+    expected.extend_from_slice(b"session_state");
+    assert_eq!(
+        session_state_info(&mut conn, "USE session_state").await,
+        expected
+    );
+
+    // Clients that don't track their session state aren't sent any changes
+    let mut untracked = mysql_async::Conn::new(opts).await.unwrap();
+    assert!(session_state_info(&mut untracked, "USE session_state")
+        .await
+        .is_empty());
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn sql_calc_found_rows() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+
+    conn.query_drop("CREATE TABLE found_rows_t (id int, PRIMARY KEY(id))")
+        .await
+        .unwrap();
+    conn.query_drop("INSERT INTO found_rows_t (id) VALUES (1), (2), (3)")
+        .await
+        .unwrap();
+    sleep().await;
+
+    let rows: Vec<i32> = conn
+        .query("SELECT SQL_CALC_FOUND_ROWS id FROM found_rows_t ORDER BY id LIMIT 1")
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![1]);
+    let found_rows: Option<u64> = conn.query_first("SELECT FOUND_ROWS()").await.unwrap();
+    assert_eq!(found_rows, Some(3));
+
+    let rows: Vec<i32> = conn
+        .exec(
+            "SELECT SQL_CALC_FOUND_ROWS id FROM found_rows_t WHERE id > ? LIMIT 1",
+            (1,),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    let found_rows: Option<u64> = conn.exec_first("SELECT FOUND_ROWS()", ()).await.unwrap();
+    assert_eq!(found_rows, Some(2));
+
+    shutdown_tx.shutdown().await;
+}
+
 #[cfg(feature = "failure_injection")]
 #[tokio::test(flavor = "multi_thread")]
 #[serial]
@@ -906,7 +1194,7 @@ async fn replication_failure_ignores_table() {
 
 #[allow(dead_code)]
 async fn last_statement_matches(dest: &str, status: &str, client: &mut mysql_async::Conn) -> bool {
-    let rows: Vec<(String, String)> = client
+    let rows: Vec<(String, String, String)> = client
         .query("EXPLAIN LAST STATEMENT")
         .await
         .expect("explain query failed");
@@ -914,3 +1202,169 @@ async fn last_statement_matches(dest: &str, status: &str, client: &mut mysql_asy
     let status_col = rows[0].1.clone();
     dest_col.contains(dest) && status_col.contains(status)
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn read_after_write() {
+    let (opts, _handle, shutdown_tx) = setup_with(
+        BackendBuilder::new()
+            .require_authentication(false)
+            .read_after_write_window(Some(std::time::Duration::from_secs(600))),
+    )
+    .await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+    conn.query_drop("CREATE TABLE raw (id int PRIMARY KEY, val int)")
+        .await
+        .unwrap();
+    conn.query_drop("INSERT INTO raw (id, val) VALUES (1, 1)")
+        .await
+        .unwrap();
+    sleep().await;
+    conn.query_drop("CREATE CACHE FROM SELECT val FROM raw WHERE id = ?")
+        .await
+        .unwrap();
+
+    // Reads made right after a write see it, whether or not it's been replicated yet: either by
+    // going to the upstream database, or because it's been replicated
+    conn.query_drop("UPDATE raw SET val = 2 WHERE id = 1")
+        .await
+        .unwrap();
+    let val: Option<i32> = conn
+        .query_first("SELECT val FROM raw WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(val, Some(2));
+
+    // Including writes we can't parse
+    conn.query_drop("REPLACE INTO raw (id, val) VALUES (1, 3)")
+        .await
+        .unwrap();
+    let val: Option<i32> = conn
+        .query_first("SELECT val FROM raw WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(val, Some(3));
+
+    // Once the write has been replicated, reads go back to ReadySet well before the window ends
+    sleep().await;
+    let val: Option<i32> = conn
+        .query_first("SELECT val FROM raw WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(val, Some(3));
+    assert_eq!(
+        last_query_info(&mut conn).await.destination,
+        QueryDestination::Readyset
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn reset_connection_resets_session() {
+    let (opts, _handle, shutdown_tx) = setup_with(
+        BackendBuilder::new()
+            .require_authentication(false)
+            .unsupported_set_mode(UnsupportedSetMode::Allow),
+    )
+    .await;
+    let mut conn = mysql_async::Conn::new(opts).await.unwrap();
+    conn.query_drop("CREATE TABLE reset_t (id int PRIMARY KEY)")
+        .await
+        .unwrap();
+    conn.query_drop("INSERT INTO reset_t (id) VALUES (1)")
+        .await
+        .unwrap();
+    sleep().await;
+    conn.query_drop("CREATE CACHE FROM SELECT id FROM reset_t WHERE id = ?")
+        .await
+        .unwrap();
+
+    // Unsupported `SET`s proxy every later query to the upstream database
+    conn.query_drop("SET @x = 1").await.unwrap();
+    conn.query_drop("CREATE TEMPORARY TABLE reset_tmp (id int)")
+        .await
+        .unwrap();
+    conn.query_drop("SELECT id FROM reset_t WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(
+        last_query_info(&mut conn).await.destination,
+        QueryDestination::Upstream
+    );
+
+    conn.reset().await.unwrap();
+
+    let x: Option<Option<i32>> = conn.query_first("SELECT @x").await.unwrap();
+    assert_eq!(x, Some(None));
+    conn.query_drop("SELECT * FROM reset_tmp")
+        .await
+        .unwrap_err();
+    let ids: Vec<i32> = conn
+        .query("SELECT id FROM reset_t WHERE id = 1")
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![1]);
+    assert_eq!(
+        last_query_info(&mut conn).await.destination,
+        QueryDestination::Readyset
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
+/// Returns the status code and body of a `GET` request to `path` on the HTTP server at `addr`
+async fn http_get(addr: std::net::SocketAddr, path: &str) -> (hyper::StatusCode, String) {
+    let res = hyper::Client::new()
+        .get(format!("http://{addr}{path}").parse().unwrap())
+        .await
+        .unwrap();
+    let status = res.status();
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn readyz() {
+    let (_opts, handle, shutdown_tx) = setup().await;
+    let mut health_reporter = health_reporter::HealthReporter::new();
+    let router = NoriaAdapterHttpRouter {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        query_cache: Box::leak(Box::new(QueryStatusCache::new())),
+        health_reporter: health_reporter.clone(),
+        failpoint_channel: None,
+        prometheus_handle: None,
+        controller: (*handle).clone(),
+        max_replication_lag: Some(std::time::Duration::from_secs(30)),
+    };
+    let listener = router.create_listener().await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (router_shutdown_tx, router_shutdown_rx) = readyset_util::shutdown::channel();
+    tokio::spawn(NoriaAdapterHttpRouter::route_requests(
+        router,
+        listener,
+        router_shutdown_rx,
+    ));
+
+    assert_eq!(
+        http_get(addr, "/healthz").await,
+        (hyper::StatusCode::OK, "ok".to_owned())
+    );
+
+    // Not ready until the adapter is listening for connections
+    let (status, body) = http_get(addr, "/readyz").await;
+    assert_eq!(status, hyper::StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body.contains("adapter is in unhealthy state"), "{body}");
+
+    health_reporter.set_state(health_reporter::State::Healthy);
+    eventually!(run_test: {
+        http_get(addr, "/readyz").await
+    }, then_assert: |result| {
+        assert_eq!(result, (hyper::StatusCode::OK, "ready".to_owned()));
+    });
+
+    router_shutdown_tx.shutdown().await;
+    shutdown_tx.shutdown().await;
+}
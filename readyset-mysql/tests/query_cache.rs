@@ -677,3 +677,45 @@ async fn drop_all_caches() {
 
     shutdown_tx.shutdown().await;
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restore_all_caches() {
+    let query_status_cache: &'static _ = Box::leak(Box::new(QueryStatusCache::new()));
+    let (opts, _handle, shutdown_tx) = setup(
+        query_status_cache,
+        false, // fallback disabled
+        MigrationMode::OutOfBand,
+        UnsupportedSetMode::Error,
+    )
+    .await;
+
+    let mut conn = Conn::new(opts).await.unwrap();
+    conn.query_drop("CREATE TABLE t (a INT, b INT)")
+        .await
+        .unwrap();
+    sleep().await;
+    conn.query_drop("INSERT INTO t (a, b) VALUES (1, 2)")
+        .await
+        .unwrap();
+    sleep().await;
+
+    conn.query_drop("CREATE CACHE test FROM SELECT b FROM t WHERE a = ?")
+        .await
+        .unwrap();
+    let stmt = conn.prep("SELECT b FROM t WHERE a = ?").await.unwrap();
+    let res: Vec<i32> = conn.exec(&stmt, (1,)).await.unwrap();
+    assert_eq!(res, vec![2]);
+
+    conn.query_drop("ALTER READYSET RESTORE CACHES")
+        .await
+        .unwrap();
+
+    // Statements prepared before the caches were restored read from the re-created cache, as do
+    // new queries
+    let res: Vec<i32> = conn.exec(&stmt, (1,)).await.unwrap();
+    assert_eq!(res, vec![2]);
+    let res: Vec<i32> = conn.query("SELECT b FROM t WHERE a = 1").await.unwrap();
+    assert_eq!(res, vec![2]);
+
+    shutdown_tx.shutdown().await;
+}
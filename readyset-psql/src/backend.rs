@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::ops::Deref;
 use std::str::FromStr;
@@ -8,9 +9,11 @@ use clap::ValueEnum;
 use eui48::MacAddressFormat;
 use psql_srv as ps;
 use readyset_adapter::backend as cl;
-use readyset_data::DfValue;
+use readyset_adapter::params::convert_param;
+use readyset_data::{DfType, DfValue};
 use thiserror::Error;
 
+use crate::cursor::{self, Cursor, CursorStatement, Cursors, TransactionBoundary};
 use crate::error::Error;
 use crate::query_handler::PostgreSqlQueryHandler;
 use crate::response::{PrepareResponse, QueryResponse};
@@ -51,6 +54,10 @@ impl FromStr for AuthenticationMethod {
 pub struct Backend {
     inner: cl::Backend<PostgreSqlUpstream, PostgreSqlQueryHandler>,
     authentication_method: AuthenticationMethod,
+    /// The cursors declared with `DECLARE` statements on this connection
+    cursors: Cursors,
+    /// The prepared statements which start or end a transaction, by id
+    transaction_boundaries: HashMap<u32, TransactionBoundary>,
 }
 
 impl Backend {
@@ -58,6 +65,8 @@ impl Backend {
         Self {
             inner,
             authentication_method: Default::default(),
+            cursors: Default::default(),
+            transaction_boundaries: Default::default(),
         }
     }
 
@@ -89,6 +98,61 @@ impl Backend {
     async fn execute(&mut self, id: u32, params: &[DfValue]) -> Result<QueryResponse<'_>, Error> {
         Ok(QueryResponse(self.inner.execute(id, params).await?))
     }
+
+    /// Prepare `query` as the statement `statement_id` and execute it, returning the schema and
+    /// results of the rows it reads. Preparing the query rather than running it ad hoc means that
+    /// rows from the upstream database are streamed with their types, rather than read in full as
+    /// text.
+    async fn execute_once(
+        &mut self,
+        statement_id: u32,
+        query: &str,
+    ) -> Result<(Vec<ps::Column>, Resultset), ps::Error> {
+        let schema = self
+            .prepare(query)
+            .await?
+            .try_into_ps(statement_id)?
+            .row_schema;
+        let response: ps::QueryResponse<Resultset> =
+            self.execute(statement_id, &[]).await?.try_into()?;
+        match response {
+            ps::QueryResponse::Select { resultset, .. } => Ok((schema, resultset)),
+            _ => Err(ps::Error::Unsupported(
+                "cursors for statements which don't return rows".into(),
+            )),
+        }
+    }
+
+    async fn cursor_statement(
+        &mut self,
+        statement: CursorStatement<'_>,
+    ) -> Result<ps::QueryResponse<Resultset>, ps::Error> {
+        match statement {
+            CursorStatement::Declare { name, hold, query } => {
+                self.cursors.check_declarable(&name, hold)?;
+                self.cursors.read_ahead().await?;
+                let statement_id = self.next_prepared_id();
+                let results = self.execute_once(statement_id, query).await;
+                self.inner.remove_statement(statement_id).await?;
+                let (schema, results) = results?;
+                self.cursors
+                    .declare(name, Cursor::new(schema, results, hold))?;
+                Ok(ps::QueryResponse::Command)
+            }
+            CursorStatement::Fetch { name, count, skip } => {
+                let response = self.cursors.get_mut(&name)?.fetch(count).await?;
+                Ok(if skip {
+                    ps::QueryResponse::Command
+                } else {
+                    response
+                })
+            }
+            CursorStatement::Close(name) => {
+                self.cursors.close(name.as_deref())?;
+                Ok(ps::QueryResponse::Command)
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -119,12 +183,25 @@ impl ps::Backend for Backend {
     }
 
     async fn on_query(&mut self, query: &str) -> Result<ps::QueryResponse<Resultset>, ps::Error> {
-        self.query(query).await?.try_into()
+        if let Some(statement) = cursor::parse(query) {
+            return self.cursor_statement(statement?).await;
+        }
+        self.cursors.read_ahead().await?;
+        let response = self.query(query).await?.try_into()?;
+        if let Some(boundary) = cursor::transaction_boundary(query) {
+            self.cursors.transaction_boundary(boundary);
+        }
+        Ok(response)
     }
 
     async fn on_prepare(&mut self, query: &str) -> Result<ps::PrepareResponse, ps::Error> {
+        self.cursors.read_ahead().await?;
         let statement_id = self.next_prepared_id(); // If prepare succeeds it will get this id
-        self.prepare(query).await?.try_into_ps(statement_id)
+        let response = self.prepare(query).await?.try_into_ps(statement_id)?;
+        if let Some(boundary) = cursor::transaction_boundary(query) {
+            self.transaction_boundaries.insert(statement_id, boundary);
+        }
+        Ok(response)
     }
 
     async fn on_execute(
@@ -136,11 +213,17 @@ impl ps::Backend for Backend {
             .iter()
             .map(|p| ParamRef(p).try_into())
             .collect::<Result<Vec<DfValue>, ps::Error>>()?;
-        self.execute(statement_id, &params).await?.try_into()
+        self.cursors.read_ahead().await?;
+        let response = self.execute(statement_id, &params).await?.try_into()?;
+        if let Some(boundary) = self.transaction_boundaries.get(&statement_id) {
+            self.cursors.transaction_boundary(*boundary);
+        }
+        Ok(response)
     }
 
-    async fn on_close(&mut self, _statement_id: u32) -> Result<(), ps::Error> {
-        Ok(())
+    async fn on_close(&mut self, statement_id: u32) -> Result<(), ps::Error> {
+        self.transaction_boundaries.remove(&statement_id);
+        Ok(self.inner.remove_statement(statement_id).await?)
     }
 }
 
@@ -152,35 +235,51 @@ impl TryFrom<ParamRef<'_>> for DfValue {
     type Error = ps::Error;
 
     fn try_from(v: ParamRef) -> Result<Self, Self::Error> {
-        match v.0 {
-            ps::Value::Null => Ok(DfValue::None),
-            ps::Value::Bool(b) => Ok(DfValue::from(*b)),
+        use readyset_adapter::params::ParamValue;
+
+        // psql-srv has already decoded each value according to the type of its parameter, so the
+        // type only needs passing along for byte arrays (which are never text, even if they happen
+        // to be valid UTF-8)
+        let value = match v.0 {
+            ps::Value::Null => ParamValue::Null,
+            ps::Value::Bool(b) => ParamValue::Bool(*b),
             ps::Value::BpChar(v)
             | ps::Value::VarChar(v)
             | ps::Value::Name(v)
-            | ps::Value::Text(v) => Ok(v.as_str().into()),
-            ps::Value::Char(v) => Ok((*v).into()),
-            ps::Value::Int(v) => Ok((*v).into()),
-            ps::Value::BigInt(v) => Ok((*v).into()),
-            ps::Value::SmallInt(v) => Ok((*v).into()),
-            ps::Value::Oid(v) => Ok((*v).into()),
-            ps::Value::Double(v) => DfValue::try_from(*v)
-                .map_err(|_| ps::Error::Unsupported(format!("f64 with value `{}`", v))),
-            ps::Value::Float(v) => DfValue::try_from(*v)
-                .map_err(|_| ps::Error::Unsupported(format!("f32 with value `{}`", v))),
-            ps::Value::Numeric(d) => Ok(DfValue::from(*d)),
-            ps::Value::Timestamp(v) => Ok((*v).into()),
-            ps::Value::TimestampTz(v) => Ok(DfValue::from(*v)),
-            ps::Value::Date(v) => Ok((*v).into()),
-            ps::Value::Time(v) => Ok((*v).into()),
-            ps::Value::ByteArray(b) => Ok(DfValue::ByteArray(Arc::new(b.clone()))),
-            ps::Value::MacAddress(m) => Ok(DfValue::from(m.to_string(MacAddressFormat::HexString))),
-            ps::Value::Inet(ip) => Ok(DfValue::from(ip.to_string())),
-            ps::Value::Uuid(uuid) => Ok(DfValue::from(uuid.to_string())),
-            ps::Value::Json(v) | ps::Value::Jsonb(v) => Ok(DfValue::from(v.to_string())),
-            ps::Value::Bit(bits) | ps::Value::VarBit(bits) => Ok(DfValue::from(bits.clone())),
-            ps::Value::Array(arr, _) => Ok(DfValue::from(arr.clone())),
-            ps::Value::PassThrough(p) => Ok(DfValue::PassThrough(Arc::new(p.clone()))),
-        }
+            | ps::Value::Text(v) => ParamValue::Text(v.as_str()),
+            ps::Value::Char(v) => ParamValue::Int((*v).into()),
+            ps::Value::Int(v) => ParamValue::Int((*v).into()),
+            ps::Value::BigInt(v) => ParamValue::Int(*v),
+            ps::Value::SmallInt(v) => ParamValue::Int((*v).into()),
+            ps::Value::Oid(v) => ParamValue::UnsignedInt((*v).into()),
+            ps::Value::Double(v) => ParamValue::Double(*v),
+            ps::Value::Float(v) => ParamValue::Float(*v),
+            ps::Value::Numeric(d) => ParamValue::Numeric(*d),
+            ps::Value::ByteArray(b) => {
+                return convert_param(ParamValue::Bytes(b), &DfType::Blob)
+                    .map_err(|e| Error::from(e).into())
+            }
+            ps::Value::Timestamp(v) => ParamValue::Value((*v).into()),
+            ps::Value::TimestampTz(v) => ParamValue::Value(DfValue::from(*v)),
+            ps::Value::Date(v) => ParamValue::Value((*v).into()),
+            ps::Value::Time(v) => ParamValue::Value((*v).into()),
+            ps::Value::MacAddress(m) => {
+                ParamValue::Value(DfValue::from(m.to_string(MacAddressFormat::HexString)))
+            }
+            ps::Value::Inet(ip) => ParamValue::Value(DfValue::from(ip.to_string())),
+            ps::Value::Uuid(uuid) => ParamValue::Value(DfValue::from(uuid.to_string())),
+            ps::Value::Json(v) | ps::Value::Jsonb(v) => {
+                ParamValue::Value(DfValue::from(v.to_string()))
+            }
+            ps::Value::Bit(bits) | ps::Value::VarBit(bits) => {
+                ParamValue::Value(DfValue::from(bits.clone()))
+            }
+            ps::Value::Array(arr, _) => ParamValue::Value(DfValue::from(arr.clone())),
+            ps::Value::PassThrough(p) => {
+                ParamValue::Value(DfValue::PassThrough(Arc::new(p.clone())))
+            }
+        };
+
+        convert_param(value, &DfType::Unknown).map_err(|e| Error::from(e).into())
     }
 }
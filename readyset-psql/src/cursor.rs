@@ -0,0 +1,529 @@
+//! Support for cursors declared with SQL statements (`DECLARE`, `FETCH`, `MOVE`, and `CLOSE`),
+//! which some clients (such as psql with `FETCH_COUNT` set, or reporting tools) use to read large
+//! resultsets a few rows at a time.
+//!
+//! When a cursor is declared its query is executed like any other query, answered either by
+//! ReadySet or by the upstream database, and `FETCH` statements then read rows from its results
+//! without running any further queries. Results from the upstream database are streamed over the
+//! upstream connection, so they're only read ahead into the cursor when another statement needs
+//! that connection. Cursors can only be fetched forwards. As in PostgreSQL, cursors not declared
+//! `WITH HOLD` can only be declared in a transaction, and are closed when that transaction ends.
+//!
+//! Cursor statements are only supported in simple queries, not as prepared statements.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use psql_srv as ps;
+use readyset_data::DfValue;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::Type;
+
+use crate::resultset::Resultset;
+
+/// How many rows to read from a cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FetchCount {
+    Count(u64),
+    All,
+}
+
+/// A statement operating on a cursor
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum CursorStatement<'a> {
+    /// `DECLARE name CURSOR [WITH HOLD] FOR query`
+    Declare {
+        name: String,
+        hold: bool,
+        query: &'a str,
+    },
+    /// `FETCH`, or `MOVE` if `skip` is true, which moves the cursor without returning any rows
+    Fetch {
+        name: String,
+        count: FetchCount,
+        skip: bool,
+    },
+    /// `CLOSE name`, or `CLOSE ALL` if the name is `None`
+    Close(Option<String>),
+}
+
+/// Parse `query` as a cursor statement, returning `None` if it isn't one and an error if it's a
+/// cursor statement that is malformed or unsupported
+pub(crate) fn parse(query: &str) -> Option<Result<CursorStatement<'_>, ps::Error>> {
+    let mut tokens = Tokens::new(query);
+    if tokens.keyword("DECLARE") {
+        Some(parse_declare(tokens))
+    } else if tokens.keyword("FETCH") {
+        Some(parse_fetch(tokens, false))
+    } else if tokens.keyword("MOVE") {
+        Some(parse_fetch(tokens, true))
+    } else if tokens.keyword("CLOSE") {
+        Some(parse_close(tokens))
+    } else {
+        None
+    }
+}
+
+/// A statement that starts or ends a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TransactionBoundary {
+    Start,
+    End,
+}
+
+/// Returns whether `query` starts or ends a transaction, if it does either
+pub(crate) fn transaction_boundary(query: &str) -> Option<TransactionBoundary> {
+    let mut tokens = Tokens::new(query);
+    if tokens.keyword("BEGIN") || tokens.keyword("START") {
+        return Some(TransactionBoundary::Start);
+    }
+    let end = if tokens.keyword("ROLLBACK") {
+        // `ROLLBACK TO SAVEPOINT` and `ROLLBACK PREPARED` leave the current transaction open
+        !tokens.keyword("TO") && !tokens.keyword("PREPARED")
+    } else if tokens.keyword("COMMIT") {
+        !tokens.keyword("PREPARED")
+    } else {
+        tokens.keyword("END") || tokens.keyword("ABORT")
+    };
+    end.then_some(TransactionBoundary::End)
+}
+
+fn syntax_error(statement: &str) -> ps::Error {
+    ps::Error::ParseError(format!("invalid {statement} statement"))
+}
+
+fn parse_declare(mut tokens: Tokens<'_>) -> Result<CursorStatement<'_>, ps::Error> {
+    let name = tokens.identifier().ok_or_else(|| syntax_error("DECLARE"))?;
+    if tokens.keyword("BINARY") {
+        return Err(ps::Error::Unsupported("binary cursors".into()));
+    }
+    let _ = tokens.keyword("ASENSITIVE") || tokens.keyword("INSENSITIVE");
+    if tokens.keyword("NO") && !tokens.keyword("SCROLL") {
+        return Err(syntax_error("DECLARE"));
+    }
+    let _ = tokens.keyword("SCROLL");
+    if !tokens.keyword("CURSOR") {
+        return Err(syntax_error("DECLARE"));
+    }
+
+    let hold = if tokens.keyword("WITH") {
+        Some(true)
+    } else if tokens.keyword("WITHOUT") {
+        Some(false)
+    } else {
+        None
+    };
+    if hold.is_some() && !tokens.keyword("HOLD") {
+        return Err(syntax_error("DECLARE"));
+    }
+
+    if !tokens.keyword("FOR") || tokens.rest().is_empty() {
+        return Err(syntax_error("DECLARE"));
+    }
+    Ok(CursorStatement::Declare {
+        name,
+        hold: hold.unwrap_or(false),
+        query: tokens.rest(),
+    })
+}
+
+fn parse_fetch(mut tokens: Tokens<'_>, skip: bool) -> Result<CursorStatement<'_>, ps::Error> {
+    let statement = if skip { "MOVE" } else { "FETCH" };
+    let backwards =
+        || ps::Error::Unsupported("fetching rows from a cursor other than forwards".into());
+
+    let count = if tokens.keyword("NEXT") {
+        FetchCount::Count(1)
+    } else if tokens.keyword("FORWARD") {
+        if tokens.keyword("ALL") {
+            FetchCount::All
+        } else {
+            match tokens.count() {
+                Some(count) => FetchCount::Count(count.try_into().map_err(|_| backwards())?),
+                None => FetchCount::Count(1),
+            }
+        }
+    } else if tokens.keyword("ALL") {
+        FetchCount::All
+    } else if let Some(count) = tokens.count() {
+        FetchCount::Count(count.try_into().map_err(|_| backwards())?)
+    } else if ["PRIOR", "FIRST", "LAST", "ABSOLUTE", "RELATIVE", "BACKWARD"]
+        .into_iter()
+        .any(|direction| tokens.keyword(direction))
+    {
+        return Err(backwards());
+    } else {
+        FetchCount::Count(1)
+    };
+
+    let _ = tokens.keyword("FROM") || tokens.keyword("IN");
+    let name = tokens.identifier().ok_or_else(|| syntax_error(statement))?;
+    if !tokens.rest().is_empty() {
+        return Err(syntax_error(statement));
+    }
+    Ok(CursorStatement::Fetch { name, count, skip })
+}
+
+fn parse_close(mut tokens: Tokens<'_>) -> Result<CursorStatement<'_>, ps::Error> {
+    let name = if tokens.keyword("ALL") {
+        None
+    } else {
+        Some(tokens.identifier().ok_or_else(|| syntax_error("CLOSE"))?)
+    };
+    if !tokens.rest().is_empty() {
+        return Err(syntax_error("CLOSE"));
+    }
+    Ok(CursorStatement::Close(name))
+}
+
+/// The remainder of a cursor statement, split into tokens as they're needed
+struct Tokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(query: &'a str) -> Self {
+        Self {
+            rest: query.trim_start(),
+        }
+    }
+
+    /// Returns the word at the start of the remaining input, which may be empty
+    fn word(&self) -> &'a str {
+        let len = self
+            .rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(self.rest.len());
+        &self.rest[..len]
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.rest = self.rest[len..].trim_start();
+    }
+
+    /// Consume the given keyword if it's next, returning whether it was
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let word = self.word();
+        let matches = word.eq_ignore_ascii_case(keyword);
+        if matches {
+            self.advance(word.len());
+        }
+        matches
+    }
+
+    /// Consume an identifier, which is folded to lowercase unless it's quoted
+    fn identifier(&mut self) -> Option<String> {
+        if let Some(quoted) = self.rest.strip_prefix('"') {
+            let mut identifier = String::new();
+            let mut chars = quoted.char_indices();
+            while let Some((i, c)) = chars.next() {
+                if c != '"' {
+                    identifier.push(c);
+                } else if quoted[i + 1..].starts_with('"') {
+                    identifier.push('"');
+                    chars.next();
+                } else {
+                    self.advance(i + 2);
+                    return (!identifier.is_empty()).then_some(identifier);
+                }
+            }
+            return None;
+        }
+
+        let word = self.word();
+        if word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.advance(word.len());
+        Some(word.to_lowercase())
+    }
+
+    /// Consume an optionally signed integer
+    fn count(&mut self) -> Option<i64> {
+        let (sign, unsigned) = match self.rest.strip_prefix(|c| c == '-' || c == '+') {
+            Some(unsigned) => (&self.rest[..1], unsigned.trim_start()),
+            None => ("", self.rest),
+        };
+        let len = unsigned
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(unsigned.len());
+        let count = format!("{sign}{}", &unsigned[..len]).parse().ok()?;
+        self.rest = unsigned;
+        self.advance(len);
+        Some(count)
+    }
+
+    /// Returns the rest of the statement, without any trailing semicolons
+    fn rest(&self) -> &'a str {
+        self.rest
+            .trim_end_matches(|c: char| c == ';' || c.is_whitespace())
+    }
+}
+
+/// A cursor that has been declared, holding the results it hasn't yet returned
+pub(crate) struct Cursor {
+    schema: Vec<ps::Column>,
+    field_types: Arc<Vec<Type>>,
+    /// Rows read ahead from `results` to free up the upstream connection, which are returned
+    /// before any rows still in `results`
+    read_ahead: VecDeque<Vec<DfValue>>,
+    results: Resultset,
+    hold: bool,
+}
+
+impl Cursor {
+    pub(crate) fn new(schema: Vec<ps::Column>, results: Resultset, hold: bool) -> Self {
+        Self {
+            field_types: Arc::new(schema.iter().map(|c| c.col_type.clone()).collect()),
+            schema,
+            read_ahead: VecDeque::new(),
+            results,
+            hold,
+        }
+    }
+
+    /// Remove the next `count` rows from the cursor, returning them as the response to a `FETCH`
+    pub(crate) async fn fetch(
+        &mut self,
+        count: FetchCount,
+    ) -> Result<ps::QueryResponse<Resultset>, ps::Error> {
+        let limit = match count {
+            FetchCount::Count(count) => count as usize,
+            FetchCount::All => usize::MAX,
+        };
+        let mut rows = vec![];
+        while rows.len() < limit {
+            let row = match self.read_ahead.pop_front() {
+                Some(row) => row,
+                None => match self.results.try_next().await? {
+                    Some(row) => row.values,
+                    None => break,
+                },
+            };
+            rows.push(row);
+        }
+        Ok(ps::QueryResponse::Select {
+            schema: self.schema.clone(),
+            resultset: Resultset::from_rows(rows, self.field_types.clone()),
+        })
+    }
+
+    /// If the cursor's results are being streamed from the upstream database, read the rest of
+    /// them into the cursor, so that the upstream connection can be used for other statements
+    async fn read_ahead(&mut self) -> Result<(), ps::Error> {
+        if !self.results.is_upstream_stream() {
+            return Ok(());
+        }
+        while let Some(row) = self.results.try_next().await? {
+            self.read_ahead.push_back(row.values);
+        }
+        self.results = Resultset::empty();
+        Ok(())
+    }
+}
+
+/// The cursors declared by a single connection, by name, along with whether the connection is in
+/// a transaction
+#[derive(Default)]
+pub(crate) struct Cursors {
+    cursors: HashMap<String, Cursor>,
+    in_transaction: bool,
+}
+
+impl Cursors {
+    /// Read the rest of any results being streamed from the upstream database into their cursors.
+    /// This must be called before running any other statement against the upstream database,
+    /// which can't answer it until those results have been read.
+    pub(crate) async fn read_ahead(&mut self) -> Result<(), ps::Error> {
+        for cursor in self.cursors.values_mut() {
+            cursor.read_ahead().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns an error if a cursor named `name` can't be declared, either because one has already
+    /// been declared or because it isn't `WITH HOLD` and we're not in a transaction
+    pub(crate) fn check_declarable(&self, name: &str, hold: bool) -> Result<(), ps::Error> {
+        if !hold && !self.in_transaction {
+            return Err(ps::Error::Database {
+                sqlstate: SqlState::NO_ACTIVE_SQL_TRANSACTION,
+                message: "DECLARE CURSOR can only be used in transaction blocks".into(),
+                fields: Default::default(),
+            });
+        }
+        if self.cursors.contains_key(name) {
+            return Err(ps::Error::Database {
+                sqlstate: SqlState::DUPLICATE_CURSOR,
+                message: format!("cursor \"{name}\" already exists"),
+                fields: Default::default(),
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn declare(&mut self, name: String, cursor: Cursor) -> Result<(), ps::Error> {
+        self.check_declarable(&name, cursor.hold)?;
+        self.cursors.insert(name, cursor);
+        Ok(())
+    }
+
+    pub(crate) fn get_mut(&mut self, name: &str) -> Result<&mut Cursor, ps::Error> {
+        self.cursors.get_mut(name).ok_or_else(|| not_found(name))
+    }
+
+    /// Close the cursor named `name`, or all cursors if `name` is `None`
+    pub(crate) fn close(&mut self, name: Option<&str>) -> Result<(), ps::Error> {
+        match name {
+            Some(name) => self
+                .cursors
+                .remove(name)
+                .map(|_| ())
+                .ok_or_else(|| not_found(name)),
+            None => {
+                self.cursors.clear();
+                Ok(())
+            }
+        }
+    }
+
+    /// Record that a statement starting or ending a transaction succeeded, closing the cursors
+    /// that don't outlive the transaction they were declared in if it ended
+    pub(crate) fn transaction_boundary(&mut self, boundary: TransactionBoundary) {
+        match boundary {
+            TransactionBoundary::Start => self.in_transaction = true,
+            TransactionBoundary::End => {
+                self.in_transaction = false;
+                self.cursors.retain(|_, cursor| cursor.hold);
+            }
+        }
+    }
+}
+
+fn not_found(name: &str) -> ps::Error {
+    ps::Error::Database {
+        sqlstate: SqlState::INVALID_CURSOR_NAME,
+        message: format!("cursor \"{name}\" does not exist"),
+        fields: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(query: &str) -> CursorStatement<'_> {
+        parse(query).unwrap().unwrap()
+    }
+
+    #[test]
+    fn parse_declare() {
+        assert_eq!(
+            parse_ok("DECLARE _psql_cursor NO SCROLL CURSOR FOR SELECT * FROM t;"),
+            CursorStatement::Declare {
+                name: "_psql_cursor".into(),
+                hold: false,
+                query: "SELECT * FROM t",
+            }
+        );
+        assert_eq!(
+            parse_ok("declare \"My \"\"Cursor\" cursor with hold for select 1"),
+            CursorStatement::Declare {
+                name: "My \"Cursor".into(),
+                hold: true,
+                query: "select 1",
+            }
+        );
+        assert!(parse("DECLARE c BINARY CURSOR FOR SELECT 1")
+            .unwrap()
+            .is_err());
+        assert!(parse("DECLARE c CURSOR WITH SELECT 1").unwrap().is_err());
+        assert!(parse("DECLARE c CURSOR FOR").unwrap().is_err());
+        assert!(parse("SELECT * FROM declared").is_none());
+    }
+
+    #[test]
+    fn parse_fetch() {
+        let fetch = |name: &str, count| CursorStatement::Fetch {
+            name: name.into(),
+            count,
+            skip: false,
+        };
+        assert_eq!(
+            parse_ok("FETCH FORWARD 100 FROM _psql_cursor"),
+            fetch("_psql_cursor", FetchCount::Count(100))
+        );
+        assert_eq!(parse_ok("fetch c"), fetch("c", FetchCount::Count(1)));
+        assert_eq!(
+            parse_ok("FETCH NEXT IN c;"),
+            fetch("c", FetchCount::Count(1))
+        );
+        assert_eq!(parse_ok("FETCH ALL c"), fetch("c", FetchCount::All));
+        assert_eq!(
+            parse_ok("FETCH 5 FROM \"C\""),
+            fetch("C", FetchCount::Count(5))
+        );
+        assert_eq!(
+            parse_ok("MOVE FORWARD ALL IN c"),
+            CursorStatement::Fetch {
+                name: "c".into(),
+                count: FetchCount::All,
+                skip: true,
+            }
+        );
+        assert!(parse("FETCH BACKWARD 5 FROM c").unwrap().is_err());
+        assert!(parse("FETCH -1 FROM c").unwrap().is_err());
+        assert!(parse("FETCH 5 FROM c d").unwrap().is_err());
+    }
+
+    #[test]
+    fn parse_close() {
+        assert_eq!(
+            parse_ok("CLOSE _psql_cursor"),
+            CursorStatement::Close(Some("_psql_cursor".into()))
+        );
+        assert_eq!(parse_ok("close all;"), CursorStatement::Close(None));
+    }
+
+    #[test]
+    fn transaction_boundaries() {
+        use TransactionBoundary::*;
+
+        assert_eq!(transaction_boundary("BEGIN"), Some(Start));
+        assert_eq!(
+            transaction_boundary("start transaction read only"),
+            Some(Start)
+        );
+        assert_eq!(transaction_boundary("COMMIT"), Some(End));
+        assert_eq!(transaction_boundary("rollback;"), Some(End));
+        assert_eq!(transaction_boundary("END TRANSACTION"), Some(End));
+        assert_eq!(transaction_boundary("ROLLBACK TO SAVEPOINT s"), None);
+        assert_eq!(transaction_boundary("COMMIT PREPARED 'tx'"), None);
+        assert_eq!(transaction_boundary("SELECT * FROM commit"), None);
+    }
+
+    #[test]
+    fn cursors_close_at_transaction_end() {
+        let mut cursors = Cursors::default();
+        assert!(cursors
+            .declare("c1".into(), Cursor::new(vec![], Resultset::empty(), false))
+            .is_err());
+
+        cursors.transaction_boundary(TransactionBoundary::Start);
+        cursors
+            .declare("c1".into(), Cursor::new(vec![], Resultset::empty(), false))
+            .unwrap();
+        cursors
+            .declare("c2".into(), Cursor::new(vec![], Resultset::empty(), true))
+            .unwrap();
+        assert!(cursors
+            .declare("c2".into(), Cursor::new(vec![], Resultset::empty(), true))
+            .is_err());
+
+        cursors.transaction_boundary(TransactionBoundary::End);
+        assert!(cursors.get_mut("c1").is_err());
+        assert!(cursors.get_mut("c2").is_ok());
+        cursors.close(Some("c2")).unwrap();
+        assert!(cursors.close(Some("c2")).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 #![feature(box_patterns, type_alias_impl_trait)]
 mod backend;
+mod cursor;
 mod error;
 mod query_handler;
 mod response;
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use nom_sql::{
@@ -8,7 +9,7 @@ use nom_sql::{
 };
 use readyset_adapter::backend::noria_connector::QueryResult;
 use readyset_adapter::backend::{noria_connector, SelectSchema};
-use readyset_adapter::{QueryHandler, SetBehavior};
+use readyset_adapter::{QueryHandler, SetBehavior, NOW_PARAMETER_GRANULARITY_VARIABLE};
 use readyset_errors::ReadySetResult;
 
 enum AllowedParameterValue {
@@ -355,6 +356,20 @@ impl QueryHandler for PostgreSqlQueryHandler {
 
     fn handle_set_statement(stmt: &SetStatement) -> SetBehavior {
         match stmt {
+            SetStatement::PostgresParameter(SetPostgresParameter { name, value, .. })
+                if name.eq_ignore_ascii_case(NOW_PARAMETER_GRANULARITY_VARIABLE) =>
+            {
+                let granularity = match value {
+                    SetPostgresParameterValue::Value(PostgresParameterValue::Single(
+                        PostgresParameterValueInner::Literal(Literal::UnsignedInteger(secs)),
+                    )) => Some(*secs),
+                    SetPostgresParameterValue::Value(PostgresParameterValue::Single(
+                        PostgresParameterValueInner::Literal(Literal::Integer(secs)),
+                    )) => u64::try_from(*secs).ok(),
+                    _ => None,
+                };
+                SetBehavior::SetNowParameterGranularity(granularity.map(Duration::from_secs))
+            }
             SetStatement::PostgresParameter(SetPostgresParameter { name, .. })
                 if ALLOWED_PARAMETERS_ANY_VALUE.contains(name.to_ascii_lowercase().as_str()) =>
             {
@@ -454,6 +469,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn now_parameter_granularity() {
+        assert_eq!(
+            PostgreSqlQueryHandler::handle_set_statement(&parse_set_statement(
+                "SET readyset_now_parameter_granularity = 60"
+            )),
+            SetBehavior::SetNowParameterGranularity(Some(Duration::from_secs(60))),
+        );
+
+        assert_eq!(
+            PostgreSqlQueryHandler::handle_set_statement(&parse_set_statement(
+                "SET readyset_now_parameter_granularity TO off"
+            )),
+            SetBehavior::SetNowParameterGranularity(None),
+        );
+    }
+
     mod search_path {
         use super::*;
 
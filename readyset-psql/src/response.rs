@@ -9,7 +9,7 @@ use readyset_adapter::backend::{
 use readyset_client::results::{ResultIterator, Results};
 use readyset_client::ColumnSchema;
 use readyset_data::DfType;
-use upstream::StatementMeta;
+use upstream::{StatementMeta, WriteKind};
 
 use crate::resultset::Resultset;
 use crate::schema::{NoriaSchema, SelectSchema};
@@ -86,6 +86,14 @@ impl<'a> TryFrom<QueryResponse<'a>> for ps::QueryResponse<Resultset> {
                     resultset,
                 })
             }
+            Noria(NoriaResult::SelectPages { pages, schema }) => {
+                let select_schema = SelectSchema(schema);
+                let resultset = Resultset::from_readyset_pages(pages, &select_schema)?;
+                Ok(Select {
+                    schema: select_schema.try_into()?,
+                    resultset,
+                })
+            }
             Noria(NoriaResult::Update {
                 num_rows_updated, ..
             }) => Ok(Update(num_rows_updated)),
@@ -214,9 +222,15 @@ impl<'a> TryFrom<QueryResponse<'a>> for ps::QueryResponse<Resultset> {
                     resultset: Resultset::from_stream(stream, first_row, field_types),
                 })
             }
-            Upstream(upstream::QueryResult::Write { num_rows_affected }) => {
-                Ok(Insert(num_rows_affected))
-            }
+            Upstream(upstream::QueryResult::Write {
+                num_rows_affected,
+                kind,
+            }) => Ok(match kind {
+                WriteKind::Insert => Insert(num_rows_affected),
+                WriteKind::Update => Update(num_rows_affected),
+                WriteKind::Delete => Delete(num_rows_affected),
+                WriteKind::Other => Command,
+            }),
             Upstream(upstream::QueryResult::Command) => Ok(Command),
             Upstream(upstream::QueryResult::SimpleQuery(resp)) => Ok(SimpleQuery(resp)),
         }
@@ -2,9 +2,11 @@ use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
-use futures::{ready, Stream};
+use futures::{ready, Stream, StreamExt};
 use psql_srv as ps;
-use readyset_client::results::ResultIterator;
+use readyset_adapter::backend::noria_connector::ResultPages;
+use readyset_client::results::{ResultIterator, Results};
+use readyset_data::DfValue;
 use tokio_postgres::types::Type;
 use tokio_postgres::{GenericResult, ResultStream};
 
@@ -14,6 +16,11 @@ use crate::schema::{type_to_pgsql, SelectSchema};
 enum ResultsetInner {
     Empty,
     ReadySet(Box<<ResultIterator as IntoIterator>::IntoIter>),
+    /// Rows read from ReadySet one page at a time, with the rows of the page that was last read
+    ReadySetPages {
+        rows: std::vec::IntoIter<Vec<DfValue>>,
+        pages: ResultPages,
+    },
     Stream {
         first_row: Option<tokio_postgres::Row>,
         stream: Pin<Box<ResultStream>>,
@@ -43,21 +50,37 @@ impl Resultset {
         results: ResultIterator,
         schema: &SelectSchema,
     ) -> Result<Self, ps::Error> {
-        // Extract the appropriate `tokio_postgres` `Type` for each column in the schema.
-        let project_field_types = Arc::new(
-            schema
-                .0
-                .schema
-                .iter()
-                .map(|c| type_to_pgsql(&c.column_type))
-                .collect::<Result<Vec<_>, _>>()?,
-        );
         Ok(Resultset {
             results: ResultsetInner::ReadySet(Box::new(results.into_iter())),
-            project_field_types,
+            project_field_types: project_field_types(schema)?,
+        })
+    }
+
+    /// Creates a resultset of rows read from ReadySet one page at a time, reading each page once
+    /// the rows of the one before it have been sent
+    pub fn from_readyset_pages(
+        pages: ResultPages,
+        schema: &SelectSchema,
+    ) -> Result<Self, ps::Error> {
+        Ok(Resultset {
+            results: ResultsetInner::ReadySetPages {
+                rows: Vec::new().into_iter(),
+                pages,
+            },
+            project_field_types: project_field_types(schema)?,
         })
     }
 
+    /// Creates a resultset of rows which have already been read, with the given field types
+    pub fn from_rows(rows: Vec<Vec<DfValue>>, project_field_types: Arc<Vec<Type>>) -> Self {
+        Self {
+            results: ResultsetInner::ReadySet(Box::new(
+                ResultIterator::owned(vec![Results::new(rows)]).into_iter(),
+            )),
+            project_field_types,
+        }
+    }
+
     pub fn from_stream(
         stream: Pin<Box<ResultStream>>,
         first_row: tokio_postgres::Row,
@@ -71,6 +94,24 @@ impl Resultset {
             project_field_types: Arc::new(schema),
         }
     }
+
+    /// Returns true if the results are being streamed from the upstream database, in which case
+    /// the upstream connection can't be used for anything else until they've all been read
+    pub fn is_upstream_stream(&self) -> bool {
+        matches!(self.results, ResultsetInner::Stream { .. })
+    }
+}
+
+/// Extract the appropriate `tokio_postgres` `Type` for each column in the schema.
+fn project_field_types(schema: &SelectSchema) -> Result<Arc<Vec<Type>>, ps::Error> {
+    Ok(Arc::new(
+        schema
+            .0
+            .schema
+            .iter()
+            .map(|c| type_to_pgsql(&c.column_type))
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
 }
 
 impl Stream for Resultset {
@@ -81,6 +122,16 @@ impl Stream for Resultset {
         let next = match &mut self.get_mut().results {
             ResultsetInner::Empty => None,
             ResultsetInner::ReadySet(i) => i.next().map(Ok),
+            ResultsetInner::ReadySetPages { rows, pages } => loop {
+                if let Some(row) = rows.next() {
+                    break Some(Ok(row));
+                }
+                match ready!(pages.poll_next_unpin(cx)) {
+                    None => break None,
+                    Some(Err(e)) => break Some(Err(ps::Error::from(crate::Error::from(e)))),
+                    Some(Ok(page)) => *rows = page.into_iter(),
+                }
+            },
             ResultsetInner::Stream { first_row, stream } => {
                 let row = match first_row.take() {
                     Some(row) => Some(Ok(row)),
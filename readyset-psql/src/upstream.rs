@@ -17,6 +17,7 @@ use psql_srv::Column;
 use readyset_adapter::fallback_cache::FallbackCache;
 use readyset_adapter::upstream_database::{NoriaCompare, UpstreamDestination};
 use readyset_adapter::{UpstreamConfig, UpstreamDatabase, UpstreamPrepare};
+use readyset_client::replication::ReplicationOffset;
 use readyset_client::ColumnSchema;
 use readyset_data::DfValue;
 use readyset_errors::{internal_err, invariant_eq, unsupported, ReadySetError, ReadySetResult};
@@ -38,8 +39,9 @@ pub struct PostgreSqlUpstream {
     client: pgsql::Client,
     /// A tokio task that handles the connection, required by `tokio_postgres` to operate
     _connection_handle: tokio::task::JoinHandle<Result<(), pgsql::Error>>,
-    /// Map from prepared statement IDs to prepared statements
-    prepared_statements: HashMap<u32, pgsql::Statement>,
+    /// Map from prepared statement IDs to prepared statements, along with the kind of write each
+    /// one performs
+    prepared_statements: HashMap<u32, (pgsql::Statement, WriteKind)>,
     /// ID for the next prepared statement
     statement_id_counter: u32,
     /// The user used to connect to the upstream, if any
@@ -59,11 +61,64 @@ pub enum QueryResult {
     },
     Write {
         num_rows_affected: u64,
+        kind: WriteKind,
     },
     Command,
     SimpleQuery(Vec<SimpleQueryMessage>),
 }
 
+/// The kind of write a prepared statement performs, which determines the tag of the
+/// `CommandComplete` message sent to the client once it's executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteKind {
+    Insert,
+    Update,
+    Delete,
+    /// Any other statement, such as DDL
+    Other,
+}
+
+impl WriteKind {
+    /// Determine the kind of write `query` performs from its leading keyword, looking past any
+    /// common table expressions
+    fn of_query(query: &str) -> Self {
+        let mut depth = 0usize;
+        let mut quote = None;
+        let mut word = String::new();
+        let mut in_with = false;
+        for c in query.chars().chain(std::iter::once(' ')) {
+            if let Some(q) = quote {
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            if c.is_ascii_alphabetic() || c == '_' {
+                word.push(c.to_ascii_uppercase());
+                continue;
+            }
+            if depth == 0 && !word.is_empty() {
+                match word.as_str() {
+                    "INSERT" => return Self::Insert,
+                    "UPDATE" => return Self::Update,
+                    "DELETE" => return Self::Delete,
+                    "WITH" if !in_with => in_with = true,
+                    _ if !in_with => return Self::Other,
+                    _ => {}
+                }
+            }
+            word.clear();
+            match c {
+                '\'' | '"' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        Self::Other
+    }
+}
+
 impl Debug for QueryResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -76,9 +131,13 @@ impl Debug for QueryResult {
                 .field("first_row", first_row)
                 .field("stream", &"...")
                 .finish(),
-            Self::Write { num_rows_affected } => f
+            Self::Write {
+                num_rows_affected,
+                kind,
+            } => f
                 .debug_struct("Write")
                 .field("num_rows_affected", num_rows_affected)
+                .field("kind", kind)
                 .finish(),
             Self::Command => write!(f, "Command"),
             Self::SimpleQuery(ms) => f.debug_tuple("SimpleQuery").field(ms).finish(),
@@ -329,11 +388,18 @@ impl UpstreamDatabase for PostgreSqlUpstream {
 
         self.statement_id_counter += 1;
         let statement_id = self.statement_id_counter;
-        self.prepared_statements.insert(statement_id, statement);
+        self.prepared_statements
+            .insert(statement_id, (statement, WriteKind::of_query(query)));
 
         Ok(UpstreamPrepare { statement_id, meta })
     }
 
+    async fn remove_statement(&mut self, statement_id: u32) -> Result<(), Error> {
+        // Dropping the statement closes it in the upstream database once nothing else is using it
+        self.prepared_statements.remove(&statement_id);
+        Ok(())
+    }
+
     async fn query<'a, S>(&'a mut self, query: S) -> Result<Self::QueryResult<'a>, Error>
     where
         S: AsRef<str> + Send + Sync + 'a,
@@ -357,7 +423,7 @@ impl UpstreamDatabase for PostgreSqlUpstream {
         statement_id: u32,
         params: &[DfValue],
     ) -> Result<Self::QueryResult<'a>, Error> {
-        let statement = self
+        let (statement, kind) = self
             .prepared_statements
             .get(&statement_id)
             .ok_or(ReadySetError::PreparedStatementMissing { statement_id })?;
@@ -374,9 +440,10 @@ impl UpstreamDatabase for PostgreSqlUpstream {
         match stream.next().await {
             None => Ok(QueryResult::EmptyRead),
             Some(Err(e)) => Err(e.into()),
-            Some(Ok(GenericResult::NumRows(num_rows_affected))) => {
-                Ok(QueryResult::Write { num_rows_affected })
-            }
+            Some(Ok(GenericResult::NumRows(num_rows_affected))) => Ok(QueryResult::Write {
+                num_rows_affected,
+                kind: *kind,
+            }),
             Some(Ok(GenericResult::Row(first_row))) => {
                 Ok(QueryResult::Stream { first_row, stream })
             }
@@ -401,6 +468,28 @@ impl UpstreamDatabase for PostgreSqlUpstream {
         Ok(QueryResult::Command)
     }
 
+    async fn replication_offset(&mut self) -> Result<Option<ReplicationOffset>, Error> {
+        let lsn: i64 = self
+            .client
+            .query_one("SELECT (pg_current_wal_lsn() - '0/0')::bigint", &[])
+            .await?
+            .get(0);
+        Ok(Some(ReplicationOffset {
+            offset: lsn as _,
+            replication_log_name: String::new(),
+            gtid_set: None,
+        }))
+    }
+
+    async fn utc_offset(&mut self) -> Result<Option<i32>, Error> {
+        Ok(Some(
+            self.client
+                .query_one("SELECT EXTRACT(TIMEZONE FROM now())::int4", &[])
+                .await?
+                .get(0),
+        ))
+    }
+
     async fn schema_dump(&mut self) -> Result<Vec<u8>, anyhow::Error> {
         let config = pgsql::Config::from_str(self.url())?;
         let mut pg_dump = Command::new("pg_dump");
@@ -459,6 +548,28 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn write_kind_of_query() {
+        assert_eq!(
+            WriteKind::of_query("INSERT INTO t VALUES ($1)"),
+            WriteKind::Insert
+        );
+        assert_eq!(
+            WriteKind::of_query("  update t SET x = 'delete' WHERE id = $1"),
+            WriteKind::Update
+        );
+        assert_eq!(
+            WriteKind::of_query(
+                "WITH old AS (SELECT id FROM t WHERE \"insert\" < now()) DELETE FROM t USING old"
+            ),
+            WriteKind::Delete
+        );
+        assert_eq!(
+            WriteKind::of_query("CREATE TABLE t (x int)"),
+            WriteKind::Other
+        );
+    }
+
     fn test_column() -> NomColumn {
         NomColumn {
             name: "t".into(),
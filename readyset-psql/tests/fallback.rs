@@ -1034,3 +1034,57 @@ async fn replication_failure_retries_if_failed_to_drop(failpoint: &str) {
 
     shutdown_tx.shutdown().await;
 }
+
+#[tokio::test(flavor = "multi_thread")]
+#[serial]
+async fn cursors_in_transactions() {
+    let (config, _handle, shutdown_tx) = setup().await;
+    let client = connect(config).await;
+
+    client
+        .simple_query("CREATE TABLE cursor_t (x int)")
+        .await
+        .unwrap();
+    client
+        .simple_query("INSERT INTO cursor_t (x) VALUES (1), (2), (3)")
+        .await
+        .unwrap();
+    sleep().await;
+
+    let fetch = |query: &'static str| {
+        let client = &client;
+        async move {
+            client
+                .simple_query(query)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter_map(|m| match m {
+                    SimpleQueryMessage::Row(r) => Some(r.get(0).unwrap().to_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    client.simple_query("BEGIN").await.unwrap();
+    // Reads in a transaction are proxied upstream, so the cursor's rows are streamed from there
+    client
+        .simple_query("DECLARE c CURSOR FOR SELECT x FROM cursor_t ORDER BY x")
+        .await
+        .unwrap();
+    assert_eq!(fetch("FETCH 1 FROM c").await, vec!["1"]);
+    // Running another query while the cursor has rows left reads them ahead of the next fetch
+    assert_eq!(fetch("SELECT count(*) FROM cursor_t").await, vec!["3"]);
+    assert_eq!(fetch("FETCH ALL FROM c").await, vec!["2", "3"]);
+
+    // Committing with the extended query protocol closes the cursor too
+    client.execute("COMMIT", &[]).await.unwrap();
+    client.simple_query("FETCH c").await.unwrap_err();
+    client
+        .simple_query("DECLARE c CURSOR FOR SELECT x FROM cursor_t ORDER BY x")
+        .await
+        .unwrap_err();
+
+    shutdown_tx.shutdown().await;
+}
@@ -1553,3 +1553,44 @@ async fn same_query_different_search_path() {
 
     shutdown_tx.shutdown().await;
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn declare_and_fetch_cursor() {
+    let (opts, _handle, shutdown_tx) = setup().await;
+    let conn = connect(opts).await;
+    conn.simple_query("CREATE TABLE t (x int)").await.unwrap();
+    conn.simple_query("INSERT INTO t (x) VALUES (1), (2), (3)")
+        .await
+        .unwrap();
+    sleep().await;
+
+    let fetch = |query: &'static str| {
+        let conn = &conn;
+        async move {
+            conn.simple_query(query)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter_map(|m| match m {
+                    SimpleQueryMessage::Row(r) => Some(r.get(0).unwrap().to_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Cursors without HOLD can only be declared in a transaction
+    conn.simple_query("DECLARE c NO SCROLL CURSOR FOR SELECT x FROM t ORDER BY x")
+        .await
+        .unwrap_err();
+    conn.simple_query("DECLARE c NO SCROLL CURSOR WITH HOLD FOR SELECT x FROM t ORDER BY x")
+        .await
+        .unwrap();
+    assert_eq!(fetch("FETCH FORWARD 2 FROM c").await, vec!["1", "2"]);
+    assert_eq!(fetch("FETCH FORWARD 2 FROM c").await, vec!["3"]);
+    assert!(fetch("FETCH FORWARD 2 FROM c").await.is_empty());
+    conn.simple_query("CLOSE c").await.unwrap();
+    conn.simple_query("FETCH c").await.unwrap_err();
+
+    shutdown_tx.shutdown().await;
+}
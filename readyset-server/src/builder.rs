@@ -15,7 +15,7 @@ use readyset_util::shutdown::{self, ShutdownSender};
 
 use crate::controller::replication::ReplicationStrategy;
 use crate::handle::Handle;
-use crate::{Config, FrontierStrategy, ReuseConfigType, VolumeId};
+use crate::{BaseTableShardColumn, Config, FrontierStrategy, ReuseConfigType, VolumeId};
 
 /// Used to construct a worker.
 #[derive(Clone)]
@@ -61,11 +61,24 @@ impl Builder {
             builder.set_memory_limit(opts.memory, Duration::from_secs(opts.memory_check_freq));
         }
         builder.set_eviction_kind(opts.eviction_kind);
+        if let Some(dir) = opts.reader_spill_dir {
+            builder.set_reader_spill(
+                dir,
+                (opts.reader_spill_max_bytes > 0).then_some(opts.reader_spill_max_bytes),
+            );
+        }
+        if opts.cache_memory_quota > 0 {
+            builder.set_cache_memory_quota(opts.cache_memory_quota);
+        }
+        if opts.memory_budget > 0 {
+            builder.set_memory_budget(opts.memory_budget);
+        }
 
         builder.set_sharding(match opts.shards {
             0 | 1 => None,
             x => Some(x),
         });
+        builder.set_base_table_shard_columns(opts.shard_base_table);
         builder.set_quorum(opts.quorum);
         if opts.no_partial {
             builder.disable_partial();
@@ -151,6 +164,12 @@ impl Builder {
         self.config.sharding = shards.filter(|s| *s > 1);
     }
 
+    /// Set the base tables to shard by a column other than their primary key, if sharding is
+    /// enabled with [`Self::set_sharding`]
+    pub fn set_base_table_shard_columns(&mut self, shard_columns: Vec<BaseTableShardColumn>) {
+        self.config.base_table_shard_columns = shard_columns;
+    }
+
     /// Set how many workers this worker should wait for before becoming a controller. More workers
     /// can join later, but they won't be assigned any of the initial domains.
     pub fn set_quorum(&mut self, quorum: usize) {
@@ -215,6 +234,12 @@ impl Builder {
         self.config.replicator_config.upstream_db_url = Some(url.into());
     }
 
+    /// Sets the list of tables to replicate, in the format of
+    /// [`replicators::Config::replication_tables`]
+    pub fn set_replication_tables(&mut self, tables: String) {
+        self.config.replicator_config.replication_tables = Some(tables.into());
+    }
+
     /// Sets configuration for the replicator thread
     pub fn set_replicator_config(&mut self, config: UpstreamConfig) {
         self.config.replicator_config = config;
@@ -306,6 +331,26 @@ impl Builder {
         self.config.domain_config.eviction_kind = value;
     }
 
+    /// Sets the values of [`Config::domain_config::reader_spill_dir`] and
+    /// [`Config::domain_config::reader_spill_max_bytes`]. See documentation of those fields for
+    /// more information.
+    pub fn set_reader_spill(&mut self, dir: PathBuf, max_bytes: Option<u64>) {
+        self.config.domain_config.reader_spill_dir = Some(dir);
+        self.config.domain_config.reader_spill_max_bytes = max_bytes;
+    }
+
+    /// Sets the value of [`Config::domain_config::cache_memory_quota`]. See documentation of that
+    /// field for more information.
+    pub fn set_cache_memory_quota(&mut self, quota: usize) {
+        self.config.domain_config.cache_memory_quota = Some(quota);
+    }
+
+    /// Sets the value of [`Config::domain_config::memory_budget`]. See documentation of that
+    /// field for more information.
+    pub fn set_memory_budget(&mut self, budget: usize) {
+        self.config.domain_config.memory_budget = Some(budget);
+    }
+
     /// Assigns a telemetry reporter to this ReadySet server
     pub fn set_telemetry_sender(&mut self, value: TelemetrySender) {
         self.telemetry = value;
@@ -317,11 +362,6 @@ impl Builder {
         self.wait_for_failpoint = value;
     }
 
-    /// Sets whether to log statements in the replicator
-    pub fn set_replicator_statement_logging(&mut self, value: bool) {
-        self.config.replicator_statement_logging = value;
-    }
-
     /// Start a server instance and return a handle to it. This method also returns a
     /// [`ShutdownSender`] that should be used to shut down the server when it is no longer needed.
     pub fn start(
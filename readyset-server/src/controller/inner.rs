@@ -14,17 +14,20 @@ use std::time::Duration;
 use database_utils::UpstreamConfig;
 use failpoint_macros::failpoint;
 use hyper::Method;
+use nom_sql::Relation;
 use readyset_client::consensus::Authority;
 use readyset_client::internal::ReplicaAddress;
 use readyset_client::recipe::ExtendRecipeSpec;
-use readyset_client::replication::ReplicationOffset;
+use readyset_client::replication::{ReplicationCaptureRequest, ReplicationOffset};
 use readyset_client::status::{ReadySetStatus, SnapshotStatus};
 use readyset_client::WorkerDescriptor;
-use readyset_errors::{ReadySetError, ReadySetResult};
+use readyset_errors::{unsupported, ReadySetError, ReadySetResult};
 use readyset_telemetry_reporter::TelemetrySender;
+use readyset_tracing::statement_logging;
 use readyset_util::futures::abort_on_panic;
 use readyset_util::shutdown::ShutdownReceiver;
 use readyset_version::RELEASE_VERSION;
+use replicators::{ConsistencyChecks, ReplicationCapture, ReplicationHealth, ResnapshotRequests};
 use reqwest::Url;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Notify;
@@ -54,10 +57,17 @@ pub struct Leader {
 
     /// The amount of time to wait for a worker request to complete.
     worker_request_timeout: Duration,
-    /// Whether to log statements received by the replicators
-    replicator_statement_logging: bool,
     /// Configuration for the replicator
     pub(super) replicator_config: UpstreamConfig,
+    /// Tables requested to be resnapshotted by the replicators, with `ALTER READYSET RESNAPSHOT
+    /// TABLE`
+    resnapshot_requests: ResnapshotRequests,
+    /// The capture of replication events to a file in progress, if any
+    replication_capture: ReplicationCapture,
+    /// The results of the consistency checks of replicated tables
+    consistency_checks: ConsistencyChecks,
+    /// Whether the replicators are connected to each upstream database, and how far behind it
+    replication_health: ReplicationHealth,
     /// A client to the current authority.
     pub(super) authority: Arc<Authority>,
 }
@@ -89,6 +99,9 @@ impl Leader {
     /// a connection to the primary was lost for any reason, all we want is to
     /// connect again, and catch up from the binlog
     ///
+    /// A separate replication loop is run for each upstream database, as listed by
+    /// [`UpstreamConfig::replication_sources`].
+    ///
     /// TODO: how to handle the case where we need a full new replica
     async fn start_replication_task(
         &mut self,
@@ -105,59 +118,123 @@ impl Leader {
 
         let authority = Arc::clone(&self.authority);
         let replicator_restart_timeout = self.replicator_config.replicator_restart_timeout;
-        let config = self.replicator_config.clone();
-        let replicator_statement_logging = self.replicator_statement_logging;
+        let shutdown_grace_period = self.replicator_config.replicator_shutdown_grace_period;
+        let sources = self.replicator_config.replication_sources();
+        let replicator_config = self.replicator_config.clone();
+        let resnapshot_requests = self.resnapshot_requests.clone();
+        let replication_capture = self.replication_capture.clone();
+        let consistency_checks = self.consistency_checks.clone();
+        let replication_health = self.replication_health.clone();
+
+        // Each upstream we replicate from notifies once its initial snapshot is complete, and we're
+        // only ready once all of them have
+        let source_notifications = sources
+            .iter()
+            .map(|_| Arc::new(Notify::new()))
+            .collect::<Vec<_>>();
+        tokio::spawn({
+            let source_notifications = source_notifications.clone();
+            async move {
+                for notification in source_notifications {
+                    notification.notified().await;
+                }
+                ready_notification.notify_one();
+            }
+        });
 
         // The replication task ideally won't panic, but if it does and we arent replicating, that
         // will mean the data we return, will be more and more stale, and the transaction logs on
         // the upstream will be filling up disk
         // So, we abort on any panic of the replicator task.
         tokio::spawn(abort_on_panic(async move {
-            let replication_future = async move {
-                // The replicator wants to know if we're restarting the server so that it can
-                // resnapshot to capture changes made to replication-tables.
-                let mut server_startup = true;
-                loop {
-                    let noria: readyset_client::ReadySetHandle =
-                        readyset_client::ReadySetHandle::new(Arc::clone(&authority)).await;
-
-                    match replicators::NoriaAdapter::start(
-                        noria,
-                        config.clone(),
-                        Some(ready_notification.clone()),
-                        telemetry_sender.clone(),
-                        server_startup,
-                        replicator_statement_logging,
-                    )
-                    .await
-                    {
-                        // Unrecoverable errors, propagate the error the controller and kill the
-                        // loop.
-                        Err(err @ ReadySetError::RecipeInvariantViolated(_)) => {
-                            if let Err(e) = replication_error.send(err) {
-                                error!(error = %e, "Could not notify controller of critical error. The system may be in an invalid state");
+            let replication_futures = sources.into_iter().zip(source_notifications).map(
+                |(config, ready_notification)| {
+                    let authority = Arc::clone(&authority);
+                    let telemetry_sender = telemetry_sender.clone();
+                    let replication_error = replication_error.clone();
+                    let resnapshot_requests = resnapshot_requests.clone();
+                    let replication_capture = replication_capture.clone();
+                    let consistency_checks = consistency_checks.clone();
+                    let replication_health = replication_health.clone();
+                    let shutdown_rx = shutdown_rx.clone();
+                    async move {
+                        // The replicator wants to know if we're restarting the server so that it
+                        // can resnapshot to capture changes made to replication-tables.
+                        let mut server_startup = true;
+                        loop {
+                            let noria: readyset_client::ReadySetHandle =
+                                readyset_client::ReadySetHandle::new(Arc::clone(&authority)).await;
+
+                            match replicators::NoriaAdapter::start(
+                                noria,
+                                config.clone(),
+                                Some(ready_notification.clone()),
+                                telemetry_sender.clone(),
+                                server_startup,
+                                resnapshot_requests.clone(),
+                                replication_capture.clone(),
+                                consistency_checks.clone(),
+                                replication_health.clone(),
+                                shutdown_rx.clone(),
+                            )
+                            .await
+                            {
+                                // Replication stopped cleanly after a shutdown signal
+                                Ok(()) => break,
+                                // Unrecoverable errors, propagate the error the controller and
+                                // kill the loop.
+                                Err(err @ ReadySetError::RecipeInvariantViolated(_)) => {
+                                    if let Err(e) = replication_error.send(err) {
+                                        error!(error = %e, "Could not notify controller of critical error. The system may be in an invalid state");
+                                    }
+                                    break;
+                                }
+                                Err(error) => {
+                                    // On each replication error we wait for
+                                    // `replicator_restart_timeout` then try again
+                                    error!(
+                                        target: "replicators",
+                                        %error,
+                                        source = ?config.replication_source,
+                                        timeout_sec=replicator_restart_timeout.as_secs(),
+                                        "Error in replication, will retry after timeout"
+                                    );
+                                    tokio::time::sleep(replicator_restart_timeout).await;
+                                }
                             }
-                            break;
-                        }
-                        Err(error) => {
-                            // On each replication error we wait for `replicator_restart_timeout`
-                            // then try again
-                            error!(
-                                target: "replicators",
-                                %error,
-                                timeout_sec=replicator_restart_timeout.as_secs(),
-                                "Error in replication, will retry after timeout"
-                            );
-                            tokio::time::sleep(replicator_restart_timeout).await;
+                            server_startup = false;
                         }
                     }
-                    server_startup = false;
-                }
+                },
+            );
+
+            let mut replication = Box::pin(futures::future::join_all(replication_futures));
+            let shutdown = tokio::select! {
+                _ = &mut replication => false,
+                _ = shutdown_rx.recv() => true,
             };
 
-            tokio::select! {
-                _ = replication_future => {},
-                _ = shutdown_rx.recv() => {},
+            // Each replicator stops on its own once it receives the shutdown signal, after
+            // persisting its replication offset and disconnecting from the upstream database, but
+            // we can't wait for that forever
+            if shutdown
+                && tokio::time::timeout(shutdown_grace_period, &mut replication)
+                    .await
+                    .is_err()
+            {
+                warn!(
+                    grace_period_secs = shutdown_grace_period.as_secs(),
+                    "Replication did not stop within its shutdown grace period, stopping it"
+                );
+            }
+            drop(replication);
+
+            // Replication has stopped now that the replication futures have been dropped
+            if shutdown && replicator_config.drop_replication_slot_on_shutdown {
+                info!("Dropping replication slots on shutdown");
+                if let Err(error) = replicators::drop_replication_slots(replicator_config).await {
+                    warn!(%error, "Could not drop replication slots on shutdown");
+                }
             }
         }));
     }
@@ -314,6 +391,11 @@ impl Leader {
                     check_quorum!(ds);
                     return_serialized!(ds.verbose_views())
                 }
+                (&Method::POST, "/cache_statements") => {
+                    let ds = futures::executor::block_on(self.dataflow_state_handle.read());
+                    check_quorum!(ds);
+                    return_serialized!(ds.cache_statements())
+                }
                 (&Method::POST, "/view_statuses") => {
                     let (queries, dialect) = bincode::deserialize(&body)?;
                     let ds = futures::executor::block_on(self.dataflow_state_handle.read());
@@ -422,6 +504,73 @@ impl Leader {
                     })?;
                     return_serialized!(res);
                 }
+                (&Method::POST, "/quarantined_tables") => {
+                    let ds = futures::executor::block_on(self.dataflow_state_handle.read());
+                    check_quorum!(ds);
+                    return_serialized!(ds.quarantined_tables());
+                }
+                (&Method::POST, "/resnapshot_table") => {
+                    let table: Relation = bincode::deserialize(&body)?;
+                    if self.replicator_config.upstream_db_url.is_none() {
+                        unsupported!("Cannot resnapshot a table without an upstream database");
+                    }
+                    let ds = futures::executor::block_on(self.dataflow_state_handle.read());
+                    check_quorum!(ds);
+                    if !ds.tables().contains_key(&table) {
+                        return Err(ReadySetError::TableNotFound {
+                            name: table.name.to_string(),
+                            schema: table.schema.as_ref().map(|s| s.to_string()),
+                        });
+                    }
+                    info!(
+                        table = %table.display_unquoted(),
+                        "Requesting resnapshot of table"
+                    );
+                    self.resnapshot_requests.request(table);
+                    return_serialized!(());
+                }
+                (&Method::POST, "/capture_replication_events") => {
+                    let request: ReplicationCaptureRequest = bincode::deserialize(&body)?;
+                    if self.replicator_config.upstream_db_url.is_none() {
+                        unsupported!(
+                            "Cannot capture replication events without an upstream database"
+                        );
+                    }
+                    self.replication_capture.start(request)?;
+                    return_serialized!(());
+                }
+                (&Method::POST, "/replicator_statement_logging") => {
+                    let enabled: Option<bool> = bincode::deserialize(&body)?;
+                    let component = statement_logging::Component::Replicator;
+                    if let Some(enabled) = enabled {
+                        component.set_enabled(enabled);
+                    }
+                    return_serialized!(component.is_enabled());
+                }
+                (&Method::POST, "/table_checksum") => {
+                    let table: Relation = bincode::deserialize(&body)?;
+                    let res = futures::executor::block_on(async move {
+                        let ds = self.dataflow_state_handle.read().await;
+                        check_quorum!(ds);
+                        ds.table_checksum(&table).await
+                    })?;
+                    return_serialized!(res);
+                }
+                (&Method::POST, "/trace_reader") => {
+                    let (name, duration): (Relation, Duration) = bincode::deserialize(&body)?;
+                    let res = futures::executor::block_on(async move {
+                        let ds = self.dataflow_state_handle.read().await;
+                        check_quorum!(ds);
+                        ds.trace_reader(&name, duration).await
+                    })?;
+                    return_serialized!(res);
+                }
+                (&Method::POST, "/consistency_check") => {
+                    return_serialized!(self.consistency_checks.results());
+                }
+                (&Method::POST, "/replication_health") => {
+                    return_serialized!(self.replication_health.sources());
+                }
                 (&Method::POST, "/snapshotting_tables") => {
                     // this method can't be `async` since `Leader` isn't Send because `Graph`
                     // isn't Send :(
@@ -432,6 +581,23 @@ impl Leader {
                     })?;
                     return_serialized!(res);
                 }
+                (&Method::POST, "/cache_stats") => {
+                    let max_keys = bincode::deserialize(&body)?;
+                    let res = futures::executor::block_on(async move {
+                        let ds = self.dataflow_state_handle.read().await;
+                        check_quorum!(ds);
+                        ds.cache_stats(max_keys).await
+                    })?;
+                    return_serialized!(res);
+                }
+                (&Method::POST, "/memory_usage") => {
+                    let res = futures::executor::block_on(async move {
+                        let ds = self.dataflow_state_handle.read().await;
+                        check_quorum!(ds);
+                        ds.memory_usage().await
+                    })?;
+                    return_serialized!(res);
+                }
                 (&Method::POST, "/node_sizes") => {
                     let res = futures::executor::block_on(async move {
                         let ds = self.dataflow_state_handle.read().await;
@@ -529,6 +695,18 @@ impl Leader {
                 })?;
                 return_serialized!(ret);
             }
+            (&Method::POST, "/restore_all_queries") => {
+                require_leader_ready()?;
+                let dialect = bincode::deserialize(&body)?;
+                let ret = futures::executor::block_on(async move {
+                    let mut writer = self.dataflow_state_handle.write().await;
+                    check_quorum!(writer.as_ref());
+                    writer.as_mut().restore_all_queries(dialect).await?;
+                    self.dataflow_state_handle.commit(writer, authority).await?;
+                    Ok(())
+                })?;
+                return_serialized!(ret);
+            }
             (&Method::POST, "/set_schema_replication_offset") => {
                 let body: Option<ReplicationOffset> = bincode::deserialize(&body)?;
                 let ret = futures::executor::block_on(async move {
@@ -539,6 +717,29 @@ impl Leader {
                 })?;
                 return_serialized!(ret);
             }
+            (&Method::POST, "/set_source_schema_replication_offset") => {
+                let (source, offset): (String, Option<ReplicationOffset>) =
+                    bincode::deserialize(&body)?;
+                let ret = futures::executor::block_on(async move {
+                    let mut writer = self.dataflow_state_handle.write().await;
+                    check_quorum!(writer.as_ref());
+                    writer
+                        .as_mut()
+                        .set_source_schema_replication_offset(source, offset);
+                    self.dataflow_state_handle.commit(writer, authority).await
+                })?;
+                return_serialized!(ret);
+            }
+            (&Method::POST, "/set_table_quarantined") => {
+                let (table, quarantined): (Relation, bool) = bincode::deserialize(&body)?;
+                let ret = futures::executor::block_on(async move {
+                    let mut writer = self.dataflow_state_handle.write().await;
+                    check_quorum!(writer.as_ref());
+                    writer.as_mut().set_table_quarantined(table, quarantined);
+                    self.dataflow_state_handle.commit(writer, authority).await
+                })?;
+                return_serialized!(ret);
+            }
             (&Method::POST, "/remove_node") => {
                 require_leader_ready()?;
                 let body = bincode::deserialize(&body)?;
@@ -684,7 +885,6 @@ impl Leader {
         state: ControllerState,
         controller_uri: Url,
         authority: Arc<Authority>,
-        replicator_statement_logging: bool,
         replicator_config: UpstreamConfig,
         worker_request_timeout: Duration,
     ) -> Self {
@@ -707,8 +907,13 @@ impl Leader {
 
             controller_uri,
 
-            replicator_statement_logging,
+            replication_capture: ReplicationCapture::new(
+                replicator_config.replication_capture_dir.clone(),
+            ),
             replicator_config,
+            resnapshot_requests: ResnapshotRequests::default(),
+            consistency_checks: ConsistencyChecks::default(),
+            replication_health: ReplicationHealth::default(),
             authority,
             worker_request_timeout,
         }
@@ -724,6 +929,7 @@ pub(super) fn request_type(req: &ControllerRequest) -> ControllerRequestType {
         | (&Method::POST, "/extend_recipe")
         | (&Method::POST, "/remove_query")
         | (&Method::POST, "/remove_all_queries")
+        | (&Method::POST, "/restore_all_queries")
         | (&Method::POST, "/set_replication_offset")
         | (&Method::POST, "/replicate_readers")
         | (&Method::POST, "/remove_node") => ControllerRequestType::Write,
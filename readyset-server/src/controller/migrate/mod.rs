@@ -884,6 +884,7 @@ fn plan_add_nodes(
             &mut new_nodes,
             &topo,
             shards,
+            &dataflow_state.base_table_shard_columns,
         )?;
         topo = t;
 
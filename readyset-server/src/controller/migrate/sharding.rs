@@ -1,10 +1,51 @@
 use std::collections::{HashMap, HashSet};
 
 use dataflow::prelude::*;
-use dataflow::{node, ops};
+use dataflow::{node, ops, LookupIndex};
 use petgraph::graph::NodeIndex;
 use readyset_errors::{internal, invariant, invariant_eq, ReadySetResult};
-use tracing::{debug, error, info_span, trace};
+use tracing::{debug, error, info_span, trace, warn};
+
+use crate::BaseTableShardColumn;
+
+/// Returns the index of the column the base table `node` was declared to be sharded by in
+/// `base_table_shard_columns`, if any.
+///
+/// The column has to be part of the table's primary key, if it has one, so that rows with the same
+/// primary key always end up in the same shard, where the base node can enforce that it's unique.
+fn declared_shard_column(
+    node: &Node,
+    base_table_shard_columns: &[BaseTableShardColumn],
+) -> Option<usize> {
+    let base = node.get_base()?;
+    let shard_column = base_table_shard_columns
+        .iter()
+        .find(|shard_column| shard_column.table == *node.name())?;
+    let col = node
+        .columns()
+        .iter()
+        .position(|col| shard_column.column == col.name());
+    match (col, base.primary_key()) {
+        (None, _) => {
+            warn!(
+                table = %node.name().display_unquoted(),
+                column = %shard_column.column,
+                "Shard column not found in base table, sharding by its primary key instead"
+            );
+            None
+        }
+        (Some(col), Some(primary_key)) if !primary_key.contains(&col) => {
+            warn!(
+                table = %node.name().display_unquoted(),
+                column = %shard_column.column,
+                "Shard column is not part of the base table's primary key, sharding by its \
+                 primary key instead"
+            );
+            None
+        }
+        (col, _) => col,
+    }
+}
 
 #[allow(clippy::cognitive_complexity)]
 pub fn shard(
@@ -12,6 +53,7 @@ pub fn shard(
     new: &mut HashSet<NodeIndex>,
     topo_list: &[NodeIndex],
     sharding_factor: usize,
+    base_table_shard_columns: &[BaseTableShardColumn],
 ) -> ReadySetResult<(Vec<NodeIndex>, HashMap<(NodeIndex, NodeIndex), NodeIndex>)> {
     // we must keep track of changes we make to the parent of a node, since this remapping must be
     // communicated to the nodes so they know the true identifier of their parent in the graph.
@@ -28,9 +70,16 @@ pub fn shard(
             .collect();
 
         let mut need_sharding = if graph[node].is_internal() || graph[node].is_base() {
-            // suggest_indexes is okay because `node` *must* be new, and therefore will return
-            // global node indices.
-            graph[node].suggest_indexes(node)
+            if let Some(col) = declared_shard_column(&graph[node], base_table_shard_columns) {
+                // base tables with a declared shard column are sharded by that column, rather
+                // than by the primary key they look up into themselves by
+                debug!(column = col, "sharding base node by declared column");
+                HashMap::from([(node, LookupIndex::Strict(Index::hash_map(vec![col])))])
+            } else {
+                // suggest_indexes is okay because `node` *must* be new, and therefore will return
+                // global node indices.
+                graph[node].suggest_indexes(node)
+            }
         } else if let Some(r) = graph[node].as_reader() {
             invariant_eq!(input_shardings.len(), 1);
             let ni = input_shardings.keys().next().cloned().unwrap();
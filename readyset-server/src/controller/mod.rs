@@ -139,6 +139,7 @@ impl ControllerState {
             source,
             0,
             config.sharding,
+            config.base_table_shard_columns.clone(),
             config.domain_config.clone(),
             config.persistence.clone(),
             materializations,
@@ -492,7 +493,6 @@ impl Controller {
                     state,
                     self.our_descriptor.controller_uri.clone(),
                     self.authority.clone(),
-                    self.config.replicator_statement_logging,
                     self.config.replicator_config.clone(),
                     self.config.worker_request_timeout,
                 );
@@ -1233,6 +1233,7 @@ mod tests {
         let offset = ReplicationOffset {
             offset: 1,
             replication_log_name: "binlog".to_owned(),
+            gtid_set: None,
         };
 
         noria
@@ -1279,6 +1280,68 @@ mod tests {
         shutdown_tx.shutdown().await;
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn source_replication_offsets() {
+        let (mut noria, shutdown_tx) = start_simple("source_replication_offsets").await;
+
+        let offset = ReplicationOffset {
+            offset: 1,
+            replication_log_name: "binlog".to_owned(),
+            gtid_set: None,
+        };
+        let source_offset = ReplicationOffset {
+            offset: 2,
+            replication_log_name: "other-binlog".to_owned(),
+            gtid_set: None,
+        };
+
+        noria
+            .set_schema_replication_offset(Some(&offset))
+            .await
+            .unwrap();
+        noria
+            .set_source_schema_replication_offset("other", Some(&source_offset))
+            .await
+            .unwrap();
+
+        let offsets = noria.replication_offsets().await.unwrap();
+        assert_eq!(offsets.schema, Some(offset.clone()));
+        assert_eq!(offsets.sources["other"], source_offset);
+
+        let source = offsets.for_source(Some("other"), |_| true);
+        assert_eq!(source.schema, Some(source_offset));
+
+        noria
+            .set_source_schema_replication_offset("other", None)
+            .await
+            .unwrap();
+        let offsets = noria.replication_offsets().await.unwrap();
+        assert_eq!(offsets.schema, Some(offset));
+        assert!(offsets.sources.is_empty());
+
+        shutdown_tx.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn quarantined_tables() {
+        let (mut noria, shutdown_tx) = start_simple("quarantined_tables").await;
+        let table = Relation {
+            schema: Some("db".into()),
+            name: "t".into(),
+        };
+
+        assert!(noria.quarantined_tables().await.unwrap().is_empty());
+        noria.set_table_quarantined(&table, true).await.unwrap();
+        assert_eq!(
+            noria.quarantined_tables().await.unwrap(),
+            HashSet::from([table.clone()])
+        );
+        noria.set_table_quarantined(&table, false).await.unwrap();
+        assert!(noria.quarantined_tables().await.unwrap().is_empty());
+
+        shutdown_tx.shutdown().await;
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn key_count_rpc() {
         let (mut noria, shutdown_tx) = start_simple("all_tables").await;
@@ -1517,6 +1580,7 @@ mod tests {
             .set_replication_offset(ReplicationOffset {
                 offset: 1,
                 replication_log_name: "log".into(),
+                gtid_set: None,
             })
             .await
             .unwrap();
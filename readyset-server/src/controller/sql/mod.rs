@@ -6,7 +6,7 @@ use ::mir::visualize::GraphViz;
 use ::mir::DfNodeIndex;
 use ::serde::{Deserialize, Serialize};
 use nom_sql::{
-    CacheInner, CompoundSelectOperator, CompoundSelectStatement, CreateTableBody,
+    CacheInner, ColumnConstraint, CompoundSelectOperator, CompoundSelectStatement, CreateTableBody,
     FieldDefinitionExpr, Relation, SelectSpecification, SelectStatement, SqlIdentifier, SqlType,
     TableExpr,
 };
@@ -122,6 +122,45 @@ pub(crate) struct SqlIncorporator {
     permissive_writes: bool,
 }
 
+/// Returns true if `error`, from planning a query, means that the query isn't valid against the
+/// current schema of the tables and views it reads from
+fn invalid_for_schema(error: &ReadySetError) -> bool {
+    error.caused_by_missing_column()
+        || error.caused_by_table_not_found()
+        || error.caused_by_view_not_found()
+        || error.caused_by_invalid_query()
+        || error.caused_by_unsupported()
+}
+
+/// Returns true if the table with the body `current` has to be recreated to have the body `new`.
+///
+/// The character sets and collations of columns weren't always recorded when snapshotting
+/// tables, so a column only gaining (or losing) one of them doesn't count as a change - otherwise
+/// every table would be recreated the first time we snapshot after upgrading.
+fn table_changed(current: &CreateTableBody, new: &CreateTableBody) -> bool {
+    if current.fields.len() != new.fields.len() {
+        return true;
+    }
+
+    let mut current = current.clone();
+    let mut new = new.clone();
+    for (current_field, new_field) in current.fields.iter_mut().zip(new.fields.iter_mut()) {
+        let kinds: [fn(&ColumnConstraint) -> bool; 2] = [
+            |c| matches!(c, ColumnConstraint::CharacterSet(_)),
+            |c| matches!(c, ColumnConstraint::Collation(_)),
+        ];
+        for is_kind in kinds {
+            if !current_field.constraints.iter().any(is_kind)
+                || !new_field.constraints.iter().any(is_kind)
+            {
+                current_field.constraints.retain(|c| !is_kind(c));
+                new_field.constraints.retain(|c| !is_kind(c));
+            }
+        }
+    }
+    current != new
+}
+
 impl SqlIncorporator {
     /// Creates a new `SqlIncorporator` for an empty flow graph.
     pub(super) fn new() -> Self {
@@ -233,11 +272,12 @@ impl SqlIncorporator {
                             body: current_body, ..
                         }) => {
                             // Table already exists, so check if it has been changed.
-                            if current_body != &body {
+                            if table_changed(current_body, &body) {
                                 // Table has changed. Drop and recreate.
                                 trace!(
                                     table = %cts.table.display_unquoted(),
-                                    "table exists and has changed. Dropping and recreating..."
+                                    "table exists and has changed. Dropping and recreating, along \
+                                     with dependent caches..."
                                 );
                                 self.drop_and_recreate_table(&cts.table.clone(), body, mig)?;
                                 continue;
@@ -796,12 +836,29 @@ impl SqlIncorporator {
         Ok(Some(removal_result.dataflow_nodes_to_remove))
     }
 
+    /// Drop the table with the given name and recreate it with the given `body`, re-planning the
+    /// views and caches that depend on it, directly or through other views, as part of the same
+    /// migration.
+    ///
+    /// Since a migration is applied to the graph all at once, readers of those caches go straight
+    /// from the old version of each cache to the re-planned one, rather than seeing a window in
+    /// which the cache doesn't exist. Caches that are no longer valid against the new schema of the
+    /// table (for example because they reference a column that was dropped) are dropped; any other
+    /// error re-planning them is returned.
     fn drop_and_recreate_table(
         &mut self,
         table: &Relation,
         body: CreateTableBody,
         mig: &mut Migration,
     ) -> ReadySetResult<()> {
+        let (dependent_views, dependent_caches): (Vec<_>, Vec<_>) = self
+            .registry
+            .expressions_depending_on_table(table)
+            .into_iter()
+            .filter(|expr| !matches!(expr, RecipeExpr::Table { .. }))
+            .cloned()
+            .partition(|expr| matches!(expr, RecipeExpr::View { .. }));
+
         let removed_node_indices = self.remove_expression(table, mig)?;
         if removed_node_indices.is_none() {
             error!(
@@ -818,6 +875,50 @@ impl SqlIncorporator {
             name: table.clone(),
             body,
         })?;
+
+        let schema_search_path = table.schema.iter().cloned().collect::<Vec<_>>();
+        // Views are only compiled once a cache reads from them, so they're all re-added before any
+        // of the caches
+        for expr in dependent_views {
+            if let RecipeExpr::View { name, definition } = expr {
+                self.add_view(name, definition, schema_search_path.clone())?;
+            }
+        }
+        for expr in dependent_caches {
+            let RecipeExpr::Cache {
+                name,
+                statement,
+                always,
+            } = expr
+            else {
+                continue;
+            };
+            match self.add_query(
+                Some(name.clone()),
+                statement,
+                always,
+                &schema_search_path,
+                mig,
+            ) {
+                Ok(_) => {}
+                Err(error) if invalid_for_schema(&error) => {
+                    warn!(
+                        %error,
+                        table = %table.display_unquoted(),
+                        cache = %name.display_unquoted(),
+                        "Cache is no longer valid against the new schema of its table; dropping it"
+                    );
+                }
+                Err(error) => {
+                    return Err(error.context(format!(
+                        "failed to re-plan cache {} against the new schema of table {}",
+                        name.display_unquoted(),
+                        table.display_unquoted()
+                    )))
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -1,5 +1,5 @@
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use nom_sql::analysis::visit::{self, Visitor};
 use nom_sql::{
@@ -582,6 +582,30 @@ impl ExprRegistry {
             .flatten()
     }
 
+    /// Returns the views and caches that depend on the table with the given name (or alias),
+    /// either directly or through other views
+    pub(super) fn expressions_depending_on_table(&self, table_name: &Relation) -> Vec<&RecipeExpr> {
+        let mut seen = HashSet::new();
+        let mut queue = self
+            .aliases
+            .get(table_name)
+            .into_iter()
+            .copied()
+            .collect::<VecDeque<_>>();
+        let mut dependents = vec![];
+        while let Some(id) = queue.pop_front() {
+            for dep in self.dependencies.get(&id).into_iter().flatten() {
+                if seen.insert(*dep) {
+                    if let Some(expr) = self.expressions.get(dep) {
+                        dependents.push(expr);
+                    }
+                    queue.push_back(*dep);
+                }
+            }
+        }
+        dependents
+    }
+
     /// Returns an iterator over a list of expressions that contain columns referencing the given
     /// custom type
     pub(super) fn expressions_referencing_custom_type(
@@ -1057,6 +1081,33 @@ mod tests {
             assert!(registry.aliases.is_empty());
         }
 
+        #[test]
+        fn expressions_depending_on_table() {
+            let mut registry = setup();
+            registry
+                .add_query(RecipeExpr::Cache {
+                    name: "view_query".into(),
+                    statement: parse_select_statement(Dialect::MySQL, "SELECT * FROM test_view")
+                        .unwrap(),
+                    always: false,
+                })
+                .unwrap();
+
+            let mut names = registry
+                .expressions_depending_on_table(&"test_table".into())
+                .into_iter()
+                .map(|expr| expr.name().clone())
+                .collect::<Vec<_>>();
+            names.sort();
+            assert_eq!(
+                names,
+                vec!["test_query".into(), "test_view".into(), "view_query".into()]
+            );
+            assert!(registry
+                .expressions_depending_on_table(&"other_table".into())
+                .is_empty());
+        }
+
         #[test]
         fn len() {
             let registry = setup();
@@ -18,7 +18,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use array2::Array2;
 use common::IndexPair;
@@ -39,12 +39,16 @@ use readyset_client::builders::{
 };
 use readyset_client::consensus::{Authority, AuthorityControl};
 use readyset_client::debug::info::GraphInfo;
-use readyset_client::debug::stats::{DomainStats, GraphStats, NodeStats};
+use readyset_client::debug::stats::{
+    CacheMemoryUsage, CacheStats, DomainStats, GraphStats, MemoryUsage, NodeStats,
+};
 use readyset_client::internal::{MaterializationStatus, ReplicaAddress};
 use readyset_client::metrics::recorded;
 use readyset_client::recipe::changelist::{Change, ChangeList};
 use readyset_client::recipe::ExtendRecipeSpec;
-use readyset_client::replication::{ReplicationOffset, ReplicationOffsetState, ReplicationOffsets};
+use readyset_client::replication::{
+    ReplicationOffset, ReplicationOffsetState, ReplicationOffsets, TableChecksum,
+};
 use readyset_client::{
     NodeSize, TableReplicationStatus, TableStatus, ViewCreateRequest, ViewFilter, ViewRequest,
     ViewSchema,
@@ -75,6 +79,7 @@ use crate::controller::{
 use crate::coordination::{DomainDescriptor, RunDomainResponse};
 use crate::internal::LocalNodeIndex;
 use crate::worker::WorkerRequestKind;
+use crate::BaseTableShardColumn;
 
 /// Number of concurrent requests to make when making multiple simultaneous requests to domains (eg
 /// for replication offsets)
@@ -91,6 +96,9 @@ pub struct DfState {
     pub(super) source: NodeIndex,
     pub(super) ndomains: usize,
     pub(super) sharding: Option<usize>,
+    /// Base tables to shard by a column other than their primary key, if `sharding` is set
+    #[serde(default)]
+    pub(super) base_table_shard_columns: Vec<BaseTableShardColumn>,
 
     pub(super) domain_config: DomainConfig,
 
@@ -138,6 +146,16 @@ pub struct DfState {
     /// such as logictests where we may OOM from the recipe size.
     // TODO(ENG-838): Remove when dataflow state does not keep entire recipe chain.
     keep_prior_recipes: bool,
+
+    /// Latest replication position for the schema of each additional upstream database, by the
+    /// name of the upstream
+    #[serde(default)]
+    source_schema_replication_offsets: HashMap<String, ReplicationOffset>,
+
+    /// Tables the replicator has stopped replicating because of a destructive schema change, until
+    /// they're next snapshotted
+    #[serde(default)]
+    quarantined_tables: HashSet<Relation>,
 }
 
 impl DfState {
@@ -147,6 +165,7 @@ impl DfState {
         source: NodeIndex,
         ndomains: usize,
         sharding: Option<usize>,
+        base_table_shard_columns: Vec<BaseTableShardColumn>,
         domain_config: DomainConfig,
         persistence: PersistenceParameters,
         materializations: Materializations,
@@ -162,6 +181,7 @@ impl DfState {
             source,
             ndomains,
             sharding,
+            base_table_shard_columns,
             domain_config,
             persistence,
             materializations,
@@ -176,6 +196,8 @@ impl DfState {
             remap: Default::default(),
             keep_prior_recipes,
             replication_strategy,
+            source_schema_replication_offsets: Default::default(),
+            quarantined_tables: Default::default(),
         }
     }
 
@@ -386,6 +408,7 @@ impl DfState {
             .ok_or_else(|| internal_err!("Schema expects valid column indices"))?;
 
         let key_mapping = Vec::from(reader.mapping());
+        let index_type = reader.index().map(|index| index.index_type);
 
         let schema = self.view_schema(reader_node)?;
         let domain =
@@ -419,6 +442,7 @@ impl DfState {
             schema,
             replica_shard_addrs: Array2::from_rows(replicas),
             key_mapping,
+            index_type,
             view_request_timeout: self.domain_config.view_request_timeout,
         }))
     }
@@ -577,9 +601,14 @@ impl DfState {
             .map(|k| k.to_owned())
             .unwrap_or_default();
 
+        let shard_column = match node.sharded_by() {
+            Sharding::ByColumn(col, _) => Some(col),
+            _ => None,
+        };
+
         let mut is_primary = false;
         if key.is_empty() {
-            if let Sharding::ByColumn(col, _) = node.sharded_by() {
+            if let Some(col) = shard_column {
                 key = vec![col];
             }
         } else {
@@ -654,6 +683,7 @@ impl DfState {
             addr: node.local_addr(),
             key,
             key_is_primary: is_primary,
+            shard_column,
             dropped: base_operator.get_dropped(),
             table_name: node.name().clone(),
             columns,
@@ -695,6 +725,121 @@ impl DfState {
         Ok(GraphStats { domains })
     }
 
+    /// Get statistics about the reads of, and evictions from, the reader of each cache, including
+    /// at most `max_keys` of the most frequently read keys of each cache
+    pub(super) async fn cache_stats(
+        &self,
+        max_keys: usize,
+    ) -> ReadySetResult<BTreeMap<Relation, CacheStats>> {
+        let stats_per_domain: Vec<(DomainIndex, Vec<Vec<Vec<(NodeIndex, CacheStats)>>>)> = self
+            .query_domains::<_, Vec<(NodeIndex, CacheStats)>>(
+                self.domains
+                    .keys()
+                    .map(|di| (*di, DomainRequest::RequestReaderStats { max_keys })),
+            )
+            .try_collect()
+            .await?;
+
+        // Each shard and replica of a reader reports its own statistics, so add them together
+        let mut stats_per_reader: HashMap<NodeIndex, CacheStats> = HashMap::new();
+        for (node_index, stats) in stats_per_domain
+            .into_iter()
+            .flat_map(|(_domain, per_shard_stats)| per_shard_stats.into_iter().flatten().flatten())
+        {
+            match stats_per_reader.get_mut(&node_index) {
+                Some(existing) => existing.merge(stats, max_keys),
+                None => {
+                    stats_per_reader.insert(node_index, stats);
+                }
+            }
+        }
+
+        Ok(self
+            .ingredients
+            .externals(petgraph::EdgeDirection::Outgoing)
+            .filter_map(|n| {
+                #[allow(clippy::indexing_slicing)] // just came from self.ingredients
+                let node = &self.ingredients[n];
+                node.is_reader().then(|| {
+                    (
+                        node.name().clone(),
+                        stats_per_reader.remove(&n).unwrap_or_default(),
+                    )
+                })
+            })
+            .collect())
+    }
+
+    /// Get the approximate memory used by base tables, materialized state, and readers, both in
+    /// total and for each cache, along with the configured memory budget and cache quota
+    pub(super) async fn memory_usage(&self) -> ReadySetResult<MemoryUsage> {
+        let sizes: HashMap<NodeIndex, u64> = self
+            .node_sizes()
+            .await?
+            .into_iter()
+            .map(|(ni, size)| (ni, size.bytes.bytes() as u64))
+            .collect();
+        let size_of = |ni: &NodeIndex| sizes.get(ni).copied().unwrap_or(0);
+
+        let mut usage = MemoryUsage {
+            memory_budget: self.domain_config.memory_budget.map(|b| b as u64),
+            cache_memory_quota: self.domain_config.cache_memory_quota.map(|q| q as u64),
+            ..Default::default()
+        };
+        for (ni, size) in &sizes {
+            #[allow(clippy::indexing_slicing)] // node sizes come from nodes in the graph
+            let node = &self.ingredients[*ni];
+            if node.is_base() {
+                usage.base_table_bytes += size;
+            } else if node.is_reader() {
+                usage.reader_bytes += size;
+            } else {
+                usage.state_bytes += size;
+            }
+        }
+
+        for reader in self
+            .ingredients
+            .externals(petgraph::EdgeDirection::Outgoing)
+        {
+            #[allow(clippy::indexing_slicing)] // just came from self.ingredients
+            let node = &self.ingredients[reader];
+            if !node.is_reader() {
+                continue;
+            }
+
+            // Count the state of every node the reader reads from, up to (but not including) the
+            // base tables
+            let mut state_bytes = 0;
+            let mut visited = HashSet::new();
+            let mut to_visit = vec![reader];
+            while let Some(ni) = to_visit.pop() {
+                for parent in self
+                    .ingredients
+                    .neighbors_directed(ni, petgraph::EdgeDirection::Incoming)
+                {
+                    #[allow(clippy::indexing_slicing)] // just came from self.ingredients
+                    let parent_node = &self.ingredients[parent];
+                    if parent_node.is_source() || parent_node.is_base() || !visited.insert(parent) {
+                        continue;
+                    }
+                    state_bytes += size_of(&parent);
+                    to_visit.push(parent);
+                }
+            }
+
+            usage.caches.insert(
+                node.name().clone(),
+                CacheMemoryUsage {
+                    reader_bytes: size_of(&reader),
+                    state_bytes,
+                },
+            );
+        }
+
+        Ok(usage)
+    }
+
     pub(super) fn get_instances(&self) -> Vec<(WorkerIdentifier, bool)> {
         self.workers
             .iter()
@@ -784,7 +929,10 @@ impl DfState {
                 .map(|domain| (domain, DomainRequest::RequestReplicationOffsets)),
         )
         .try_fold(
-            ReplicationOffsets::with_schema_offset(self.schema_replication_offset.clone()),
+            ReplicationOffsets {
+                sources: self.source_schema_replication_offsets.clone(),
+                ..ReplicationOffsets::with_schema_offset(self.schema_replication_offset.clone())
+            },
             |mut acc, (domain, domain_offs)| async move {
                 for shard in domain_offs {
                     for replica in shard {
@@ -873,6 +1021,82 @@ impl DfState {
             .collect())
     }
 
+    /// Compute a checksum of the rows of the base table `table`, combining the checksums of each of
+    /// its shards
+    pub(super) async fn table_checksum(&self, table: &Relation) -> ReadySetResult<TableChecksum> {
+        let not_found = || ReadySetError::TableNotFound {
+            name: table.name.clone().into(),
+            schema: table.schema.clone().map(Into::into),
+        };
+        let ni = self.recipe.node_addr_for(table).map_err(|_| not_found())?;
+        let node = self
+            .ingredients
+            .node_weight(ni)
+            .ok_or_else(|| ReadySetError::NodeNotFound { index: ni.index() })?;
+        if !node.is_base() {
+            return Err(not_found());
+        }
+        let domain =
+            self.domains
+                .get(&node.domain())
+                .ok_or_else(|| ReadySetError::UnknownDomain {
+                    domain_index: node.domain().index(),
+                })?;
+
+        let shards = domain
+            .send_to_healthy::<TableChecksum>(
+                DomainRequest::RequestTableChecksum {
+                    node: node.local_addr(),
+                },
+                &self.workers,
+            )
+            .await?;
+        Ok(shards
+            .into_iter()
+            .flatten()
+            .fold(TableChecksum::default(), |mut checksum, shard| {
+                checksum.combine(shard);
+                checksum
+            }))
+    }
+
+    /// Log every write and replay to the readers of the view named `name` for `duration`, returning
+    /// `false` if there is no such view
+    pub(super) async fn trace_reader(
+        &self,
+        name: &Relation,
+        duration: Duration,
+    ) -> ReadySetResult<bool> {
+        let Some(ni) = self
+            .recipe
+            .node_addr_for(name)
+            .ok()
+            .or_else(|| self.views().get(name).copied()) else {
+            return Ok(false);
+        };
+        let name = self.recipe.resolve_alias(name).unwrap_or(name);
+        let Some(reader) = self.find_reader_for(ni, name, &None) else {
+            return Ok(false);
+        };
+
+        #[allow(clippy::indexing_slicing)] // `find_reader_for` returns valid indices
+        let node = &self.ingredients[reader];
+        self.domains
+            .get(&node.domain())
+            .ok_or_else(|| ReadySetError::UnknownDomain {
+                domain_index: node.domain().index(),
+            })?
+            .send_to_healthy::<()>(
+                DomainRequest::TraceReader {
+                    node: node.local_addr(),
+                    duration,
+                },
+                &self.workers,
+            )
+            .await?;
+        Ok(true)
+    }
+
     /// Returns a list of all table names that are currently involved in snapshotting.
     pub(super) async fn snapshotting_tables(&self) -> ReadySetResult<HashSet<Relation>> {
         let domains = self.domains_with_base_tables().await?;
@@ -1188,6 +1412,34 @@ impl DfState {
         self.schema_replication_offset = offset;
     }
 
+    pub(super) fn set_source_schema_replication_offset(
+        &mut self,
+        source: String,
+        offset: Option<ReplicationOffset>,
+    ) {
+        match offset {
+            Some(offset) => {
+                self.source_schema_replication_offsets
+                    .insert(source, offset);
+            }
+            None => {
+                self.source_schema_replication_offsets.remove(&source);
+            }
+        }
+    }
+
+    pub(super) fn quarantined_tables(&self) -> &HashSet<Relation> {
+        &self.quarantined_tables
+    }
+
+    pub(super) fn set_table_quarantined(&mut self, table: Relation, quarantined: bool) {
+        if quarantined {
+            self.quarantined_tables.insert(table);
+        } else {
+            self.quarantined_tables.remove(&table);
+        }
+    }
+
     pub(super) async fn flush_partial(&mut self) -> ReadySetResult<u64> {
         // get statistics for current domain sizes
         // and evict all state from partial nodes
@@ -1271,6 +1523,35 @@ impl DfState {
         r
     }
 
+    /// Refuse to create any new caches in `changelist` if the memory used by materialized state
+    /// and readers, plus the memory we expect a new cache to use, would exceed
+    /// [`DomainConfig::memory_budget`]
+    async fn check_memory_budget(&self, changelist: &ChangeList) -> ReadySetResult<()> {
+        let Some(budget) = self.domain_config.memory_budget else {
+            return Ok(());
+        };
+        let new_caches = changelist
+            .changes()
+            .filter(|change| matches!(change, Change::CreateCache(_)))
+            .count() as u64;
+        if new_caches == 0 {
+            return Ok(());
+        }
+
+        let usage = self.memory_usage().await?;
+        let used = usage.in_memory_bytes();
+        let projected = usage.projected_cache_bytes() * new_caches;
+        if used + projected > budget as u64 {
+            warn!(used, projected, budget, "Cache would exceed memory budget");
+            return Err(ReadySetError::MemoryBudgetExceeded {
+                used,
+                projected,
+                budget: budget as u64,
+            });
+        }
+        Ok(())
+    }
+
     pub(super) async fn extend_recipe(
         &mut self,
         recipe_spec: ExtendRecipeSpec<'_>,
@@ -1287,6 +1568,10 @@ impl DfState {
             }
         }
 
+        if !dry_run {
+            self.check_memory_budget(&recipe_spec.changes).await?;
+        }
+
         match self.apply_recipe(recipe_spec.changes, dry_run).await {
             Ok(x) => {
                 if let Some(offset) = &recipe_spec.replication_offset {
@@ -1339,6 +1624,57 @@ impl DfState {
         .await
     }
 
+    /// Returns the names of all caches in the recipe (not including aliases), paired with their
+    /// `CREATE CACHE` statements and ordered by the name of the cache
+    fn caches(&self) -> Vec<(Relation, CreateCacheStatement)> {
+        let mut caches = self
+            .recipe
+            .cache_names()
+            .filter_map(|name| match self.recipe.expression_by_alias(name)? {
+                SqlQuery::CreateCache(stmt) => Some((name.clone(), stmt)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        caches.sort_by(|(n1, _), (n2, _)| n1.cmp(n2));
+        caches
+    }
+
+    /// Returns the `CREATE CACHE` statements for all caches in the recipe (not including
+    /// aliases), ordered by the name of the cache
+    pub(super) fn cache_statements(&self) -> Vec<CreateCacheStatement> {
+        self.caches().into_iter().map(|(_, stmt)| stmt).collect()
+    }
+
+    /// Drops all caches and re-creates them under the same names from their `CREATE CACHE`
+    /// statements, as part of a single migration. Since the caches are re-created against the
+    /// current schema, this can be used to rebuild all caches after large schema changes.
+    /// Re-creating caches against any schema other than the current one is not supported.
+    ///
+    /// If any of the caches can't be re-created, the whole operation fails and the existing caches
+    /// are left untouched.
+    pub(super) async fn restore_all_queries(&mut self, dialect: Dialect) -> ReadySetResult<()> {
+        let (drops, creates): (Vec<_>, Vec<_>) = self
+            .caches()
+            .into_iter()
+            .map(|(name, stmt)| {
+                (
+                    Change::Drop {
+                        name: name.clone(),
+                        if_exists: true,
+                    },
+                    Change::CreateCache(CreateCacheStatement {
+                        name: Some(name),
+                        ..stmt
+                    }),
+                )
+            })
+            .unzip();
+        let changes = drops.into_iter().chain(creates).collect::<Vec<_>>();
+
+        self.apply_recipe(ChangeList::from_changes(changes, dialect), false)
+            .await
+    }
+
     /// Runs all the necessary steps to recover the full [`DfState`], when said state only
     /// has the bare minimum information.
     ///
@@ -14,6 +14,7 @@ use hyper::{self, Body, Method, Request, Response, StatusCode};
 use readyset_client::consensus::{Authority, AuthorityControl};
 use readyset_client::metrics::recorded;
 use readyset_errors::ReadySetError;
+use readyset_tracing::statement_logging;
 use readyset_util::shutdown::ShutdownReceiver;
 use tokio::net::TcpListener;
 use tokio::sync::mpsc::Sender;
@@ -209,6 +210,26 @@ impl Service<Request<Body>> for NoriaServerHttpRouter {
                     .body(hyper::Body::from(vec![]));
                 Box::pin(async move { Ok(res.unwrap()) })
             }
+            (&Method::GET, "/statement-logging") => {
+                let res = statement_logging_response(res);
+                Box::pin(async move { Ok(res.unwrap()) })
+            }
+            (&Method::POST, "/statement-logging") => Box::pin(async move {
+                let body = hyper::body::to_bytes(req.into_body()).await?;
+                let res = match serde_json::from_slice(&body) {
+                    Ok(changes) => {
+                        statement_logging::update(&changes);
+                        statement_logging_response(res)
+                    }
+                    Err(_) => res
+                        .status(StatusCode::BAD_REQUEST)
+                        .header(CONTENT_TYPE, "text/plain")
+                        .body(hyper::Body::from(
+                            "body must be a JSON object mapping components to booleans",
+                        )),
+                };
+                Ok(res.unwrap())
+            }),
             (&Method::POST, "/worker_request") => {
                 metrics::increment_counter!(recorded::SERVER_WORKER_REQUESTS);
 
@@ -310,3 +331,13 @@ impl Service<Request<Body>> for NoriaServerHttpRouter {
         }
     }
 }
+
+/// Respond with whether each component, including the replicator, is logging statements
+fn statement_logging_response(
+    res: hyper::http::response::Builder,
+) -> hyper::http::Result<Response<Body>> {
+    // Serializing a map of unit enum variants to booleans can't fail
+    let json = serde_json::to_string(&statement_logging::statuses()).unwrap();
+    res.header(CONTENT_TYPE, "application/json")
+        .body(hyper::Body::from(json))
+}
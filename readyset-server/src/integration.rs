@@ -7,6 +7,7 @@
 
 use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::num::NonZeroUsize;
 use std::ops::Bound;
 use std::sync::Arc;
 use std::time::Duration;
@@ -25,7 +26,7 @@ use dataflow::utils::{dataflow_column, make_columns};
 use dataflow::{
     BinaryOperator, DurabilityMode, Expr as DfExpr, PersistenceParameters, ReaderProcessing,
 };
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use itertools::Itertools;
 use nom_sql::{parse_create_cache, parse_create_view, parse_query, OrderType, Relation, SqlQuery};
 use readyset_client::consensus::{Authority, LocalAuthority, LocalAuthorityStore};
@@ -116,6 +117,144 @@ async fn it_completes() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn bulk_lookup() {
+    let (mut g, shutdown_tx) = start_simple_unsharded("bulk_lookup").await;
+    let a = g
+        .migrate(|mig| {
+            let a = mig.add_base(
+                "a",
+                make_columns(&["a", "b"]),
+                Base::new().with_primary_key([0]),
+            );
+
+            let mut emits = HashMap::new();
+            emits.insert(a, vec![0, 1]);
+            let u = Union::new(emits, union::DuplicateMode::UnionAll).unwrap();
+            let c = mig.add_ingredient("c", make_columns(&["a", "b"]), u);
+            mig.maintain_anonymous(c, &Index::hash_map(vec![0]));
+            a
+        })
+        .await;
+
+    let mut cq = g.view("c").await.unwrap().into_reader_handle().unwrap();
+    let mut muta = g.table_by_index(a).await.unwrap();
+    for i in 1..=5 {
+        muta.insert(vec![i.into(), (i * 10).into()]).await.unwrap();
+    }
+    sleep().await;
+
+    let keys = |keys: &[i32]| -> Vec<Vec<DfValue>> {
+        keys.iter().map(|&k| vec![DfValue::from(k)]).collect()
+    };
+
+    // None of the keys have been read yet, so a non-blocking lookup misses on all of them (and
+    // triggers replays for them)
+    let res = cq.bulk_lookup(keys(&[5, 3]), false).await.unwrap();
+    assert_eq!(res, vec![None, None]);
+
+    let res = cq.bulk_lookup(keys(&[5, 3, 42, 1]), true).await.unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Some(vec![vec![5.into(), 50.into()]]),
+            Some(vec![vec![3.into(), 30.into()]]),
+            Some(vec![]),
+            Some(vec![vec![1.into(), 10.into()]]),
+        ]
+    );
+
+    // Keys that have been filled are returned even if other keys in the same lookup miss
+    let res = cq.bulk_lookup(keys(&[2, 1, 42, 4]), false).await.unwrap();
+    assert_eq!(
+        res,
+        vec![
+            None,
+            Some(vec![vec![1.into(), 10.into()]]),
+            Some(vec![]),
+            None
+        ]
+    );
+
+    // Repeated keys each get their own results
+    let res = cq.bulk_lookup(keys(&[1, 1]), true).await.unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Some(vec![vec![1.into(), 10.into()]]),
+            Some(vec![vec![1.into(), 10.into()]]),
+        ]
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lookup_stream() {
+    let (mut g, shutdown_tx) = start_simple_unsharded("lookup_stream").await;
+    let a = g
+        .migrate(|mig| {
+            let a = mig.add_base(
+                "a",
+                make_columns(&["a", "b"]),
+                Base::new().with_primary_key([0]),
+            );
+
+            let mut emits = HashMap::new();
+            emits.insert(a, vec![0, 1]);
+            let u = Union::new(emits, union::DuplicateMode::UnionAll).unwrap();
+            let c = mig.add_ingredient("c", make_columns(&["a", "b"]), u);
+            mig.maintain_anonymous(c, &Index::hash_map(vec![1]));
+            a
+        })
+        .await;
+
+    let mut cq = g.view("c").await.unwrap().into_reader_handle().unwrap();
+    let mut muta = g.table_by_index(a).await.unwrap();
+    for i in 0..25 {
+        muta.insert(vec![i.into(), 1.into()]).await.unwrap();
+    }
+    sleep().await;
+
+    let mut lookup_batches = |key: i32, block: bool, batch_size: usize| {
+        cq.lookup_stream(&[key.into()], block, NonZeroUsize::new(batch_size).unwrap())
+            .unwrap()
+            .try_collect::<Vec<_>>()
+    };
+
+    // The key hasn't been read yet, so a non-blocking lookup misses
+    lookup_batches(1, false, 10).await.unwrap_err();
+
+    // The reader replies to each request for the next batch with at most `batch_size` rows
+    let batches = lookup_batches(1, true, 10).await.unwrap();
+    assert_eq!(
+        batches.iter().map(Vec::len).collect::<Vec<_>>(),
+        vec![10, 10, 5]
+    );
+    let mut rows = batches.concat();
+    rows.sort();
+    assert_eq!(
+        rows,
+        (0..25)
+            .map(|i| vec![i.into(), 1.into()])
+            .collect::<Vec<Vec<DfValue>>>()
+    );
+
+    // If the last batch is full, the empty reply to the request after it isn't returned
+    let batches = lookup_batches(1, true, 5).await.unwrap();
+    assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![5; 5]);
+
+    let batches = lookup_batches(1, true, 100).await.unwrap();
+    assert_eq!(batches.iter().map(Vec::len).collect::<Vec<_>>(), vec![25]);
+
+    assert_eq!(
+        lookup_batches(2, true, 10).await.unwrap(),
+        Vec::<Vec<_>>::new()
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
 fn timestamp(pairs: Vec<(u32, u64)>) -> Timestamp {
     let mut t = Timestamp::default();
     for p in pairs {
@@ -334,6 +473,96 @@ async fn sharded_shuffle() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "Ignoring sharded tests"]
+async fn base_sharded_by_declared_column() {
+    use crate::BaseTableShardColumn;
+
+    let mut builder = Builder::for_tests();
+    builder.set_sharding(Some(DEFAULT_SHARDING));
+    builder.set_persistence(get_persistence_params("base_sharded_by_declared_column"));
+    builder.set_base_table_shard_columns(vec![BaseTableShardColumn {
+        table: "base".into(),
+        column: "tenant".into(),
+    }]);
+    let (mut g, shutdown_tx) = builder.start_local().await.unwrap();
+
+    let a = g
+        .migrate(|mig| {
+            let a = mig.add_base(
+                "base",
+                make_columns(&["id", "tenant", "value"]),
+                Base::new().with_primary_key([0, 1]),
+            );
+            mig.maintain_anonymous(a, &Index::hash_map(vec![0]));
+            a
+        })
+        .await;
+
+    let mut base = g.table_by_index(a).await.unwrap();
+    let mut view = g.view("base").await.unwrap().into_reader_handle().unwrap();
+
+    // Find a tenant whose rows live in a different shard than those of tenant 0
+    let other_tenant = (1..)
+        .map(DfValue::from)
+        .find(|t| {
+            readyset_client::shard_by(t, DEFAULT_SHARDING)
+                != readyset_client::shard_by(&DfValue::from(0), DEFAULT_SHARDING)
+        })
+        .unwrap();
+
+    base.insert(vec![1.into(), 0.into(), 10.into()])
+        .await
+        .unwrap();
+    sleep().await;
+
+    // An update that moves the row to a different shard removes it from the old one
+    base.update(
+        vec![1.into(), 0.into()],
+        vec![
+            (0, Modification::Set(1.into())),
+            (1, Modification::Set(other_tenant.clone())),
+            (2, Modification::Set(11.into())),
+        ],
+    )
+    .await
+    .unwrap();
+    sleep().await;
+    assert_eq!(
+        view.lookup(&[1.into()], true).await.unwrap().into_vec(),
+        vec![vec![1.into(), other_tenant.clone(), 11.into()]]
+    );
+
+    // ...and can be undone by an update in the other direction
+    base.update(
+        vec![1.into(), other_tenant.clone()],
+        vec![
+            (0, Modification::Set(1.into())),
+            (1, Modification::Set(0.into())),
+            (2, Modification::Set(12.into())),
+        ],
+    )
+    .await
+    .unwrap();
+    sleep().await;
+    assert_eq!(
+        view.lookup(&[1.into()], true).await.unwrap().into_vec(),
+        vec![vec![1.into(), 0.into(), 12.into()]]
+    );
+
+    // Updates that move a row without saying what the rest of it is are rejected
+    let err = base
+        .update(
+            vec![1.into(), 0.into()],
+            vec![(1, Modification::Set(other_tenant.clone()))],
+        )
+        .await
+        .unwrap_err();
+    assert!(err.caused_by_unsupported(), "{err}");
+
+    shutdown_tx.shutdown().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn broad_recursing_upquery() {
     let nshards = 16;
@@ -8893,6 +9122,48 @@ async fn drop_and_recreate_different_columns() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn changed_table_replans_dependent_caches() {
+    let (mut g, shutdown_tx) =
+        start_simple_unsharded("changed_table_replans_dependent_caches").await;
+    let create = "
+        CREATE TABLE table_1 (column_1 INT, column_2 INT);
+        CREATE CACHE t1 FROM SELECT column_1 FROM table_1 WHERE column_1 = ?;
+        CREATE CACHE t2 FROM SELECT column_2 FROM table_1;
+        CREATE VIEW v1 AS SELECT column_1 FROM table_1;
+        CREATE CACHE t3 FROM SELECT column_1 FROM v1 WHERE column_1 = ?;
+    ";
+    g.extend_recipe(ChangeList::from_str(create, Dialect::DEFAULT_MYSQL).unwrap())
+        .await
+        .unwrap();
+
+    let recreate = "CREATE TABLE table_1 (column_1 INT, column_3 TEXT);";
+    g.extend_recipe(ChangeList::from_str(recreate, Dialect::DEFAULT_MYSQL).unwrap())
+        .await
+        .unwrap();
+
+    // `t2` references a column that no longer exists, so it can't be re-planned
+    assert_view_not_found(g.view("t2").await, "t2");
+
+    let mut table = g.table("table_1").await.unwrap();
+    table
+        .insert(vec![1.into(), "a".try_into().unwrap()])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let mut view = g.view("t1").await.unwrap().into_reader_handle().unwrap();
+    let results = view.lookup(&[1.into()], true).await.unwrap().into_vec();
+    assert_eq!(results, vec![vec![DfValue::from(1)]]);
+
+    // `t3` depends on the table through a view, and is re-planned along with it
+    let mut view = g.view("t3").await.unwrap().into_reader_handle().unwrap();
+    let results = view.lookup(&[1.into()], true).await.unwrap().into_vec();
+    assert_eq!(results, vec![vec![DfValue::from(1)]]);
+
+    shutdown_tx.shutdown().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn simple_dry_run() {
     let (mut g, shutdown_tx) = start_simple_unsharded("simple_dry_run").await;
@@ -9030,6 +9301,48 @@ async fn drop_view_schema_qualified() {
     shutdown_tx.shutdown().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn trace_reader() {
+    let (mut g, shutdown_tx) = start_simple_unsharded("trace_reader").await;
+
+    g.extend_recipe(
+        ChangeList::from_str(
+            "CREATE TABLE t1 (id int, val int);
+         CREATE CACHE q FROM SELECT val FROM t1 WHERE id = ?;",
+            Dialect::DEFAULT_MYSQL,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    assert!(g
+        .trace_reader(&"q".into(), Duration::from_secs(60))
+        .await
+        .unwrap());
+    assert!(!g
+        .trace_reader(&"nonexistent".into(), Duration::from_secs(60))
+        .await
+        .unwrap());
+
+    // Writes and replays to a traced reader are still applied
+    let mut t1 = g.table("t1").await.unwrap();
+    let mut q = g.view("q").await.unwrap().into_reader_handle().unwrap();
+    t1.insert(vec![1.into(), 2.into()]).await.unwrap();
+    sleep().await;
+    assert_eq!(
+        q.lookup(&[1.into()], true).await.unwrap().into_vec(),
+        vec![vec![DfValue::from(2)]]
+    );
+    t1.insert(vec![1.into(), 3.into()]).await.unwrap();
+    sleep().await;
+    let mut res = q.lookup(&[1.into()], true).await.unwrap().into_vec();
+    res.sort();
+    assert_eq!(res, vec![vec![DfValue::from(2)], vec![DfValue::from(3)]]);
+
+    shutdown_tx.shutdown().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn read_from_dropped_query() {
     let (mut g, shutdown_tx) = start_simple_unsharded("read_from_dropped_query").await;
@@ -9343,3 +9656,173 @@ async fn views_out_of_order() {
 
     shutdown_tx.shutdown().await;
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn memory_budget_rejects_new_caches() {
+    readyset_tracing::init_test_logging();
+    let mut builder = Builder::for_tests();
+    builder.set_persistence(get_persistence_params("memory_budget_rejects_new_caches"));
+    // Each new cache is projected to use its whole quota, which is more than the entire budget
+    builder.set_cache_memory_quota(1 << 20);
+    builder.set_memory_budget(1 << 10);
+    let (mut g, shutdown_tx) = builder.start_local().await.unwrap();
+
+    g.extend_recipe(
+        ChangeList::from_str(
+            "CREATE TABLE t (id INT PRIMARY KEY);",
+            Dialect::DEFAULT_MYSQL,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let err = g
+        .extend_recipe(
+            ChangeList::from_str(
+                "CREATE CACHE q FROM SELECT id FROM t WHERE id = ?;",
+                Dialect::DEFAULT_MYSQL,
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.caused_by_memory_budget_exceeded(), "{err}");
+    assert_view_not_found(g.view("q").await, "q");
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cache_memory_quota_evicts_from_readers() {
+    readyset_tracing::init_test_logging();
+    const QUOTA: usize = 64 << 10;
+    let mut builder = Builder::for_tests();
+    builder.set_persistence(get_persistence_params(
+        "cache_memory_quota_evicts_from_readers",
+    ));
+    builder.set_cache_memory_quota(QUOTA);
+    let (mut g, shutdown_tx) = builder.start_local().await.unwrap();
+
+    g.extend_recipe(
+        ChangeList::from_str(
+            "CREATE TABLE t (id INT PRIMARY KEY, value TEXT);
+             CREATE CACHE q FROM SELECT id, value FROM t WHERE id = ?;",
+            Dialect::DEFAULT_MYSQL,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let mut t = g.table("t").await.unwrap();
+    let value = "x".repeat(256);
+    t.insert_many((0..2000).map(|id| vec![DfValue::from(id), DfValue::from(value.as_str())]))
+        .await
+        .unwrap();
+
+    // Filling every key puts the reader several times over its quota
+    let mut q = g.view("q").await.unwrap().into_reader_handle().unwrap();
+    for id in 0..2000 {
+        let rows = q.lookup(&[id.into()], true).await.unwrap().into_vec();
+        assert_eq!(rows.len(), 1);
+    }
+
+    eventually!(run_test: {
+        g.memory_usage().await.unwrap()
+    }, then_assert: |usage| {
+        assert!(usage.reader_bytes > 0);
+        assert!(usage.reader_bytes <= QUOTA as u64, "{usage:?}");
+    });
+
+    // Evicted keys are filled again on the next read
+    let rows = q.lookup(&[0.into()], true).await.unwrap().into_vec();
+    assert_eq!(
+        rows,
+        vec![vec![DfValue::from(0), DfValue::from(value.as_str())]]
+    );
+
+    shutdown_tx.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restore_all_queries() {
+    let (mut g, shutdown_tx) = start_simple_unsharded("restore_all_queries").await;
+
+    g.extend_recipe(
+        ChangeList::from_str(
+            "CREATE TABLE t (a int, b int, c text);
+             CREATE CACHE q1 FROM SELECT a FROM t WHERE b = ?;
+             CREATE CACHE FROM SELECT b FROM t WHERE a = ?;",
+            Dialect::DEFAULT_MYSQL,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+
+    let mut t = g.table("t").await.unwrap();
+    t.insert_many(vec![
+        vec![1.into(), 2.into(), "a".into()],
+        vec![3.into(), 2.into(), "b".into()],
+    ])
+    .await
+    .unwrap();
+    sleep().await;
+
+    let statements = g.cache_statements().await.unwrap();
+    let names = statements
+        .iter()
+        .map(|stmt| stmt.name.clone().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(names.len(), 2);
+    assert!(names.contains(&"q1".into()));
+
+    g.restore_all_queries(Dialect::DEFAULT_MYSQL).await.unwrap();
+
+    // Both caches are re-created under the same names, including the one that was named by
+    // ReadySet, and read from the base table
+    assert_eq!(g.cache_statements().await.unwrap(), statements);
+    let mut q1 = g.view("q1").await.unwrap().into_reader_handle().unwrap();
+    let mut rows = q1.lookup(&[2.into()], true).await.unwrap().into_vec();
+    rows.sort();
+    assert_eq!(rows, vec![vec![1.into()], vec![3.into()]]);
+    let other_name = names.iter().find(|name| **name != "q1".into()).unwrap();
+    let mut other = g
+        .view(other_name.clone())
+        .await
+        .unwrap()
+        .into_reader_handle()
+        .unwrap();
+    assert_eq!(
+        other.lookup(&[3.into()], true).await.unwrap().into_vec(),
+        vec![vec![2.into()]]
+    );
+
+    // `json_object` can only be planned with PostgreSQL semantics, so re-creating this cache with
+    // MySQL semantics fails
+    g.extend_recipe(
+        ChangeList::from_str(
+            "CREATE CACHE q2 FROM SELECT json_object(c) FROM t WHERE a = $1",
+            Dialect::DEFAULT_POSTGRESQL,
+        )
+        .unwrap(),
+    )
+    .await
+    .unwrap();
+    let statements = g.cache_statements().await.unwrap();
+
+    g.restore_all_queries(Dialect::DEFAULT_MYSQL)
+        .await
+        .unwrap_err();
+
+    // None of the caches were dropped, and they can still be read from
+    assert_eq!(g.cache_statements().await.unwrap(), statements);
+    let mut q1 = g.view("q1").await.unwrap().into_reader_handle().unwrap();
+    let mut rows = q1.lookup(&[2.into()], true).await.unwrap().into_vec();
+    rows.sort();
+    assert_eq!(rows, vec![vec![1.into()], vec![3.into()]]);
+    g.view("q2").await.unwrap();
+
+    shutdown_tx.shutdown().await;
+}
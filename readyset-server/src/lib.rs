@@ -460,11 +460,13 @@ pub mod manual {
 
 use std::net::{IpAddr, ToSocketAddrs};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::anyhow;
 use clap::Args;
 use dataflow::DomainConfig;
+use nom_sql::{Relation, SqlIdentifier};
 use serde::{Deserialize, Serialize};
 
 /// Configuration for a running ReadySet cluster
@@ -478,6 +480,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 pub struct Config {
     pub(crate) sharding: Option<usize>,
+    /// Base tables to shard by a column other than their primary key, if sharding is enabled
+    #[serde(default)]
+    pub(crate) base_table_shard_columns: Vec<BaseTableShardColumn>,
     #[serde(default)]
     pub(crate) materialization_config: materialization::Config,
     pub(crate) domain_config: DomainConfig,
@@ -490,8 +495,6 @@ pub struct Config {
     pub(crate) mir_config: sql::mir::Config,
     #[serde(flatten)]
     pub(crate) replicator_config: UpstreamConfig,
-    #[serde(default)]
-    pub(crate) replicator_statement_logging: bool,
     pub(crate) keep_prior_recipes: bool,
     #[serde(default)]
     pub(crate) replication_strategy: ReplicationStrategy,
@@ -509,6 +512,7 @@ impl Default for Config {
             sharding: Some(2),
             #[cfg(not(test))]
             sharding: None,
+            base_table_shard_columns: vec![],
             materialization_config: Default::default(),
             domain_config: DomainConfig {
                 aggressively_update_state_sizes: false,
@@ -518,13 +522,16 @@ impl Default for Config {
                 // now.
                 table_request_timeout: Duration::from_millis(1800000),
                 eviction_kind: dataflow::EvictionKind::Random,
+                reader_spill_dir: None,
+                reader_spill_max_bytes: None,
+                cache_memory_quota: None,
+                memory_budget: None,
             },
             persistence: Default::default(),
             quorum: 1,
             reuse: None,
             abort_on_task_failure: true,
             mir_config: Default::default(),
-            replicator_statement_logging: false,
             replicator_config: Default::default(),
             keep_prior_recipes: true,
             replication_strategy: Default::default(),
@@ -534,6 +541,57 @@ impl Default for Config {
     }
 }
 
+/// A base table whose state is sharded by a column chosen by the user, rather than by its primary
+/// key. See [`WorkerOptions::shard_base_table`].
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct BaseTableShardColumn {
+    /// The base table, qualified with its schema
+    pub table: Relation,
+    /// The column to shard the table by
+    pub column: SqlIdentifier,
+}
+
+/// Parses `<schema>.<table>:<column>`
+///
+/// # Examples
+///
+/// ```rust
+/// use readyset_server::BaseTableShardColumn;
+///
+/// let shard_column: BaseTableShardColumn = "public.events:tenant_id".parse().unwrap();
+/// assert_eq!(shard_column.table.schema.as_deref(), Some("public"));
+/// assert_eq!(shard_column.table.name, "events");
+/// assert_eq!(shard_column.column, "tenant_id");
+///
+/// assert!("events:tenant_id".parse::<BaseTableShardColumn>().is_err());
+/// assert!("public.events".parse::<BaseTableShardColumn>().is_err());
+/// ```
+impl FromStr for BaseTableShardColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':').and_then(|(table, column)| {
+            let (schema, name) = table.split_once('.')?;
+            Some((schema.trim(), name.trim(), column.trim()))
+        }) {
+            Some((schema, name, column))
+                if !schema.is_empty() && !name.is_empty() && !column.is_empty() =>
+            {
+                Ok(Self {
+                    table: Relation {
+                        schema: Some(schema.into()),
+                        name: name.into(),
+                    },
+                    column: column.into(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid base table shard column, expected `<schema>.<table>:<column>`: {s}"
+            )),
+        }
+    }
+}
+
 /// Parse and normalize the given string as an [`IpAddr`]
 pub fn resolve_addr(addr: &str) -> anyhow::Result<IpAddr> {
     Ok([addr, ":0"]
@@ -578,6 +636,27 @@ pub struct WorkerOptions {
     #[clap(long = "eviction-policy", default_value_t = dataflow::EvictionKind::LRU)]
     pub eviction_kind: dataflow::EvictionKind,
 
+    /// Directory to write the rows of cached keys evicted to stay within --memory to. Evicted keys
+    /// are read back from disk when they're next queried, rather than being recomputed. If not
+    /// set, evicted keys are discarded.
+    #[clap(long, env = "READER_SPILL_DIR")]
+    pub reader_spill_dir: Option<PathBuf>,
+
+    /// Maximum number of bytes of evicted rows each cache can write to --reader-spill-dir (0 =
+    /// unlimited)
+    #[clap(long, env = "READER_SPILL_MAX_BYTES", default_value = "0")]
+    pub reader_spill_max_bytes: u64,
+
+    /// Maximum size, in bytes, of the reader of each partially materialized cache. Caches over
+    /// their quota have keys evicted until they fit (0 = unlimited)
+    #[clap(long, env = "CACHE_MEMORY_QUOTA", default_value = "0")]
+    pub cache_memory_quota: usize,
+
+    /// Budget, in bytes, for the total size of materialized state and readers. New caches are
+    /// refused if they're projected to exceed it (0 = unlimited)
+    #[clap(long, env = "MEMORY_BUDGET", default_value = "0")]
+    pub memory_budget: usize,
+
     /// Disable partial
     #[clap(long = "nopartial", hide = true)]
     pub no_partial: bool,
@@ -598,6 +677,13 @@ pub struct WorkerOptions {
     #[clap(long, default_value = "0", env = "NORIA_SHARDS", hide = true)]
     pub shards: usize,
 
+    /// Shard the state of a base table by the given column, such as a tenant id, rather than by
+    /// its primary key, as `<schema>.<table>:<column>`. The column must be part of the table's
+    /// primary key, if it has one. Multiple tables are separated by spaces. Only has an effect if
+    /// sharding is enabled with `--shards`.
+    #[clap(long, env = "SHARD_BASE_TABLES", value_delimiter = ' ', hide = true)]
+    pub shard_base_table: Vec<BaseTableShardColumn>,
+
     /// Volume associated with the server.
     #[clap(long, env = "VOLUME_ID")]
     pub volume_id: Option<VolumeId>,
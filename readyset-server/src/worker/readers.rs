@@ -1,8 +1,12 @@
 #![allow(missing_docs)]
 
 use core::task::Context;
+use std::borrow::Cow;
 use std::collections::hash_map::Entry::Occupied;
+use std::collections::HashMap;
 use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
 use std::task::Poll;
 use std::time;
 use std::time::Duration;
@@ -15,7 +19,7 @@ use dataflow::{
 };
 use failpoint_macros::set_failpoint;
 use futures::pin_mut;
-use futures_util::future::TryFutureExt;
+use futures_util::future::{Either, FutureExt, TryFutureExt};
 use pin_project::pin_project;
 use readyset_client::consistency::Timestamp;
 #[cfg(feature = "failure_injection")]
@@ -23,8 +27,8 @@ use readyset_client::failpoints;
 use readyset_client::metrics::recorded;
 use readyset_client::results::ResultIterator;
 use readyset_client::{
-    KeyComparison, LookupResult, ReadQuery, ReadReply, ReadReplyStats, ReaderAddress, Tagged,
-    ViewQuery,
+    KeyComparison, LookupResult, ReadPage, ReadQuery, ReadReply, ReadReplyStats, ReaderAddress,
+    Tagged, ViewQuery,
 };
 use readyset_errors::internal_err;
 use readyset_util::shutdown::ShutdownReceiver;
@@ -44,6 +48,10 @@ const RETRY_TIMEOUT: Duration = Duration::from_micros(100);
 
 const WAIT_BEFORE_WARNING: Duration = Duration::from_secs(7);
 
+/// How long the rest of the results of a paged read are kept for after a page of them was last
+/// read, before they're dropped.
+const READ_CURSOR_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// A batch of records either intended for local consumption only via the
 /// [`ServerReadReplyBatch::Unserialized`] variant, that avoids cloning entirely or for remote
 /// serialization using the [`ServerReadReplyBatch: :Serialized`] variant.
@@ -63,6 +71,13 @@ impl ServerReadReplyBatch {
     /// Construct a [`ServerReadReplyBatch`] by serializing a result set, and storing the serialized
     /// bytes.
     fn serialize(mut rs: ResultIterator) -> Self {
+        Self::serialize_rows(&mut rs, usize::MAX).0
+    }
+
+    /// Construct a [`ServerReadReplyBatch`] by serializing at most `max_rows` rows of a result
+    /// set, leaving the rest of them in `rs`. Returns the number of rows serialized along with
+    /// the batch.
+    fn serialize_rows(rs: &mut ResultIterator, max_rows: usize) -> (Self, usize) {
         let mut v = Vec::with_capacity(16 * 1024);
 
         let options = bincode::DefaultOptions::default();
@@ -72,7 +87,10 @@ impl ServerReadReplyBatch {
         usize::MAX.serialize(&mut ser).unwrap(); // Prepend the maximum possible room for length encoding
 
         let mut n = 0usize;
-        while let Some(row) = rs.next() {
+        while n < max_rows {
+            let Some(row) = rs.next() else {
+                break;
+            };
             row.serialize(&mut ser).unwrap();
             n += 1;
         }
@@ -85,10 +103,13 @@ impl ServerReadReplyBatch {
         // Now encode the proper length
         n.serialize(&mut ser).unwrap();
 
-        Self::Serialized {
-            serialized_data: v.into(),
-            skip_bytes,
-        }
+        (
+            Self::Serialized {
+                serialized_data: v.into(),
+                skip_bytes,
+            },
+            n,
+        )
     }
 
     /// Return this [`ServerReadReplyBatch`] as its unserialized [`ResultIterator`] if it is
@@ -126,12 +147,86 @@ type Reply = ReadySetResult<Tagged<ReadReply<ServerReadReplyBatch>>>;
 /// An Ack to resolve a blocking read.
 pub type Ack = oneshot::Sender<Reply>;
 
+/// The rest of the results of paged reads against the readers on a worker, by the cursor that each
+/// is read with.
+///
+/// These are shared by all of the connections to the worker's reader server, since the pages of a
+/// read can be requested over different connections. Results hold onto the rows they were read
+/// from even once the reader has moved on, so any whose next page isn't read within
+/// [`READ_CURSOR_IDLE_TIMEOUT`] are dropped.
+#[derive(Clone, Default)]
+pub struct ReadCursors {
+    inner: Arc<Mutex<ReadCursorsInner>>,
+}
+
+#[derive(Default)]
+struct ReadCursorsInner {
+    next_cursor: u64,
+    results: HashMap<u64, (ResultIterator, time::Instant)>,
+}
+
+impl ReadCursors {
+    /// Keep `results` to be read later, returning the cursor to read them with
+    fn insert(&self, results: ResultIterator) -> u64 {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .results
+            .retain(|_, (_, inserted)| inserted.elapsed() < READ_CURSOR_IDLE_TIMEOUT);
+        let cursor = inner.next_cursor;
+        inner.next_cursor += 1;
+        inner
+            .results
+            .insert(cursor, (results, time::Instant::now()));
+        cursor
+    }
+
+    /// Take the results kept for `cursor`, if they haven't been read or dropped already
+    fn remove(&self, cursor: u64) -> Option<ResultIterator> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.results.remove(&cursor).map(|(results, _)| results)
+    }
+}
+
+/// Take the next page of at most `page_size` rows from `results`, keeping the rest of them in
+/// `cursors` if the page is full
+fn next_page(
+    mut results: ResultIterator,
+    page_size: NonZeroUsize,
+    cursors: &ReadCursors,
+) -> ReadPage<ServerReadReplyBatch> {
+    let (rows, num_rows) = ServerReadReplyBatch::serialize_rows(&mut results, page_size.get());
+    let cursor = (num_rows == page_size.get()).then(|| cursors.insert(results));
+    ReadPage { rows, cursor }
+}
+
+/// Convert a reply to a normal read returning raw results into a reply to a [`ReadQuery::Paged`],
+/// with the first page of the results
+fn first_page(reply: Reply, page_size: NonZeroUsize, cursors: &ReadCursors) -> Reply {
+    let Tagged { tag, v } = reply?;
+    let page = match v {
+        ReadReply::Normal(Ok(LookupResult::Results(results, _))) => results
+            .into_iter()
+            .next()
+            .and_then(ServerReadReplyBatch::into_unserialized)
+            .map(|results| Some(next_page(results, page_size, cursors)))
+            .ok_or_else(|| internal_err!("Expected a single raw result set")),
+        ReadReply::Normal(Ok(LookupResult::NonBlockingMiss)) => Ok(None),
+        ReadReply::Normal(Err(e)) => Err(e),
+        _ => Err(internal_err!("Unexpected reply to a normal read")),
+    };
+    Ok(Tagged {
+        tag,
+        v: ReadReply::Page(page),
+    })
+}
+
 /// Creates a handler that can be used to perform read queries against a set of
 /// Readers.
 #[derive(Clone)]
 pub struct ReadRequestHandler {
     global_readers: Readers,
     readers_cache: ReaderMap,
+    cursors: ReadCursors,
     wait: tokio::sync::mpsc::UnboundedSender<(BlockingRead, Ack)>,
     miss_ctr: metrics::Counter,
     hit_ctr: metrics::Counter,
@@ -146,16 +241,32 @@ pub enum CallResult<F: Future<Output = Reply>> {
     Async(F),
 }
 
+impl<F: Future<Output = Reply>> CallResult<F> {
+    /// Convert the future that an asynchronous result will be resolved by, using `f`
+    fn map_async<G, M>(self, f: M) -> CallResult<G>
+    where
+        G: Future<Output = Reply>,
+        M: FnOnce(F) -> G,
+    {
+        match self {
+            CallResult::Immediate(reply) => CallResult::Immediate(reply),
+            CallResult::Async(fut) => CallResult::Async(f(fut)),
+        }
+    }
+}
+
 impl ReadRequestHandler {
     /// Creates a new request handler that can be used to query Readers.
     pub fn new(
         readers: Readers,
+        cursors: ReadCursors,
         wait: tokio::sync::mpsc::UnboundedSender<(BlockingRead, Ack)>,
         upquery_timeout: Duration,
     ) -> Self {
         Self {
             global_readers: readers,
             readers_cache: Default::default(),
+            cursors,
             wait,
             miss_ctr: metrics::register_counter!(recorded::SERVER_VIEW_QUERY_MISS),
             hit_ctr: metrics::register_counter!(recorded::SERVER_VIEW_QUERY_HIT),
@@ -212,15 +323,25 @@ impl ReadRequestHandler {
             Err(LookupError::Destroyed) => reply_with_error!(ReadySetError::ViewDestroyed),
             Err(LookupError::Error(e)) => reply_with_error!(e),
             // We missed some keys
-            Err(LookupError::Miss((misses, _))) if consistency_miss => (misses, None),
-            Err(LookupError::Miss((misses, notifier))) => (misses, Some(notifier)),
+            Err(LookupError::Miss((misses, _))) if consistency_miss => {
+                reader.record_lookup(&key_comparisons, &misses, true);
+                (misses, None)
+            }
+            Err(LookupError::Miss((misses, notifier))) => {
+                reader.record_lookup(&key_comparisons, &misses, false);
+                (misses, Some(notifier))
+            }
             // We hit on all keys, but there is a consistency miss. This just counts as a miss,
             // but no keys needs triggering.
-            Ok(_) if consistency_miss => (vec![], None),
+            Ok(_) if consistency_miss => {
+                reader.record_lookup(&key_comparisons, &[], true);
+                (vec![], None)
+            }
             Ok(hit) => {
                 // We hit on all keys, and there is no consistency miss, can return results
                 // immediately
                 self.hit_ctr.increment(1);
+                reader.record_lookup(&key_comparisons, &[], false);
 
                 let results = ResultIterator::new(hit, &reader.post_lookup, limit, offset, filter);
 
@@ -263,6 +384,7 @@ impl ReadRequestHandler {
                     timestamp,
                     upquery_timeout: self.upquery_timeout,
                     raw_result,
+                    per_key: false,
                     receiver,
                     eviction_epoch: reader.eviction_epoch(),
                 },
@@ -278,6 +400,133 @@ impl ReadRequestHandler {
         }
     }
 
+    /// Look up each of the keys in `query` separately, replying with the results for each key. If
+    /// the query is non-blocking, keys that miss are replied to with `None`, otherwise the reply
+    /// waits until every key has been filled.
+    pub fn handle_per_key_read_query(
+        &mut self,
+        tag: u32,
+        target: ReaderAddress,
+        query: ViewQuery,
+    ) -> CallResult<impl Future<Output = Reply>> {
+        let ViewQuery {
+            key_comparisons,
+            block,
+            timestamp,
+            filter,
+            limit,
+            offset,
+        } = query;
+
+        macro_rules! reply {
+            ($e: expr) => {
+                return CallResult::Immediate(Ok(Tagged {
+                    tag,
+                    v: ReadReply::PerKey($e),
+                }))
+            };
+        }
+
+        let reader =
+            match get_reader_from_cache(&target, &mut self.readers_cache, &self.global_readers) {
+                Ok(r) => r,
+                Err(e) => reply!(Err(e)),
+            };
+
+        let consistency_miss = !has_sufficient_timestamp(reader, &timestamp);
+        let PerKeyLookup {
+            results,
+            misses,
+            receiver,
+        } = match lookup_per_key(reader, &key_comparisons, limit, offset, &filter) {
+            Ok(lookup) => lookup,
+            Err(e) => reply!(Err(e)),
+        };
+        reader.record_lookup(&key_comparisons, &misses, consistency_miss);
+
+        if misses.is_empty() && !consistency_miss {
+            self.hit_ctr.increment(1);
+            reply!(Ok(results));
+        }
+
+        self.miss_ctr.increment(1);
+        if !misses.is_empty() {
+            reader.trigger(misses.into_iter().map(|k| k.into_owned()));
+        }
+
+        if !block {
+            // Keys that hit are still only returned if the reader is consistent with the timestamp
+            // of the read
+            reply!(Ok(if consistency_miss {
+                results.into_iter().map(|_| None).collect()
+            } else {
+                results
+            }));
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let r = self.wait.send((
+            BlockingRead {
+                tag,
+                target,
+                key_comparisons,
+                truth: self.global_readers.clone(),
+                first: time::Instant::now(),
+                warned: false,
+                limit,
+                offset,
+                filter,
+                timestamp,
+                upquery_timeout: self.upquery_timeout,
+                raw_result: false,
+                per_key: true,
+                receiver,
+                eviction_epoch: reader.eviction_epoch(),
+            },
+            tx,
+        ));
+
+        if r.is_err() {
+            // we're shutting down
+            return CallResult::Immediate(Err(ReadySetError::ServerShuttingDown));
+        }
+
+        CallResult::Async(rx.map_ok_or_else(|e| Err(internal_err!("{e}")), |o| o))
+    }
+
+    /// Run `query` against a reader, replying with at most `page_size` rows of its results and
+    /// keeping the rest of them to be read with [`ReadQuery::NextPage`]
+    pub fn handle_paged_read_query(
+        &mut self,
+        tag: u32,
+        target: ReaderAddress,
+        query: ViewQuery,
+        page_size: NonZeroUsize,
+    ) -> CallResult<impl Future<Output = Reply>> {
+        let cursors = self.cursors.clone();
+        match self.handle_normal_read_query(tag, target, query, true) {
+            CallResult::Immediate(reply) => {
+                CallResult::Immediate(first_page(reply, page_size, &cursors))
+            }
+            CallResult::Async(reply) => {
+                CallResult::Async(reply.map(move |reply| first_page(reply, page_size, &cursors)))
+            }
+        }
+    }
+
+    fn handle_next_page_query(&mut self, tag: u32, cursor: u64, page_size: NonZeroUsize) -> Reply {
+        let page = self
+            .cursors
+            .remove(cursor)
+            .map(|results| Some(next_page(results, page_size, &self.cursors)))
+            .ok_or(ReadySetError::ReadCursorNotFound(cursor));
+
+        Ok(Tagged {
+            tag,
+            v: ReadReply::Page(page),
+        })
+    }
+
     fn handle_size_query(&mut self, tag: u32, target: &ReaderAddress) -> Reply {
         let reader = get_reader_from_cache(target, &mut self.readers_cache, &self.global_readers)?;
 
@@ -315,6 +564,28 @@ impl Service<Tagged<ReadQuery>> for ReadRequestHandler {
                 let span = readyset_tracing::child_span!(INFO, "normal_read_query");
                 let _g = span.enter();
                 self.handle_normal_read_query(tag, target, query, false)
+                    .map_async(|f| Either::Left(Either::Left(f)))
+            }
+            ReadQuery::PerKey { target, query } => {
+                let span = readyset_tracing::child_span!(INFO, "per_key_read_query");
+                let _g = span.enter();
+                self.handle_per_key_read_query(tag, target, query)
+                    .map_async(|f| Either::Left(Either::Right(f)))
+            }
+            ReadQuery::Paged {
+                target,
+                query,
+                page_size,
+            } => {
+                let span = readyset_tracing::child_span!(INFO, "paged_read_query");
+                let _g = span.enter();
+                self.handle_paged_read_query(tag, target, query, page_size)
+                    .map_async(Either::Right)
+            }
+            ReadQuery::NextPage { cursor, page_size } => {
+                let span = readyset_tracing::child_span!(INFO, "next_page_query");
+                let _g = span.enter();
+                CallResult::Immediate(self.handle_next_page_query(tag, cursor, page_size))
             }
             ReadQuery::Size { ref target } => {
                 let span = readyset_tracing::child_span!(INFO, "size_query");
@@ -376,6 +647,8 @@ pub(crate) async fn listen(
 ) {
     let stream = shutdown_rx.clone().wrap_stream(TcpListenerStream::new(on));
     pin_mut!(stream);
+    // The pages of a paged read can be requested over any of the connections to this worker
+    let cursors = ReadCursors::default();
     while let Some(stream) = stream.next().await {
         let mut shutdown_rx = shutdown_rx.clone();
         set_failpoint!(failpoints::READ_QUERY);
@@ -399,7 +672,7 @@ pub(crate) async fn listen(
             }
         });
 
-        let r = ReadRequestHandler::new(readers, tx, upquery_timeout);
+        let r = ReadRequestHandler::new(readers, cursors.clone(), tx, upquery_timeout);
 
         let server =
             server::Server::new(AsyncBincodeStream::from(stream).for_async(), r).map_err(|e| {
@@ -449,6 +722,56 @@ fn has_sufficient_timestamp(reader: &SingleReadHandle, timestamp: &Option<Timest
         .satisfies(timestamp.as_ref().unwrap())
 }
 
+/// The results of looking up each of a list of keys in a reader separately
+struct PerKeyLookup<'a> {
+    /// The results for each key, or `None` if the key missed
+    results: Vec<Option<ServerReadReplyBatch>>,
+    /// The keys that missed
+    misses: Vec<Cow<'a, KeyComparison>>,
+    /// A notifier for when the reader is next updated, if any of the keys missed
+    receiver: Option<ReaderUpdatedNotifier>,
+}
+
+/// Look up each of `keys` in `reader` separately, applying the reader's post-lookup operations to
+/// the results for each key
+fn lookup_per_key<'a>(
+    reader: &SingleReadHandle,
+    keys: &'a [KeyComparison],
+    limit: Option<usize>,
+    offset: Option<usize>,
+    filter: &Option<DfExpr>,
+) -> ReadySetResult<PerKeyLookup<'a>> {
+    let mut lookup = PerKeyLookup {
+        results: Vec::with_capacity(keys.len()),
+        misses: Vec::new(),
+        receiver: None,
+    };
+    for key in keys {
+        match reader.get_multi_with_notifier(std::slice::from_ref(key)) {
+            Ok(hit) => {
+                lookup
+                    .results
+                    .push(Some(ServerReadReplyBatch::serialize(ResultIterator::new(
+                        hit,
+                        &reader.post_lookup,
+                        limit,
+                        offset,
+                        filter.clone(),
+                    ))))
+            }
+            Err(LookupError::Miss((misses, notifier))) => {
+                lookup.results.push(None);
+                lookup.misses.extend(misses);
+                lookup.receiver = Some(notifier);
+            }
+            Err(LookupError::NotReady) => return Err(ReadySetError::ViewNotYetAvailable),
+            Err(LookupError::Destroyed) => return Err(ReadySetError::ViewDestroyed),
+            Err(LookupError::Error(e)) => return Err(e),
+        }
+    }
+    Ok(lookup)
+}
+
 /// Issues a blocking read against a reader. This can be repeatedly polled via `check` for
 /// completion.
 #[pin_project]
@@ -465,6 +788,9 @@ pub struct BlockingRead {
     timestamp: Option<Timestamp>,
     upquery_timeout: Duration,
     raw_result: bool,
+    /// Whether to reply with the results for each key separately, as for a
+    /// [`ReadQuery::PerKey`]
+    per_key: bool,
     receiver: Option<ReaderUpdatedNotifier>,
     eviction_epoch: usize,
 }
@@ -500,6 +826,29 @@ impl BlockingRead {
             Ok(_) if consistency_miss => vec![],
             Err(LookupError::Miss((misses, _))) => misses,
             Err(_) => return Poll::Ready(Err(ReadySetError::ServerShuttingDown)),
+            // We hit on all keys, so look them up again one at a time to split up their results.
+            // Keys can be evicted in between, in which case we keep waiting for them.
+            Ok(_) if self.per_key => match lookup_per_key(
+                reader,
+                &self.key_comparisons,
+                self.limit,
+                self.offset,
+                &self.filter,
+            ) {
+                Ok(lookup) if lookup.misses.is_empty() => {
+                    return Poll::Ready(Ok(Tagged {
+                        tag: self.tag,
+                        v: ReadReply::PerKey(Ok(lookup.results)),
+                    }))
+                }
+                Ok(lookup) => lookup.misses,
+                Err(e) => {
+                    return Poll::Ready(Ok(Tagged {
+                        tag: self.tag,
+                        v: ReadReply::PerKey(Err(e)),
+                    }))
+                }
+            },
             Ok(hit) => {
                 // We hit on all keys, and there is no consistency miss, can return results
                 let results = ResultIterator::new(
@@ -373,6 +373,25 @@ impl Replica {
 
                 // Handle domain requests
                 domain_req = requests.recv() => match domain_req {
+                    // Reading every row of a table can take a while, so do it on a blocking
+                    // thread rather than holding up the domain
+                    Some(WrappedDomainRequest {
+                        req: DomainRequest::RequestTableChecksum { node },
+                        done_tx,
+                    }) => match domain.table_checksum_task(node) {
+                        Ok(task) => {
+                            tokio::task::spawn_blocking(move || {
+                                let _ = done_tx.send(
+                                    bincode::serialize(&task.run())
+                                        .map(Some)
+                                        .map_err(Into::into),
+                                );
+                            });
+                        }
+                        Err(error) => {
+                            let _ = done_tx.send(Err(error));
+                        }
+                    },
                     Some(req) => {
                         let _guard = span.enter();
                         if req.done_tx.send(domain.domain_request(req.req, out)).is_err() {
@@ -431,7 +450,10 @@ impl Replica {
                 Some(res) = send_packets.next() => res?,
 
                 // Update domain sizes when `refresh_sizes` expires
-                Some(_) = refresh_sizes.next() => domain.update_state_sizes(),
+                Some(_) = refresh_sizes.next() => {
+                    domain.update_state_sizes();
+                    domain.enforce_cache_memory_quota(out);
+                }
 
                 // Wait for a possible sleep
                 _ = tokio::time::sleep(domain.next_poll_duration().unwrap_or_else(|| Duration::from_secs(3600))) => domain.handle_timeout()?,
@@ -200,7 +200,10 @@ impl<'ast> VisitorMut<'ast> for AnonymizeVisitor<'_> {
             | nom_sql::ShowStatement::ProxiedQueries(..)
             | nom_sql::ShowStatement::ReadySetStatus
             | nom_sql::ShowStatement::ReadySetVersion
-            | nom_sql::ShowStatement::ReadySetTables => {}
+            | nom_sql::ShowStatement::ReadySetTables
+            | nom_sql::ShowStatement::ReadySetCreateCaches
+            | nom_sql::ShowStatement::ReadySetCacheStats
+            | nom_sql::ShowStatement::ReadySetMemory => {}
         }
         Ok(())
     }
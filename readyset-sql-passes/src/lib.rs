@@ -168,7 +168,11 @@ impl Rewrite for SelectStatement {
                 context.search_path,
                 context.invalidating_tables.as_deref_mut(),
             )?
-            .expand_stars(context.view_schemas, context.non_replicated_relations)?
+            .expand_stars(
+                context.view_schemas,
+                context.base_schemas,
+                context.non_replicated_relations,
+            )?
             .expand_implied_tables(context.view_schemas)?
             .normalize_topk_with_aggregate()?
             .rewrite_count_star(context.view_schemas, context.non_replicated_relations)?
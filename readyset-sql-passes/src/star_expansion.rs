@@ -3,7 +3,8 @@ use std::mem;
 
 use nom_sql::analysis::visit_mut::{self, VisitorMut};
 use nom_sql::{
-    Column, Expr, FieldDefinitionExpr, Relation, SelectStatement, SqlIdentifier, SqlQuery,
+    Column, CreateTableBody, Expr, FieldDefinitionExpr, Relation, SelectStatement, SqlIdentifier,
+    SqlQuery,
 };
 use readyset_errors::{ReadySetError, ReadySetResult};
 
@@ -11,19 +12,33 @@ use crate::util::{self, outermost_named_tables};
 
 pub trait StarExpansion: Sized {
     /// Expand all `*` column references in the query given a map from tables to the lists of
-    /// columns in those tables
+    /// columns in those tables, omitting any columns which are invisible in the `CREATE TABLE`
+    /// statements for base tables in `base_schemas`
     fn expand_stars(
         self,
         table_columns: &HashMap<Relation, Vec<SqlIdentifier>>,
+        base_schemas: &HashMap<Relation, CreateTableBody>,
         non_replicated_relations: &HashSet<Relation>,
     ) -> ReadySetResult<Self>;
 }
 
 struct ExpandStarsVisitor<'schema> {
     table_columns: &'schema HashMap<Relation, Vec<SqlIdentifier>>,
+    base_schemas: &'schema HashMap<Relation, CreateTableBody>,
     non_replicated_relations: &'schema HashSet<Relation>,
 }
 
+impl<'schema> ExpandStarsVisitor<'schema> {
+    /// Returns true if `column` is an invisible column in the base table `table`
+    fn is_invisible(&self, table: &Relation, column: &SqlIdentifier) -> bool {
+        self.base_schemas.get(table).map_or(false, |body| {
+            body.fields
+                .iter()
+                .any(|f| f.column.name == *column && f.is_invisible())
+        })
+    }
+}
+
 impl<'ast, 'schema> VisitorMut<'ast> for ExpandStarsVisitor<'schema> {
     type Error = ReadySetError;
 
@@ -62,6 +77,9 @@ impl<'ast, 'schema> VisitorMut<'ast> for ExpandStarsVisitor<'schema> {
                 }
             })?
             .into_iter()
+            .filter(|f| !self.is_invisible(&table, f))
+            .collect::<Vec<_>>()
+            .into_iter()
             .map(move |f| FieldDefinitionExpr::Expr {
                 expr: Expr::Column(Column {
                     table: Some(table.clone()),
@@ -99,10 +117,12 @@ impl StarExpansion for SelectStatement {
     fn expand_stars(
         mut self,
         table_columns: &HashMap<Relation, Vec<SqlIdentifier>>,
+        base_schemas: &HashMap<Relation, CreateTableBody>,
         non_replicated_relations: &HashSet<Relation>,
     ) -> ReadySetResult<Self> {
         let mut visitor = ExpandStarsVisitor {
             table_columns,
+            base_schemas,
             non_replicated_relations,
         };
         visitor.visit_select_statement(&mut self)?;
@@ -114,12 +134,15 @@ impl StarExpansion for SqlQuery {
     fn expand_stars(
         self,
         write_schemas: &HashMap<Relation, Vec<SqlIdentifier>>,
+        base_schemas: &HashMap<Relation, CreateTableBody>,
         non_replicated_relations: &HashSet<Relation>,
     ) -> ReadySetResult<Self> {
         Ok(match self {
-            SqlQuery::Select(sq) => {
-                SqlQuery::Select(sq.expand_stars(write_schemas, non_replicated_relations)?)
-            }
+            SqlQuery::Select(sq) => SqlQuery::Select(sq.expand_stars(
+                write_schemas,
+                base_schemas,
+                non_replicated_relations,
+            )?),
             _ => self,
         })
     }
@@ -135,7 +158,9 @@ mod tests {
     fn expands_stars(source: &str, expected: &str, schema: HashMap<Relation, Vec<SqlIdentifier>>) {
         let q = parse_query(Dialect::MySQL, source).unwrap();
         let expected = parse_query(Dialect::MySQL, expected).unwrap();
-        let res = q.expand_stars(&schema, &Default::default()).unwrap();
+        let res = q
+            .expand_stars(&schema, &Default::default(), &Default::default())
+            .unwrap();
         assert_eq!(
             res,
             expected,
@@ -242,4 +267,29 @@ mod tests {
             ]),
         );
     }
+
+    #[test]
+    fn omits_invisible_columns() {
+        let create_table = match parse_query(
+            Dialect::MySQL,
+            "CREATE TABLE t (my_row_id BIGINT UNSIGNED NOT NULL AUTO_INCREMENT INVISIBLE, \
+             a INT, PRIMARY KEY (my_row_id))",
+        )
+        .unwrap()
+        {
+            SqlQuery::CreateTable(stmt) => stmt,
+            _ => panic!(),
+        };
+        let base_schemas = HashMap::from([("t".into(), create_table.body.unwrap())]);
+        let schema = HashMap::from([("t".into(), vec!["my_row_id".into(), "a".into()])]);
+
+        let q = parse_query(Dialect::MySQL, "SELECT * FROM t").unwrap();
+        let res = q
+            .expand_stars(&schema, &base_schemas, &Default::default())
+            .unwrap();
+        assert_eq!(
+            res,
+            parse_query(Dialect::MySQL, "SELECT t.a FROM t").unwrap()
+        );
+    }
 }
@@ -0,0 +1,98 @@
+//! Captures the replication events a running readyset-server receives from its upstream database
+//! over the next few minutes to a file, which can be attached to a bug report to reproduce
+//! replication issues.
+//!
+//! The file is written by the readyset-server, within the directory it was started with
+//! `--replication-capture-dir`. Values in rows can be redacted by replacing them with a hash of the
+//! value, which keeps equal values equal. The captured events can be applied to another deployment
+//! with `replay_replication`.
+//!
+//! # Example
+//!
+//! ```bash
+//! cargo run --bin capture_replication -- --deployment readyset --minutes 10 --redact text replication.jsonl
+//! ```
+#![warn(clippy::panic)]
+
+use std::time::Duration;
+
+use clap::builder::NonEmptyStringValueParser;
+use clap::{Parser, ValueEnum};
+use readyset_client::consensus::AuthorityType;
+use readyset_client::replication::{CaptureRedaction, ReplicationCaptureRequest};
+use readyset_client::ReadySetHandle;
+
+#[derive(Parser)]
+#[clap(name = "capture_replication")]
+struct CaptureReplication {
+    #[clap(short, long, env("AUTHORITY_ADDRESS"), default_value("127.0.0.1:2181"))]
+    authority_address: String,
+
+    #[clap(long, env("AUTHORITY"), default_value("zookeeper"), value_parser = ["consul", "zookeeper"])]
+    authority: AuthorityType,
+
+    #[clap(short, long, env("DEPLOYMENT"), value_parser = NonEmptyStringValueParser::new())]
+    deployment: String,
+
+    /// The number of minutes to capture replication events for.
+    #[clap(long, default_value = "5")]
+    minutes: u64,
+
+    /// Which values in rows to replace with a hash of the value: none, text and byte array
+    /// values, or all values (values that can't be hashed without changing their type are
+    /// replaced with NULL).
+    #[clap(long, value_enum, default_value = "none")]
+    redact: Redact,
+
+    /// The name of the file to write the captured events to, within the readyset-server's
+    /// `--replication-capture-dir`.
+    file_name: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Redact {
+    None,
+    Text,
+    All,
+}
+
+impl From<Redact> for CaptureRedaction {
+    fn from(redact: Redact) -> Self {
+        match redact {
+            Redact::None => CaptureRedaction::None,
+            Redact::Text => CaptureRedaction::Text,
+            Redact::All => CaptureRedaction::All,
+        }
+    }
+}
+
+impl CaptureReplication {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let authority = self
+            .authority
+            .to_authority(&self.authority_address, &self.deployment)
+            .await;
+
+        let mut handle: ReadySetHandle = ReadySetHandle::new(authority).await;
+        handle.ready().await?;
+
+        handle
+            .capture_replication_events(&ReplicationCaptureRequest {
+                file_name: self.file_name.clone(),
+                duration: Duration::from_secs(self.minutes * 60),
+                redaction: self.redact.into(),
+            })
+            .await?;
+        println!(
+            "Capturing replication events to {} for {} minutes",
+            self.file_name, self.minutes
+        );
+
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    CaptureReplication::parse().run().await
+}
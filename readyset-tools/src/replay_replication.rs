@@ -0,0 +1,65 @@
+//! Applies the replication events in a file written by `capture_replication` to a running
+//! readyset-server, as if they'd just been received from its upstream database, to reproduce
+//! replication issues seen in another deployment.
+//!
+//! The deployment should have the same tables as the one the events were captured from, with rows
+//! as of before the first captured event. Events are only applied to tables that exist in the
+//! deployment, and are skipped for tables whose replication offset is already past them.
+//!
+//! # Example
+//!
+//! ```bash
+//! cargo run --bin replay_replication -- --deployment readyset --database-type mysql replication.jsonl
+//! ```
+#![warn(clippy::panic)]
+
+use std::path::PathBuf;
+
+use clap::builder::NonEmptyStringValueParser;
+use clap::Parser;
+use database_utils::DatabaseType;
+use readyset_client::consensus::AuthorityType;
+use readyset_client::ReadySetHandle;
+use replicators::NoriaAdapter;
+
+#[derive(Parser)]
+#[clap(name = "replay_replication")]
+struct ReplayReplication {
+    #[clap(short, long, env("AUTHORITY_ADDRESS"), default_value("127.0.0.1:2181"))]
+    authority_address: String,
+
+    #[clap(long, env("AUTHORITY"), default_value("zookeeper"), value_parser = ["consul", "zookeeper"])]
+    authority: AuthorityType,
+
+    #[clap(short, long, env("DEPLOYMENT"), value_parser = NonEmptyStringValueParser::new())]
+    deployment: String,
+
+    /// The type of the upstream database the events were captured from.
+    #[clap(long, value_enum)]
+    database_type: DatabaseType,
+
+    /// The path of the file of captured events to replay.
+    path: PathBuf,
+}
+
+impl ReplayReplication {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let authority = self
+            .authority
+            .to_authority(&self.authority_address, &self.deployment)
+            .await;
+
+        let mut handle: ReadySetHandle = ReadySetHandle::new(authority).await;
+        handle.ready().await?;
+
+        NoriaAdapter::replay_capture(handle, &self.path, self.database_type).await?;
+        println!("Replayed replication events from {}", self.path.display());
+
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    ReplayReplication::parse().run().await
+}
@@ -0,0 +1,120 @@
+//! Inspects or converts the replication offsets persisted in the base table state of a stopped
+//! readyset-server.
+//!
+//! Each base table's RocksDB state stores the replication offset of the last write applied to it,
+//! tagged with the version of the encoding it was written with. This tool can be used to see which
+//! offsets are stored (and at which encoding version) before an upgrade, and to convert them to
+//! the current encoding ahead of time, rather than letting the server do it on startup.
+//!
+//! # Example
+//!
+//! ```bash
+//! cargo run --bin replication_offsets -- inspect /var/lib/readyset
+//! cargo run --bin replication_offsets -- migrate /var/lib/readyset/readyset-public-t1-0.db
+//! ```
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use dataflow_state::{
+    migrate_persisted_replication_offset, read_persisted_replication_offset,
+    PersistedReplicationOffset,
+};
+use readyset_client::replication::REPLICATION_OFFSET_VERSION;
+
+#[derive(Parser)]
+#[clap(name = "replication_offsets")]
+struct ReplicationOffsets {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the replication offset persisted for each base table, and the version of the
+    /// encoding it was persisted with.
+    Inspect {
+        /// Paths to base table state (directories ending in `.db`), or to a directory containing
+        /// base table state (the readyset-server `--db-dir`).
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Convert the replication offset persisted for each base table to the current encoding.
+    ///
+    /// The readyset-server must not be running.
+    Migrate {
+        /// Paths to base table state (directories ending in `.db`), or to a directory containing
+        /// base table state (the readyset-server `--db-dir`).
+        #[clap(required = true)]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Expand each of `paths` into the base table state directories it refers to
+fn base_table_paths(paths: &[PathBuf]) -> anyhow::Result<Vec<PathBuf>> {
+    let mut res = vec![];
+    for path in paths {
+        if is_base_table_path(path) {
+            res.push(path.clone());
+            continue;
+        }
+
+        let mut children = fs::read_dir(path)
+            .with_context(|| format!("Reading {}", path.display()))?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        children.retain(|child| is_base_table_path(child));
+        children.sort();
+        res.extend(children);
+    }
+    Ok(res)
+}
+
+fn is_base_table_path(path: &Path) -> bool {
+    path.is_dir() && path.extension().map_or(false, |ext| ext == "db")
+}
+
+fn print_offset(path: &Path, persisted: &PersistedReplicationOffset) {
+    let offset = match &persisted.offset {
+        Some(offset) => format!("{offset} (raw offset: {})", offset.offset),
+        None => "none".to_owned(),
+    };
+    let status = if persisted.version == REPLICATION_OFFSET_VERSION {
+        "current"
+    } else {
+        "needs migration"
+    };
+    println!(
+        "{}: {offset}, version {} ({status})",
+        path.display(),
+        persisted.version
+    );
+}
+
+impl ReplicationOffsets {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self.command {
+            Command::Inspect { paths } => {
+                for path in base_table_paths(&paths)? {
+                    let persisted = read_persisted_replication_offset(&path)
+                        .with_context(|| format!("Reading {}", path.display()))?;
+                    print_offset(&path, &persisted);
+                }
+            }
+            Command::Migrate { paths } => {
+                for path in base_table_paths(&paths)? {
+                    let persisted = migrate_persisted_replication_offset(&path)
+                        .with_context(|| format!("Migrating {}", path.display()))?;
+                    print_offset(&path, &persisted);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    ReplicationOffsets::parse().run()
+}
@@ -38,6 +38,7 @@ mod percent;
 use percent::Percent;
 pub mod presampled;
 pub mod propagation;
+pub mod statement_logging;
 
 pub fn warn_if_debug_build() {
     if cfg!(debug) {
@@ -79,7 +80,10 @@ pub struct Options {
     #[clap(long, env = "TRACING_SAMPLE_PERCENT", default_value_t = Percent(0.01))]
     tracing_sample_percent: Percent,
 
-    /// Whether to log all statements received by ReadySet via the client or replicators
+    /// Whether to log all statements received by ReadySet via the client or replicators.
+    ///
+    /// Statement logging can also be enabled or disabled for each component at runtime, via the
+    /// `/statement-logging` endpoint of the adapter's HTTP server.
     #[clap(long, env = "STATEMENT_LOGGING")]
     pub statement_logging: bool,
 
@@ -260,6 +264,10 @@ impl Options {
     /// }
     /// ```
     pub fn init(&self, service_name: &str, deployment: &str) -> Result<(), Error> {
+        for component in statement_logging::Component::ALL {
+            component.set_enabled(self.statement_logging);
+        }
+
         if self.tracing_host.is_some() {
             self.init_logging_and_tracing(service_name, deployment)
         } else {
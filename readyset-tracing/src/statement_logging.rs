@@ -0,0 +1,96 @@
+//! Runtime control of statement logging.
+//!
+//! Statement logging can be enabled or disabled separately for each [`Component`] while ReadySet
+//! is running (such as via the `/statement-logging` endpoint of the adapter's HTTP server), so that
+//! statements can be logged to debug an issue in production without a restart. All components
+//! start out logging statements if `--statement-logging` is passed.
+//!
+//! Statements logged while `--statement-logging` isn't passed are logged along with other log
+//! events, rather than to the statement log file.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+static REPLICATOR: AtomicBool = AtomicBool::new(false);
+static MYSQL_FRONTEND: AtomicBool = AtomicBool::new(false);
+static POSTGRESQL_FRONTEND: AtomicBool = AtomicBool::new(false);
+
+/// A component of ReadySet which logs the statements it receives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Component {
+    /// The replicator, which logs the replication events it receives from the upstream database
+    Replicator,
+    /// The MySQL frontend of the adapter, which logs the commands it receives from clients
+    #[serde(rename = "mysql_frontend")]
+    MySqlFrontend,
+    /// The PostgreSQL frontend of the adapter, which logs the messages it receives from clients
+    #[serde(rename = "postgresql_frontend")]
+    PostgreSqlFrontend,
+}
+
+impl Component {
+    /// All the components
+    pub const ALL: [Component; 3] = [
+        Component::Replicator,
+        Component::MySqlFrontend,
+        Component::PostgreSqlFrontend,
+    ];
+
+    fn flag(self) -> &'static AtomicBool {
+        match self {
+            Component::Replicator => &REPLICATOR,
+            Component::MySqlFrontend => &MYSQL_FRONTEND,
+            Component::PostgreSqlFrontend => &POSTGRESQL_FRONTEND,
+        }
+    }
+
+    /// Returns whether this component should log the statements it receives
+    #[inline]
+    pub fn is_enabled(self) -> bool {
+        self.flag().load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable statement logging for this component
+    pub fn set_enabled(self, enabled: bool) {
+        self.flag().store(enabled, Ordering::Relaxed)
+    }
+}
+
+/// Returns whether each component is logging statements
+pub fn statuses() -> BTreeMap<Component, bool> {
+    Component::ALL
+        .into_iter()
+        .map(|component| (component, component.is_enabled()))
+        .collect()
+}
+
+/// Enable or disable statement logging for each component in `changes`, leaving the other
+/// components unchanged
+pub fn update(changes: &BTreeMap<Component, bool>) {
+    for (component, enabled) in changes {
+        component.set_enabled(*enabled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_components() {
+        let changes: BTreeMap<Component, bool> =
+            serde_json::from_str(r#"{"mysql_frontend": true}"#).unwrap();
+        update(&changes);
+        assert!(Component::MySqlFrontend.is_enabled());
+        assert!(!Component::Replicator.is_enabled());
+
+        Component::MySqlFrontend.set_enabled(false);
+        assert_eq!(
+            serde_json::to_string(&statuses()).unwrap(),
+            r#"{"replicator":false,"mysql_frontend":false,"postgresql_frontend":false}"#
+        );
+    }
+}
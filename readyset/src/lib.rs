@@ -10,7 +10,7 @@ use std::fs::remove_dir_all;
 use std::io;
 use std::marker::Send;
 use std::net::{IpAddr, SocketAddr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Mutex, RwLock};
@@ -26,18 +26,22 @@ use futures_util::future::FutureExt;
 use futures_util::stream::StreamExt;
 use health_reporter::{HealthReporter as AdapterHealthReporter, State as AdapterState};
 use metrics_exporter_prometheus::PrometheusBuilder;
-use nom_sql::Relation;
+use nom_sql::{CacheInner, CreateCacheStatement, Relation};
 use readyset_adapter::backend::noria_connector::{NoriaConnector, ReadBehavior};
 use readyset_adapter::backend::MigrationMode;
+use readyset_adapter::cache_warmer::{CacheWarmer, KeyStatistics};
 use readyset_adapter::fallback_cache::{
     DiskModeledCache, EvictionModeledCache, FallbackCache, SimpleFallbackCache,
 };
 use readyset_adapter::http_router::NoriaAdapterHttpRouter;
 use readyset_adapter::migration_handler::MigrationHandler;
 use readyset_adapter::proxied_queries_reporter::ProxiedQueriesReporter;
-use readyset_adapter::query_status_cache::{MigrationStyle, QueryStatusCache};
+use readyset_adapter::query_status_cache::{
+    MigrationStyle, QueryStatusCache, QueryStatusPersister,
+};
+use readyset_adapter::result_cache::{ResultCache, ResultCacheInvalidator};
 use readyset_adapter::views_synchronizer::ViewsSynchronizer;
-use readyset_adapter::{Backend, BackendBuilder, QueryHandler, UpstreamDatabase};
+use readyset_adapter::{rewrite, Backend, BackendBuilder, QueryHandler, UpstreamDatabase};
 use readyset_client::consensus::{AuthorityControl, AuthorityType, ConsulAuthority};
 #[cfg(feature = "failure_injection")]
 use readyset_client::failpoints;
@@ -69,6 +73,10 @@ const REGISTER_HTTP_INTERVAL: Duration = Duration::from_secs(20);
 const AWS_PRIVATE_IP_ENDPOINT: &str = "http://169.254.169.254/latest/meta-data/local-ipv4";
 const AWS_METADATA_TOKEN_ENDPOINT: &str = "http://169.254.169.254/latest/api/token";
 
+// How frequently to retry creating caches from `--cache-statements-file` whose tables aren't
+// available yet
+const CACHE_STATEMENTS_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Timeout to use when connecting to the upstream database
 const UPSTREAM_CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
 
@@ -234,6 +242,15 @@ pub struct Options {
     #[clap(long, env = "METRICS_ADDRESS", default_value = "0.0.0.0:6034")]
     metrics_address: SocketAddr,
 
+    /// The maximum number of seconds replication can be behind the upstream database for the
+    /// adapter to report that it's ready to serve traffic on the `/readyz` endpoint. Replication
+    /// is also considered this far behind if no replication events have been received for this
+    /// long, including the heartbeats MySQL sends every second and the keepalives PostgreSQL
+    /// sends every half of `wal_sender_timeout` when they have no new writes. Replication lag
+    /// isn't checked if set to 0.
+    #[clap(long, env = "READINESS_MAX_REPLICATION_LAG_SECS", default_value = "60")]
+    readiness_max_replication_lag_secs: u64,
+
     /// Allow database connections authenticated as this user. Defaults to the username in
     /// --upstream-db-url if not set. Ignored if --allow-unauthenticated-connections is passed
     #[clap(long, env = "ALLOWED_USERNAME", short = 'u')]
@@ -267,6 +284,10 @@ pub struct Options {
     #[clap(flatten)]
     pub tracing: readyset_tracing::Options,
 
+    /// readyset-mysql-specific options
+    #[clap(flatten)]
+    pub mysql_options: mysql::Options,
+
     /// readyset-psql-specific options
     #[clap(flatten)]
     pub psql_options: psql::Options,
@@ -360,6 +381,80 @@ pub struct Options {
     #[clap(flatten)]
     fallback_cache_options: FallbackCacheOptions,
 
+    /// Cache the results of ad hoc, non-parameterized SELECT queries that are proxied to the
+    /// upstream database in the adapter's memory, using at most this many bytes.
+    ///
+    /// Results are only cached for queries which read from tables that are replicated by
+    /// ReadySet, and are invalidated once writes to those tables are replicated, so they can be
+    /// stale for up to the replication lag plus `--result-cache-invalidation-interval-ms`.
+    /// Results aren't cached if this option isn't set.
+    #[clap(long, env = "RESULT_CACHE_MAX_BYTES")]
+    result_cache_max_bytes: Option<usize>,
+
+    /// The interval in milliseconds at which to check for writes to replicated tables, to
+    /// invalidate the results in the cache enabled by `--result-cache-max-bytes`.
+    #[clap(
+        long,
+        env = "RESULT_CACHE_INVALIDATION_INTERVAL_MS",
+        default_value = "1000"
+    )]
+    result_cache_invalidation_interval_ms: u64,
+
+    /// Record the keys each cache is read with, and when a cache is created (or its reader is
+    /// recreated) read it with the keys it was most recently read with before serving it.
+    #[clap(long, env = "CACHE_WARMUP")]
+    cache_warmup: bool,
+
+    /// Save the keys recorded for `--cache-warmup` to this file, and load them on startup, so that
+    /// a restart doesn't leave every cache cold. Implies `--cache-warmup`.
+    ///
+    /// The file contains the parameter values of the queries run against each cache, and is
+    /// created readable only by its owner.
+    #[clap(long, env = "CACHE_WARMUP_FILE")]
+    cache_warmup_file: Option<PathBuf>,
+
+    /// The maximum number of keys to record for each cache for `--cache-warmup`
+    #[clap(long, env = "CACHE_WARMUP_KEYS_PER_CACHE", default_value = "100")]
+    cache_warmup_keys_per_cache: usize,
+
+    /// The interval in seconds at which to check for caches to warm up, and to save the keys
+    /// recorded to `--cache-warmup-file`
+    #[clap(long, env = "CACHE_WARMUP_INTERVAL_SECS", default_value = "5")]
+    cache_warmup_interval_secs: u64,
+
+    /// Cache queries that compare a column with `NOW()` or `CURRENT_TIMESTAMP` in their `WHERE`
+    /// clause rather than proxying them, by binding the current time to the query whenever it's
+    /// executed. The current time is rounded down to a multiple of this many seconds, so that
+    /// executions within the same interval share results, which may be up to this many seconds
+    /// stale.
+    ///
+    /// Queries calling `NOW()` are proxied if this option isn't set. Either way, clients can
+    /// change this for their own connection with `SET readyset_now_parameter_granularity = <secs>`
+    /// (or `= OFF` to proxy such queries). The current time is given in the time zone of the
+    /// client's session on the upstream database.
+    #[clap(long, env = "NOW_PARAMETER_GRANULARITY_SECS")]
+    now_parameter_granularity_secs: Option<u64>,
+
+    /// After a connection makes a write, route that connection's reads to the upstream database
+    /// for up to this many milliseconds, or until ReadySet has replicated past the write
+    /// (whichever comes first), so that the connection always reads its own writes.
+    ///
+    /// Reads are served from ReadySet straight after writes if this option isn't set.
+    #[clap(long, env = "READ_AFTER_WRITE_WINDOW_MS")]
+    read_after_write_window_ms: Option<u64>,
+
+    /// Save the migration state of each query the adapter has processed to this file, and load it
+    /// when the adapter starts, so that queries which were already migrated before a restart are
+    /// served from ReadySet straight away rather than being migrated again.
+    ///
+    /// Migration states aren't persisted if this option isn't set.
+    #[clap(long, env = "QUERY_STATUS_FILE")]
+    query_status_file: Option<PathBuf>,
+
+    /// The interval in seconds at which to save migration states to `--query-status-file`
+    #[clap(long, env = "QUERY_STATUS_SAVE_INTERVAL_SECS", default_value = "5")]
+    query_status_save_interval_secs: u64,
+
     /// Whether to allow ReadySet to automatically create inlined caches when we receive a CREATE
     /// CACHE command for a query with unsupported placeholders.
     ///
@@ -375,6 +470,14 @@ pub struct Options {
     #[clap(long, env = "NO_UPSTREAM_CONNECTIONS")]
     no_upstream_connections: bool,
 
+    /// Path to a file of `CREATE CACHE` statements (such as the output of `SHOW READYSET CREATE
+    /// CACHES`) to create when the adapter starts up.
+    ///
+    /// Statements referencing tables that haven't been snapshotted yet are retried until the
+    /// tables are available.
+    #[clap(long, env = "CACHE_STATEMENTS_FILE")]
+    cache_statements_file: Option<PathBuf>,
+
     /// If supplied we will clean up assets for the supplied deployment. If an upstream url is
     /// supplied, we will also clean up various assets related to upstream (replication slot, etc.)
     #[clap(long)]
@@ -555,6 +658,9 @@ where
                 .server_worker_options
                 .enable_experimental_paginate_support;
         let no_upstream_connections = options.no_upstream_connections;
+        let now_parameter_granularity = options
+            .now_parameter_granularity_secs
+            .map(Duration::from_secs);
 
         let rh = rt.block_on(async {
             let authority = authority
@@ -700,6 +806,21 @@ where
                 .automatic_placeholder_inlining(options.automatic_placeholder_inlining),
         ));
 
+        if let Some(path) = &options.query_status_file {
+            match query_status_cache.load(path) {
+                Ok(num_queries) => rs_connect.in_scope(
+                    || info!(path = %path.display(), num_queries, "Loaded query statuses"),
+                ),
+                Err(error) => rs_connect.in_scope(|| {
+                    warn!(
+                        %error,
+                        path = %path.display(),
+                        "Could not load query statuses; queries will be migrated again"
+                    )
+                }),
+            }
+        }
+
         let telemetry_sender = rt.block_on(async {
             let proxied_queries_reporter =
                 Arc::new(ProxiedQueriesReporter::new(query_status_cache));
@@ -745,6 +866,9 @@ where
             prometheus_handle,
             health_reporter: health_reporter.clone(),
             failpoint_channel: tx,
+            controller: rh.clone(),
+            max_replication_lag: (options.readiness_max_replication_lag_secs > 0)
+                .then(|| Duration::from_secs(options.readiness_max_replication_lag_secs)),
         };
 
         let router_shutdown_rx = shutdown_rx.clone();
@@ -857,7 +981,8 @@ where
                     .instrument(connection.in_scope(|| {
                         span!(Level::DEBUG, "Building migration task noria connector")
                     }))
-                    .await;
+                    .await
+                    .with_now_parameter_granularity(now_parameter_granularity);
 
                 let controller_handle = dry_run.then(|| rh.clone());
                 let mut migration_handler = MigrationHandler::new(
@@ -900,6 +1025,120 @@ where
             rt.handle().spawn(abort_on_panic(fut));
         }
 
+        let result_cache: Option<
+            ResultCache<
+                <<H as ConnectionHandler>::UpstreamDatabase as UpstreamDatabase>::CachedReadResult,
+            >,
+        > = options.result_cache_max_bytes.map(ResultCache::new);
+        if let Some(result_cache) = &result_cache {
+            rs_connect.in_scope(|| info!("Spawning result cache invalidator task"));
+            let rh = rh.clone();
+            let result_cache = result_cache.clone();
+            let poll_interval =
+                Duration::from_millis(options.result_cache_invalidation_interval_ms);
+            let shutdown_rx = shutdown_rx.clone();
+            let fut = async move {
+                let mut invalidator =
+                    ResultCacheInvalidator::new(rh, result_cache, poll_interval, shutdown_rx);
+                invalidator.run().await
+            };
+            rt.handle().spawn(abort_on_panic(fut));
+        }
+
+        if let Some(path) = &options.query_status_file {
+            rs_connect.in_scope(|| info!("Spawning query status persister task"));
+            let path = path.clone();
+            let save_interval = Duration::from_secs(options.query_status_save_interval_secs);
+            let shutdown_rx = shutdown_rx.clone();
+            let fut = async move {
+                let mut persister =
+                    QueryStatusPersister::new(query_status_cache, path, save_interval, shutdown_rx);
+                persister.run().await
+            };
+            rt.handle().spawn(abort_on_panic(fut));
+        }
+
+        let key_statistics = if options.cache_warmup || options.cache_warmup_file.is_some() {
+            let key_statistics = KeyStatistics::new(options.cache_warmup_keys_per_cache);
+
+            rs_connect.in_scope(|| info!("Spawning cache warmer task"));
+            let rh = rh.clone();
+            let path = options.cache_warmup_file.clone();
+            let poll_interval = Duration::from_secs(options.cache_warmup_interval_secs);
+            let expr_dialect = self.expr_dialect;
+            let shutdown_rx = shutdown_rx.clone();
+            let warmer_key_statistics = key_statistics.clone();
+            let fut = async move {
+                let mut warmer = CacheWarmer::new(
+                    rh,
+                    warmer_key_statistics,
+                    path,
+                    poll_interval,
+                    expr_dialect,
+                    shutdown_rx,
+                );
+                warmer.run().await
+            };
+            rt.handle().spawn(abort_on_panic(fut));
+            Some(key_statistics)
+        } else {
+            None
+        };
+
+        if let Some(path) = &options.cache_statements_file {
+            let statements = read_cache_statements(path, self.parse_dialect)?;
+            rs_connect.in_scope(|| {
+                info!(
+                    path = %path.display(),
+                    num_statements = statements.len(),
+                    "Spawning task to create caches from file"
+                )
+            });
+            let rh = rh.clone();
+            let (auto_increments, query_cache) = (auto_increments.clone(), query_cache.clone());
+            let upstream_config = options.server_worker_options.replicator_config.clone();
+            let expr_dialect = self.expr_dialect;
+            let parse_dialect = self.parse_dialect;
+            let fallback_cache = fallback_cache.clone();
+            let fut = async move {
+                let schema_search_path =
+                    if upstream_config.upstream_db_url.is_some() && !no_upstream_connections {
+                        let mut upstream =
+                            H::UpstreamDatabase::connect(upstream_config, fallback_cache).await?;
+                        upstream.schema_search_path().await?
+                    } else {
+                        Default::default()
+                    };
+
+                let noria = NoriaConnector::new(
+                    rh,
+                    auto_increments,
+                    query_cache,
+                    noria_read_behavior,
+                    expr_dialect,
+                    parse_dialect,
+                    schema_search_path,
+                    server_supports_pagination,
+                )
+                .await
+                .with_now_parameter_granularity(now_parameter_granularity);
+                create_caches_from_statements(
+                    noria,
+                    statements,
+                    server_supports_pagination,
+                    now_parameter_granularity,
+                )
+                .await;
+                Ok::<_, <H::UpstreamDatabase as UpstreamDatabase>::Error>(())
+            }
+            .map(|res| {
+                if let Err(error) = res {
+                    error!(%error, "Could not create caches from file");
+                }
+            });
+            rt.handle().spawn(abort_on_panic(fut));
+        }
+
         // Spin up async task that is in charge of creating a session with the authority,
         // regularly updating the heartbeat to keep the session live, and registering the adapters
         // http endpoint.
@@ -938,8 +1177,6 @@ where
                 builder.cannot_become_leader();
             }
 
-            builder.set_replicator_statement_logging(options.tracing.statement_logging);
-
             builder.set_telemetry_sender(telemetry_sender.clone());
 
             let server_handle = rt.block_on(async move {
@@ -1001,7 +1238,12 @@ where
                 .migration_mode(migration_mode)
                 .query_max_failure_seconds(options.query_max_failure_seconds)
                 .telemetry_sender(telemetry_sender.clone())
-                .fallback_recovery_seconds(options.fallback_recovery_seconds);
+                .fallback_recovery_seconds(options.fallback_recovery_seconds)
+                .read_after_write_window(
+                    options
+                        .read_after_write_window_ms
+                        .map(Duration::from_millis),
+                );
             let telemetry_sender = telemetry_sender.clone();
 
             // Initialize the reader layer for the adapter.
@@ -1010,12 +1252,19 @@ where
                 // When the `BlockingRead` completes, tell the future to resolve with ack.
                 let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<(BlockingRead, Ack)>();
                 rt.handle().spawn(retry_misses(rx));
-                ReadRequestHandler::new(readers.clone(), tx, Duration::from_secs(5))
+                ReadRequestHandler::new(
+                    readers.clone(),
+                    Default::default(),
+                    tx,
+                    Duration::from_secs(5),
+                )
             });
 
             let query_status_cache = query_status_cache;
             let upstream_config = upstream_config.clone();
             let fallback_cache = fallback_cache.clone();
+            let result_cache = result_cache.clone();
+            let key_statistics = key_statistics.clone();
             let fut = async move {
                 let upstream_res =
                     if upstream_config.upstream_db_url.is_some() && !no_upstream_connections {
@@ -1072,13 +1321,14 @@ where
                                     server_supports_pagination,
                                 )
                                 .instrument(debug_span!("Building noria connector"))
-                                .await;
+                                .await
+                                .with_key_statistics(key_statistics)
+                                .with_now_parameter_granularity(now_parameter_granularity);
 
-                                let backend = backend_builder.clone().build(
-                                    noria,
-                                    upstream,
-                                    query_status_cache,
-                                );
+                                let backend = backend_builder
+                                    .clone()
+                                    .build(noria, upstream, query_status_cache)
+                                    .with_result_cache(result_cache);
                                 connection_handler.process_connection(s, backend).await;
                             }
                             Err(error) => {
@@ -1230,6 +1480,78 @@ async fn my_aws_ip() -> anyhow::Result<IpAddr> {
         .parse()?)
 }
 
+/// Read and parse the `CREATE CACHE` statements in the file at `path`, making sure every one of
+/// them can be created without having to consult the upstream database or the query status cache
+fn read_cache_statements(
+    path: &Path,
+    dialect: nom_sql::Dialect,
+) -> anyhow::Result<Vec<CreateCacheStatement>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?;
+    let statements = nom_sql::parse_create_caches(dialect, contents)
+        .map_err(|e| anyhow!("Could not parse {}: {e}", path.display()))?;
+    for stmt in &statements {
+        match &stmt.inner {
+            Ok(CacheInner::Statement(_)) => {}
+            Ok(CacheInner::Id(id)) => bail!(
+                "{}: caches must be created from a query, not a query ID (got {id})",
+                path.display()
+            ),
+            Err(unparsed) => bail!("{}: could not parse query: {unparsed}", path.display()),
+        }
+    }
+    Ok(statements)
+}
+
+/// Create a cache for each of `statements`.
+///
+/// Statements that fail because a table they reference doesn't exist yet are retried every
+/// [`CACHE_STATEMENTS_RETRY_INTERVAL`], since on startup we might still be snapshotting.
+async fn create_caches_from_statements(
+    mut noria: NoriaConnector,
+    statements: Vec<CreateCacheStatement>,
+    server_supports_pagination: bool,
+    now_parameter_granularity: Option<Duration>,
+) {
+    for stmt in statements {
+        let mut query = match stmt.inner {
+            Ok(CacheInner::Statement(query)) => *query,
+            // Checked by `read_cache_statements`
+            _ => continue,
+        };
+        if let Err(error) = rewrite::process_query(
+            &mut query,
+            server_supports_pagination,
+            now_parameter_granularity,
+        ) {
+            error!(%error, "Could not create cache from file");
+            continue;
+        }
+
+        loop {
+            match noria
+                .handle_create_cached_query(stmt.name.as_ref(), &query, None, stmt.always)
+                .await
+            {
+                Ok(()) => break,
+                Err(error)
+                    if error.caused_by_table_not_found()
+                        || error.caused_by_table_not_replicated()
+                        || error.is_networking_related() =>
+                {
+                    debug!(%error, "Could not create cache from file yet, retrying");
+                    tokio::time::sleep(CACHE_STATEMENTS_RETRY_INTERVAL).await;
+                }
+                Err(error) => {
+                    error!(%error, "Could not create cache from file");
+                    break;
+                }
+            }
+        }
+    }
+    info!("Finished creating caches from file");
+}
+
 /// Facilitates continuously updating consul with this adapters externally accessibly http
 /// endpoint.
 async fn reconcile_endpoint_registration(
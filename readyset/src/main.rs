@@ -12,9 +12,9 @@ fn main() -> anyhow::Result<()> {
         DatabaseType::MySQL => NoriaAdapter {
             description: "MySQL adapter for ReadySet.",
             default_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3306),
-            connection_handler: MySqlHandler {
-                enable_statement_logging: options.tracing.statement_logging,
-            },
+            connection_handler: MySqlHandler::new(readyset::mysql::Config {
+                options: options.mysql_options.clone(),
+            })?,
             database_type: DatabaseType::MySQL,
             parse_dialect: nom_sql::Dialect::MySQL,
             expr_dialect: readyset_data::Dialect::DEFAULT_MYSQL,
@@ -25,7 +25,6 @@ fn main() -> anyhow::Result<()> {
             default_address: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3306),
             connection_handler: PsqlHandler::new(readyset::psql::Config {
                 options: options.psql_options.clone(),
-                enable_statement_logging: options.tracing.statement_logging,
             })?,
             database_type: DatabaseType::PostgreSQL,
             parse_dialect: nom_sql::Dialect::PostgreSQL,
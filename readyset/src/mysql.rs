@@ -1,15 +1,90 @@
+use std::path::PathBuf;
+
 use async_trait::async_trait;
-use mysql_srv::MySqlIntermediary;
+use clap::Parser;
+use mysql_srv::{CompressionOptions, MySqlIntermediary, TlsConfig};
+use readyset_errors::ReadySetResult;
 use readyset_mysql::{MySqlQueryHandler, MySqlUpstream};
 use tokio::net::TcpStream;
 use tracing::{error, instrument};
 
 use crate::ConnectionHandler;
 
-#[derive(Clone, Copy)]
+/// readyset-mysql specific options
+#[derive(Clone, Debug, Parser)]
+pub struct Options {
+    /// The PEM-encoded certificate chain used by ReadySet for establishing TLS connections with
+    /// MySQL clients as the server.
+    ///
+    /// ReadySet will not accept TLS connections from MySQL clients if there is no certificate
+    /// specified.
+    #[clap(long, env = "MYSQL_TLS_CERT", requires = "mysql_tls_key")]
+    mysql_tls_cert: Option<PathBuf>,
+
+    /// The PEM-encoded private key for the certificate passed to `--mysql-tls-cert`
+    #[clap(long, env = "MYSQL_TLS_KEY", requires = "mysql_tls_cert")]
+    mysql_tls_key: Option<PathBuf>,
+
+    /// PEM-encoded CA certificates to verify MySQL client certificates with.
+    ///
+    /// If specified, MySQL clients must connect over TLS and present a certificate signed by one
+    /// of these CAs; plaintext connections are rejected.
+    #[clap(long, env = "MYSQL_TLS_CLIENT_CA", requires = "mysql_tls_cert")]
+    mysql_tls_client_ca: Option<PathBuf>,
+
+    /// Writes to MySQL clients that negotiate the compressed protocol which are shorter than this
+    /// many bytes are sent uncompressed.
+    ///
+    /// Results are compressed each time they're flushed to the client, so the rows of a large
+    /// result set are compressed in batches, while small result sets (which aren't worth
+    /// compressing) are flushed whole. Writes that don't compress well are followed by a few
+    /// writes that aren't compressed at all.
+    #[clap(long, env = "MYSQL_COMPRESSION_MIN_LENGTH", default_value = "1024")]
+    mysql_compression_min_length: usize,
+
+    /// The zlib compression level (from 0 to 9) used for replies to MySQL clients that negotiate
+    /// zlib compression. Clients that negotiate zstd compression choose their own level.
+    #[clap(
+        long,
+        env = "MYSQL_COMPRESSION_LEVEL",
+        default_value = "6",
+        value_parser = clap::value_parser!(u32).range(0..=9)
+    )]
+    mysql_compression_level: u32,
+}
+
+/// Contains mysql-srv specific `Options`.
+pub struct Config {
+    pub options: Options,
+}
+
+#[derive(Clone)]
 pub struct MySqlHandler {
-    /// Whether to log statements received by the client
-    pub enable_statement_logging: bool,
+    /// How to accept TLS connections from clients, if TLS is enabled
+    pub tls: Option<TlsConfig>,
+    /// How to compress replies to clients that negotiate the compressed protocol
+    pub compression: CompressionOptions,
+}
+
+impl MySqlHandler {
+    pub fn new(config: Config) -> ReadySetResult<MySqlHandler> {
+        let options = config.options;
+        let tls = match (options.mysql_tls_cert, options.mysql_tls_key) {
+            (Some(cert), Some(key)) => Some(mysql_srv::tls_config(
+                &cert,
+                &key,
+                options.mysql_tls_client_ca.as_deref(),
+            )?),
+            _ => None,
+        };
+
+        let compression = CompressionOptions {
+            min_length: options.mysql_compression_min_length,
+            zlib_level: options.mysql_compression_level,
+        };
+
+        Ok(MySqlHandler { tls, compression })
+    }
 }
 
 #[async_trait]
@@ -24,12 +99,10 @@ impl ConnectionHandler for MySqlHandler {
         backend: readyset_adapter::Backend<MySqlUpstream, MySqlQueryHandler>,
     ) {
         if let Err(e) = MySqlIntermediary::run_on_tcp(
-            readyset_mysql::Backend {
-                noria: backend,
-                enable_statement_logging: self.enable_statement_logging,
-            },
+            readyset_mysql::Backend::new(backend),
             stream,
-            self.enable_statement_logging,
+            self.tls.clone(),
+            self.compression,
         )
         .await
         {
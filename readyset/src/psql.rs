@@ -38,16 +38,13 @@ pub struct Options {
     postgres_authentication_method: AuthenticationMethod,
 }
 
-/// Contains psql-srv specific `Options` and whether to enable statement logging.
+/// Contains psql-srv specific `Options`.
 pub struct Config {
     pub options: Options,
-    pub enable_statement_logging: bool,
 }
 
 #[derive(Clone)]
 pub struct PsqlHandler {
-    /// Whether to log statements received from the client
-    pub enable_statement_logging: bool,
     /// Authentication method to use for clients
     pub authentication_method: AuthenticationMethod,
     /// Optional struct to accept a TLS handshake and return a `TlsConnection`.
@@ -84,7 +81,6 @@ impl PsqlHandler {
         };
 
         Ok(PsqlHandler {
-            enable_statement_logging: config.enable_statement_logging,
             authentication_method: config.options.postgres_authentication_method,
             tls_acceptor,
         })
@@ -106,7 +102,6 @@ impl ConnectionHandler for PsqlHandler {
             readyset_psql::Backend::new(backend)
                 .with_authentication_method(self.authentication_method),
             stream,
-            self.enable_statement_logging,
             self.tls_acceptor.clone(),
         )
         .await;
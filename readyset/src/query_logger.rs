@@ -5,7 +5,7 @@ use metrics::{register_counter, register_histogram, Counter, Histogram, SharedSt
 use nom_sql::SqlQuery;
 use readyset_client::query::QueryId;
 use readyset_client_metrics::{
-    recorded, DatabaseType, EventType, QueryExecutionEvent, SqlQueryType,
+    recorded, DatabaseType, EventType, ExecutionPath, QueryExecutionEvent, SqlQueryType,
 };
 use readyset_sql_passes::anonymize::anonymize_literals;
 use readyset_util::shutdown::ShutdownReceiver;
@@ -13,14 +13,18 @@ use tokio::select;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tracing::{info, info_span};
 
+/// The application or client library that issued a query, if the client identified itself
+type Client = Option<Arc<str>>;
+
 pub(crate) struct QueryLogger {
-    per_id_metrics: BTreeMap<QueryId, QueryMetrics>,
-    per_query_metrics: HashMap<Arc<SqlQuery>, QueryMetrics>,
+    per_id_metrics: BTreeMap<(QueryId, Client), QueryMetrics>,
+    per_query_metrics: HashMap<(Arc<SqlQuery>, Client), QueryMetrics>,
 }
 
 struct QueryMetrics {
     query: SharedString,
     query_id: Option<SharedString>,
+    client: Option<SharedString>,
     num_keys: Counter,
     cache_misses: Counter,
     cache_keys_missed: Counter,
@@ -32,6 +36,7 @@ struct QueryHistograms {
     parse_time: Option<Histogram>,
     upstream_exe_time: Option<Histogram>,
     readyset_exe_time: Option<Histogram>,
+    path_exe_time: BTreeMap<ExecutionPath, Histogram>,
 }
 
 impl QueryMetrics {
@@ -50,6 +55,9 @@ impl QueryMetrics {
                 if let Some(id) = &self.query_id {
                     labels.push(("query_id", id.clone()));
                 }
+                if let Some(client) = &self.client {
+                    labels.push(("client", client.clone()));
+                }
 
                 register_histogram!(recorded::QUERY_LOG_PARSE_TIME, &labels)
             })
@@ -71,11 +79,43 @@ impl QueryMetrics {
                 if let Some(id) = &self.query_id {
                     labels.push(("query_id", id.clone()));
                 }
+                if let Some(client) = &self.client {
+                    labels.push(("client", client.clone()));
+                }
 
                 register_histogram!(recorded::QUERY_LOG_EXECUTION_TIME, &labels)
             })
     }
 
+    fn execution_path_histogram(
+        &mut self,
+        kind: (EventType, SqlQueryType),
+        path: ExecutionPath,
+    ) -> &mut Histogram {
+        self.histograms
+            .entry(kind)
+            .or_default()
+            .path_exe_time
+            .entry(path)
+            .or_insert_with(|| {
+                let mut labels = vec![
+                    ("query", self.query.clone()),
+                    ("event_type", SharedString::from(kind.0)),
+                    ("query_type", SharedString::from(kind.1)),
+                    ("path", SharedString::from(path)),
+                ];
+
+                if let Some(id) = &self.query_id {
+                    labels.push(("query_id", id.clone()));
+                }
+                if let Some(client) = &self.client {
+                    labels.push(("client", client.clone()));
+                }
+
+                register_histogram!(recorded::QUERY_LOG_EXECUTION_PATH_TIME, &labels)
+            })
+    }
+
     fn upstream_histogram(&mut self, kind: (EventType, SqlQueryType)) -> &mut Histogram {
         self.histograms
             .entry(kind)
@@ -92,6 +132,9 @@ impl QueryMetrics {
                 if let Some(id) = &self.query_id {
                     labels.push(("query_id", id.clone()));
                 }
+                if let Some(client) = &self.client {
+                    labels.push(("client", client.clone()));
+                }
 
                 register_histogram!(recorded::QUERY_LOG_EXECUTION_TIME, &labels)
             })
@@ -103,7 +146,7 @@ impl QueryLogger {
         SharedString::from(match query {
             SqlQuery::Select(stmt) => {
                 let mut stmt = stmt.clone();
-                if readyset_adapter::rewrite::process_query(&mut stmt, true).is_ok() {
+                if readyset_adapter::rewrite::process_query(&mut stmt, true, None).is_ok() {
                     anonymize_literals(&mut stmt);
                     // FIXME(ENG-2499): Use correct dialect.
                     stmt.display(nom_sql::Dialect::MySQL).to_string()
@@ -115,60 +158,53 @@ impl QueryLogger {
         })
     }
 
-    fn metrics_for_id(&mut self, query_id: QueryId, query: Arc<SqlQuery>) -> &mut QueryMetrics {
-        self.per_id_metrics.entry(query_id).or_insert_with(|| {
-            let query_string = Self::query_string(&query);
-            let query_id = SharedString::from(query_id.to_string());
-
-            QueryMetrics {
-                num_keys: register_counter!(
-                    recorded::QUERY_LOG_TOTAL_KEYS_READ,
-                    "query" => query_string.clone(),
-                    "query_id" => query_id.clone(),
-                ),
-                cache_misses: register_counter!(
-                    recorded::QUERY_LOG_QUERY_CACHE_MISSED,
-                    "query" => query_string.clone(),
-                    "query_id" => query_id.clone(),
-                ),
-                cache_keys_missed: register_counter!(
-                    recorded::QUERY_LOG_TOTAL_CACHE_MISSES,
-                    "query" => query_string.clone(),
-                    "query_id" => query_id.clone(),
-                ),
-                query: query_string,
-                query_id: Some(query_id),
-                histograms: BTreeMap::new(),
-            }
-        })
+    fn new_metrics(
+        query: &SqlQuery,
+        query_id: Option<QueryId>,
+        client: Option<&str>,
+    ) -> QueryMetrics {
+        let query_string = Self::query_string(query);
+        let query_id = query_id.map(|id| SharedString::from(id.to_string()));
+        let client = client.map(|client| SharedString::from(client.to_owned()));
+
+        let mut labels = vec![("query", query_string.clone())];
+        if let Some(id) = &query_id {
+            labels.push(("query_id", id.clone()));
+        }
+        if let Some(client) = &client {
+            labels.push(("client", client.clone()));
+        }
+
+        QueryMetrics {
+            num_keys: register_counter!(recorded::QUERY_LOG_TOTAL_KEYS_READ, &labels),
+            cache_misses: register_counter!(recorded::QUERY_LOG_QUERY_CACHE_MISSED, &labels),
+            cache_keys_missed: register_counter!(recorded::QUERY_LOG_TOTAL_CACHE_MISSES, &labels),
+            query: query_string,
+            query_id,
+            client,
+            histograms: BTreeMap::new(),
+        }
     }
 
-    fn metrics_for_query(&mut self, query: Arc<SqlQuery>) -> &mut QueryMetrics {
-        self.per_query_metrics
-            .entry(query)
-            .or_insert_with_key(|query| {
-                let query_string = Self::query_string(query);
-
-                QueryMetrics {
-                    num_keys: register_counter!(
-                        readyset_client_metrics::recorded::QUERY_LOG_TOTAL_KEYS_READ,
-                        "query" => query_string.clone(),
-                    ),
-                    cache_misses: register_counter!(
-                        readyset_client_metrics::recorded::QUERY_LOG_QUERY_CACHE_MISSED,
-                        "query" => query_string.clone(),
-                    ),
-                    cache_keys_missed: register_counter!(
-                        readyset_client_metrics::recorded::QUERY_LOG_TOTAL_CACHE_MISSES,
-                        "query" => query_string.clone(),
-                    ),
-                    query: query_string,
-                    query_id: None,
-                    histograms: BTreeMap::new(),
-                }
+    fn metrics_for_id(
+        &mut self,
+        query_id: QueryId,
+        query: Arc<SqlQuery>,
+        client: Client,
+    ) -> &mut QueryMetrics {
+        self.per_id_metrics
+            .entry((query_id, client))
+            .or_insert_with_key(|(query_id, client)| {
+                Self::new_metrics(&query, Some(*query_id), client.as_deref())
             })
     }
 
+    fn metrics_for_query(&mut self, query: Arc<SqlQuery>, client: Client) -> &mut QueryMetrics {
+        self.per_query_metrics
+            .entry((query, client))
+            .or_insert_with_key(|(query, client)| Self::new_metrics(query, None, client.as_deref()))
+    }
+
     /// Async task that logs query stats.
     pub(crate) async fn run(
         mut receiver: UnboundedReceiver<QueryExecutionEvent>,
@@ -202,15 +238,19 @@ impl QueryLogger {
                         }
                     };
 
+                    // Computed before moving the query out of the event
+                    let execution_path = event.execution_path();
+                    let execution_duration = event.execution_duration();
+
                     let query = match event.query {
                         Some(query) => query,
                         None => continue,
                     };
 
                     let metrics = if let Some(id) = event.query_id {
-                        logger.metrics_for_id(id, query)
+                        logger.metrics_for_id(id, query, event.client)
                     } else {
-                        logger.metrics_for_query(query)
+                        logger.metrics_for_query(query, event.client)
                     };
 
                     if let Some(num_keys) = event.num_keys {
@@ -241,6 +281,12 @@ impl QueryLogger {
                             .upstream_histogram((event.event, event.sql_type))
                             .record(duration);
                     }
+
+                    if let (Some(path), Some(duration)) = (execution_path, execution_duration) {
+                        metrics
+                            .execution_path_histogram((event.event, event.sql_type), path)
+                            .record(duration);
+                    }
                 }
             }
         }
@@ -0,0 +1,353 @@
+//! Periodically checking that replicated tables have the same rows in ReadySet as in the upstream
+//! database, per [`UpstreamConfig::consistency_check_interval`]
+//!
+//! Each check computes a [`TableChecksum`] of every row of the table in ReadySet, and the same
+//! checksum of every row read from the upstream table, converted to ReadySet values in the same
+//! way as when the table is snapshotted. The checksums don't depend on the order of the rows, so
+//! the tables don't need to be read in the same order.
+//!
+//! Both checksums are computed as of the same replication offset. The upstream table is read in a
+//! transaction that sees it as of the replication offset at which it started, in the same way as
+//! when it's snapshotted, and the replication loop is sent a [`ChecksumRequest`] for that offset.
+//! The replication loop computes the checksum of the table in ReadySet once it has applied every
+//! write up to that offset, before it applies any later ones, pausing replication until it's
+//! done.
+//!
+//! [`UpstreamConfig::consistency_check_interval`]: database_utils::UpstreamConfig::consistency_check_interval
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::future::{self, Either};
+use metrics::{counter, gauge};
+use nom_sql::Relation;
+use readyset_client::metrics::recorded;
+use readyset_client::replication::{ReplicationOffset, TableChecksum, TableConsistency};
+use readyset_client::{ReadySetHandle, TableReplicationStatus};
+use readyset_data::{DfType, DfValue, Dialect};
+use readyset_errors::{internal_err, ReadySetError, ReadySetResult};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::db_util::table_column_types;
+use crate::mysql_connector::MySqlReplicator;
+use crate::postgres_connector;
+use crate::snapshot_throttle::SnapshotThrottle;
+use crate::table_filter::TableFilter;
+
+/// How many rows of an upstream table with a primary key to read at a time
+const CHUNK_SIZE: u64 = 10_000;
+
+/// How long to wait for replication to reach the offset a table was read upstream at
+const REPLICATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How many times to try checking a table whose checksum in ReadySet couldn't be computed at the
+/// offset it was read upstream at, because replication was already past it
+const MAX_CHECK_ATTEMPTS: usize = 3;
+
+/// A request for the replication loop to compute the checksum of `table` in ReadySet as of
+/// `offset`: after every write up to `offset` has been applied, and before any later write is
+pub(crate) struct ChecksumRequest {
+    pub(crate) table: Relation,
+    pub(crate) offset: ReplicationOffset,
+    /// Sent the checksum, or `None` if replication was already past `offset` when the request was
+    /// received
+    pub(crate) response: oneshot::Sender<ReadySetResult<Option<TableChecksum>>>,
+}
+
+/// The upstream database that tables are checked against
+pub(crate) enum Upstream {
+    MySql(MySqlReplicator),
+    PostgreSql(deadpool_postgres::Pool),
+}
+
+impl Upstream {
+    /// Check tables against the MySQL database `pool` is connected to
+    pub(crate) fn mysql(pool: mysql_async::Pool) -> Self {
+        Upstream::MySql(MySqlReplicator {
+            pool,
+            table_filter: TableFilter::for_all_tables(),
+            source: None,
+            snapshot_parallelism: None,
+            snapshot_chunk_size: Some(CHUNK_SIZE),
+            checkpoints: None,
+            throttle: SnapshotThrottle::default(),
+            resnapshot_tables: HashSet::new(),
+            dump: None,
+        })
+    }
+
+    fn dialect(&self) -> Dialect {
+        match self {
+            Upstream::MySql(_) => Dialect::DEFAULT_MYSQL,
+            Upstream::PostgreSql(_) => Dialect::DEFAULT_POSTGRESQL,
+        }
+    }
+
+    /// Compute the checksum of every row of `table` in the upstream database, coercing the values
+    /// of each row to the types of `columns` as they would be when written to ReadySet. The
+    /// replication offset the table is read as of is sent on `offset` before it's read.
+    async fn table_checksum(
+        &self,
+        table: &Relation,
+        columns: &[(String, DfType)],
+        offset: oneshot::Sender<ReplicationOffset>,
+    ) -> ReadySetResult<TableChecksum> {
+        let mut checksum = TableChecksum::default();
+        let add_row = |mut row: Vec<DfValue>| -> ReadySetResult<()> {
+            for (value, (_, ty)) in row.iter_mut().zip(columns) {
+                value.maybe_coerce_for_table_op(ty)?;
+            }
+            checksum.add_row(&row);
+            Ok(())
+        };
+
+        match self {
+            Upstream::MySql(replicator) => {
+                let (table_offset, mut dumper) =
+                    replicator.dump_table_at_current_position(table).await?;
+                let _ = offset.send(table_offset);
+                dumper.for_each_row(add_row).await?;
+            }
+            Upstream::PostgreSql(pool) => {
+                postgres_connector::read_table_at_current_position(
+                    pool, table, CHUNK_SIZE, offset, add_row,
+                )
+                .await?;
+            }
+        }
+        Ok(checksum)
+    }
+}
+
+/// A handle to the results of the most recent consistency check of each replicated table. Cloning a
+/// [`ConsistencyChecks`] returns a handle to the same results.
+#[derive(Debug, Clone, Default)]
+pub struct ConsistencyChecks {
+    results: Arc<Mutex<BTreeMap<Relation, TableConsistency>>>,
+}
+
+impl ConsistencyChecks {
+    /// Returns the result of the most recent consistency check of each table that has been
+    /// checked
+    pub fn results(&self) -> BTreeMap<Relation, TableConsistency> {
+        self.results.lock().expect("poisoned").clone()
+    }
+
+    /// Record the checksums computed by a check of `table`, returning the table's new result
+    fn record(
+        &self,
+        table: &Relation,
+        readyset: TableChecksum,
+        upstream: TableChecksum,
+    ) -> TableConsistency {
+        let mut results = self.results.lock().expect("poisoned");
+        let consecutive_mismatches = match results.get(table) {
+            _ if readyset == upstream => 0,
+            Some(previous) => previous.consecutive_mismatches + 1,
+            None => 1,
+        };
+        let result = TableConsistency {
+            readyset,
+            upstream,
+            consecutive_mismatches,
+            checked_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        results.insert(table.clone(), result.clone());
+        result
+    }
+}
+
+/// Every `interval`, check each table replicated according to `table_filter` against `upstream`,
+/// sending requests for the checksums of the tables in ReadySet to the replication loop on
+/// `checksum_requests`, and recording the results in `checks`. Never returns.
+pub(crate) async fn check_tables(
+    mut noria: ReadySetHandle,
+    upstream: Upstream,
+    table_filter: &TableFilter,
+    interval: Duration,
+    checksum_requests: mpsc::Sender<ChecksumRequest>,
+    checks: ConsistencyChecks,
+) {
+    let mut interval = tokio::time::interval(interval);
+    // The first tick completes immediately, and tables have only just been snapshotted
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+
+        let tables = match noria.table_statuses().await {
+            Ok(tables) => tables,
+            Err(error) => {
+                warn!(%error, "Could not list tables to check for consistency");
+                continue;
+            }
+        };
+        for (table, status) in tables {
+            if status.replication_status != TableReplicationStatus::Snapshotted
+                || !table.schema.as_ref().map_or(false, |schema| {
+                    table_filter.should_be_processed(schema.as_str(), table.name.as_str())
+                })
+            {
+                continue;
+            }
+
+            if let Err(error) =
+                check_table(&mut noria, &upstream, &table, &checksum_requests, &checks).await
+            {
+                warn!(%error, %table, "Could not check table for consistency");
+            }
+        }
+    }
+}
+
+/// Compute the checksums of `table` in ReadySet and in `upstream` as of the same replication
+/// offset, reading both at once. Returns `None` if replication was already past the offset the
+/// table was read upstream at by the time the replication loop received the request for its
+/// checksum in ReadySet.
+async fn table_checksums(
+    upstream: &Upstream,
+    table: &Relation,
+    columns: &[(String, DfType)],
+    checksum_requests: &mpsc::Sender<ChecksumRequest>,
+) -> ReadySetResult<Option<(TableChecksum, TableChecksum)>> {
+    let (offset_tx, offset_rx) = oneshot::channel();
+    let upstream_checksum = upstream.table_checksum(table, columns, offset_tx);
+    let readyset_checksum = async {
+        // If the table couldn't be read upstream, the error is returned from reading it
+        let Ok(offset) = offset_rx.await else {
+            return future::pending().await;
+        };
+        let (response_tx, response_rx) = oneshot::channel();
+        checksum_requests
+            .send(ChecksumRequest {
+                table: table.clone(),
+                offset,
+                response: response_tx,
+            })
+            .await
+            .map_err(|_| internal_err!("Replication has stopped"))?;
+        tokio::time::timeout(REPLICATION_TIMEOUT, response_rx)
+            .await
+            .map_err(|_| {
+                ReadySetError::ReplicationFailed(
+                    "Timed out waiting for replication to reach the offset the table was read at"
+                        .into(),
+                )
+            })?
+            .map_err(|_| internal_err!("Replication has stopped"))?
+    };
+    futures::pin_mut!(upstream_checksum, readyset_checksum);
+
+    match future::select(upstream_checksum, readyset_checksum).await {
+        Either::Left((upstream_checksum, readyset_checksum)) => {
+            let upstream_checksum = upstream_checksum?;
+            Ok(readyset_checksum
+                .await?
+                .map(|readyset_checksum| (readyset_checksum, upstream_checksum)))
+        }
+        // Don't bother reading the rest of the upstream table if there's nothing to compare it to
+        Either::Right((readyset_checksum, upstream_checksum)) => match readyset_checksum? {
+            Some(readyset_checksum) => Ok(Some((readyset_checksum, upstream_checksum.await?))),
+            None => Ok(None),
+        },
+    }
+}
+
+/// Compare the checksum of `table` in ReadySet with its checksum in `upstream`, recording the
+/// result in `checks`
+async fn check_table(
+    noria: &mut ReadySetHandle,
+    upstream: &Upstream,
+    table: &Relation,
+    checksum_requests: &mpsc::Sender<ChecksumRequest>,
+    checks: &ConsistencyChecks,
+) -> ReadySetResult<()> {
+    let mutator = noria.table(table.clone()).await?;
+    let columns = table_column_types(&mutator, upstream.dialect());
+
+    debug!(%table, "Checking table for consistency");
+    let mut attempt = 1;
+    let (readyset_checksum, upstream_checksum) = loop {
+        match table_checksums(upstream, table, &columns, checksum_requests).await? {
+            Some(checksums) => break checksums,
+            None if attempt < MAX_CHECK_ATTEMPTS => {
+                debug!(%table, attempt, "Replication was past the offset the table was read at");
+                attempt += 1;
+            }
+            None => {
+                info!(
+                    %table,
+                    "Replication kept getting past the offset the table was read at before it \
+                     could be checked, checking again next time"
+                );
+                return Ok(());
+            }
+        }
+    };
+    let result = checks.record(table, readyset_checksum, upstream_checksum);
+
+    let table_name = table.display_unquoted().to_string();
+    if result.consecutive_mismatches > 0 {
+        counter!(
+            recorded::REPLICATOR_CONSISTENCY_CHECK_MISMATCHES,
+            1u64,
+            "table_name" => table_name.clone()
+        );
+    }
+    gauge!(
+        recorded::REPLICATOR_CONSISTENCY_CHECK_DIVERGED,
+        if result.diverged() { 1.0 } else { 0.0 },
+        "table_name" => table_name
+    );
+
+    if result.diverged() {
+        warn!(
+            %table,
+            readyset_rows = result.readyset.rows,
+            upstream_rows = result.upstream.rows,
+            checks = result.consecutive_mismatches,
+            "Table has diverged from the upstream database"
+        );
+    } else if result.consecutive_mismatches > 0 {
+        info!(
+            %table,
+            readyset_rows = result.readyset.rows,
+            upstream_rows = result.upstream.rows,
+            "Table checksum differs from the upstream database, checking again before reporting \
+             divergence"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum(rows: u64) -> TableChecksum {
+        TableChecksum { rows, checksum: 0 }
+    }
+
+    #[test]
+    fn record_counts_consecutive_mismatches() {
+        let checks = ConsistencyChecks::default();
+        let table = Relation {
+            schema: Some("public".into()),
+            name: "t".into(),
+        };
+
+        assert!(checks.record(&table, checksum(1), checksum(2)).diverged());
+        let result = checks.record(&table, checksum(1), checksum(2));
+        assert!(result.diverged());
+        assert_eq!(result.consecutive_mismatches, 2);
+        assert_eq!(checks.results()[&table], result);
+
+        let result = checks.record(&table, checksum(2), checksum(2));
+        assert!(!result.diverged());
+        assert_eq!(result.consecutive_mismatches, 0);
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 
 use nom_sql::Dialect;
+use readyset_data::DfType;
 use readyset_errors::ReadySetError;
 use readyset_sql_passes::anonymize::{Anonymize, Anonymizer};
 use readyset_telemetry_reporter::{TelemetryBuilder, TelemetryEvent, TelemetrySender};
@@ -180,6 +181,31 @@ pub fn error_is_slot_not_found(err: &ReadySetError, slot_name: &str) -> bool {
         .ends_with(&format!("replication slot \"{slot_name}\" does not exist"))
 }
 
+/// Returns the name of each column of `table`, along with the type that values read from the
+/// upstream database (with the given `dialect`) should be coerced to before being written to it
+pub(crate) fn table_column_types(
+    table: &readyset_client::Table,
+    dialect: readyset_data::Dialect,
+) -> Vec<(String, DfType)> {
+    table
+        .columns()
+        .iter()
+        .map(|name| {
+            let ty = table
+                .schema()
+                .and_then(|schema| {
+                    schema
+                        .fields
+                        .iter()
+                        .find(|field| field.column.name == *name)
+                })
+                .and_then(|field| DfType::from_sql_type(&field.sql_type, dialect, |_| None).ok())
+                .unwrap_or_default();
+            (name.to_string(), ty)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use nom_sql::SqlIdentifier;
@@ -0,0 +1,237 @@
+//! Detection of replicated schema changes that would drop or rebuild the caches depending on a
+//! table, for [`UpstreamConfig::destructive_ddl_policy`](database_utils::UpstreamConfig).
+
+use nom_sql::{AlterTableDefinition, AlterTableStatement, CreateTableBody, SqlType};
+
+/// Returns the rank of an integer type, and whether it's unsigned, or `None` if `ty` isn't an
+/// integer type
+fn integer_rank(ty: &SqlType) -> Option<(u8, bool)> {
+    match ty {
+        SqlType::TinyInt(_) => Some((1, false)),
+        SqlType::UnsignedTinyInt(_) => Some((1, true)),
+        SqlType::SmallInt(_) => Some((2, false)),
+        SqlType::UnsignedSmallInt(_) => Some((2, true)),
+        SqlType::Int(_) => Some((4, false)),
+        SqlType::UnsignedInt(_) => Some((4, true)),
+        SqlType::BigInt(_) => Some((8, false)),
+        SqlType::UnsignedBigInt(_) => Some((8, true)),
+        _ => None,
+    }
+}
+
+/// Returns the maximum length of a string type, with `None` for unbounded, or `Err` if `ty` isn't
+/// a string type
+fn string_capacity(ty: &SqlType) -> Result<Option<u32>, ()> {
+    match ty {
+        SqlType::Char(len) => Ok(Some(len.unwrap_or(1).into())),
+        SqlType::VarChar(len) => Ok(len.map(u32::from)),
+        SqlType::TinyText => Ok(Some(u8::MAX.into())),
+        SqlType::Text => Ok(Some(u16::MAX.into())),
+        SqlType::MediumText => Ok(Some((1 << 24) - 1)),
+        SqlType::LongText | SqlType::Citext => Ok(None),
+        _ => Err(()),
+    }
+}
+
+/// Returns the size rank of a blob type, or `None` if `ty` isn't a blob type
+fn blob_rank(ty: &SqlType) -> Option<u8> {
+    match ty {
+        SqlType::TinyBlob => Some(1),
+        SqlType::Blob => Some(2),
+        SqlType::MediumBlob => Some(3),
+        SqlType::LongBlob => Some(4),
+        _ => None,
+    }
+}
+
+/// Returns the precision and scale of a fixed-point type, with `None` for unbounded, or `Err` if
+/// `ty` isn't a fixed-point type
+fn decimal_precision(ty: &SqlType) -> Result<Option<(u16, u8)>, ()> {
+    match ty {
+        SqlType::Decimal(precision, scale) => Ok(Some(((*precision).into(), *scale))),
+        SqlType::Numeric(Some((precision, scale))) => Ok(Some((*precision, scale.unwrap_or(0)))),
+        SqlType::Numeric(None) => Ok(None),
+        _ => Err(()),
+    }
+}
+
+/// Returns true if every value of type `from` can be stored in a column of type `to` without
+/// losing information, so that changing a column from `from` to `to` doesn't narrow it
+pub(crate) fn is_widening(from: &SqlType, to: &SqlType) -> bool {
+    if from == to {
+        return true;
+    }
+
+    if let (Some((from_rank, from_unsigned)), Some((to_rank, to_unsigned))) =
+        (integer_rank(from), integer_rank(to))
+    {
+        return if from_unsigned == to_unsigned {
+            to_rank >= from_rank
+        } else {
+            from_unsigned && to_rank > from_rank
+        };
+    }
+
+    if let (Ok(from_len), Ok(to_len)) = (string_capacity(from), string_capacity(to)) {
+        return match (from_len, to_len) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(from_len), Some(to_len)) => to_len >= from_len,
+        };
+    }
+
+    if let (Some(from_rank), Some(to_rank)) = (blob_rank(from), blob_rank(to)) {
+        return to_rank >= from_rank;
+    }
+
+    if let (Ok(from_precision), Ok(to_precision)) = (decimal_precision(from), decimal_precision(to))
+    {
+        return match (from_precision, to_precision) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some((from_precision, from_scale)), Some((to_precision, to_scale))) => {
+                to_scale >= from_scale
+                    && to_precision.saturating_sub(to_scale.into())
+                        >= from_precision.saturating_sub(from_scale.into())
+            }
+        };
+    }
+
+    matches!(
+        (from, to),
+        (SqlType::Float, SqlType::Double | SqlType::Real) | (SqlType::Real, SqlType::Double)
+    )
+}
+
+/// If `alter` makes a destructive change to a table whose current schema is `schema`, returns a
+/// description of that change.
+///
+/// Destructive changes are dropping a column, changing the type of a column to a type that isn't
+/// a widening of its current type (see [`is_widening`]), and any change we couldn't parse.
+/// Changing a column we don't know the current type of is considered destructive.
+pub(crate) fn destructive_alter_table(
+    alter: &AlterTableStatement,
+    schema: Option<&CreateTableBody>,
+) -> Option<String> {
+    let definitions = match &alter.definitions {
+        Ok(definitions) => definitions,
+        Err(unparsed) => return Some(format!("unparseable ALTER TABLE definition: {unparsed}")),
+    };
+
+    definitions.iter().find_map(|definition| match definition {
+        AlterTableDefinition::DropColumn { name, .. } => Some(format!("drops column {name}")),
+        AlterTableDefinition::ChangeColumn { name, spec } => {
+            let current_type = schema.and_then(|schema| {
+                schema
+                    .fields
+                    .iter()
+                    .find(|field| field.column.name == *name)
+                    .map(|field| &field.sql_type)
+            });
+            match current_type {
+                Some(current_type) if is_widening(current_type, &spec.sql_type) => None,
+                Some(_) => Some(format!("narrows the type of column {name}")),
+                None => Some(format!("changes column {name}, whose type is unknown")),
+            }
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use nom_sql::{parse_alter_table, parse_create_table, Dialect};
+
+    use super::*;
+
+    #[test]
+    fn integer_widening() {
+        assert!(is_widening(&SqlType::Int(None), &SqlType::BigInt(None)));
+        assert!(is_widening(&SqlType::Int(Some(11)), &SqlType::Int(None)));
+        assert!(is_widening(
+            &SqlType::UnsignedInt(None),
+            &SqlType::BigInt(None)
+        ));
+        assert!(!is_widening(&SqlType::BigInt(None), &SqlType::Int(None)));
+        assert!(!is_widening(
+            &SqlType::UnsignedInt(None),
+            &SqlType::Int(None)
+        ));
+        assert!(!is_widening(
+            &SqlType::TinyInt(None),
+            &SqlType::UnsignedBigInt(None)
+        ));
+    }
+
+    #[test]
+    fn string_widening() {
+        assert!(is_widening(
+            &SqlType::VarChar(Some(10)),
+            &SqlType::VarChar(Some(20))
+        ));
+        assert!(is_widening(&SqlType::Char(Some(10)), &SqlType::Text));
+        assert!(is_widening(&SqlType::Text, &SqlType::VarChar(None)));
+        assert!(!is_widening(
+            &SqlType::VarChar(Some(20)),
+            &SqlType::VarChar(Some(10))
+        ));
+        assert!(!is_widening(&SqlType::LongText, &SqlType::Text));
+        assert!(!is_widening(&SqlType::Text, &SqlType::Int(None)));
+    }
+
+    #[test]
+    fn numeric_widening() {
+        assert!(is_widening(&SqlType::Float, &SqlType::Double));
+        assert!(!is_widening(&SqlType::Double, &SqlType::Float));
+        assert!(is_widening(
+            &SqlType::Decimal(10, 2),
+            &SqlType::Decimal(12, 4)
+        ));
+        assert!(is_widening(
+            &SqlType::Decimal(10, 2),
+            &SqlType::Numeric(None)
+        ));
+        assert!(!is_widening(
+            &SqlType::Decimal(10, 2),
+            &SqlType::Decimal(10, 4)
+        ));
+        assert!(!is_widening(&SqlType::Int(None), &SqlType::Float));
+    }
+
+    #[test]
+    fn destructive_alters() {
+        let schema = parse_create_table(
+            Dialect::MySQL,
+            "CREATE TABLE t (id INT, name VARCHAR(10), score BIGINT)",
+        )
+        .unwrap()
+        .body
+        .unwrap();
+        let alter = |sql: &str| parse_alter_table(Dialect::MySQL, sql).unwrap();
+
+        assert!(
+            destructive_alter_table(&alter("ALTER TABLE t DROP COLUMN name"), Some(&schema))
+                .is_some()
+        );
+        assert!(destructive_alter_table(
+            &alter("ALTER TABLE t MODIFY COLUMN score INT"),
+            Some(&schema)
+        )
+        .is_some());
+        assert!(destructive_alter_table(
+            &alter("ALTER TABLE t MODIFY COLUMN name VARCHAR(20)"),
+            Some(&schema)
+        )
+        .is_none());
+        assert!(destructive_alter_table(
+            &alter("ALTER TABLE t MODIFY COLUMN name VARCHAR(20)"),
+            None
+        )
+        .is_some());
+        assert!(destructive_alter_table(
+            &alter("ALTER TABLE t ADD COLUMN extra INT"),
+            Some(&schema)
+        )
+        .is_none());
+    }
+}
@@ -6,17 +6,32 @@
     iter_intersperse,
     let_chains
 )]
+pub(crate) mod consistency_check;
 pub mod db_util;
+pub(crate) mod destructive_ddl;
 pub(crate) mod mysql_connector;
 pub(crate) mod noria_adapter;
 pub(crate) mod postgres_connector;
+pub(crate) mod privileges;
+pub(crate) mod replication_capture;
+pub(crate) mod replication_health;
+pub(crate) mod resnapshot_requests;
+pub(crate) mod schema_export;
+pub(crate) mod snapshot_checkpoint;
+pub(crate) mod snapshot_throttle;
 pub(crate) mod table_filter;
+pub(crate) mod table_retention;
+pub(crate) mod time_zone;
 
 use std::time::Duration;
 
-pub use mysql_connector::BinlogPosition;
-pub use noria_adapter::{cleanup, NoriaAdapter};
+pub use consistency_check::ConsistencyChecks;
+pub use mysql_connector::{BinlogPosition, GtidSet};
+pub use noria_adapter::{cleanup, drop_replication_slots, NoriaAdapter};
 pub use postgres_connector::PostgresPosition;
+pub use replication_capture::ReplicationCapture;
+pub use replication_health::ReplicationHealth;
+pub use resnapshot_requests::ResnapshotRequests;
 
 /// Provide a simplistic human-readable estimate for how much time remains to complete an operation
 pub(crate) fn estimate_remaining_time(elapsed: Duration, progress: f64, total: f64) -> String {
@@ -0,0 +1,338 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use readyset_errors::{ReadySetError, ReadySetResult};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
+
+/// The magic number every binlog file starts with
+const BINLOG_MAGIC: [u8; 4] = [0xfe, b'b', b'i', b'n'];
+
+/// The length of the header of every (v4) binlog event
+const EVENT_HEADER_LEN: usize = 19;
+
+/// The offset of the size of the event (including its header) in the header of a binlog event
+const EVENT_SIZE_OFFSET: usize = 9;
+
+/// The offset of the server version in a format description event, which follows the binlog
+/// version after the header, and its length
+const SERVER_VERSION_OFFSET: usize = EVENT_HEADER_LEN + 2;
+const SERVER_VERSION_LEN: usize = 50;
+
+fn binlog_file_error(path: &Path, error: impl std::fmt::Display) -> ReadySetError {
+    ReadySetError::ReplicationFailed(format!(
+        "Failed to read binlog file {}: {error}",
+        path.display()
+    ))
+}
+
+/// Splits a binlog file name into its basename and its sequence number, such as `binlog` and `3`
+/// for `binlog.000003`
+fn split_file_name(file_name: &str) -> Option<(&str, u64)> {
+    let (basename, suffix) = file_name.rsplit_once('.')?;
+    Some((basename, suffix.parse().ok()?))
+}
+
+/// Fill `buf` from `file`, returning `false` if the end of the file was reached first (such as
+/// when MySQL is still writing the event we're reading)
+async fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read(&mut buf[read..]).await? {
+            0 => return Ok(false),
+            n => read += n,
+        }
+    }
+    Ok(true)
+}
+
+/// Reads binlog events from binlog files archived from a MySQL server, such as for replaying them
+/// without a connection to the server.
+///
+/// Events are read from the binlog file we're opened at, and then from each binlog file in the
+/// same directory that has the same basename and a higher sequence number, in order, as MySQL
+/// would send them to a replica.
+pub(crate) struct BinlogFiles {
+    dir: PathBuf,
+    /// The name of the binlog file we're reading events from
+    file_name: String,
+    file: File,
+    /// The offset in [`Self::file`] of the next event to read
+    offset: u64,
+}
+
+impl BinlogFiles {
+    /// Open the binlog file named `file_name` in `dir`, to read events starting at `position`.
+    ///
+    /// Also returns the raw format description event at the start of the file, which describes
+    /// how the events in it are encoded, and has to be read before any of them (even if
+    /// `position` is past it).
+    pub(crate) async fn open(
+        dir: PathBuf,
+        file_name: String,
+        position: u32,
+    ) -> ReadySetResult<(Self, Vec<u8>)> {
+        let mut files = Self::open_file(dir, file_name).await?;
+        let path = files.path();
+        let format_description = files
+            .next_event_in_file()
+            .await
+            .map_err(|e| binlog_file_error(&path, e))?
+            .ok_or_else(|| binlog_file_error(&path, "missing format description event"))?;
+        files.offset = u64::from(position).max(BINLOG_MAGIC.len() as u64);
+        Ok((files, format_description))
+    }
+
+    async fn open_file(dir: PathBuf, file_name: String) -> ReadySetResult<Self> {
+        let path = dir.join(&file_name);
+        let mut file = File::open(&path)
+            .await
+            .map_err(|e| binlog_file_error(&path, e))?;
+        let mut magic = [0; BINLOG_MAGIC.len()];
+        if !read_full(&mut file, &mut magic)
+            .await
+            .map_err(|e| binlog_file_error(&path, e))?
+            || magic != BINLOG_MAGIC
+        {
+            return Err(binlog_file_error(&path, "not a binlog file"));
+        }
+
+        Ok(BinlogFiles {
+            dir,
+            file_name,
+            file,
+            offset: BINLOG_MAGIC.len() as u64,
+        })
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(&self.file_name)
+    }
+
+    /// The directory the binlog files are in
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The name of the binlog file the last event we read was in
+    pub(crate) fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// Read the next event in the current file, or return `None` if we've read all the events
+    /// written to it so far
+    async fn next_event_in_file(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.file.seek(SeekFrom::Start(self.offset)).await?;
+        let mut event = vec![0; EVENT_HEADER_LEN];
+        if !read_full(&mut self.file, &mut event).await? {
+            return Ok(None);
+        }
+
+        let mut size = [0; 4];
+        size.copy_from_slice(&event[EVENT_SIZE_OFFSET..EVENT_SIZE_OFFSET + 4]);
+        let size = u32::from_le_bytes(size) as usize;
+        if size < EVENT_HEADER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid event size {size} at offset {}", self.offset),
+            ));
+        }
+        event.resize(size, 0);
+        if !read_full(&mut self.file, &mut event[EVENT_HEADER_LEN..]).await? {
+            return Ok(None);
+        }
+
+        self.offset += size as u64;
+        Ok(Some(event))
+    }
+
+    /// Returns the name of the binlog file in [`Self::dir`] that comes after the current one, if
+    /// there is one yet
+    async fn next_file_name(&self) -> io::Result<Option<String>> {
+        let Some((basename, current)) = split_file_name(&self.file_name) else {
+            return Ok(None);
+        };
+
+        let mut next: Option<(u64, String)> = None;
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(file_name) = entry.file_name().into_string() else {
+                continue;
+            };
+            match split_file_name(&file_name) {
+                Some((b, n))
+                    if b == basename
+                        && n > current
+                        && next.as_ref().map_or(true, |(next, _)| n < *next) =>
+                {
+                    next = Some((n, file_name))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(next.map(|(_, file_name)| file_name))
+    }
+
+    /// Read the raw bytes of the next binlog event, moving on to the next binlog file once we've
+    /// read every event in the current one. Returns `None` if we've read every event written so
+    /// far.
+    pub(crate) async fn next_event(&mut self) -> ReadySetResult<Option<Vec<u8>>> {
+        let path = self.path();
+        if let Some(event) = self
+            .next_event_in_file()
+            .await
+            .map_err(|e| binlog_file_error(&path, e))?
+        {
+            return Ok(Some(event));
+        }
+
+        // MySQL only starts a new binlog file once it's done writing to the previous one, so if
+        // there is one we've read everything there is to read in the current file
+        match self
+            .next_file_name()
+            .await
+            .map_err(|e| binlog_file_error(&self.dir, e))?
+        {
+            Some(file_name) => {
+                *self = Self::open_file(self.dir.clone(), file_name).await?;
+                let path = self.path();
+                self.next_event_in_file()
+                    .await
+                    .map_err(|e| binlog_file_error(&path, e))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Returns true if the raw format description event `format_description` was written by a MariaDB
+/// server
+pub(crate) fn written_by_mariadb(format_description: &[u8]) -> bool {
+    format_description
+        .get(SERVER_VERSION_OFFSET..SERVER_VERSION_OFFSET + SERVER_VERSION_LEN)
+        .map_or(false, |version| {
+            String::from_utf8_lossy(version).contains("MariaDB")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A binlog event of the given type, with `data` after the header
+    fn event(event_type: u8, log_pos: u32, data: &[u8]) -> Vec<u8> {
+        let mut event = vec![0; 4];
+        event.push(event_type);
+        event.extend(1u32.to_le_bytes());
+        event.extend(((EVENT_HEADER_LEN + data.len()) as u32).to_le_bytes());
+        event.extend(log_pos.to_le_bytes());
+        event.extend([0, 0]);
+        event.extend(data);
+        event
+    }
+
+    fn format_description(server_version: &str) -> Vec<u8> {
+        let mut data = 4u16.to_le_bytes().to_vec();
+        let mut version = server_version.as_bytes().to_vec();
+        version.resize(SERVER_VERSION_LEN, 0);
+        data.extend(version);
+        event(15, 0, &data)
+    }
+
+    async fn write_binlog(dir: &Path, file_name: &str, events: &[Vec<u8>]) {
+        let mut contents = BINLOG_MAGIC.to_vec();
+        for event in events {
+            contents.extend(event);
+        }
+        tokio::fs::write(dir.join(file_name), contents)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reads_events_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let fde = format_description("8.0.32");
+        let first = event(2, 200, b"first");
+        let second = event(2, 300, b"second");
+        write_binlog(dir.path(), "binlog.000001", &[fde.clone(), first.clone()]).await;
+        write_binlog(dir.path(), "binlog.000002", &[fde.clone(), second.clone()]).await;
+        write_binlog(dir.path(), "other.000003", &[fde.clone()]).await;
+        tokio::fs::write(dir.path().join("binlog.index"), "")
+            .await
+            .unwrap();
+
+        let (mut files, format_description) =
+            BinlogFiles::open(dir.path().to_owned(), "binlog.000001".into(), 4)
+                .await
+                .unwrap();
+        assert_eq!(format_description, fde);
+        assert!(!written_by_mariadb(&format_description));
+
+        assert_eq!(files.next_event().await.unwrap(), Some(fde.clone()));
+        assert_eq!(files.next_event().await.unwrap(), Some(first));
+        assert_eq!(files.next_event().await.unwrap(), Some(fde));
+        assert_eq!(files.file_name(), "binlog.000002");
+        assert_eq!(files.next_event().await.unwrap(), Some(second));
+        assert_eq!(files.next_event().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn starts_at_position() {
+        let dir = tempfile::tempdir().unwrap();
+        let fde = format_description("10.6.12-MariaDB");
+        let first = event(2, 200, b"first");
+        let second = event(2, 300, b"second");
+        write_binlog(
+            dir.path(),
+            "binlog.000001",
+            &[fde.clone(), first.clone(), second.clone()],
+        )
+        .await;
+
+        let position = (BINLOG_MAGIC.len() + fde.len() + first.len()) as u32;
+        let (mut files, format_description) =
+            BinlogFiles::open(dir.path().to_owned(), "binlog.000001".into(), position)
+                .await
+                .unwrap();
+        assert!(written_by_mariadb(&format_description));
+        assert_eq!(files.next_event().await.unwrap(), Some(second));
+        assert_eq!(files.next_event().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn incomplete_event_is_read_once_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let fde = format_description("8.0.32");
+        let first = event(2, 200, b"first");
+        let mut contents = BINLOG_MAGIC.to_vec();
+        contents.extend(&fde);
+        contents.extend(&first[..EVENT_HEADER_LEN + 2]);
+        let path = dir.path().join("binlog.000001");
+        tokio::fs::write(&path, &contents).await.unwrap();
+
+        let (mut files, _) = BinlogFiles::open(dir.path().to_owned(), "binlog.000001".into(), 4)
+            .await
+            .unwrap();
+        assert_eq!(files.next_event().await.unwrap(), Some(fde));
+        assert_eq!(files.next_event().await.unwrap(), None);
+
+        contents.extend(&first[EVENT_HEADER_LEN + 2..]);
+        tokio::fs::write(&path, &contents).await.unwrap();
+        assert_eq!(files.next_event().await.unwrap(), Some(first));
+    }
+
+    #[tokio::test]
+    async fn rejects_non_binlog_files() {
+        let dir = tempfile::tempdir().unwrap();
+        tokio::fs::write(dir.path().join("binlog.000001"), "not a binlog")
+            .await
+            .unwrap();
+        assert!(
+            BinlogFiles::open(dir.path().to_owned(), "binlog.000001".into(), 4)
+                .await
+                .is_err()
+        );
+    }
+}
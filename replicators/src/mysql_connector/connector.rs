@@ -1,46 +1,169 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
+use std::fmt::Display;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
-use binlog::consts::{BinlogChecksumAlg, EventType};
-use metrics::counter;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use binlog::consts::{BinlogChecksumAlg, EventType, UnknownEventType};
+use database_utils::{ReplicationRewindPolicy, DEFAULT_REPLICATION_SERVER_ID};
+use metrics::{counter, gauge, histogram};
 use mysql::binlog::events::StatusVarVal;
 use mysql::binlog::jsonb::{self, JsonbToJsonError};
 use mysql::prelude::Queryable;
 use mysql_async as mysql;
 use mysql_common::binlog;
+use mysql_common::binlog::events::EventData;
 use mysql_common::binlog::row::BinlogRow;
 use mysql_common::binlog::value::BinlogValue;
-use nom_sql::Relation;
+use nom_sql::{Relation, SqlQuery};
+use rayon::prelude::*;
 use readyset_client::metrics::recorded;
-use readyset_client::recipe::ChangeList;
+use readyset_client::recipe::changelist::{Change, ChangeList};
 use readyset_client::replication::ReplicationOffset;
+use readyset_client::TableOperation;
 use readyset_data::{DfValue, Dialect};
-use readyset_errors::{ReadySetError, ReadySetResult};
-use tracing::{info, warn};
-
-use super::BinlogPosition;
+use readyset_errors::{internal_err, ReadySetError, ReadySetResult};
+use readyset_tracing::statement_logging::Component;
+use tracing::{error, info, warn};
+
+use super::binlog_files::{written_by_mariadb, BinlogFiles};
+use super::geometry::geometry_value;
+use super::minimal_row_image::{MinimalRowImages, PartialRow};
+use super::snapshot::binlog_position;
+use super::unparsed_ddl::unparsed_ddl_table;
+use super::{is_connection_error, json_diff, mysql_error, BinlogPosition};
 use crate::noria_adapter::{Connector, ReplicationAction};
+use crate::table_filter::TableFilter;
+use crate::time_zone::UpstreamTimeZone;
 
 const CHECKSUM_QUERY: &str = "SET @master_binlog_checksum='CRC32'";
-const DEFAULT_SERVER_ID: u32 = u32::MAX - 55;
+
+/// Asks the primary to send us a HEARTBEAT_EVENT every second (the period is in nanoseconds)
+/// whenever it has no new events for us, so that we can tell we're caught up with it
+const HEARTBEAT_PERIOD_QUERY: &str = "SET @master_heartbeat_period=1000000000";
+
+/// The maximum number of table operations to buffer for a single transaction. Transactions larger
+/// than this are applied in several batches, so that we don't have to hold the whole transaction
+/// in memory.
+const MAX_QUEUED_TRANSACTION_ACTIONS: usize = 100_000;
+
+/// The number of times in a row we request the binlog again after reading an invalid event from
+/// it, without making any progress in between, before giving up
+const MAX_CONSECUTIVE_INVALID_EVENTS: u32 = 5;
+
+/// Tells a MariaDB primary that we understand MariaDB's GTID events
+/// (`MARIA_SLAVE_CAPABILITY_GTID`), so that it doesn't rewrite them into `BEGIN` query events for
+/// us
+const MARIADB_CAPABILITY_QUERY: &str = "SET @mariadb_slave_capability=4";
+
+/// Binlog event types specific to MariaDB, which `mysql_common` doesn't know about. See
+/// <https://mariadb.com/kb/en/2-binlog-event-header/>
+const MARIADB_ANNOTATE_ROWS_EVENT: u8 = 160;
+const MARIADB_BINLOG_CHECKPOINT_EVENT: u8 = 161;
+const MARIADB_GTID_EVENT: u8 = 162;
+const MARIADB_GTID_LIST_EVENT: u8 = 163;
+const MARIADB_START_ENCRYPTION_EVENT: u8 = 164;
+
+/// The type of the event MySQL 8.0.20+ wraps the (possibly compressed) events of each transaction
+/// in if `binlog_transaction_compression` is enabled
+const TRANSACTION_PAYLOAD_EVENT: u8 = 40;
+
+/// Set in the flags of a MariaDB GTID event if the event group is not wrapped in a transaction,
+/// such as for DDL statements
+const MARIADB_FL_STANDALONE: u8 = 1;
+
+/// The flavor of the upstream database server, which determines how we register as a replica and
+/// which binlog events we can expect to receive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ServerFlavor {
+    MySql,
+    MariaDb,
+}
+
+impl ServerFlavor {
+    /// Detect the flavor of the server `connection` is connected to
+    pub(super) async fn detect(connection: &mut mysql::Conn) -> mysql::Result<Self> {
+        let version: Option<String> = connection.query_first("SELECT VERSION()").await?;
+        if version.map_or(false, |v| v.contains("MariaDB")) {
+            Ok(ServerFlavor::MariaDb)
+        } else {
+            Ok(ServerFlavor::MySql)
+        }
+    }
+
+    /// The statements that prevent DDL changes on the whole server while we snapshot, without
+    /// blocking writes, followed by the statement that releases them again.
+    ///
+    /// MariaDB doesn't support `LOCK INSTANCE FOR BACKUP`, but the `BLOCK_DDL` stage of its
+    /// `BACKUP STAGE` commands has the same effect.
+    pub(super) fn ddl_lock_statements(self) -> (&'static [&'static str], &'static str) {
+        match self {
+            ServerFlavor::MySql => (&["LOCK INSTANCE FOR BACKUP"], "UNLOCK INSTANCE"),
+            ServerFlavor::MariaDb => (
+                &["BACKUP STAGE START", "BACKUP STAGE BLOCK_DDL"],
+                "BACKUP STAGE END",
+            ),
+        }
+    }
+
+    /// The privilege needed to run [`Self::ddl_lock_statements`]
+    pub(super) fn ddl_lock_privilege(self) -> &'static str {
+        match self {
+            ServerFlavor::MySql => "BACKUP_ADMIN",
+            ServerFlavor::MariaDb => "RELOAD",
+        }
+    }
+}
+
+/// Where a [`MySqlBinlogConnector`] reads binlog events from
+enum BinlogSource {
+    /// The binlog stream of a MySQL server we've registered with as a replica
+    Server {
+        /// The options used to connect to the MySQL server, kept around so that we can reconnect
+        opts: mysql::Opts,
+        /// This is the underlying (regular) MySQL connection
+        connection: mysql::Conn,
+    },
+    /// Binlog files archived from a MySQL server, which we replay instead of reading the binlog
+    /// from the server
+    Files(BinlogFiles),
+}
+
+/// How long to wait for more events to be written to the binlog files we're replaying once we've
+/// read all of them, before checking again
+const BINLOG_FILES_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 /// A connector that connects to a MySQL server and starts reading binlogs from a given position.
 ///
 /// The server must be configured with `binlog_format` set to `row` and `binlog_row_image` set to
-/// `full`.
+/// `full`, or to `minimal` if the connector is configured to resolve minimal row images (see
+/// [`MinimalRowImages`]).
 ///
-/// The connector user may optionally have the following permissions:
-/// * `BACKUP_ADMIN` - (optional) to perform LOCK INSTANCE FOR BACKUP, not available on RDS
+/// The connector user must have the following permissions:
+/// * `BACKUP_ADMIN` - (optional) to perform LOCK INSTANCE FOR BACKUP, not available on RDS. On
+///   MariaDB, `RELOAD` is needed instead, to perform BACKUP STAGE
 /// * `SELECT` - to be able to perform a snapshot
 /// * `LOCK TABLES` - this permission is required for table level locks
-/// * `SHOW DATABASES` - to see databases for a snapshot
+/// * `SHOW DATABASES` - (optional) to see databases for a snapshot
 /// * `REPLICATION SLAVE` - to be able to connect and read the binlog
 /// * `REPLICATION CLIENT` - to use SHOW MASTER STATUS, SHOW SLAVE STATUS, and SHOW BINARY LOGS;
 ///
+/// These are checked when replication starts, by [`check_privileges`](super::check_privileges).
+///
 /// The connector must also be assigned a unique `server_id` value
+///
+/// MariaDB primaries are supported as well, though only by binlog file and position: MariaDB
+/// GTIDs are not compatible with the MySQL GTIDs we track in [`BinlogPosition::gtid_set`].
 pub(crate) struct MySqlBinlogConnector {
-    /// This is the underlying (regular) MySQL connection
-    connection: mysql::Conn,
+    /// Where we read binlog events from
+    source: BinlogSource,
+    /// Whether we're replicating from MySQL or MariaDB, detected when we first connect
+    flavor: ServerFlavor,
     /// Reader is a decoder for binlog events
     reader: binlog::EventStreamReader,
     /// The binlog "slave" must be assigned a unique `server_id` in the replica topology
@@ -51,8 +174,50 @@ pub(crate) struct MySqlBinlogConnector {
     /// The GTID of the current transaction. Table modification events will have
     /// the current GTID attached if enabled in mysql.
     current_gtid: Option<u64>,
-    /// Whether to log statements received by the connector
-    enable_statement_logging: bool,
+    /// The source UUID and transaction number of the current transaction, which is added to the
+    /// executed GTID set in [`Self::next_position`] once the transaction commits
+    pending_gtid: Option<([u8; 16], u64)>,
+    /// Whether to request the binlog using GTID auto-positioning rather than by file and position,
+    /// if we know the set of GTIDs executed as of [`Self::next_position`]
+    gtid_auto_position: bool,
+    /// What to do if the upstream's binlog position is behind [`Self::next_position`] when we
+    /// connect
+    rewind_policy: ReplicationRewindPolicy,
+    /// The time zone the upstream returns `TIMESTAMP` values in, which we convert the values in
+    /// rows events to
+    time_zone: UpstreamTimeZone,
+    /// The remaining events of the transaction payload event we're in the middle of processing, if
+    /// any, to be processed before we read the next event from the binlog stream
+    payload_events: VecDeque<binlog::events::Event>,
+    /// The position of the end of the transaction payload event whose events are in
+    /// [`Self::payload_events`]
+    payload_end_position: u32,
+    /// The table operations buffered for the current transaction, grouped by table, if we've seen
+    /// the `BEGIN` of the transaction. These are returned together once the transaction commits.
+    transaction: Option<Vec<(Relation, Vec<TableOperation>)>>,
+    /// The text of the statement that caused the rows events that follow, from the last
+    /// `ROWS_QUERY_EVENT` in the current transaction. These are only logged if the upstream has
+    /// `binlog_rows_query_log_events` enabled.
+    rows_query: Option<String>,
+    /// The text of each statement that caused the table operations buffered in
+    /// [`Self::transaction`], in order
+    transaction_queries: Vec<String>,
+    /// How long to keep trying to reconnect if the connection to the binlog stream drops, before
+    /// returning an error
+    reconnect_timeout: Duration,
+    /// The number of invalid events we've read since we last returned an action, see
+    /// [`MAX_CONSECUTIVE_INVALID_EVENTS`]
+    consecutive_invalid_events: u32,
+    /// The tables we replicate, as configured by `--replication-tables` and
+    /// `--replication-tables-ignore`. Events for any other table are skipped before we decode
+    /// their rows.
+    table_filter: TableFilter,
+    /// If set, rows events are logged with `binlog_row_image=MINIMAL`, and this resolves the
+    /// columns missing from their row images
+    minimal_row_images: Option<MinimalRowImages>,
+    /// How far behind the primary we were as of the last event we read that tells us, see
+    /// [`Self::record_lag`]
+    lag: Option<Duration>,
 }
 
 impl PartialOrd for BinlogPosition {
@@ -86,35 +251,11 @@ impl PartialOrd for BinlogPosition {
 impl TryFrom<&BinlogPosition> for ReplicationOffset {
     type Error = ReadySetError;
 
-    /// `ReplicationOffset` is a filename and a u128 offset
-    /// We use the binlog basefile name as the filename, and we use the binlog suffix len for
-    /// the top 5 bits, which can be as big as 31 digits in theory, but we only allow up to 17
-    /// decimal digits, which is more than enough for the binlog spec. This is required to be
-    /// able to properly format the integer back to string, including any leading zeroes.
-    /// The following 59 bits are used for the numerical value of the suffix, finally the last
-    /// 64 bits of the offset are the actual binlog offset.
+    /// See [`ReplicationOffset::from_binlog_position`] for how binlog positions are encoded
     fn try_from(value: &BinlogPosition) -> Result<Self, Self::Error> {
-        let (basename, suffix) = value.binlog_file.rsplit_once('.').ok_or_else(|| {
-            ReadySetError::ReplicationFailed(format!("Invalid binlog name {}", value.binlog_file))
-        })?;
-
-        let suffix_len = suffix.len() as u128;
-
-        if suffix_len > 17 {
-            // 17 digit decimal number is the most we can fit into 59 bits
-            return Err(ReadySetError::ReplicationFailed(format!(
-                "Invalid binlog suffix {}",
-                value.binlog_file
-            )));
-        }
-
-        let suffix = suffix.parse::<u128>().map_err(|_| {
-            ReadySetError::ReplicationFailed(format!("Invalid binlog suffix {}", value.binlog_file))
-        })?;
-
         Ok(ReplicationOffset {
-            offset: (suffix_len << 123) + (suffix << 64) + (value.position as u128),
-            replication_log_name: basename.to_string(),
+            gtid_set: value.gtid_set.as_ref().map(|gtid_set| gtid_set.to_string()),
+            ..ReplicationOffset::from_binlog_position(&value.binlog_file, value.position as u64)?
         })
     }
 }
@@ -140,6 +281,12 @@ impl From<&ReplicationOffset> for BinlogPosition {
         BinlogPosition {
             binlog_file: format!("{0}.{1:02$}", val.replication_log_name, suffix, suffix_len),
             position,
+            // We only ever persist GTID sets that we formatted ourselves, so if this fails to parse
+            // we just fall back to positioning by file name and position
+            gtid_set: val
+                .gtid_set
+                .as_ref()
+                .and_then(|gtid_set| gtid_set.parse().ok()),
         }
     }
 }
@@ -154,33 +301,142 @@ impl MySqlBinlogConnector {
     /// The binlog replica must be assigned a unique `server_id` in the replica topology
     /// if one is not assigned we will use (u32::MAX - 55)
     fn server_id(&self) -> u32 {
-        self.server_id.unwrap_or(DEFAULT_SERVER_ID)
+        self.server_id.unwrap_or(DEFAULT_REPLICATION_SERVER_ID)
     }
 
     /// In order to request a binlog, we must first register as a replica, and let the primary
     /// know what type of checksum we support (NONE and CRC32 are the options), NONE seems to work
     /// but others use CRC32 🤷‍♂️
     async fn register_as_replica(&mut self) -> mysql::Result<()> {
-        self.connection.query_drop(CHECKSUM_QUERY).await?;
+        let server_id = self.server_id();
+        let BinlogSource::Server { connection, .. } = &mut self.source else {
+            return Ok(());
+        };
+        connection.query_drop(CHECKSUM_QUERY).await?;
+        connection.query_drop(HEARTBEAT_PERIOD_QUERY).await?;
+        if self.flavor == ServerFlavor::MariaDb {
+            connection.query_drop(MARIADB_CAPABILITY_QUERY).await?;
+        }
 
-        let cmd = mysql_common::packets::ComRegisterSlave::new(self.server_id());
-        self.connection.write_command(&cmd).await?;
+        let cmd = mysql_common::packets::ComRegisterSlave::new(server_id);
+        connection.write_command(&cmd).await?;
         // Server will respond with OK.
-        self.connection.read_packet().await?;
+        connection.read_packet().await?;
         Ok(())
     }
 
     /// After we have registered as a replica, we can request the binlog
     async fn request_binlog(&mut self) -> mysql::Result<()> {
-        let cmd = mysql_common::packets::ComBinlogDump::new(self.server_id())
-            .with_pos(self.next_position.position)
-            .with_filename(self.next_position.binlog_file.as_bytes());
+        let server_id = self.server_id();
+        let BinlogSource::Server { connection, .. } = &mut self.source else {
+            return Ok(());
+        };
+        match &self.next_position.gtid_set {
+            Some(gtid_set) if self.gtid_auto_position && self.flavor == ServerFlavor::MySql => {
+                // With GTID auto-positioning the primary sends every transaction that isn't in the
+                // given set, starting with a (fake) ROTATE_EVENT telling us which binlog file that
+                // is in.
+                info!(%gtid_set, "Requesting binlog using GTID auto-positioning");
+                let cmd = mysql_common::packets::ComBinlogDumpGtid::new(server_id)
+                    .with_flags(mysql_common::packets::BinlogDumpFlags::BINLOG_THROUGH_GTID)
+                    .with_sids(gtid_set.to_sids());
+                connection.write_command(&cmd).await?;
+            }
+            _ => {
+                let cmd = mysql_common::packets::ComBinlogDump::new(server_id)
+                    .with_pos(self.next_position.position)
+                    .with_filename(self.next_position.binlog_file.as_bytes());
+                connection.write_command(&cmd).await?;
+            }
+        }
 
-        self.connection.write_command(&cmd).await?;
-        self.connection.read_packet().await?;
+        connection.read_packet().await?;
         Ok(())
     }
 
+    /// Returns the [`ReplicationAction`] to apply the given `actions` against `table`, or buffers
+    /// them and returns `None` if we're in a transaction (unless the transaction has grown too
+    /// large to keep buffering)
+    fn table_action(
+        &mut self,
+        table: Relation,
+        actions: Vec<TableOperation>,
+    ) -> Option<ReplicationAction> {
+        let transaction = match &mut self.transaction {
+            Some(transaction) => transaction,
+            None => {
+                return Some(ReplicationAction::TableAction {
+                    table,
+                    actions,
+                    txid: self.current_gtid,
+                    query: self.rows_query.clone(),
+                })
+            }
+        };
+
+        match transaction.iter_mut().find(|(t, _)| *t == table) {
+            Some((_, buffered)) => buffered.extend(actions),
+            None => transaction.push((table, actions)),
+        }
+        if let Some(query) = &self.rows_query {
+            if self.transaction_queries.last() != Some(query) {
+                self.transaction_queries.push(query.clone());
+            }
+        }
+
+        let num_actions = transaction
+            .iter()
+            .map(|(_, actions)| actions.len())
+            .sum::<usize>();
+        if num_actions >= MAX_QUEUED_TRANSACTION_ACTIONS {
+            warn!(
+                num_actions,
+                "Transaction too large to buffer, applying it in multiple batches"
+            );
+            self.flush_transaction()
+        } else {
+            None
+        }
+    }
+
+    /// Returns a [`ReplicationAction::Transaction`] for the table operations buffered for the
+    /// current transaction, if there are any
+    fn flush_transaction(&mut self) -> Option<ReplicationAction> {
+        let tables = std::mem::take(self.transaction.as_mut()?);
+        let queries = std::mem::take(&mut self.transaction_queries);
+        (!tables.is_empty()).then_some(ReplicationAction::Transaction {
+            tables,
+            txid: self.current_gtid,
+            queries,
+        })
+    }
+
+    /// Called when the current transaction ends, to return the remainder of its buffered table
+    /// operations
+    fn end_transaction(&mut self) -> Option<ReplicationAction> {
+        let action = self.flush_transaction();
+        self.transaction = None;
+        self.rows_query = None;
+        self.transaction_queries.clear();
+        action
+    }
+
+    /// Called when the current transaction commits, to add its GTID to the set of executed GTIDs
+    fn commit_pending_gtid(&mut self) {
+        if let (Some((sid, gno)), Some(gtid_set)) =
+            (self.pending_gtid.take(), &mut self.next_position.gtid_set)
+        {
+            gtid_set.add(sid, gno);
+        }
+    }
+
+    /// Returns true if we replicate the table described by `tme`. Rows events for any other table
+    /// are skipped without decoding their rows.
+    fn should_replicate(&self, tme: &binlog::events::TableMapEvent<'static>) -> bool {
+        self.table_filter
+            .should_be_processed::<str, str>(&tme.database_name(), &tme.table_name())
+    }
+
     /// Compute the checksum of the event and compare to the supplied checksum
     fn validate_event_checksum(event: &binlog::events::Event) -> bool {
         if let Ok(Some(BinlogChecksumAlg::BINLOG_CHECKSUM_ALG_CRC32)) =
@@ -197,36 +453,654 @@ impl MySqlBinlogConnector {
     }
 
     /// Connect to a given MySQL database and subscribe to the binlog
+    ///
+    /// `table_offsets` holds the replication offset of each table in ReadySet, which is only used
+    /// with `minimal_row_image`.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn connect<O: Into<mysql::Opts>>(
         mysql_opts: O,
         next_position: BinlogPosition,
         server_id: Option<u32>,
-        enable_statement_logging: bool,
+        gtid_auto_position: bool,
+        rewind_policy: ReplicationRewindPolicy,
+        reconnect_timeout: Duration,
+        table_filter: TableFilter,
+        minimal_row_image: bool,
+        table_offsets: HashMap<Relation, ReplicationOffset>,
     ) -> ReadySetResult<Self> {
+        let mysql_opts = mysql_opts.into();
+        let mut connection = mysql::Conn::new(mysql_opts.clone())
+            .await
+            .map_err(mysql_error)?;
+        let upstream_position = binlog_position(&mut connection)
+            .await
+            .map_err(mysql_error)?;
+        let flavor = ServerFlavor::detect(&mut connection)
+            .await
+            .map_err(mysql_error)?;
+        let time_zone = UpstreamTimeZone::fetch_mysql(&mut connection)
+            .await
+            .map_err(mysql_error)?;
+        if flavor == ServerFlavor::MariaDb {
+            if gtid_auto_position {
+                warn!("GTID auto-positioning is not supported for MariaDB, using binlog positions");
+            }
+            info!("Replicating from MariaDB");
+        }
+        let minimal_row_images = if minimal_row_image {
+            Some(MinimalRowImages::connect(mysql_opts.clone(), table_offsets).await?)
+        } else {
+            None
+        };
+
         let mut connector = MySqlBinlogConnector {
-            connection: mysql::Conn::new(mysql_opts).await?,
+            source: BinlogSource::Server {
+                opts: mysql_opts,
+                connection,
+            },
+            flavor,
             reader: binlog::EventStreamReader::new(binlog::consts::BinlogVersion::Version4),
             server_id,
             next_position,
             current_gtid: None,
-            enable_statement_logging,
+            pending_gtid: None,
+            gtid_auto_position,
+            rewind_policy,
+            time_zone,
+            payload_events: VecDeque::new(),
+            payload_end_position: 0,
+            transaction: None,
+            rows_query: None,
+            transaction_queries: Vec::new(),
+            reconnect_timeout,
+            consecutive_invalid_events: 0,
+            table_filter,
+            minimal_row_images,
+            lag: None,
         };
 
-        connector.register_as_replica().await?;
-        connector.request_binlog().await?;
+        connector.register_as_replica().await.map_err(mysql_error)?;
+        connector.check_for_rewind(&upstream_position)?;
+        connector
+            .request_binlog()
+            .await
+            .map_err(|e| connector.binlog_error(e))?;
 
         Ok(connector)
     }
 
-    /// Get the next raw binlog event
-    async fn next_event(&mut self) -> mysql::Result<binlog::events::Event> {
-        let packet = self.connection.read_packet().await?;
-        // TODO: byte 0 of packet should be zero, unless EOF is reached, however we should never get
-        // one without the NON_BLOCKING SQL flag set
-        assert_eq!(packet.first(), Some(&0));
-        let event = self.reader.read(&packet[1..])?;
-        assert!(Self::validate_event_checksum(&event)); // TODO: definitely should never fail a CRC check, but what to do if we do?
-        Ok(event)
+    /// Replay the binlog files archived from a MySQL server in `dir` starting from `next_position`,
+    /// rather than reading the binlog from the server itself.
+    ///
+    /// Once we've read every event in the files, we keep checking for more events being written
+    /// to them (or for new files), as if we were waiting for the server to send them. Since we
+    /// don't connect to the server, `TIMESTAMP` values are read as UTC, and minimal row images
+    /// aren't supported.
+    pub(crate) async fn replay_files(
+        dir: PathBuf,
+        next_position: BinlogPosition,
+        table_filter: TableFilter,
+    ) -> ReadySetResult<Self> {
+        let (files, flavor, reader) = Self::open_binlog_files(dir, &next_position).await?;
+        info!(
+            dir = %files.dir().display(),
+            position = %next_position,
+            "Replaying binlog files"
+        );
+
+        Ok(MySqlBinlogConnector {
+            source: BinlogSource::Files(files),
+            flavor,
+            reader,
+            server_id: None,
+            next_position,
+            current_gtid: None,
+            pending_gtid: None,
+            gtid_auto_position: false,
+            rewind_policy: ReplicationRewindPolicy::Error,
+            time_zone: UpstreamTimeZone::default(),
+            payload_events: VecDeque::new(),
+            payload_end_position: 0,
+            transaction: None,
+            rows_query: None,
+            transaction_queries: Vec::new(),
+            reconnect_timeout: Duration::ZERO,
+            consecutive_invalid_events: 0,
+            table_filter,
+            minimal_row_images: None,
+            lag: None,
+        })
+    }
+
+    /// Check that the upstream database, currently at `upstream_position`, hasn't moved backwards
+    /// since [`Self::next_position`] (for example because it was restored from a backup), in
+    /// which case resuming replication from our position would apply the wrong transactions.
+    ///
+    /// If we track GTIDs, the upstream has moved backwards if it hasn't executed every
+    /// transaction we've replicated, regardless of its binlog file and position. If it has, but
+    /// the binlog position still moved backwards, we can only resume with GTID auto-positioning,
+    /// which we switch to if the rewind policy allows it.
+    fn check_for_rewind(&mut self, upstream_position: &BinlogPosition) -> ReadySetResult<()> {
+        let position_behind = matches!(
+            upstream_position.partial_cmp(&self.next_position),
+            Some(Ordering::Less)
+        );
+        let rewound = match (&self.next_position.gtid_set, &upstream_position.gtid_set) {
+            (Some(ours), Some(theirs)) if self.flavor == ServerFlavor::MySql => {
+                if !ours.is_subset(theirs) {
+                    true
+                } else if position_behind && !self.gtid_auto_position {
+                    if self.rewind_policy == ReplicationRewindPolicy::ReconcileGtid {
+                        warn!(
+                            position = %self.next_position,
+                            %upstream_position,
+                            "Upstream binlog position moved backwards, but it has executed every \
+                             transaction we replicated. Resuming with GTID auto-positioning"
+                        );
+                        self.gtid_auto_position = true;
+                    }
+                    !self.gtid_auto_position
+                } else {
+                    false
+                }
+            }
+            _ => position_behind,
+        };
+
+        if rewound {
+            error!(
+                position = %self.next_position,
+                %upstream_position,
+                "Upstream replication position moved backwards"
+            );
+            return Err(ReadySetError::ReplicationOffsetRewound {
+                offset: self.next_position.to_string(),
+                upstream_offset: upstream_position.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Convert an error encountered while reading the binlog into a [`ReadySetError`] with
+    /// [`mysql_error`], additionally reporting `ER_MASTER_FATAL_ERROR_READING_BINLOG` (which the
+    /// server sends if, for example, the binlog file we asked for has been purged) as
+    /// [`ReadySetError::ReplicationOffsetPurged`]
+    fn binlog_error(&self, error: mysql::Error) -> ReadySetError {
+        /// `ER_MASTER_FATAL_ERROR_READING_BINLOG`
+        const FATAL_ERROR_READING_BINLOG: u16 = 1236;
+
+        match error {
+            mysql::Error::Server(e) if e.code == FATAL_ERROR_READING_BINLOG => {
+                counter!(recorded::REPLICATOR_OFFSET_PURGED, 1u64);
+                ReadySetError::ReplicationOffsetPurged {
+                    offset: match &self.next_position.gtid_set {
+                        Some(gtid_set) if self.gtid_auto_position => gtid_set.to_string(),
+                        _ => format!(
+                            "{}:{}",
+                            self.next_position.binlog_file, self.next_position.position
+                        ),
+                    },
+                    message: e.message,
+                }
+            }
+            error => mysql_error(error),
+        }
+    }
+
+    /// Open a new connection to the MySQL server described by `opts` in place of `connection`
+    /// (and for resolving minimal row images, if we do), returning the server's current binlog
+    /// position
+    async fn reconnect_to_server(
+        opts: &mysql::Opts,
+        connection: &mut mysql::Conn,
+        minimal_row_images: Option<&mut MinimalRowImages>,
+    ) -> mysql::Result<BinlogPosition> {
+        *connection = mysql::Conn::new(opts.clone()).await?;
+        if let Some(row_images) = minimal_row_images {
+            row_images.reconnect(opts.clone()).await?;
+        }
+        binlog_position(connection).await
+    }
+
+    /// Open the binlog files in `dir` to read events from `position`, returning them along with
+    /// the flavor of the server that wrote them, and a reader set up to decode their events
+    async fn open_binlog_files(
+        dir: PathBuf,
+        position: &BinlogPosition,
+    ) -> ReadySetResult<(BinlogFiles, ServerFlavor, binlog::EventStreamReader)> {
+        let (files, format_description) =
+            BinlogFiles::open(dir, position.binlog_file.clone(), position.position).await?;
+        let flavor = if written_by_mariadb(&format_description) {
+            ServerFlavor::MariaDb
+        } else {
+            ServerFlavor::MySql
+        };
+        let mut reader = binlog::EventStreamReader::new(binlog::consts::BinlogVersion::Version4);
+        reader
+            .read(&format_description)
+            .map_err(|e| unsupported_event(format!("Failed to decode binlog event: {e}")))?;
+        Ok((files, flavor, reader))
+    }
+
+    /// Reconnect to the MySQL server and resume reading the binlog from `position`, discarding the
+    /// state of any transaction we were in the middle of. Connection errors are retried with
+    /// exponential backoff for up to [`Self::reconnect_timeout`].
+    async fn reconnect(&mut self, position: &ReplicationOffset) -> ReadySetResult<()> {
+        let mut backoff = ExponentialBackoff {
+            max_elapsed_time: Some(self.reconnect_timeout),
+            ..Default::default()
+        };
+
+        loop {
+            self.next_position = position.into();
+            self.current_gtid = None;
+            self.pending_gtid = None;
+            self.payload_events.clear();
+            self.transaction = None;
+            self.rows_query = None;
+            self.transaction_queries.clear();
+
+            let res = match &mut self.source {
+                BinlogSource::Server { opts, connection } => {
+                    Self::reconnect_to_server(opts, connection, self.minimal_row_images.as_mut())
+                        .await
+                }
+                BinlogSource::Files(files) => {
+                    // There's nothing to reconnect to, but we still have to read the files again
+                    // from `position`
+                    let (files, flavor, reader) =
+                        Self::open_binlog_files(files.dir().to_owned(), &self.next_position)
+                            .await?;
+                    self.source = BinlogSource::Files(files);
+                    self.flavor = flavor;
+                    self.reader = reader;
+                    info!(%position, "Reopened binlog files");
+                    return Ok(());
+                }
+            };
+            let res = match res {
+                Ok(upstream_position) => {
+                    self.register_as_replica().await.map(|()| upstream_position)
+                }
+                Err(error) => Err(error),
+            };
+
+            match res {
+                Ok(upstream_position) => {
+                    // We might have reconnected to a different server, such as a replica that was
+                    // promoted after a failover
+                    self.check_for_rewind(&upstream_position)?;
+                    self.request_binlog()
+                        .await
+                        .map_err(|e| self.binlog_error(e))?;
+                    info!(%position, "Reconnected to binlog stream");
+                    return Ok(());
+                }
+                Err(error) if is_connection_error(&error) => match backoff.next_backoff() {
+                    Some(delay) => {
+                        warn!(%error, ?delay, "Failed to reconnect to binlog stream, retrying");
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(mysql_error(error)),
+                },
+                Err(error) => return Err(self.binlog_error(error)),
+            }
+        }
+    }
+
+    /// Get the next raw binlog event, and advance [`Self::next_position`] past it.
+    ///
+    /// The events in a transaction payload event are returned one by one, as if they had been
+    /// sent individually, but since they don't have positions of their own we only advance past
+    /// the payload event once we return the last of them.
+    ///
+    /// Returns `None` if we're replaying binlog files and have read every event written to them so
+    /// far, after waiting for [`BINLOG_FILES_POLL_INTERVAL`] for more to be written.
+    async fn next_event(&mut self) -> ReadySetResult<Option<binlog::events::Event>> {
+        loop {
+            if let Some(event) = self.payload_events.pop_front() {
+                if self.payload_events.is_empty() {
+                    self.next_position.position = self.payload_end_position;
+                }
+                return Ok(Some(event));
+            }
+
+            let (event, event_type) = match &mut self.source {
+                BinlogSource::Server { connection, .. } => {
+                    let packet = connection
+                        .read_packet()
+                        .await
+                        .map_err(|e| self.binlog_error(e))?;
+                    // Byte 0 of packet should be zero, unless EOF is reached, however we should
+                    // never get one without the NON_BLOCKING SQL flag set
+                    if packet.first() != Some(&0) {
+                        return Err(unsupported_event(format!(
+                            "Unexpected binlog packet header {:?}",
+                            packet.first()
+                        )));
+                    }
+                    // The event type is the 5th byte of the event header
+                    (self.reader.read(&packet[1..]), packet.get(5).copied())
+                }
+                BinlogSource::Files(files) => {
+                    let Some(data) = files.next_event().await? else {
+                        tokio::time::sleep(BINLOG_FILES_POLL_INTERVAL).await;
+                        return Ok(None);
+                    };
+                    // We may have moved on to the next file without reading a ROTATE_EVENT, if
+                    // the server didn't shut down cleanly
+                    if files.file_name() != self.next_position.binlog_file {
+                        self.next_position.binlog_file = files.file_name().to_owned();
+                    }
+                    (self.reader.read(&data), data.get(4).copied())
+                }
+            };
+            let event = event
+                .map_err(|e| unsupported_event(format!("Failed to decode binlog event: {e}")))?;
+            if !Self::validate_event_checksum(&event) {
+                return Err(unsupported_event(format!(
+                    "Checksum mismatch for binlog event at {}:{}",
+                    self.next_position.binlog_file,
+                    event.header().log_pos()
+                )));
+            }
+
+            if event_type == Some(TRANSACTION_PAYLOAD_EVENT) {
+                self.payload_events = self.read_transaction_payload(&event)?;
+                self.payload_end_position = event.header().log_pos();
+                if self.payload_events.is_empty() {
+                    self.next_position.position = self.payload_end_position;
+                }
+                continue;
+            }
+
+            self.next_position.position = event.header().log_pos();
+            return Ok(Some(event));
+        }
+    }
+
+    /// Record how far behind the primary we are in [`recorded::REPLICATOR_LAG`], based on the
+    /// timestamp of `event`, which is the time the primary started executing the statement it
+    /// belongs to. The lag is also kept in [`Self::lag`].
+    fn record_lag(&mut self, event: &binlog::events::Event) {
+        let lag = match event.header().event_type() {
+            // Heartbeats are only sent when the primary has no new events for us
+            Ok(EventType::HEARTBEAT_EVENT) => 0,
+            // Artificial events, such as the ROTATE_EVENT sent when we start reading the binlog,
+            // have no timestamp
+            _ if event.header().timestamp() == 0 => return,
+            _ => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                now.saturating_sub(event.header().timestamp().into())
+            }
+        };
+        gauge!(recorded::REPLICATOR_LAG, lag as f64);
+        self.lag = Some(Duration::from_secs(lag));
+    }
+
+    /// Record the per-table replication metrics for a rows event of the given `kind` for `table`,
+    /// which is `event_size` bytes long, and took `conversion_time` to convert to `actions`
+    fn record_rows_event(
+        table: &Relation,
+        kind: RowsEventKind,
+        actions: &[TableOperation],
+        event_size: u32,
+        conversion_time: Duration,
+    ) {
+        let table_name = table.display_unquoted().to_string();
+        counter!(
+            recorded::REPLICATOR_TABLE_ROWS,
+            kind.rows(actions) as u64,
+            "table_name" => table_name.clone(),
+            "operation" => kind.operation(),
+        );
+        counter!(
+            recorded::REPLICATOR_TABLE_BYTES,
+            event_size.into(),
+            "table_name" => table_name.clone(),
+        );
+        histogram!(
+            recorded::REPLICATOR_TABLE_CONVERSION_TIME,
+            conversion_time.as_micros() as f64,
+            "table_name" => table_name,
+        );
+    }
+
+    /// Handle a rows event of the given `kind`, which may be either the V1 or the V2 variant of the
+    /// event, returning the action to take for its rows, if any.
+    ///
+    /// The V1 variants of the rows events are used from MySQL 5.1.16 until MySQL 5.6, and are still
+    /// emitted by some managed services. They only differ from the V2 variants in that they lack
+    /// the extra data header, which `mysql_common` takes care of for us.
+    async fn handle_rows_event(
+        &mut self,
+        binlog_event: &binlog::events::Event,
+        event_type: EventType,
+        kind: RowsEventKind,
+    ) -> ReadySetResult<Option<ReplicationAction>> {
+        let ev = match binlog_event.read_data().map_err(unsupported_event)? {
+            Some(EventData::RowsEvent(ev)) => ev,
+            _ => {
+                return Err(unsupported_event(format!(
+                    "Failed to decode {event_type:?} as a rows event"
+                )))
+            }
+        };
+        if Component::Replicator.is_enabled() {
+            info!(target: "replicator_statement", "{:?}", ev);
+        }
+        // Retrieve the corresponding TABLE_MAP_EVENT
+        let tme = self
+            .reader
+            .get_tme(ev.table_id())
+            .ok_or_else(|| tme_not_found(&format!("{event_type:?}"), ev.table_id()))?;
+        if !self.should_replicate(tme) {
+            return Ok(None);
+        }
+
+        let conversion_start = Instant::now();
+        let table = tme_relation(tme);
+        let missing_image =
+            move || unsupported_event(format!("Missing row image in {event_type:?}"));
+        let actions = match (&mut self.minimal_row_images, kind) {
+            (Some(row_images), RowsEventKind::Write) => {
+                let columns = ev
+                    .columns_after_image()
+                    .ok_or_else(missing_image)?
+                    .iter_ones()
+                    .collect::<Vec<_>>();
+                let rows =
+                    binlog_rows_to_minimal_inserts(ev.rows(tme), &columns, tme, &self.time_zone)?;
+                let position = (&self.next_position).try_into()?;
+                row_images.inserts(&table, rows, &position).await?
+            }
+            (Some(row_images), RowsEventKind::Update) => {
+                let before = ev
+                    .columns_before_image()
+                    .ok_or_else(missing_image)?
+                    .iter_ones()
+                    .collect::<Vec<_>>();
+                let after = ev
+                    .columns_after_image()
+                    .ok_or_else(missing_image)?
+                    .iter_ones()
+                    .collect::<Vec<_>>();
+                let rows = binlog_rows_to_minimal_updates(
+                    ev.rows(tme),
+                    &before,
+                    &after,
+                    tme,
+                    &self.time_zone,
+                )?;
+                row_images.updates(&table, rows).await?
+            }
+            (Some(row_images), RowsEventKind::Delete) => {
+                let columns = ev
+                    .columns_before_image()
+                    .ok_or_else(missing_image)?
+                    .iter_ones()
+                    .collect::<Vec<_>>();
+                let rows =
+                    binlog_rows_to_minimal_deletes(ev.rows(tme), &columns, tme, &self.time_zone)?;
+                row_images.deletes(&table, rows).await?
+            }
+            (None, RowsEventKind::Write) => {
+                binlog_rows_to_inserts(ev.rows(tme).collect(), tme, self.time_zone).await?
+            }
+            (None, RowsEventKind::Update) => {
+                binlog_rows_to_updates(ev.rows(tme).collect(), tme, self.time_zone).await?
+            }
+            (None, RowsEventKind::Delete) => {
+                binlog_rows_to_deletes(ev.rows(tme).collect(), tme, self.time_zone).await?
+            }
+        };
+        Self::record_rows_event(
+            &table,
+            kind,
+            &actions,
+            binlog_event.header().event_size(),
+            conversion_start.elapsed(),
+        );
+        Ok(self.table_action(table, actions))
+    }
+
+    /// Decompress and decode the events contained in a TRANSACTION_PAYLOAD_EVENT.
+    ///
+    /// The event starts with a list of (type, length, value) fields, all encoded as length-encoded
+    /// integers, terminated by a field of type 0, followed by the payload itself. We only need the
+    /// compression type field (type 2), which is either 0 for ZSTD or 255 for no compression.
+    fn read_transaction_payload(
+        &mut self,
+        event: &binlog::events::Event,
+    ) -> ReadySetResult<VecDeque<binlog::events::Event>> {
+        const HEADER_END_MARK: u64 = 0;
+        const COMPRESSION_TYPE_FIELD: u64 = 2;
+        const ZSTD: u64 = 0;
+        const NONE: u64 = 255;
+
+        fn truncated() -> ReadySetError {
+            unsupported_event("Truncated TRANSACTION_PAYLOAD_EVENT")
+        }
+
+        let mut data = event.data();
+        let mut compression_type = NONE;
+        loop {
+            let field_type = read_lenenc_int(&mut data).ok_or_else(truncated)?;
+            if field_type == HEADER_END_MARK {
+                break;
+            }
+            let len = read_lenenc_int(&mut data).ok_or_else(truncated)? as usize;
+            let mut value = data.get(..len).ok_or_else(truncated)?;
+            data = &data[len..];
+            if field_type == COMPRESSION_TYPE_FIELD {
+                compression_type = read_lenenc_int(&mut value).ok_or_else(truncated)?;
+            }
+        }
+
+        let payload = match compression_type {
+            ZSTD => zstd::stream::decode_all(data).map_err(|e| {
+                unsupported_event(format!("Failed to decompress transaction payload: {e}"))
+            })?,
+            NONE => data.to_vec(),
+            _ => {
+                return Err(unsupported_event(format!(
+                    "Unknown transaction payload compression type {compression_type}"
+                )))
+            }
+        };
+
+        // The events in the payload are serialized without checksums, but our reader expects
+        // events to have them if the binlog stream does, so compute them as we go
+        let with_checksum = matches!(
+            event.footer().get_checksum_alg(),
+            Ok(Some(BinlogChecksumAlg::BINLOG_CHECKSUM_ALG_CRC32))
+        );
+        let mut events = VecDeque::new();
+        let mut payload = &payload[..];
+        while !payload.is_empty() {
+            // The event size is the 4 bytes following the timestamp, type and server id in the
+            // 19-byte event header
+            let size = payload
+                .get(9..13)
+                .map(|size| u32::from_le_bytes(size.try_into().unwrap()) as usize)
+                .filter(|size| *size >= 19 && *size <= payload.len())
+                .ok_or_else(truncated)?;
+            let mut raw_event = payload[..size].to_vec();
+            payload = &payload[size..];
+            if with_checksum {
+                raw_event[9..13].copy_from_slice(&(size as u32 + 4).to_le_bytes());
+                let checksum = crc32fast::hash(&raw_event);
+                raw_event.extend_from_slice(&checksum.to_le_bytes());
+            }
+            events.push_back(self.reader.read(&raw_event).map_err(|e| {
+                unsupported_event(format!("Failed to decode transaction payload event: {e}"))
+            })?);
+        }
+
+        Ok(events)
+    }
+
+    /// Handle a MariaDB-specific binlog event, of the raw event type `event_type`
+    fn handle_mariadb_event(
+        &mut self,
+        event_type: u8,
+        event: &binlog::events::Event,
+    ) -> ReadySetResult<()> {
+        match event_type {
+            MARIADB_GTID_EVENT => {
+                // The GTID of the following event group, which takes the place of the `BEGIN`
+                // query event MySQL writes at the start of a transaction. The event consists of
+                // an 8-byte sequence number, a 4-byte replication domain id and a 1-byte flags
+                // field, all little-endian.
+                let data = event.data();
+                let (seq_no, domain_id, flags) = match data.get(..13) {
+                    Some(data) => (
+                        u64::from_le_bytes(data[..8].try_into().unwrap()),
+                        u32::from_le_bytes(data[8..12].try_into().unwrap()),
+                        data[12],
+                    ),
+                    None => return Err(unsupported_event("Truncated MariaDB GTID event")),
+                };
+                if Component::Replicator.is_enabled() {
+                    info!(
+                        target: "replicator_statement",
+                        gtid = %format!("{domain_id}-{}-{seq_no}", event.header().server_id()),
+                        flags,
+                        "MariaDB GTID event"
+                    );
+                }
+                self.current_gtid = Some(seq_no);
+                if flags & MARIADB_FL_STANDALONE == 0 {
+                    self.transaction = Some(Vec::new());
+                }
+            }
+            MARIADB_START_ENCRYPTION_EVENT => {
+                return Err(unsupported_event(
+                    "Encrypted MariaDB binlogs are not supported",
+                ));
+            }
+            // The query that caused the following rows events, the list of GTIDs executed before
+            // the current binlog file, and the binlog files needed for crash recovery, none of
+            // which we need
+            MARIADB_ANNOTATE_ROWS_EVENT
+            | MARIADB_GTID_LIST_EVENT
+            | MARIADB_BINLOG_CHECKPOINT_EVENT => {
+                if Component::Replicator.is_enabled() {
+                    info!(target: "replicator_statement", "unhandled MariaDB event: {event_type}");
+                }
+            }
+            _ => {
+                return Err(unsupported_event(format!(
+                    "Unknown binlog event type {event_type}"
+                )))
+            }
+        }
+
+        Ok(())
     }
 
     /// Process binlog events until an actionable event occurs.
@@ -238,26 +1112,44 @@ impl MySqlBinlogConnector {
     pub(crate) async fn next_action_inner(
         &mut self,
         until: Option<&ReplicationOffset>,
-    ) -> mysql::Result<(ReplicationAction, &BinlogPosition)> {
+    ) -> ReadySetResult<(ReplicationAction, &BinlogPosition)> {
         use mysql_common::binlog::events;
 
         loop {
-            let binlog_event = self.next_event().await?;
-
-            self.next_position.position = binlog_event.header().log_pos();
+            let Some(binlog_event) = self.next_event().await? else {
+                // There are no new events in the binlog files we're replaying, which is as if
+                // the server had sent us a heartbeat
+                if self.transaction.is_none() {
+                    return Ok((ReplicationAction::Heartbeat, &self.next_position));
+                }
+                continue;
+            };
+            self.record_lag(&binlog_event);
+
+            let event_type = match binlog_event.header().event_type() {
+                Ok(event_type) => event_type,
+                Err(UnknownEventType(event_type)) if self.flavor == ServerFlavor::MariaDb => {
+                    // None of the MariaDB-specific events are actionable on their own
+                    self.handle_mariadb_event(event_type, &binlog_event)?;
+                    continue;
+                }
+                Err(ev) => {
+                    return Err(unsupported_event(format!(
+                        "Unknown binlog event type {}",
+                        ev
+                    )))
+                }
+            };
 
-            match binlog_event
-                .header()
-                .event_type()
-                .map_err(|ev| format!("Unknown binlog event type {}", ev))?
-            {
+            match event_type {
                 EventType::ROTATE_EVENT => {
                     // Written when mysqld switches to a new binary log file.
                     // This occurs when someone issues a FLUSH LOGS statement or the current binary
                     // log file becomes too large. The maximum size is
                     // determined by max_binlog_size.
-                    let ev: events::RotateEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
+                    let ev: events::RotateEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "{:?}", ev);
                     }
 
@@ -266,6 +1158,7 @@ impl MySqlBinlogConnector {
                         // This should never happen, but better to panic than to get the wrong
                         // position
                         position: u32::try_from(ev.position()).unwrap(),
+                        gtid_set: self.next_position.gtid_set.take(),
                     };
 
                     return Ok((ReplicationAction::LogPosition, &self.next_position));
@@ -273,39 +1166,129 @@ impl MySqlBinlogConnector {
 
                 EventType::QUERY_EVENT => {
                     // Written when an updating statement is done.
-                    let ev: events::QueryEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
+                    let ev: events::QueryEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "{:?}", ev);
                     }
 
-                    let schema = match ev
+                    match &*ev.query() {
+                        "BEGIN" => {
+                            self.transaction = Some(Vec::new());
+                            continue;
+                        }
+                        // Transactions on non-transactional engines end with a `COMMIT` (or a
+                        // `ROLLBACK`, which still leaves the non-transactional changes applied)
+                        // rather than an XID_EVENT
+                        "COMMIT" | "ROLLBACK" => {
+                            self.commit_pending_gtid();
+                            if let Some(action) = self.end_transaction() {
+                                return Ok((action, &self.next_position));
+                            }
+                            continue;
+                        }
+                        // Any other query outside of a transaction is DDL, which is always in a
+                        // transaction of its own
+                        _ if self.transaction.is_none() => self.commit_pending_gtid(),
+                        _ => {}
+                    }
+
+                    let updated_dbs = match ev
                         .status_vars()
                         .get_status_var(binlog::consts::StatusVarKey::UpdatedDbNames)
                         .as_ref()
                         .and_then(|v| v.get_value().ok())
                     {
-                        Some(StatusVarVal::UpdatedDbNames(names)) if !names.is_empty() => {
-                            // IMPORTANT: For some statements there can be more than one update db,
-                            // for example `DROP TABLE db1.tbl, db2.table;` Will have `db1` and
-                            // `db2` listed, however we only need the schema to filter out
-                            // `CREATE TABLE` and `ALTER TABLE` and those always change only one DB.
-                            names.first().unwrap().as_str().to_string()
-                        }
+                        Some(StatusVarVal::UpdatedDbNames(names)) if !names.is_empty() => names,
                         // If the query does not affect the schema, just keep going
-                        // TODO: Transactions begin with the `BEGIN` queries, but we do not
-                        // currently support those
                         _ => continue,
                     };
 
-                    let changes = match ChangeList::from_str(&ev.query(), Dialect::DEFAULT_MYSQL) {
+                    // Unqualified names in the statement refer to the session's default database.
+                    // A statement can update more than one database (for example `DROP TABLE
+                    // db1.t1, db2.t2`), so we qualify every name in it with its database below.
+                    let schema = match ev.schema() {
+                        schema if !schema.is_empty() => schema.into_owned(),
+                        _ => updated_dbs.first().unwrap().as_str().to_string(),
+                    };
+
+                    let query = ev.query();
+                    let mut changes = match ChangeList::from_str(&query, Dialect::DEFAULT_MYSQL) {
                         Ok(changelist) => changelist.changes,
-                        Err(error) => {
-                            warn!(%error, "Error extending recipe, DDL statement will not be used");
-                            counter!(recorded::REPLICATOR_FAILURE, 1u64);
-                            continue;
-                        }
+                        Err(error) => match nom_sql::parse_query(nom_sql::Dialect::MySQL, &query) {
+                            // Renames aren't a change to the recipe, so they get their own action
+                            Ok(SqlQuery::RenameTable(stmt)) => {
+                                let renames = stmt
+                                    .ops
+                                    .into_iter()
+                                    .map(|mut op| {
+                                        qualify(&mut op.from, &schema);
+                                        qualify(&mut op.to, &schema);
+                                        op
+                                    })
+                                    .collect();
+                                if let Some(row_images) = &mut self.minimal_row_images {
+                                    row_images.schema_changed();
+                                }
+                                return Ok((
+                                    ReplicationAction::RenameTables { schema, renames },
+                                    &self.next_position,
+                                ));
+                            }
+                            // If we can tell which table the statement changes, it's up to the
+                            // adapter's DDL conflict policy what to do about it
+                            _ => match unparsed_ddl_table(&query) {
+                                Some(mut table) => {
+                                    qualify(&mut table, &schema);
+                                    if let Some(row_images) = &mut self.minimal_row_images {
+                                        row_images.schema_changed();
+                                    }
+                                    return Ok((
+                                        ReplicationAction::UnparsedDdl {
+                                            table,
+                                            error: error.to_string(),
+                                        },
+                                        &self.next_position,
+                                    ));
+                                }
+                                None => {
+                                    warn!(
+                                        %error,
+                                        "Error extending recipe, DDL statement will not be used"
+                                    );
+                                    counter!(recorded::REPLICATOR_FAILURE, 1u64);
+                                    continue;
+                                }
+                            },
+                        },
                     };
 
+                    for change in &mut changes {
+                        match change {
+                            Change::CreateTable(stmt) => qualify(&mut stmt.table, &schema),
+                            Change::AlterTable(stmt) => qualify(&mut stmt.table, &schema),
+                            Change::CreateView(stmt) => qualify(&mut stmt.name, &schema),
+                            Change::Drop { name, .. } => qualify(name, &schema),
+                            _ => {}
+                        }
+                    }
+
+                    // `CREATE TABLE`s for tables we don't replicate are still passed on, so that
+                    // the table is recorded as non-replicated
+                    changes.retain(|change| match change {
+                        Change::AlterTable(stmt) => self.table_filter.should_be_processed(
+                            stmt.table.schema.as_deref().unwrap_or_default(),
+                            stmt.table.name.as_str(),
+                        ),
+                        _ => true,
+                    });
+                    if changes.is_empty() {
+                        continue;
+                    }
+
+                    if let Some(row_images) = &mut self.minimal_row_images {
+                        row_images.schema_changed();
+                    }
                     return Ok((
                         ReplicationAction::DdlChange { schema, changes },
                         &self.next_position,
@@ -324,147 +1307,71 @@ impl MySqlBinlogConnector {
                     // TABLE_MAP_EVENT events: one per table used by events in the sequence.
                     // Those events are implicitly handled by our lord and saviour
                     // `binlog::EventStreamReader`
-                    if self.enable_statement_logging {
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "unhandled event: {:?}", ev);
                     }
                 }
 
-                EventType::WRITE_ROWS_EVENT => {
-                    // This is the event we get on `INSERT INTO`
-                    let ev: events::WriteRowsEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
-                        info!(target: "replicator_statement", "{:?}", ev);
-                    }
-                    // Retrieve the corresponding TABLE_MAP_EVENT
-                    let tme = self
-                        .reader
-                        .get_tme(ev.table_id())
-                        .ok_or("TME not found for WRITE_ROWS_EVENT")?;
-
-                    let mut inserted_rows = Vec::new();
-
-                    for row in ev.rows(tme) {
-                        // For each row in the event we produce a vector of ReadySet types that
-                        // represent that row
-                        inserted_rows.push(readyset_client::TableOperation::Insert(
-                            binlog_row_to_noria_row(
-                                &row?.1.ok_or("Missing data in WRITE_ROWS_EVENT")?,
-                                tme,
-                            )?,
-                        ));
+                // This is the event we get on `INSERT INTO`
+                event_type @ (EventType::WRITE_ROWS_EVENT | EventType::WRITE_ROWS_EVENT_V1) => {
+                    if let Some(action) = self
+                        .handle_rows_event(&binlog_event, event_type, RowsEventKind::Write)
+                        .await?
+                    {
+                        return Ok((action, &self.next_position));
                     }
-
-                    return Ok((
-                        ReplicationAction::TableAction {
-                            table: Relation {
-                                schema: Some(tme.database_name().into()),
-                                name: tme.table_name().into(),
-                            },
-                            actions: inserted_rows,
-                            txid: self.current_gtid,
-                        },
-                        &self.next_position,
-                    ));
                 }
 
-                EventType::UPDATE_ROWS_EVENT => {
-                    // This is the event we get on `UPDATE`
-                    let ev: events::UpdateRowsEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
-                        info!(target: "replicator_statement", "{:?}", ev);
-                    }
-                    // Retrieve the corresponding TABLE_MAP_EVENT
-                    let tme = self
-                        .reader
-                        .get_tme(ev.table_id())
-                        .ok_or_else(|| format!("TME not found for UPDATE_ROWS_EVENT {:?}", ev))?;
-
-                    let mut updated_rows = Vec::new();
-
-                    for row in ev.rows(tme) {
-                        // For each row in the event we produce a pair of ReadySet table operations
-                        // to delete the previous entry and insert the new
-                        // one
-                        let row = &row?;
-                        updated_rows.push(readyset_client::TableOperation::DeleteRow {
-                            row: binlog_row_to_noria_row(
-                                row.0.as_ref().ok_or_else(|| {
-                                    format!("Missing before rows in UPDATE_ROWS_EVENT {:?}", row)
-                                })?,
-                                tme,
-                            )?,
-                        });
-
-                        updated_rows.push(readyset_client::TableOperation::Insert(
-                            binlog_row_to_noria_row(
-                                row.1.as_ref().ok_or_else(|| {
-                                    format!("Missing after rows in UPDATE_ROWS_EVENT {:?}", row)
-                                })?,
-                                tme,
-                            )?,
-                        ));
+                // This is the event we get on `UPDATE`
+                event_type @ (EventType::UPDATE_ROWS_EVENT | EventType::UPDATE_ROWS_EVENT_V1) => {
+                    if let Some(action) = self
+                        .handle_rows_event(&binlog_event, event_type, RowsEventKind::Update)
+                        .await?
+                    {
+                        return Ok((action, &self.next_position));
                     }
-
-                    return Ok((
-                        ReplicationAction::TableAction {
-                            table: Relation {
-                                schema: Some(tme.database_name().into()),
-                                name: tme.table_name().into(),
-                            },
-                            actions: updated_rows,
-                            txid: self.current_gtid,
-                        },
-                        &self.next_position,
-                    ));
                 }
 
-                EventType::DELETE_ROWS_EVENT => {
-                    // This is the event we get on `ALTER TABLE`
-                    let ev: events::DeleteRowsEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
+                EventType::PARTIAL_UPDATE_ROWS_EVENT => {
+                    // This is the event we get on `UPDATE` for updates that only change part of a
+                    // JSON column, if `binlog_row_value_options=PARTIAL_JSON`
+                    let ev: events::PartialUpdateRowsEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "{:?}", ev);
                     }
-                    // Retrieve the corresponding TABLE_MAP_EVENT
                     let tme = self
                         .reader
                         .get_tme(ev.table_id())
-                        .ok_or_else(|| format!("TME not found for UPDATE_ROWS_EVENT {:?}", ev))?;
-
-                    let mut deleted_rows = Vec::new();
-
-                    for row in ev.rows(tme) {
-                        // For each row in the event we produce a vector of ReadySet types that
-                        // represent that row
-                        deleted_rows.push(readyset_client::TableOperation::DeleteRow {
-                            row: binlog_row_to_noria_row(
-                                &row?.0.ok_or("Missing data in DELETE_ROWS_EVENT")?,
-                                tme,
-                            )?,
-                        });
+                        .ok_or_else(|| tme_not_found("PARTIAL_UPDATE_ROWS_EVENT", ev.table_id()))?;
+                    if self.should_replicate(tme) {
+                        let conversion_start = Instant::now();
+                        let actions =
+                            binlog_rows_to_partial_updates(ev.rows(tme), tme, &self.time_zone)?;
+                        let table = tme_relation(tme);
+                        Self::record_rows_event(
+                            &table,
+                            RowsEventKind::Update,
+                            &actions,
+                            binlog_event.header().event_size(),
+                            conversion_start.elapsed(),
+                        );
+                        if let Some(action) = self.table_action(table, actions) {
+                            return Ok((action, &self.next_position));
+                        }
                     }
+                }
 
-                    return Ok((
-                        ReplicationAction::TableAction {
-                            table: Relation {
-                                schema: Some(tme.database_name().into()),
-                                name: tme.table_name().into(),
-                            },
-                            actions: deleted_rows,
-                            txid: self.current_gtid,
-                        },
-                        &self.next_position,
-                    ));
+                // This is the event we get on `DELETE`
+                event_type @ (EventType::DELETE_ROWS_EVENT | EventType::DELETE_ROWS_EVENT_V1) => {
+                    if let Some(action) = self
+                        .handle_rows_event(&binlog_event, event_type, RowsEventKind::Delete)
+                        .await?
+                    {
+                        return Ok((action, &self.next_position));
+                    }
                 }
 
-                EventType::WRITE_ROWS_EVENT_V1 => unimplemented!(), /* The V1 event numbers are */
-                // used from 5.1.16 until
-                // mysql-5.6.
-                EventType::UPDATE_ROWS_EVENT_V1 => unimplemented!(), /* The V1 event numbers are */
-                // used from 5.1.16 until
-                // mysql-5.6.
-                EventType::DELETE_ROWS_EVENT_V1 => unimplemented!(), /* The V1 event numbers are */
-                // used from 5.1.16 until
-                // mysql-5.6.
                 EventType::GTID_EVENT => {
                     // GTID stands for Global Transaction IDentifier It is composed of two parts:
                     // SID for Source Identifier, and GNO for Group Number. The basic idea is to
@@ -474,29 +1381,72 @@ impl MySqlBinlogConnector {
                     // slave's binary log, the GTID is preserved.  When a slave connects to a
                     // master, the slave uses GTIDs instead of (file, offset)
                     // See also https://dev.mysql.com/doc/refman/8.0/en/replication-mode-change-online-concepts.html
-                    let ev: events::GtidEvent = binlog_event.read_event()?;
-                    if self.enable_statement_logging {
+                    let ev: events::GtidEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "{:?}", ev);
                     }
                     self.current_gtid = Some(ev.gno());
+                    self.pending_gtid = Some((ev.sid(), ev.gno()));
                 }
 
-                /*
+                EventType::XID_EVENT => {
+                    // Generated for a commit of a transaction that modifies one or more tables of
+                    // an XA-capable storage engine.
+                    if Component::Replicator.is_enabled() {
+                        info!(target: "replicator_statement", "commit: {:?}", self.pending_gtid);
+                    }
+                    self.commit_pending_gtid();
+                    if let Some(action) = self.end_transaction() {
+                        return Ok((action, &self.next_position));
+                    }
+                }
 
-                EventType::ANONYMOUS_GTID_EVENT => {}
+                EventType::ROWS_QUERY_EVENT => {
+                    // The statement that caused the rows events that follow, if the upstream has
+                    // `binlog_rows_query_log_events` enabled
+                    let ev: events::RowsQueryEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    let query = ev.query().into_owned();
+                    if Component::Replicator.is_enabled() {
+                        info!(target: "replicator_statement", %query, "rows query");
+                    }
+                    self.rows_query = Some(query);
+                }
 
-                EventType::XID_EVENT => {
-                    // Generated for a commit of a transaction that modifies one or more tables of an XA-capable
-                    // storage engine. Normal transactions are implemented by sending a QUERY_EVENT containing a
-                    // BEGIN statement and a QUERY_EVENT containing a COMMIT statement
-                    // (or a ROLLBACK statement if the transaction is rolled back).
+                EventType::INCIDENT_EVENT => {
+                    // Written when something out of the ordinary happened on the primary that
+                    // might have left the binlog inconsistent with its data, such as a
+                    // non-transactional statement failing partway through. We can't tell which
+                    // tables are affected, so all of them need to be snapshotted again.
+                    let ev: events::IncidentEvent =
+                        binlog_event.read_event().map_err(unsupported_event)?;
+                    warn!(
+                        message = %ev.message(),
+                        "Upstream reported an incident, replicated data may be inconsistent"
+                    );
+                    return Ok((
+                        ReplicationAction::ResnapshotRequired {
+                            reason: format!("Upstream reported incident: {}", ev.message()),
+                        },
+                        &self.next_position,
+                    ));
+                }
+
+                EventType::HEARTBEAT_EVENT if self.transaction.is_none() => {
+                    // Sent by the primary when it has no new events for us, which lets the adapter
+                    // apply writes it's buffered rather than waiting for the next event
+                    return Ok((ReplicationAction::Heartbeat, &self.next_position));
                 }
 
+                /*
+
+                EventType::ANONYMOUS_GTID_EVENT => {}
+
                 EventType::START_EVENT_V3 // Old version of FORMAT_DESCRIPTION_EVENT
                 | EventType::FORMAT_DESCRIPTION_EVENT // A descriptor event that is written to the beginning of each binary log file. This event is used as of MySQL 5.0; it supersedes START_EVENT_V3.
                 | EventType::STOP_EVENT // Written when mysqld stops
-                | EventType::INCIDENT_EVENT // The event is used to inform the slave that something out of the ordinary happened on the master that might cause the database to be in an inconsistent state.
-                | EventType::HEARTBEAT_EVENT => {} // The event is originated by master's dump thread and sent straight to slave without being logged. Slave itself does not store it in relay log but rather uses a data for immediate checks and throws away the event.
+                | EventType::HEARTBEAT_EVENT => {} // The event is originated by master's dump thread and sent straight to slave without being logged. Slave itself does not store it in relay log but rather uses a data for immediate checks and throws away the event. We use it to report replication lag in `record_lag`.
 
                 EventType::UNKNOWN_EVENT | EventType::SLAVE_EVENT => {} // Ignored events
 
@@ -518,25 +1468,28 @@ impl MySqlBinlogConnector {
                 EventType::PRE_GA_DELETE_ROWS_EVENT => {} // Obsolete version of DELETE_ROWS_EVENT.
 
                 EventType::IGNORABLE_EVENT => {} // In some situations, it is necessary to send over ignorable data to the slave: data that a slave can handle in case there is code for handling it, but which can be ignored if it is not recognized.
-                EventType::ROWS_QUERY_EVENT => {} // Query that caused the following ROWS_EVENT
 
                 EventType::PREVIOUS_GTIDS_EVENT => {}
                 EventType::TRANSACTION_CONTEXT_EVENT => {}
                 EventType::VIEW_CHANGE_EVENT => {}
                 EventType::XA_PREPARE_LOG_EVENT => {}
-                EventType::PARTIAL_UPDATE_ROWS_EVENT => {}
                 EventType::ENUM_END_EVENT => {}
                 */
                 ev => {
-                    if self.enable_statement_logging {
+                    if Component::Replicator.is_enabled() {
                         info!(target: "replicator_statement", "unhandled event: {:?}", ev);
                     }
                 }
             }
 
             // We didn't get an actionable event, but we still need to check that we haven't reached
-            // the until limit
-            if let Some(limit) = until {
+            // the until limit. We can't report our position while we have table operations buffered
+            // for the current transaction though, since they would be skipped as already applied.
+            let buffering = self
+                .transaction
+                .as_ref()
+                .map_or(false, |transaction| !transaction.is_empty());
+            if let Some(limit) = until.filter(|_| !buffering) {
                 let limit = BinlogPosition::try_from(limit).expect("Valid binlog limit");
                 if self.next_position >= limit {
                     return Ok((ReplicationAction::LogPosition, &self.next_position));
@@ -550,6 +1503,7 @@ fn binlog_val_to_noria_val(
     val: &mysql_common::value::Value,
     col_kind: mysql_common::constants::ColumnType,
     meta: &[u8],
+    time_zone: &UpstreamTimeZone,
 ) -> mysql::Result<DfValue> {
     // Not all values are coerced to the value expected by ReadySet directly
 
@@ -575,7 +1529,9 @@ fn binlog_val_to_noria_val(
                 // currently set to None
                 return Ok(DfValue::None);
             }
-            let time = chrono::naive::NaiveDateTime::from_timestamp(epoch, 0);
+            // The upstream returns timestamps in the time zone of the session, so convert them
+            // to the same local time we'd have read when snapshotting
+            let time = time_zone.local_datetime(epoch, 0);
             // Can unwrap because we know it maps directly to [`DfValue`]
             Ok(time.try_into().unwrap())
         }
@@ -583,71 +1539,752 @@ fn binlog_val_to_noria_val(
             // When meta is anything else, `mysql_common` encodes this value as number of
             // seconds.microseconds (since UNIX EPOCH)
             let s = String::from_utf8_lossy(buf);
-            let (secs, usecs) = s.split_once('.').unwrap(); // safe to unwrap because format is fixed
+            let (secs, fraction) = s.split_once('.').unwrap(); // safe to unwrap because format is fixed
             let secs = secs.parse::<i64>().unwrap();
-            let usecs = usecs.parse::<u32>().unwrap();
-            let time = chrono::naive::NaiveDateTime::from_timestamp(secs, usecs * 32);
+            // The fraction has as many digits as the column's precision, so pad it to get the
+            // number of microseconds
+            let usecs = format!("{:0<6.6}", fraction).parse::<u32>().unwrap();
+            if secs == 0 && usecs == 0 {
+                // As above, the 0 epoch is reserved for the zero timestamp
+                return Ok(DfValue::None);
+            }
+            let time = time_zone.local_datetime(secs, usecs * 1000);
             // Can wrap because we know this maps directly to [`DfValue`]
             Ok(time.try_into().unwrap())
         }
+        (ColumnType::MYSQL_TYPE_GEOMETRY, _) => Ok(geometry_value(buf)?),
         _ => Ok(val
             .try_into()
             .map_err(|e| format!("Unable to coerce value {}", e))?),
     }
 }
 
+/// Read a length-encoded integer from the start of `data`, advancing it past the integer, or
+/// return `None` if `data` is too short
+fn read_lenenc_int(data: &mut &[u8]) -> Option<u64> {
+    let (&first, rest) = data.split_first()?;
+    let len = match first {
+        0xfc => 2,
+        0xfd => 3,
+        0xfe => 8,
+        _ => {
+            *data = rest;
+            return Some(first as u64);
+        }
+    };
+    let bytes = rest.get(..len)?;
+    *data = &rest[len..];
+    let mut buf = [0; 8];
+    buf[..len].copy_from_slice(bytes);
+    Some(u64::from_le_bytes(buf))
+}
+
+/// The ReadySet table written to by the rows events described by `tme`
+fn tme_relation(tme: &binlog::events::TableMapEvent<'static>) -> Relation {
+    Relation {
+        schema: Some(tme.database_name().into()),
+        name: tme.table_name().into(),
+    }
+}
+
+/// Qualify `table` with `schema`, the default database of the statement it was named in, if the
+/// statement didn't name a database for it
+fn qualify(table: &mut Relation, schema: &str) {
+    if table.schema.is_none() {
+        table.schema = Some(schema.into());
+    }
+}
+
+/// The rows decoded from any of the `*_ROWS_EVENT` binlog events, as (before image, after image)
+/// pairs
+type BinlogRowsResult = io::Result<(Option<BinlogRow>, Option<BinlogRow>)>;
+
+/// Rows events with at least this many rows are converted to table operations in parallel
+const PARALLEL_CONVERSION_MIN_ROWS: usize = 1024;
+
+/// The number of rows in each chunk of a rows event that's converted in parallel
+const PARALLEL_CONVERSION_CHUNK_SIZE: usize = 256;
+
+/// Convert each of `rows` to table operations with `convert`, which appends the operations for
+/// the row it's passed to the given list.
+///
+/// Converting the rows of a huge rows event (such as one from a bulk load) one by one would stall
+/// replication, so events with at least [`PARALLEL_CONVERSION_MIN_ROWS`] rows are split into
+/// chunks that are converted in parallel on the blocking thread pool. Either way, the operations
+/// are returned in the same order as the rows they were converted from.
+async fn convert_binlog_rows<F>(
+    rows: Vec<BinlogRowsResult>,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: UpstreamTimeZone,
+    convert: F,
+) -> ReadySetResult<Vec<TableOperation>>
+where
+    F: Fn(
+            BinlogRowsResult,
+            &binlog::events::TableMapEvent<'static>,
+            UpstreamTimeZone,
+            &mut Vec<TableOperation>,
+        ) -> ReadySetResult<()>
+        + Send
+        + Sync
+        + 'static,
+{
+    let convert_rows = move |rows: Vec<BinlogRowsResult>,
+                             tme: &binlog::events::TableMapEvent<'static>|
+          -> ReadySetResult<Vec<TableOperation>> {
+        let mut actions = Vec::with_capacity(rows.len());
+        for row in rows {
+            convert(row, tme, time_zone, &mut actions)?;
+        }
+        Ok(actions)
+    };
+
+    if rows.len() < PARALLEL_CONVERSION_MIN_ROWS {
+        return convert_rows(rows, tme);
+    }
+
+    let tme = tme.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut chunks = vec![];
+        let mut rows = rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            chunks.push(
+                rows.by_ref()
+                    .take(PARALLEL_CONVERSION_CHUNK_SIZE)
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        // `collect` on an indexed parallel iterator keeps the chunks in order
+        let chunks = chunks
+            .into_par_iter()
+            .map(|chunk| convert_rows(chunk, &tme))
+            .collect::<ReadySetResult<Vec<_>>>()?;
+        Ok(chunks.into_iter().flatten().collect())
+    })
+    .await
+    .map_err(|e| internal_err!("Converting binlog rows panicked: {e}"))?
+}
+
+/// For each row in a WRITE_ROWS_EVENT we produce an insert of the ReadySet row representing it
+async fn binlog_rows_to_inserts(
+    rows: Vec<BinlogRowsResult>,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: UpstreamTimeZone,
+) -> ReadySetResult<Vec<TableOperation>> {
+    convert_binlog_rows(rows, tme, time_zone, |row, tme, time_zone, actions| {
+        actions.push(TableOperation::Insert(binlog_row_to_noria_row(
+            &row.map_err(unsupported_event)?
+                .1
+                .ok_or_else(|| unsupported_event("Missing data in WRITE_ROWS_EVENT"))?,
+            tme,
+            &time_zone,
+        )?));
+        Ok(())
+    })
+    .await
+}
+
+/// The kind of binlog rows event a list of table operations was converted from, which determines
+/// how its rows are converted, and labels the per-table replication metrics
+#[derive(Debug, Clone, Copy)]
+enum RowsEventKind {
+    Write,
+    Update,
+    Delete,
+}
+
+impl RowsEventKind {
+    /// The value of the `operation` label for this kind of event
+    fn operation(self) -> &'static str {
+        match self {
+            RowsEventKind::Write => "insert",
+            RowsEventKind::Update => "update",
+            RowsEventKind::Delete => "delete",
+        }
+    }
+
+    /// The number of rows in the event that `actions` were converted from
+    fn rows(self, actions: &[TableOperation]) -> usize {
+        match self {
+            RowsEventKind::Write | RowsEventKind::Delete => actions.len(),
+            // Updated rows either become a single update, or a delete of the old row followed by
+            // an insert of the new one
+            RowsEventKind::Update => actions
+                .iter()
+                .filter(|action| !matches!(action, TableOperation::DeleteRow { .. }))
+                .count(),
+        }
+    }
+}
+
+/// For each row in an UPDATE_ROWS_EVENT we produce a pair of ReadySet table operations to delete
+/// the previous entry and insert the new one
+async fn binlog_rows_to_updates(
+    rows: Vec<BinlogRowsResult>,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: UpstreamTimeZone,
+) -> ReadySetResult<Vec<TableOperation>> {
+    convert_binlog_rows(rows, tme, time_zone, |row, tme, time_zone, actions| {
+        let row = &row.map_err(unsupported_event)?;
+        actions.push(TableOperation::DeleteRow {
+            row: binlog_row_to_noria_row(
+                row.0
+                    .as_ref()
+                    .ok_or_else(|| unsupported_event("Missing before rows in UPDATE_ROWS_EVENT"))?,
+                tme,
+                &time_zone,
+            )?,
+        });
+
+        actions.push(TableOperation::Insert(binlog_row_to_noria_row(
+            row.1
+                .as_ref()
+                .ok_or_else(|| unsupported_event("Missing after rows in UPDATE_ROWS_EVENT"))?,
+            tme,
+            &time_zone,
+        )?));
+        Ok(())
+    })
+    .await
+}
+
+/// For each row in a PARTIAL_UPDATE_ROWS_EVENT we produce a pair of ReadySet table operations to
+/// delete the previous entry and insert the new one, like for an UPDATE_ROWS_EVENT.
+///
+/// With `binlog_row_value_options=PARTIAL_JSON`, the after image of a row only holds the changes
+/// made to each JSON column that was partially updated, so we apply those to the value of the
+/// column in the before image to get its new value.
+fn binlog_rows_to_partial_updates(
+    rows: impl Iterator<Item = BinlogRowsResult>,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<Vec<TableOperation>> {
+    let mut updated_rows = Vec::new();
+    for row in rows {
+        let (before, after) = row.map_err(unsupported_event)?;
+        let before = before
+            .ok_or_else(|| unsupported_event("Missing before rows in PARTIAL_UPDATE_ROWS_EVENT"))?;
+        let after = after
+            .ok_or_else(|| unsupported_event("Missing after rows in PARTIAL_UPDATE_ROWS_EVENT"))?;
+        if before.len() != after.len() {
+            return Err(unsupported_event(
+                "Mismatched before and after rows in PARTIAL_UPDATE_ROWS_EVENT",
+            ));
+        }
+
+        let old_row = binlog_row_to_noria_row(&before, tme, time_zone)?;
+        let new_row = (0..after.len())
+            .map(|idx| match after.as_ref(idx).unwrap() {
+                BinlogValue::JsonDiff(diffs) if diffs.is_empty() => Ok(old_row[idx].clone()),
+                BinlogValue::JsonDiff(diffs) => {
+                    apply_json_diffs(&before, idx, diffs).map_err(|message| {
+                        ReadySetError::ReplicationConversionError {
+                            table: tme_relation(tme),
+                            column: column_name(&after, idx, idx),
+                            message,
+                        }
+                    })
+                }
+                _ => binlog_value_to_noria_value(&after, idx, idx, tme, time_zone),
+            })
+            .collect::<ReadySetResult<Vec<_>>>()?;
+
+        updated_rows.push(TableOperation::DeleteRow { row: old_row });
+        updated_rows.push(TableOperation::Insert(new_row));
+    }
+    Ok(updated_rows)
+}
+
+/// Apply `diffs` to the value of the JSON column at `idx` in `before`, returning the new value of
+/// the column
+fn apply_json_diffs(
+    before: &BinlogRow,
+    idx: usize,
+    diffs: &[binlog::jsondiff::JsonDiff<'_>],
+) -> Result<DfValue, String> {
+    let mut doc = match before.as_ref(idx).unwrap() {
+        BinlogValue::Jsonb(val) => {
+            let json: Result<serde_json::Value, _> = val.clone().try_into();
+            json.map_err(|e| e.to_string())?
+        }
+        _ => return Err("Partial JSON update of a column without a JSON value".into()),
+    };
+    json_diff::apply_diffs(&mut doc, diffs)?;
+    Ok(DfValue::from(doc.to_string()))
+}
+
+/// For each row in a DELETE_ROWS_EVENT we produce a delete of the ReadySet row representing it
+async fn binlog_rows_to_deletes(
+    rows: Vec<BinlogRowsResult>,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: UpstreamTimeZone,
+) -> ReadySetResult<Vec<TableOperation>> {
+    convert_binlog_rows(rows, tme, time_zone, |row, tme, time_zone, actions| {
+        actions.push(TableOperation::DeleteRow {
+            row: binlog_row_to_noria_row(
+                &row.map_err(unsupported_event)?
+                    .0
+                    .ok_or_else(|| unsupported_event("Missing data in DELETE_ROWS_EVENT"))?,
+                tme,
+                &time_zone,
+            )?,
+        });
+        Ok(())
+    })
+    .await
+}
+
 fn binlog_row_to_noria_row(
     binlog_row: &BinlogRow,
     tme: &binlog::events::TableMapEvent<'static>,
-) -> mysql::Result<Vec<DfValue>> {
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<Vec<DfValue>> {
+    if binlog_row.len() as u64 != tme.columns_count() {
+        return Err(ReadySetError::ReplicationFailed(format!(
+            "Row image for {} doesn't include every column. Set binlog_row_image=FULL on the \
+             upstream database, or enable --mysql-minimal-row-image.",
+            tme_relation(tme).display_unquoted()
+        )));
+    }
     (0..binlog_row.len())
-        .map(|idx| {
-            match binlog_row.as_ref(idx).unwrap() {
-                BinlogValue::Value(val) => {
-                    let (kind, meta) = (
-                        tme.get_column_type(idx)
-                            .map_err(|e| format!("Unable to get column type {}", e))?
-                            .unwrap(),
-                        tme.get_column_metadata(idx).unwrap(),
-                    );
-                    binlog_val_to_noria_val(val, kind, meta)
-                }
-                BinlogValue::Jsonb(val) => {
-                    let json: Result<serde_json::Value, _> = val.clone().try_into(); // urgh no TryFrom impl
-                    match json {
-                        Ok(val) => Ok(DfValue::from(val.to_string())),
-                        Err(JsonbToJsonError::Opaque) => match val {
-                            jsonb::Value::Opaque(opaque_val) => {
-                                // As far as I can *tell* Opaque is just a raw JSON string, which we
-                                // can just translate into a DfValue as JSON directly without going
-                                // through serde_json::Value first.
-                                Ok(DfValue::from(opaque_val.data().as_ref()))
-                            }
-                            _ => {
-                                #[allow(clippy::unreachable)] // actually unreachable
-                                {
-                                    unreachable!("Opaque error only returned for opaque values")
-                                }
-                            }
-                        },
-                        Err(JsonbToJsonError::InvalidUtf8(err)) => Err(err.to_string().into()),
-                        Err(JsonbToJsonError::InvalidJsonb(e)) => Err(e.into()),
+        .map(|idx| binlog_value_to_noria_value(binlog_row, idx, idx, tme, time_zone))
+        .collect()
+}
+
+/// Convert a row image from a rows event logged with `binlog_row_image=MINIMAL`, which holds the
+/// values of the table columns at the indices in `columns`, to a [`PartialRow`]
+fn binlog_row_to_partial_row(
+    binlog_row: &BinlogRow,
+    columns: &[usize],
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<PartialRow> {
+    if binlog_row.len() != columns.len() {
+        return Err(unsupported_event(
+            "Row image doesn't match its columns bitmap",
+        ));
+    }
+    let mut row = vec![None; tme.columns_count() as usize];
+    for (idx, &column) in columns.iter().enumerate() {
+        *row.get_mut(column)
+            .ok_or_else(|| unsupported_event("Row image column out of range"))? = Some(
+            binlog_value_to_noria_value(binlog_row, idx, column, tme, time_zone)?,
+        );
+    }
+    Ok(row)
+}
+
+/// For each row in a WRITE_ROWS_EVENT logged with `binlog_row_image=MINIMAL`, we produce the
+/// [`PartialRow`] of its after image, which holds the values of the columns at `columns`
+fn binlog_rows_to_minimal_inserts(
+    rows: impl Iterator<Item = BinlogRowsResult>,
+    columns: &[usize],
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<Vec<PartialRow>> {
+    rows.map(|row| {
+        binlog_row_to_partial_row(
+            &row.map_err(unsupported_event)?
+                .1
+                .ok_or_else(|| unsupported_event("Missing data in WRITE_ROWS_EVENT"))?,
+            columns,
+            tme,
+            time_zone,
+        )
+    })
+    .collect()
+}
+
+/// For each row in an UPDATE_ROWS_EVENT logged with `binlog_row_image=MINIMAL`, we produce the
+/// [`PartialRow`]s of its before and after images, which hold the values of the columns at
+/// `before_columns` and `after_columns` respectively
+fn binlog_rows_to_minimal_updates(
+    rows: impl Iterator<Item = BinlogRowsResult>,
+    before_columns: &[usize],
+    after_columns: &[usize],
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<Vec<(PartialRow, PartialRow)>> {
+    rows.map(|row| {
+        let (before, after) = row.map_err(unsupported_event)?;
+        let before =
+            before.ok_or_else(|| unsupported_event("Missing before rows in UPDATE_ROWS_EVENT"))?;
+        let after =
+            after.ok_or_else(|| unsupported_event("Missing after rows in UPDATE_ROWS_EVENT"))?;
+        Ok((
+            binlog_row_to_partial_row(&before, before_columns, tme, time_zone)?,
+            binlog_row_to_partial_row(&after, after_columns, tme, time_zone)?,
+        ))
+    })
+    .collect()
+}
+
+/// For each row in a DELETE_ROWS_EVENT logged with `binlog_row_image=MINIMAL`, we produce the
+/// [`PartialRow`] of its before image, which holds the values of the columns at `columns`
+fn binlog_rows_to_minimal_deletes(
+    rows: impl Iterator<Item = BinlogRowsResult>,
+    columns: &[usize],
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<Vec<PartialRow>> {
+    rows.map(|row| {
+        binlog_row_to_partial_row(
+            &row.map_err(unsupported_event)?
+                .0
+                .ok_or_else(|| unsupported_event("Missing data in DELETE_ROWS_EVENT"))?,
+            columns,
+            tme,
+            time_zone,
+        )
+    })
+    .collect()
+}
+
+/// Convert the value at `idx` in `binlog_row`, of the column at index `column` of the table, to a
+/// ReadySet value. These are only different for row images that don't include every column.
+fn binlog_value_to_noria_value(
+    binlog_row: &BinlogRow,
+    idx: usize,
+    column: usize,
+    tme: &binlog::events::TableMapEvent<'static>,
+    time_zone: &UpstreamTimeZone,
+) -> ReadySetResult<DfValue> {
+    let value: mysql::Result<DfValue> = match binlog_row.as_ref(idx).unwrap() {
+        BinlogValue::Value(val) => {
+            let (kind, meta) = (
+                tme.get_column_type(column)
+                    .map_err(|e| format!("Unable to get column type {}", e))?
+                    .unwrap(),
+                tme.get_column_metadata(column).unwrap(),
+            );
+            binlog_val_to_noria_val(val, kind, meta, time_zone)
+        }
+        BinlogValue::Jsonb(val) => {
+            let json: Result<serde_json::Value, _> = val.clone().try_into(); // urgh no TryFrom impl
+            match json {
+                Ok(val) => Ok(DfValue::from(val.to_string())),
+                Err(JsonbToJsonError::Opaque) => match val {
+                    jsonb::Value::Opaque(opaque_val) => {
+                        // As far as I can *tell* Opaque is just a raw JSON string, which we
+                        // can just translate into a DfValue as JSON directly without going
+                        // through serde_json::Value first.
+                        Ok(DfValue::from(opaque_val.data().as_ref()))
                     }
-                }
-                _ => Err(format!("Expected a value in WRITE_ROWS_EVENT {:?}", binlog_row).into()),
+                    _ => {
+                        #[allow(clippy::unreachable)] // actually unreachable
+                        {
+                            unreachable!("Opaque error only returned for opaque values")
+                        }
+                    }
+                },
+                Err(JsonbToJsonError::InvalidUtf8(err)) => Err(err.to_string().into()),
+                Err(JsonbToJsonError::InvalidJsonb(e)) => Err(e.into()),
             }
-        })
-        .collect()
+        }
+        _ => Err(format!("Expected a value in WRITE_ROWS_EVENT {:?}", binlog_row).into()),
+    };
+    value.map_err(|e| ReadySetError::ReplicationConversionError {
+        table: tme_relation(tme),
+        column: column_name(binlog_row, idx, column),
+        message: e.to_string(),
+    })
+}
+
+/// The name of the value at `idx` in `binlog_row`, of the column at index `column` of the table, if
+/// the binlog includes column names (with `binlog_row_metadata=FULL`), or otherwise the column's
+/// 1-based position in the form `@N`, as used by `mysqlbinlog`
+fn column_name(binlog_row: &BinlogRow, idx: usize, column: usize) -> String {
+    match binlog_row.columns_ref().get(idx).map(|c| c.name_str()) {
+        Some(name) if !name.is_empty() => name.into_owned(),
+        _ => format!("@{}", column + 1),
+    }
+}
+
+/// Construct the error returned when we read an event from the binlog stream that we can't
+/// decode, or that we don't know how to handle. [`MySqlBinlogConnector::next_action`] requests the
+/// binlog again after these, in case the event was corrupted in transit.
+fn unsupported_event<E: Display>(error: E) -> ReadySetError {
+    ReadySetError::ReplicationUnsupportedEvent(error.to_string())
+}
+
+/// Construct the error returned when a rows event refers to a table we haven't seen a
+/// TABLE_MAP_EVENT for
+fn tme_not_found(event: &str, table_id: u64) -> ReadySetError {
+    ReadySetError::ReplicationTableMapNotFound {
+        event: event.to_owned(),
+        table_id,
+    }
 }
 
 #[async_trait]
 impl Connector for MySqlBinlogConnector {
+    fn lag(&self) -> Option<Duration> {
+        self.lag
+    }
+
+    fn in_transaction(&self) -> bool {
+        // Once a transaction's remaining actions are returned, it's ended
+        self.transaction.is_some()
+    }
+
     async fn next_action(
         &mut self,
-        _: &ReplicationOffset,
+        last_pos: &ReplicationOffset,
         until: Option<&ReplicationOffset>,
     ) -> ReadySetResult<(ReplicationAction, ReplicationOffset)> {
-        let (action, pos) = self.next_action_inner(until).await?;
-        Ok((action, pos.try_into()?))
+        loop {
+            match self.next_action_inner(until).await {
+                Ok((action, pos)) => {
+                    self.consecutive_invalid_events = 0;
+                    return Ok((action, pos.try_into()?));
+                }
+                Err(error @ ReadySetError::ReplicationUnsupportedEvent(_))
+                    if self.consecutive_invalid_events < MAX_CONSECUTIVE_INVALID_EVENTS =>
+                {
+                    // The event may have been corrupted in transit, so request the binlog again
+                    // from the position of the last action we returned
+                    self.consecutive_invalid_events += 1;
+                    counter!(recorded::REPLICATOR_INVALID_EVENT, 1u64);
+                    warn!(
+                        %error,
+                        attempt = self.consecutive_invalid_events,
+                        "Read invalid event from binlog stream, requesting binlog again"
+                    );
+                    self.reconnect(last_pos).await?;
+                }
+                Err(error @ ReadySetError::ReplicationConnectionLost(_))
+                    if !self.reconnect_timeout.is_zero() =>
+                {
+                    // Resume from the position of the last action we returned, since the
+                    // replicator hasn't seen anything we read after that
+                    warn!(%error, "Lost connection to binlog stream, reconnecting");
+                    self.reconnect(last_pos).await?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn disconnect(&mut self, _last_pos: &ReplicationOffset) -> ReadySetResult<()> {
+        let BinlogSource::Server { opts, connection } = &self.source else {
+            return Ok(());
+        };
+        // The primary's binlog dump thread doesn't read from our connection while it sends us
+        // events, so it wouldn't see a COM_QUIT until it next failed to send us one. Kill it from
+        // another connection instead, so that we stop being registered as a replica straight away
+        // and can register again with the same server ID as soon as we restart.
+        let mut conn = mysql::Conn::new(opts.clone()).await.map_err(mysql_error)?;
+        conn.query_drop(format!("KILL CONNECTION {}", connection.id()))
+            .await
+            .map_err(mysql_error)?;
+        conn.disconnect().await.map_err(mysql_error)?;
+        info!("Disconnected from binlog stream");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    /// The length of the header of every (v4) binlog event
+    const EVENT_HEADER_LEN: usize = 19;
+
+    /// The ID of the table in [`table_map`]
+    const TABLE_ID: u64 = 42;
+
+    /// The length of the post-header of each event type from `START_EVENT_V3` to
+    /// `HEARTBEAT_EVENT`, as written in format description events by MySQL 5.5
+    const MYSQL_5_5_POST_HEADER_LENGTHS: [u8; 27] = [
+        56, 13, 0, 8, 0, 18, 0, 4, 4, 4, 4, 18, 0, 0, 84, 0, 4, 26, 8, 0, 0, 0, 8, 8, 8, 2, 0,
+    ];
+
+    /// Write the given events, each given as its type and its data after the header, to the binlog
+    /// file `binlog.000001` in `dir`, laid out as MySQL 5.5 writes them. Returns the position of
+    /// the end of each event.
+    async fn write_binlog(dir: &Path, events: &[(EventType, Vec<u8>)]) -> Vec<u32> {
+        let mut contents = vec![0xfe, b'b', b'i', b'n'];
+        let mut positions = vec![];
+        for (event_type, data) in events {
+            let event_size = (EVENT_HEADER_LEN + data.len()) as u32;
+            let log_pos = contents.len() as u32 + event_size;
+            contents.extend(0u32.to_le_bytes()); // timestamp
+            contents.push(*event_type as u8);
+            contents.extend(1u32.to_le_bytes()); // server ID
+            contents.extend(event_size.to_le_bytes());
+            contents.extend(log_pos.to_le_bytes());
+            contents.extend(0u16.to_le_bytes()); // flags
+            contents.extend(data);
+            positions.push(log_pos);
+        }
+        tokio::fs::write(dir.join("binlog.000001"), contents)
+            .await
+            .unwrap();
+        positions
+    }
+
+    /// Replay the binlog file written by [`write_binlog`] to `dir`, from `position`
+    async fn replay(dir: &Path, position: u32) -> MySqlBinlogConnector {
+        MySqlBinlogConnector::replay_files(
+            dir.to_owned(),
+            BinlogPosition {
+                binlog_file: "binlog.000001".into(),
+                position,
+                gtid_set: None,
+            },
+            TableFilter::for_all_tables(),
+        )
+        .await
+        .unwrap()
+    }
+
+    /// A format description event as written by MySQL 5.5, which doesn't checksum events
+    fn format_description() -> (EventType, Vec<u8>) {
+        let mut data = 4u16.to_le_bytes().to_vec(); // binlog version
+        let mut server_version = b"5.5.62-log".to_vec();
+        server_version.resize(50, 0);
+        data.extend(server_version);
+        data.extend(0u32.to_le_bytes()); // create timestamp
+        data.push(EVENT_HEADER_LEN as u8);
+        data.extend(MYSQL_5_5_POST_HEADER_LENGTHS);
+        (EventType::FORMAT_DESCRIPTION_EVENT, data)
+    }
+
+    /// A table map event for `db.t`, which has the columns `id INT NOT NULL` and `x INT`
+    fn table_map() -> (EventType, Vec<u8>) {
+        let mut data = TABLE_ID.to_le_bytes()[..6].to_vec();
+        data.extend(1u16.to_le_bytes()); // flags
+        data.extend([2, b'd', b'b', 0]);
+        data.extend([1, b't', 0]);
+        data.push(2); // column count
+        data.extend([3, 3]); // MYSQL_TYPE_LONG
+        data.push(0); // metadata length
+        data.push(0b10); // nullable columns
+        (EventType::TABLE_MAP_EVENT, data)
+    }
+
+    /// A row image of `db.t` in a rows event
+    fn row(id: i32, x: Option<i32>) -> Vec<u8> {
+        let mut data = vec![if x.is_none() { 0b10 } else { 0 }]; // null columns
+        data.extend(id.to_le_bytes());
+        if let Some(x) = x {
+            data.extend(x.to_le_bytes());
+        }
+        data
+    }
+
+    /// A V1 rows event of the given type for `db.t`, with the given row images, which include
+    /// every column
+    fn rows_event_v1(event_type: EventType, rows: &[Vec<u8>]) -> (EventType, Vec<u8>) {
+        let mut data = TABLE_ID.to_le_bytes()[..6].to_vec();
+        data.extend(1u16.to_le_bytes()); // STMT_END_F
+        data.push(2); // column count
+        data.push(0b11); // columns in the (before) row images
+        if matches!(event_type, EventType::UPDATE_ROWS_EVENT_V1) {
+            data.push(0b11); // columns in the after row images
+        }
+        data.extend(rows.concat());
+        (event_type, data)
+    }
+
+    #[tokio::test]
+    async fn v1_rows_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let positions = write_binlog(
+            dir.path(),
+            &[
+                format_description(),
+                table_map(),
+                rows_event_v1(
+                    EventType::WRITE_ROWS_EVENT_V1,
+                    &[row(1, Some(10)), row(2, None)],
+                ),
+                table_map(),
+                rows_event_v1(
+                    EventType::UPDATE_ROWS_EVENT_V1,
+                    &[row(1, Some(10)), row(1, Some(11))],
+                ),
+                table_map(),
+                rows_event_v1(EventType::DELETE_ROWS_EVENT_V1, &[row(2, None)]),
+            ],
+        )
+        .await;
+        let mut connector = replay(dir.path(), positions[0]).await;
+
+        let expected = [
+            (
+                positions[2],
+                vec![
+                    TableOperation::Insert(vec![1.into(), 10.into()]),
+                    TableOperation::Insert(vec![2.into(), DfValue::None]),
+                ],
+            ),
+            (
+                positions[4],
+                vec![
+                    TableOperation::DeleteRow {
+                        row: vec![1.into(), 10.into()],
+                    },
+                    TableOperation::Insert(vec![1.into(), 11.into()]),
+                ],
+            ),
+            (
+                positions[6],
+                vec![TableOperation::DeleteRow {
+                    row: vec![2.into(), DfValue::None],
+                }],
+            ),
+        ];
+        for (position, expected_actions) in expected {
+            let (action, pos) = connector.next_action_inner(None).await.unwrap();
+            assert_eq!(pos.position, position);
+            match action {
+                ReplicationAction::TableAction { table, actions, .. } => {
+                    assert_eq!(
+                        table,
+                        Relation {
+                            schema: Some("db".into()),
+                            name: "t".into(),
+                        }
+                    );
+                    assert_eq!(actions, expected_actions);
+                }
+                action => panic!("Unexpected action {action:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn incident_requires_resnapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut incident = 1u16.to_le_bytes().to_vec(); // INCIDENT_LOST_EVENTS
+        incident.push(11);
+        incident.extend(b"LOST_EVENTS");
+        let positions = write_binlog(
+            dir.path(),
+            &[format_description(), (EventType::INCIDENT_EVENT, incident)],
+        )
+        .await;
+
+        let mut connector = replay(dir.path(), positions[0]).await;
+        let (action, pos) = connector.next_action_inner(None).await.unwrap();
+        assert_eq!(pos.position, positions[1]);
+        match action {
+            ReplicationAction::ResnapshotRequired { reason } => {
+                assert!(reason.contains("LOST_EVENTS"), "{reason}")
+            }
+            action => panic!("Unexpected action {action:?}"),
+        }
+
+        // Once every table has been snapshotted again, replication resumes after the incident
+        let mut connector = replay(dir.path(), positions[1]).await;
+        let (action, _) = connector.next_action_inner(None).await.unwrap();
+        assert!(
+            matches!(action, ReplicationAction::Heartbeat),
+            "Unexpected action {action:?}"
+        );
     }
 }
@@ -0,0 +1,712 @@
+//! Snapshotting tables from a logical dump taken with `mysqldump`, instead of by reading them from
+//! the upstream database, per [`UpstreamConfig::mysql_snapshot_dump`].
+//!
+//! The dump must have been taken with `--source-data` (or `--master-data` on older versions), so
+//! that it records the binlog coordinates it's consistent with, which we start replicating from
+//! once the tables have been loaded. If it was taken with GTIDs enabled, the executed GTID set it
+//! records (as `GTID_PURGED`) is kept as well. Each table in the dump is snapshotted from the dump;
+//! any other table is snapshotted from the upstream database as usual.
+//!
+//! Only the rows in the dump are used: the schema is always loaded from the upstream database, so
+//! the tables mustn't have been altered since the dump was taken. Binary values should be dumped
+//! with `--hex-blob`.
+//!
+//! `TIMESTAMP` values are dumped in the time zone the dump sets (UTC, unless it was taken with
+//! `--skip-tz-utc`), so they're converted to the upstream's time zone, which is the one they're
+//! given in when replicated from the binlog.
+//!
+//! [`UpstreamConfig::mysql_snapshot_dump`]: database_utils::UpstreamConfig::mysql_snapshot_dump
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use bit_vec::BitVec;
+use chrono::NaiveDateTime;
+use nom_sql::Relation;
+use readyset_data::{DfType, DfValue};
+use readyset_errors::{ReadySetError, ReadySetResult};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader, SeekFrom, Take};
+
+use super::{BinlogPosition, GtidSet};
+use crate::time_zone::UpstreamTimeZone;
+
+fn dump_file_error(path: &Path, error: impl std::fmt::Display) -> ReadySetError {
+    ReadySetError::ReplicationFailed(format!(
+        "Failed to read dump file {}: {error}",
+        path.display()
+    ))
+}
+
+/// A `mysqldump` file, indexed by the tables whose rows it contains
+pub(crate) struct DumpFile {
+    path: PathBuf,
+    position: BinlogPosition,
+    /// The range of bytes of the file holding the `INSERT` statements for each table
+    tables: HashMap<Relation, Range<u64>>,
+    /// How to convert the `TIMESTAMP` values in the dump, if they were dumped in a different time
+    /// zone than the upstream's
+    timestamps: Option<TimestampConversion>,
+}
+
+impl DumpFile {
+    /// Open the dump file at `path`, reading it through once to find the binlog position it was
+    /// taken at and where each table's rows are. `TIMESTAMP` values are converted to
+    /// `upstream_time_zone`, the time zone of the upstream's sessions.
+    pub(crate) async fn open(
+        path: PathBuf,
+        upstream_time_zone: UpstreamTimeZone,
+    ) -> ReadySetResult<Self> {
+        let mut reader = BufReader::new(
+            File::open(&path)
+                .await
+                .map_err(|e| dump_file_error(&path, e))?,
+        );
+
+        let mut binlog_file = None;
+        let mut gtid_set = None;
+        let mut dump_time_zone = None;
+        let mut database: Option<String> = None;
+        let mut tables = HashMap::new();
+        let mut offset = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let len = reader
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(|e| dump_file_error(&path, e))?;
+            if len == 0 {
+                break;
+            }
+            let start = offset;
+            offset += len as u64;
+
+            if let Some(rest) = line.strip_prefix(b"INSERT INTO ") {
+                let name = Input::new(rest)
+                    .identifier()
+                    .ok_or_else(|| dump_file_error(&path, "Malformed INSERT statement"))?;
+                let table = qualify(&path, &database, name)?;
+                tables
+                    .entry(table)
+                    .and_modify(|range: &mut Range<u64>| range.end = offset)
+                    .or_insert(start..offset);
+            } else if let Some(rest) = line.strip_prefix(b"CREATE TABLE ") {
+                // Tables without any rows are still snapshotted from the dump
+                if let Some(name) = Input::new(rest).identifier() {
+                    tables.insert(qualify(&path, &database, name)?, offset..offset);
+                }
+            } else if let Some(rest) = line.strip_prefix(b"USE ") {
+                database = Input::new(rest).identifier();
+            } else if let Ok(line) = std::str::from_utf8(&line) {
+                let line = line.trim_start_matches("-- ").trim();
+                if let Some((_, name)) = line
+                    .strip_prefix("Host: ")
+                    .and_then(|host| host.split_once("Database: "))
+                {
+                    database = Some(name.trim().to_owned());
+                } else if line.starts_with("CHANGE MASTER TO")
+                    || line.starts_with("CHANGE REPLICATION SOURCE TO")
+                {
+                    binlog_file = Some(parse_binlog_coordinates(line).ok_or_else(|| {
+                        dump_file_error(&path, format!("Malformed binlog coordinates: {line}"))
+                    })?);
+                } else if let Some((_, rest)) = line
+                    .split_once("SET TIME_ZONE='")
+                    .filter(|_| dump_time_zone.is_none())
+                {
+                    // The time zone is set once at the start of the dump, and restored at the end
+                    let time_zone = rest
+                        .split_once('\'')
+                        .map_or(rest, |(time_zone, _)| time_zone);
+                    dump_time_zone = Some(UpstreamTimeZone::parse(time_zone).ok_or_else(|| {
+                        dump_file_error(&path, format!("Unknown time zone: {time_zone}"))
+                    })?);
+                } else if let Some(rest) = line.strip_prefix("SET @@GLOBAL.GTID_PURGED=") {
+                    // Long GTID sets are split across several lines
+                    let mut gtids = rest.to_owned();
+                    while !gtids.trim_end().ends_with(';') {
+                        let mut next = String::new();
+                        let len = reader
+                            .read_line(&mut next)
+                            .await
+                            .map_err(|e| dump_file_error(&path, e))?;
+                        if len == 0 {
+                            break;
+                        }
+                        offset += len as u64;
+                        gtids.push_str(&next);
+                    }
+                    gtid_set = parse_gtid_purged(&gtids)
+                        .map_err(|e| dump_file_error(&path, format!("Malformed GTID set: {e}")))?;
+                }
+            }
+        }
+
+        let Some((binlog_file, position)) = binlog_file else {
+            return Err(dump_file_error(
+                &path,
+                "The dump doesn't record its binlog coordinates; take it with --source-data",
+            ));
+        };
+        Ok(Self {
+            path,
+            position: BinlogPosition {
+                binlog_file,
+                position,
+                gtid_set,
+            },
+            tables,
+            timestamps: dump_time_zone
+                .filter(|dump| *dump != upstream_time_zone)
+                .map(|dump| TimestampConversion {
+                    dump,
+                    upstream: upstream_time_zone,
+                }),
+        })
+    }
+
+    /// The binlog position the dump is consistent with
+    pub(crate) fn position(&self) -> &BinlogPosition {
+        &self.position
+    }
+
+    /// Returns true if the dump contains the rows of `table`
+    pub(crate) fn contains(&self, table: &Relation) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    /// Read the rows of `table` from the dump, as values for the given `columns` of the table
+    /// (each with the type it should be coerced to), in order
+    pub(crate) async fn rows(
+        &self,
+        table: &Relation,
+        columns: Vec<(String, DfType)>,
+    ) -> ReadySetResult<DumpRows> {
+        let range = self.tables.get(table).cloned().unwrap_or_default();
+        let mut file = File::open(&self.path)
+            .await
+            .map_err(|e| dump_file_error(&self.path, e))?;
+        file.seek(SeekFrom::Start(range.start))
+            .await
+            .map_err(|e| dump_file_error(&self.path, e))?;
+        Ok(DumpRows {
+            path: self.path.clone(),
+            reader: BufReader::new(file.take(range.end - range.start)),
+            columns,
+            timestamps: self.timestamps,
+            line: Vec::new(),
+            rows: VecDeque::new(),
+        })
+    }
+}
+
+/// Qualify a table `name` with the database the dump is in at that point
+fn qualify(path: &Path, database: &Option<String>, name: String) -> ReadySetResult<Relation> {
+    let Some(database) = database else {
+        return Err(dump_file_error(
+            path,
+            format!("Could not tell which database table `{name}` is in"),
+        ));
+    };
+    Ok(Relation {
+        schema: Some(database.as_str().into()),
+        name: name.into(),
+    })
+}
+
+/// Parse the binlog file and position out of a `CHANGE MASTER TO` or `CHANGE REPLICATION SOURCE
+/// TO` statement
+fn parse_binlog_coordinates(statement: &str) -> Option<(String, u32)> {
+    let option = |names: [&str; 2]| {
+        statement
+            .split(|c| c == ',' || c == ';')
+            .filter_map(|option| option.split_once('='))
+            .find(|(name, _)| names.iter().any(|n| name.trim().ends_with(n)))
+            .map(|(_, value)| value.trim())
+    };
+    let file = option(["MASTER_LOG_FILE", "SOURCE_LOG_FILE"])?;
+    let position = option(["MASTER_LOG_POS", "SOURCE_LOG_POS"])?;
+    Some((
+        file.strip_prefix('\'')?.strip_suffix('\'')?.to_owned(),
+        position.parse().ok()?,
+    ))
+}
+
+/// Parse the GTID set from the value assigned to `GTID_PURGED`, which is quoted, possibly preceded
+/// by a versioned comment with a `+` in it, and split across lines
+fn parse_gtid_purged(value: &str) -> ReadySetResult<Option<GtidSet>> {
+    let gtids = value
+        .rsplit_once("*/")
+        .map_or(value, |(_, gtids)| gtids)
+        .trim()
+        .trim_end_matches(';')
+        .trim_matches('\'')
+        .split_whitespace()
+        .collect::<String>();
+    GtidSet::from_executed(&gtids)
+}
+
+/// Converts `TIMESTAMP` values from the time zone they were dumped in to the upstream's
+#[derive(Debug, Clone, Copy)]
+struct TimestampConversion {
+    dump: UpstreamTimeZone,
+    upstream: UpstreamTimeZone,
+}
+
+impl TimestampConversion {
+    /// Convert `value` if it's a date and time, leaving anything else (such as MySQL's zero
+    /// date) as it is
+    fn convert(&self, value: DfValue) -> DfValue {
+        let converted = <&str>::try_from(&value).ok().and_then(|value| {
+            let local = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+            Some(self.upstream.localize(&self.dump.to_utc(&local)?))
+        });
+        match converted {
+            Some(datetime) => DfValue::from(datetime),
+            None => value,
+        }
+    }
+}
+
+/// The rows of a single table in a dump file
+pub(crate) struct DumpRows {
+    path: PathBuf,
+    reader: BufReader<Take<File>>,
+    columns: Vec<(String, DfType)>,
+    timestamps: Option<TimestampConversion>,
+    line: Vec<u8>,
+    /// Rows already read from the current `INSERT` statement
+    rows: VecDeque<Vec<DfValue>>,
+}
+
+impl DumpRows {
+    /// Returns the next row of the table, or `None` once every row has been read
+    pub(crate) async fn next(&mut self) -> ReadySetResult<Option<Vec<DfValue>>> {
+        while self.rows.is_empty() {
+            self.line.clear();
+            let len = self
+                .reader
+                .read_until(b'\n', &mut self.line)
+                .await
+                .map_err(|e| dump_file_error(&self.path, e))?;
+            if len == 0 {
+                return Ok(None);
+            }
+            if let Some(rest) = self.line.strip_prefix(b"INSERT INTO ") {
+                self.rows = parse_insert(rest, &self.columns, self.timestamps)
+                    .map_err(|e| dump_file_error(&self.path, e))?
+                    .into();
+            }
+        }
+        Ok(self.rows.pop_front())
+    }
+}
+
+/// Parse the rows inserted by an `INSERT` statement written by `mysqldump`, starting after `INSERT
+/// INTO`, returning values for each of `columns` coerced to the column's type, with `TIMESTAMP`
+/// values converted by `timestamps` if given
+fn parse_insert(
+    statement: &[u8],
+    columns: &[(String, DfType)],
+    timestamps: Option<TimestampConversion>,
+) -> Result<Vec<Vec<DfValue>>, String> {
+    let malformed = || "Malformed INSERT statement".to_owned();
+    let mut input = Input::new(statement);
+    input.identifier().ok_or_else(malformed)?;
+
+    // With `--complete-insert`, statements name the columns they insert into, which may not be in
+    // the same order as the table's columns
+    let (positions, num_values) = if input.consume(b"(") {
+        let mut names = Vec::new();
+        loop {
+            names.push(input.identifier().ok_or_else(malformed)?);
+            if !input.consume(b",") {
+                break;
+            }
+        }
+        if !input.consume(b")") {
+            return Err(malformed());
+        }
+        let positions = columns
+            .iter()
+            .map(|(column, _)| {
+                names
+                    .iter()
+                    .position(|name| name == column)
+                    .ok_or_else(|| format!("Column {column} is missing from INSERT statement"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        (positions, names.len())
+    } else {
+        ((0..columns.len()).collect(), columns.len())
+    };
+
+    if !input.consume(b"VALUES") {
+        return Err(malformed());
+    }
+    let mut rows = Vec::new();
+    loop {
+        if !input.consume(b"(") {
+            return Err(malformed());
+        }
+        let mut values = Vec::with_capacity(columns.len());
+        loop {
+            values.push(input.value().ok_or_else(malformed)?);
+            if !input.consume(b",") {
+                break;
+            }
+        }
+        if !input.consume(b")") {
+            return Err(malformed());
+        }
+        // A different number of values means the table was altered since the dump was taken
+        if values.len() != num_values {
+            return Err(format!(
+                "Expected {num_values} values in each row, but found {}",
+                values.len()
+            ));
+        }
+
+        let row = positions
+            .iter()
+            .zip(columns)
+            .map(|(&position, (column, ty))| {
+                let mut value = values[position].clone();
+                if let (Some(timestamps), DfType::Timestamp { .. }) = (timestamps, ty) {
+                    value = timestamps.convert(value);
+                }
+                // Values in the dump are only typed as far as the SQL literal they're written as,
+                // so dates and decimals, for example, are read as text
+                value
+                    .coerce_to(ty, &DfType::Unknown)
+                    .and_then(|mut value| {
+                        value.maybe_coerce_for_table_op(ty)?;
+                        Ok(value)
+                    })
+                    .map_err(|e| format!("Invalid value for column {column}: {e}"))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        rows.push(row);
+
+        if !input.consume(b",") {
+            break;
+        }
+    }
+    Ok(rows)
+}
+
+/// The remainder of a statement in a dump file, parsed as it's needed
+struct Input<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn new(rest: &'a [u8]) -> Self {
+        Self { rest }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some((c, rest)) = self.rest.split_first() {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.rest = rest;
+        }
+    }
+
+    /// Consume `token` if it's next, returning whether it was
+    fn consume(&mut self, token: &[u8]) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consume a backtick-quoted identifier
+    fn identifier(&mut self) -> Option<String> {
+        if !self.consume(b"`") {
+            return None;
+        }
+        let mut identifier = Vec::new();
+        loop {
+            let (&c, rest) = self.rest.split_first()?;
+            self.rest = rest;
+            if c == b'`' {
+                if !self.rest.starts_with(b"`") {
+                    return String::from_utf8(identifier).ok();
+                }
+                self.rest = &self.rest[1..];
+            }
+            identifier.push(c);
+        }
+    }
+
+    /// Consume the contents of a single-quoted string, with the escapes `mysqldump` uses, after
+    /// the opening quote
+    fn string(&mut self) -> Option<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            let (&c, rest) = self.rest.split_first()?;
+            self.rest = rest;
+            match c {
+                b'\'' if self.rest.starts_with(b"'") => {
+                    self.rest = &self.rest[1..];
+                    bytes.push(b'\'');
+                }
+                b'\'' => return Some(bytes),
+                b'\\' => {
+                    let (&escaped, rest) = self.rest.split_first()?;
+                    self.rest = rest;
+                    bytes.push(match escaped {
+                        b'0' => 0,
+                        b'b' => 0x08,
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'Z' => 0x1a,
+                        c => c,
+                    });
+                }
+                c => bytes.push(c),
+            }
+        }
+    }
+
+    /// Consume a single value in a row
+    fn value(&mut self) -> Option<DfValue> {
+        self.skip_whitespace();
+        if self.consume(b"NULL") {
+            Some(DfValue::None)
+        } else if self.consume(b"'") {
+            let bytes = self.string()?;
+            Some(match String::from_utf8(bytes) {
+                Ok(s) => DfValue::from(s),
+                Err(e) => DfValue::ByteArray(e.into_bytes().into()),
+            })
+        } else if self.consume(b"_binary") {
+            if !self.consume(b"'") {
+                return None;
+            }
+            Some(DfValue::ByteArray(self.string()?.into()))
+        } else if self.consume(b"0x") {
+            let len = self
+                .rest
+                .iter()
+                .position(|c| !c.is_ascii_hexdigit())
+                .unwrap_or(self.rest.len());
+            let bytes = hex::decode(&self.rest[..len]).ok()?;
+            self.rest = &self.rest[len..];
+            Some(DfValue::ByteArray(bytes.into()))
+        } else if self.consume(b"b'") {
+            let len = self.rest.iter().position(|&c| c == b'\'')?;
+            let bits = BitVec::from_iter(self.rest[..len].iter().map(|&c| c == b'1'));
+            self.rest = &self.rest[len + 1..];
+            Some(DfValue::from(bits))
+        } else {
+            let len = self
+                .rest
+                .iter()
+                .position(|c| !(c.is_ascii_digit() || b"+-.eE".contains(c)))
+                .unwrap_or(self.rest.len());
+            let number = std::str::from_utf8(&self.rest[..len]).ok()?;
+            if number.is_empty() {
+                return None;
+            }
+            self.rest = &self.rest[len..];
+            // Decimals are kept as text, so that coercing them to the column's type doesn't lose
+            // any precision
+            Some(if let Ok(i) = number.parse::<i64>() {
+                DfValue::Int(i)
+            } else if let Ok(i) = number.parse::<u64>() {
+                DfValue::UnsignedInt(i)
+            } else {
+                DfValue::from(number)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const DUMP: &str = r#"-- MySQL dump 10.13  Distrib 8.0.32, for Linux (x86_64)
+--
+-- Host: 127.0.0.1    Database: db1
+-- ------------------------------------------------------
+-- Server version	8.0.32
+SET @@GLOBAL.GTID_PURGED=/*!80000 '+'*/ '3e11fa47-71ca-11e1-9e33-c80aa9429562:1-5,
+3e11fa47-71ca-11e1-9e33-c80aa9429563:1-3';
+
+--
+-- Position to start replication or point-in-time recovery from
+--
+
+-- CHANGE REPLICATION SOURCE TO SOURCE_LOG_FILE='binlog.000003', SOURCE_LOG_POS=157;
+
+--
+-- Table structure for table `t1`
+--
+
+DROP TABLE IF EXISTS `t1`;
+CREATE TABLE `t1` (
+  `id` int NOT NULL,
+  `name` text,
+  PRIMARY KEY (`id`)
+) ENGINE=InnoDB;
+
+LOCK TABLES `t1` WRITE;
+INSERT INTO `t1` VALUES (1,'it\'s'),(2,NULL);
+INSERT INTO `t1` (`name`, `id`) VALUES ('three',3);
+UNLOCK TABLES;
+
+CREATE TABLE `empty` (
+  `id` int NOT NULL
+) ENGINE=InnoDB;
+"#;
+
+    fn table(name: &str) -> Relation {
+        Relation {
+            schema: Some("db1".into()),
+            name: name.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn read_dump_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(DUMP.as_bytes()).unwrap();
+        let dump = DumpFile::open(file.path().to_owned(), UpstreamTimeZone::default())
+            .await
+            .unwrap();
+
+        assert_eq!(dump.position().binlog_file, "binlog.000003");
+        assert_eq!(dump.position().position, 157);
+        assert!(dump.position().gtid_set.is_some());
+        assert!(dump.contains(&table("t1")));
+        assert!(dump.contains(&table("empty")));
+        assert!(!dump.contains(&table("t2")));
+
+        let columns = vec![
+            ("id".to_owned(), DfType::Int),
+            ("name".to_owned(), DfType::DEFAULT_TEXT),
+        ];
+        let mut rows = dump.rows(&table("t1"), columns.clone()).await.unwrap();
+        let mut all = vec![];
+        while let Some(row) = rows.next().await.unwrap() {
+            all.push(row);
+        }
+        assert_eq!(
+            all,
+            vec![
+                vec![DfValue::from(1), DfValue::from("it's")],
+                vec![DfValue::from(2), DfValue::None],
+                vec![DfValue::from(3), DfValue::from("three")],
+            ]
+        );
+
+        let mut rows = dump.rows(&table("empty"), columns).await.unwrap();
+        assert_eq!(rows.next().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn timestamps_are_converted_to_upstream_time_zone() {
+        let dump = r#"-- Host: 127.0.0.1    Database: db1
+/*!40103 SET @OLD_TIME_ZONE=@@TIME_ZONE */;
+/*!40103 SET TIME_ZONE='+00:00' */;
+-- CHANGE MASTER TO MASTER_LOG_FILE='binlog.000003', MASTER_LOG_POS=157;
+INSERT INTO `t` VALUES ('2023-01-01 12:00:00','2023-01-01 12:00:00'),(NULL,NULL);
+/*!40103 SET TIME_ZONE=@OLD_TIME_ZONE */;
+"#;
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(dump.as_bytes()).unwrap();
+        let upstream_time_zone = UpstreamTimeZone::parse("-04:00").unwrap();
+        let dump = DumpFile::open(file.path().to_owned(), upstream_time_zone)
+            .await
+            .unwrap();
+
+        let timestamp = DfType::Timestamp {
+            subsecond_digits: 0,
+        };
+        let datetime = DfType::DateTime {
+            subsecond_digits: 0,
+        };
+        let columns = vec![
+            ("ts".to_owned(), timestamp.clone()),
+            ("dt".to_owned(), datetime.clone()),
+        ];
+        let mut rows = dump.rows(&table("t"), columns).await.unwrap();
+        let at = |hour, ty: &DfType| {
+            DfValue::from(chrono::NaiveDate::from_ymd(2023, 1, 1).and_hms(hour, 0, 0))
+                .coerce_to(ty, &DfType::Unknown)
+                .unwrap()
+        };
+        // Only the TIMESTAMP is in UTC in the dump, and is replicated in the upstream's time zone
+        assert_eq!(
+            rows.next().await.unwrap(),
+            Some(vec![at(8, &timestamp), at(12, &datetime)])
+        );
+        assert_eq!(
+            rows.next().await.unwrap(),
+            Some(vec![DfValue::None, DfValue::None])
+        );
+        assert_eq!(rows.next().await.unwrap(), None);
+    }
+
+    #[test]
+    fn parse_values() {
+        let columns = vec![
+            ("a".to_owned(), DfType::BigInt),
+            ("b".to_owned(), DfType::Unknown),
+            ("c".to_owned(), DfType::Unknown),
+            ("d".to_owned(), DfType::Unknown),
+        ];
+        let rows = parse_insert(
+            br"`t` VALUES (-5,0x00ff,_binary 'a\0b','1.50'),(9,'\n','',2.5e3);",
+            &columns,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec![
+                    DfValue::from(-5),
+                    DfValue::ByteArray(vec![0, 255].into()),
+                    DfValue::ByteArray(b"a\0b".to_vec().into()),
+                    DfValue::from("1.50"),
+                ],
+                vec![
+                    DfValue::from(9),
+                    DfValue::from("\n"),
+                    DfValue::from(""),
+                    DfValue::from("2.5e3"),
+                ],
+            ]
+        );
+        assert!(parse_insert(b"`t` VALUES (1,'unterminated);", &columns, None).is_err());
+        // Rows with too few or too many values
+        assert!(parse_insert(b"`t` VALUES (1,2,3);", &columns, None).is_err());
+        assert!(parse_insert(b"`t` VALUES (1,2,3,4,5);", &columns, None).is_err());
+        assert!(parse_insert(b"`t` (`a`,`b`,`c`,`d`) VALUES (1,2,3);", &columns, None).is_err());
+    }
+
+    #[test]
+    fn binlog_coordinates() {
+        assert_eq!(
+            parse_binlog_coordinates(
+                "CHANGE MASTER TO MASTER_LOG_FILE='mysql-bin.000012', MASTER_LOG_POS=4;"
+            ),
+            Some(("mysql-bin.000012".to_owned(), 4))
+        );
+        assert_eq!(parse_binlog_coordinates("CHANGE MASTER TO;"), None);
+    }
+}
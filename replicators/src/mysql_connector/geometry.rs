@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use readyset_data::DfValue;
+
+/// The WKB type codes of the geometries a spatial value can hold, as described in
+/// <https://dev.mysql.com/doc/refman/8.0/en/gis-data-formats.html#gis-wkb-format>
+mod wkb_type {
+    pub(super) const POINT: u32 = 1;
+    pub(super) const LINE_STRING: u32 = 2;
+    pub(super) const POLYGON: u32 = 3;
+    pub(super) const MULTI_POINT: u32 = 4;
+    pub(super) const MULTI_LINE_STRING: u32 = 5;
+    pub(super) const MULTI_POLYGON: u32 = 6;
+    pub(super) const GEOMETRY_COLLECTION: u32 = 7;
+}
+
+/// The size in bytes of a WKB point's coordinates
+const POINT_LEN: usize = 16;
+
+/// Reads the parts of a WKB value, in the byte order given by the value's header
+struct WkbReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if self.data.len() < len {
+            return Err("Truncated geometry value".into());
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(if self.little_endian {
+            u32::from_le_bytes(buf)
+        } else {
+            u32::from_be_bytes(buf)
+        })
+    }
+
+    /// Read the byte order and type code that start every (possibly nested) geometry
+    fn read_header(&mut self) -> Result<u32, String> {
+        self.little_endian = match self.take(1)?[0] {
+            0 => false,
+            1 => true,
+            b => return Err(format!("Invalid geometry byte order {b}")),
+        };
+        self.read_u32()
+    }
+
+    /// Skip over `count` sequences of points, each prefixed with its number of points
+    fn skip_point_lists(&mut self, count: u32) -> Result<(), String> {
+        for _ in 0..count {
+            let points = self.read_u32()? as usize;
+            self.take(points.saturating_mul(POINT_LEN))?;
+        }
+        Ok(())
+    }
+
+    /// Skip over a whole geometry, checking that it's well-formed. If `element_type` is given, the
+    /// geometry is an element of a multi-geometry and must be of that type.
+    fn skip_geometry(&mut self, element_type: Option<u32>) -> Result<(), String> {
+        let ty = self.read_header()?;
+        if element_type.map_or(false, |element_type| element_type != ty) {
+            return Err(format!("Unexpected geometry type {ty} in multi-geometry"));
+        }
+        match ty {
+            wkb_type::POINT => {
+                self.take(POINT_LEN)?;
+            }
+            wkb_type::LINE_STRING => self.skip_point_lists(1)?,
+            wkb_type::POLYGON => {
+                let rings = self.read_u32()?;
+                self.skip_point_lists(rings)?;
+            }
+            wkb_type::MULTI_POINT
+            | wkb_type::MULTI_LINE_STRING
+            | wkb_type::MULTI_POLYGON
+            | wkb_type::GEOMETRY_COLLECTION => {
+                let element_type = match ty {
+                    wkb_type::MULTI_POINT => Some(wkb_type::POINT),
+                    wkb_type::MULTI_LINE_STRING => Some(wkb_type::LINE_STRING),
+                    wkb_type::MULTI_POLYGON => Some(wkb_type::POLYGON),
+                    _ => None,
+                };
+                for _ in 0..self.read_u32()? {
+                    self.skip_geometry(element_type)?;
+                }
+            }
+            _ => return Err(format!("Unknown geometry type {ty}")),
+        }
+        Ok(())
+    }
+}
+
+/// Convert a value of a MySQL spatial column to a [`DfValue`].
+///
+/// MySQL stores spatial values in its internal geometry format, a 4-byte little-endian SRID
+/// followed by the WKB representation of the geometry. We don't support spatial functions, so we
+/// check that the value is well-formed and pass those bytes through unchanged as a
+/// [`DfValue::ByteArray`], which is also what clients get when they select a spatial column.
+pub(crate) fn geometry_value(bytes: &[u8]) -> Result<DfValue, String> {
+    let mut reader = WkbReader {
+        data: bytes,
+        little_endian: true,
+    };
+    reader.take(4)?;
+    reader.skip_geometry(None)?;
+    if !reader.data.is_empty() {
+        return Err("Trailing bytes after geometry value".into());
+    }
+    Ok(DfValue::ByteArray(Arc::new(bytes.to_vec())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `POINT(1 2)` with SRID 0, as MySQL stores it
+    fn point() -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 0, 1];
+        bytes.extend(wkb_type::POINT.to_le_bytes());
+        bytes.extend(1f64.to_le_bytes());
+        bytes.extend(2f64.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn point_passes_through() {
+        let bytes = point();
+        assert_eq!(
+            geometry_value(&bytes).unwrap(),
+            DfValue::ByteArray(Arc::new(bytes))
+        );
+    }
+
+    #[test]
+    fn big_endian_polygon() {
+        // SRID 4326, then `POLYGON((0 0, 1 0, 1 1, 0 0))` in big-endian WKB
+        let mut bytes = 4326u32.to_le_bytes().to_vec();
+        bytes.push(0);
+        bytes.extend(wkb_type::POLYGON.to_be_bytes());
+        bytes.extend(1u32.to_be_bytes());
+        bytes.extend(4u32.to_be_bytes());
+        for (x, y) in [(0f64, 0f64), (1., 0.), (1., 1.), (0., 0.)] {
+            bytes.extend(x.to_be_bytes());
+            bytes.extend(y.to_be_bytes());
+        }
+        assert!(geometry_value(&bytes).is_ok());
+    }
+
+    #[test]
+    fn geometry_collection_with_mixed_byte_orders() {
+        let mut bytes = vec![0, 0, 0, 0, 0];
+        bytes.extend(wkb_type::GEOMETRY_COLLECTION.to_be_bytes());
+        bytes.extend(2u32.to_be_bytes());
+        // Both elements are little-endian points, without an SRID of their own
+        bytes.extend(&point()[4..]);
+        bytes.extend(&point()[4..]);
+        assert!(geometry_value(&bytes).is_ok());
+    }
+
+    #[test]
+    fn multi_point_of_line_strings_is_invalid() {
+        let mut bytes = vec![0, 0, 0, 0, 1];
+        bytes.extend(wkb_type::MULTI_POINT.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.push(1);
+        bytes.extend(wkb_type::LINE_STRING.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        assert!(geometry_value(&bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_and_trailing_bytes_are_invalid() {
+        let bytes = point();
+        assert!(geometry_value(&bytes[..bytes.len() - 1]).is_err());
+        assert!(geometry_value(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(geometry_value(&[]).is_err());
+    }
+}
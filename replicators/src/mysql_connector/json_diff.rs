@@ -0,0 +1,263 @@
+use std::convert::TryInto;
+
+use mysql_common::binlog::jsonb::{self, JsonbToJsonError};
+use mysql_common::binlog::jsondiff::{JsonDiff, JsonDiffOperation};
+use serde_json::Value;
+
+/// A single leg of a JSON path, as used in the JSON diffs logged by MySQL
+#[derive(Debug, PartialEq, Eq)]
+enum PathLeg {
+    /// `.key` or `."key"`, addressing a member of an object
+    Member(String),
+    /// `[N]`, addressing an element of an array
+    Index(usize),
+}
+
+/// Parse the JSON path of a diff (for example `$.a."b c"[1]`) into its legs.
+///
+/// The paths in diffs always address a single value, so unlike JSON paths in general they never
+/// contain wildcards or ranges.
+fn parse_path(path: &str) -> Option<Vec<PathLeg>> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut legs = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some(legs);
+        }
+
+        if let Some(member) = rest.strip_prefix('.') {
+            let member = member.trim_start();
+            let len = if member.starts_with('"') {
+                // A quoted member name is a JSON string, which ends at the first quote that isn't
+                // escaped
+                let mut escaped = false;
+                1 + member[1..].find(|c: char| {
+                    let end = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    end
+                })? + 1
+            } else {
+                member
+                    .find(|c: char| c == '.' || c == '[' || c.is_whitespace())
+                    .unwrap_or(member.len())
+            };
+            let name = if member.starts_with('"') {
+                serde_json::from_str(&member[..len]).ok()?
+            } else if len > 0 {
+                member[..len].to_owned()
+            } else {
+                return None;
+            };
+            legs.push(PathLeg::Member(name));
+            rest = &member[len..];
+        } else if let Some(index) = rest.strip_prefix('[') {
+            let (index, after) = index.split_once(']')?;
+            legs.push(PathLeg::Index(index.trim().parse().ok()?));
+            rest = after;
+        } else {
+            return None;
+        }
+    }
+}
+
+/// Convert a binary JSON value from a diff into a JSON value
+fn jsonb_to_json(value: &jsonb::Value<'_>) -> Result<Value, String> {
+    let json: Result<Value, _> = value.clone().try_into();
+    match json {
+        Ok(json) => Ok(json),
+        Err(JsonbToJsonError::Opaque) => match value {
+            // Opaque values hold MySQL types that JSON has no equivalent for, such as dates, which
+            // we represent as strings
+            jsonb::Value::Opaque(opaque) => Ok(Value::String(
+                String::from_utf8_lossy(opaque.data().as_ref()).into_owned(),
+            )),
+            _ => Err("Opaque error only returned for opaque values".into()),
+        },
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Apply `operation` to the value at the path made up of `legs` in `doc`, returning `None` if the
+/// operation doesn't apply to the document
+fn apply_operation(
+    doc: &mut Value,
+    operation: JsonDiffOperation,
+    legs: &[PathLeg],
+    value: Option<Value>,
+) -> Option<()> {
+    let Some((last, parents)) = legs.split_last() else {
+        // The path `$` refers to the whole document, which can only be replaced
+        return match operation {
+            JsonDiffOperation::REPLACE => {
+                *doc = value?;
+                Some(())
+            }
+            _ => None,
+        };
+    };
+
+    let mut target = doc;
+    for leg in parents {
+        target = match (leg, target) {
+            (PathLeg::Member(name), Value::Object(object)) => object.get_mut(name)?,
+            (PathLeg::Index(index), Value::Array(array)) => array.get_mut(*index)?,
+            _ => return None,
+        };
+    }
+
+    match (operation, last, target) {
+        (
+            JsonDiffOperation::REPLACE | JsonDiffOperation::INSERT,
+            PathLeg::Member(name),
+            Value::Object(object),
+        ) => {
+            object.insert(name.clone(), value?);
+        }
+        (JsonDiffOperation::REPLACE, PathLeg::Index(index), Value::Array(array)) => {
+            *array.get_mut(*index)? = value?;
+        }
+        (JsonDiffOperation::INSERT, PathLeg::Index(index), Value::Array(array)) => {
+            // Inserting past the end of an array appends to it
+            let index = (*index).min(array.len());
+            array.insert(index, value?);
+        }
+        (JsonDiffOperation::REMOVE, PathLeg::Member(name), Value::Object(object)) => {
+            object.remove(name)?;
+        }
+        (JsonDiffOperation::REMOVE, PathLeg::Index(index), Value::Array(array))
+            if *index < array.len() =>
+        {
+            array.remove(*index);
+        }
+        _ => return None,
+    }
+
+    Some(())
+}
+
+/// Apply the diffs logged for a partial update of a JSON column (with
+/// `binlog_row_value_options=PARTIAL_JSON`) to `doc`, the value of the column before the update,
+/// in order.
+pub(crate) fn apply_diffs(doc: &mut Value, diffs: &[JsonDiff<'_>]) -> Result<(), String> {
+    for diff in diffs {
+        let path = diff.path();
+        let legs = parse_path(&path).ok_or_else(|| format!("Invalid JSON path {path}"))?;
+        let value = diff.value().map(jsonb_to_json).transpose()?;
+        apply_operation(doc, diff.operation(), &legs, value)
+            .ok_or_else(|| format!("Unable to apply JSON diff at path {path}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_simple_paths() {
+        assert_eq!(parse_path("$"), Some(vec![]));
+        assert_eq!(
+            parse_path("$.a[1].b"),
+            Some(vec![
+                PathLeg::Member("a".into()),
+                PathLeg::Index(1),
+                PathLeg::Member("b".into())
+            ])
+        );
+        assert_eq!(
+            parse_path("$[0][ 2 ]"),
+            Some(vec![PathLeg::Index(0), PathLeg::Index(2)])
+        );
+    }
+
+    #[test]
+    fn parse_quoted_members() {
+        assert_eq!(
+            parse_path(r#"$."a b"."c\"d".e"#),
+            Some(vec![
+                PathLeg::Member("a b".into()),
+                PathLeg::Member("c\"d".into()),
+                PathLeg::Member("e".into())
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_invalid_paths() {
+        assert_eq!(parse_path("a.b"), None);
+        assert_eq!(parse_path("$."), None);
+        assert_eq!(parse_path("$[x]"), None);
+        assert_eq!(parse_path("$[1"), None);
+        assert_eq!(parse_path(r#"$."a"#), None);
+        assert_eq!(parse_path("$.a[*]"), None);
+    }
+
+    fn apply(doc: &mut Value, operation: JsonDiffOperation, path: &str, value: Option<Value>) {
+        apply_operation(doc, operation, &parse_path(path).unwrap(), value).unwrap()
+    }
+
+    #[test]
+    fn apply_operations() {
+        let mut doc = json!({"a": [1, 2], "b": {"c": "d"}});
+        apply(
+            &mut doc,
+            JsonDiffOperation::REPLACE,
+            "$.a[0]",
+            Some(json!(3)),
+        );
+        apply(
+            &mut doc,
+            JsonDiffOperation::INSERT,
+            "$.a[1]",
+            Some(json!(4)),
+        );
+        apply(
+            &mut doc,
+            JsonDiffOperation::INSERT,
+            "$.a[10]",
+            Some(json!(5)),
+        );
+        apply(&mut doc, JsonDiffOperation::REMOVE, "$.a[2]", None);
+        apply(
+            &mut doc,
+            JsonDiffOperation::INSERT,
+            "$.b.e",
+            Some(json!([])),
+        );
+        apply(
+            &mut doc,
+            JsonDiffOperation::REPLACE,
+            "$.b.c",
+            Some(json!(null)),
+        );
+        assert_eq!(doc, json!({"a": [3, 4, 5], "b": {"c": null, "e": []}}));
+
+        apply(&mut doc, JsonDiffOperation::REMOVE, "$.b", None);
+        let replacement = json!({"x": doc["a"].clone()});
+        apply(&mut doc, JsonDiffOperation::REPLACE, "$", Some(replacement));
+        assert_eq!(doc, json!({"x": [3, 4, 5]}));
+    }
+
+    #[test]
+    fn apply_invalid_operations() {
+        let mut doc = json!({"a": [1, 2]});
+        for (operation, path, value) in [
+            (JsonDiffOperation::REPLACE, "$.a[2]", Some(json!(3))),
+            (JsonDiffOperation::REPLACE, "$.b.c", Some(json!(3))),
+            (JsonDiffOperation::REPLACE, "$.a", None),
+            (JsonDiffOperation::REMOVE, "$.b", None),
+            (JsonDiffOperation::REMOVE, "$.a[2]", None),
+            (JsonDiffOperation::REMOVE, "$", None),
+            (JsonDiffOperation::INSERT, "$.a.b", Some(json!(3))),
+        ] {
+            assert_eq!(
+                apply_operation(&mut doc, operation, &parse_path(path).unwrap(), value),
+                None
+            );
+        }
+        assert_eq!(doc, json!({"a": [1, 2]}));
+    }
+}
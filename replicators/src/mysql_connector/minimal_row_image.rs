@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use mysql::prelude::Queryable;
+use mysql_async as mysql;
+use nom_sql::Relation;
+use readyset_client::replication::ReplicationOffset;
+use readyset_client::{Modification, TableOperation};
+use readyset_data::DfValue;
+use readyset_errors::{ReadySetError, ReadySetResult};
+use tracing::debug;
+
+use super::mysql_error;
+use super::snapshot::mysql_row_to_noria_row;
+
+/// A row from a rows event, holding the value of each column of the table that's in the row
+/// image, and `None` for every column that isn't.
+///
+/// With `binlog_row_image=MINIMAL`, the before image of a row only includes the columns needed to
+/// identify it (its primary key, if the table has one), and the after image only includes the
+/// columns set by the statement.
+pub(crate) type PartialRow = Vec<Option<DfValue>>;
+
+/// The columns of a table in the upstream database
+#[derive(Debug)]
+struct TableColumns {
+    /// The name of each column, in order
+    names: Vec<String>,
+    /// The indices of the columns of the table's primary key, in key order. Empty if the table
+    /// has no primary key.
+    primary_key: Vec<usize>,
+}
+
+/// Converts the rows of rows events logged with `binlog_row_image=MINIMAL` to table operations,
+/// resolving the columns missing from their row images.
+///
+/// Updates and deletes are applied by primary key, leaving the base tables to fill in the columns
+/// that weren't logged. For inserts, which need every column, the missing columns (set to their
+/// defaults by the upstream) are read back from the upstream database with a point lookup. If the
+/// inserted row no longer exists upstream, there's no way to tell what the missing columns were,
+/// so the table has to be resnapshotted.
+pub(crate) struct MinimalRowImages {
+    /// A connection to the upstream database for looking up table columns and rows, separate from
+    /// the connection we read the binlog stream from
+    connection: mysql::Conn,
+    /// The columns of each table we've converted rows for, looked up from the upstream as needed.
+    /// Cleared whenever the schema changes.
+    tables: HashMap<Relation, Arc<TableColumns>>,
+    /// The replication offset of each table in ReadySet as of when we started replicating. Writes
+    /// to a table from before its offset are skipped, so we don't look up the rows they insert,
+    /// which may well no longer exist upstream (for example if the table was resnapshotted
+    /// because an earlier lookup found nothing).
+    table_offsets: HashMap<Relation, ReplicationOffset>,
+}
+
+impl MinimalRowImages {
+    pub(crate) async fn connect(
+        opts: mysql::Opts,
+        table_offsets: HashMap<Relation, ReplicationOffset>,
+    ) -> ReadySetResult<Self> {
+        Ok(Self {
+            connection: mysql::Conn::new(opts).await.map_err(mysql_error)?,
+            tables: HashMap::new(),
+            table_offsets,
+        })
+    }
+
+    /// Reconnect to the upstream database, which may be a different server than before, such as
+    /// a replica promoted after a failover
+    pub(crate) async fn reconnect(&mut self, opts: mysql::Opts) -> mysql::Result<()> {
+        self.connection = mysql::Conn::new(opts).await?;
+        self.tables.clear();
+        Ok(())
+    }
+
+    /// Called when the schema of the upstream database changes, since the columns we've looked
+    /// up for each table may no longer be accurate
+    pub(crate) fn schema_changed(&mut self) {
+        self.tables.clear();
+    }
+
+    /// Returns the columns of `table` in the upstream database
+    async fn columns(&mut self, table: &Relation) -> ReadySetResult<Arc<TableColumns>> {
+        if let Some(columns) = self.tables.get(table) {
+            return Ok(Arc::clone(columns));
+        }
+
+        let rows: Vec<(String, Option<u64>)> = self
+            .connection
+            .exec(
+                "SELECT c.COLUMN_NAME, k.ORDINAL_POSITION
+                 FROM information_schema.COLUMNS c
+                 LEFT JOIN information_schema.KEY_COLUMN_USAGE k
+                 ON k.TABLE_SCHEMA = c.TABLE_SCHEMA AND k.TABLE_NAME = c.TABLE_NAME
+                 AND k.COLUMN_NAME = c.COLUMN_NAME AND k.CONSTRAINT_NAME = 'PRIMARY'
+                 WHERE c.TABLE_SCHEMA = ? AND c.TABLE_NAME = ?
+                 ORDER BY c.ORDINAL_POSITION",
+                (
+                    table.schema.as_deref().unwrap_or_default(),
+                    table.name.as_str(),
+                ),
+            )
+            .await
+            .map_err(mysql_error)?;
+        if rows.is_empty() {
+            return Err(ReadySetError::ReplicationFailed(format!(
+                "Unable to look up the columns of {} in the upstream database",
+                table.display_unquoted()
+            )));
+        }
+
+        let primary_key = rows
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, (_, key_position))| key_position.map(|pos| (pos, idx)))
+            .sorted()
+            .map(|(_, idx)| idx)
+            .collect();
+        let columns = Arc::new(TableColumns {
+            names: rows.into_iter().map(|(name, _)| name).collect(),
+            primary_key,
+        });
+        debug!(table = %table.display_unquoted(), ?columns, "Looked up table columns");
+        self.tables.insert(table.clone(), Arc::clone(&columns));
+        Ok(columns)
+    }
+
+    /// Look up the current value of the row of `table` matching `row` in the upstream database,
+    /// by its primary key if the table has one, or otherwise by every column in `row`
+    async fn lookup(
+        &mut self,
+        table: &Relation,
+        row: &PartialRow,
+    ) -> ReadySetResult<Option<Vec<DfValue>>> {
+        let columns = self.columns(table).await?;
+        let key_columns = if columns.primary_key.is_empty() {
+            (0..row.len()).filter(|&idx| row[idx].is_some()).collect()
+        } else {
+            columns.primary_key.clone()
+        };
+
+        let conditions = key_columns
+            .iter()
+            .map(|&idx| {
+                let name = columns
+                    .names
+                    .get(idx)
+                    .ok_or_else(|| column_mismatch(table))?;
+                Ok(format!("{} <=> ?", quote_identifier(name)))
+            })
+            .collect::<ReadySetResult<Vec<_>>>()?
+            .join(" AND ");
+        // `SELECT *` would leave out any INVISIBLE columns, which are still logged in the binlog
+        let query = format!(
+            "SELECT {} FROM {}.{} WHERE {conditions} LIMIT 1",
+            columns
+                .names
+                .iter()
+                .map(|name| quote_identifier(name))
+                .join(", "),
+            quote_identifier(table.schema.as_deref().unwrap_or_default()),
+            quote_identifier(table.name.as_str()),
+        );
+        let params = key(table, row, &key_columns)?
+            .iter()
+            .map(|val| mysql_common::value::Value::try_from(val).map(mysql_value))
+            .collect::<ReadySetResult<Vec<_>>>()?;
+
+        let found: Option<mysql::Row> = self
+            .connection
+            .exec_first(query.as_str(), params)
+            .await
+            .map_err(mysql_error)?;
+        found.map(mysql_row_to_noria_row).transpose()
+    }
+
+    /// Convert the after images of the rows of a WRITE_ROWS_EVENT at `position` to inserts
+    pub(crate) async fn inserts(
+        &mut self,
+        table: &Relation,
+        rows: Vec<PartialRow>,
+        position: &ReplicationOffset,
+    ) -> ReadySetResult<Vec<TableOperation>> {
+        if self
+            .table_offsets
+            .get(table)
+            .map_or(false, |offset| position <= offset)
+        {
+            return Ok(vec![]);
+        }
+
+        let mut inserts = Vec::with_capacity(rows.len());
+        for row in rows {
+            if let Some(row) = complete_row(&row) {
+                inserts.push(TableOperation::Insert(row));
+                continue;
+            }
+
+            match self.lookup(table, &row).await? {
+                // The upstream row may have changed since, but the values logged for the insert
+                // are the ones it had at this point in the binlog. Any later changes to the other
+                // columns will be applied when we get to them.
+                Some(current) if current.len() == row.len() => {
+                    inserts.push(TableOperation::Insert(overlay(current, row)))
+                }
+                Some(_) => return Err(column_mismatch(table)),
+                // The row has since been deleted or had its primary key changed, so the values of
+                // the columns that weren't logged are lost. Later updates to the row only log the
+                // columns they change, so we can't leave it out either.
+                None => {
+                    return Err(ReadySetError::TableResnapshotNeeded {
+                        table: table.clone(),
+                        reason: "a row inserted with a minimal row image no longer exists upstream"
+                            .to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(inserts)
+    }
+
+    /// Convert the (before image, after image) pairs of the rows of an UPDATE_ROWS_EVENT to
+    /// updates
+    pub(crate) async fn updates(
+        &mut self,
+        table: &Relation,
+        rows: Vec<(PartialRow, PartialRow)>,
+    ) -> ReadySetResult<Vec<TableOperation>> {
+        let mut updates = Vec::with_capacity(rows.len() * 2);
+        for (before, after) in rows {
+            match complete_row(&before) {
+                // Tables without a primary key log the whole row as the before image, so we can
+                // replace it without knowing the values of the columns the update didn't set
+                Some(old_row) => {
+                    let new_row = overlay(old_row.clone(), after);
+                    updates.push(TableOperation::DeleteRow { row: old_row });
+                    updates.push(TableOperation::Insert(new_row));
+                }
+                None => {
+                    let columns = self.columns(table).await?;
+                    updates.push(TableOperation::Update {
+                        key: key(table, &before, &columns.primary_key)?,
+                        update: after.into_iter().map(Modification::from).collect(),
+                    });
+                }
+            }
+        }
+        Ok(updates)
+    }
+
+    /// Convert the before images of the rows of a DELETE_ROWS_EVENT to deletes
+    pub(crate) async fn deletes(
+        &mut self,
+        table: &Relation,
+        rows: Vec<PartialRow>,
+    ) -> ReadySetResult<Vec<TableOperation>> {
+        let mut deletes = Vec::with_capacity(rows.len());
+        for row in rows {
+            match complete_row(&row) {
+                Some(row) => deletes.push(TableOperation::DeleteRow { row }),
+                None => {
+                    let columns = self.columns(table).await?;
+                    deletes.push(TableOperation::DeleteByKey {
+                        key: key(table, &row, &columns.primary_key)?,
+                    });
+                }
+            }
+        }
+        Ok(deletes)
+    }
+}
+
+/// Returns `row` as a full row, if it holds a value for every column
+fn complete_row(row: &PartialRow) -> Option<Vec<DfValue>> {
+    row.iter().cloned().collect()
+}
+
+/// Replace the values in `row` with those of each column in `changes`
+fn overlay(row: Vec<DfValue>, changes: PartialRow) -> Vec<DfValue> {
+    row.into_iter()
+        .zip(changes)
+        .map(|(val, change)| change.unwrap_or(val))
+        .collect()
+}
+
+/// Returns the values of the columns at `key_columns` in `row`, all of which must be in the row
+/// image
+fn key(table: &Relation, row: &PartialRow, key_columns: &[usize]) -> ReadySetResult<Vec<DfValue>> {
+    if key_columns.is_empty() {
+        return Err(ReadySetError::ReplicationFailed(format!(
+            "Unable to identify the row of {} to change from its minimal row image",
+            table.display_unquoted()
+        )));
+    }
+    key_columns
+        .iter()
+        .map(|&idx| {
+            row.get(idx).cloned().flatten().ok_or_else(|| {
+                ReadySetError::ReplicationFailed(format!(
+                    "Minimal row image for {} is missing primary key column {}",
+                    table.display_unquoted(),
+                    idx + 1
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Quote `ident` for use as an identifier in a MySQL query
+fn quote_identifier(ident: &str) -> String {
+    format!("`{}`", ident.replace('`', "``"))
+}
+
+/// Construct the error returned when the columns of a table in the upstream database don't match
+/// the rows logged for it
+fn column_mismatch(table: &Relation) -> ReadySetError {
+    ReadySetError::ReplicationFailed(format!(
+        "Columns of {} in the upstream database don't match its rows in the binlog",
+        table.display_unquoted()
+    ))
+}
+
+/// Convert a value of the version of `mysql_common` we depend on to one of the version re-exported
+/// by `mysql_async`, which are otherwise the same type
+fn mysql_value(val: mysql_common::value::Value) -> mysql::Value {
+    use mysql_common::value::Value;
+
+    match val {
+        Value::NULL => mysql::Value::NULL,
+        Value::Bytes(b) => mysql::Value::Bytes(b),
+        Value::Int(i) => mysql::Value::Int(i),
+        Value::UInt(u) => mysql::Value::UInt(u),
+        Value::Float(f) => mysql::Value::Float(f),
+        Value::Double(d) => mysql::Value::Double(d),
+        Value::Date(y, m, d, hh, mm, ss, us) => mysql::Value::Date(y, m, d, hh, mm, ss, us),
+        Value::Time(is_neg, d, hh, mm, ss, us) => mysql::Value::Time(is_neg, d, hh, mm, ss, us),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_rows() {
+        assert_eq!(
+            complete_row(&vec![Some(1.into()), Some(DfValue::None)]),
+            Some(vec![1.into(), DfValue::None])
+        );
+        assert_eq!(complete_row(&vec![Some(1.into()), None]), None);
+    }
+
+    #[test]
+    fn overlay_changes() {
+        assert_eq!(
+            overlay(
+                vec![1.into(), "a".into(), 2.into()],
+                vec![None, Some("b".into()), None]
+            ),
+            vec![1.into(), "b".into(), 2.into()]
+        );
+    }
+
+    #[test]
+    fn key_in_key_order() {
+        let table = Relation::from("t");
+        let row = vec![Some(1.into()), None, Some(2.into())];
+        assert_eq!(
+            key(&table, &row, &[2, 0]).unwrap(),
+            vec![2.into(), 1.into()]
+        );
+        key(&table, &row, &[1]).unwrap_err();
+        key(&table, &row, &[]).unwrap_err();
+    }
+}
@@ -1,11 +1,78 @@
+use std::fmt::{self, Display};
+
+use mysql_async as mysql;
+use readyset_errors::ReadySetError;
+
+mod binlog_files;
 mod connector;
+mod dump_file;
+mod geometry;
+mod json_diff;
+mod minimal_row_image;
+mod privileges;
 mod snapshot;
+mod source_selection;
+mod unparsed_ddl;
 
 pub(crate) use connector::MySqlBinlogConnector;
+pub(crate) use dump_file::DumpFile;
+pub(crate) use privileges::check_privileges;
+pub use readyset_client::replication::GtidSet;
 pub(crate) use snapshot::MySqlReplicator;
+pub(crate) use source_selection::SourceSelector;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct BinlogPosition {
     pub binlog_file: String,
     pub position: u32,
+    /// The set of GTIDs executed as of this position, if GTIDs are enabled on the upstream
+    /// database
+    pub gtid_set: Option<GtidSet>,
+}
+
+impl Display for BinlogPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.binlog_file, self.position)?;
+        if let Some(gtid_set) = &self.gtid_set {
+            write!(f, " (executed GTIDs: {gtid_set})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `error` means we lost our connection to the MySQL server, in which case it's
+/// worth trying to reconnect
+pub(crate) fn is_connection_error(error: &mysql::Error) -> bool {
+    /// `ER_SERVER_SHUTDOWN`, sent to connected clients when the server is shutting down
+    const SERVER_SHUTDOWN: u16 = 1053;
+
+    match error {
+        mysql::Error::Io(_) | mysql::Error::Driver(mysql::DriverError::ConnectionClosed) => true,
+        mysql::Error::Server(e) => e.code == SERVER_SHUTDOWN,
+        _ => false,
+    }
+}
+
+/// Returns true if `error` means the user we're connected as lacks a privilege needed for the
+/// statement we ran
+pub(crate) fn is_access_denied(error: &mysql::ServerError) -> bool {
+    /// `ER_DBACCESS_DENIED_ERROR`, `ER_ACCESS_DENIED_ERROR`, `ER_TABLEACCESS_DENIED_ERROR` and
+    /// `ER_SPECIFIC_ACCESS_DENIED_ERROR`
+    const ACCESS_DENIED: [u16; 4] = [1044, 1045, 1142, 1227];
+
+    ACCESS_DENIED.contains(&error.code)
+}
+
+/// Convert an error returned by the MySQL client into a [`ReadySetError`], distinguishing lost
+/// connections and missing permissions from other replication failures
+pub(crate) fn mysql_error(error: mysql::Error) -> ReadySetError {
+    match error {
+        mysql::Error::Server(e) if is_access_denied(&e) => {
+            ReadySetError::ReplicationPermissionMissing(e.message)
+        }
+        error if is_connection_error(&error) => {
+            ReadySetError::ReplicationConnectionLost(error.to_string())
+        }
+        error => error.into(),
+    }
 }
@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, HashSet};
+
+use mysql::prelude::Queryable;
+use mysql_async as mysql;
+use readyset_errors::ReadySetResult;
+
+use super::connector::ServerFlavor;
+use super::mysql_error;
+use super::snapshot::{get_table_list, TableKind};
+use crate::privileges::PrivilegeReport;
+use crate::table_filter::TableFilter;
+
+/// Quote `ident` as a MySQL identifier, for naming it in the privileges we report as missing
+fn quote(ident: &str) -> String {
+    format!("`{}`", ident.replace('`', "``"))
+}
+
+/// The privileges granted to the user we're connected as, read from the privilege tables in
+/// `information_schema` rather than by trying out statements that need them.
+///
+/// Privileges a user only has through a role aren't listed in these tables, so a user relying on
+/// roles will be reported as missing them.
+struct Grants {
+    /// The current user, formatted like the `GRANTEE` column of the privilege tables
+    /// (`'user'@'host'`)
+    grantee: String,
+    /// The privileges granted globally, `ON *.*`
+    global: HashSet<String>,
+}
+
+impl Grants {
+    async fn load(conn: &mut mysql::Conn) -> mysql::Result<Self> {
+        let current_user: String = conn
+            .query_first("SELECT CURRENT_USER()")
+            .await?
+            .unwrap_or_default();
+        let (user, host) = current_user
+            .rsplit_once('@')
+            .unwrap_or((current_user.as_str(), "%"));
+        let grantee = format!("'{user}'@'{host}'");
+        let global = conn
+            .exec(
+                "SELECT PRIVILEGE_TYPE FROM information_schema.USER_PRIVILEGES WHERE GRANTEE = ?",
+                (&grantee,),
+            )
+            .await?
+            .into_iter()
+            .collect();
+        Ok(Self { grantee, global })
+    }
+
+    fn has_global(&self, privilege: &str) -> bool {
+        self.global.contains(privilege)
+    }
+
+    /// Returns the privileges the user has on every table in `schema`, granted either globally or
+    /// on the schema. Schema grants may use wildcards, which `LIKE` matches the same way MySQL
+    /// does.
+    async fn schema_privileges(
+        &self,
+        conn: &mut mysql::Conn,
+        schema: &str,
+    ) -> mysql::Result<HashSet<String>> {
+        let mut privileges: HashSet<String> = conn
+            .exec(
+                "SELECT PRIVILEGE_TYPE FROM information_schema.SCHEMA_PRIVILEGES \
+                 WHERE GRANTEE = ? AND ? LIKE TABLE_SCHEMA",
+                (&self.grantee, schema),
+            )
+            .await?
+            .into_iter()
+            .collect();
+        privileges.extend(self.global.iter().cloned());
+        Ok(privileges)
+    }
+
+    /// Returns the tables in `schema` which the user was granted `SELECT` on individually
+    async fn selectable_tables(
+        &self,
+        conn: &mut mysql::Conn,
+        schema: &str,
+    ) -> mysql::Result<HashSet<String>> {
+        Ok(conn
+            .exec(
+                "SELECT TABLE_NAME FROM information_schema.TABLE_PRIVILEGES \
+                 WHERE GRANTEE = ? AND TABLE_SCHEMA = ? AND PRIVILEGE_TYPE = 'SELECT'",
+                (&self.grantee, schema),
+            )
+            .await?
+            .into_iter()
+            .collect())
+    }
+}
+
+/// Check that the user `conn` is connected as has each of the privileges listed in the docs for
+/// [`MySqlBinlogConnector`](super::MySqlBinlogConnector), and return an error listing the required
+/// privileges it's missing, if any.
+///
+/// Privileges are read from `information_schema`, and any error reading them is returned as is.
+pub(crate) async fn check_privileges(
+    conn: &mut mysql::Conn,
+    table_filter: &TableFilter,
+) -> ReadySetResult<()> {
+    let mut report = PrivilegeReport::default();
+    let grants = Grants::load(conn).await.map_err(mysql_error)?;
+
+    if !grants.has_global("REPLICATION CLIENT") {
+        report.missing("REPLICATION CLIENT", "to read the current binlog position");
+    }
+    if !grants.has_global("REPLICATION SLAVE") {
+        report.missing("REPLICATION SLAVE", "to read the binlog");
+    }
+
+    let mut tables = BTreeMap::<String, Vec<String>>::new();
+    for (schema, table) in get_table_list(conn, TableKind::BaseTable)
+        .await
+        .map_err(mysql_error)?
+    {
+        if table_filter.should_be_processed(schema.as_str(), table.as_str()) {
+            tables.entry(schema).or_default().push(table);
+        }
+    }
+
+    for (schema, tables) in tables {
+        let privileges = grants
+            .schema_privileges(conn, &schema)
+            .await
+            .map_err(mysql_error)?;
+        if !privileges.contains("SELECT") {
+            let selectable = grants
+                .selectable_tables(conn, &schema)
+                .await
+                .map_err(mysql_error)?;
+            for table in tables.iter().filter(|table| !selectable.contains(*table)) {
+                report.missing(
+                    format!("SELECT ON {}.{}", quote(&schema), quote(table)),
+                    "to snapshot tables",
+                );
+            }
+        }
+        if !privileges.contains("LOCK TABLES") {
+            report.missing(
+                format!("LOCK TABLES ON {}.*", quote(&schema)),
+                "to get a consistent binlog position for each table during snapshot",
+            );
+        }
+    }
+
+    let flavor = ServerFlavor::detect(conn).await.map_err(mysql_error)?;
+    let privilege = flavor.ddl_lock_privilege();
+    if !grants.has_global(privilege) {
+        report.absent_optional(privilege, "to prevent DDL changes during snapshot");
+    }
+    if !grants.has_global("SHOW DATABASES") {
+        report.absent_optional("SHOW DATABASES", "to see every database when snapshotting");
+    }
+
+    report.finish()
+}
@@ -1,7 +1,9 @@
+use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::fmt::{self, Display};
 use std::future;
+use std::sync::Arc;
 use std::time::Instant;
 
 use futures::future::TryFutureExt;
@@ -9,26 +11,33 @@ use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use itertools::Itertools;
 use metrics::register_gauge;
+use mysql::consts::ColumnType;
 use mysql::prelude::Queryable;
 use mysql::{Transaction, TxOpts};
 use mysql_async as mysql;
-use nom_sql::Relation;
+use nom_sql::{ColumnConstraint, CreateTableStatement, Relation};
 use readyset_client::metrics::recorded;
 use readyset_client::recipe::changelist::{Change, ChangeList};
 use readyset_client::replication::{ReplicationOffset, ReplicationOffsets};
-use readyset_data::Dialect;
-use readyset_errors::ReadySetResult;
+use readyset_data::{DfType, Dialect};
+use readyset_errors::{ReadySetError, ReadySetResult};
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, info_span, warn};
 use tracing_futures::Instrument;
 
-use super::BinlogPosition;
-use crate::db_util::DatabaseSchemas;
+use super::connector::ServerFlavor;
+use super::dump_file::DumpFile;
+use super::geometry::geometry_value;
+use super::{BinlogPosition, GtidSet};
+use crate::db_util::{table_column_types, DatabaseSchemas};
+use crate::noria_adapter::{set_source_schema_replication_offset, source_replication_offsets};
+use crate::snapshot_checkpoint::{SnapshotCheckpoint, SnapshotCheckpoints};
+use crate::snapshot_throttle::SnapshotThrottle;
 use crate::table_filter::TableFilter;
 
 const BATCH_SIZE: usize = 1000; // How many queries to buffer before pushing to ReadySet
 
-const MAX_SNAPSHOT_BATCH: usize = 8; // How many tables to snapshot at the same time
+const MAX_SNAPSHOT_BATCH: usize = 8; // How many tables to snapshot at the same time by default
 
 /// A list of databases MySQL uses internally, they should not be replicated
 pub const MYSQL_INTERNAL_DBS: &[&str] =
@@ -51,6 +60,22 @@ pub(crate) struct MySqlReplicator {
     pub(crate) pool: mysql::Pool,
     /// Filters out the desired tables to snapshot and replicate
     pub(crate) table_filter: TableFilter,
+    /// The name of the additional upstream database we're snapshotting, if any
+    pub(crate) source: Option<String>,
+    /// The maximum number of tables to snapshot at the same time, [`MAX_SNAPSHOT_BATCH`] if not
+    /// set
+    pub(crate) snapshot_parallelism: Option<usize>,
+    /// If set, tables with a primary key are read in chunks of this many rows
+    pub(crate) snapshot_chunk_size: Option<u64>,
+    /// Where to persist the progress of tables read in chunks, if anywhere
+    pub(crate) checkpoints: Option<SnapshotCheckpoints>,
+    /// Limits the rate at which rows are snapshotted
+    pub(crate) throttle: SnapshotThrottle,
+    /// Tables requested to be resnapshotted, which are truncated and snapshotted again even
+    /// though they already have a replication offset
+    pub(crate) resnapshot_tables: HashSet<Relation>,
+    /// A dump file to snapshot the tables it contains from, instead of the upstream database
+    pub(crate) dump: Option<Arc<DumpFile>>,
 }
 
 /// Get the list of tables defined in the database
@@ -65,7 +90,7 @@ pub async fn load_table_list<Q: Queryable>(
 }
 
 /// Get the list of tables defined in the database for all (non-internal) schemas
-async fn get_table_list<Q: Queryable>(
+pub(crate) async fn get_table_list<Q: Queryable>(
     q: &mut Q,
     kind: TableKind,
 ) -> mysql::Result<Vec<(String, String)>> {
@@ -110,6 +135,78 @@ pub async fn create_for_table<Q: Queryable>(
     }
 }
 
+/// Get the name, character set, and collation of each of the string columns of the named table.
+///
+/// `SHOW CREATE TABLE` omits the character set and collation of columns that use the table's
+/// defaults, so these are loaded from `information_schema` instead.
+pub(crate) async fn column_collations<Q: Queryable>(
+    q: &mut Q,
+    db: &str,
+    table_name: &str,
+) -> mysql::Result<Vec<(String, String, String)>> {
+    q.exec(
+        "SELECT COLUMN_NAME, CHARACTER_SET_NAME, COLLATION_NAME FROM information_schema.COLUMNS \
+         WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? AND COLLATION_NAME IS NOT NULL",
+        (db, table_name),
+    )
+    .await
+}
+
+/// Add the character set and collation of each column in `collations`, as returned by
+/// [`column_collations`], to the columns of the `CREATE TABLE` statements in `changelist` that
+/// don't already specify them
+fn add_column_collations(changelist: &mut ChangeList, collations: &[(String, String, String)]) {
+    for change in &mut changelist.changes {
+        let Change::CreateTable(CreateTableStatement { body: Ok(body), .. }) = change else {
+            continue;
+        };
+        for field in &mut body.fields {
+            let Some((_, charset, collation)) = collations
+                .iter()
+                .find(|(column, _, _)| field.column.name == column.as_str())
+            else {
+                continue;
+            };
+            if field.character_set().is_none() {
+                field
+                    .constraints
+                    .push(ColumnConstraint::CharacterSet(charset.clone()));
+            }
+            if field.collation().is_none() {
+                field
+                    .constraints
+                    .push(ColumnConstraint::Collation(collation.clone()));
+            }
+        }
+    }
+}
+
+/// Use the SHOW MASTER STATUS statement to determine the current binary log file name and
+/// position of the server `q` is connected to.
+pub(crate) async fn binlog_position<Q: Queryable>(q: &mut Q) -> mysql::Result<BinlogPosition> {
+    let query = "SHOW MASTER STATUS";
+    let pos: mysql::Row = q.query_first(query).await?.ok_or(
+        "Empty response for SHOW MASTER STATUS. \
+         Ensure the binlog_format parameter is set to ROW and, if using RDS, backup retention \
+         is greater than 0",
+    )?;
+
+    let file: String = pos.get(0).expect("Binlog file name");
+    let offset: u32 = pos.get(1).expect("Binlog offset");
+    // The `Executed_Gtid_Set` column is empty if GTIDs are disabled, and missing altogether
+    // on MariaDB, which uses an incompatible GTID format
+    let gtid_set = match pos.get::<String, _>("Executed_Gtid_Set") {
+        Some(gtid_set) => GtidSet::from_executed(&gtid_set).map_err(|e| e.to_string())?,
+        None => None,
+    };
+
+    Ok(BinlogPosition {
+        binlog_file: file,
+        position: offset,
+        gtid_set,
+    })
+}
+
 fn tx_opts() -> TxOpts {
     let mut tx_opts = mysql::TxOpts::default();
     tx_opts
@@ -126,11 +223,15 @@ impl MySqlReplicator {
     /// If `install` is set to `true`, will also install the tables in ReadySet one-by-one, skipping
     /// over any tables that fail to install
     ///
+    /// If `full_snapshot` is set to `true`, each table is dropped before it is re-created, to clear
+    /// out any old data and replication offsets.
+    ///
     /// Returns a list of tables that should be dumped to readyset
     async fn load_recipe_with_meta_lock(
         &mut self,
         noria: &mut readyset_client::ReadySetHandle,
         db_schemas: &mut DatabaseSchemas,
+        full_snapshot: bool,
     ) -> ReadySetResult<(Transaction<'static>, Vec<Relation>)> {
         let mut tx = self.pool.start_transaction(tx_opts()).await?;
 
@@ -138,6 +239,13 @@ impl MySqlReplicator {
             .query_drop("SET SESSION MAX_EXECUTION_TIME=0")
             .await
             .map_err(log_err);
+        // Generated invisible primary keys are hidden from `SHOW CREATE TABLE` by default, but are
+        // still present in binlog rows. This variable only exists on MySQL 8.0.30+, so ignore any
+        // errors.
+        let _ = tx
+            .query_drop("SET SESSION show_gipk_in_create_table_and_information_schema = ON")
+            .await
+            .map_err(log_err);
 
         // After we loaded the list of currently existing tables, we will acquire a metadata
         // lock on all of them to prevent DDL changes.
@@ -191,6 +299,12 @@ impl MySqlReplicator {
         let mut bad_tables = Vec::new();
         // Process `CREATE TABLE` statements
         for (db, table) in replicated_tables.iter() {
+            let collations = column_collations(&mut tx, db, table)
+                .await
+                .unwrap_or_else(|error| {
+                    warn!(%error, %db, %table, "Could not load column collations");
+                    vec![]
+                });
             match create_for_table(&mut tx, db, table, TableKind::BaseTable)
                 .map_err(|e| e.into())
                 .and_then(|create_table| {
@@ -202,7 +316,26 @@ impl MySqlReplicator {
                         nom_sql::Dialect::MySQL,
                     );
 
-                    future::ready(ChangeList::from_str(create_table, Dialect::DEFAULT_MYSQL))
+                    future::ready(
+                        ChangeList::from_str(create_table, Dialect::DEFAULT_MYSQL).map(
+                            |mut changelist| {
+                                add_column_collations(&mut changelist, &collations);
+                                if full_snapshot {
+                                    changelist.changes.insert(
+                                        0,
+                                        Change::Drop {
+                                            name: Relation {
+                                                schema: Some(db.into()),
+                                                name: table.into(),
+                                            },
+                                            if_exists: true,
+                                        },
+                                    );
+                                }
+                                changelist
+                            },
+                        ),
+                    )
                 })
                 .and_then(|changelist| {
                     noria.extend_recipe_no_leader_ready(
@@ -280,9 +413,12 @@ impl MySqlReplicator {
         // no ddl changes took place between the binlog position and the schema that we loaded
         let binlog_position = self.get_binlog_position().await?;
 
-        noria
-            .set_schema_replication_offset(Some(&binlog_position.try_into()?))
-            .await?;
+        set_source_schema_replication_offset(
+            noria,
+            self.source.as_deref(),
+            Some(&binlog_position.try_into()?),
+        )
+        .await?;
 
         let table_list = replicated_tables
             .into_iter()
@@ -313,37 +449,109 @@ impl MySqlReplicator {
             .query_drop("SET SESSION MAX_EXECUTION_TIME=0")
             .await
             .map_err(log_err);
+        let _ = tx
+            .query_drop("SET SESSION show_gipk_in_create_table_and_information_schema = ON")
+            .await
+            .map_err(log_err);
+
+        // `SELECT *` omits invisible columns, but rows in the binlog include them, so if the table
+        // has any we need to select every column explicitly
+        let columns: Vec<(String, String)> = tx
+            .exec(
+                "SELECT COLUMN_NAME, EXTRA FROM information_schema.COLUMNS \
+                 WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+                (
+                    table.schema.as_ref().map(|s| s.as_str()),
+                    table.name.as_str(),
+                ),
+            )
+            .await?;
+        let select_list = if columns
+            .iter()
+            .any(|(_, extra)| extra.to_ascii_uppercase().contains("INVISIBLE"))
+        {
+            columns
+                .iter()
+                .map(|(name, _)| format!("`{}`", name.replace('`', "``")))
+                .join(", ")
+        } else {
+            "*".to_owned()
+        };
+
+        // Read tables with a primary key in chunks, if configured to. The rows we get back have
+        // the same columns as `columns`, in the same order, whether or not we select `*`.
+        let chunks = match self.snapshot_chunk_size {
+            Some(chunk_size) => {
+                let key_columns: Vec<String> = tx
+                    .exec(
+                        "SELECT COLUMN_NAME FROM information_schema.KEY_COLUMN_USAGE \
+                         WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ? AND CONSTRAINT_NAME = 'PRIMARY' \
+                         ORDER BY ORDINAL_POSITION",
+                        (
+                            table.schema.as_ref().map(|s| s.as_str()),
+                            table.name.as_str(),
+                        ),
+                    )
+                    .await?;
+                let key = key_columns
+                    .iter()
+                    .map(|key_column| columns.iter().position(|(name, _)| name == key_column))
+                    .collect::<Option<Vec<_>>>();
+                match key {
+                    Some(key) if !key.is_empty() => Some(ChunkQueries::new(
+                        &select_list,
+                        table,
+                        &key_columns,
+                        key,
+                        chunk_size,
+                    )),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
 
         let query_count = format!(
             "select count(*) from {}",
             table.display(nom_sql::Dialect::MySQL)
         );
-        let query = format!("select * from {}", table.display(nom_sql::Dialect::MySQL));
+        let query = format!(
+            "select {select_list} from {}",
+            table.display(nom_sql::Dialect::MySQL)
+        );
         Ok(TableDumper {
             query_count,
             query,
+            chunks,
             tx,
         })
     }
 
+    /// Start a transaction that sees `table` as of the current binlog position, returning that
+    /// position along with the dumper to read the table with. The table is locked just long enough
+    /// to read the position and start the transaction, so no writes to it can be made in between.
+    pub(crate) async fn dump_table_at_current_position(
+        &self,
+        table: &Relation,
+    ) -> ReadySetResult<(ReplicationOffset, TableDumper)> {
+        info!("Acquiring read lock");
+        let mut read_lock = self.lock_table(table).await?;
+        // We acquire the position for each table individually, since it changes from
+        // one lock to the other
+        let repl_offset = ReplicationOffset::try_from(self.get_binlog_position().await?)?;
+        let dumper = self.dump_table(table).await?;
+
+        // At this point we have a transaction that will see *that* table at *this* binlog
+        // position, so we can drop the read lock
+        read_lock.query_drop("UNLOCK TABLES").await?;
+        info!("Read lock released");
+        Ok((repl_offset, dumper))
+    }
+
     /// Use the SHOW MASTER STATUS statement to determine the current binary log
     /// file name and position.
     async fn get_binlog_position(&self) -> mysql::Result<BinlogPosition> {
-        let mut conn = self.pool.get_conn().await?;
-        let query = "SHOW MASTER STATUS";
-        let pos: mysql::Row = conn.query_first(query).await?.ok_or(
-            "Empty response for SHOW MASTER STATUS. \
-             Ensure the binlog_format parameter is set to ROW and, if using RDS, backup retention \
-             is greater than 0",
-        )?;
-
-        let file: String = pos.get(0).expect("Binlog file name");
-        let offset: u32 = pos.get(1).expect("Binlog offset");
-
-        Ok(BinlogPosition {
-            binlog_file: file,
-            position: offset,
-        })
+        binlog_position(&mut self.pool.get_conn().await?).await
     }
 
     /// Issue a `LOCK TABLES tbl_name READ` for the table name provided
@@ -359,10 +567,15 @@ impl MySqlReplicator {
 
     /// Replicate a single table from the provided TableDumper and into ReadySet by
     /// converting every MySQL row into ReadySet row and calling `insert_many` in batches
+    ///
+    /// If `checkpoint` is set, the table is read in chunks starting after the checkpoint's last key
     async fn replicate_table(
         mut dumper: TableDumper,
         mut table_mutator: readyset_client::Table,
         snapshot_report_interval_secs: u16,
+        checkpoint: Option<SnapshotCheckpoint>,
+        checkpoints: Option<SnapshotCheckpoints>,
+        throttle: SnapshotThrottle,
     ) -> ReadySetResult<()> {
         let mut cnt = 0;
 
@@ -374,6 +587,19 @@ impl MySqlReplicator {
             .map_err(log_err)?
             .unwrap_or(0);
 
+        if let Some(checkpoint) = checkpoint {
+            return Self::replicate_table_chunked(
+                dumper,
+                table_mutator,
+                nrows,
+                snapshot_report_interval_secs,
+                checkpoint,
+                checkpoints,
+                throttle,
+            )
+            .await;
+        }
+
         let mut row_stream = dumper.stream().await.map_err(log_err)?;
         let mut rows = Vec::with_capacity(BATCH_SIZE);
 
@@ -409,6 +635,7 @@ impl MySqlReplicator {
             if rows.len() == BATCH_SIZE {
                 // We aggregate rows into batches and then send them all to noria
                 let send_rows = std::mem::replace(&mut rows, Vec::with_capacity(BATCH_SIZE));
+                throttle.acquire(&send_rows).await;
                 table_mutator.insert_many(send_rows).await.map_err(|err| {
                     progress_percentage_metric.set(0.0);
                     log_err(err)
@@ -429,11 +656,13 @@ impl MySqlReplicator {
         }
 
         if !rows.is_empty() {
+            throttle.acquire(&rows).await;
             table_mutator.insert_many(rows).await.map_err(|err| {
                 progress_percentage_metric.set(0.0);
                 log_err(err)
             })?;
         }
+        table_mutator.flush_operations().await?;
 
         info!(rows_replicated = %cnt, "Replication finished");
         progress_percentage_metric.set(100.0);
@@ -441,6 +670,168 @@ impl MySqlReplicator {
         Ok(())
     }
 
+    /// Replicate a single table from `dump` into ReadySet, coercing the values of each row to the
+    /// types of the table's `columns` and calling `insert_many` in batches
+    async fn replicate_table_from_dump(
+        dump: Arc<DumpFile>,
+        mut table_mutator: readyset_client::Table,
+        columns: Vec<(String, DfType)>,
+        snapshot_report_interval_secs: u16,
+        throttle: SnapshotThrottle,
+    ) -> ReadySetResult<()> {
+        let table = table_mutator.table_name().clone();
+        let mut row_stream = dump.rows(&table, columns).await.map_err(log_err)?;
+        let mut rows = Vec::with_capacity(BATCH_SIZE);
+        let mut cnt = 0;
+
+        info!("Replication from dump file started");
+
+        table_mutator.set_snapshot_mode(true).await?;
+        let progress_percentage_metric: metrics::Gauge = register_gauge!(
+            recorded::REPLICATOR_SNAPSHOT_PERCENT,
+            "name" => table.display(nom_sql::Dialect::MySQL).to_string(),
+        );
+
+        let mut last_report_time = Instant::now();
+        let snapshot_report_interval_secs = snapshot_report_interval_secs as u64;
+
+        while let Some(row) = row_stream.next().await.map_err(|err| {
+            progress_percentage_metric.set(0.0);
+            log_err(err)
+        })? {
+            rows.push(row);
+            cnt += 1;
+
+            if rows.len() == BATCH_SIZE {
+                let send_rows = std::mem::replace(&mut rows, Vec::with_capacity(BATCH_SIZE));
+                throttle.acquire(&send_rows).await;
+                table_mutator.insert_many(send_rows).await.map_err(|err| {
+                    progress_percentage_metric.set(0.0);
+                    log_err(err)
+                })?;
+            }
+
+            // The number of rows in the dump isn't known up front, so only the rows replicated so
+            // far are reported
+            if snapshot_report_interval_secs != 0
+                && last_report_time.elapsed().as_secs() > snapshot_report_interval_secs
+            {
+                last_report_time = Instant::now();
+                info!(rows_replicated = %cnt, "Snapshotting progress");
+            }
+        }
+
+        if !rows.is_empty() {
+            throttle.acquire(&rows).await;
+            table_mutator.insert_many(rows).await.map_err(|err| {
+                progress_percentage_metric.set(0.0);
+                log_err(err)
+            })?;
+        }
+
+        info!(rows_replicated = %cnt, "Replication finished");
+        progress_percentage_metric.set(100.0);
+
+        Ok(())
+    }
+
+    /// Replicate a single table from the provided TableDumper into ReadySet one chunk at a time,
+    /// in primary key order, starting after the last key of `checkpoint`.
+    ///
+    /// Every chunk is read in the dumper's transaction, which is what gives all the chunks a
+    /// consistent view of the table, so a chunk that fails to be read isn't retried: the error may
+    /// well have ended the transaction.
+    ///
+    /// After each chunk is written, `checkpoint` is updated and persisted to `checkpoints`, if set
+    async fn replicate_table_chunked(
+        mut dumper: TableDumper,
+        mut table_mutator: readyset_client::Table,
+        nrows: usize,
+        snapshot_report_interval_secs: u16,
+        mut checkpoint: SnapshotCheckpoint,
+        checkpoints: Option<SnapshotCheckpoints>,
+        throttle: SnapshotThrottle,
+    ) -> ReadySetResult<()> {
+        if checkpoint.last_key.is_empty() {
+            info!(rows = %nrows, "Replication started");
+        } else {
+            info!(
+                rows = %nrows,
+                rows_replicated = %checkpoint.rows,
+                chunks = %checkpoint.chunks,
+                "Replication resumed from checkpoint"
+            );
+        }
+
+        table_mutator.set_snapshot_mode(true).await?;
+        let table = table_mutator.table_name().clone();
+        let progress_percentage_metric: metrics::Gauge = register_gauge!(
+            recorded::REPLICATOR_SNAPSHOT_PERCENT,
+            "name" => table.display(nom_sql::Dialect::MySQL).to_string(),
+        );
+
+        let start_time = Instant::now();
+        let mut last_report_time = start_time;
+        let snapshot_report_interval_secs = snapshot_report_interval_secs as u64;
+        let start_rows = checkpoint.rows;
+
+        loop {
+            let after = (!checkpoint.last_key.is_empty()).then_some(&checkpoint.last_key[..]);
+            let chunk = dumper.read_chunk(after).await.map_err(|error| {
+                progress_percentage_metric.set(0.0);
+                log_err(error)
+            })?;
+
+            let Some(last_key) = chunk.last().map(|row| dumper.chunk_key(row)) else {
+                break;
+            };
+            let chunk_rows = chunk.len();
+
+            for batch in &chunk.into_iter().chunks(BATCH_SIZE) {
+                let batch = batch.collect::<Vec<_>>();
+                throttle.acquire(&batch).await;
+                table_mutator.insert_many(batch).await.map_err(|err| {
+                    progress_percentage_metric.set(0.0);
+                    log_err(err)
+                })?;
+            }
+
+            checkpoint.last_key = last_key;
+            checkpoint.rows += chunk_rows;
+            checkpoint.chunks += 1;
+            if let Some(checkpoints) = &checkpoints {
+                if let Err(error) = checkpoints.save(&table, &checkpoint).await {
+                    warn!(%error, "Could not save snapshot checkpoint");
+                }
+            }
+
+            let cnt = checkpoint.rows;
+            let progress_percent = (cnt as f64 / nrows as f64) * 100.;
+            progress_percentage_metric.set(progress_percent);
+            debug!(chunk = checkpoint.chunks, rows_replicated = %cnt, "Replicated chunk");
+
+            if snapshot_report_interval_secs != 0
+                && last_report_time.elapsed().as_secs() > snapshot_report_interval_secs
+            {
+                last_report_time = Instant::now();
+                let estimate = crate::estimate_remaining_time(
+                    start_time.elapsed(),
+                    (cnt - start_rows) as f64,
+                    nrows.saturating_sub(start_rows) as f64,
+                );
+                let progress = format!("{:.2}%", progress_percent);
+                info!(rows_replicated = %cnt, chunks = %checkpoint.chunks, %progress, %estimate, "Snapshotting progress");
+            }
+        }
+
+        table_mutator.flush_operations().await?;
+
+        info!(rows_replicated = %checkpoint.rows, chunks = %checkpoint.chunks, "Replication finished");
+        progress_percentage_metric.set(100.0);
+
+        Ok(())
+    }
+
     /// This function replicates an entire MySQL database into a clean
     /// ReadySet deployment.
     ///
@@ -451,14 +842,29 @@ impl MySqlReplicator {
     ///   the schema
     /// * `extend_recipe`: Replicate and install the recipe (`CREATE TABLE` ...; `CREATE VIEW` ...;)
     ///   in addition to the rows
+    /// * `full_snapshot`: Snapshot *all* tables, even those that already have replication offsets
+    ///   in ReadySet
     pub(crate) async fn snapshot_to_noria(
         mut self,
         noria: &mut readyset_client::ReadySetHandle,
         db_schemas: &mut DatabaseSchemas,
         snapshot_report_interval_secs: u16,
+        full_snapshot: bool,
     ) -> ReadySetResult<()> {
+        // A full snapshot drops every table, so there's nothing left to resume
+        if full_snapshot {
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.clear(&self.table_filter).await?;
+            }
+        }
+
         let result = self
-            .replicate_to_noria_with_table_locks(noria, db_schemas, snapshot_report_interval_secs)
+            .replicate_to_noria_with_table_locks(
+                noria,
+                db_schemas,
+                snapshot_report_interval_secs,
+                full_snapshot,
+            )
             .await;
 
         // Wait for all connections to finish, not strictly necessary
@@ -476,10 +882,12 @@ impl MySqlReplicator {
         noria: &mut readyset_client::ReadySetHandle,
         db_schemas: &mut DatabaseSchemas,
         snapshot_report_interval_secs: u16,
+        full_snapshot: bool,
     ) -> ReadySetResult<()> {
         // NOTE: There are two ways to prevent DDL changes in MySQL:
         // `FLUSH TABLES WITH READ LOCK` or `LOCK INSTANCE FOR BACKUP`. Both are not
-        // possible in RDS however.
+        // possible in RDS however. MariaDB doesn't have `LOCK INSTANCE FOR BACKUP`, but
+        // `BACKUP STAGE BLOCK_DDL` does the same thing there.
 
         // It would be really good if we could prevent DDL changes during snapshotting,
         // but in the common case we are running on AWS RDS, and it is simply not allowed
@@ -491,32 +899,58 @@ impl MySqlReplicator {
         // lock the metadata for the replicated tables, however if new `CREATE TABLE`
         // statements are issued between the time when we collect the existing table list
         // and get the binlog position, we will not be able to detect them.
-        let _instance_lock = {
+        let mut instance_lock = {
             let mut conn = self.pool.get_conn().await?;
-            match conn.query_drop("LOCK INSTANCE FOR BACKUP").await {
-                Ok(_) => Some(conn),
+            let flavor = ServerFlavor::detect(&mut conn).await?;
+            let (lock, unlock) = flavor.ddl_lock_statements();
+            let mut locked = Ok(());
+            for statement in lock {
+                locked = conn.query_drop(*statement).await;
+                if locked.is_err() {
+                    break;
+                }
+            }
+            match locked {
+                Ok(_) => Some((conn, unlock)),
                 Err(err) => {
                     warn!(%err, "Failed to aquire instance lock, DDL changes may cause inconsistency");
+                    // A `BACKUP STAGE` may have started before a later stage failed
+                    let _ = conn.query_drop(unlock).await;
                     None
                 }
             }
         };
 
-        let (_meta_lock, table_list) = self
-            .load_recipe_with_meta_lock(noria, db_schemas)
+        let result = async {
+            let (_meta_lock, table_list) = self
+                .load_recipe_with_meta_lock(noria, db_schemas, full_snapshot)
+                .await
+                .map_err(log_err)?;
+
+            // Replication offsets could change following a schema update, so get a new list
+            let replication_offsets =
+                source_replication_offsets(noria, self.source.as_deref(), &self.table_filter)
+                    .await?;
+
+            self.dump_tables(
+                noria,
+                table_list,
+                &replication_offsets,
+                snapshot_report_interval_secs,
+            )
             .await
-            .map_err(log_err)?;
+        }
+        .await;
 
-        // Replication offsets could change following a schema update, so get a new list
-        let replication_offsets = noria.replication_offsets().await?;
+        // A MariaDB backup stage isn't released when the connection is returned to the pool, so
+        // release the lock explicitly, whether or not the snapshot succeeded
+        if let Some((conn, unlock)) = &mut instance_lock {
+            if let Err(err) = conn.query_drop(*unlock).await {
+                warn!(%err, "Failed to release instance lock");
+            }
+        }
 
-        self.dump_tables(
-            noria,
-            table_list,
-            &replication_offsets,
-            snapshot_report_interval_secs,
-        )
-        .await
+        result
     }
 
     /// Spawns a new tokio task that replicates a given table to noria, returning
@@ -531,29 +965,99 @@ impl MySqlReplicator {
             "Snapshotting table",
             table = %table.display(nom_sql::Dialect::MySQL)
         );
-        span.in_scope(|| info!("Acquiring read lock"));
-        let mut read_lock = self.lock_table(&table).await?;
-        // We acquire the position for each table individually, since it changes from
-        // one lock to the other
-        let repl_offset = ReplicationOffset::try_from(self.get_binlog_position().await?)?;
-        span.in_scope(|| info!("Snapshotting table"));
 
-        let dumper = self.dump_table(&table).instrument(span.clone()).await?;
+        // Tables in the dump file are read from there, as of the binlog position it was taken at,
+        // unless they've been requested to be resnapshotted from the upstream database
+        let dump = self
+            .dump
+            .as_ref()
+            .filter(|dump| dump.contains(&table) && !self.resnapshot_tables.contains(&table));
+        if let Some(dump) = dump {
+            span.in_scope(|| info!("Snapshotting table from dump file"));
+            let dump = Arc::clone(dump);
+            let repl_offset = ReplicationOffset::try_from(dump.position().clone())?;
+            let table_mutator = noria.table(table.clone()).instrument(span.clone()).await?;
+            let columns = table_column_types(&table_mutator, Dialect::DEFAULT_MYSQL);
+            let throttle = self.throttle.clone();
+
+            return Ok(tokio::spawn(async move {
+                (
+                    table,
+                    repl_offset,
+                    Self::replicate_table_from_dump(
+                        dump,
+                        table_mutator,
+                        columns,
+                        snapshot_report_interval_secs,
+                        throttle,
+                    )
+                    .instrument(span)
+                    .await,
+                )
+            }));
+        }
 
-        // At this point we have a transaction that will see *that* table at *this* binlog
-        // position, so we can drop the read lock
-        read_lock.query_drop("UNLOCK TABLES").await?;
-        span.in_scope(|| info!("Read lock released"));
+        span.in_scope(|| info!("Snapshotting table"));
+        let (repl_offset, dumper) = self
+            .dump_table_at_current_position(&table)
+            .instrument(span.clone())
+            .await?;
 
-        let table_mutator = noria.table(table.clone()).instrument(span.clone()).await?;
+        let mut table_mutator = noria.table(table.clone()).instrument(span.clone()).await?;
+
+        // Tables requested to be resnapshotted start over from scratch. Their operations are
+        // buffered and sent in one batch once every row has been read, so that readers keep seeing
+        // the table's old rows until they're replaced by the new ones, rather than an empty table,
+        // and so they aren't checkpointed either.
+        let resnapshot = self.resnapshot_tables.contains(&table);
+        if resnapshot {
+            span.in_scope(|| info!("Truncating table to resnapshot it"));
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.remove(&table).await?;
+            }
+            table_mutator.buffer_operations();
+            table_mutator.truncate().instrument(span.clone()).await?;
+        }
+
+        // Tables read in chunks resume from their checkpoint, if they have one, in which case the
+        // table's replication offset is the one its snapshot was originally started at
+        let checkpoint = if dumper.is_chunked() {
+            let resumed = match &self.checkpoints {
+                Some(checkpoints) => checkpoints.load(&table).await.unwrap_or_else(|error| {
+                    span.in_scope(|| warn!(%error, "Could not load snapshot checkpoint"));
+                    None
+                }),
+                None => None,
+            };
+            Some(resumed.unwrap_or_else(|| SnapshotCheckpoint {
+                offset: repl_offset.clone(),
+                last_key: vec![],
+                rows: 0,
+                chunks: 0,
+            }))
+        } else {
+            None
+        };
+        let repl_offset = checkpoint
+            .as_ref()
+            .map_or(repl_offset, |checkpoint| checkpoint.offset.clone());
+        let checkpoints = self.checkpoints.clone().filter(|_| !resnapshot);
+        let throttle = self.throttle.clone();
 
         Ok(tokio::spawn(async move {
             (
                 table,
                 repl_offset,
-                Self::replicate_table(dumper, table_mutator, snapshot_report_interval_secs)
-                    .instrument(span)
-                    .await,
+                Self::replicate_table(
+                    dumper,
+                    table_mutator,
+                    snapshot_report_interval_secs,
+                    checkpoint,
+                    checkpoints,
+                    throttle,
+                )
+                .instrument(span)
+                .await,
             )
         }))
     }
@@ -566,6 +1070,10 @@ impl MySqlReplicator {
         replication_offsets: &ReplicationOffsets,
         snapshot_report_interval_secs: u16,
     ) -> ReadySetResult<()> {
+        let parallelism = self
+            .snapshot_parallelism
+            .unwrap_or(MAX_SNAPSHOT_BATCH)
+            .max(1);
         let mut replication_tasks = FuturesUnordered::new();
         let mut compacting_tasks = FuturesUnordered::new();
 
@@ -573,7 +1081,7 @@ impl MySqlReplicator {
         // We pop front because we add the tables before the views, and the views depend on the
         // tables. TODO: do we need to fully finish tables before views?
         while let Some(table) = table_list.pop() {
-            if replication_offsets.has_table(&table) {
+            if replication_offsets.has_table(&table) && !self.resnapshot_tables.contains(&table) {
                 info!(
                     table = %table.display(nom_sql::Dialect::MySQL),
                     "Replication offset already exists for table, skipping snapshot"
@@ -585,7 +1093,7 @@ impl MySqlReplicator {
                 );
             }
 
-            if replication_tasks.len() == MAX_SNAPSHOT_BATCH {
+            if replication_tasks.len() >= parallelism {
                 break;
             }
         }
@@ -595,6 +1103,7 @@ impl MySqlReplicator {
             match task_result.unwrap() {
                 (table, repl_offset, Ok(())) => {
                     let mut noria_table = noria.table(table.clone()).await?;
+                    let checkpoints = self.checkpoints.clone();
                     compacting_tasks.push(tokio::spawn(async move {
                         let span = info_span!(
                             "Compacting table",
@@ -607,6 +1116,15 @@ impl MySqlReplicator {
                             .instrument(span.clone())
                             .await?;
 
+                        // The table no longer needs to be resumed
+                        if let Some(checkpoints) = checkpoints {
+                            if let Err(error) = checkpoints.remove(&table).await {
+                                span.in_scope(
+                                    || warn!(%error, "Could not remove snapshot checkpoint"),
+                                );
+                            }
+                        }
+
                         span.in_scope(|| info!("Set replication offset, compacting table"));
                         noria_table
                             .set_snapshot_mode(false)
@@ -632,9 +1150,10 @@ impl MySqlReplicator {
             }
 
             // If still have tables to snapshot add them to the task list
-            while replication_tasks.len() < MAX_SNAPSHOT_BATCH && !table_list.is_empty() {
+            while replication_tasks.len() < parallelism && !table_list.is_empty() {
                 let table = table_list.pop().expect("Not empty");
-                if replication_offsets.has_table(&table) {
+                if replication_offsets.has_table(&table) && !self.resnapshot_tables.contains(&table)
+                {
                     info!(
                         table = %table.display(nom_sql::Dialect::MySQL),
                         "Replication offset already exists for table, skipping snapshot"
@@ -663,6 +1182,9 @@ impl MySqlReplicator {
 pub(crate) struct TableDumper {
     query_count: String,
     query: String,
+    /// If set, the table is read in chunks using these queries rather than all at once with
+    /// `query`
+    chunks: Option<ChunkQueries>,
     tx: mysql::Transaction<'static>,
 }
 
@@ -672,6 +1194,108 @@ impl TableDumper {
             query: self.tx.exec_iter(&self.query, ()).await?,
         })
     }
+
+    /// Call `f` with every row of the table, reading it in chunks if it's read in chunks
+    pub(crate) async fn for_each_row(
+        &mut self,
+        mut f: impl FnMut(Vec<readyset_data::DfValue>) -> ReadySetResult<()>,
+    ) -> ReadySetResult<()> {
+        if self.is_chunked() {
+            let mut after = None;
+            loop {
+                let chunk = self.read_chunk(after.as_deref()).await?;
+                let Some(last) = chunk.last() else {
+                    return Ok(());
+                };
+                after = Some(self.chunk_key(last));
+                chunk.into_iter().try_for_each(&mut f)?;
+            }
+        } else {
+            let mut rows = self.stream().await?;
+            while let Some(row) = rows.next().await? {
+                f(row)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Returns true if the table is read in chunks
+    fn is_chunked(&self) -> bool {
+        self.chunks.is_some()
+    }
+
+    /// Read the chunk of rows following the row with the primary key `after`, or the first chunk
+    /// if `after` is `None`. Returns an empty chunk once all the rows have been read.
+    async fn read_chunk(
+        &mut self,
+        after: Option<&[readyset_data::DfValue]>,
+    ) -> ReadySetResult<Vec<Vec<readyset_data::DfValue>>> {
+        let chunks = self
+            .chunks
+            .as_ref()
+            .expect("Reading chunks of a table that isn't chunked");
+        let rows: Vec<mysql::Row> = match after {
+            None => self.tx.exec(&chunks.first, ()).await?,
+            Some(key) => {
+                let params = key
+                    .iter()
+                    .map(|val| mysql_common::value::Value::try_from(val).map(value_from_common))
+                    .collect::<ReadySetResult<Vec<_>>>()?;
+                self.tx.exec(&chunks.next, params).await?
+            }
+        };
+        rows.into_iter().map(mysql_row_to_noria_row).collect()
+    }
+
+    /// Returns the values of the primary key columns of `row`
+    fn chunk_key(&self, row: &[readyset_data::DfValue]) -> Vec<readyset_data::DfValue> {
+        let chunks = self
+            .chunks
+            .as_ref()
+            .expect("Reading chunks of a table that isn't chunked");
+        chunks.key.iter().map(|idx| row[*idx].clone()).collect()
+    }
+}
+
+/// The queries used to read a table in fixed-size chunks, ordered by its primary key, so that
+/// each chunk picks up from where the previous one left off
+#[derive(Debug)]
+struct ChunkQueries {
+    /// The query for the first chunk
+    first: String,
+    /// The query for every subsequent chunk, taking the primary key of the last row of the
+    /// previous chunk as parameters
+    next: String,
+    /// The indices of the primary key columns within each row
+    key: Vec<usize>,
+}
+
+impl ChunkQueries {
+    fn new(
+        select_list: &str,
+        table: &Relation,
+        key_columns: &[String],
+        key: Vec<usize>,
+        chunk_size: u64,
+    ) -> Self {
+        let table = table.display(nom_sql::Dialect::MySQL);
+        let key_list = key_columns
+            .iter()
+            .map(|name| format!("`{}`", name.replace('`', "``")))
+            .join(", ");
+        let placeholders = key_columns.iter().map(|_| "?").join(", ");
+
+        Self {
+            first: format!(
+                "select {select_list} from {table} order by {key_list} limit {chunk_size}"
+            ),
+            next: format!(
+                "select {select_list} from {table} where ({key_list}) > ({placeholders}) \
+                 order by {key_list} limit {chunk_size}"
+            ),
+            key,
+        }
+    }
 }
 
 // Just another helper struct to make it streamable
@@ -690,15 +1314,49 @@ impl<'a> TableStream<'a> {
 }
 
 /// Convert each entry in a row to a ReadySet type that can be inserted into the base tables
-fn mysql_row_to_noria_row(row: mysql::Row) -> ReadySetResult<Vec<readyset_data::DfValue>> {
+pub(crate) fn mysql_row_to_noria_row(
+    row: mysql::Row,
+) -> ReadySetResult<Vec<readyset_data::DfValue>> {
     let mut noria_row = Vec::with_capacity(row.len());
     for idx in 0..row.len() {
         let val = value_to_value(row.as_ref(idx).unwrap());
-        noria_row.push(readyset_data::DfValue::try_from(val)?);
+        let val = match val {
+            // Spatial values have to be kept as bytes, even if they happen to be valid UTF-8, to
+            // match the values we get from the binlog
+            mysql_common::value::Value::Bytes(ref bytes)
+                if row.columns_ref()[idx].column_type() == ColumnType::MYSQL_TYPE_GEOMETRY =>
+            {
+                geometry_value(bytes).map_err(|details| ReadySetError::DfValueConversionError {
+                    src_type: "GEOMETRY".into(),
+                    target_type: "ByteArray".into(),
+                    details,
+                })?
+            }
+            val => readyset_data::DfValue::try_from(val)?,
+        };
+        noria_row.push(val);
     }
     Ok(noria_row)
 }
 
+/// The inverse of [`value_to_value`]
+fn value_from_common(val: mysql_common::value::Value) -> mysql::Value {
+    match val {
+        mysql_common::value::Value::NULL => mysql::Value::NULL,
+        mysql_common::value::Value::Bytes(b) => mysql::Value::Bytes(b),
+        mysql_common::value::Value::Int(i) => mysql::Value::Int(i),
+        mysql_common::value::Value::UInt(u) => mysql::Value::UInt(u),
+        mysql_common::value::Value::Float(f) => mysql::Value::Float(f),
+        mysql_common::value::Value::Double(d) => mysql::Value::Double(d),
+        mysql_common::value::Value::Date(y, m, d, hh, mm, ss, us) => {
+            mysql::Value::Date(y, m, d, hh, mm, ss, us)
+        }
+        mysql_common::value::Value::Time(is_neg, d, hh, mm, ss, us) => {
+            mysql::Value::Time(is_neg, d, hh, mm, ss, us)
+        }
+    }
+}
+
 /// Although both are of the exact same type, there is a conflict between reexported versions
 fn value_to_value(val: &mysql::Value) -> mysql_common::value::Value {
     match val {
@@ -734,3 +1392,72 @@ impl Display for TableKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_queries() {
+        let queries = ChunkQueries::new(
+            "*",
+            &Relation {
+                schema: Some("db".into()),
+                name: "t".into(),
+            },
+            &["a".to_owned(), "b".to_owned()],
+            vec![0, 2],
+            100,
+        );
+        assert_eq!(
+            queries.first,
+            "select * from `db`.`t` order by `a`, `b` limit 100"
+        );
+        assert_eq!(
+            queries.next,
+            "select * from `db`.`t` where (`a`, `b`) > (?, ?) order by `a`, `b` limit 100"
+        );
+        assert_eq!(queries.key, vec![0, 2]);
+    }
+
+    #[test]
+    fn add_column_collations_fills_in_defaults() {
+        let mut changelist = ChangeList::from_str(
+            "CREATE TABLE t (a INT, b VARCHAR(10), c TEXT COLLATE utf8mb4_bin)",
+            Dialect::DEFAULT_MYSQL,
+        )
+        .unwrap();
+        add_column_collations(
+            &mut changelist,
+            &[
+                (
+                    "b".to_owned(),
+                    "utf8mb4".to_owned(),
+                    "utf8mb4_0900_ai_ci".to_owned(),
+                ),
+                (
+                    "c".to_owned(),
+                    "utf8mb4".to_owned(),
+                    "utf8mb4_bin".to_owned(),
+                ),
+            ],
+        );
+
+        let Change::CreateTable(CreateTableStatement { body: Ok(body), .. }) = &changelist.changes[0]
+        else {
+            panic!("Expected a CREATE TABLE");
+        };
+        assert_eq!(body.fields[0].collation(), None);
+        assert_eq!(body.fields[1].character_set(), Some("utf8mb4"));
+        assert_eq!(body.fields[1].collation(), Some("utf8mb4_0900_ai_ci"));
+        assert_eq!(body.fields[2].character_set(), Some("utf8mb4"));
+        assert_eq!(
+            body.fields[2]
+                .constraints
+                .iter()
+                .filter(|c| matches!(c, ColumnConstraint::Collation(_)))
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,549 @@
+//! Choosing which MySQL server to read the binlog from, out of the primary given by
+//! [`UpstreamConfig::upstream_db_url`] and its replicas given by
+//! [`UpstreamConfig::replication_source_candidates`].
+//!
+//! Each server is checked by connecting to it and timing a trivial query, and reading its binlog
+//! position and executed GTID set (and, for replicas, how far behind the primary it is). The
+//! healthy server with the lowest latency is chosen, but replication only switches away from a
+//! healthy server if another one has much lower latency, so that it doesn't switch back and forth
+//! between servers with similar latencies.
+//!
+//! Binlog file names and positions aren't comparable between servers, so replication always
+//! resumes on a server with GTID auto-positioning. That's only possible once the replication
+//! offsets of the schema and every table are aligned at the same transaction boundary, since a
+//! single GTID set then records everything replicated to every table. They're aligned whenever
+//! replication stops cleanly, including to switch servers, which waits for the end of the current
+//! transaction. Each time replication starts with aligned offsets, they're rebased onto the start
+//! of the chosen server's binlog, keeping their GTID set, so that every event we read from that
+//! server is one we haven't replicated yet.
+//!
+//! If replication stopped without aligning the offsets, such as after an error, it resumes from
+//! the server it was last using, and can only switch servers once it next stops cleanly. Since
+//! which server that was isn't persisted, this is the primary if ReadySet itself restarted.
+//!
+//! [`UpstreamConfig::upstream_db_url`]: database_utils::UpstreamConfig::upstream_db_url
+//! [`UpstreamConfig::replication_source_candidates`]: database_utils::UpstreamConfig::replication_source_candidates
+
+use std::time::{Duration, Instant};
+
+use database_utils::UpstreamConfig;
+use metrics::counter;
+use mysql_async as mysql;
+use mysql_async::prelude::Queryable;
+use readyset_client::metrics::recorded;
+use readyset_client::replication::{ReplicationOffset, ReplicationOffsets};
+use readyset_client::ReadySetHandle;
+use readyset_errors::{invalid_err, ReadySetError, ReadySetResult};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+use super::snapshot::binlog_position;
+use super::{mysql_error, BinlogPosition, GtidSet};
+use crate::noria_adapter::{set_source_schema_replication_offset, source_replication_offsets};
+use crate::table_filter::TableFilter;
+
+/// Replication only switches from a healthy server to one whose latency is less than this
+/// fraction of its own
+const SWITCH_LATENCY_RATIO: f64 = 0.5;
+
+/// The state of a server, as of the last time it was checked
+#[derive(Debug, Clone)]
+struct Probe {
+    /// The round trip time of a trivial query to the server
+    latency: Duration,
+    /// How far behind the primary the server is, or `None` if it isn't replicating from the
+    /// primary (or can't tell how far behind it is)
+    lag: Option<Duration>,
+    /// The server's current binlog position, including the set of GTIDs it has executed
+    position: BinlogPosition,
+}
+
+impl Probe {
+    /// Returns true if the server is at most `max_lag` behind the primary, and has executed every
+    /// transaction in `replicated`, so that replication can resume on it
+    fn is_healthy(&self, replicated: &GtidSet, max_lag: Duration) -> bool {
+        self.lag.map_or(false, |lag| lag <= max_lag)
+            && self
+                .position
+                .gtid_set
+                .as_ref()
+                .map_or(false, |executed| replicated.is_subset(executed))
+    }
+}
+
+/// Returns the index of the server to replicate from, given the result of checking each server
+/// (or `None` if it couldn't be checked), the index of the server we're replicating from, and the
+/// set of GTIDs we've replicated so far.
+///
+/// This is the healthy server with the lowest latency, unless `current` is healthy and the lowest
+/// latency isn't less than [`SWITCH_LATENCY_RATIO`] of its latency. If no server is healthy,
+/// returns `current`.
+fn choose(
+    current: usize,
+    probes: &[Option<Probe>],
+    replicated: &GtidSet,
+    max_lag: Duration,
+) -> usize {
+    let healthy = |i: usize| {
+        probes
+            .get(i)
+            .and_then(Option::as_ref)
+            .filter(|probe| probe.is_healthy(replicated, max_lag))
+    };
+    let Some((best, best_probe)) = (0..probes.len())
+        .filter_map(|i| healthy(i).map(|probe| (i, probe)))
+        .min_by_key(|(_, probe)| probe.latency)
+    else {
+        return current;
+    };
+
+    match healthy(current) {
+        Some(current_probe)
+            if best_probe.latency.as_secs_f64()
+                >= current_probe.latency.as_secs_f64() * SWITCH_LATENCY_RATIO =>
+        {
+            current
+        }
+        _ => best,
+    }
+}
+
+/// Returns the set of GTIDs replicated as of `offsets`, if every offset that's present is at the
+/// same position and records a GTID set, so that replication can resume from that set on any
+/// server. Returns `None` otherwise, including if no offsets are present at all.
+fn aligned_gtid_set(offsets: &ReplicationOffsets) -> ReadySetResult<Option<GtidSet>> {
+    let mut present = offsets
+        .schema
+        .iter()
+        .chain(offsets.tables.values().flatten());
+    let Some(first) = present.next() else {
+        return Ok(None);
+    };
+    let Some(gtid_set) = &first.gtid_set else {
+        return Ok(None);
+    };
+    if present.any(|offset| {
+        offset.replication_log_name != first.replication_log_name
+            || offset.offset != first.offset
+            || offset.gtid_set != first.gtid_set
+    }) {
+        return Ok(None);
+    }
+    Ok(Some(gtid_set.parse()?))
+}
+
+/// Returns the set of GTIDs replicated as of the most recent of `offsets`, which is the one whose
+/// GTID set contains those of all the others. Returns `None` if any offset that's present doesn't
+/// record a GTID set, or if no offsets are present at all.
+fn replicated_gtid_set(offsets: &ReplicationOffsets) -> ReadySetResult<Option<GtidSet>> {
+    let mut replicated: Option<GtidSet> = None;
+    for offset in offsets
+        .schema
+        .iter()
+        .chain(offsets.tables.values().flatten())
+    {
+        let gtid_set = match &offset.gtid_set {
+            Some(gtid_set) => gtid_set.parse::<GtidSet>()?,
+            None => return Ok(None),
+        };
+        match &replicated {
+            Some(max) if gtid_set.is_subset(max) => {}
+            Some(max) if !max.is_subset(&gtid_set) => {
+                return Err(ReadySetError::ReplicationFailed(format!(
+                    "Replication offsets have diverging GTID sets {max} and {gtid_set}"
+                )))
+            }
+            _ => replicated = Some(gtid_set),
+        }
+    }
+    Ok(replicated)
+}
+
+/// Returns how far behind the primary the replica `conn` is connected to is, or `None` if it
+/// isn't replicating
+async fn replica_lag(conn: &mut mysql::Conn) -> mysql::Result<Option<Duration>> {
+    let status: Option<mysql::Row> = match conn.query_first("SHOW REPLICA STATUS").await {
+        Ok(status) => status,
+        // Before MySQL 8.0.22, the statement is only called `SHOW SLAVE STATUS`
+        Err(mysql::Error::Server(_)) => conn.query_first("SHOW SLAVE STATUS").await?,
+        Err(error) => return Err(error),
+    };
+    Ok(status
+        .and_then(|status| {
+            status
+                .get::<Option<u64>, _>("Seconds_Behind_Source")
+                .or_else(|| status.get("Seconds_Behind_Master"))
+                .flatten()
+        })
+        .map(Duration::from_secs))
+}
+
+/// Chooses which server to read the MySQL binlog from, and watches for a better one while
+/// replicating. See the [module documentation](self).
+pub(crate) struct SourceSelector {
+    /// The connection options for each server, the primary first
+    servers: Vec<mysql::Opts>,
+    /// How far behind the primary a replica may be for us to replicate from it
+    max_lag: Duration,
+    /// How often to check every server while replicating
+    check_interval: Duration,
+    /// The index in [`Self::servers`] of the server we're replicating from
+    current: usize,
+}
+
+impl SourceSelector {
+    /// Build a selector to choose between the `primary` and the replicas given by
+    /// [`UpstreamConfig::replication_source_candidates`], returning `None` if there aren't any
+    /// replicas to choose from
+    pub(crate) fn new(
+        primary: mysql::Opts,
+        config: &UpstreamConfig,
+    ) -> ReadySetResult<Option<Self>> {
+        if config.replication_source_candidates.is_empty()
+            || config.mysql_binlog_replay_dir.is_some()
+        {
+            return Ok(None);
+        }
+        if !config.mysql_gtid_auto_position {
+            warn!(
+                "--replication-source-candidates requires --mysql-gtid-auto-position, only \
+                 replicating from the primary"
+            );
+            return Ok(None);
+        }
+
+        let mut servers = vec![primary];
+        for url in &config.replication_source_candidates {
+            servers.push(mysql::Opts::from_url(url.as_str()).map_err(|e| {
+                invalid_err!("Invalid URL supplied to --replication-source-candidates: {e}")
+            })?);
+        }
+        if let Some(cert_path) = &config.ssl_root_cert {
+            let ssl_opts = mysql::SslOpts::default().with_root_cert_path(Some(cert_path.clone()));
+            for server in &mut servers {
+                *server = mysql::OptsBuilder::from_opts(server.clone())
+                    .ssl_opts(ssl_opts.clone())
+                    .into();
+            }
+        }
+
+        Ok(Some(Self {
+            servers,
+            max_lag: config.replication_source_max_lag,
+            check_interval: config.replication_source_check_interval,
+            current: 0,
+        }))
+    }
+
+    /// The host and port of the server at `index`, for logs and metrics
+    fn host(&self, index: usize) -> String {
+        let opts = &self.servers[index];
+        format!("{}:{}", opts.ip_or_hostname(), opts.tcp_port())
+    }
+
+    /// Check the server at `index`
+    async fn probe(&self, index: usize) -> ReadySetResult<Probe> {
+        let mut conn = mysql::Conn::new(self.servers[index].clone())
+            .await
+            .map_err(mysql_error)?;
+        let start = Instant::now();
+        conn.query_drop("SELECT 1").await.map_err(mysql_error)?;
+        let latency = start.elapsed();
+        let lag = if index == 0 {
+            Some(Duration::ZERO)
+        } else {
+            replica_lag(&mut conn).await.map_err(mysql_error)?
+        };
+        let position = binlog_position(&mut conn).await.map_err(mysql_error)?;
+        let _ = conn.disconnect().await;
+        Ok(Probe {
+            latency,
+            lag,
+            position,
+        })
+    }
+
+    /// Check every server, logging any we can't connect to
+    async fn probe_all(&self) -> Vec<Option<Probe>> {
+        futures::future::join_all((0..self.servers.len()).map(|i| async move {
+            match self.probe(i).await {
+                Ok(probe) => Some(probe),
+                Err(error) => {
+                    warn!(%error, server = %self.host(i), "Could not check replication source");
+                    None
+                }
+            }
+        }))
+        .await
+    }
+
+    /// Record that we're replicating from the server at `index`
+    fn switch_to(&mut self, index: usize) {
+        if index != self.current {
+            let (from, to) = (self.host(self.current), self.host(index));
+            info!(%from, %to, "Switching replication source");
+            counter!(
+                recorded::REPLICATOR_SOURCE_SWITCHES,
+                1u64,
+                "from" => from,
+                "to" => to
+            );
+            self.current = index;
+        }
+    }
+
+    /// Choose the server to replicate from, with `offsets` as the replication offsets of the
+    /// upstream database named `source`, returning the server's connection options.
+    ///
+    /// If we're about to take a snapshot, which is always taken from the primary, the primary is
+    /// chosen. If the offsets that are present are aligned, they're rebased onto the start of the
+    /// chosen server's binlog, both in `offsets` and in ReadySet. Otherwise, the server we were
+    /// last using is chosen and the offsets are left as they are (see the
+    /// [module documentation](self)).
+    pub(crate) async fn prepare(
+        &mut self,
+        noria: &mut ReadySetHandle,
+        source: Option<&str>,
+        offsets: &mut ReplicationOffsets,
+        snapshot: bool,
+    ) -> ReadySetResult<mysql::Opts> {
+        let Some(replicated) = aligned_gtid_set(offsets)? else {
+            if snapshot && self.current != 0 {
+                if offsets.schema.is_some() || offsets.tables.values().any(Option::is_some) {
+                    // The offsets we have are positions in the binlog of the server we were
+                    // using, which can't be compared with that of the primary
+                    return Err(ReadySetError::FullResnapshotNeeded(format!(
+                        "Replication stopped while reading the binlog of {} without aligning \
+                         replication offsets, so they can't be used with a snapshot of the \
+                         primary",
+                        self.host(self.current)
+                    )));
+                }
+                self.switch_to(0);
+            }
+            return Ok(self.servers[self.current].clone());
+        };
+
+        let (index, position) = if snapshot {
+            (0, self.probe(0).await?.position)
+        } else {
+            let probes = self.probe_all().await;
+            let index = choose(self.current, &probes, &replicated, self.max_lag);
+            match probes.into_iter().nth(index).flatten() {
+                Some(probe) => (index, probe.position),
+                None => {
+                    return Err(ReadySetError::ReplicationFailed(format!(
+                        "No replication source is available, could not connect to {}",
+                        self.host(index)
+                    )))
+                }
+            }
+        };
+        self.switch_to(index);
+
+        let (basename, suffix) = position.binlog_file.rsplit_once('.').ok_or_else(|| {
+            ReadySetError::ReplicationFailed(format!(
+                "Invalid binlog file name {}",
+                position.binlog_file
+            ))
+        })?;
+        let base: ReplicationOffset = BinlogPosition {
+            binlog_file: format!("{basename}.{:0width$}", 0, width = suffix.len()),
+            position: 0,
+            gtid_set: Some(replicated),
+        }
+        .try_into()?;
+
+        info!(server = %self.host(index), offset = %base, "Rebasing replication offsets");
+        set_source_schema_replication_offset(noria, source, Some(&base)).await?;
+        offsets.schema = Some(base.clone());
+        for (table, offset) in &mut offsets.tables {
+            if let Some(offset) = offset {
+                noria
+                    .table(table.clone())
+                    .await?
+                    .set_replication_offset(base.clone())
+                    .await?;
+                *offset = base.clone();
+            }
+        }
+
+        Ok(self.servers[index].clone())
+    }
+
+    /// Every check interval, check every server, and notify `switch` once replication should
+    /// switch to a different server than the one it's using, because that server has become
+    /// unhealthy or another one has much lower latency. The GTIDs replicated so far are read from
+    /// the replication offsets of `source` in `noria`. Never returns.
+    pub(crate) async fn monitor(
+        &self,
+        mut noria: ReadySetHandle,
+        source: Option<&str>,
+        table_filter: &TableFilter,
+        switch: &Notify,
+    ) {
+        let mut interval = tokio::time::interval(self.check_interval);
+        // The first tick completes immediately, and we've only just chosen a server
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+
+            let replicated =
+                match source_replication_offsets(&mut noria, source, table_filter).await {
+                    Ok(offsets) => replicated_gtid_set(&offsets),
+                    Err(error) => Err(error),
+                };
+            let replicated = match replicated {
+                Ok(Some(replicated)) => replicated,
+                Ok(None) => continue,
+                Err(error) => {
+                    warn!(%error, "Could not load replicated GTIDs to check replication sources");
+                    continue;
+                }
+            };
+
+            let probes = self.probe_all().await;
+            let index = choose(self.current, &probes, &replicated, self.max_lag);
+            if index != self.current {
+                let current_healthy = probes[self.current]
+                    .as_ref()
+                    .map_or(false, |probe| probe.is_healthy(&replicated, self.max_lag));
+                if current_healthy {
+                    info!(
+                        to = %self.host(index),
+                        "Found a replication source with lower latency, failing back to it"
+                    );
+                } else {
+                    warn!(
+                        from = %self.host(self.current),
+                        to = %self.host(index),
+                        "Replication source is unhealthy, failing over"
+                    );
+                }
+                switch.notify_one();
+                return futures::future::pending().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "3e11fa47-71ca-11e1-9e33-c80aa9429562";
+
+    fn gtids(end: u64) -> GtidSet {
+        format!("{UUID}:1-{end}").parse().unwrap()
+    }
+
+    fn probe(latency_ms: u64, lag_secs: Option<u64>, executed: u64) -> Option<Probe> {
+        Some(Probe {
+            latency: Duration::from_millis(latency_ms),
+            lag: lag_secs.map(Duration::from_secs),
+            position: BinlogPosition {
+                binlog_file: "binlog.000001".into(),
+                position: 4,
+                gtid_set: Some(gtids(executed)),
+            },
+        })
+    }
+
+    const MAX_LAG: Duration = Duration::from_secs(30);
+
+    #[test]
+    fn chooses_lowest_latency_healthy_source() {
+        let probes = [probe(50, Some(0), 10), probe(5, Some(1), 10), None];
+        assert_eq!(choose(0, &probes, &gtids(10), MAX_LAG), 1);
+    }
+
+    #[test]
+    fn stays_unless_latency_is_much_lower() {
+        let probes = [probe(10, Some(0), 10), probe(8, Some(1), 10)];
+        assert_eq!(choose(0, &probes, &gtids(10), MAX_LAG), 0);
+        assert_eq!(choose(1, &probes, &gtids(10), MAX_LAG), 1);
+    }
+
+    #[test]
+    fn fails_over_from_unhealthy_source() {
+        // Too far behind the primary
+        let probes = [probe(50, Some(0), 10), probe(5, Some(60), 10)];
+        assert_eq!(choose(1, &probes, &gtids(10), MAX_LAG), 0);
+        // Not replicating
+        let probes = [probe(50, Some(0), 10), probe(5, None, 10)];
+        assert_eq!(choose(1, &probes, &gtids(10), MAX_LAG), 0);
+        // Unreachable
+        let probes = [probe(50, Some(0), 10), None];
+        assert_eq!(choose(1, &probes, &gtids(10), MAX_LAG), 0);
+    }
+
+    #[test]
+    fn skips_sources_missing_replicated_transactions() {
+        let probes = [probe(50, Some(0), 12), probe(5, Some(0), 11)];
+        assert_eq!(choose(0, &probes, &gtids(12), MAX_LAG), 0);
+        assert_eq!(choose(0, &probes, &gtids(11), MAX_LAG), 1);
+    }
+
+    #[test]
+    fn stays_if_nothing_is_healthy() {
+        let probes = [None, probe(5, Some(60), 10)];
+        assert_eq!(choose(1, &probes, &gtids(10), MAX_LAG), 1);
+    }
+
+    #[test]
+    fn aligned_gtid_set_requires_aligned_offsets() {
+        let offset = |position, end| ReplicationOffset {
+            gtid_set: Some(gtids(end).to_string()),
+            ..ReplicationOffset::from_binlog_position("binlog.000001", position).unwrap()
+        };
+        let mut offsets = ReplicationOffsets::default();
+        assert_eq!(aligned_gtid_set(&offsets).unwrap(), None);
+
+        offsets.schema = Some(offset(10, 5));
+        offsets.tables.insert("t1".into(), Some(offset(10, 5)));
+        offsets.tables.insert("t2".into(), None);
+        assert_eq!(aligned_gtid_set(&offsets).unwrap(), Some(gtids(5)));
+
+        // A table that's behind hasn't replicated every transaction in the schema's GTID set
+        offsets.tables.insert("t3".into(), Some(offset(7, 4)));
+        assert_eq!(aligned_gtid_set(&offsets).unwrap(), None);
+
+        // A table in the middle of a transaction has the same GTID set at a later position
+        offsets.tables.insert("t3".into(), Some(offset(12, 5)));
+        assert_eq!(aligned_gtid_set(&offsets).unwrap(), None);
+
+        offsets.tables.insert(
+            "t3".into(),
+            Some(ReplicationOffset {
+                gtid_set: None,
+                ..offset(10, 5)
+            }),
+        );
+        assert_eq!(aligned_gtid_set(&offsets).unwrap(), None);
+    }
+
+    #[test]
+    fn replicated_gtid_set_is_the_most_recent() {
+        let offset = |end| ReplicationOffset {
+            gtid_set: Some(gtids(end).to_string()),
+            ..ReplicationOffset::from_binlog_position("binlog.000001", end).unwrap()
+        };
+        let mut offsets = ReplicationOffsets {
+            schema: Some(offset(3)),
+            ..Default::default()
+        };
+        offsets.tables.insert("t1".into(), Some(offset(7)));
+        offsets.tables.insert("t2".into(), Some(offset(5)));
+        offsets.tables.insert("t3".into(), None);
+        assert_eq!(replicated_gtid_set(&offsets).unwrap(), Some(gtids(7)));
+
+        offsets.tables.insert(
+            "t4".into(),
+            Some(ReplicationOffset {
+                gtid_set: None,
+                ..offset(1)
+            }),
+        );
+        assert_eq!(replicated_gtid_set(&offsets).unwrap(), None);
+    }
+}
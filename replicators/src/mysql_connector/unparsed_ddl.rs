@@ -0,0 +1,233 @@
+//! Identification of the table changed by a DDL statement that we couldn't parse, so that
+//! [`UpstreamConfig::ddl_conflict_policy`](database_utils::UpstreamConfig) can be applied to it.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use nom_sql::Relation;
+
+/// A token of a DDL statement: either an identifier or keyword (with any backquotes removed), or
+/// a single punctuation character
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Word { text: String, quoted: bool },
+    Punct(char),
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, Token::Word { text, quoted: false } if text.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// Returns whether the `-` just read from `chars` starts a `-- ` comment, which has to be followed
+/// by whitespace (or the end of the statement)
+fn starts_dash_comment(chars: &Peekable<Chars>) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next() == Some('-') && ahead.next().map_or(true, char::is_whitespace)
+}
+
+/// Skip the rest of the current line of `chars`, including the line ending
+fn skip_line(chars: &mut Peekable<Chars>) {
+    for c in chars.by_ref() {
+        if c == '\n' {
+            break;
+        }
+    }
+}
+
+/// Split as much of `query` as we need to look at into tokens, skipping whitespace and comments
+fn tokenize(query: &str) -> Vec<Token> {
+    /// We never need to look further into a statement than this many tokens
+    const MAX_TOKENS: usize = 12;
+
+    let mut tokens = vec![];
+    let mut chars = query.chars().peekable();
+    while tokens.len() < MAX_TOKENS {
+        let Some(c) = chars.next() else {
+            break;
+        };
+        match c {
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = None;
+                for c in chars.by_ref() {
+                    if prev == Some('*') && c == '/' {
+                        break;
+                    }
+                    prev = Some(c);
+                }
+            }
+            '#' => skip_line(&mut chars),
+            '-' if starts_dash_comment(&chars) => skip_line(&mut chars),
+            '`' => {
+                let mut text = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '`' {
+                        // A doubled backquote is an escaped backquote
+                        if chars.peek() == Some(&'`') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                    text.push(c);
+                }
+                tokens.push(Token::Word { text, quoted: true });
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '$' => {
+                let mut text = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                        break;
+                    }
+                    text.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word {
+                    text,
+                    quoted: false,
+                });
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+    tokens
+}
+
+/// Parse a possibly schema-qualified table name from the start of `tokens`
+fn table_name(tokens: &[Token]) -> Option<Relation> {
+    match tokens {
+        [Token::Word { text: schema, .. }, Token::Punct('.'), Token::Word { text: name, .. }, ..] => {
+            Some(Relation {
+                schema: Some(schema.as_str().into()),
+                name: name.as_str().into(),
+            })
+        }
+        [Token::Word { text: name, .. }, ..] => Some(Relation {
+            schema: None,
+            name: name.as_str().into(),
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the table changed by the DDL statement `query`, if it's a statement that changes the
+/// schema or contents of a single existing or new table: `ALTER TABLE`, `CREATE TABLE` (other than
+/// for temporary tables), or `TRUNCATE TABLE`.
+///
+/// This only looks at the start of the statement, so that it can be used for statements we
+/// couldn't parse.
+pub(crate) fn unparsed_ddl_table(query: &str) -> Option<Relation> {
+    fn skip_keyword(rest: &mut &[Token], keyword: &str) -> bool {
+        match rest.split_first() {
+            Some((token, tail)) if token.is_keyword(keyword) => {
+                *rest = tail;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    let tokens = tokenize(query);
+    let mut rest = tokens.as_slice();
+    if skip_keyword(&mut rest, "alter") {
+        skip_keyword(&mut rest, "online");
+        skip_keyword(&mut rest, "ignore");
+        if !skip_keyword(&mut rest, "table") {
+            return None;
+        }
+    } else if skip_keyword(&mut rest, "create") {
+        if !skip_keyword(&mut rest, "table")
+            || (skip_keyword(&mut rest, "if")
+                && !(skip_keyword(&mut rest, "not") && skip_keyword(&mut rest, "exists")))
+        {
+            return None;
+        }
+    } else if skip_keyword(&mut rest, "truncate") {
+        skip_keyword(&mut rest, "table");
+    } else {
+        return None;
+    }
+
+    table_name(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(schema: Option<&str>, name: &str) -> Relation {
+        Relation {
+            schema: schema.map(Into::into),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn alter_table() {
+        assert_eq!(
+            unparsed_ddl_table("ALTER TABLE t ADD SOMETHING UNSUPPORTED"),
+            Some(relation(None, "t"))
+        );
+        assert_eq!(
+            unparsed_ddl_table("alter online table `db`.`my ``table``` foo"),
+            Some(relation(Some("db"), "my `table`"))
+        );
+        assert_eq!(
+            unparsed_ddl_table("/* comment */ ALTER TABLE db.t foo"),
+            Some(relation(Some("db"), "t"))
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            unparsed_ddl_table("-- comment\nALTER /* inline */ TABLE # another comment\n t foo"),
+            Some(relation(None, "t"))
+        );
+        assert_eq!(
+            unparsed_ddl_table("ALTER TABLE -- comment `not_t`\n db.t foo"),
+            Some(relation(Some("db"), "t"))
+        );
+        // `--` not followed by whitespace isn't a comment
+        assert_eq!(unparsed_ddl_table("--ALTER TABLE t foo"), None);
+    }
+
+    #[test]
+    fn unseparated_table_name() {
+        assert_eq!(
+            unparsed_ddl_table("ALTER TABLE`t`ADD COLUMN x int"),
+            Some(relation(None, "t"))
+        );
+    }
+
+    #[test]
+    fn create_table() {
+        assert_eq!(
+            unparsed_ddl_table("CREATE TABLE IF NOT EXISTS t (x unsupported)"),
+            Some(relation(None, "t"))
+        );
+        assert_eq!(unparsed_ddl_table("CREATE TEMPORARY TABLE t (x int)"), None);
+    }
+
+    #[test]
+    fn truncate_table() {
+        assert_eq!(
+            unparsed_ddl_table("TRUNCATE db.t"),
+            Some(relation(Some("db"), "t"))
+        );
+        assert_eq!(
+            unparsed_ddl_table("TRUNCATE TABLE t"),
+            Some(relation(None, "t"))
+        );
+    }
+
+    #[test]
+    fn other_statements() {
+        assert_eq!(unparsed_ddl_table("CREATE PROCEDURE p() BEGIN END"), None);
+        assert_eq!(unparsed_ddl_table("DROP TABLE t"), None);
+        assert_eq!(unparsed_ddl_table(""), None);
+    }
+}
@@ -1,17 +1,22 @@
 use std::collections::{hash_map, HashMap, HashSet};
 use std::mem;
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use database_utils::{DatabaseURL, UpstreamConfig};
+use database_utils::{
+    DatabaseType, DatabaseURL, DdlConflictPolicy, DestructiveDdlPolicy, ReplicationRewindPolicy,
+    UpstreamConfig,
+};
 use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
 use failpoint_macros::set_failpoint;
 use futures::FutureExt;
+use itertools::Itertools;
 use metrics::{counter, histogram};
 use mysql::prelude::Queryable;
 use mysql::{OptsBuilder, PoolConstraints, PoolOpts, SslOpts};
-use nom_sql::Relation;
+use nom_sql::{Relation, RenameTableOperation};
 use postgres_native_tls::MakeTlsConnector;
 use readyset_client::consistency::Timestamp;
 #[cfg(feature = "failure_injection")]
@@ -26,17 +31,31 @@ use readyset_errors::{
 };
 use readyset_telemetry_reporter::{TelemetryBuilder, TelemetryEvent, TelemetrySender};
 use readyset_util::select;
-use tokio::sync::Notify;
+use readyset_util::shutdown::{self, ShutdownReceiver};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Notify};
 use tracing::{debug, error, info, info_span, trace, warn, Instrument};
 use {mysql_async as mysql, tokio_postgres as pgsql};
 
+use crate::consistency_check::{self, ChecksumRequest, ConsistencyChecks, Upstream};
 use crate::db_util::{CreateSchema, DatabaseSchemas};
-use crate::mysql_connector::{MySqlBinlogConnector, MySqlReplicator};
+use crate::destructive_ddl::destructive_alter_table;
+use crate::mysql_connector::{
+    self, DumpFile, MySqlBinlogConnector, MySqlReplicator, SourceSelector,
+};
 use crate::postgres_connector::{
-    drop_publication, drop_readyset_schema, drop_replication_slot, PostgresReplicator,
+    self, drop_publication, drop_readyset_schema, drop_replication_slot, PostgresReplicator,
     PostgresWalConnector, PUBLICATION_NAME, REPLICATION_SLOT,
 };
+use crate::replication_capture::{CaptureFileConnector, ReplicationCapture};
+use crate::replication_health::{ReplicationHealth, SourceHealth};
+use crate::resnapshot_requests::{replicates, ResnapshotRequests};
+use crate::schema_export::SchemaExporter;
+use crate::snapshot_checkpoint::SnapshotCheckpoints;
+use crate::snapshot_throttle::SnapshotThrottle;
 use crate::table_filter::TableFilter;
+use crate::table_retention;
+use crate::time_zone::UpstreamTimeZone;
 
 /// Time to wait for requests to coalesce between snapshotting. Useful for preventing a series of
 /// DDL changes from thrashing snapshotting
@@ -44,7 +63,7 @@ const WAIT_BEFORE_RESNAPSHOT: Duration = Duration::from_secs(3);
 
 const RESNAPSHOT_SLOT: &str = "readyset_resnapshot";
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum ReplicationAction {
     TableAction {
         table: Relation,
@@ -54,12 +73,56 @@ pub(crate) enum ReplicationAction {
         /// the same transaction id. These id's should be monotonically
         /// increasing across transactions.
         txid: Option<u64>,
+        /// The text of the upstream statement that caused the writes, if the upstream logs it
+        /// (only MySQL with `binlog_rows_query_log_events` enabled)
+        query: Option<String>,
+    },
+    /// Writes to one or more tables that were committed upstream in a single transaction, which
+    /// are applied together at the position of the commit
+    Transaction {
+        /// The writes to each table, in the order in which each table was first written to
+        tables: Vec<(Relation, Vec<TableOperation>)>,
+        /// The transaction id of the transaction, as for [`ReplicationAction::TableAction`]
+        txid: Option<u64>,
+        /// The text of each upstream statement that caused the writes, in order, if the upstream
+        /// logs them (as for [`ReplicationAction::TableAction`])
+        #[serde(default)]
+        queries: Vec<String>,
+    },
+    /// One or more tables were truncated upstream, by a single `TRUNCATE` statement
+    Truncate {
+        /// The truncated tables, qualified with their schema
+        tables: Vec<Relation>,
     },
     DdlChange {
         schema: String,
         changes: Vec<Change>,
     },
+    /// One or more tables were renamed upstream, by a single `RENAME TABLE` statement
+    RenameTables {
+        /// The default schema of the statement
+        schema: String,
+        /// The renames, in the order they were made, with both names qualified with their schema
+        renames: Vec<RenameTableOperation>,
+    },
+    /// A DDL statement changing `table` that we couldn't parse, which is handled according to
+    /// [`UpstreamConfig::ddl_conflict_policy`]
+    UnparsedDdl {
+        /// The table changed by the statement, qualified with its schema
+        table: Relation,
+        /// The error we got parsing the statement
+        error: String,
+    },
     LogPosition,
+    /// The upstream database has no new events for us, which gives us a chance to apply any
+    /// writes we've buffered while waiting for a checkpoint
+    Heartbeat,
+    /// The upstream database reported that the data we've replicated from it may be inconsistent
+    /// with its own, so every table needs to be snapshotted again
+    ResnapshotRequired {
+        /// A description of what happened upstream
+        reason: String,
+    },
 }
 
 #[async_trait]
@@ -80,49 +143,89 @@ pub(crate) trait Connector {
         last_pos: &ReplicationOffset,
         until: Option<&ReplicationOffset>,
     ) -> ReadySetResult<(ReplicationAction, ReplicationOffset)>;
+
+    /// How far behind the upstream database replication was as of the last action returned by
+    /// [`Connector::next_action`], if the upstream database lets us tell
+    fn lag(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Returns true if the last action returned by [`Connector::next_action`] was only part of a
+    /// transaction, the rest of which hasn't been returned yet
+    fn in_transaction(&self) -> bool {
+        false
+    }
+
+    /// Tell the upstream database we've stopped replicating from it, having applied every action
+    /// up to `last_pos`, and close the connection we replicate over
+    async fn disconnect(&mut self, _last_pos: &ReplicationOffset) -> ReadySetResult<()> {
+        Ok(())
+    }
 }
 
-/// Cleans up replication related assets on the upstream database as supplied by the
+/// Cleans up replication related assets on each of the upstream databases supplied by the
 /// UpstreamConfig.
 pub async fn cleanup(config: UpstreamConfig) -> ReadySetResult<()> {
-    if let DatabaseURL::PostgreSQL(options) = config
+    for config in config.replication_sources() {
+        cleanup_source(config).await?;
+    }
+    Ok(())
+}
+
+/// Connects to the upstream Postgres database at [`UpstreamConfig::upstream_db_url`] with a
+/// replication connection, returning the client along with the name of the replication slot used
+/// to replicate from it, or `None` if the upstream database isn't Postgres
+async fn postgres_replication_client(
+    config: &UpstreamConfig,
+) -> ReadySetResult<Option<(pgsql::Client, String)>> {
+    let DatabaseURL::PostgreSQL(options) = config
         .upstream_db_url
         .as_ref()
         .ok_or_else(|| internal_err!("Replication URL not supplied"))?
         .parse()
         .map_err(|e| invalid_err!("Invalid URL supplied to --upstream-db-url: {e}"))?
-    {
-        let connector = {
-            let mut builder = native_tls::TlsConnector::builder();
-            if config.disable_upstream_ssl_verification {
-                builder.danger_accept_invalid_certs(true);
-            }
-            if let Some(root_cert) = config.get_root_cert().await {
-                builder.add_root_certificate(root_cert?);
-            }
-            builder.build().unwrap() // Never returns an error
-        };
-        let tls_connector = postgres_native_tls::MakeTlsConnector::new(connector);
+    else {
+        return Ok(None);
+    };
 
-        let repl_slot_name = match &config.replication_server_id {
-            Some(server_id) => {
-                format!("{}_{}", REPLICATION_SLOT, server_id)
-            }
-            _ => REPLICATION_SLOT.to_string(),
-        };
+    let connector = {
+        let mut builder = native_tls::TlsConnector::builder();
+        if config.disable_upstream_ssl_verification {
+            builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(root_cert) = config.get_root_cert().await {
+            builder.add_root_certificate(root_cert?);
+        }
+        builder.build().unwrap() // Never returns an error
+    };
+    let tls_connector = postgres_native_tls::MakeTlsConnector::new(connector);
 
-        let dbname = options.get_dbname().ok_or_else(|| {
-            ReadySetError::ReplicationFailed("No database specified for replication".to_string())
-        })?;
+    let repl_slot_name = match &config.replication_server_id {
+        Some(server_id) => {
+            format!("{}_{}", REPLICATION_SLOT, server_id)
+        }
+        _ => REPLICATION_SLOT.to_string(),
+    };
 
-        let mut cleanup_opts = options.clone();
+    let dbname = options.get_dbname().ok_or_else(|| {
+        ReadySetError::ReplicationFailed("No database specified for replication".to_string())
+    })?;
 
-        cleanup_opts
-            .dbname(dbname.as_ref())
-            .set_replication_database();
-        let (mut client, connection) = cleanup_opts.connect(tls_connector).await?;
-        let _connection_handle = tokio::spawn(connection);
+    let mut replication_opts = options.clone();
+
+    replication_opts
+        .dbname(dbname.as_ref())
+        .set_replication_database();
+    let (client, connection) = replication_opts.connect(tls_connector).await?;
+    tokio::spawn(connection);
+
+    Ok(Some((client, repl_slot_name)))
+}
 
+/// Cleans up replication related assets on the single upstream database at
+/// [`UpstreamConfig::upstream_db_url`]
+async fn cleanup_source(config: UpstreamConfig) -> ReadySetResult<()> {
+    if let Some((mut client, repl_slot_name)) = postgres_replication_client(&config).await? {
         drop_publication(&mut client, &repl_slot_name).await?;
 
         drop_replication_slot(&mut client, &repl_slot_name).await?;
@@ -133,6 +236,158 @@ pub async fn cleanup(config: UpstreamConfig) -> ReadySetResult<()> {
     Ok(())
 }
 
+/// Drops the Postgres replication slot on each of the upstream databases supplied by the
+/// UpstreamConfig, so that they stop retaining WAL for ReadySet. Used on shutdown if
+/// [`UpstreamConfig::drop_replication_slot_on_shutdown`] is set.
+///
+/// Replication must have stopped, although the upstream database may not have noticed yet.
+pub async fn drop_replication_slots(config: UpstreamConfig) -> ReadySetResult<()> {
+    /// How many times to try dropping a slot that the upstream still considers active
+    const MAX_ATTEMPTS: usize = 10;
+
+    for config in config.replication_sources() {
+        let Some((mut client, repl_slot_name)) = postgres_replication_client(&config).await? else {
+            continue;
+        };
+
+        let mut attempt = 1;
+        loop {
+            match drop_replication_slot(&mut client, &repl_slot_name).await {
+                Err(err) if attempt < MAX_ATTEMPTS && err.to_string().contains("is active") => {
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_millis(500)).await;
+                }
+                res => break res?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load the replication offsets for the schema of the upstream database named `source` (or the
+/// primary upstream, if `None`) and the tables we replicate from it, as selected by
+/// `table_filter`. See [`ReplicationOffsets::for_source`].
+pub(crate) async fn source_replication_offsets(
+    noria: &mut ReadySetHandle,
+    source: Option<&str>,
+    table_filter: &TableFilter,
+) -> ReadySetResult<ReplicationOffsets> {
+    Ok(noria
+        .replication_offsets()
+        .await?
+        .for_source(source, |table| {
+            table.schema.as_deref().map_or(false, |schema| {
+                table_filter.should_be_processed(schema, table.name.as_str())
+            })
+        }))
+}
+
+/// Set the replication offset for the schema of the upstream database named `source`, or of the
+/// primary upstream if `None`
+pub(crate) async fn set_source_schema_replication_offset(
+    noria: &mut ReadySetHandle,
+    source: Option<&str>,
+    offset: Option<&ReplicationOffset>,
+) -> ReadySetResult<()> {
+    match source {
+        Some(source) => {
+            noria
+                .set_source_schema_replication_offset(source, offset)
+                .await
+        }
+        None => noria.set_schema_replication_offset(offset).await,
+    }
+}
+
+/// Load the tables we replicate, as selected by `table_filter`, which were quarantined because of
+/// a destructive schema change (see [`DestructiveDdlPolicy::Quarantine`])
+async fn quarantined_tables(
+    noria: &mut ReadySetHandle,
+    table_filter: &TableFilter,
+) -> ReadySetResult<HashSet<Relation>> {
+    Ok(noria
+        .quarantined_tables()
+        .await?
+        .into_iter()
+        .filter(|table| replicates(table_filter, table))
+        .collect())
+}
+
+/// Lift the quarantine of the tables that were just snapshotted, since the snapshot applied their
+/// current upstream schema: every table we replicate if `full_snapshot`, and otherwise the tables
+/// in `resnapshot_tables`
+async fn unquarantine_snapshotted_tables(
+    noria: &mut ReadySetHandle,
+    table_filter: &TableFilter,
+    full_snapshot: bool,
+    resnapshot_tables: &HashMap<Relation, u64>,
+) -> ReadySetResult<()> {
+    for table in quarantined_tables(noria, table_filter).await? {
+        if full_snapshot || resnapshot_tables.contains_key(&table) {
+            info!(
+                table = %table.display_unquoted(),
+                "Snapshot applied the upstream schema of quarantined table, resuming replication"
+            );
+            noria.set_table_quarantined(&table, false).await?;
+        }
+    }
+    Ok(())
+}
+
+/// When the replicator applies the writes it has buffered and persists the replication offsets of
+/// the tables they're for, as configured by [`UpstreamConfig::mysql_checkpoint_events`] and
+/// [`UpstreamConfig::mysql_checkpoint_interval`]
+#[derive(Debug, Clone, Copy, Default)]
+struct CheckpointPolicy {
+    /// Take a checkpoint once this many events have been buffered
+    events: Option<u64>,
+    /// Take a checkpoint once the oldest buffered event is this old
+    interval: Option<Duration>,
+}
+
+impl CheckpointPolicy {
+    fn from_config(config: &UpstreamConfig) -> Self {
+        Self {
+            events: config.mysql_checkpoint_events,
+            interval: config.mysql_checkpoint_interval,
+        }
+    }
+
+    /// Returns true if a checkpoint should be taken of `pending`
+    fn is_due(&self, pending: &PendingWrites) -> bool {
+        if self.events.is_none() && self.interval.is_none() {
+            return true;
+        }
+
+        self.events.map_or(false, |events| pending.events >= events)
+            || self.interval.map_or(false, |interval| {
+                pending
+                    .since
+                    .map_or(false, |since| since.elapsed() >= interval)
+            })
+    }
+}
+
+/// The writes to a single table buffered until the next checkpoint
+#[derive(Debug)]
+struct PendingTableWrites {
+    actions: Vec<TableOperation>,
+    txid: Option<u64>,
+    /// The replication offset of the last event the writes came from
+    pos: ReplicationOffset,
+}
+
+/// Writes received from the upstream database but not yet applied, waiting for the next
+/// checkpoint
+#[derive(Debug, Default)]
+struct PendingWrites {
+    tables: HashMap<Relation, PendingTableWrites>,
+    /// The number of events the writes came from
+    events: u64,
+    /// When the first of the writes was received
+    since: Option<Instant>,
+}
+
 /// An adapter that converts database events into ReadySet API calls
 pub struct NoriaAdapter {
     /// The ReadySet API handle
@@ -156,20 +411,69 @@ pub struct NoriaAdapter {
     table_filter: TableFilter,
     /// If the connector can partially resnapshot a database
     supports_resnapshot: bool,
+    /// The name of the additional upstream database we're replicating from, or `None` if we're
+    /// replicating from the primary upstream (see [`UpstreamConfig::replication_source`])
+    source: Option<String>,
+    /// When to apply the writes in `pending_writes`
+    checkpoint_policy: CheckpointPolicy,
+    /// Table writes buffered until the next checkpoint. These are applied together with the
+    /// replication offset of the last event they came from, so if we crash before then, we resume
+    /// replicating from before all of them.
+    pending_writes: PendingWrites,
+    /// What to do with destructive schema changes to replicated tables
+    destructive_ddl_policy: DestructiveDdlPolicy,
+    /// Tables we've stopped replicating because of a destructive schema change, per
+    /// [`DestructiveDdlPolicy::Quarantine`]. These are persisted in ReadySet, so they stay
+    /// quarantined across restarts until they're next snapshotted.
+    quarantined_tables: HashSet<Relation>,
+    /// What to do with schema changes to replicated tables that we can't parse
+    ddl_conflict_policy: DdlConflictPolicy,
+    /// Tables requested to be resnapshotted, which stop streaming replication to take a partial
+    /// snapshot if we replicate any of them
+    resnapshot_requests: ResnapshotRequests,
+    /// The capture of replication events to a file in progress, if any
+    replication_capture: ReplicationCapture,
+    /// Where we record whether we're connected to the upstream database, and how far behind it
+    /// replication is
+    source_health: SourceHealth,
+    /// Signals that streaming replication should stop, see [`NoriaAdapter::shut_down`]
+    shutdown_rx: ShutdownReceiver,
+    /// Notified when streaming replication should stop to switch to reading the binlog from a
+    /// different MySQL server, see [`SourceSelector::monitor`]
+    source_switch: Arc<Notify>,
+    /// Exports the schema of replicated tables whenever it changes, if configured to
+    schema_exporter: Option<SchemaExporter>,
+    /// Requests from consistency checks for the checksums of tables as of a replication offset
+    checksum_requests: mpsc::Receiver<ChecksumRequest>,
+    /// The request for the checksum of a table as of an offset we haven't replicated up to yet,
+    /// if any
+    pending_checksum: Option<ChecksumRequest>,
 }
 
 impl NoriaAdapter {
+    /// Replicate from the upstream database at [`UpstreamConfig::upstream_db_url`], restarting
+    /// replication on any recoverable error. Returns once replication has been shut down by a
+    /// signal on `shutdown_rx`.
+    #[allow(clippy::too_many_arguments)]
     pub async fn start(
         noria: ReadySetHandle,
         mut config: UpstreamConfig,
         mut notify: Option<Arc<Notify>>,
         telemetry_sender: TelemetrySender,
         server_startup: bool,
-        enable_statement_logging: bool,
-    ) -> ReadySetResult<!> {
+        resnapshot_requests: ResnapshotRequests,
+        replication_capture: ReplicationCapture,
+        consistency_checks: ConsistencyChecks,
+        replication_health: ReplicationHealth,
+        shutdown_rx: ShutdownReceiver,
+    ) -> ReadySetResult<()> {
         // Resnapshot when restarting the server to apply changes that may have been made to the
         // replication-tables config parameter.
         let mut resnapshot = server_startup;
+        // Set if the MySQL binlog position we need to resume from has been purged upstream, or the
+        // upstream has moved backwards past it, in which case every table needs to be snapshotted
+        // again
+        let mut full_resnapshot = false;
         let url: DatabaseURL = config
             .upstream_db_url
             .take()
@@ -177,6 +481,20 @@ impl NoriaAdapter {
             .parse()
             .map_err(|e| invalid_err!("Invalid URL supplied to --upstream-db-url: {e}"))?;
 
+        let mut source_selector = match &url {
+            DatabaseURL::MySQL(options) => SourceSelector::new(options.clone(), &config)?,
+            DatabaseURL::PostgreSQL(_) if !config.replication_source_candidates.is_empty() => {
+                return Err(invalid_err!(
+                    "--replication-source-candidates is only supported for MySQL, since \
+                     PostgreSQL replication slots can't move between servers"
+                ));
+            }
+            DatabaseURL::PostgreSQL(_) => None,
+        };
+
+        replication_health
+            .source(config.replication_source.as_deref())
+            .disconnected();
         while let Err(err) = match url.clone() {
             DatabaseURL::MySQL(options) => {
                 let noria = noria.clone();
@@ -187,8 +505,14 @@ impl NoriaAdapter {
                     config,
                     &mut notify,
                     resnapshot,
+                    full_resnapshot,
                     &telemetry_sender,
-                    enable_statement_logging,
+                    resnapshot_requests.clone(),
+                    replication_capture.clone(),
+                    consistency_checks.clone(),
+                    replication_health.clone(),
+                    shutdown_rx.clone(),
+                    source_selector.as_mut(),
                 )
                 .await
             }
@@ -230,15 +554,55 @@ impl NoriaAdapter {
                     tls_connector,
                     pool,
                     repl_slot_name,
-                    enable_statement_logging,
+                    resnapshot_requests.clone(),
+                    replication_capture.clone(),
+                    consistency_checks.clone(),
+                    replication_health.clone(),
+                    shutdown_rx.clone(),
                 )
                 .await
             }
         } {
+            replication_health
+                .source(config.replication_source.as_deref())
+                .disconnected();
             match err {
                 ReadySetError::ResnapshotNeeded => {
                     tokio::time::sleep(WAIT_BEFORE_RESNAPSHOT).await;
                     resnapshot = true;
+                    full_resnapshot = false;
+                }
+                ReadySetError::ReplicationSourceChanged => {
+                    resnapshot = false;
+                    full_resnapshot = false;
+                }
+                err @ ReadySetError::ReplicationOffsetPurged { .. }
+                    if config.resnapshot_on_purged_binlog =>
+                {
+                    error!(
+                        error = %err,
+                        "Replication offset purged upstream, taking a full snapshot"
+                    );
+                    tokio::time::sleep(WAIT_BEFORE_RESNAPSHOT).await;
+                    resnapshot = true;
+                    full_resnapshot = true;
+                }
+                err @ ReadySetError::FullResnapshotNeeded(_) => {
+                    error!(error = %err, "Taking a full snapshot");
+                    tokio::time::sleep(WAIT_BEFORE_RESNAPSHOT).await;
+                    resnapshot = true;
+                    full_resnapshot = true;
+                }
+                err @ ReadySetError::ReplicationOffsetRewound { .. }
+                    if config.replication_rewind_policy != ReplicationRewindPolicy::Error =>
+                {
+                    error!(
+                        error = %err,
+                        "Upstream replication position moved backwards, taking a full snapshot"
+                    );
+                    tokio::time::sleep(WAIT_BEFORE_RESNAPSHOT).await;
+                    resnapshot = true;
+                    full_resnapshot = true;
                 }
                 err => {
                     warn!(error=%err, "Restarting adapter after error encountered");
@@ -246,7 +610,72 @@ impl NoriaAdapter {
                 }
             }
         }
-        unreachable!("inner loop will never stop with an Ok status");
+        replication_health
+            .source(config.replication_source.as_deref())
+            .disconnected();
+        Ok(())
+    }
+
+    /// Apply the replication events in the capture file at `path`, written by a
+    /// [`ReplicationCapture`], to ReadySet as if they'd just been received from an upstream
+    /// database of type `database_type`. Returns once every event in the file has been applied.
+    ///
+    /// As in streaming replication, events are only applied to tables that exist in ReadySet, and
+    /// are skipped for tables whose replication offset is already past them.
+    pub async fn replay_capture(
+        mut noria: ReadySetHandle,
+        path: &Path,
+        database_type: DatabaseType,
+    ) -> ReadySetResult<()> {
+        let connector = Box::new(CaptureFileConnector::open(path).await?);
+        let config = UpstreamConfig::default();
+        let table_filter = TableFilter::for_all_tables();
+        let quarantined_tables = quarantined_tables(&mut noria, &table_filter).await?;
+        let replication_offsets = noria.replication_offsets().await?;
+        let (_shutdown_tx, shutdown_rx) = shutdown::channel();
+        let (_checksum_tx, checksum_requests) = mpsc::channel(1);
+        let mut adapter = NoriaAdapter {
+            noria,
+            connector,
+            replication_offsets,
+            mutator_map: HashMap::new(),
+            warned_missing_tables: HashSet::new(),
+            table_filter,
+            supports_resnapshot: false,
+            dialect: match database_type {
+                DatabaseType::MySQL => Dialect::DEFAULT_MYSQL,
+                DatabaseType::PostgreSQL => Dialect::DEFAULT_POSTGRESQL,
+            },
+            source: None,
+            checkpoint_policy: CheckpointPolicy::from_config(&config),
+            pending_writes: PendingWrites::default(),
+            destructive_ddl_policy: config.destructive_ddl_policy,
+            quarantined_tables,
+            ddl_conflict_policy: config.ddl_conflict_policy,
+            resnapshot_requests: ResnapshotRequests::default(),
+            replication_capture: ReplicationCapture::default(),
+            source_health: SourceHealth::default(),
+            shutdown_rx,
+            source_switch: Arc::new(Notify::new()),
+            schema_exporter: None,
+            checksum_requests,
+            pending_checksum: None,
+        };
+
+        // The capture file connector doesn't look at the position it's passed
+        let mut position = ReplicationOffset {
+            offset: 0,
+            replication_log_name: String::new(),
+            gtid_set: None,
+        };
+        match adapter.main_loop(&mut position, None).await {
+            Err(ReadySetError::ReplicationCaptureEnded) => {
+                adapter.checkpoint().await?;
+                info!(%position, "Replayed replication capture");
+                Ok(())
+            }
+            res => res,
+        }
     }
 
     /// Finish the build and begin monitoring the binlog for changes
@@ -259,15 +688,22 @@ impl NoriaAdapter {
     /// * Each table is individually replicated into ReadySet
     /// * READ LOCK is released
     /// * Adapter keeps reading binlog from the next position keeping ReadySet up to date
+    #[allow(clippy::too_many_arguments)]
     async fn start_inner_mysql(
         mut mysql_options: mysql::Opts,
         mut noria: ReadySetHandle,
         mut config: UpstreamConfig,
         ready_notify: &mut Option<Arc<Notify>>,
         resnapshot: bool,
+        full_resnapshot: bool,
         telemetry_sender: &TelemetrySender,
-        enable_statement_logging: bool,
-    ) -> ReadySetResult<!> {
+        resnapshot_requests: ResnapshotRequests,
+        replication_capture: ReplicationCapture,
+        consistency_checks: ConsistencyChecks,
+        replication_health: ReplicationHealth,
+        shutdown_rx: ShutdownReceiver,
+        mut source_selector: Option<&mut SourceSelector>,
+    ) -> ReadySetResult<()> {
         use crate::mysql_connector::BinlogPosition;
 
         if let Some(cert_path) = config.ssl_root_cert.clone() {
@@ -277,14 +713,46 @@ impl NoriaAdapter {
                 .into();
         }
 
-        // Load the replication offset for all tables and the schema from ReadySet
-        let mut replication_offsets = noria.replication_offsets().await?;
-
+        let schema_exporter = SchemaExporter::new(&config, noria.clone(), Dialect::DEFAULT_MYSQL)?;
         let table_filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             config.replication_tables.take(),
+            config.replication_tables_ignore.take(),
             mysql_options.db_name(),
         )?;
+        let source = config.replication_source.take();
+        let source_health = replication_health.source(source.as_deref());
+
+        // Load the replication offset for all tables and the schema from ReadySet
+        let mut replication_offsets =
+            source_replication_offsets(&mut noria, source.as_deref(), &table_filter).await?;
+
+        // Choose the server to read the binlog from, if there are replicas of the primary to
+        // choose from
+        let binlog_options = match source_selector.as_deref_mut() {
+            Some(selector) => {
+                let snapshot = resnapshot
+                    || replication_offsets.schema.is_none()
+                    || replication_offsets.tables.values().any(Option::is_none);
+                selector
+                    .prepare(
+                        &mut noria,
+                        source.as_deref(),
+                        &mut replication_offsets,
+                        snapshot,
+                    )
+                    .await?
+            }
+            None => mysql_options.clone(),
+        };
+
+        // Only the server we're about to use has to be reachable, since we may have chosen a
+        // replica because the primary isn't
+        mysql_connector::check_privileges(
+            &mut mysql::Conn::new(binlog_options.clone()).await?,
+            &table_filter,
+        )
+        .await?;
 
         let mut db_schemas = DatabaseSchemas::new();
 
@@ -318,9 +786,45 @@ impl NoriaAdapter {
                     .flatten()
                     .unwrap_or_else(|| "unknown".to_owned());
 
+                let resnapshot_tables = resnapshot_requests.pending(&table_filter);
+                // A dump file is only used for the initial snapshot, since by the time tables are
+                // resnapshotted it's likely to be out of date
+                let dump = match &config.mysql_snapshot_dump {
+                    Some(path)
+                        if !full_resnapshot
+                            && resnapshot_tables.is_empty()
+                            && replication_offsets.tables.values().all(Option::is_none) =>
+                    {
+                        let time_zone =
+                            UpstreamTimeZone::fetch_mysql(&mut pool.get_conn().await?).await?;
+                        let dump = DumpFile::open(path.clone(), time_zone).await?;
+                        span.in_scope(|| {
+                            info!(
+                                path = %path.display(),
+                                position = %dump.position(),
+                                "Snapshotting tables from dump file"
+                            )
+                        });
+                        Some(Arc::new(dump))
+                    }
+                    _ => None,
+                };
                 let replicator = MySqlReplicator {
                     pool,
                     table_filter: table_filter.clone(),
+                    source: source.clone(),
+                    snapshot_parallelism: config.snapshot_parallelism,
+                    snapshot_chunk_size: config.snapshot_chunk_size,
+                    checkpoints: config
+                        .snapshot_chunk_size
+                        .and(config.snapshot_checkpoint_dir.clone())
+                        .map(SnapshotCheckpoints::new),
+                    throttle: SnapshotThrottle::new(
+                        config.snapshot_max_rows_per_sec,
+                        config.snapshot_max_bytes_per_sec,
+                    ),
+                    resnapshot_tables: resnapshot_tables.keys().cloned().collect(),
+                    dump,
                 };
 
                 let snapshot_start = Instant::now();
@@ -336,6 +840,7 @@ impl NoriaAdapter {
                         &mut noria,
                         &mut db_schemas,
                         config.snapshot_report_interval_secs,
+                        full_resnapshot,
                     )
                     .instrument(span.clone())
                     .await;
@@ -353,9 +858,19 @@ impl NoriaAdapter {
                 );
 
                 snapshot_result?;
+                resnapshot_requests.complete(&resnapshot_tables);
+                unquarantine_snapshotted_tables(
+                    &mut noria,
+                    &table_filter,
+                    full_resnapshot,
+                    &resnapshot_tables,
+                )
+                .await?;
 
                 // Get updated offests, after potential replication happened
-                replication_offsets = noria.replication_offsets().await?;
+                replication_offsets =
+                    source_replication_offsets(&mut noria, source.as_deref(), &table_filter)
+                        .await?;
 
                 // If we have some offsets in `replication_offsets`, that means some tables were
                 // already snapshot before we started up. But if we're in this block
@@ -393,19 +908,39 @@ impl NoriaAdapter {
             (Some(pos), _) => pos.clone().into(),
         };
 
-        // TODO: it is possible that the binlog position from noria is no longer
-        // present on the primary, in which case the connection will fail, and we would
-        // need to perform a new snapshot
-        let connector = Box::new(
-            MySqlBinlogConnector::connect(
-                mysql_options.clone(),
-                pos.clone(),
-                config.replication_server_id,
-                enable_statement_logging,
-            )
-            .await?,
-        );
+        // It is possible that the binlog position from noria is no longer present on the primary
+        // (unless we're using GTID auto-positioning), in which case the connection will fail with
+        // `ReplicationOffsetPurged`, and we need to perform a new snapshot
+        let connector: Box<dyn Connector + Send + Sync> = match &config.mysql_binlog_replay_dir {
+            Some(dir) => Box::new(
+                MySqlBinlogConnector::replay_files(dir.clone(), pos.clone(), table_filter.clone())
+                    .await?,
+            ),
+            None => Box::new(
+                MySqlBinlogConnector::connect(
+                    binlog_options.clone(),
+                    pos.clone(),
+                    config.replication_server_id,
+                    config.mysql_gtid_auto_position,
+                    config.replication_rewind_policy,
+                    config.replication_reconnect_timeout,
+                    table_filter.clone(),
+                    config.mysql_minimal_row_image,
+                    replication_offsets
+                        .tables
+                        .iter()
+                        .filter_map(|(table, offset)| Some((table.clone(), offset.clone()?)))
+                        .collect(),
+                )
+                .await?,
+            ),
+        };
 
+        let quarantined_tables = quarantined_tables(&mut noria, &table_filter).await?;
+        let background_filter = table_filter.clone();
+        let background_source = source.clone();
+        let source_switch = Arc::new(Notify::new());
+        let (checksum_tx, checksum_requests) = mpsc::channel(1);
         let mut adapter = NoriaAdapter {
             noria: noria.clone(),
             connector,
@@ -415,6 +950,20 @@ impl NoriaAdapter {
             table_filter,
             supports_resnapshot: true,
             dialect: Dialect::DEFAULT_MYSQL,
+            source,
+            checkpoint_policy: CheckpointPolicy::from_config(&config),
+            pending_writes: PendingWrites::default(),
+            destructive_ddl_policy: config.destructive_ddl_policy,
+            quarantined_tables,
+            ddl_conflict_policy: config.ddl_conflict_policy,
+            resnapshot_requests,
+            replication_capture,
+            source_health,
+            shutdown_rx,
+            source_switch: source_switch.clone(),
+            schema_exporter,
+            checksum_requests,
+            pending_checksum: None,
         };
 
         let mut current_pos: ReplicationOffset = pos.try_into()?;
@@ -442,10 +991,50 @@ impl NoriaAdapter {
         if let Some(notify) = ready_notify.take() {
             notify.notify_one();
         }
+        adapter.export_schema();
+
+        let time_zone =
+            UpstreamTimeZone::fetch_mysql(&mut mysql::Conn::new(binlog_options).await?).await?;
+
+        select! {
+            result = adapter.main_loop(&mut current_pos, None).fuse() => return result,
+            _ = table_retention::expire_rows(
+                noria.clone(),
+                mem::take(&mut config.table_retention),
+                &background_filter,
+                time_zone,
+            ).fuse() => {}
+            _ = async {
+                match source_selector.as_deref() {
+                    Some(selector) => {
+                        selector.monitor(
+                            noria.clone(),
+                            background_source.as_deref(),
+                            &background_filter,
+                            &source_switch,
+                        ).await
+                    }
+                    None => futures::future::pending().await,
+                }
+            }.fuse() => {}
+            _ = async {
+                match config.consistency_check_interval {
+                    Some(interval) => {
+                        consistency_check::check_tables(
+                            noria.clone(),
+                            Upstream::mysql(mysql::Pool::new(mysql_options)),
+                            &background_filter,
+                            interval,
+                            checksum_tx,
+                            consistency_checks,
+                        ).await
+                    }
+                    None => futures::future::pending().await,
+                }
+            }.fuse() => {}
+        }
 
-        adapter.main_loop(&mut current_pos, None).await?;
-
-        unreachable!("`main_loop` will never stop with an Ok status if `until = None`");
+        unreachable!("Only `main_loop` returns, once replication is shut down");
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -459,8 +1048,12 @@ impl NoriaAdapter {
         tls_connector: MakeTlsConnector,
         pool: deadpool_postgres::Pool,
         repl_slot_name: String,
-        enable_statement_logging: bool,
-    ) -> ReadySetResult<!> {
+        resnapshot_requests: ResnapshotRequests,
+        replication_capture: ReplicationCapture,
+        consistency_checks: ConsistencyChecks,
+        replication_health: ReplicationHealth,
+        shutdown_rx: ShutdownReceiver,
+    ) -> ReadySetResult<()> {
         macro_rules! handle_joinhandle_result {
             ($res: expr) => {
                 match $res {
@@ -477,17 +1070,87 @@ impl NoriaAdapter {
             ReadySetError::ReplicationFailed("No database specified for replication".to_string())
         })?;
 
-        // Attempt to retrieve the latest replication offset from ReadySet-server, if none is
-        // present begin the snapshot process
-        let replication_offsets = noria.replication_offsets().await?;
-        let pos = replication_offsets.max_offset()?.map(Into::into);
-        let snapshot_report_interval_secs = config.snapshot_report_interval_secs;
+        // If we're replicating from a list of publications, replicate exactly the tables they
+        // contain. Publications listed more than once would be named more than once to the server,
+        // and seen as missing when resuming, so deduplicate them.
+        let publications = config
+            .replication_publications
+            .iter()
+            .unique()
+            .cloned()
+            .collect::<Vec<_>>();
+        let publication_tables = if publications.is_empty() {
+            None
+        } else {
+            if config.replication_tables.is_some() {
+                return Err(ReadySetError::ReplicationFailed(
+                    "--replication-tables can't be combined with --replication-publications"
+                        .to_string(),
+                ));
+            }
+            let tables =
+                postgres_connector::publication_tables(&*pool.get().await?, &publications).await?;
+            if tables.is_empty() {
+                return Err(ReadySetError::ReplicationFailed(format!(
+                    "Publications {} don't contain any tables",
+                    publications.join(", ")
+                )));
+            }
+            config.replication_tables = Some(
+                tables
+                    .iter()
+                    .map(|(schema, table)| {
+                        format!(
+                            "{}.{}",
+                            nom_sql::Dialect::PostgreSQL.quote_identifier(schema),
+                            nom_sql::Dialect::PostgreSQL.quote_identifier(table)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+                    .into(),
+            );
+            Some(tables)
+        };
 
+        let schema_exporter =
+            SchemaExporter::new(&config, noria.clone(), Dialect::DEFAULT_POSTGRESQL)?;
         let table_filter = TableFilter::try_new(
             nom_sql::Dialect::PostgreSQL,
             config.replication_tables.take(),
+            config.replication_tables_ignore.take(),
             None,
         )?;
+        let source = config.replication_source.take();
+        let source_health = replication_health.source(source.as_deref());
+
+        // Attempt to retrieve the latest replication offset from ReadySet-server, if none is
+        // present begin the snapshot process
+        let replication_offsets =
+            source_replication_offsets(&mut noria, source.as_deref(), &table_filter).await?;
+        let pos = replication_offsets.max_offset()?.map(Into::into);
+        let snapshot_report_interval_secs = config.snapshot_report_interval_secs;
+        let snapshot_parallelism = config.snapshot_parallelism;
+        let snapshot_chunk_size = config.snapshot_chunk_size;
+        let snapshot_checkpoints = snapshot_chunk_size
+            .and(config.snapshot_checkpoint_dir.clone())
+            .map(SnapshotCheckpoints::new);
+        let snapshot_throttle = SnapshotThrottle::new(
+            config.snapshot_max_rows_per_sec,
+            config.snapshot_max_bytes_per_sec,
+        );
+        let destructive_ddl_policy = config.destructive_ddl_policy;
+        let ddl_conflict_policy = config.ddl_conflict_policy;
+        let slot_lag_warn_bytes = config.replication_slot_lag_warn_bytes;
+        // A dump file is only used for the initial snapshot, for which the connector keeps the
+        // replication slot the dump was taken from
+        let snapshot_dump = config
+            .postgres_snapshot_dump
+            .clone()
+            .filter(|_| pos.is_none());
+        let table_retention = mem::take(&mut config.table_retention);
+        let consistency_check_interval = config.consistency_check_interval;
+        let monitor_pool = pool.clone();
 
         // For Postgres 13, once we setup ddl replication, the following query can be rejected, so
         // run it ahead of time.
@@ -511,6 +1174,16 @@ impl NoriaAdapter {
             }
         };
 
+        {
+            let (client, connection) = pgsql_opts.connect(tls_connector.clone()).await?;
+            let connection_handle = tokio::spawn(connection);
+
+            select! {
+                result = postgres_connector::check_privileges(&client, &table_filter) => result?,
+                c = connection_handle.fuse() => return handle_joinhandle_result!(c),
+            }
+        }
+
         let mut connector = Box::new(
             PostgresWalConnector::connect(
                 pgsql_opts.clone(),
@@ -519,13 +1192,15 @@ impl NoriaAdapter {
                 pos,
                 tls_connector.clone(),
                 &repl_slot_name,
-                enable_statement_logging,
             )
             .await?,
         );
 
         info!("Connected to PostgreSQL");
 
+        // If we just (re)created the replication slot, it can't be used to replicate any writes
+        // from before it was created, so every table has to be snapshotted
+        let created_slot = connector.replication_slot.is_some();
         let resnapshot_slot_name = format!("{}_{}", RESNAPSHOT_SLOT, repl_slot_name);
         let replication_slot = if let Some(slot) = &connector.replication_slot {
             Some(slot.clone())
@@ -565,21 +1240,50 @@ impl NoriaAdapter {
                 .and_then(|row| row.try_get::<_, String>(0))
                 .unwrap_or_else(|_| "unknown".to_owned());
 
-            let mut replicator =
-                PostgresReplicator::new(&mut client, pool, &mut noria, table_filter.clone())
-                    .await?;
+            let resnapshot_tables = resnapshot_requests.pending(&table_filter);
+            let dump = match snapshot_dump {
+                Some(path) => {
+                    let dump = postgres_connector::DumpFile::open(path.clone()).await?;
+                    info!(
+                        path = %path.display(),
+                        slot = %replication_slot.slot_name,
+                        "Snapshotting tables from dump file"
+                    );
+                    Some(Arc::new(dump))
+                }
+                None => None,
+            };
+            let mut replicator = PostgresReplicator::new(
+                &mut client,
+                pool,
+                &mut noria,
+                table_filter.clone(),
+                source.clone(),
+                snapshot_parallelism,
+                snapshot_chunk_size,
+                snapshot_checkpoints.clone(),
+                snapshot_throttle.clone(),
+                resnapshot_tables.keys().cloned().collect(),
+                dump,
+            )
+            .await?;
 
+            // If we don't have a consistent replication offset from ReadySet, that might be
+            // because only *some* tables are missing a replication offset - in that case we
+            // need to resnapshot *all* tables, because we just dropped the replication slot
+            // above, which prevents us from replicating any writes to tables we do have a
+            // replication offset for that happened while we weren't running.
+            //
+            // If we're persisting snapshot checkpoints and didn't just create the
+            // replication slot, we can still replicate those writes, so we instead keep
+            // the tables that were already (partially) snapshotted and resume them.
+            let full_snapshot = created_slot || (pos.is_none() && snapshot_checkpoints.is_none());
             select! {
                 snapshot_result = replicator.snapshot_to_noria(
                     &replication_slot,
                     &mut create_schema,
                     snapshot_report_interval_secs,
-                    // If we don't have a consistent replication offset from ReadySet, that might be
-                    // because only *some* tables are missing a replication offset - in that case we
-                    // need to resnapshot *all* tables, because we just dropped the replication slot
-                    // above, which prevents us from replicating any writes to tables we do have a
-                    // replication offset for that happened while we weren't running.
-                    /* full_snapshot = */ pos.is_none()
+                    full_snapshot,
                 ).fuse() =>  {
                     let status = if snapshot_result.is_err() {
                         SnapshotStatusTag::Failed.value()
@@ -594,11 +1298,19 @@ impl NoriaAdapter {
                     );
 
                     snapshot_result?;
+                    resnapshot_requests.complete(&resnapshot_tables);
                 },
                 c = connection_handle.fuse() => return handle_joinhandle_result!(c),
             }
 
             info!("Snapshot finished");
+            unquarantine_snapshotted_tables(
+                &mut noria,
+                &table_filter,
+                full_snapshot,
+                &resnapshot_tables,
+            )
+            .await?;
             histogram!(
                 recorded::REPLICATOR_SNAPSHOT_DURATION,
                 snapshot_start.elapsed().as_micros() as f64
@@ -620,11 +1332,17 @@ impl NoriaAdapter {
             create_schema.send_schemas(telemetry_sender).await;
         }
 
+        let publication_names = if publications.is_empty() {
+            PUBLICATION_NAME.to_owned()
+        } else {
+            publications.join(",")
+        };
         connector
-            .start_replication(&repl_slot_name, PUBLICATION_NAME, version_num)
+            .start_replication(&repl_slot_name, &publication_names, version_num)
             .await?;
 
-        let replication_offsets = noria.replication_offsets().await?;
+        let replication_offsets =
+            source_replication_offsets(&mut noria, source.as_deref(), &table_filter).await?;
         trace!(?replication_offsets, "Loaded replication offsets");
         let mut min_pos = replication_offsets
             .min_present_offset()?
@@ -635,8 +1353,11 @@ impl NoriaAdapter {
             .expect("Maximum offset must be present after snapshot")
             .clone();
 
+        let quarantined_tables = quarantined_tables(&mut noria, &table_filter).await?;
+        let background_filter = table_filter.clone();
+        let (checksum_tx, checksum_requests) = mpsc::channel(1);
         let mut adapter = NoriaAdapter {
-            noria,
+            noria: noria.clone(),
             connector,
             replication_offsets,
             mutator_map: HashMap::new(),
@@ -644,6 +1365,22 @@ impl NoriaAdapter {
             table_filter,
             supports_resnapshot: true,
             dialect: Dialect::DEFAULT_POSTGRESQL,
+            source,
+            // We acknowledge WAL positions to the upstream as soon as we've received them, so
+            // writes can't be held back from being applied
+            checkpoint_policy: CheckpointPolicy::default(),
+            pending_writes: PendingWrites::default(),
+            destructive_ddl_policy,
+            quarantined_tables,
+            ddl_conflict_policy,
+            resnapshot_requests,
+            replication_capture,
+            source_health,
+            shutdown_rx,
+            source_switch: Arc::new(Notify::new()),
+            schema_exporter,
+            checksum_requests,
+            pending_checksum: None,
         };
 
         if min_pos != max_pos {
@@ -655,12 +1392,49 @@ impl NoriaAdapter {
         if let Some(notify) = ready_notify.take() {
             notify.notify_one();
         }
+        adapter.export_schema();
+
+        let time_zone = UpstreamTimeZone::fetch_postgres(&*monitor_pool.get().await?).await?;
 
         info!("Streaming replication started");
 
-        adapter.main_loop(&mut min_pos, None).await?;
+        select! {
+            result = adapter.main_loop(&mut min_pos, None).fuse() => return result,
+            _ = postgres_connector::monitor_replication_slot(
+                monitor_pool.clone(),
+                repl_slot_name,
+                slot_lag_warn_bytes,
+            ).fuse() => {}
+            error = postgres_connector::monitor_publications(
+                monitor_pool.clone(),
+                noria.clone(),
+                publications,
+                publication_tables,
+            ).fuse() => return Err(error),
+            _ = table_retention::expire_rows(
+                noria.clone(),
+                table_retention,
+                &background_filter,
+                time_zone,
+            ).fuse() => {}
+            _ = async {
+                match consistency_check_interval {
+                    Some(interval) => {
+                        consistency_check::check_tables(
+                            noria,
+                            Upstream::PostgreSql(monitor_pool),
+                            &background_filter,
+                            interval,
+                            checksum_tx,
+                            consistency_checks,
+                        ).await
+                    }
+                    None => futures::future::pending().await,
+                }
+            }.fuse() => {}
+        }
 
-        unreachable!("`main_loop` will never stop with an Ok status if `until = None`");
+        unreachable!("Only `main_loop` returns, once replication is shut down");
     }
 
     /// Apply a DDL string to noria with the current log position
@@ -671,18 +1445,29 @@ impl NoriaAdapter {
         pos: ReplicationOffset,
     ) -> ReadySetResult<()> {
         let mut changelist = ChangeList::from_changes(changes, self.dialect);
+        self.handle_destructive_ddl(&mut changelist, &schema)
+            .await?;
 
-        // Remove DDL changes outside the filtered scope
+        // Remove DDL changes outside the filtered scope. Tables named without a schema are in
+        // the default schema of the statement.
+        let table_schema = |table: &Relation| {
+            table
+                .schema
+                .as_ref()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| schema.clone())
+        };
         let mut non_replicated_tables = vec![];
         changelist.changes_mut().retain(|change| match change {
             Change::CreateTable(stmt) => {
+                let stmt_schema = table_schema(&stmt.table);
                 let keep = self
                     .table_filter
-                    .should_be_processed(schema.as_str(), stmt.table.name.as_str())
+                    .should_be_processed(stmt_schema.as_str(), stmt.table.name.as_str())
                     && stmt.body.is_ok();
                 if !keep {
                     non_replicated_tables.push(Relation {
-                        schema: Some(schema.clone().into()),
+                        schema: Some(stmt_schema.into()),
                         name: stmt.table.name.clone(),
                     })
                 }
@@ -690,7 +1475,7 @@ impl NoriaAdapter {
             }
             Change::AlterTable(stmt) => self
                 .table_filter
-                .should_be_processed(schema.as_str(), stmt.table.name.as_str()),
+                .should_be_processed(table_schema(&stmt.table).as_str(), stmt.table.name.as_str()),
             _ => true,
         });
 
@@ -723,11 +1508,28 @@ impl NoriaAdapter {
             })
             .collect::<Vec<_>>();
 
-        match self
-            .noria
-            .extend_recipe_with_offset(changelist.clone(), &pos, false)
-            .await
-        {
+        let result = match &self.source {
+            None => {
+                self.noria
+                    .extend_recipe_with_offset(changelist.clone(), &pos, false)
+                    .await
+            }
+            // The offset stored with the recipe is the primary upstream's, so we track ours
+            // separately
+            Some(source) => match self
+                .noria
+                .extend_recipe_no_leader_ready(changelist.clone())
+                .await
+            {
+                Ok(()) => {
+                    self.noria
+                        .set_source_schema_replication_offset(source, Some(&pos))
+                        .await
+                }
+                Err(e) => Err(e),
+            },
+        };
+        match result {
             // ReadySet likely entered an invalid state, fail the replicator.
             Err(e @ ReadySetError::RecipeInvariantViolated(_)) => return Err(e),
             Err(error) => {
@@ -771,14 +1573,148 @@ impl NoriaAdapter {
             }
         }
 
+        self.export_schema();
         Ok(())
     }
 
+    /// Export the schema of the replicated tables in the background, if it's changed since it was
+    /// last exported
+    fn export_schema(&self) {
+        if let Some(exporter) = &self.schema_exporter {
+            exporter.export(&self.table_filter);
+        }
+    }
+
+    /// Handle tables being renamed upstream.
+    ///
+    /// Tables can't be renamed in place, so if any of the tables involved are replicated (under
+    /// either name) we drop them under their old names and resnapshot, which creates them anew
+    /// under their new names. Otherwise we only need to update which names we record as
+    /// non-replicated.
+    async fn handle_rename_tables(
+        &mut self,
+        schema: String,
+        renames: Vec<RenameTableOperation>,
+        pos: ReplicationOffset,
+    ) -> ReadySetResult<()> {
+        let is_replicated = |table: &Relation| {
+            table.schema.as_deref().map_or(false, |s| {
+                self.table_filter.should_be_processed(s, &table.name)
+            })
+        };
+        if self.supports_resnapshot
+            && renames
+                .iter()
+                .any(|op| is_replicated(&op.from) || is_replicated(&op.to))
+        {
+            if let Some(pos) = self.replication_offsets.max_offset()?.cloned() {
+                self.handle_log_position(pos).await?;
+            }
+            let drops = renames
+                .into_iter()
+                .map(|op| Change::Drop {
+                    name: op.from,
+                    if_exists: true,
+                })
+                .collect::<Vec<_>>();
+            self.noria
+                .extend_recipe(ChangeList::from_changes(drops, self.dialect))
+                .await?;
+            self.clear_mutator_cache();
+            return Err(ReadySetError::ResnapshotNeeded);
+        }
+
+        let changes = renames
+            .into_iter()
+            .flat_map(|op| {
+                [
+                    Change::Drop {
+                        name: op.from,
+                        if_exists: true,
+                    },
+                    Change::AddNonReplicatedRelation(op.to),
+                ]
+            })
+            .collect();
+        self.handle_ddl_change(schema, changes, pos).await
+    }
+
+    /// Handle a DDL statement changing `table` that we couldn't parse, according to
+    /// [`Self::ddl_conflict_policy`]
+    async fn handle_unparsed_ddl(
+        &mut self,
+        table: Relation,
+        error: String,
+        pos: ReplicationOffset,
+    ) -> ReadySetResult<()> {
+        let is_replicated = table.schema.as_deref().map_or(false, |schema| {
+            self.table_filter.should_be_processed(schema, &table.name)
+        });
+        if !is_replicated {
+            return Ok(());
+        }
+
+        counter!(recorded::REPLICATOR_FAILURE, 1u64);
+        match self.ddl_conflict_policy {
+            DdlConflictPolicy::Ignore => {
+                warn!(
+                    %error,
+                    table = %table.display_unquoted(),
+                    "Error extending recipe, DDL statement will not be used"
+                );
+                Ok(())
+            }
+            DdlConflictPolicy::Skip => {
+                error!(
+                    %error,
+                    table = %table.display_unquoted(),
+                    "Could not parse DDL statement, no longer replicating the table"
+                );
+                // Deny replication of the table too, so that we don't start replicating it again
+                // when it's next written to or snapshotted
+                if let Some(schema) = &table.schema {
+                    self.table_filter
+                        .deny_replication(schema.as_str(), table.name.as_str());
+                }
+                self.remove_table_from_readyset(table).await?;
+                self.clear_mutator_cache();
+                self.handle_log_position(pos).await?;
+                self.export_schema();
+                Ok(())
+            }
+            DdlConflictPolicy::Resnapshot if self.supports_resnapshot => {
+                error!(
+                    %error,
+                    table = %table.display_unquoted(),
+                    "Could not parse DDL statement, snapshotting the table again"
+                );
+                if let Some(pos) = self.replication_offsets.max_offset()?.cloned() {
+                    self.handle_log_position(pos).await?;
+                }
+                self.noria
+                    .extend_recipe(ChangeList::from_changes(
+                        vec![Change::Drop {
+                            name: table,
+                            if_exists: true,
+                        }],
+                        self.dialect,
+                    ))
+                    .await?;
+                self.clear_mutator_cache();
+                Err(ReadySetError::ResnapshotNeeded)
+            }
+            DdlConflictPolicy::Pause | DdlConflictPolicy::Resnapshot => {
+                Err(ReadySetError::ReplicationPausedAtDdl { table, error })
+            }
+        }
+    }
+
     /// Update the log position of the schema and the tables
     async fn handle_log_position(&mut self, pos: ReplicationOffset) -> ReadySetResult<()> {
         // Update the log position for the schema
         debug!(%pos, "Setting schema replication offset");
-        self.noria.set_schema_replication_offset(Some(&pos)).await?;
+        set_source_schema_replication_offset(&mut self.noria, self.source.as_deref(), Some(&pos))
+            .await?;
 
         // Update the log position for the tables that are behind this offset
         let tables = self
@@ -863,67 +1799,299 @@ impl NoriaAdapter {
         // First check if we should skip this action due to insufficient log position or lack of
         // interest
         match &action {
-            ReplicationAction::DdlChange { .. } | ReplicationAction::LogPosition => {
-                match &self.replication_offsets.schema {
-                    Some(cur) if pos <= *cur => {
-                        if !catchup {
-                            warn!(%pos, %cur, "Skipping schema update for earlier entry");
-                        }
-                        return Ok(());
+            ReplicationAction::DdlChange { .. }
+            | ReplicationAction::RenameTables { .. }
+            | ReplicationAction::UnparsedDdl { .. }
+            | ReplicationAction::LogPosition => match &self.replication_offsets.schema {
+                Some(cur) if pos <= *cur => {
+                    if !catchup {
+                        warn!(%pos, %cur, "Skipping schema update for earlier entry");
                     }
-                    _ => {}
+                    return Ok(());
                 }
-            }
+                _ => {}
+            },
             ReplicationAction::TableAction { table, .. } => {
-                match self.replication_offsets.tables.get(table) {
-                    Some(Some(cur)) if pos <= *cur => {
-                        if !catchup {
-                            warn!(
-                                table = %table.display_unquoted(),
-                                %pos,
-                                %cur,
-                                "Skipping table action for earlier entry"
-                            );
-                        }
-                        return Ok(());
-                    }
-                    Some(Some(cur)) => {
-                        trace!(table = %table.display_unquoted(), %cur);
-                    }
-                    _ => {
-                        trace!(
-                            table = %table.display_unquoted(),
-                            "no replication offset for table"
-                        );
-                    }
-                }
-
-                if !self.table_filter.should_be_processed(
-                    table.schema.as_deref().ok_or_else(|| {
-                        internal_err!("All tables should have a schema in the replicator")
-                    })?,
-                    &table.name,
-                ) {
+                if self.should_skip_table_action(table, &pos, catchup)? {
                     return Ok(());
                 }
             }
+            // Tables in a transaction or truncate are checked individually below
+            ReplicationAction::Transaction { .. }
+            | ReplicationAction::Truncate { .. }
+            | ReplicationAction::Heartbeat => {}
+            // Incidents are never skipped, even if they're at or before the schema's offset, since
+            // tables that are behind the schema (such as ones that weren't snapshotted along with
+            // it) may not have been snapshotted since the incident. This doesn't cause a loop:
+            // the full snapshot taken for an incident moves every table, and the schema, past it,
+            // so replication resumes after it.
+            ReplicationAction::ResnapshotRequired { .. } => {}
+        }
+
+        // Anything other than writes may persist the schema's replication offset, or change the
+        // tables the buffered writes are for, so the buffered writes have to be applied first
+        if !matches!(
+            action,
+            ReplicationAction::TableAction { .. }
+                | ReplicationAction::Transaction { .. }
+                | ReplicationAction::Truncate { .. }
+                | ReplicationAction::Heartbeat
+        ) {
+            self.checkpoint().await?;
         }
 
         match action {
             ReplicationAction::DdlChange { schema, changes } => {
                 self.handle_ddl_change(schema, changes, pos).await
             }
+            ReplicationAction::RenameTables { schema, renames } => {
+                self.handle_rename_tables(schema, renames, pos).await
+            }
+            ReplicationAction::UnparsedDdl { table, error } => {
+                self.handle_unparsed_ddl(table, error, pos).await
+            }
             ReplicationAction::TableAction {
                 table,
                 actions,
                 txid,
-            } => self.handle_table_actions(table, actions, txid, pos).await,
+                query,
+            } => {
+                if let Some(query) = &query {
+                    debug!(
+                        table = %table.display_unquoted(),
+                        %query,
+                        "Applying writes from upstream statement"
+                    );
+                }
+                self.buffer_table_actions(table, actions, txid, pos);
+                self.checkpoint_if_due(true).await
+            }
+            ReplicationAction::Transaction {
+                tables,
+                txid,
+                queries,
+            } => {
+                if !queries.is_empty() {
+                    debug!(
+                        tables = %tables
+                            .iter()
+                            .map(|(table, _)| table.display_unquoted())
+                            .join(", "),
+                        ?queries,
+                        "Applying writes from upstream transaction"
+                    );
+                }
+                for (table, actions) in tables {
+                    if self.should_skip_table_action(&table, &pos, catchup)? {
+                        continue;
+                    }
+                    self.buffer_table_actions(table, actions, txid, pos.clone());
+                }
+                self.checkpoint_if_due(true).await
+            }
+            ReplicationAction::Truncate { tables } => {
+                for table in tables {
+                    if self.should_skip_table_action(&table, &pos, catchup)? {
+                        continue;
+                    }
+                    self.buffer_truncate(table, pos.clone());
+                }
+                self.checkpoint_if_due(true).await
+            }
+            ReplicationAction::Heartbeat => self.checkpoint_if_due(false).await,
             ReplicationAction::LogPosition => self.handle_log_position(pos).await,
+            ReplicationAction::ResnapshotRequired { reason } => {
+                Err(ReadySetError::FullResnapshotNeeded(reason))
+            }
+        }
+    }
+
+    /// Buffer writes to `table` from the event at `pos` until the next checkpoint
+    fn buffer_table_actions(
+        &mut self,
+        table: Relation,
+        actions: Vec<TableOperation>,
+        txid: Option<u64>,
+        pos: ReplicationOffset,
+    ) {
+        match self.pending_writes.tables.entry(table) {
+            hash_map::Entry::Occupied(mut entry) => {
+                let writes = entry.get_mut();
+                writes.actions.extend(actions);
+                writes.txid = txid.or(writes.txid);
+                writes.pos = pos;
+            }
+            hash_map::Entry::Vacant(entry) => {
+                entry.insert(PendingTableWrites { actions, txid, pos });
+            }
+        }
+        self.pending_writes.since.get_or_insert_with(Instant::now);
+    }
+
+    /// Buffer a truncate of `table` from the event at `pos` until the next checkpoint.
+    ///
+    /// Base tables apply a truncate before any other writes in the same batch, so any writes to
+    /// `table` that are already buffered are discarded here - the truncate would remove them
+    /// anyway - to keep it at the start of the table's buffered writes.
+    fn buffer_truncate(&mut self, table: Relation, pos: ReplicationOffset) {
+        if let Some(writes) = self.pending_writes.tables.get_mut(&table) {
+            writes.actions.clear();
         }
+        self.buffer_table_actions(table, vec![TableOperation::Truncate], None, pos);
+    }
+
+    /// Take a checkpoint if the checkpoint policy says one is due. If `new_event` is set, the
+    /// event we've just received counts towards the policy's number of events.
+    async fn checkpoint_if_due(&mut self, new_event: bool) -> ReadySetResult<()> {
+        if self.pending_writes.tables.is_empty() {
+            return Ok(());
+        }
+
+        if new_event {
+            self.pending_writes.events += 1;
+        }
+        if self.checkpoint_policy.is_due(&self.pending_writes) {
+            self.checkpoint().await?;
+        }
+        Ok(())
+    }
+
+    /// Apply all the buffered writes, along with the replication offset of the last event each
+    /// table's writes came from
+    async fn checkpoint(&mut self) -> ReadySetResult<()> {
+        // Remove tables one at a time, so that if applying the writes to one of them fails, the
+        // writes to the others stay buffered
+        while let Some(table) = self.pending_writes.tables.keys().next().cloned() {
+            if let Some(writes) = self.pending_writes.tables.remove(&table) {
+                self.handle_table_actions(table, writes.actions, writes.txid, writes.pos)
+                    .await?;
+            }
+        }
+        self.pending_writes.events = 0;
+        self.pending_writes.since = None;
+        Ok(())
+    }
+
+    /// Apply the [`DestructiveDdlPolicy`] to the changes in `changelist`, removing destructive
+    /// changes to replicated tables (and any later changes to those tables) if we're quarantining
+    /// them, or failing if we're rejecting them. Tables named without a schema are in `schema`.
+    async fn handle_destructive_ddl(
+        &mut self,
+        changelist: &mut ChangeList,
+        schema: &str,
+    ) -> ReadySetResult<()> {
+        if self.destructive_ddl_policy == DestructiveDdlPolicy::Apply {
+            return Ok(());
+        }
+
+        let qualify = |table: &Relation| Relation {
+            schema: Some(table.schema.clone().unwrap_or_else(|| schema.into())),
+            name: table.name.clone(),
+        };
+        let changes = mem::take(changelist.changes_mut());
+        for change in changes {
+            let table = match &change {
+                Change::CreateTable(stmt) => qualify(&stmt.table),
+                Change::AlterTable(stmt) => qualify(&stmt.table),
+                Change::Drop { name, .. } => qualify(name),
+                _ => {
+                    changelist.changes_mut().push(change);
+                    continue;
+                }
+            };
+            if self.quarantined_tables.contains(&table) {
+                debug!(
+                    table = %table.display_unquoted(),
+                    "Skipping schema change to quarantined table"
+                );
+                continue;
+            }
+
+            let reason = match (&change, self.mutator_for_table(&table).await?) {
+                (Change::AlterTable(stmt), Some(mutator)) => {
+                    destructive_alter_table(stmt, mutator.schema())
+                }
+                (Change::Drop { .. }, Some(_)) => Some("drops the table".to_owned()),
+                _ => None,
+            };
+            let Some(reason) = reason else {
+                changelist.changes_mut().push(change);
+                continue;
+            };
+
+            if self.destructive_ddl_policy == DestructiveDdlPolicy::Reject {
+                return Err(ReadySetError::ReplicationFailed(format!(
+                    "Refusing to apply destructive schema change to table {}: {reason}",
+                    table.display_unquoted()
+                )));
+            }
+
+            error!(
+                table = %table.display_unquoted(),
+                %reason,
+                "Destructive schema change to replicated table, quarantining the table instead of \
+                 applying it"
+            );
+            counter!(
+                recorded::REPLICATOR_DDL_QUARANTINED,
+                1u64,
+                "table_name" => table.display_unquoted().to_string()
+            );
+            self.noria.set_table_quarantined(&table, true).await?;
+            self.quarantined_tables.insert(table);
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if actions for `table` at `pos` should be skipped, either because the table
+    /// has already seen them, because the table is not being replicated, or because the table has
+    /// been quarantined
+    fn should_skip_table_action(
+        &self,
+        table: &Relation,
+        pos: &ReplicationOffset,
+        catchup: bool,
+    ) -> ReadySetResult<bool> {
+        if self.quarantined_tables.contains(table) {
+            trace!(table = %table.display_unquoted(), "table is quarantined");
+            return Ok(true);
+        }
+
+        match self.replication_offsets.tables.get(table) {
+            Some(Some(cur)) if *pos <= *cur => {
+                if !catchup {
+                    warn!(
+                        table = %table.display_unquoted(),
+                        %pos,
+                        %cur,
+                        "Skipping table action for earlier entry"
+                    );
+                }
+                return Ok(true);
+            }
+            Some(Some(cur)) => {
+                trace!(table = %table.display_unquoted(), %cur);
+            }
+            _ => {
+                trace!(
+                    table = %table.display_unquoted(),
+                    "no replication offset for table"
+                );
+            }
+        }
+
+        Ok(!self.table_filter.should_be_processed(
+            table.schema.as_deref().ok_or_else(|| {
+                internal_err!("All tables should have a schema in the replicator")
+            })?,
+            &table.name,
+        ))
     }
 
     /// Loop over the actions. `until` may be passed to set a replication offset to stop
-    /// replicating at.
+    /// replicating at. Otherwise, returns once a shutdown signal is received on
+    /// [`Self::shutdown_rx`], after shutting down replication with [`Self::shut_down`].
     async fn main_loop(
         &mut self,
         position: &mut ReplicationOffset,
@@ -937,10 +2105,34 @@ impl NoriaAdapter {
             ));
 
             if until.as_ref().map(|u| *position >= *u).unwrap_or(false) {
+                self.checkpoint().await?;
                 return Ok(());
             }
 
-            let (action, pos) = match self.connector.next_action(position, until.as_ref()).await {
+            if until.is_none() {
+                self.handle_checksum_requests(position, None).await?;
+            }
+
+            // Only stop in between transactions, so that the offsets persisted when stopping don't
+            // point into the middle of one, which couldn't be resumed from on a different server
+            let can_stop = until.is_none() && !self.connector.in_transaction();
+            let next_action = select! {
+                biased;
+                _ = self.resnapshot_requests.requested(&self.table_filter) => {
+                    info!("Resnapshot of a table requested, taking a partial snapshot");
+                    return Err(ReadySetError::ResnapshotNeeded);
+                }
+                _ = self.shutdown_rx.recv(), if can_stop => {
+                    info!("Shutdown signal received, stopping replication");
+                    return self.shut_down(position).await;
+                }
+                _ = self.source_switch.notified(), if can_stop => {
+                    self.shut_down(position).await?;
+                    return Err(ReadySetError::ReplicationSourceChanged);
+                }
+                next_action = self.connector.next_action(position, until.as_ref()) => next_action,
+            };
+            let (action, pos) = match next_action {
                 Ok(next_action) => next_action,
                 // In some cases, we may fail to replicate because of unsupported operations, stop
                 // replicating a table if we encounter this type of error.
@@ -948,10 +2140,20 @@ impl NoriaAdapter {
                     self.deny_replication_for_table(table, source).await?;
                     continue;
                 }
+                Err(ReadySetError::TableResnapshotNeeded { table, reason }) => {
+                    warn!(table = %table.display_unquoted(), %reason, "Resnapshotting table");
+                    self.resnapshot_requests.request(table);
+                    return Err(ReadySetError::ResnapshotNeeded);
+                }
                 Err(e) => return Err(e),
             };
+            if until.is_none() {
+                self.handle_checksum_requests(position, Some(&pos)).await?;
+            }
             *position = pos.clone();
             debug!(%position, "Received replication action");
+            self.source_health.received(self.connector.lag());
+            self.replication_capture.record(&action, &pos);
 
             trace!(?action);
 
@@ -978,6 +2180,59 @@ impl NoriaAdapter {
         }
     }
 
+    /// Receive any requests for the checksums of tables sent by consistency checks, and compute the
+    /// checksum of the table in the pending request if every action up to its offset has been
+    /// handled and none after it. `position` is the position of the last action handled, and
+    /// `next` the position of the action about to be handled, if any.
+    ///
+    /// Requests are only received here, between actions, since waiting for them alongside the next
+    /// action could interrupt the connector in the middle of reading it.
+    async fn handle_checksum_requests(
+        &mut self,
+        position: &ReplicationOffset,
+        next: Option<&ReplicationOffset>,
+    ) -> ReadySetResult<()> {
+        while let Ok(request) = self.checksum_requests.try_recv() {
+            if *position > request.offset {
+                let _ = request.response.send(Ok(None));
+            } else {
+                self.pending_checksum = Some(request);
+            }
+        }
+
+        let Some(request) = self.pending_checksum.take() else {
+            return Ok(());
+        };
+        let reached =
+            *position >= request.offset || next.map_or(false, |next| *next > request.offset);
+        if !reached {
+            self.pending_checksum = Some(request);
+        } else if !request.response.is_closed() {
+            debug!(table = %request.table.display_unquoted(), %position, "Computing table checksum");
+            self.checkpoint().await?;
+            let checksum = self.noria.table_checksum(&request.table).await;
+            let _ = request.response.send(checksum.map(Some));
+        }
+        Ok(())
+    }
+
+    /// Stop replicating, once every action up to `position` has been received and handled: apply
+    /// the writes buffered for the next checkpoint, persist `position` as the replication offset
+    /// of the schema and tables, and disconnect from the upstream database, so that replication
+    /// can resume from exactly where it left off as soon as it restarts.
+    ///
+    /// Failing to disconnect isn't an error, since the offsets have been persisted by then, and
+    /// the upstream database may well be unreachable if we're switching to a different one.
+    async fn shut_down(&mut self, position: &ReplicationOffset) -> ReadySetResult<()> {
+        self.checkpoint().await?;
+        self.handle_log_position(position.clone()).await?;
+        if let Err(error) = self.connector.disconnect(position).await {
+            warn!(%error, "Could not disconnect from the upstream database");
+        }
+        info!(%position, "Replication shut down");
+        Ok(())
+    }
+
     /// When schema changes there is a risk the cached mutators will no longer be in sync
     /// and we need to drop them all
     fn clear_mutator_cache(&mut self) {
@@ -1008,6 +2263,7 @@ impl NoriaAdapter {
             "Removing table state from readyset"
         );
         self.replication_offsets.tables.remove(&table);
+        self.pending_writes.tables.remove(&table);
         self.mutator_map.remove(&table);
         // Dropping the table cleans up any dataflow state that may have been made as well as
         // cleaning up the base table on disk.
@@ -1085,3 +2341,71 @@ pub async fn pg_pool(
     let mgr = Manager::from_config(config, tls, mgr_config);
     Pool::builder(mgr).max_size(pool_size).build()
 }
+
+#[cfg(test)]
+mod tests {
+    use readyset_client::consensus::{Authority, LocalAuthority, LocalAuthorityStore};
+    use readyset_client::replication::{CaptureRedaction, ReplicationCaptureRequest};
+    use readyset_server::Builder;
+
+    use super::*;
+
+    fn offset(offset: u128) -> ReplicationOffset {
+        ReplicationOffset {
+            offset,
+            replication_log_name: "binlog.000001".into(),
+            gtid_set: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn incident_requires_full_resnapshot() {
+        let authority = Arc::new(Authority::from(LocalAuthority::new_with_store(Arc::new(
+            LocalAuthorityStore::new(),
+        ))));
+        let (mut handle, shutdown_tx) = Builder::for_tests()
+            .start(Arc::clone(&authority))
+            .await
+            .unwrap();
+        handle.backend_ready().await;
+
+        // The schema has been replicated past the incident, but tables that are behind it have to
+        // be snapshotted again all the same
+        handle
+            .set_schema_replication_offset(Some(&offset(20)))
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let capture = ReplicationCapture::new(Some(dir.path().to_owned()));
+        capture
+            .start(ReplicationCaptureRequest {
+                file_name: "capture.jsonl".into(),
+                duration: Duration::from_secs(60),
+                redaction: CaptureRedaction::None,
+            })
+            .unwrap();
+        capture.record(
+            &ReplicationAction::ResnapshotRequired {
+                reason: "Upstream reported incident: LOST_EVENTS".into(),
+            },
+            &offset(10),
+        );
+        capture.stop().await;
+
+        let res = NoriaAdapter::replay_capture(
+            ReadySetHandle::new(Arc::clone(&authority)).await,
+            &dir.path().join("capture.jsonl"),
+            DatabaseType::MySQL,
+        )
+        .await;
+        match res {
+            Err(ReadySetError::FullResnapshotNeeded(reason)) => {
+                assert!(reason.contains("LOST_EVENTS"), "{reason}")
+            }
+            res => panic!("Expected a full resnapshot to be needed, got {res:?}"),
+        }
+
+        shutdown_tx.shutdown().await;
+    }
+}
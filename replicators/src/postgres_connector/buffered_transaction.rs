@@ -0,0 +1,259 @@
+//! Buffering of the WAL records of transactions that we can't apply until they commit: large
+//! transactions that are streamed to us while they're still in progress, and prepared (two-phase)
+//! transactions.
+//!
+//! Records are buffered as the raw messages we received, and parsed again once the transaction
+//! commits. Since such transactions can be arbitrarily large, the total size of the records held
+//! in memory across all buffered transactions is capped at [`MAX_IN_MEMORY_BYTES`], after which
+//! any further records are spilled to an anonymous temporary file.
+
+use std::collections::{HashSet, VecDeque};
+use std::io::SeekFrom;
+
+use bytes::Bytes;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, BufWriter};
+
+/// The maximum total size, in bytes, of the records of all buffered transactions to keep in
+/// memory
+pub(crate) const MAX_IN_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+/// A WAL record of a buffered transaction, as the body of the `CopyData` message it was received
+/// in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct BufferedRecord {
+    /// Whether the record was received as part of a streamed transaction, and so has to be parsed
+    /// as such
+    pub(crate) in_stream: bool,
+    /// The xid of the (sub)transaction the record is from, if it's part of a streamed transaction
+    pub(crate) subxid: Option<i32>,
+    /// The message the record was received in
+    pub(crate) body: Bytes,
+}
+
+/// Records that were spilled to disk, along with whether we're still writing them or have
+/// started reading them back
+enum Spilled {
+    Writing(BufWriter<File>),
+    Reading(BufReader<File>),
+}
+
+/// The buffered records of a single transaction
+#[derive(Default)]
+pub(crate) struct BufferedTransaction {
+    /// The records held in memory, which come before any records that were spilled to disk
+    in_memory: VecDeque<BufferedRecord>,
+    /// The total size of the bodies of `in_memory`
+    in_memory_bytes: usize,
+    /// The records spilled to disk, if any
+    spilled: Option<Spilled>,
+    /// The number of records in `spilled` that haven't been read back yet
+    num_spilled: usize,
+    /// The xids of subtransactions that aborted, whose records are skipped when reading the
+    /// transaction back
+    aborted: HashSet<i32>,
+}
+
+impl BufferedTransaction {
+    /// Add a record to the end of the transaction. `buffered_bytes` is the total size of the
+    /// records of all buffered transactions held in memory, which is updated if the record is
+    /// kept in memory; if it would exceed [`MAX_IN_MEMORY_BYTES`], the record is spilled to disk
+    /// instead.
+    pub(crate) async fn push(
+        &mut self,
+        record: BufferedRecord,
+        buffered_bytes: &mut usize,
+    ) -> std::io::Result<()> {
+        let size = record.body.len();
+        if self.spilled.is_none() && *buffered_bytes + size <= MAX_IN_MEMORY_BYTES {
+            *buffered_bytes += size;
+            self.in_memory_bytes += size;
+            self.in_memory.push_back(record);
+            return Ok(());
+        }
+
+        if self.spilled.is_none() {
+            let file = tokio::task::spawn_blocking(tempfile::tempfile).await??;
+            self.spilled = Some(Spilled::Writing(BufWriter::new(File::from_std(file))));
+        }
+        let Some(Spilled::Writing(writer)) = &mut self.spilled else {
+            return Err(io_error(
+                "Can't add records to a transaction that's being read back",
+            ));
+        };
+        writer.write_u8(record.in_stream as u8).await?;
+        writer.write_u8(record.subxid.is_some() as u8).await?;
+        writer.write_i32(record.subxid.unwrap_or_default()).await?;
+        writer.write_u32(size as u32).await?;
+        writer.write_all(&record.body).await?;
+        self.num_spilled += 1;
+        Ok(())
+    }
+
+    /// Mark the subtransaction with the given xid as aborted, so its records are skipped
+    pub(crate) fn abort_subtransaction(&mut self, subxid: i32) {
+        self.aborted.insert(subxid);
+    }
+
+    /// Returns the total size of the records of this transaction held in memory
+    pub(crate) fn in_memory_bytes(&self) -> usize {
+        self.in_memory_bytes
+    }
+
+    /// Returns whether any of the records of this transaction were spilled to disk
+    pub(crate) fn is_spilled(&self) -> bool {
+        self.spilled.is_some()
+    }
+
+    /// Remove and return the next record of the transaction that isn't from an aborted
+    /// subtransaction, or `None` once every record has been read. No more records can be added
+    /// once this has been called.
+    pub(crate) async fn next_record(&mut self) -> std::io::Result<Option<BufferedRecord>> {
+        loop {
+            let record = match self.in_memory.pop_front() {
+                Some(record) => {
+                    self.in_memory_bytes -= record.body.len();
+                    record
+                }
+                None if self.num_spilled == 0 => return Ok(None),
+                None => self.read_spilled().await?,
+            };
+            if !record
+                .subxid
+                .map_or(false, |xid| self.aborted.contains(&xid))
+            {
+                return Ok(Some(record));
+            }
+        }
+    }
+
+    async fn read_spilled(&mut self) -> std::io::Result<BufferedRecord> {
+        self.spilled = match self.spilled.take() {
+            Some(Spilled::Writing(mut writer)) => {
+                writer.flush().await?;
+                let mut file = writer.into_inner();
+                file.seek(SeekFrom::Start(0)).await?;
+                Some(Spilled::Reading(BufReader::new(file)))
+            }
+            spilled => spilled,
+        };
+        let Some(Spilled::Reading(reader)) = &mut self.spilled else {
+            return Err(io_error("No spilled records to read"));
+        };
+
+        let in_stream = reader.read_u8().await? != 0;
+        let has_subxid = reader.read_u8().await? != 0;
+        let subxid = reader.read_i32().await?;
+        let mut body = vec![0; reader.read_u32().await? as usize];
+        reader.read_exact(&mut body).await?;
+        self.num_spilled -= 1;
+
+        Ok(BufferedRecord {
+            in_stream,
+            subxid: has_subxid.then_some(subxid),
+            body: body.into(),
+        })
+    }
+}
+
+fn io_error(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Other, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(subxid: i32, body: &[u8]) -> BufferedRecord {
+        BufferedRecord {
+            in_stream: true,
+            subxid: Some(subxid),
+            body: Bytes::copy_from_slice(body),
+        }
+    }
+
+    async fn read_all(txn: &mut BufferedTransaction) -> Vec<BufferedRecord> {
+        let mut records = vec![];
+        while let Some(record) = txn.next_record().await.unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    #[tokio::test]
+    async fn records_are_kept_in_memory_up_to_limit() {
+        let mut buffered_bytes = 0;
+        let mut txn = BufferedTransaction::default();
+        txn.push(record(1, b"one"), &mut buffered_bytes)
+            .await
+            .unwrap();
+        txn.push(record(1, b"two"), &mut buffered_bytes)
+            .await
+            .unwrap();
+        assert_eq!(buffered_bytes, 6);
+        assert_eq!(txn.in_memory_bytes(), 6);
+        assert!(!txn.is_spilled());
+
+        assert_eq!(
+            read_all(&mut txn).await,
+            vec![record(1, b"one"), record(1, b"two")]
+        );
+        assert_eq!(txn.in_memory_bytes(), 0);
+    }
+
+    #[tokio::test]
+    async fn records_over_limit_are_spilled_in_order() {
+        // Another transaction is already using nearly all of the memory
+        let mut buffered_bytes = MAX_IN_MEMORY_BYTES - 4;
+        let mut txn = BufferedTransaction::default();
+        txn.push(record(1, b"one"), &mut buffered_bytes)
+            .await
+            .unwrap();
+        txn.push(record(1, b"two"), &mut buffered_bytes)
+            .await
+            .unwrap();
+        // Once spilled, later records go to disk even if they'd fit in memory
+        txn.push(
+            BufferedRecord {
+                in_stream: false,
+                subxid: None,
+                body: Bytes::from_static(b"3"),
+            },
+            &mut buffered_bytes,
+        )
+        .await
+        .unwrap();
+        assert_eq!(buffered_bytes, MAX_IN_MEMORY_BYTES - 1);
+        assert!(txn.is_spilled());
+
+        assert_eq!(
+            read_all(&mut txn).await,
+            vec![
+                record(1, b"one"),
+                record(1, b"two"),
+                BufferedRecord {
+                    in_stream: false,
+                    subxid: None,
+                    body: Bytes::from_static(b"3"),
+                }
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn aborted_subtransactions_are_skipped() {
+        let mut buffered_bytes = MAX_IN_MEMORY_BYTES - 8;
+        let mut txn = BufferedTransaction::default();
+        for (subxid, body) in [(1, b"aaaa"), (2, b"bbbb"), (1, b"cccc"), (2, b"dddd")] {
+            txn.push(record(subxid, body), &mut buffered_bytes)
+                .await
+                .unwrap();
+        }
+        txn.abort_subtransaction(2);
+
+        assert_eq!(
+            read_all(&mut txn).await,
+            vec![record(1, b"aaaa"), record(1, b"cccc")]
+        );
+    }
+}
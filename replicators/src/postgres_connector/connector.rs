@@ -1,17 +1,26 @@
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
 use database_utils::UpstreamConfig;
 #[cfg(feature = "failure_injection")]
 use failpoint_macros::set_failpoint;
 use futures::FutureExt;
+use itertools::Itertools;
+use metrics::gauge;
 use nom_sql::Relation;
 use pgsql::SimpleQueryMessage;
 use postgres_native_tls::MakeTlsConnector;
 use postgres_protocol::escape::escape_literal;
 #[cfg(feature = "failure_injection")]
 use readyset_client::failpoints;
+use readyset_client::metrics::recorded;
+use readyset_client::recipe::changelist::{Change, ChangeList};
 use readyset_client::replication::ReplicationOffset;
-use readyset_client::TableOperation;
-use readyset_errors::{invariant, set_failpoint_return_err, ReadySetError, ReadySetResult};
+use readyset_client::{ReadySetHandle, TableOperation};
+use readyset_data::Dialect;
+use readyset_errors::{set_failpoint_return_err, ReadySetError, ReadySetResult};
+use readyset_tracing::statement_logging::Component;
 use readyset_util::select;
 use tokio_postgres as pgsql;
 use tracing::{debug, error, info, trace, warn};
@@ -19,11 +28,22 @@ use tracing::{debug, error, info, trace, warn};
 use super::ddl_replication::setup_ddl_replication;
 use super::lsn::Lsn;
 use super::wal_reader::{WalEvent, WalReader};
-use super::{PostgresPosition, PUBLICATION_NAME};
+use super::{pg_error, PostgresPosition, PUBLICATION_NAME};
 use crate::db_util::error_is_slot_not_found;
 use crate::noria_adapter::{Connector, ReplicationAction};
 use crate::postgres_connector::wal::{TableErrorKind, WalError};
 
+/// How often to tell the server how far we've applied the WAL, so that it can release the WAL
+/// before that position
+const STATUS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often to check how much WAL the server is retaining for the replication slot
+const SLOT_MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often to check whether tables have been added to or dropped from the publications we
+/// replicate from
+const PUBLICATION_MONITOR_INTERVAL: Duration = Duration::from_secs(30);
+
 /// A connector that connects to a PostgreSQL server and starts reading WAL from the "noria"
 /// replication slot with the "noria" publication.
 ///
@@ -36,6 +56,8 @@ use crate::postgres_connector::wal::{TableErrorKind, WalError};
 /// `CREATE` - To create a publication, the user must have the CREATE privilege in the database. To
 /// add tables to a publication, the user must have ownership rights on the table. To create a
 /// publication that publishes all tables automatically, the user must be a superuser.
+///
+/// These are checked when replication starts, by [`check_privileges`](super::check_privileges).
 pub struct PostgresWalConnector {
     /// This is the underlying (regular) PostgreSQL client
     client: pgsql::Client,
@@ -49,8 +71,8 @@ pub struct PostgresWalConnector {
     next_position: Option<PostgresPosition>,
     /// The replication slot if was created for this connector
     pub(crate) replication_slot: Option<CreatedSlot>,
-    /// Whether to log statements received by the connector
-    enable_statement_logging: bool,
+    /// When we last sent the server a standby status update
+    last_status_update: Instant,
 }
 
 /// The decoded response to `IDENTIFY_SYSTEM`
@@ -80,7 +102,8 @@ pub(crate) struct CreatedSlot {
     pub(crate) consistent_point: i64,
     /// The identifier of the snapshot exported by the command. The snapshot is valid until a
     /// new command is executed on this connection or the replication connection is closed.
-    /// Null if the created slot is physical.
+    /// Null if the created slot is physical, and empty for a slot we didn't create ourselves,
+    /// whose tables are snapshotted from a dump instead.
     pub(crate) snapshot_name: String,
     /// The name of the output plugin used by the newly-created replication slot.
     /// Null if the created slot is physical.
@@ -97,16 +120,28 @@ impl PostgresWalConnector {
         next_position: Option<PostgresPosition>,
         tls_connector: MakeTlsConnector,
         repl_slot_name: &str,
-        enable_statement_logging: bool,
     ) -> ReadySetResult<Self> {
         if !config.disable_setup_ddl_replication {
             setup_ddl_replication(pg_config.clone(), tls_connector.clone()).await?;
         }
         pg_config.dbname(dbname.as_ref()).set_replication_database();
 
-        let (client, connection) = pg_config.connect(tls_connector).await?;
+        let (client, connection) = pg_config.connect(tls_connector).await.map_err(pg_error)?;
         let connection_handle = tokio::spawn(connection);
 
+        // Replicate from the publications we've been given, if any, rather than creating our own
+        let publications = if config.replication_publications.is_empty() {
+            vec![PUBLICATION_NAME.to_owned()]
+        } else {
+            config
+                .replication_publications
+                .iter()
+                .unique()
+                .cloned()
+                .collect()
+        };
+        let create_publication = config.replication_publications.is_empty();
+
         let mut connector = PostgresWalConnector {
             client,
             connection_handle,
@@ -114,23 +149,61 @@ impl PostgresWalConnector {
             peek: None,
             next_position,
             replication_slot: None,
-            enable_statement_logging,
+            last_status_update: Instant::now(),
         };
 
-        if next_position.is_none() {
+        if next_position.is_none() && config.postgres_snapshot_dump.is_some() {
+            // Tables are snapshotted from a dump taken from the snapshot our replication slot
+            // exported when it was created, so keep the slot to replicate from where it was created
+            let slot = connector.dumped_slot(repl_slot_name, &publications).await?;
+            connector.replication_slot = Some(slot);
+        } else if next_position.is_none() {
             // If we don't have a consistent replication offset to start replicating from, drop and
             // recreate our replication slot.
             //
             // Note that later on, this means we'll need to make sure we resnapshot *all* tables!
             connector
-                .create_publication_and_slot(repl_slot_name)
+                .create_publication_and_slot(repl_slot_name, create_publication)
                 .await?;
+        } else if !connector
+            .slot_and_publications_exist(repl_slot_name, &publications)
+            .await?
+        {
+            // The slot (or a publication it decodes with) was dropped while we weren't running,
+            // so the WAL we'd need to resume from may be gone. Recreate them, which means
+            // resnapshotting all tables, just like when we have no replication offset.
+            warn!(
+                slot = repl_slot_name,
+                ?publications,
+                "Replication slot or publication missing, recreating them"
+            );
+            connector
+                .create_publication_and_slot(repl_slot_name, create_publication)
+                .await?;
+        }
+
+        if !create_publication {
+            // We can't recreate publications we were given, so make sure they exist rather than
+            // failing later with a less helpful error
+            for publication in &publications {
+                if !connector.publication_exists(publication).await? {
+                    return Err(ReadySetError::ReplicationFailed(format!(
+                        "Publication {publication} does not exist"
+                    )));
+                }
+            }
         }
 
         Ok(connector)
     }
 
-    async fn create_publication_and_slot(&mut self, repl_slot_name: &str) -> ReadySetResult<()> {
+    /// Create our replication slot, and our publication for all tables if `create_publication` is
+    /// set
+    async fn create_publication_and_slot(
+        &mut self,
+        repl_slot_name: &str,
+        create_publication: bool,
+    ) -> ReadySetResult<()> {
         let system = self.identify_system().await?;
         debug!(
             id = %system.id,
@@ -139,20 +212,23 @@ impl PostgresWalConnector {
             dbname = ?system.dbname
         );
 
-        match self.create_publication(PUBLICATION_NAME).await {
-            Ok(()) => {
-                // Created a new publication, everything is good
-            }
-            Err(err)
-                if err.to_string().contains("publication")
-                    && err.to_string().contains("already exists") =>
-            {
-                // This is an existing publication we are going to use
-            }
-            Err(err) if err.to_string().contains("permission denied") => {
-                error!("Insufficient permissions to create publication FOR ALL TABLES");
+        // If we replicate from publications we were given, we don't need our own
+        if create_publication {
+            match self.create_publication(PUBLICATION_NAME).await {
+                Ok(()) => {
+                    // Created a new publication, everything is good
+                }
+                Err(err)
+                    if err.to_string().contains("publication")
+                        && err.to_string().contains("already exists") =>
+                {
+                    // This is an existing publication we are going to use
+                }
+                Err(err) if err.to_string().contains("permission denied") => {
+                    error!("Insufficient permissions to create publication FOR ALL TABLES");
+                }
+                Err(err) => return Err(err),
             }
-            Err(err) => return Err(err),
         }
 
         // Drop the existing slot if any
@@ -173,6 +249,79 @@ impl PostgresWalConnector {
         Ok(())
     }
 
+    /// Returns the replication slot `slot` that a dump given by
+    /// [`UpstreamConfig::postgres_snapshot_dump`] was taken from, as of the position it was
+    /// created at. The slot and all of the `publications` must already exist. Since the snapshot
+    /// the slot exported can't be used again, the returned slot has no snapshot name.
+    async fn dumped_slot(
+        &mut self,
+        slot: &str,
+        publications: &[String],
+    ) -> ReadySetResult<CreatedSlot> {
+        if !self.slot_and_publications_exist(slot, publications).await? {
+            return Err(ReadySetError::ReplicationFailed(format!(
+                "Replication slot {slot} and publications {} must be created before taking the \
+                 dump to snapshot from",
+                publications.join(", ")
+            )));
+        }
+
+        let query = format!(
+            "SELECT confirmed_flush_lsn FROM pg_replication_slots WHERE slot_name = {}",
+            escape_literal(slot),
+        );
+        let confirmed_flush_lsn = self
+            .simple_query(&query)
+            .await?
+            .into_iter()
+            .find_map(|m| match m {
+                SimpleQueryMessage::Row(r) => r.get(0).map(|v| v.to_owned()),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        Ok(CreatedSlot {
+            slot_name: slot.to_owned(),
+            consistent_point: parse_wal(&confirmed_flush_lsn)?,
+            snapshot_name: String::new(),
+            output_plugin: "pgoutput".to_owned(),
+        })
+    }
+
+    /// Returns true if the replication slot `slot` and all of the `publications` exist on the
+    /// server
+    async fn slot_and_publications_exist(
+        &mut self,
+        slot: &str,
+        publications: &[String],
+    ) -> ReadySetResult<bool> {
+        let query = format!(
+            "SELECT 1 FROM pg_replication_slots WHERE slot_name = {} \
+             AND (SELECT count(*) FROM pg_publication WHERE pubname IN ({})) = {}",
+            escape_literal(slot),
+            publications.iter().map(|p| escape_literal(p)).join(", "),
+            publications.len(),
+        );
+        Ok(self
+            .simple_query(&query)
+            .await?
+            .iter()
+            .any(|m| matches!(m, SimpleQueryMessage::Row(_))))
+    }
+
+    /// Returns true if the publication `publication` exists on the server
+    async fn publication_exists(&mut self, publication: &str) -> ReadySetResult<bool> {
+        let query = format!(
+            "SELECT 1 FROM pg_publication WHERE pubname = {}",
+            escape_literal(publication),
+        );
+        Ok(self
+            .simple_query(&query)
+            .await?
+            .iter()
+            .any(|m| matches!(m, SimpleQueryMessage::Row(_))))
+    }
+
     /// Waits and returns the next WAL event, while monitoring the connection
     /// handle for errors.
     async fn next_event(&mut self) -> Result<(WalEvent, Lsn), WalError> {
@@ -303,20 +452,37 @@ impl PostgresWalConnector {
             .unwrap_or_default();
 
         let inner_client = self.client.inner();
-        let wal_position = self.next_position.unwrap_or_default();
-        let messages_support = if version >= 140000 {
-            ", \"messages\" 'true'"
+        let resumed_from = self.next_position.unwrap_or_default().lsn;
+        // We don't acknowledge WAL past the prepare of a transaction we haven't seen the commit of
+        // yet (see `send_standy_status_update`), so start from there if it's earlier, since the
+        // server won't send the changes of a transaction that was prepared before where we start.
+        // Anything that was already applied before `resumed_from` is skipped when we get to it.
+        let wal_position = match parse_wal(&confirmed_flush_lsn) {
+            Ok(confirmed_flush) => resumed_from.min(Lsn(confirmed_flush)),
+            Err(_) => resumed_from,
+        };
+        // Postgres 14 added logical decoding messages, and streaming of large transactions while
+        // they're still in progress (protocol version 2), rather than spilling them to disk on the
+        // server until they commit. Postgres 15 added decoding of transactions prepared for
+        // two-phase commit (protocol version 3), which are buffered until `COMMIT PREPARED`.
+        let (proto_version, options) = if version >= 150000 {
+            (
+                "3",
+                ", \"messages\" 'true', \"streaming\" 'on', \"two_phase\" 'on'",
+            )
+        } else if version >= 140000 {
+            ("2", ", \"messages\" 'true', \"streaming\" 'on'")
         } else {
-            ""
+            ("1", "")
         };
 
-        debug!(%wal_position, %slot, postgres_version = %version, %confirmed_flush_lsn, "Starting replication");
+        debug!(%wal_position, %slot, postgres_version = %version, %confirmed_flush_lsn, proto_version, "Starting replication");
 
         let query = format!(
             "START_REPLICATION SLOT {slot} LOGICAL {wal_position} (
-                \"proto_version\" '1',
+                \"proto_version\" '{proto_version}',
                 \"publication_names\" '{publication}'
-                {messages_support}
+                {options}
             )",
         );
 
@@ -340,12 +506,12 @@ impl PostgresWalConnector {
             }
         }
 
-        self.reader = Some(WalReader::new(wal));
+        self.reader = Some(WalReader::new(wal, resumed_from));
 
         Ok(())
     }
 
-    fn send_standy_status_update(&self, ack: PostgresPosition) -> ReadySetResult<()> {
+    fn send_standy_status_update(&mut self, ack: PostgresPosition) -> ReadySetResult<()> {
         use bytes::{BufMut, BytesMut};
 
         // The difference between UNIX and Postgres epoch
@@ -357,7 +523,12 @@ impl PostgresWalConnector {
             .as_micros() as u64
             - J2000_EPOCH_GAP;
 
-        let pos = ack.lsn.0 + 1;
+        let mut pos = ack.lsn.0 + 1;
+        // Don't acknowledge the changes of prepared transactions we haven't applied yet, so that
+        // the server sends them again if we restart before they're committed
+        if let Some(prepare_lsn) = self.reader.as_ref().and_then(|r| r.oldest_prepared_lsn()) {
+            pos = pos.min(prepare_lsn.0);
+        }
 
         // Can reply with StandbyStatusUpdate or HotStandbyFeedback
         let mut b = BytesMut::with_capacity(39);
@@ -374,6 +545,7 @@ impl PostgresWalConnector {
             .send(pgsql::connection::RequestMessages::Single(
                 pgsql::codec::FrontendMessage::Raw(b.freeze()),
             ))?;
+        self.last_status_update = Instant::now();
 
         Ok(())
     }
@@ -420,7 +592,7 @@ impl PostgresWalConnector {
 
     /// Perform a simple query and return the resulting rows
     async fn simple_query(&mut self, query: &str) -> ReadySetResult<Vec<SimpleQueryMessage>> {
-        Ok(self.client.simple_query(query).await?)
+        self.client.simple_query(query).await.map_err(pg_error)
     }
 }
 
@@ -448,6 +620,187 @@ pub async fn drop_replication_slot(client: &mut pgsql::Client, name: &str) -> Re
     }
 }
 
+/// Periodically check how much WAL the server is retaining for the replication slot `slot`,
+/// recording it in the [`recorded::REPLICATOR_SLOT_RETAINED_WAL`] metric, and logging a warning if
+/// it's more than `warn_bytes` (unless that's 0). Never returns.
+pub(crate) async fn monitor_replication_slot(
+    pool: deadpool_postgres::Pool,
+    slot: String,
+    warn_bytes: u64,
+) {
+    let mut interval = tokio::time::interval(SLOT_MONITOR_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let retained: ReadySetResult<Option<i64>> = async {
+            let client = pool.get().await?;
+            let row = client
+                .query_opt(
+                    "SELECT pg_wal_lsn_diff(pg_current_wal_lsn(), restart_lsn)::bigint \
+                     FROM pg_replication_slots WHERE slot_name = $1",
+                    &[&slot],
+                )
+                .await?;
+            Ok(row.and_then(|row| row.get::<_, Option<i64>>(0)))
+        }
+        .await;
+
+        match retained {
+            Ok(Some(retained)) => {
+                gauge!(recorded::REPLICATOR_SLOT_RETAINED_WAL, retained as f64);
+                if warn_bytes > 0 && retained > warn_bytes as i64 {
+                    warn!(
+                        %slot,
+                        retained_bytes = retained,
+                        "Upstream database is retaining a large amount of WAL for the replication \
+                         slot"
+                    );
+                }
+            }
+            Ok(None) => debug!(%slot, "Replication slot not found while checking retained WAL"),
+            Err(error) => {
+                debug!(%error, %slot, "Could not check WAL retained for replication slot")
+            }
+        }
+    }
+}
+
+/// Returns the schema and name of each of the tables in any of the `publications`.
+///
+/// Since Postgres 15, tables can be published with a row filter or a column list, which only
+/// apply to the changes we replicate and not to the snapshot, so the data we hold would be
+/// inconsistent with what's replicated; if any of the tables are, returns
+/// [`ReadySetError::Unsupported`] listing them.
+pub(crate) async fn publication_tables(
+    client: &pgsql::Client,
+    publications: &[String],
+) -> ReadySetResult<BTreeSet<(String, String)>> {
+    let version: u32 = client
+        .query_one("SHOW server_version_num", &[])
+        .await
+        .map_err(pg_error)?
+        .get::<_, String>(0)
+        .parse()
+        .map_err(|e| ReadySetError::ReplicationFailed(format!("Invalid server version: {e}")))?;
+    if version >= 150000 {
+        let filtered: Vec<String> = client
+            .query(
+                "SELECT DISTINCT format('%I.%I', n.nspname, c.relname) \
+                 FROM pg_publication p \
+                 JOIN pg_publication_rel pr ON pr.prpubid = p.oid \
+                 JOIN pg_class c ON c.oid = pr.prrelid \
+                 JOIN pg_namespace n ON n.oid = c.relnamespace \
+                 WHERE p.pubname = ANY($1) \
+                 AND (pr.prqual IS NOT NULL OR pr.prattrs IS NOT NULL)",
+                &[&publications],
+            )
+            .await
+            .map_err(pg_error)?
+            .into_iter()
+            .map(|row| row.get(0))
+            .collect();
+        if !filtered.is_empty() {
+            return Err(ReadySetError::Unsupported(format!(
+                "Tables published with a row filter or column list can't be replicated: {}",
+                filtered.join(", ")
+            )));
+        }
+    }
+
+    Ok(client
+        .query(
+            "SELECT schemaname::text, tablename::text FROM pg_publication_tables \
+             WHERE pubname = ANY($1)",
+            &[&publications],
+        )
+        .await
+        .map_err(pg_error)?
+        .into_iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect())
+}
+
+/// Periodically check whether any tables have been added to or dropped from the `publications`
+/// we replicate from (with `ALTER PUBLICATION`) since they contained `tables`, and if so, return
+/// [`ReadySetError::ResnapshotNeeded`] so that replication restarts with the new set of tables,
+/// snapshotting any that were added. Tables that were dropped from the publications are dropped
+/// from ReadySet first, since they'll no longer be replicated.
+///
+/// If a row filter or column list is added to any of the tables, returns the error from
+/// [`publication_tables`]. If `tables` is `None`, because we aren't replicating from a list of
+/// publications, never returns.
+pub(crate) async fn monitor_publications(
+    pool: deadpool_postgres::Pool,
+    mut noria: ReadySetHandle,
+    publications: Vec<String>,
+    tables: Option<BTreeSet<(String, String)>>,
+) -> ReadySetError {
+    let Some(tables) = tables else {
+        return futures::future::pending().await;
+    };
+
+    let mut interval = tokio::time::interval(PUBLICATION_MONITOR_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let current = async {
+            let client = pool.get().await?;
+            publication_tables(&client, &publications).await
+        }
+        .await;
+        let current = match current {
+            Ok(current) if current != tables => current,
+            Ok(_) => continue,
+            // A row filter or column list was added to a table we replicate, so what we
+            // replicate from now on won't match what we hold
+            Err(error) if error.caused_by_unsupported() => return error,
+            Err(error) => {
+                debug!(%error, ?publications, "Could not check tables in publications");
+                continue;
+            }
+        };
+
+        let dropped = tables
+            .difference(&current)
+            .map(|(schema, name)| Relation {
+                schema: Some(schema.into()),
+                name: name.into(),
+            })
+            .collect::<Vec<_>>();
+        info!(
+            added = current.difference(&tables).count(),
+            dropped = dropped.len(),
+            "Tables in replicated publications changed, restarting replication"
+        );
+
+        if !dropped.is_empty() {
+            let changes = dropped
+                .into_iter()
+                .flat_map(|table| {
+                    [
+                        Change::Drop {
+                            name: table.clone(),
+                            if_exists: true,
+                        },
+                        Change::AddNonReplicatedRelation(table),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            if let Err(error) = noria
+                .extend_recipe(ChangeList::from_changes(
+                    changes,
+                    Dialect::DEFAULT_POSTGRESQL,
+                ))
+                .await
+            {
+                return error;
+            }
+        }
+
+        return ReadySetError::ResnapshotNeeded;
+    }
+}
+
 pub async fn drop_publication(client: &mut pgsql::Client, name: &str) -> ReadySetResult<()> {
     info!(slot = name, "Dropping publication if exists");
     client
@@ -488,6 +841,10 @@ impl Drop for PostgresWalConnector {
 
 #[async_trait]
 impl Connector for PostgresWalConnector {
+    fn lag(&self) -> Option<Duration> {
+        self.reader.as_ref()?.lag()
+    }
+
     /// Process WAL events and batch them into actions
     async fn next_action(
         &mut self,
@@ -496,6 +853,12 @@ impl Connector for PostgresWalConnector {
     ) -> ReadySetResult<(ReplicationAction, ReplicationOffset)> {
         set_failpoint_return_err!(failpoints::POSTGRES_REPLICATION_NEXT_ACTION);
 
+        // `last_pos` has been applied, so let the server release the WAL before it rather than
+        // waiting for it to ask for a status update
+        if self.last_status_update.elapsed() >= STATUS_UPDATE_INTERVAL {
+            self.send_standy_status_update(last_pos.into())?;
+        }
+
         // Calling the ReadySet API is a bit expensive, therefore we try to queue as many actions
         // as possible before calling into the API. We therefore try to stop batching actions when
         // we hit this limit, BUT note that we may substantially exceed this limit in cases where
@@ -552,6 +915,7 @@ impl Connector for PostgresWalConnector {
                         table: cur_table,
                         actions,
                         txid: None,
+                        query: None,
                     },
                     cur_lsn.into(),
                 ));
@@ -561,67 +925,30 @@ impl Connector for PostgresWalConnector {
 
             trace!(?event);
             // Don't log the statement if we're catching up
-            if self.enable_statement_logging {
+            if Component::Replicator.is_enabled() {
                 info!(target: "replicator_statement", "{:?}", event);
             }
 
             // Check if next event is for another table, in which case we have to flush the events
             // accumulated for this table and store the next event in `peek`.
             match &mut event {
-                WalEvent::Truncate { tables } => {
-                    let (matching, mut other_tables) =
-                        tables.drain(..).partition::<Vec<_>, _>(|(schema, table)| {
-                            cur_table.schema.as_deref() == Some(schema.as_str())
-                                && cur_table.name == table.as_str()
-                        });
-
-                    if actions.is_empty() {
-                        invariant!(matching.is_empty());
-                        if let Some((schema, name)) = other_tables.pop() {
-                            if !other_tables.is_empty() {
-                                self.peek = Some((
-                                    WalEvent::Truncate {
-                                        tables: other_tables,
-                                    },
-                                    lsn,
-                                ));
-                            }
-
-                            actions.push(TableOperation::Truncate);
-                            return Ok((
-                                ReplicationAction::TableAction {
-                                    table: Relation {
-                                        schema: Some(schema.into()),
-                                        name: name.into(),
-                                    },
-                                    actions,
-                                    txid: None,
-                                },
-                                PostgresPosition::from(lsn).into(),
-                            ));
-                        } else {
-                            // Empty truncate op
-                            continue;
-                        }
+                WalEvent::Truncate { tables } if !actions.is_empty() => {
+                    if tables.iter().any(|(schema, table)| {
+                        cur_table.schema.as_deref() == Some(schema.as_str())
+                            && cur_table.name == table.as_str()
+                    }) {
+                        // The writes to the current table would all be truncated anyway, so
+                        // there's no need to apply them
+                        actions.clear();
                     } else {
-                        if !other_tables.is_empty() {
-                            self.peek = Some((
-                                WalEvent::Truncate {
-                                    tables: other_tables,
-                                },
-                                lsn,
-                            ));
-                        }
-
-                        if !matching.is_empty() {
-                            actions.push(TableOperation::Truncate);
-                        }
-
+                        // Apply the writes to the current table before truncating the others
+                        self.peek = Some((event, lsn));
                         return Ok((
                             ReplicationAction::TableAction {
                                 table: cur_table,
                                 actions,
                                 txid: None,
+                                query: None,
                             },
                             cur_lsn.into(),
                         ));
@@ -642,6 +969,7 @@ impl Connector for PostgresWalConnector {
                                 table: cur_table,
                                 actions,
                                 txid: None,
+                                query: None,
                             },
                             cur_lsn.into(),
                         ));
@@ -674,14 +1002,37 @@ impl Connector for PostgresWalConnector {
                                 table: cur_table,
                                 actions,
                                 txid: None,
+                                query: None,
                             },
                             cur_lsn.into(),
                         ));
                     }
                 }
+                WalEvent::Truncate { tables } => {
+                    if !tables.is_empty() {
+                        return Ok((
+                            ReplicationAction::Truncate {
+                                tables: tables
+                                    .into_iter()
+                                    .map(|(schema, name)| Relation {
+                                        schema: Some(schema.into()),
+                                        name: name.into(),
+                                    })
+                                    .collect(),
+                            },
+                            PostgresPosition::from(lsn).into(),
+                        ));
+                    }
+                }
                 WalEvent::WantsKeepaliveResponse => {
                     self.send_standy_status_update(last_pos.into())?;
                 }
+                // Report our position even if there are no writes to replicate, so that anything
+                // waiting for replication to reach a position doesn't wait for the next write
+                WalEvent::CaughtUp if actions.is_empty() => {
+                    return Ok((ReplicationAction::LogPosition, cur_lsn.into()));
+                }
+                WalEvent::CaughtUp => {}
                 WalEvent::Commit => {
                     if !actions.is_empty() {
                         // On commit we flush, because there is no knowing when the next commit is
@@ -691,6 +1042,7 @@ impl Connector for PostgresWalConnector {
                                 table: cur_table,
                                 actions,
                                 txid: None,
+                                query: None,
                             },
                             cur_lsn.into(),
                         ));
@@ -716,8 +1068,28 @@ impl Connector for PostgresWalConnector {
                 WalEvent::UpdateByKey { key, set, .. } => {
                     actions.push(TableOperation::Update { key, update: set })
                 }
-                WalEvent::Truncate { .. } => actions.push(TableOperation::Truncate),
             }
         }
     }
+
+    async fn disconnect(&mut self, last_pos: &ReplicationOffset) -> ReadySetResult<()> {
+        use bytes::{BufMut, BytesMut};
+
+        // Let the server release the WAL we've applied, then ask the walsender to exit with a
+        // Terminate message, so that the replication slot is released straight away rather than
+        // once the server notices the connection has dropped
+        self.send_standy_status_update(last_pos.into())?;
+        let mut b = BytesMut::with_capacity(5);
+        b.put_u8(b'X'); // Terminate
+        b.put_i32(4); // Message length (including this field)
+        self.client
+            .inner()
+            .send(pgsql::connection::RequestMessages::Single(
+                pgsql::codec::FrontendMessage::Raw(b.freeze()),
+            ))?;
+        // The connection ends once the server closes it
+        let _ = (&mut self.connection_handle).await;
+        info!("Disconnected from replication slot");
+        Ok(())
+    }
 }
@@ -0,0 +1,550 @@
+//! Snapshotting tables from a logical dump taken with `pg_dump`, instead of by reading them from
+//! the upstream database, per [`UpstreamConfig::postgres_snapshot_dump`].
+//!
+//! `pg_dump` doesn't record a WAL position the dump is consistent with, so it has to be taken from
+//! the snapshot exported when our replication slot was created, and replication starts from where
+//! the slot was created. Since that snapshot can't be used again, every replicated table is
+//! snapshotted from the dump.
+//!
+//! Rows are read from the `COPY ... FROM stdin` blocks of a plain format dump, so the dump mustn't
+//! be taken with `--inserts`. Only the rows in the dump are used: the schema is loaded from the
+//! upstream database, so the tables mustn't have been altered since the dump was taken.
+//!
+//! [`UpstreamConfig::postgres_snapshot_dump`]: database_utils::UpstreamConfig::postgres_snapshot_dump
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use nom_sql::Relation;
+use readyset_data::{DfType, DfValue};
+use readyset_errors::{ReadySetError, ReadySetResult};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader, SeekFrom, Take};
+
+fn dump_file_error(path: &Path, error: impl std::fmt::Display) -> ReadySetError {
+    ReadySetError::ReplicationFailed(format!(
+        "Failed to read dump file {}: {error}",
+        path.display()
+    ))
+}
+
+/// The rows of a table copied by a `COPY` statement in a dump file
+#[derive(Debug, Clone)]
+struct CopiedRows {
+    /// The columns the rows have values for, in order
+    columns: Vec<String>,
+    /// The range of bytes of the file holding the rows
+    range: Range<u64>,
+}
+
+/// A `pg_dump` file, indexed by the tables whose rows it contains
+pub(crate) struct DumpFile {
+    path: PathBuf,
+    tables: HashMap<Relation, CopiedRows>,
+}
+
+impl DumpFile {
+    /// Open the dump file at `path`, reading it through once to find where each table's rows are
+    pub(crate) async fn open(path: PathBuf) -> ReadySetResult<Self> {
+        let mut reader = BufReader::new(
+            File::open(&path)
+                .await
+                .map_err(|e| dump_file_error(&path, e))?,
+        );
+
+        let mut tables = HashMap::new();
+        // The table whose rows are being read, with its columns and where its rows start
+        let mut copying: Option<(Relation, Vec<String>, u64)> = None;
+        let mut offset = 0;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let len = reader
+                .read_until(b'\n', &mut line)
+                .await
+                .map_err(|e| dump_file_error(&path, e))?;
+            if len == 0 {
+                break;
+            }
+            let start = offset;
+            offset += len as u64;
+
+            match copying.take() {
+                Some((table, columns, rows_start)) if trim_newline(&line) == b"\\." => {
+                    tables.insert(
+                        table,
+                        CopiedRows {
+                            columns,
+                            range: rows_start..start,
+                        },
+                    );
+                }
+                Some(copy) => copying = Some(copy),
+                None => {
+                    if let Some(rest) = line.strip_prefix(b"COPY ") {
+                        let (table, columns) = parse_copy(rest)
+                            .ok_or_else(|| dump_file_error(&path, "Malformed COPY statement"))?;
+                        copying = Some((table, columns, offset));
+                    }
+                }
+            }
+        }
+
+        if let Some((table, ..)) = copying {
+            return Err(dump_file_error(
+                &path,
+                format!(
+                    "The rows of {} aren't terminated",
+                    table.display(nom_sql::Dialect::PostgreSQL)
+                ),
+            ));
+        }
+        Ok(Self { path, tables })
+    }
+
+    /// Read the rows of `table` from the dump, as values for the given `columns` of the table
+    /// (each with the type it should be coerced to), in order
+    pub(crate) async fn rows(
+        &self,
+        table: &Relation,
+        columns: Vec<(String, DfType)>,
+    ) -> ReadySetResult<DumpRows> {
+        let Some(copied) = self.tables.get(table) else {
+            return Err(dump_file_error(
+                &self.path,
+                format!(
+                    "The dump doesn't contain {}",
+                    table.display(nom_sql::Dialect::PostgreSQL)
+                ),
+            ));
+        };
+        // The rows may list the columns in a different order than the table has them
+        let positions = columns
+            .iter()
+            .map(|(column, _)| {
+                copied
+                    .columns
+                    .iter()
+                    .position(|name| name == column)
+                    .ok_or_else(|| {
+                        dump_file_error(
+                            &self.path,
+                            format!("Column {column} is missing from COPY statement"),
+                        )
+                    })
+            })
+            .collect::<ReadySetResult<Vec<_>>>()?;
+
+        let mut file = File::open(&self.path)
+            .await
+            .map_err(|e| dump_file_error(&self.path, e))?;
+        file.seek(SeekFrom::Start(copied.range.start))
+            .await
+            .map_err(|e| dump_file_error(&self.path, e))?;
+        Ok(DumpRows {
+            path: self.path.clone(),
+            reader: BufReader::new(file.take(copied.range.end - copied.range.start)),
+            columns,
+            positions,
+            num_values: copied.columns.len(),
+            line: Vec::new(),
+        })
+    }
+}
+
+fn trim_newline(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\n").unwrap_or(line)
+}
+
+/// Parse the table and columns out of a `COPY` statement written by `pg_dump`, starting after
+/// `COPY`
+fn parse_copy(statement: &[u8]) -> Option<(Relation, Vec<String>)> {
+    let mut input = Input {
+        rest: std::str::from_utf8(statement).ok()?,
+    };
+    let schema = input.identifier()?;
+    if !input.consume(".") {
+        return None;
+    }
+    let name = input.identifier()?;
+
+    let mut columns = Vec::new();
+    if input.consume("(") {
+        loop {
+            columns.push(input.identifier()?);
+            if !input.consume(",") {
+                break;
+            }
+        }
+        if !input.consume(")") {
+            return None;
+        }
+    }
+    if !input.consume("FROM") || !input.consume("stdin") {
+        return None;
+    }
+
+    Some((
+        Relation {
+            schema: Some(schema.into()),
+            name: name.into(),
+        },
+        columns,
+    ))
+}
+
+/// The remainder of a `COPY` statement, parsed as it's needed
+struct Input<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Input<'a> {
+    /// Consume `token` if it's next, returning whether it was
+    fn consume(&mut self, token: &str) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Parse an identifier, which is double quoted if it isn't all lowercase letters, digits and
+    /// underscores
+    fn identifier(&mut self) -> Option<String> {
+        self.rest = self.rest.trim_start();
+        if let Some(rest) = self.rest.strip_prefix('"') {
+            let mut ident = String::new();
+            let mut chars = rest.char_indices();
+            while let Some((i, c)) = chars.next() {
+                if c != '"' {
+                    ident.push(c);
+                } else if rest[i + 1..].starts_with('"') {
+                    ident.push('"');
+                    chars.next();
+                } else {
+                    self.rest = &rest[i + 1..];
+                    return Some(ident);
+                }
+            }
+            None
+        } else {
+            let len = self
+                .rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(self.rest.len());
+            let (ident, rest) = self.rest.split_at(len);
+            self.rest = rest;
+            (!ident.is_empty()).then(|| ident.to_owned())
+        }
+    }
+}
+
+/// The rows of a single table in a dump file
+pub(crate) struct DumpRows {
+    path: PathBuf,
+    reader: BufReader<Take<File>>,
+    columns: Vec<(String, DfType)>,
+    /// The position of the value for each of `columns` in the dumped rows
+    positions: Vec<usize>,
+    /// The number of values in each dumped row
+    num_values: usize,
+    line: Vec<u8>,
+}
+
+impl DumpRows {
+    /// Returns the next row of the table, or `None` once every row has been read
+    pub(crate) async fn next(&mut self) -> ReadySetResult<Option<Vec<DfValue>>> {
+        self.line.clear();
+        let len = self
+            .reader
+            .read_until(b'\n', &mut self.line)
+            .await
+            .map_err(|e| dump_file_error(&self.path, e))?;
+        if len == 0 {
+            return Ok(None);
+        }
+        parse_row(
+            trim_newline(&self.line),
+            &self.columns,
+            &self.positions,
+            self.num_values,
+        )
+        .map(Some)
+        .map_err(|e| dump_file_error(&self.path, e))
+    }
+}
+
+/// Parse a row in `COPY`'s text format, returning values for each of `columns` coerced to the
+/// column's type, taken from the given `positions` in the row
+fn parse_row(
+    row: &[u8],
+    columns: &[(String, DfType)],
+    positions: &[usize],
+    num_values: usize,
+) -> Result<Vec<DfValue>, String> {
+    let values = row.split(|c| *c == b'\t').collect::<Vec<_>>();
+    // A different number of values means the table was altered since the dump was taken
+    if values.len() != num_values {
+        return Err(format!(
+            "Expected {num_values} values in each row, but found {}",
+            values.len()
+        ));
+    }
+
+    positions
+        .iter()
+        .zip(columns)
+        .map(|(&position, (column, ty))| {
+            let value = values[position];
+            if value == b"\\N" {
+                return Ok(DfValue::None);
+            }
+            coerce(unescape(value), ty)
+                .map_err(|e| format!("Invalid value for column {column}: {e}"))
+        })
+        .collect()
+}
+
+/// Undo the backslash escapes `COPY` writes values in text format with
+fn unescape(value: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(value.len());
+    let mut bytes = value.iter().copied().peekable();
+    while let Some(c) = bytes.next() {
+        if c != b'\\' {
+            unescaped.push(c);
+            continue;
+        }
+        let Some(escaped) = bytes.next() else {
+            unescaped.push(c);
+            break;
+        };
+        let digit = |c: u8, radix: u32| (c as char).to_digit(radix).map(|d| d as u8);
+        match escaped {
+            b'b' => unescaped.push(0x08),
+            b'f' => unescaped.push(0x0c),
+            b'n' => unescaped.push(b'\n'),
+            b'r' => unescaped.push(b'\r'),
+            b't' => unescaped.push(b'\t'),
+            b'v' => unescaped.push(0x0b),
+            b'0'..=b'7' => {
+                let mut byte = digit(escaped, 8).unwrap_or_default();
+                for _ in 0..2 {
+                    match bytes.peek().and_then(|c| digit(*c, 8)) {
+                        Some(d) => {
+                            byte = byte.wrapping_mul(8).wrapping_add(d);
+                            bytes.next();
+                        }
+                        None => break,
+                    }
+                }
+                unescaped.push(byte);
+            }
+            b'x' if bytes.peek().and_then(|c| digit(*c, 16)).is_some() => {
+                let mut byte = 0;
+                for _ in 0..2 {
+                    match bytes.peek().and_then(|c| digit(*c, 16)) {
+                        Some(d) => {
+                            byte = byte * 16 + d;
+                            bytes.next();
+                        }
+                        None => break,
+                    }
+                }
+                unescaped.push(byte);
+            }
+            other => unescaped.push(other),
+        }
+    }
+    unescaped
+}
+
+/// Coerce the text of a dumped value to `ty`
+fn coerce(text: Vec<u8>, ty: &DfType) -> Result<DfValue, String> {
+    let text = String::from_utf8(text).map_err(|e| e.to_string())?;
+    match ty {
+        // Booleans are dumped as `t` and `f`, which aren't otherwise read as booleans
+        DfType::Bool => match text.as_str() {
+            "t" => Ok(DfValue::from(true)),
+            "f" => Ok(DfValue::from(false)),
+            _ => Err(format!("Invalid boolean {text}")),
+        },
+        // `bytea` values are dumped in hex format, as `\x` followed by the bytes
+        DfType::Blob => {
+            let hex = text
+                .strip_prefix("\\x")
+                .ok_or("Binary values must be dumped in hex format")?;
+            Ok(DfValue::ByteArray(
+                hex::decode(hex).map_err(|e| e.to_string())?.into(),
+            ))
+        }
+        _ => {
+            let mut value = DfValue::from(text)
+                .coerce_to(ty, &DfType::Unknown)
+                .map_err(|e| e.to_string())?;
+            value
+                .maybe_coerce_for_table_op(ty)
+                .map_err(|e| e.to_string())?;
+            Ok(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    const DUMP: &str = r#"--
+-- PostgreSQL database dump
+--
+
+CREATE TABLE public.t1 (
+    id integer NOT NULL,
+    name text,
+    active boolean
+);
+
+CREATE TABLE "Other"."T2" (
+    data bytea,
+    id integer
+);
+
+COPY public.t1 (id, name, active) FROM stdin;
+1	one	t
+2	tab\there\\	f
+3	\N	\N
+\.
+
+
+COPY "Other"."T2" (data, id) FROM stdin;
+\\x0102ff	1
+\.
+
+
+COPY public.empty (id) FROM stdin;
+\.
+
+
+--
+-- PostgreSQL database dump complete
+--
+"#;
+
+    fn write_dump(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    fn relation(schema: &str, name: &str) -> Relation {
+        Relation {
+            schema: Some(schema.into()),
+            name: name.into(),
+        }
+    }
+
+    async fn read_all(rows: &mut DumpRows) -> Vec<Vec<DfValue>> {
+        let mut all = vec![];
+        while let Some(row) = rows.next().await.unwrap() {
+            all.push(row);
+        }
+        all
+    }
+
+    #[tokio::test]
+    async fn reads_copied_rows() {
+        let file = write_dump(DUMP);
+        let dump = DumpFile::open(file.path().to_owned()).await.unwrap();
+        assert!(dump
+            .rows(&relation("public", "t3"), vec![("id".into(), DfType::Int)])
+            .await
+            .is_err());
+
+        let mut rows = dump
+            .rows(
+                &relation("public", "t1"),
+                vec![
+                    ("id".into(), DfType::Int),
+                    ("name".into(), DfType::DEFAULT_TEXT),
+                    ("active".into(), DfType::Bool),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            read_all(&mut rows).await,
+            vec![
+                vec![1.into(), "one".into(), true.into()],
+                vec![2.into(), "tab\there\\".into(), false.into()],
+                vec![3.into(), DfValue::None, DfValue::None],
+            ]
+        );
+
+        // The table's columns are in a different order than the dump's
+        let mut rows = dump
+            .rows(
+                &relation("Other", "T2"),
+                vec![("id".into(), DfType::Int), ("data".into(), DfType::Blob)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            read_all(&mut rows).await,
+            vec![vec![1.into(), DfValue::ByteArray(vec![1, 2, 255].into())]]
+        );
+
+        let mut rows = dump
+            .rows(
+                &relation("public", "empty"),
+                vec![("id".into(), DfType::Int)],
+            )
+            .await
+            .unwrap();
+        assert!(read_all(&mut rows).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rows_with_wrong_number_of_values_are_rejected() {
+        let file = write_dump("COPY public.t (a, b) FROM stdin;\n1\t2\t3\n\\.\n");
+        let dump = DumpFile::open(file.path().to_owned()).await.unwrap();
+        let mut rows = dump
+            .rows(
+                &relation("public", "t"),
+                vec![("a".into(), DfType::Int), ("b".into(), DfType::Int)],
+            )
+            .await
+            .unwrap();
+        let err = rows.next().await.unwrap_err().to_string();
+        assert!(err.contains("Expected 2 values"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn unterminated_rows_are_rejected() {
+        let file = write_dump("COPY public.t (a) FROM stdin;\n1\n");
+        assert!(DumpFile::open(file.path().to_owned()).await.is_err());
+    }
+
+    #[test]
+    fn unescapes_values() {
+        assert_eq!(unescape(br"a\tb\nc\\d"), b"a\tb\nc\\d");
+        assert_eq!(unescape(br"\101\x42\x4"), b"AB\x04");
+        assert_eq!(unescape(br"\q"), b"q");
+    }
+
+    #[test]
+    fn parses_quoted_identifiers() {
+        assert_eq!(
+            parse_copy(br#""my ""schema""".tbl ("a b", c) FROM stdin;"#),
+            Some((
+                relation("my \"schema\"", "tbl"),
+                vec!["a b".to_owned(), "c".to_owned()]
+            ))
+        );
+        assert_eq!(parse_copy(b"tbl (a) FROM stdin;"), None);
+    }
+}
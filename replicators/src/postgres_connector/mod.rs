@@ -1,6 +1,9 @@
+mod buffered_transaction;
 mod connector;
 mod ddl_replication;
+mod dump_file;
 mod lsn;
+mod privileges;
 mod snapshot;
 mod wal;
 mod wal_reader;
@@ -10,14 +13,33 @@ use std::fmt::{self, Display};
 pub use connector::{
     drop_publication, drop_readyset_schema, drop_replication_slot, PostgresWalConnector,
 };
+pub(crate) use connector::{monitor_publications, monitor_replication_slot, publication_tables};
+pub(crate) use dump_file::DumpFile;
+pub(crate) use privileges::check_privileges;
 use readyset_client::replication::ReplicationOffset;
+use readyset_errors::ReadySetError;
+pub(crate) use snapshot::read_table_at_current_position;
 pub use snapshot::PostgresReplicator;
+use tokio_postgres as pgsql;
 
 use self::lsn::Lsn;
 
 pub(crate) const REPLICATION_SLOT: &str = "readyset";
 pub(crate) const PUBLICATION_NAME: &str = "readyset";
 
+/// Convert an error returned by the Postgres client into a [`ReadySetError`], distinguishing lost
+/// connections and missing privileges (such as `REPLICATION`, or ownership of the tables in the
+/// publication) from other replication failures
+pub(crate) fn pg_error(error: pgsql::Error) -> ReadySetError {
+    if error.is_closed() {
+        ReadySetError::ReplicationConnectionLost(error.to_string())
+    } else if error.code() == Some(&pgsql::error::SqlState::INSUFFICIENT_PRIVILEGE) {
+        ReadySetError::ReplicationPermissionMissing(error.to_string())
+    } else {
+        error.into()
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Copy, Default)]
 pub struct PostgresPosition {
     /// Postgres Log Sequence Number
@@ -29,6 +51,7 @@ impl From<&PostgresPosition> for ReplicationOffset {
         ReplicationOffset {
             replication_log_name: String::new(),
             offset: value.lsn.0 as _,
+            gtid_set: None,
         }
     }
 }
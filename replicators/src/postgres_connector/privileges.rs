@@ -0,0 +1,89 @@
+use std::collections::BTreeSet;
+
+use readyset_errors::ReadySetResult;
+use tokio_postgres as pgsql;
+
+use super::pg_error;
+use crate::privileges::PrivilegeReport;
+use crate::table_filter::TableFilter;
+
+/// Check that the user `client` is connected as has each of the privileges listed in the docs for
+/// [`PostgresWalConnector`](super::PostgresWalConnector), and return an error listing the required
+/// privileges it's missing, if any.
+pub(crate) async fn check_privileges(
+    client: &pgsql::Client,
+    table_filter: &TableFilter,
+) -> ReadySetResult<()> {
+    let mut report = PrivilegeReport::default();
+
+    // On RDS, the `REPLICATION` attribute can't be granted directly, and is instead conferred by
+    // membership in `rds_replication`
+    let can_replicate: bool = client
+        .query_one(
+            "SELECT EXISTS (
+                 SELECT 1 FROM pg_roles
+                 WHERE pg_has_role(current_user, oid, 'member')
+                 AND (rolreplication OR rolsuper OR rolname = 'rds_replication')
+             )",
+            &[],
+        )
+        .await
+        .and_then(|row| row.try_get(0))
+        .map_err(pg_error)?;
+    if !can_replicate {
+        report.missing(
+            "REPLICATION",
+            "to create a replication slot and stream the WAL",
+        );
+    }
+
+    let can_create: bool = client
+        .query_one(
+            "SELECT has_database_privilege(current_database(), 'CREATE')",
+            &[],
+        )
+        .await
+        .and_then(|row| row.try_get(0))
+        .map_err(pg_error)?;
+    if !can_create {
+        report.missing(
+            "CREATE ON DATABASE",
+            "to create the publication and the schema used to replicate DDL",
+        );
+    }
+
+    let tables = client
+        .query(
+            "SELECT table_schema::text, table_name::text,
+                 has_schema_privilege(quote_ident(table_schema), 'USAGE')
+                 AND has_table_privilege(
+                     quote_ident(table_schema) || '.' || quote_ident(table_name),
+                     'SELECT'
+                 )
+             FROM information_schema.tables
+             WHERE table_type = 'BASE TABLE'
+             AND table_schema NOT IN ('pg_catalog', 'information_schema')",
+            &[],
+        )
+        .await
+        .map_err(pg_error)?;
+    let mut missing_select = BTreeSet::new();
+    for row in tables {
+        let (schema, table, can_select): (String, String, bool) = (
+            row.try_get(0).map_err(pg_error)?,
+            row.try_get(1).map_err(pg_error)?,
+            row.try_get(2).map_err(pg_error)?,
+        );
+        if !can_select && table_filter.should_be_processed(schema.as_str(), table.as_str()) {
+            missing_select.insert(schema);
+        }
+    }
+    for schema in missing_select {
+        report.missing(
+            format!("USAGE and SELECT ON ALL TABLES IN SCHEMA {schema}"),
+            "to snapshot tables",
+        );
+    }
+
+    report.finish()
+}
@@ -1,34 +1,41 @@
+use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 use std::error::Error;
 use std::fmt::{self, Display};
 use std::future;
+use std::sync::Arc;
 use std::time::Instant;
 
-use futures::future::join_all;
-use futures::{pin_mut, StreamExt, TryFutureExt};
+use futures::{pin_mut, stream, StreamExt, TryFutureExt};
 use itertools::Itertools;
 use metrics::register_gauge;
 use nom_sql::{
     parse_key_specification_string, parse_sql_type, Column, ColumnConstraint, ColumnSpecification,
     CreateTableBody, CreateTableStatement, Dialect, Relation, SqlIdentifier, TableKey,
 };
-use postgres_types::{accepts, FromSql, Kind, Type};
+use postgres_types::{accepts, FromSql, Kind, ToSql, Type};
 use readyset_client::metrics::recorded;
 use readyset_client::recipe::changelist::{Change, ChangeList};
 use readyset_client::replication::ReplicationOffset;
 use readyset_client::TableOperation;
 use readyset_data::{DfType, DfValue, Dialect as DataDialect, PgEnumMetadata};
 use readyset_errors::{internal, internal_err, unsupported, ReadySetError, ReadySetResult};
+use tokio::sync::oneshot;
 use tokio_postgres as pgsql;
 use tracing::{debug, info, info_span, trace, warn, Instrument};
 
 use super::connector::CreatedSlot;
-use super::PostgresPosition;
-use crate::db_util::CreateSchema;
+use super::{DumpFile, PostgresPosition};
+use crate::db_util::{table_column_types, CreateSchema};
+use crate::noria_adapter::{set_source_schema_replication_offset, source_replication_offsets};
+use crate::snapshot_checkpoint::{SnapshotCheckpoint, SnapshotCheckpoints};
+use crate::snapshot_throttle::SnapshotThrottle;
 use crate::table_filter::TableFilter;
 
 const BATCH_SIZE: usize = 1024; // How many queries to buffer before pushing to ReadySet
 
+const MAX_CHUNK_ATTEMPTS: usize = 3; // How many times to try reading each chunk of a table
+
 macro_rules! get_transaction {
     ($self:expr) => {
         $self
@@ -46,6 +53,22 @@ pub struct PostgresReplicator<'a> {
     pub(crate) noria: &'a mut readyset_client::ReadySetHandle,
     /// Filters out tables we are not interested in
     pub(crate) table_filter: TableFilter,
+    /// The name of the additional upstream database we're snapshotting, if any
+    pub(crate) source: Option<String>,
+    /// The maximum number of tables to snapshot at the same time, or every table at once if not
+    /// set
+    pub(crate) snapshot_parallelism: Option<usize>,
+    /// If set, tables with a primary key are read in chunks of this many rows
+    pub(crate) snapshot_chunk_size: Option<u64>,
+    /// Where to persist the progress of tables read in chunks, if anywhere
+    pub(crate) checkpoints: Option<SnapshotCheckpoints>,
+    /// Limits the rate at which rows are snapshotted
+    pub(crate) throttle: SnapshotThrottle,
+    /// Tables requested to be resnapshotted, which are truncated and snapshotted again even
+    /// though they already have a replication offset
+    pub(crate) resnapshot_tables: HashSet<Relation>,
+    /// A dump file to snapshot every table from, instead of the upstream database
+    pub(crate) dump: Option<Arc<DumpFile>>,
 }
 
 #[derive(Debug)]
@@ -83,6 +106,8 @@ struct ColumnEntry {
     not_null: bool,
     /// The [`Type`] of this column
     pg_type: Type,
+    /// The name of the column's collation, if it's not the default collation for its type
+    collation: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -171,6 +196,7 @@ impl TryFrom<pgsql::Row> for ColumnEntry {
             not_null: row.try_get(1 /* pg_attribute.attnotnull */)?,
             sql_type: row.try_get(3)?,
             pg_type,
+            collation: row.try_get(13 /* pg_collation.collname */)?,
         })
     }
 }
@@ -179,11 +205,21 @@ impl Display for ColumnEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{} {}{}",
+            "{} {}",
             Dialect::PostgreSQL.quote_identifier(&self.name),
             self.sql_type,
-            if self.not_null { " NOT NULL" } else { "" },
-        )
+        )?;
+        if let Some(collation) = &self.collation {
+            write!(
+                f,
+                " COLLATE {}",
+                Dialect::PostgreSQL.quote_identifier(collation)
+            )?;
+        }
+        if self.not_null {
+            write!(f, " NOT NULL")?;
+        }
+        Ok(())
     }
 }
 
@@ -302,7 +338,10 @@ impl TableEntry {
                 member_tn.nspname,
                 (SELECT array_agg(e.enumlabel ORDER BY e.enumsortorder ASC)
                  FROM pg_enum e
-                 WHERE (member_t.oid IS NULL AND (e.enumtypid = t.oid)) OR e.enumtypid = member_t.oid)
+                 WHERE (member_t.oid IS NULL AND (e.enumtypid = t.oid)) OR e.enumtypid = member_t.oid),
+                (SELECT co.collname
+                 FROM pg_catalog.pg_collation co
+                 WHERE co.oid = a.attcollation AND a.attcollation <> t.typcollation)
             FROM pg_catalog.pg_attribute a
             JOIN pg_catalog.pg_type t ON a.atttypid = t.oid
             JOIN pg_catalog.pg_namespace tn ON t.typnamespace = tn.oid
@@ -412,6 +451,109 @@ impl Display for TableDescription {
     }
 }
 
+/// The queries used to read a table in fixed-size chunks, ordered by its primary key, so that
+/// each chunk picks up from where the previous one left off
+#[derive(Debug)]
+struct ChunkQueries {
+    /// The query for the first chunk
+    first: String,
+    /// The query for every subsequent chunk, taking the primary key of the last row of the
+    /// previous chunk as parameters, each as the text representation of the key column
+    next: String,
+}
+
+/// Start a read-only transaction on `client` that sees the snapshot named `snapshot_name`
+async fn snapshot_transaction<'a>(
+    client: &'a mut deadpool_postgres::Object,
+    snapshot_name: &str,
+) -> Result<deadpool_postgres::Transaction<'a>, pgsql::Error> {
+    let transaction = client
+        .build_transaction()
+        .deferrable(true)
+        .isolation_level(pgsql::IsolationLevel::RepeatableRead)
+        .read_only(true)
+        .start()
+        .await?;
+
+    // Ensure each table has a consistent view by using the same snapshot
+    let query = format!("SET TRANSACTION SNAPSHOT '{}'", snapshot_name);
+    transaction.query(query.as_str(), &[]).await?;
+    Ok(transaction)
+}
+
+/// Read every row of `table` as of the current WAL position, converting them to ReadySet values
+/// the same way as when the table is snapshotted, and passing each to `f`. Tables with a primary
+/// key are read in chunks of `chunk_size` rows.
+///
+/// The WAL position is sent on `position` as soon as it's known, before the table is read. Writes
+/// to the table are blocked with a `SHARE` lock just long enough to take a snapshot and read the
+/// position, so no writes to it can be made in between.
+pub(crate) async fn read_table_at_current_position(
+    pool: &deadpool_postgres::Pool,
+    table: &Relation,
+    chunk_size: u64,
+    position: oneshot::Sender<ReplicationOffset>,
+    mut f: impl FnMut(Vec<DfValue>) -> ReadySetResult<()>,
+) -> ReadySetResult<()> {
+    let schema = table
+        .schema
+        .as_ref()
+        .ok_or_else(|| internal_err!("All tables must have a schema in the replicator"))?;
+    let table_name = format!("\"{}\".\"{}\"", schema, table.name);
+
+    let mut lock_client = pool.get().await?;
+    let lock = lock_client.transaction().await?;
+    lock.batch_execute(&format!("LOCK TABLE {table_name} IN SHARE MODE"))
+        .await?;
+
+    let mut client = pool.get().await?;
+    let transaction = client
+        .build_transaction()
+        .isolation_level(pgsql::IsolationLevel::RepeatableRead)
+        .read_only(true)
+        .start()
+        .await?;
+    // The snapshot is taken by the first query in the transaction
+    let row = transaction
+        .query_one(
+            "SELECT (pg_current_wal_lsn() - '0/0')::bigint, pg_export_snapshot(), \
+             $1::text::regclass::oid",
+            &[&table_name],
+        )
+        .await?;
+    lock.commit().await?;
+    let lsn: i64 = row.try_get(0)?;
+    let snapshot_name: String = row.try_get(1)?;
+    let _ = position.send(PostgresPosition::from(lsn).into());
+
+    let description = TableEntry {
+        schema: schema.to_string(),
+        name: table.name.to_string(),
+        oid: row.try_get(2)?,
+    }
+    .get_table(&transaction)
+    .await?;
+    match description.primary_key() {
+        Some(key) => {
+            let queries = description.chunk_queries(&key, chunk_size)?;
+            let mut after = None;
+            loop {
+                let (chunk, last_key) = description
+                    .read_chunk(pool, &snapshot_name, &queries, after.as_deref())
+                    .await?;
+                chunk.into_iter().try_for_each(&mut f)?;
+                if last_key.is_none() {
+                    break;
+                }
+                after = last_key;
+            }
+        }
+        None => description.for_each_copied_row(&transaction, f).await?,
+    }
+    transaction.commit().await?;
+    Ok(())
+}
+
 impl TableDescription {
     fn schema(&self) -> ReadySetResult<&SqlIdentifier> {
         self.name
@@ -420,6 +562,258 @@ impl TableDescription {
             .ok_or_else(|| internal_err!("All tables must have a schema in the replicator"))
     }
 
+    /// Returns the indices of the columns of the table's primary key, if it has one
+    fn primary_key(&self) -> Option<Vec<usize>> {
+        self.constraints.iter().find_map(|c| match &c.definition {
+            TableKey::PrimaryKey { columns, .. } => columns
+                .iter()
+                .map(|key_column| {
+                    self.columns
+                        .iter()
+                        .position(|column| column.name == key_column.name.as_str())
+                })
+                .collect(),
+            _ => None,
+        })
+    }
+
+    /// Build the queries to read the table in chunks of `chunk_size` rows, ordered by the columns
+    /// at the indices in `key`.
+    ///
+    /// Each row read has the table's columns followed by the text representation of each of the
+    /// key columns. Keys are passed back to postgres as text and cast to the column's type, since
+    /// the text representation always round-trips, unlike converting to and from [`DfValue`].
+    fn chunk_queries(&self, key: &[usize], chunk_size: u64) -> ReadySetResult<ChunkQueries> {
+        let table = format!("\"{}\".\"{}\"", self.schema()?, self.name.name);
+        let select_list = self
+            .columns
+            .iter()
+            .map(|c| format!("\"{}\"", c.name))
+            .chain(
+                key.iter()
+                    .map(|idx| format!("\"{}\"::text", self.columns[*idx].name)),
+            )
+            .join(", ");
+        let key_list = key
+            .iter()
+            .map(|idx| format!("\"{}\"", self.columns[*idx].name))
+            .join(", ");
+        let placeholders = key
+            .iter()
+            .enumerate()
+            .map(|(i, idx)| format!("${}::text::{}", i + 1, self.columns[*idx].sql_type))
+            .join(", ");
+
+        Ok(ChunkQueries {
+            first: format!(
+                "SELECT {select_list} FROM {table} ORDER BY {key_list} LIMIT {chunk_size}"
+            ),
+            next: format!(
+                "SELECT {select_list} FROM {table} WHERE ({key_list}) > ({placeholders}) \
+                 ORDER BY {key_list} LIMIT {chunk_size}"
+            ),
+        })
+    }
+
+    /// Read the chunk of rows following the row with the primary key `after`, or the first chunk
+    /// if `after` is `None`, in a new transaction that sees the snapshot named `snapshot_name`.
+    /// Returns the rows along with the primary key of the last one, as text, or an empty chunk once
+    /// all the rows have been read.
+    async fn read_chunk(
+        &self,
+        pool: &deadpool_postgres::Pool,
+        snapshot_name: &str,
+        queries: &ChunkQueries,
+        after: Option<&[DfValue]>,
+    ) -> ReadySetResult<(Vec<Vec<DfValue>>, Option<Vec<DfValue>>)> {
+        let mut client = pool.get().await?;
+        let transaction = snapshot_transaction(&mut client, snapshot_name).await?;
+        let rows = match after {
+            None => transaction.query(queries.first.as_str(), &[]).await?,
+            Some(key) => {
+                let key = key.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                let params = key
+                    .iter()
+                    .map(|v| v as &(dyn ToSql + Sync))
+                    .collect::<Vec<_>>();
+                transaction.query(queries.next.as_str(), &params).await?
+            }
+        };
+        transaction.commit().await?;
+
+        let last_key = rows
+            .last()
+            .map(|row| {
+                (self.columns.len()..row.len())
+                    .map(|i| row.try_get::<_, String>(i).map(DfValue::from))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let rows = rows
+            .into_iter()
+            .map(|row| {
+                (0..self.columns.len())
+                    .map(|i| row.try_get::<_, DfValue>(i))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| {
+                        ReadySetError::ReplicationFailed(format!(
+                            "Failed converting to DfValue, table: {}, err: {}",
+                            self.name.display(Dialect::PostgreSQL),
+                            err
+                        ))
+                    })
+            })
+            .collect::<ReadySetResult<_>>()?;
+        Ok((rows, last_key))
+    }
+
+    /// Copy a table's contents from PostgreSQL to ReadySet in chunks of `chunk_size` rows, ordered
+    /// by the primary key columns at the indices in `key`, starting after the last key of
+    /// `checkpoint`. Each chunk is read in its own transaction on the snapshot named
+    /// `snapshot_name`, so a chunk that fails to be read is retried, up to [`MAX_CHUNK_ATTEMPTS`]
+    /// times, on a fresh connection.
+    ///
+    /// After each chunk is written, `checkpoint` is updated and persisted to `checkpoints`, if set.
+    /// Once every chunk has been written, the table's replication offset is set to the offset of
+    /// `checkpoint`.
+    #[allow(clippy::too_many_arguments)]
+    async fn dump_chunked(
+        &self,
+        pool: &deadpool_postgres::Pool,
+        snapshot_name: &str,
+        key: Vec<usize>,
+        chunk_size: u64,
+        mut noria_table: readyset_client::Table,
+        snapshot_report_interval_secs: u16,
+        mut checkpoint: SnapshotCheckpoint,
+        checkpoints: Option<SnapshotCheckpoints>,
+        throttle: &SnapshotThrottle,
+    ) -> ReadySetResult<()> {
+        let queries = self.chunk_queries(&key, chunk_size)?;
+        let nrows = {
+            let mut client = pool.get().await?;
+            let transaction = snapshot_transaction(&mut client, snapshot_name).await?;
+            transaction
+                .query_one(
+                    format!(
+                        "SELECT count(*) AS nrows FROM \"{}\".\"{}\"",
+                        self.schema()?,
+                        &self.name.name,
+                    )
+                    .as_str(),
+                    &[],
+                )
+                .await?
+                .try_get::<_, i64>("nrows")?
+        };
+
+        if checkpoint.last_key.is_empty() {
+            info!(rows = %nrows, "Snapshotting started");
+        } else {
+            info!(
+                rows = %nrows,
+                rows_replicated = %checkpoint.rows,
+                chunks = %checkpoint.chunks,
+                "Snapshotting resumed from checkpoint"
+            );
+        }
+        let progress_percentage_metric: metrics::Gauge = register_gauge!(
+            recorded::REPLICATOR_SNAPSHOT_PERCENT,
+            "schema" => self.schema()?.to_string(),
+            "name" => self.name.name.to_string()
+        );
+        let start_time = Instant::now();
+        let mut last_report_time = start_time;
+        let snapshot_report_interval_secs = snapshot_report_interval_secs as u64;
+        let start_rows = checkpoint.rows;
+
+        loop {
+            let after = (!checkpoint.last_key.is_empty()).then_some(&checkpoint.last_key[..]);
+            let mut attempt = 1;
+            let chunk = loop {
+                match self.read_chunk(pool, snapshot_name, &queries, after).await {
+                    Ok(chunk) => break chunk,
+                    Err(error) if attempt < MAX_CHUNK_ATTEMPTS => {
+                        warn!(%error, chunk = checkpoint.chunks, attempt, "Reading chunk failed, retrying");
+                        attempt += 1;
+                    }
+                    Err(error) => {
+                        progress_percentage_metric.set(0.0);
+                        return Err(error);
+                    }
+                }
+            };
+
+            let (chunk, Some(last_key)) = chunk else {
+                break;
+            };
+            let chunk_rows = chunk.len();
+
+            for batch in &chunk.into_iter().chunks(BATCH_SIZE) {
+                let batch = batch.collect::<Vec<_>>();
+                throttle.acquire(&batch).await;
+                noria_table.insert_many(batch).await.map_err(|err| {
+                    progress_percentage_metric.set(0.0);
+                    err
+                })?;
+            }
+
+            checkpoint.last_key = last_key;
+            checkpoint.rows += chunk_rows;
+            checkpoint.chunks += 1;
+            if let Some(checkpoints) = &checkpoints {
+                if let Err(error) = checkpoints.save(&self.name, &checkpoint).await {
+                    warn!(%error, "Could not save snapshot checkpoint");
+                }
+            }
+
+            let cnt = checkpoint.rows;
+            let progress_percent = (cnt as f64 / nrows as f64) * 100.;
+            progress_percentage_metric.set(progress_percent);
+            debug!(chunk = checkpoint.chunks, rows_replicated = %cnt, "Snapshotted chunk");
+
+            if snapshot_report_interval_secs != 0
+                && last_report_time.elapsed().as_secs() > snapshot_report_interval_secs
+            {
+                last_report_time = Instant::now();
+                let estimate = crate::estimate_remaining_time(
+                    start_time.elapsed(),
+                    (cnt - start_rows) as f64,
+                    (nrows as f64 - start_rows as f64).max(0.0),
+                );
+                let progress = format!("{:.2}%", progress_percent);
+                info!(rows_replicated = %cnt, chunks = %checkpoint.chunks, %progress, %estimate, "Snapshotting progress");
+            }
+        }
+
+        let wal_position = &checkpoint.offset;
+        let span = info_span!(
+            "Setting replication offset and compacting table",
+            table = %noria_table.table_name().display(Dialect::PostgreSQL),
+            %wal_position
+        );
+        noria_table
+            .perform_all([
+                TableOperation::SetReplicationOffset(wal_position.clone()),
+                TableOperation::SetSnapshotMode(false),
+            ])
+            .await?;
+        noria_table.flush_operations().await?;
+        span.in_scope(|| info!("Compacting finished"));
+
+        // The table no longer needs to be resumed
+        if let Some(checkpoints) = &checkpoints {
+            if let Err(error) = checkpoints.remove(&self.name).await {
+                warn!(%error, "Could not remove snapshot checkpoint");
+            }
+        }
+
+        info!(rows_replicated = %checkpoint.rows, chunks = %checkpoint.chunks, "Snapshotting finished");
+        progress_percentage_metric.set(100.0);
+
+        Ok(())
+    }
+
     fn try_into_change(self) -> ReadySetResult<Change> {
         Ok(Change::CreateTable(CreateTableStatement {
             if_not_exists: false,
@@ -436,11 +830,12 @@ impl TableDescription {
                             },
                             sql_type: parse_sql_type(Dialect::PostgreSQL, c.sql_type)
                                 .map_err(|e| internal_err!("Could not parse SQL type: {e}"))?,
-                            constraints: if c.not_null {
-                                vec![ColumnConstraint::NotNull]
-                            } else {
-                                vec![]
-                            },
+                            constraints: c
+                                .not_null
+                                .then_some(ColumnConstraint::NotNull)
+                                .into_iter()
+                                .chain(c.collation.map(ColumnConstraint::Collation))
+                                .collect(),
                             comment: None,
                         })
                     })
@@ -455,6 +850,39 @@ impl TableDescription {
         }))
     }
 
+    /// Call `f` with every row of the table, read with `COPY BINARY` in `transaction` and
+    /// converted the same way as by [`dump`](Self::dump)
+    async fn for_each_copied_row(
+        &self,
+        transaction: &pgsql::Transaction<'_>,
+        mut f: impl FnMut(Vec<DfValue>) -> ReadySetResult<()>,
+    ) -> ReadySetResult<()> {
+        let query = format!(
+            "COPY \"{}\".\"{}\" TO stdout BINARY",
+            self.schema()?,
+            self.name.name
+        );
+        let rows = transaction.copy_out(query.as_str()).await?;
+
+        let type_map: Vec<_> = self.columns.iter().map(|c| c.pg_type.clone()).collect();
+        let rows = pgsql::binary_copy::BinaryCopyOutStream::new(rows, &type_map);
+        pin_mut!(rows);
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            f((0..type_map.len())
+                .map(|i| row.try_get::<DfValue>(i))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| {
+                    ReadySetError::ReplicationFailed(format!(
+                        "Failed converting to DfValue, table: {}, err: {}",
+                        self.name.display(Dialect::PostgreSQL),
+                        err
+                    ))
+                })?)?;
+        }
+        Ok(())
+    }
+
     /// Copy a table's contents from PostgreSQL to ReadySet
     async fn dump<'a>(
         &self,
@@ -462,6 +890,7 @@ impl TableDescription {
         mut noria_table: readyset_client::Table,
         snapshot_report_interval_secs: u16,
         wal_position: &ReplicationOffset,
+        throttle: &SnapshotThrottle,
     ) -> ReadySetResult<()> {
         let mut cnt = 0;
 
@@ -529,6 +958,9 @@ impl TableDescription {
 
             cnt += batch_size;
 
+            let noria_rows = noria_rows_iter.collect::<Result<Vec<_>, _>>()?;
+            throttle.acquire(&noria_rows).await;
+
             if binary_row_batches.as_mut().peek().await.is_none() {
                 // This is the last batch of rows we're adding to the table, so batch the RPCs to
                 // set the replication offset and compact the table along with the insertion
@@ -538,14 +970,8 @@ impl TableDescription {
                     %wal_position
                 );
 
-                let capacity = match noria_rows_iter.size_hint() {
-                    (_, Some(high)) => high,
-                    (low, None) => low,
-                } + 2;
-                let mut actions = Vec::with_capacity(capacity);
-                for row in noria_rows_iter {
-                    actions.push(TableOperation::Insert(row?));
-                }
+                let mut actions = Vec::with_capacity(noria_rows.len() + 2);
+                actions.extend(noria_rows.into_iter().map(TableOperation::Insert));
 
                 actions.push(TableOperation::SetReplicationOffset(wal_position.clone()));
                 actions.push(TableOperation::SetSnapshotMode(false));
@@ -554,13 +980,10 @@ impl TableDescription {
                 set_replication_offset_and_snapshot_mode = true;
                 span.in_scope(|| info!("Compacting finished"));
             } else {
-                noria_table
-                    .insert_many(noria_rows_iter.collect::<Result<Vec<_>, _>>()?)
-                    .await
-                    .map_err(|err| {
-                        progress_percentage_metric.set(0.0);
-                        err
-                    })?;
+                noria_table.insert_many(noria_rows).await.map_err(|err| {
+                    progress_percentage_metric.set(0.0);
+                    err
+                })?;
             }
 
             if snapshot_report_interval_secs != 0
@@ -586,6 +1009,74 @@ impl TableDescription {
                 ])
                 .await?;
         }
+        noria_table.flush_operations().await?;
+
+        info!(rows_replicated = %cnt, "Snapshotting finished");
+        progress_percentage_metric.set(100.0);
+
+        Ok(())
+    }
+
+    /// Copy the table's rows from `dump` instead of the upstream database. The dump was taken
+    /// from the snapshot exported by the replication slot, so the rows are as of `wal_position`,
+    /// where the slot was created.
+    async fn dump_from_file(
+        &self,
+        dump: &DumpFile,
+        mut noria_table: readyset_client::Table,
+        snapshot_report_interval_secs: u16,
+        wal_position: &ReplicationOffset,
+        throttle: &SnapshotThrottle,
+    ) -> ReadySetResult<()> {
+        let columns = table_column_types(&noria_table, DataDialect::DEFAULT_POSTGRESQL);
+        let mut rows = dump.rows(&self.name, columns).await?;
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        let mut cnt = 0;
+
+        info!("Snapshotting from dump file started");
+        let progress_percentage_metric: metrics::Gauge = register_gauge!(
+            recorded::REPLICATOR_SNAPSHOT_PERCENT,
+            "schema" => self.schema()?.to_string(),
+            "name" => self.name.name.to_string()
+        );
+        let mut last_report_time = Instant::now();
+        let snapshot_report_interval_secs = snapshot_report_interval_secs as u64;
+
+        while let Some(row) = rows.next().await.map_err(|err| {
+            progress_percentage_metric.set(0.0);
+            err
+        })? {
+            batch.push(row);
+            cnt += 1;
+
+            if batch.len() == BATCH_SIZE {
+                let noria_rows = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                throttle.acquire(&noria_rows).await;
+                noria_table.insert_many(noria_rows).await.map_err(|err| {
+                    progress_percentage_metric.set(0.0);
+                    err
+                })?;
+            }
+
+            // The number of rows in the dump isn't known up front, so only the rows snapshotted so
+            // far are reported
+            if snapshot_report_interval_secs != 0
+                && last_report_time.elapsed().as_secs() > snapshot_report_interval_secs
+            {
+                last_report_time = Instant::now();
+                info!(rows_replicated = %cnt, "Snapshotting progress");
+            }
+        }
+
+        // Batch the RPCs to set the replication offset and compact the table along with the last
+        // rows
+        throttle.acquire(&batch).await;
+        let mut actions = Vec::with_capacity(batch.len() + 2);
+        actions.extend(batch.into_iter().map(TableOperation::Insert));
+        actions.push(TableOperation::SetReplicationOffset(wal_position.clone()));
+        actions.push(TableOperation::SetSnapshotMode(false));
+        noria_table.perform_all(actions).await?;
+        noria_table.flush_operations().await?;
 
         info!(rows_replicated = %cnt, "Snapshotting finished");
         progress_percentage_metric.set(100.0);
@@ -595,11 +1086,19 @@ impl TableDescription {
 }
 
 impl<'a> PostgresReplicator<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn new(
         client: &'a mut pgsql::Client,
         pool: deadpool_postgres::Pool,
         noria: &'a mut readyset_client::ReadySetHandle,
         table_filter: TableFilter,
+        source: Option<String>,
+        snapshot_parallelism: Option<usize>,
+        snapshot_chunk_size: Option<u64>,
+        checkpoints: Option<SnapshotCheckpoints>,
+        throttle: SnapshotThrottle,
+        resnapshot_tables: HashSet<Relation>,
+        dump: Option<Arc<DumpFile>>,
     ) -> ReadySetResult<PostgresReplicator<'a>> {
         let transaction = Some(
             client
@@ -615,45 +1114,98 @@ impl<'a> PostgresReplicator<'a> {
             pool,
             noria,
             table_filter,
+            source,
+            snapshot_parallelism,
+            snapshot_chunk_size,
+            checkpoints,
+            throttle,
+            resnapshot_tables,
+            dump,
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn snapshot_table(
         pool: deadpool_postgres::Pool,
         span: tracing::Span,
         table: &TableDescription,
         noria_table: readyset_client::Table,
         snapshot_report_interval_secs: u16,
+        snapshot_chunk_size: Option<u64>,
+        checkpoints: Option<SnapshotCheckpoints>,
+        throttle: SnapshotThrottle,
         snapshot_name: String,
         wal_position: &ReplicationOffset,
+        dump: Option<Arc<DumpFile>>,
     ) -> ReadySetResult<()> {
-        let mut client = pool.get().await?;
-
-        let transaction = client
-            .build_transaction()
-            .deferrable(true)
-            .isolation_level(pgsql::IsolationLevel::RepeatableRead)
-            .read_only(true)
-            .start()
-            .await?;
+        let result = match (dump, snapshot_chunk_size.zip(table.primary_key())) {
+            (Some(dump), _) => {
+                table
+                    .dump_from_file(
+                        &dump,
+                        noria_table,
+                        snapshot_report_interval_secs,
+                        wal_position,
+                        &throttle,
+                    )
+                    .instrument(span.clone())
+                    .await
+            }
+            (None, Some((chunk_size, key))) => {
+                // Resume from the table's checkpoint, if it has one, in which case the table's
+                // replication offset is the one its snapshot was originally started at
+                let resumed = match &checkpoints {
+                    Some(checkpoints) => {
+                        checkpoints.load(&table.name).await.unwrap_or_else(|error| {
+                            span.in_scope(|| warn!(%error, "Could not load snapshot checkpoint"));
+                            None
+                        })
+                    }
+                    None => None,
+                };
+                let checkpoint = resumed.unwrap_or_else(|| SnapshotCheckpoint {
+                    offset: wal_position.clone(),
+                    last_key: vec![],
+                    rows: 0,
+                    chunks: 0,
+                });
 
-        // Ensure each table has a consistent view by using the same snapshot
-        let query = format!("SET TRANSACTION SNAPSHOT '{}'", snapshot_name);
-        transaction.query(query.as_str(), &[]).await?;
+                table
+                    .dump_chunked(
+                        &pool,
+                        &snapshot_name,
+                        key,
+                        chunk_size,
+                        noria_table,
+                        snapshot_report_interval_secs,
+                        checkpoint,
+                        checkpoints,
+                        &throttle,
+                    )
+                    .instrument(span.clone())
+                    .await
+            }
+            (None, None) => {
+                let mut client = pool.get().await?;
+                let transaction = snapshot_transaction(&mut client, &snapshot_name).await?;
+
+                table
+                    .dump(
+                        &transaction,
+                        noria_table,
+                        snapshot_report_interval_secs,
+                        wal_position,
+                        &throttle,
+                    )
+                    .instrument(span.clone())
+                    .await
+            }
+        };
 
-        table
-            .dump(
-                &transaction,
-                noria_table,
-                snapshot_report_interval_secs,
-                wal_position,
-            )
-            .instrument(span.clone())
-            .await
-            .map_err(|e| ReadySetError::TableError {
-                table: table.name.clone(),
-                source: Box::new(e),
-            })
+        result.map_err(|e| ReadySetError::TableError {
+            table: table.name.clone(),
+            source: Box::new(e),
+        })
     }
 
     /// Snapshot the contents of the upstream database to ReadySet, starting with the DDL, followed
@@ -669,7 +1221,17 @@ impl<'a> PostgresReplicator<'a> {
         full_snapshot: bool,
     ) -> ReadySetResult<()> {
         let wal_position = PostgresPosition::from(replication_slot.consistent_point).into();
-        self.set_snapshot(&replication_slot.snapshot_name).await?;
+        // A slot whose tables are snapshotted from a dump has no snapshot of its own left to use
+        if self.dump.is_none() {
+            self.set_snapshot(&replication_slot.snapshot_name).await?;
+        }
+
+        // A full snapshot drops every table, so there's nothing left to resume
+        if full_snapshot {
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.clear(&self.table_filter).await?;
+            }
+        }
 
         let table_list = self.get_table_list(TableKind::RegularTable).await?;
         let view_list = self.get_table_list(TableKind::View).await?;
@@ -845,11 +1407,16 @@ impl<'a> PostgresReplicator<'a> {
             }
         }
 
-        let replication_offsets = self.noria.replication_offsets().await?;
+        let replication_offsets =
+            source_replication_offsets(self.noria, self.source.as_deref(), &self.table_filter)
+                .await?;
 
         let requires_catch_up = if !full_snapshot {
             tables
-                .drain_filter(|t| replication_offsets.has_table(&t.name))
+                .drain_filter(|t| {
+                    replication_offsets.has_table(&t.name)
+                        && !self.resnapshot_tables.contains(&t.name)
+                })
                 .for_each(|t| {
                     debug!(
                         table = %t.name.display(Dialect::PostgreSQL),
@@ -873,9 +1440,12 @@ impl<'a> PostgresReplicator<'a> {
                 schema_offset = ?replication_offsets.schema,
                 "Setting schema replication offset"
             );
-            self.noria
-                .set_schema_replication_offset(Some(&wal_position))
-                .await?;
+            set_source_schema_replication_offset(
+                self.noria,
+                self.source.as_deref(),
+                Some(&wal_position),
+            )
+            .await?;
         } else {
             debug!("Skipping schema offset update, since no catch up is required");
         }
@@ -895,6 +1465,21 @@ impl<'a> PostgresReplicator<'a> {
                 .table(table.name.clone())
                 .instrument(span.clone())
                 .await?;
+
+            // Tables requested to be resnapshotted start over from scratch. Their operations are
+            // buffered and sent in one batch once every row has been read, so that readers keep
+            // seeing the table's old rows until they're replaced by the new ones, rather than an
+            // empty table, and so they aren't checkpointed either.
+            let resnapshot = self.resnapshot_tables.contains(&table.name);
+            if resnapshot {
+                span.in_scope(|| info!("Truncating table to resnapshot it"));
+                if let Some(checkpoints) = &self.checkpoints {
+                    checkpoints.remove(&table.name).await?;
+                }
+                noria_table.buffer_operations();
+                noria_table.truncate().instrument(span.clone()).await?;
+            }
+
             span.in_scope(|| trace!("Setting snapshot mode"));
             noria_table.set_snapshot_mode(true).await?;
             span.in_scope(|| trace!("Set snapshot mode"));
@@ -908,15 +1493,23 @@ impl<'a> PostgresReplicator<'a> {
                 table,
                 noria_table,
                 snapshot_report_interval_secs,
+                self.snapshot_chunk_size,
+                self.checkpoints.clone().filter(|_| !resnapshot),
+                self.throttle.clone(),
                 snapshot_name,
                 &wal_position,
+                self.dump.clone(),
             ))
         }
 
         // Remove from the set of tables any that failed to snapshot,
         // and add them as non-replicated relations.
         // Propagate any non-TableErrors.
-        for res in join_all(futs).await {
+        let results = stream::iter(futs)
+            .buffer_unordered(self.snapshot_parallelism.unwrap_or(usize::MAX).max(1))
+            .collect::<Vec<_>>()
+            .await;
+        for res in results {
             if let Err(e) = res {
                 match e {
                     ReadySetError::TableError { ref table, .. } => {
@@ -1113,24 +1706,28 @@ mod tests {
                     sql_type: "varchar".into(),
                     not_null: true,
                     pg_type: Type::VARCHAR,
+                    collation: None,
                 },
                 ColumnEntry {
                     name: "value".into(),
                     sql_type: "varchar".into(),
                     not_null: false,
                     pg_type: Type::VARCHAR,
+                    collation: Some("en_US.utf8".into()),
                 },
                 ColumnEntry {
                     name: "created_at".into(),
                     sql_type: "timestamp(6) without time zone".into(),
                     not_null: true,
                     pg_type: Type::TIMESTAMP,
+                    collation: None,
                 },
                 ColumnEntry {
                     name: "updated_at".into(),
                     sql_type: "timestamp(6) without time zone".into(),
                     not_null: true,
                     pg_type: Type::TIMESTAMP,
+                    collation: None,
                 },
             ],
             constraints: vec![ConstraintEntry {
@@ -1155,6 +1752,10 @@ mod tests {
 
         assert_eq!(create_table.table.name, "ar_internal_metadata");
         assert_eq!(create_table.body.as_ref().unwrap().fields.len(), 4);
+        assert_eq!(
+            create_table.body.as_ref().unwrap().fields[1].collation(),
+            Some("en_US.utf8")
+        );
         assert!(create_table.body.as_ref().unwrap().keys.is_some());
         assert_eq!(
             create_table
@@ -1190,4 +1791,52 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn chunk_queries_order_by_primary_key() {
+        let column = |name: &str| ColumnEntry {
+            name: name.into(),
+            sql_type: "int".into(),
+            not_null: true,
+            pg_type: Type::INT4,
+            collation: None,
+        };
+        let desc = TableDescription {
+            name: Relation {
+                schema: Some("public".into()),
+                name: "t".into(),
+            },
+            columns: vec![column("x"), column("a"), column("b")],
+            constraints: vec![ConstraintEntry {
+                name: "t_pkey".into(),
+                definition: TableKey::PrimaryKey {
+                    constraint_name: None,
+                    index_name: None,
+                    columns: vec![
+                        Column {
+                            name: "b".into(),
+                            table: None,
+                        },
+                        Column {
+                            name: "a".into(),
+                            table: None,
+                        },
+                    ],
+                },
+                kind: Some(ConstraintKind::PrimaryKey),
+            }],
+        };
+
+        let key = desc.primary_key().unwrap();
+        assert_eq!(key, vec![2, 1]);
+        let queries = desc.chunk_queries(&key, 100).unwrap();
+        assert_eq!(
+            queries.first,
+            r#"SELECT "x", "a", "b", "b"::text, "a"::text FROM "public"."t" ORDER BY "b", "a" LIMIT 100"#
+        );
+        assert_eq!(
+            queries.next,
+            r#"SELECT "x", "a", "b", "b"::text, "a"::text FROM "public"."t" WHERE ("b", "a") > ($1::text::int, $2::text::int) ORDER BY "b", "a" LIMIT 100"#
+        );
+    }
 }
@@ -6,7 +6,7 @@
 
 use std::convert::{TryFrom, TryInto};
 
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use nom_sql::Relation;
 use readyset_errors::ReadySetError;
 
@@ -29,6 +29,8 @@ pub enum WalError {
     CorruptDelete,
     CorruptTruncate,
     CorruptMessage,
+    CorruptStream,
+    CorruptPrepare,
     TryFromSliceError,
     ReadySetError(ReadySetError),
     ConnectionLost(String),
@@ -90,7 +92,9 @@ impl From<WalError> for ReadySetError {
                     ))),
                 }
             }
-            _ => ReadySetError::ReplicationFailed(format!("WAL error: {:?}", err)),
+            WalError::ReadySetError(err) => err,
+            WalError::ConnectionLost(msg) => ReadySetError::ReplicationConnectionLost(msg),
+            _ => ReadySetError::ReplicationUnsupportedEvent(format!("WAL error: {:?}", err)),
         }
     }
 }
@@ -118,6 +122,9 @@ pub enum WalData {
         /// records, it can be split at the page boundary. In other words, the first main
         /// WAL record and its continuation records can be sent in different XLogData messages.
         data: WalRecord,
+        /// The xid of the (sub)transaction `data` belongs to, if it's a data record of a streamed
+        /// transaction.
+        xid: Option<i32>,
     },
     /// Primary keepalive message
     Keepalive {
@@ -163,6 +170,90 @@ pub enum WalData {
         /// The epoch of the catalog_xmin xid on the standby.
         epoch_catalog_xmin: i32,
     },
+    /// Sent (with protocol version 3 and two-phase commit enabled) at the start of a transaction
+    /// that's prepared with `PREPARE TRANSACTION`, instead of `Begin`. The records up to the
+    /// matching `Prepare` are the transaction's changes, which mustn't be applied until the
+    /// transaction is committed with `CommitPrepared`.
+    BeginPrepare {
+        /// The LSN of the prepare.
+        prepare_lsn: Lsn,
+        /// The end LSN of the prepared transaction.
+        end_lsn: Lsn,
+        /// Prepare timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        timestamp: i64,
+        /// Xid of the transaction.
+        xid: i32,
+        /// The user defined GID of the prepared transaction.
+        gid: Bytes,
+    },
+    /// Sent at the end of the changes of a prepared transaction, instead of `Commit`
+    Prepare {
+        /// Flags; currently unused (must be 0).
+        flags: u8,
+        /// The LSN of the prepare.
+        prepare_lsn: Lsn,
+        /// The end LSN of the prepared transaction.
+        end_lsn: Lsn,
+        /// Prepare timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        timestamp: i64,
+        /// Xid of the transaction.
+        xid: i32,
+        /// The user defined GID of the prepared transaction.
+        gid: Bytes,
+    },
+    /// Sent when a prepared transaction is committed with `COMMIT PREPARED`
+    CommitPrepared {
+        /// Flags; currently unused (must be 0).
+        flags: u8,
+        /// The LSN of the commit of the prepared transaction.
+        lsn: Lsn,
+        /// The end LSN of the commit of the prepared transaction.
+        end_lsn: Lsn,
+        /// Commit timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        timestamp: i64,
+        /// Xid of the transaction.
+        xid: i32,
+        /// The user defined GID of the prepared transaction.
+        gid: Bytes,
+    },
+    /// Sent when a prepared transaction is rolled back with `ROLLBACK PREPARED`
+    RollbackPrepared {
+        /// Flags; currently unused (must be 0).
+        flags: u8,
+        /// The end LSN of the prepared transaction.
+        prepare_end_lsn: Lsn,
+        /// The end LSN of the rollback of the prepared transaction.
+        rollback_end_lsn: Lsn,
+        /// Prepare timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        prepare_timestamp: i64,
+        /// Rollback timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        rollback_timestamp: i64,
+        /// Xid of the transaction.
+        xid: i32,
+        /// The user defined GID of the prepared transaction.
+        gid: Bytes,
+    },
+    /// Sent when a streamed transaction is prepared, instead of `StreamCommit`
+    StreamPrepare {
+        /// Flags; currently unused (must be 0).
+        flags: u8,
+        /// The LSN of the prepare.
+        prepare_lsn: Lsn,
+        /// The end LSN of the prepared transaction.
+        end_lsn: Lsn,
+        /// Prepare timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        timestamp: i64,
+        /// Xid of the transaction.
+        xid: i32,
+        /// The user defined GID of the prepared transaction.
+        gid: Bytes,
+    },
     Unknown(Bytes),
 }
 
@@ -302,6 +393,39 @@ pub enum WalRecord {
         /// The content of the logical decoding message.
         payload: Bytes,
     },
+    /// Sent (with protocol version 2 and streaming enabled) before a block of records from a
+    /// large in-progress transaction. The records between this and the next `StreamStop` each
+    /// have the xid of their (sub)transaction.
+    StreamStart {
+        /// Xid of the transaction.
+        xid: i32,
+        /// True if this is the first stream segment for the transaction.
+        first_segment: bool,
+    },
+    /// Sent at the end of a block of records from a streamed transaction
+    StreamStop,
+    /// Sent when a streamed transaction commits
+    StreamCommit {
+        /// Xid of the transaction.
+        xid: i32,
+        /// Flags; currently unused (must be 0).
+        flags: u8,
+        /// The LSN of the commit.
+        lsn: Lsn,
+        /// The end LSN of the transaction.
+        end_lsn: Lsn,
+        /// Commit timestamp of the transaction. The value is in number of microseconds since
+        /// PostgreSQL epoch (2000-01-01).
+        timestamp: i64,
+    },
+    /// Sent when a streamed transaction, or one of its subtransactions, aborts
+    StreamAbort {
+        /// Xid of the transaction.
+        xid: i32,
+        /// Xid of the subtransaction (will be same as xid of the transaction for top-level
+        /// transactions).
+        subxid: i32,
+    },
     Unknown(Bytes),
 }
 
@@ -337,39 +461,34 @@ pub enum TupleEntry {
 impl TryFrom<Bytes> for WalData {
     type Error = WalError;
 
-    // The kind of `WalData` is identified by the value of the first byte
     fn try_from(b: Bytes) -> Result<Self, Self::Error> {
-        match *b.first().ok_or(WalError::Empty)? {
-            b'k' => WalData::keepalive(b),
-            b'w' => WalData::xlog_data(b),
-            b'r' => WalData::standby_update(b),
-            b'h' => WalData::hot_standby_feedback(b),
-            _ => Ok(WalData::Unknown(b)),
-        }
+        WalData::parse(b, false)
     }
 }
 
 impl TryFrom<Bytes> for WalRecord {
     type Error = WalError;
 
-    // The kind of `WalRecord` is identified by the value of the first byte
     fn try_from(b: Bytes) -> Result<Self, Self::Error> {
-        match *b.first().ok_or(WalError::Empty)? {
-            b'B' => WalRecord::begin(b),
-            b'C' => WalRecord::commit(b),
-            b'R' => WalRecord::relation(b),
-            b'Y' => WalRecord::type_(b),
-            b'U' => WalRecord::update(b),
-            b'I' => WalRecord::insert(b),
-            b'D' => WalRecord::delete(b),
-            b'T' => WalRecord::truncate(b),
-            b'M' => WalRecord::message(b),
-            _ => Ok(WalRecord::Unknown(b)),
-        }
+        WalRecord::parse(b, false).map(|(record, _)| record)
     }
 }
 
 impl WalData {
+    /// Parse a WAL message. `in_stream` must be set if the message was received between a
+    /// [`WalRecord::StreamStart`] and a [`WalRecord::StreamStop`], since the data records of
+    /// streamed transactions have an extra field.
+    pub(crate) fn parse(b: Bytes, in_stream: bool) -> Result<Self, WalError> {
+        // The kind of `WalData` is identified by the value of the first byte
+        match *b.first().ok_or(WalError::Empty)? {
+            b'k' => WalData::keepalive(b),
+            b'w' => WalData::xlog_data(b, in_stream),
+            b'r' => WalData::standby_update(b),
+            b'h' => WalData::hot_standby_feedback(b),
+            _ => Ok(WalData::Unknown(b)),
+        }
+    }
+
     /// Parse as `Keepalive`, assumes b[0] == 'k'
     fn keepalive(b: Bytes) -> Result<Self, WalError> {
         if b.len() != 18 {
@@ -383,7 +502,7 @@ impl WalData {
     }
 
     /// Parse as `XLogData`, assumes b[0] == 'w'
-    fn xlog_data(mut b: Bytes) -> Result<Self, WalError> {
+    fn xlog_data(mut b: Bytes, in_stream: bool) -> Result<Self, WalError> {
         if b.len() < 25 {
             return Err(WalError::IncorrectLen(b[0]));
         }
@@ -391,13 +510,14 @@ impl WalData {
         let start = i64::from_be_bytes(b[1..9].try_into()?).into();
         let end = i64::from_be_bytes(b[9..17].try_into()?).into();
         let time = i64::from_be_bytes(b[17..25].try_into()?);
-        let data = b.split_off(25).try_into()?;
+        let (data, xid) = WalRecord::parse(b.split_off(25), in_stream)?;
 
         Ok(WalData::XLogData {
             start,
             end,
             time,
             data,
+            xid,
         })
     }
 
@@ -445,6 +565,52 @@ impl WalData {
 }
 
 impl WalRecord {
+    /// Parse a record, which is part of a streamed transaction if `in_stream` is set. Returns the
+    /// record, along with the xid of its (sub)transaction if it's a data record of a streamed
+    /// transaction.
+    fn parse(mut b: Bytes, in_stream: bool) -> Result<(Self, Option<i32>), WalError> {
+        // The kind of `WalRecord` is identified by the value of the first byte
+        let kind = *b.first().ok_or(WalError::Empty)?;
+
+        let mut xid = None;
+        if in_stream && matches!(kind, b'R' | b'Y' | b'I' | b'U' | b'D' | b'T' | b'M') {
+            if b.len() < 5 {
+                return Err(WalError::IncorrectLen(kind));
+            }
+            xid = Some(i32::from_be_bytes(b[1..5].try_into()?));
+
+            // Remove the xid, so the rest of the record can be parsed the same as outside of a
+            // stream
+            let mut record = BytesMut::with_capacity(b.len() - 4);
+            record.put_u8(kind);
+            record.put_slice(&b[5..]);
+            b = record.freeze();
+        }
+
+        let record = match kind {
+            b'B' => WalRecord::begin(b),
+            b'C' => WalRecord::commit(b),
+            b'R' => WalRecord::relation(b),
+            b'Y' => WalRecord::type_(b),
+            b'U' => WalRecord::update(b),
+            b'I' => WalRecord::insert(b),
+            b'D' => WalRecord::delete(b),
+            b'T' => WalRecord::truncate(b),
+            b'M' => WalRecord::message(b),
+            b'S' => WalRecord::stream_start(b),
+            b'E' => WalRecord::stream_stop(b),
+            b'c' => WalRecord::stream_commit(b),
+            b'A' => WalRecord::stream_abort(b),
+            b'b' => WalRecord::begin_prepare(b),
+            b'P' => WalRecord::prepare(b),
+            b'K' => WalRecord::commit_prepared(b),
+            b'r' => WalRecord::rollback_prepared(b),
+            b'p' => WalRecord::stream_prepare(b),
+            _ => Ok(WalRecord::Unknown(b)),
+        }?;
+        Ok((record, xid))
+    }
+
     /// Parse as `Begin`, assumes b[0] == 'B'
     fn begin(b: Bytes) -> Result<Self, WalError> {
         if b.len() != 21 {
@@ -759,6 +925,166 @@ impl WalRecord {
             payload: b,
         })
     }
+
+    /// Parse as `StreamStart`, assumes b[0] == 'S'
+    fn stream_start(b: Bytes) -> Result<Self, WalError> {
+        if b.len() != 6 {
+            return Err(WalError::CorruptStream);
+        }
+
+        let xid = i32::from_be_bytes(b[1..5].try_into()?);
+        let first_segment = b[5] == 1;
+
+        Ok(WalRecord::StreamStart { xid, first_segment })
+    }
+
+    /// Parse as `StreamStop`, assumes b[0] == 'E'
+    fn stream_stop(b: Bytes) -> Result<Self, WalError> {
+        if b.len() != 1 {
+            return Err(WalError::CorruptStream);
+        }
+
+        Ok(WalRecord::StreamStop)
+    }
+
+    /// Parse as `StreamCommit`, assumes b[0] == 'c'
+    fn stream_commit(b: Bytes) -> Result<Self, WalError> {
+        if b.len() != 30 {
+            return Err(WalError::CorruptStream);
+        }
+
+        let xid = i32::from_be_bytes(b[1..5].try_into()?);
+        let flags = b[5];
+        let lsn = i64::from_be_bytes(b[6..14].try_into()?).into();
+        let end_lsn = i64::from_be_bytes(b[14..22].try_into()?).into();
+        let timestamp = i64::from_be_bytes(b[22..30].try_into()?);
+
+        Ok(WalRecord::StreamCommit {
+            xid,
+            flags,
+            lsn,
+            end_lsn,
+            timestamp,
+        })
+    }
+
+    /// Parse as `StreamAbort`, assumes b[0] == 'A'
+    fn stream_abort(b: Bytes) -> Result<Self, WalError> {
+        // Protocol version 4 adds the LSN and timestamp of the abort, which we don't need
+        if b.len() < 9 {
+            return Err(WalError::CorruptStream);
+        }
+
+        let xid = i32::from_be_bytes(b[1..5].try_into()?);
+        let subxid = i32::from_be_bytes(b[5..9].try_into()?);
+
+        Ok(WalRecord::StreamAbort { xid, subxid })
+    }
+
+    /// Parse as `BeginPrepare`, assumes b[0] == 'b'
+    fn begin_prepare(mut b: Bytes) -> Result<Self, WalError> {
+        if b.len() < 29 {
+            return Err(WalError::CorruptPrepare);
+        }
+
+        let prepare_lsn = i64::from_be_bytes(b[1..9].try_into()?).into();
+        let end_lsn = i64::from_be_bytes(b[9..17].try_into()?).into();
+        let timestamp = i64::from_be_bytes(b[17..25].try_into()?);
+        let xid = i32::from_be_bytes(b[25..29].try_into()?);
+        let _ = b.split_to(29);
+        let gid = Self::consume_string(&mut b)?;
+
+        Ok(WalRecord::BeginPrepare {
+            prepare_lsn,
+            end_lsn,
+            timestamp,
+            xid,
+            gid,
+        })
+    }
+
+    /// Parses the fields shared by `Prepare`, `CommitPrepared` and `StreamPrepare`: the flags, two
+    /// LSNs, a timestamp, the xid and the GID
+    fn consume_prepare(mut b: Bytes) -> Result<(u8, Lsn, Lsn, i64, i32, Bytes), WalError> {
+        if b.len() < 30 {
+            return Err(WalError::CorruptPrepare);
+        }
+
+        let flags = b[1];
+        let lsn = i64::from_be_bytes(b[2..10].try_into()?).into();
+        let end_lsn = i64::from_be_bytes(b[10..18].try_into()?).into();
+        let timestamp = i64::from_be_bytes(b[18..26].try_into()?);
+        let xid = i32::from_be_bytes(b[26..30].try_into()?);
+        let _ = b.split_to(30);
+        let gid = Self::consume_string(&mut b)?;
+
+        Ok((flags, lsn, end_lsn, timestamp, xid, gid))
+    }
+
+    /// Parse as `Prepare`, assumes b[0] == 'P'
+    fn prepare(b: Bytes) -> Result<Self, WalError> {
+        let (flags, prepare_lsn, end_lsn, timestamp, xid, gid) = Self::consume_prepare(b)?;
+        Ok(WalRecord::Prepare {
+            flags,
+            prepare_lsn,
+            end_lsn,
+            timestamp,
+            xid,
+            gid,
+        })
+    }
+
+    /// Parse as `CommitPrepared`, assumes b[0] == 'K'
+    fn commit_prepared(b: Bytes) -> Result<Self, WalError> {
+        let (flags, lsn, end_lsn, timestamp, xid, gid) = Self::consume_prepare(b)?;
+        Ok(WalRecord::CommitPrepared {
+            flags,
+            lsn,
+            end_lsn,
+            timestamp,
+            xid,
+            gid,
+        })
+    }
+
+    /// Parse as `RollbackPrepared`, assumes b[0] == 'r'
+    fn rollback_prepared(mut b: Bytes) -> Result<Self, WalError> {
+        if b.len() < 38 {
+            return Err(WalError::CorruptPrepare);
+        }
+
+        let flags = b[1];
+        let prepare_end_lsn = i64::from_be_bytes(b[2..10].try_into()?).into();
+        let rollback_end_lsn = i64::from_be_bytes(b[10..18].try_into()?).into();
+        let prepare_timestamp = i64::from_be_bytes(b[18..26].try_into()?);
+        let rollback_timestamp = i64::from_be_bytes(b[26..34].try_into()?);
+        let xid = i32::from_be_bytes(b[34..38].try_into()?);
+        let _ = b.split_to(38);
+        let gid = Self::consume_string(&mut b)?;
+
+        Ok(WalRecord::RollbackPrepared {
+            flags,
+            prepare_end_lsn,
+            rollback_end_lsn,
+            prepare_timestamp,
+            rollback_timestamp,
+            xid,
+            gid,
+        })
+    }
+
+    /// Parse as `StreamPrepare`, assumes b[0] == 'p'
+    fn stream_prepare(b: Bytes) -> Result<Self, WalError> {
+        let (flags, prepare_lsn, end_lsn, timestamp, xid, gid) = Self::consume_prepare(b)?;
+        Ok(WalRecord::StreamPrepare {
+            flags,
+            prepare_lsn,
+            end_lsn,
+            timestamp,
+            xid,
+            gid,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -799,6 +1125,7 @@ mod tests {
                 start: 0.into(),
                 end: 0.into(),
                 time: 676472897894731,
+                xid: None,
                 data: WalRecord::Relation(RelationMapping {
                     id: 16431,
                     schema: Bytes::copy_from_slice(b"public"),
@@ -849,6 +1176,7 @@ mod tests {
                 start: 23900024.into(),
                 end: 23900024.into(),
                 time: 676472897894844,
+                xid: None,
                 data: WalRecord::Insert {
                     relation_id: 16431,
                     new_tuple: TupleData {
@@ -879,6 +1207,7 @@ mod tests {
                 start: 23900024.into(),
                 end: 23900024.into(),
                 time: 676472897894844,
+                xid: None,
                 data: WalRecord::Type {
                     id: 82161,
                     schema: "public".into(),
@@ -887,4 +1216,162 @@ mod tests {
             }
         );
     }
+    /// Returns an `XLogData` message containing `record`
+    fn xlog_data(record: &[u8]) -> Bytes {
+        let mut b = BytesMut::new();
+        b.put_u8(b'w');
+        b.put_i64(1);
+        b.put_i64(2);
+        b.put_i64(3);
+        b.put_slice(record);
+        b.freeze()
+    }
+
+    #[test]
+    fn wal_parse_streamed_insert() {
+        let record = b"I\0\0\0\x07\0\0@/N\0\x01t\0\0\0\x0210";
+        let expected = WalRecord::Insert {
+            relation_id: 16431,
+            new_tuple: TupleData {
+                n_cols: 1,
+                cols: vec![TupleEntry::Text(Bytes::copy_from_slice(b"10"))],
+            },
+        };
+
+        match WalData::parse(xlog_data(record), true).unwrap() {
+            WalData::XLogData { data, xid, .. } => {
+                assert_eq!(data, expected);
+                assert_eq!(xid, Some(7));
+            }
+            data => panic!("Unexpected WAL data {data:?}"),
+        }
+
+        // Outside of a stream, the same record has no xid
+        let unstreamed = b"I\0\0@/N\0\x01t\0\0\0\x0210";
+        match WalData::parse(xlog_data(unstreamed), false).unwrap() {
+            WalData::XLogData { data, xid, .. } => {
+                assert_eq!(data, expected);
+                assert_eq!(xid, None);
+            }
+            data => panic!("Unexpected WAL data {data:?}"),
+        }
+    }
+
+    #[test]
+    fn wal_parse_stream_messages() {
+        let record: WalRecord = Bytes::copy_from_slice(b"S\0\0\0\x07\x01")
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            record,
+            WalRecord::StreamStart {
+                xid: 7,
+                first_segment: true
+            }
+        );
+
+        let record: WalRecord = Bytes::copy_from_slice(b"E").try_into().unwrap();
+        assert_eq!(record, WalRecord::StreamStop);
+
+        let mut commit = BytesMut::new();
+        commit.put_u8(b'c');
+        commit.put_i32(7);
+        commit.put_u8(0);
+        commit.put_i64(100);
+        commit.put_i64(108);
+        commit.put_i64(676472897894844);
+        let record: WalRecord = commit.freeze().try_into().unwrap();
+        assert_eq!(
+            record,
+            WalRecord::StreamCommit {
+                xid: 7,
+                flags: 0,
+                lsn: 100.into(),
+                end_lsn: 108.into(),
+                timestamp: 676472897894844,
+            }
+        );
+
+        let record: WalRecord = Bytes::copy_from_slice(b"A\0\0\0\x07\0\0\0\x08")
+            .try_into()
+            .unwrap();
+        assert_eq!(record, WalRecord::StreamAbort { xid: 7, subxid: 8 });
+    }
+
+    #[test]
+    fn wal_parse_two_phase_messages() {
+        let mut begin = BytesMut::new();
+        begin.put_u8(b'b');
+        begin.put_i64(100);
+        begin.put_i64(108);
+        begin.put_i64(676472897894844);
+        begin.put_i32(7);
+        begin.put_slice(b"tx1\0");
+        let record: WalRecord = begin.freeze().try_into().unwrap();
+        assert_eq!(
+            record,
+            WalRecord::BeginPrepare {
+                prepare_lsn: 100.into(),
+                end_lsn: 108.into(),
+                timestamp: 676472897894844,
+                xid: 7,
+                gid: Bytes::copy_from_slice(b"tx1"),
+            }
+        );
+
+        let mut commit = BytesMut::new();
+        commit.put_u8(b'K');
+        commit.put_u8(0);
+        commit.put_i64(200);
+        commit.put_i64(208);
+        commit.put_i64(676472897894845);
+        commit.put_i32(7);
+        commit.put_slice(b"tx1\0");
+        let record: WalRecord = commit.freeze().try_into().unwrap();
+        assert_eq!(
+            record,
+            WalRecord::CommitPrepared {
+                flags: 0,
+                lsn: 200.into(),
+                end_lsn: 208.into(),
+                timestamp: 676472897894845,
+                xid: 7,
+                gid: Bytes::copy_from_slice(b"tx1"),
+            }
+        );
+
+        let mut rollback = BytesMut::new();
+        rollback.put_u8(b'r');
+        rollback.put_u8(0);
+        rollback.put_i64(108);
+        rollback.put_i64(308);
+        rollback.put_i64(676472897894844);
+        rollback.put_i64(676472897894846);
+        rollback.put_i32(7);
+        rollback.put_slice(b"tx1\0");
+        let record: WalRecord = rollback.freeze().try_into().unwrap();
+        assert_eq!(
+            record,
+            WalRecord::RollbackPrepared {
+                flags: 0,
+                prepare_end_lsn: 108.into(),
+                rollback_end_lsn: 308.into(),
+                prepare_timestamp: 676472897894844,
+                rollback_timestamp: 676472897894846,
+                xid: 7,
+                gid: Bytes::copy_from_slice(b"tx1"),
+            }
+        );
+
+        // The GID must be null terminated
+        let mut prepare = BytesMut::new();
+        prepare.put_u8(b'P');
+        prepare.put_u8(0);
+        prepare.put_i64(100);
+        prepare.put_i64(108);
+        prepare.put_i64(676472897894844);
+        prepare.put_i32(7);
+        prepare.put_slice(b"tx1");
+        assert!(WalRecord::try_from(prepare.freeze()).is_err());
+    }
 }
@@ -1,10 +1,14 @@
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bit_vec::BitVec;
+use bytes::Bytes;
+use metrics::gauge;
 use mysql_time::MySqlTime;
 use postgres_types::Kind;
+use readyset_client::metrics::recorded;
 use readyset_data::{Array, Collation, DfType, DfValue, Dialect};
 use readyset_errors::{unsupported, ReadySetError};
 use rust_decimal::prelude::FromStr;
@@ -12,6 +16,7 @@ use rust_decimal::Decimal;
 use tokio_postgres as pgsql;
 use tracing::{debug, error, trace};
 
+use super::buffered_transaction::{BufferedRecord, BufferedTransaction};
 use super::ddl_replication::DdlEvent;
 use super::lsn::Lsn;
 use super::wal::{self, RelationMapping, WalData, WalError, WalRecord};
@@ -21,6 +26,9 @@ use crate::postgres_connector::wal::{TableErrorKind, TupleEntry};
 pub(crate) const DDL_REPLICATION_LOG_SCHEMA: &str = "readyset";
 pub(crate) const DDL_REPLICATION_LOG_TABLE: &str = "ddl_replication_log";
 
+/// The PostgreSQL epoch (2000-01-01), as seconds since the Unix epoch
+const POSTGRES_EPOCH_SECS: u64 = 946_684_800;
+
 struct Relation {
     schema: String,
     table: String,
@@ -34,11 +42,41 @@ pub struct WalReader {
     relations: HashMap<i32, Relation>,
     /// Keeps track of the OIDs of all custom types we've seen
     custom_types: HashSet<u32>,
+    /// The xid of the transaction being streamed, between a [`WalRecord::StreamStart`] and a
+    /// [`WalRecord::StreamStop`]
+    stream_xid: Option<i32>,
+    /// The records of each streamed transaction that's still in progress, by the xid of the
+    /// transaction. Since a streamed transaction can still abort, its records are buffered here
+    /// until it commits.
+    streamed: HashMap<i32, BufferedTransaction>,
+    /// The GID and prepare LSN of the transaction being prepared, between a
+    /// [`WalRecord::BeginPrepare`] and a [`WalRecord::Prepare`], along with its records so far
+    preparing: Option<(Bytes, Lsn, BufferedTransaction)>,
+    /// The records of each transaction that's been prepared but not yet committed or rolled back,
+    /// by GID, along with the LSN of the prepare. Like streamed transactions, these are buffered
+    /// until they commit.
+    prepared: HashMap<Bytes, (Lsn, BufferedTransaction)>,
+    /// The total size of the records of all buffered transactions that are held in memory, rather
+    /// than spilled to disk
+    buffered_bytes: usize,
+    /// A buffered transaction that has committed, along with the LSN of its commit. Its records
+    /// are read back before reading any further in the WAL.
+    replaying: Option<(BufferedTransaction, Lsn)>,
+    /// The position replication was resumed from. Every transaction that committed at or before
+    /// this position has already been applied.
+    resumed_from: Lsn,
+    /// How far behind the server we were as of the last commit or keepalive we read, see
+    /// [`WalReader::lag`]
+    lag: Option<Duration>,
 }
 
 #[derive(Debug)]
 pub(crate) enum WalEvent {
     WantsKeepaliveResponse,
+    /// The server has sent us every transaction that committed before this event's LSN, and is
+    /// waiting for more WAL. Sent as a keepalive that doesn't ask for a response, which the server
+    /// only sends between transactions.
+    CaughtUp,
     Commit,
     Insert {
         schema: String,
@@ -75,41 +113,146 @@ pub(crate) enum WalEvent {
     },
 }
 
+/// Returns how long ago a transaction committed, given its commit timestamp in microseconds since
+/// the PostgreSQL epoch. Differences between our clock and the server's can make this zero.
+fn commit_lag(timestamp: i64) -> Duration {
+    let committed = UNIX_EPOCH
+        + Duration::from_secs(POSTGRES_EPOCH_SECS)
+        + Duration::from_micros(timestamp.max(0) as u64);
+    SystemTime::now()
+        .duration_since(committed)
+        .unwrap_or_default()
+}
+
+/// Keep `lag` as the current replication lag, and record it in [`recorded::REPLICATOR_LAG`]
+fn record_lag(current: &mut Option<Duration>, lag: Duration) {
+    gauge!(recorded::REPLICATOR_LAG, lag.as_secs() as f64);
+    *current = Some(lag);
+}
+
 impl WalReader {
-    pub(crate) fn new(wal: pgsql::client::Responses) -> Self {
+    pub(crate) fn new(wal: pgsql::client::Responses, resumed_from: Lsn) -> Self {
         WalReader {
             relations: Default::default(),
             custom_types: Default::default(),
+            stream_xid: None,
+            streamed: Default::default(),
+            preparing: None,
+            prepared: Default::default(),
+            buffered_bytes: 0,
+            replaying: None,
+            resumed_from,
+            lag: None,
             wal,
         }
     }
 
+    /// Returns how far behind the server replication was as of the last commit we read, going by
+    /// its commit timestamp, or zero if the server has since told us it has no more WAL for us.
+    /// `None` until we've read either.
+    pub(crate) fn lag(&self) -> Option<Duration> {
+        self.lag
+    }
+
+    /// Returns the LSN of the prepare of the oldest transaction that's been prepared but not yet
+    /// committed or rolled back, if any.
+    ///
+    /// The server mustn't be told we've flushed the WAL past this point, since after a restart it
+    /// would only send the `COMMIT PREPARED` of the transaction, and not its changes. Replication
+    /// is then resumed from no later than this point, so that the server sends the transaction
+    /// again.
+    pub(crate) fn oldest_prepared_lsn(&self) -> Option<Lsn> {
+        self.preparing
+            .iter()
+            .map(|(_, lsn, _)| *lsn)
+            .chain(self.prepared.values().map(|(lsn, _)| *lsn))
+            .min()
+    }
+
+    /// Returns the next event from the WAL.
+    ///
+    /// The records of a streamed or prepared transaction are buffered until it commits, at which
+    /// point all of its events are returned at the LSN of the commit, followed by a
+    /// [`WalEvent::Commit`] - or discarded, if it aborts or is rolled back.
     pub(crate) async fn next_event(&mut self) -> Result<(WalEvent, Lsn), WalError> {
         let WalReader {
             wal,
             relations,
             custom_types,
+            stream_xid,
+            streamed,
+            preparing,
+            prepared,
+            buffered_bytes,
+            replaying,
+            resumed_from,
+            lag,
         } = self;
 
         loop {
-            let data: WalData = match wal
-                .next()
-                .await
-                .map_err(|e| WalError::ReadySetError(e.into()))?
-            {
-                pgsql::Message::CopyData(body) => body.into_bytes().try_into()?,
-                _ => {
-                    return Err(WalError::ReadySetError(ReadySetError::ReplicationFailed(
-                        "Unexpected message during WAL replication".to_string(),
-                    )))
-                }
+            let (body, in_stream) = match replaying {
+                Some((transaction, end)) => match transaction
+                    .next_record()
+                    .await
+                    .map_err(|e| WalError::ReadySetError(e.into()))?
+                {
+                    Some(record) => (record.body, record.in_stream),
+                    None => {
+                        let end = *end;
+                        *replaying = None;
+                        return Ok((WalEvent::Commit, end));
+                    }
+                },
+                None => match wal
+                    .next()
+                    .await
+                    .map_err(|e| WalError::ReadySetError(e.into()))?
+                {
+                    pgsql::Message::CopyData(body) => (body.into_bytes(), stream_xid.is_some()),
+                    _ => {
+                        return Err(WalError::ReadySetError(
+                            ReadySetError::ReplicationUnsupportedEvent(
+                                "Unexpected message during WAL replication".to_string(),
+                            ),
+                        ))
+                    }
+                },
             };
+            let data = WalData::parse(body.clone(), in_stream)?;
 
             let (end, record) = match data {
                 WalData::Keepalive { end, reply, .. } if reply == 1 => {
                     return Ok((WalEvent::WantsKeepaliveResponse, end))
                 }
-                WalData::XLogData { end, data, .. } => (end, data),
+                WalData::Keepalive { end, .. } => {
+                    record_lag(lag, Duration::ZERO);
+                    return Ok((WalEvent::CaughtUp, end));
+                }
+                WalData::XLogData { end, data, xid, .. } => {
+                    let buffer = match (*stream_xid, xid, preparing.as_mut()) {
+                        _ if replaying.is_some() || !is_change(&data) => None,
+                        (Some(streaming), Some(_), _) => {
+                            Some(streamed.entry(streaming).or_default())
+                        }
+                        (_, _, Some((_, _, transaction))) => Some(transaction),
+                        _ => None,
+                    };
+                    if let Some(transaction) = buffer {
+                        let record = BufferedRecord {
+                            in_stream,
+                            subxid: xid,
+                            body,
+                        };
+                        transaction
+                            .push(record, buffered_bytes)
+                            .await
+                            .map_err(|e| WalError::ReadySetError(e.into()))?;
+                        continue;
+                    }
+
+                    // The changes of a buffered transaction all happen at the LSN of its commit
+                    (replaying.as_ref().map_or(end, |(_, commit)| *commit), data)
+                }
                 msg => {
                     trace!(?msg, "Unhandled message");
                     // For any other message, just keep going
@@ -120,7 +263,10 @@ impl WalReader {
             trace!(?record);
 
             match record {
-                WalRecord::Commit { .. } => return Ok((WalEvent::Commit, end)),
+                WalRecord::Commit { timestamp, .. } => {
+                    record_lag(lag, commit_lag(timestamp));
+                    return Ok((WalEvent::Commit, end));
+                }
                 WalRecord::Relation(mapping) => {
                     // Store the relation in the hash map for future use
                     let id = mapping.id;
@@ -226,14 +372,26 @@ impl WalReader {
                         // IDENTITY` is set to `FULL`
 
                         // Replace TupleEntry::Unchanged in new_tuple by the corresponding value in
-                        // old_tuple
+                        // old_tuple. Postgres always logs the old tuple of a table with `REPLICA
+                        // IDENTITY FULL` with its TOASTed values included, so this resolves every
+                        // unchanged TOASTed value as of this update, without having to look the
+                        // row up anywhere.
                         let mut new_tuple = new_tuple;
-                        while let Some(pos) = new_tuple
-                            .cols
-                            .iter()
-                            .position(|x| *x == TupleEntry::Unchanged)
-                        {
-                            new_tuple.cols[pos] = old_tuple.cols[pos].clone();
+                        for (new, old) in new_tuple.cols.iter_mut().zip(&old_tuple.cols) {
+                            if *new == TupleEntry::Unchanged {
+                                *new = old.clone();
+                            }
+                        }
+                        if new_tuple.cols.contains(&TupleEntry::Unchanged) {
+                            // If that ever doesn't hold, we can't know the row's current values.
+                            // This isn't an issue with the table itself, so rather than dropping
+                            // it, stop replicating until this is fixed.
+                            return Err(WalError::ReadySetError(ReadySetError::ReplicationFailed(
+                                format!(
+                                    "Update of table {schema}.{table} has unchanged values that \
+                                     aren't in the old row either"
+                                ),
+                            )));
                         }
 
                         return Ok((
@@ -268,7 +426,10 @@ impl WalReader {
                             end,
                         ));
                     } else if let Some(key_tuple) = key_tuple {
-                        // This happens when the update is modifying the key column
+                        // This happens when the update is modifying the key column. Any unchanged
+                        // TOASTed values in the new tuple become `Modification::None`, so they keep
+                        // their value from the row with this key in the base table, which is as of
+                        // this point in the WAL.
                         return Ok((
                             WalEvent::UpdateByKey {
                                 schema: schema.clone(),
@@ -398,6 +559,7 @@ impl WalReader {
                         }
                         Ok(ddl_event) => ddl_event,
                     };
+                    let lsn = if replaying.is_some() { end } else { lsn };
                     return Ok((WalEvent::DdlEvent { ddl_event }, lsn));
                 }
                 WalRecord::Message { prefix, .. } => {
@@ -425,6 +587,100 @@ impl WalReader {
                 WalRecord::Origin { .. } => {
                     // Just tells where the transaction originated
                 }
+                WalRecord::StreamStart { xid, .. } => *stream_xid = Some(xid),
+                WalRecord::StreamStop => *stream_xid = None,
+                WalRecord::StreamCommit { xid, timestamp, .. } => {
+                    record_lag(lag, commit_lag(timestamp));
+                    let transaction = streamed.remove(&xid).unwrap_or_default();
+                    debug!(
+                        xid,
+                        spilled = transaction.is_spilled(),
+                        "Streamed transaction committed"
+                    );
+                    *buffered_bytes -= transaction.in_memory_bytes();
+                    *replaying = Some((transaction, end));
+                }
+                WalRecord::StreamAbort { xid, subxid } => {
+                    if xid == subxid {
+                        debug!(xid, "Streamed transaction aborted");
+                        if let Some(transaction) = streamed.remove(&xid) {
+                            *buffered_bytes -= transaction.in_memory_bytes();
+                        }
+                    } else if let Some(transaction) = streamed.get_mut(&xid) {
+                        transaction.abort_subtransaction(subxid);
+                    }
+                }
+                WalRecord::BeginPrepare {
+                    prepare_lsn, gid, ..
+                } => *preparing = Some((gid, prepare_lsn, BufferedTransaction::default())),
+                WalRecord::Prepare { gid, .. } => match preparing.take() {
+                    Some((preparing_gid, prepare_lsn, transaction)) if preparing_gid == gid => {
+                        debug!(
+                            ?gid,
+                            spilled = transaction.is_spilled(),
+                            "Transaction prepared"
+                        );
+                        prepared.insert(gid, (prepare_lsn, transaction));
+                    }
+                    preparing => {
+                        if let Some((_, _, transaction)) = preparing {
+                            *buffered_bytes -= transaction.in_memory_bytes();
+                        }
+                        error!(?gid, "Prepare without matching begin prepare")
+                    }
+                },
+                WalRecord::StreamPrepare {
+                    xid,
+                    prepare_lsn,
+                    gid,
+                    ..
+                } => {
+                    let transaction = streamed.remove(&xid).unwrap_or_default();
+                    debug!(
+                        xid,
+                        ?gid,
+                        spilled = transaction.is_spilled(),
+                        "Streamed transaction prepared"
+                    );
+                    prepared.insert(gid, (prepare_lsn, transaction));
+                }
+                WalRecord::CommitPrepared { gid, timestamp, .. } => {
+                    record_lag(lag, commit_lag(timestamp));
+                    let transaction = match prepared.remove(&gid) {
+                        Some((_, transaction)) => {
+                            debug!(
+                                ?gid,
+                                spilled = transaction.is_spilled(),
+                                "Prepared transaction committed"
+                            );
+                            transaction
+                        }
+                        // A transaction that was prepared before the position we resumed from isn't
+                        // sent again, which is fine if it was committed before that position too
+                        None if end <= *resumed_from => {
+                            debug!(?gid, "Skipping commit of already applied transaction");
+                            continue;
+                        }
+                        // Otherwise, its changes are lost, and the only way to get them back is to
+                        // snapshot again
+                        None => {
+                            return Err(WalError::ReadySetError(
+                                ReadySetError::FullResnapshotNeeded(format!(
+                                    "Commit of unknown prepared transaction {}",
+                                    String::from_utf8_lossy(&gid)
+                                )),
+                            ));
+                        }
+                    };
+                    *buffered_bytes -= transaction.in_memory_bytes();
+                    *replaying = Some((transaction, end));
+                }
+                WalRecord::RollbackPrepared { gid, .. } => {
+                    debug!(?gid, "Prepared transaction rolled back");
+                    if let Some((_, transaction)) = prepared.remove(&gid) {
+                        *buffered_bytes -= transaction.in_memory_bytes();
+                    }
+                }
                 WalRecord::Unknown(payload) => {
                     error!(?payload, "Unknown message");
                 }
@@ -433,6 +689,19 @@ impl WalReader {
     }
 }
 
+/// Returns whether `record` is a change within a transaction, which has to be buffered if the
+/// transaction is streamed or prepared
+fn is_change(record: &WalRecord) -> bool {
+    matches!(
+        record,
+        WalRecord::Insert { .. }
+            | WalRecord::Update { .. }
+            | WalRecord::Delete { .. }
+            | WalRecord::Truncate { .. }
+            | WalRecord::Message { .. }
+    )
+}
+
 impl wal::TupleData {
     /// Converts a WAL tuple into a row of *maybe* DfValues.
     /// WAL tuple entries for update records can be "unchanged", which we represent here as None so
@@ -0,0 +1,75 @@
+//! Checks, performed when replication starts, that the user we connect to the upstream database as
+//! has the privileges the connectors need.
+//!
+//! Each connector tests the privileges it needs against the upstream database and records the
+//! results in a [`PrivilegeReport`], so that missing grants are reported up front by name, rather
+//! than as whatever error the upstream returns once we first rely on them.
+use readyset_errors::{ReadySetError, ReadySetResult};
+use tracing::{error, info, warn};
+
+/// A privilege the upstream user is lacking, along with what we need it for
+#[derive(Debug)]
+struct Privilege {
+    name: String,
+    purpose: &'static str,
+}
+
+/// The result of checking the privileges of the upstream user
+#[derive(Debug, Default)]
+pub(crate) struct PrivilegeReport {
+    /// Privileges without which replication can't work
+    missing: Vec<Privilege>,
+    /// Privileges that replication can do without, but which make it more robust
+    absent_optional: Vec<Privilege>,
+}
+
+impl PrivilegeReport {
+    /// Record that the required privilege `name`, which we need `purpose`, is missing
+    pub(crate) fn missing<S: Into<String>>(&mut self, name: S, purpose: &'static str) {
+        self.missing.push(Privilege {
+            name: name.into(),
+            purpose,
+        });
+    }
+
+    /// Record that the optional privilege `name`, which we use `purpose`, is absent
+    pub(crate) fn absent_optional<S: Into<String>>(&mut self, name: S, purpose: &'static str) {
+        self.absent_optional.push(Privilege {
+            name: name.into(),
+            purpose,
+        });
+    }
+
+    /// Log the results of the check, returning
+    /// [`ReadySetError::ReplicationPermissionMissing`] listing every missing required privilege,
+    /// if there are any
+    pub(crate) fn finish(self) -> ReadySetResult<()> {
+        for privilege in &self.absent_optional {
+            warn!(
+                privilege = %privilege.name,
+                purpose = privilege.purpose,
+                "Upstream user is missing optional privilege"
+            );
+        }
+
+        if self.missing.is_empty() {
+            info!("Upstream user has all required privileges");
+            return Ok(());
+        }
+
+        for privilege in &self.missing {
+            error!(
+                privilege = %privilege.name,
+                purpose = privilege.purpose,
+                "Upstream user is missing required privilege"
+            );
+        }
+        Err(ReadySetError::ReplicationPermissionMissing(
+            self.missing
+                .iter()
+                .map(|privilege| format!("{} ({})", privilege.name, privilege.purpose))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ))
+    }
+}
@@ -0,0 +1,594 @@
+//! Capturing the replication events received from the upstream database to a file, for a limited
+//! time, so that replication bugs seen in a deployment can be reproduced elsewhere
+//!
+//! A capture file is a sequence of JSON values, one per line. The first line is a
+//! [`CaptureHeader`], and each line after that is a [`CapturedEvent`] holding a replication action
+//! along with the replication offset it was received at, in the order the actions were received.
+//! Heartbeats are not captured.
+//!
+//! If the capture is redacted, values in rows are replaced with a hash of the value (see
+//! [`CaptureRedaction`]), so that the writes to a row can still be matched up with each other, and
+//! the text of upstream statements is left out. DDL is captured as is. Values are hashed with a key
+//! generated at random for each capture, which isn't written to the capture file, so equal values
+//! stay equal within a capture but a hash can't be checked against guesses of the value.
+//!
+//! Captures are only written to files directly within [`UpstreamConfig::replication_capture_dir`],
+//! by a task of their own so that replication never waits on the file being written. If writing
+//! falls [`MAX_QUEUED_EVENTS`] events behind, the capture is ended early.
+//!
+//! Captured events can be applied to another ReadySet deployment with
+//! [`NoriaAdapter::replay_capture`], which reads them back with a [`CaptureFileConnector`].
+//!
+//! [`UpstreamConfig::replication_capture_dir`]: database_utils::UpstreamConfig::replication_capture_dir
+//! [`NoriaAdapter::replay_capture`]: crate::NoriaAdapter::replay_capture
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use readyset_client::replication::{
+    CaptureRedaction, ReplicationCaptureRequest, ReplicationOffset,
+};
+use readyset_client::{Modification, TableOperation};
+use readyset_data::DfValue;
+use readyset_errors::{
+    internal, internal_err, invalid, invalid_err, unsupported, ReadySetError, ReadySetResult,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter, Lines};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::noria_adapter::{Connector, ReplicationAction};
+
+/// The version of the format of capture files
+const CAPTURE_FORMAT_VERSION: u32 = 1;
+
+/// The maximum number of captured events waiting to be written to the capture file
+const MAX_QUEUED_EVENTS: usize = 10_000;
+
+/// The first line of a capture file
+#[derive(Debug, Serialize, Deserialize)]
+struct CaptureHeader {
+    version: u32,
+    redaction: CaptureRedaction,
+}
+
+/// A single replication action in a capture file
+#[derive(Debug, Serialize, Deserialize)]
+struct CapturedEvent {
+    offset: ReplicationOffset,
+    action: ReplicationAction,
+}
+
+#[derive(Debug)]
+struct ActiveCapture {
+    /// Identifies this capture, so that the task ending it doesn't end a later one
+    id: u64,
+    path: PathBuf,
+    until: Instant,
+    /// Sends captured events to the task writing them to the capture file
+    events: mpsc::Sender<CapturedEvent>,
+    /// The task writing the capture file, which finishes once `events` has been dropped
+    writer: JoinHandle<()>,
+}
+
+impl ActiveCapture {
+    /// Stop capturing events, returning the task writing the capture file, which finishes once
+    /// every event captured so far has been written
+    fn finish(self) -> JoinHandle<()> {
+        self.writer
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    active: Option<ActiveCapture>,
+    next_id: u64,
+}
+
+/// A handle to the replication capture in progress, if any. Cloning a [`ReplicationCapture`]
+/// returns a handle to the same capture.
+///
+/// A [`ReplicationCapture`] created with [`Default`] refuses to start captures.
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationCapture {
+    /// The directory captures are written to, if captures are enabled
+    dir: Option<PathBuf>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ReplicationCapture {
+    /// Create a handle to capture replication events to files within `dir`, or which refuses to
+    /// start captures if `dir` is `None`
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            dir,
+            inner: Default::default(),
+        }
+    }
+
+    /// Start capturing replication events to a file, as described by `request`. Returns an error
+    /// if a capture is already in progress, or if the requested file isn't directly within the
+    /// directory captures are written to.
+    ///
+    /// Must be called from within a tokio runtime, which is used to write the capture file and to
+    /// end the capture once its duration has passed.
+    pub fn start(&self, request: ReplicationCaptureRequest) -> ReadySetResult<()> {
+        let Some(dir) = &self.dir else {
+            unsupported!(
+                "Capturing replication events requires --replication-capture-dir to be set"
+            );
+        };
+        let path = capture_path(dir, &request.file_name)?;
+
+        let mut inner = self.inner.lock().expect("poisoned");
+        if let Some(active) = &inner.active && active.until > Instant::now() {
+            internal!(
+                "Already capturing replication events to {}",
+                active.path.display()
+            );
+        }
+        inner.active = None;
+
+        let file = File::from_std(std::fs::File::create(&path)?);
+        let (events, events_rx) = mpsc::channel(MAX_QUEUED_EVENTS);
+        let header = CaptureHeader {
+            version: CAPTURE_FORMAT_VERSION,
+            redaction: request.redaction,
+        };
+        info!(
+            path = %path.display(),
+            duration_secs = request.duration.as_secs(),
+            redaction = ?request.redaction,
+            "Capturing replication events"
+        );
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.active = Some(ActiveCapture {
+            id,
+            writer: tokio::spawn(write_capture(path.clone(), file, header, events_rx)),
+            path,
+            until: Instant::now() + request.duration,
+            events,
+        });
+
+        let handle = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(request.duration).await;
+            handle.end(id);
+        });
+        Ok(())
+    }
+
+    /// End the capture with the given `id`, if it's still in progress, returning the task writing
+    /// its capture file
+    fn end(&self, id: u64) -> Option<JoinHandle<()>> {
+        let mut inner = self.inner.lock().expect("poisoned");
+        if inner
+            .active
+            .as_ref()
+            .map_or(false, |active| active.id == id)
+        {
+            inner.active.take().map(ActiveCapture::finish)
+        } else {
+            None
+        }
+    }
+
+    /// End the capture in progress, if any, returning once every event it captured has been
+    /// written to its capture file
+    pub async fn stop(&self) {
+        let writer = self
+            .inner
+            .lock()
+            .expect("poisoned")
+            .active
+            .take()
+            .map(ActiveCapture::finish);
+        if let Some(writer) = writer {
+            let _ = writer.await;
+        }
+    }
+
+    /// Queue `action`, received at `offset`, to be written to the capture in progress, if any
+    pub(crate) fn record(&self, action: &ReplicationAction, offset: &ReplicationOffset) {
+        if matches!(action, ReplicationAction::Heartbeat) {
+            return;
+        }
+
+        let mut inner = self.inner.lock().expect("poisoned");
+        let Some(active) = inner.active.as_ref() else {
+            return;
+        };
+        if active.until <= Instant::now() {
+            inner.active = None;
+            return;
+        }
+
+        let event = CapturedEvent {
+            offset: offset.clone(),
+            action: action.clone(),
+        };
+        if let Err(error) = active.events.try_send(event) {
+            warn!(
+                path = %active.path.display(),
+                %error,
+                "Could not capture replication event, ending capture"
+            );
+            inner.active = None;
+        }
+    }
+}
+
+/// Returns the path of the capture file named `file_name` within `dir`, refusing names that would
+/// refer to a file anywhere else
+fn capture_path(dir: &Path, file_name: &str) -> ReadySetResult<PathBuf> {
+    let mut components = Path::new(file_name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) => Ok(dir.join(name)),
+        _ => invalid!(
+            "Replication capture file name {file_name:?} must be the name of a file, without \
+             a directory"
+        ),
+    }
+}
+
+/// Write `header` to `file`, followed by each event received on `events`, redacted as described
+/// by the header, until every sender of `events` has been dropped
+async fn write_capture(
+    path: PathBuf,
+    file: File,
+    header: CaptureHeader,
+    mut events: mpsc::Receiver<CapturedEvent>,
+) {
+    let key = RandomState::new();
+    let mut writer = BufWriter::new(file);
+    let mut written = 0;
+    let res: ReadySetResult<()> = async {
+        write_line(&mut writer, &header).await?;
+        while let Some(mut event) = events.recv().await {
+            event.action = redact(&event.action, header.redaction, &key);
+            write_line(&mut writer, &event).await?;
+            written += 1;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+    .await;
+
+    match res {
+        Ok(()) => info!(
+            path = %path.display(),
+            events = written,
+            "Finished capturing replication events"
+        ),
+        Err(error) => warn!(
+            path = %path.display(),
+            %error,
+            "Could not write captured replication events, ending capture"
+        ),
+    }
+}
+
+async fn write_line<T: Serialize>(writer: &mut BufWriter<File>, value: &T) -> ReadySetResult<()> {
+    let mut line = serde_json::to_vec(value)
+        .map_err(|e| internal_err!("Could not serialize replication event: {e}"))?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// Reads back the replication events in a capture file, for replaying them with
+/// [`NoriaAdapter::replay_capture`](crate::NoriaAdapter::replay_capture). Returns
+/// [`ReadySetError::ReplicationCaptureEnded`] once every event has been read.
+pub(crate) struct CaptureFileConnector {
+    path: PathBuf,
+    lines: Lines<BufReader<File>>,
+}
+
+impl CaptureFileConnector {
+    /// Open the capture file at `path`, checking that it was written in a format we can read
+    pub(crate) async fn open(path: &Path) -> ReadySetResult<Self> {
+        let mut lines = BufReader::new(File::open(path).await?).lines();
+        let header: CaptureHeader = match lines.next_line().await? {
+            Some(line) => parse_line(path, &line)?,
+            None => invalid!("Replication capture {} is empty", path.display()),
+        };
+        if header.version != CAPTURE_FORMAT_VERSION {
+            unsupported!(
+                "Replication capture {} has unsupported format version {}",
+                path.display(),
+                header.version
+            );
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            lines,
+        })
+    }
+}
+
+#[async_trait]
+impl Connector for CaptureFileConnector {
+    async fn next_action(
+        &mut self,
+        _: &ReplicationOffset,
+        _: Option<&ReplicationOffset>,
+    ) -> ReadySetResult<(ReplicationAction, ReplicationOffset)> {
+        let Some(line) = self.lines.next_line().await? else {
+            return Err(ReadySetError::ReplicationCaptureEnded);
+        };
+        let event: CapturedEvent = parse_line(&self.path, &line)?;
+        Ok((event.action, event.offset))
+    }
+}
+
+fn parse_line<T: DeserializeOwned>(path: &Path, line: &str) -> ReadySetResult<T> {
+    serde_json::from_str(line).map_err(|e| {
+        invalid_err!(
+            "Invalid line in replication capture {}: {e}",
+            path.display()
+        )
+    })
+}
+
+/// Returns a copy of `action` with the values in its rows redacted according to `redaction`,
+/// hashing values with `key`
+fn redact(
+    action: &ReplicationAction,
+    redaction: CaptureRedaction,
+    key: &RandomState,
+) -> ReplicationAction {
+    let redact_ops = |ops: &[TableOperation]| -> Vec<TableOperation> {
+        ops.iter()
+            .map(|op| redact_operation(op, redaction, key))
+            .collect()
+    };
+    match action {
+        _ if redaction == CaptureRedaction::None => action.clone(),
+        ReplicationAction::TableAction {
+            table,
+            actions,
+            txid,
+            query: _,
+        } => ReplicationAction::TableAction {
+            table: table.clone(),
+            actions: redact_ops(actions),
+            txid: *txid,
+            query: None,
+        },
+        ReplicationAction::Transaction {
+            tables,
+            txid,
+            queries: _,
+        } => ReplicationAction::Transaction {
+            tables: tables
+                .iter()
+                .map(|(table, ops)| (table.clone(), redact_ops(ops)))
+                .collect(),
+            txid: *txid,
+            queries: vec![],
+        },
+        _ => action.clone(),
+    }
+}
+
+fn redact_operation(
+    op: &TableOperation,
+    redaction: CaptureRedaction,
+    key: &RandomState,
+) -> TableOperation {
+    let row = |row: &[DfValue]| -> Vec<DfValue> {
+        row.iter()
+            .map(|value| redact_value(value, redaction, key))
+            .collect()
+    };
+    let update = |update: &[Modification]| -> Vec<Modification> {
+        update
+            .iter()
+            .map(|modification| match modification {
+                Modification::Set(value) => Modification::Set(redact_value(value, redaction, key)),
+                Modification::Apply(op, value) => {
+                    Modification::Apply(op.clone(), redact_value(value, redaction, key))
+                }
+                Modification::None => Modification::None,
+            })
+            .collect()
+    };
+    match op {
+        TableOperation::Insert(r) => TableOperation::Insert(row(r)),
+        TableOperation::DeleteByKey { key } => TableOperation::DeleteByKey { key: row(key) },
+        TableOperation::DeleteRow { row: r } => TableOperation::DeleteRow { row: row(r) },
+        TableOperation::InsertOrUpdate { row: r, update: u } => TableOperation::InsertOrUpdate {
+            row: row(r),
+            update: update(u),
+        },
+        TableOperation::Update { update: u, key } => TableOperation::Update {
+            update: update(u),
+            key: row(key),
+        },
+        TableOperation::Truncate
+        | TableOperation::DeleteExpired { .. }
+        | TableOperation::SetReplicationOffset(_)
+        | TableOperation::SetSnapshotMode(_) => op.clone(),
+    }
+}
+
+fn redact_value(value: &DfValue, redaction: CaptureRedaction, key: &RandomState) -> DfValue {
+    let hash = || {
+        let mut hasher = key.build_hasher();
+        value.hash(&mut hasher);
+        hasher.finish()
+    };
+    match (value, redaction) {
+        (_, CaptureRedaction::None) | (DfValue::None, _) => value.clone(),
+        (DfValue::Text(_) | DfValue::TinyText(_), _) => {
+            DfValue::from(format!("redacted-{:016x}", hash()))
+        }
+        (DfValue::ByteArray(_), _) => DfValue::ByteArray(Arc::new(hash().to_be_bytes().to_vec())),
+        (DfValue::Int(_), CaptureRedaction::All) => DfValue::Int(hash() as i64),
+        (DfValue::UnsignedInt(_), CaptureRedaction::All) => DfValue::UnsignedInt(hash()),
+        (_, CaptureRedaction::All) => DfValue::None,
+        (_, CaptureRedaction::Text) => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nom_sql::Relation;
+
+    use super::*;
+
+    fn insert(row: Vec<DfValue>) -> ReplicationAction {
+        ReplicationAction::TableAction {
+            table: Relation {
+                schema: Some("public".into()),
+                name: "t".into(),
+            },
+            actions: vec![TableOperation::Insert(row)],
+            txid: None,
+            query: Some("INSERT INTO t VALUES (1, 'secret', 1.5)".into()),
+        }
+    }
+
+    fn inserted_row(action: ReplicationAction) -> Vec<DfValue> {
+        match action {
+            ReplicationAction::TableAction {
+                mut actions, query, ..
+            } => {
+                assert_eq!(query, None);
+                match actions.remove(0) {
+                    TableOperation::Insert(row) => row,
+                    op => panic!("Unexpected operation {op:?}"),
+                }
+            }
+            action => panic!("Unexpected action {action:?}"),
+        }
+    }
+
+    #[test]
+    fn redact_values() {
+        let key = RandomState::new();
+        let row = vec![
+            DfValue::from(1),
+            DfValue::from("secret"),
+            DfValue::Double(1.5),
+        ];
+
+        let text = inserted_row(redact(&insert(row.clone()), CaptureRedaction::Text, &key));
+        assert_eq!(text[0], DfValue::from(1));
+        assert_ne!(text[1], DfValue::from("secret"));
+        assert_eq!(text[2], DfValue::Double(1.5));
+        // Redacting with the same key is deterministic, so that equal values stay equal
+        assert_eq!(
+            text,
+            inserted_row(redact(&insert(row.clone()), CaptureRedaction::Text, &key))
+        );
+        // but values hash differently with the key of another capture
+        let other_key = RandomState::new();
+        assert_ne!(
+            text,
+            inserted_row(redact(
+                &insert(row.clone()),
+                CaptureRedaction::Text,
+                &other_key
+            ))
+        );
+
+        let all = inserted_row(redact(&insert(row), CaptureRedaction::All, &key));
+        assert_ne!(all[0], DfValue::from(1));
+        assert_eq!(all[1], text[1]);
+        assert_eq!(all[2], DfValue::None);
+    }
+
+    #[test]
+    fn capture_files_must_be_within_dir() {
+        let dir = Path::new("/var/lib/readyset/captures");
+        assert_eq!(
+            capture_path(dir, "capture.jsonl").unwrap(),
+            dir.join("capture.jsonl")
+        );
+        for file_name in [
+            "",
+            "/etc/passwd",
+            "../capture.jsonl",
+            "sub/capture.jsonl",
+            "..",
+        ] {
+            assert!(capture_path(dir, file_name).is_err(), "{file_name:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn captures_are_refused_without_dir() {
+        assert!(ReplicationCapture::default()
+            .start(ReplicationCaptureRequest {
+                file_name: "capture.jsonl".into(),
+                duration: Duration::from_secs(60),
+                redaction: CaptureRedaction::None,
+            })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn capture_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let capture = ReplicationCapture::new(Some(dir.path().to_owned()));
+        let offset = ReplicationOffset {
+            offset: 1,
+            replication_log_name: "binlog.000001".into(),
+            gtid_set: None,
+        };
+
+        // Nothing is captured before a capture is started
+        capture.record(&insert(vec![DfValue::from(1)]), &offset);
+        capture
+            .start(ReplicationCaptureRequest {
+                file_name: "capture.jsonl".into(),
+                duration: Duration::from_secs(60),
+                redaction: CaptureRedaction::None,
+            })
+            .unwrap();
+        assert!(capture
+            .start(ReplicationCaptureRequest {
+                file_name: "other.jsonl".into(),
+                duration: Duration::from_secs(60),
+                redaction: CaptureRedaction::None,
+            })
+            .is_err());
+
+        capture.record(&ReplicationAction::Heartbeat, &offset);
+        capture.record(&insert(vec![DfValue::from(2)]), &offset);
+        let id = capture.inner.lock().unwrap().active.as_ref().unwrap().id;
+        capture.end(id).unwrap().await.unwrap();
+        capture.record(&insert(vec![DfValue::from(3)]), &offset);
+
+        let mut connector = CaptureFileConnector::open(&dir.path().join("capture.jsonl"))
+            .await
+            .unwrap();
+        let (action, action_offset) = connector.next_action(&offset, None).await.unwrap();
+        assert_eq!(action_offset, offset);
+        match action {
+            ReplicationAction::TableAction { actions, .. } => {
+                assert_eq!(
+                    actions,
+                    vec![TableOperation::Insert(vec![DfValue::from(2)])]
+                )
+            }
+            action => panic!("Unexpected action {action:?}"),
+        }
+        assert!(matches!(
+            connector.next_action(&offset, None).await,
+            Err(ReadySetError::ReplicationCaptureEnded)
+        ));
+    }
+}
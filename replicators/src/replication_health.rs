@@ -0,0 +1,143 @@
+//! Tracking whether the replicator is connected to each upstream database, and how far behind it
+//! replication is, so that adapters can tell whether they're ready to serve traffic
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use readyset_client::replication::ReplicationSourceHealth;
+
+/// Stands in for an unknown lag or event time in a [`SourceState`]
+const UNKNOWN: u64 = u64::MAX;
+
+/// The health of replication from a single upstream database, kept in atomics so that it can be
+/// updated on every replication action without locking
+#[derive(Debug)]
+struct SourceState {
+    connected: AtomicBool,
+    /// The replication lag in milliseconds, or [`UNKNOWN`]
+    lag_ms: AtomicU64,
+    /// The time we last received a replication event, in milliseconds since the Unix epoch, or
+    /// [`UNKNOWN`]
+    last_event_ms: AtomicU64,
+}
+
+impl Default for SourceState {
+    fn default() -> Self {
+        Self {
+            connected: AtomicBool::new(false),
+            lag_ms: AtomicU64::new(UNKNOWN),
+            last_event_ms: AtomicU64::new(UNKNOWN),
+        }
+    }
+}
+
+impl SourceState {
+    fn health(&self) -> ReplicationSourceHealth {
+        let known = |ms: u64| (ms != UNKNOWN).then(|| Duration::from_millis(ms));
+        ReplicationSourceHealth {
+            connected: self.connected.load(Ordering::Relaxed),
+            lag: known(self.lag_ms.load(Ordering::Relaxed)),
+            last_event: known(self.last_event_ms.load(Ordering::Relaxed))
+                .map(|since_epoch| UNIX_EPOCH + since_epoch),
+        }
+    }
+}
+
+/// A handle to the health of replication from each upstream database. Cloning a
+/// [`ReplicationHealth`] returns a handle to the same health.
+#[derive(Debug, Clone, Default)]
+pub struct ReplicationHealth {
+    sources: Arc<Mutex<BTreeMap<Option<String>, Arc<SourceState>>>>,
+}
+
+impl ReplicationHealth {
+    /// Returns the health of replication from each upstream database we've started replicating
+    /// from, keyed by the name of the additional upstream database, or `None` for the primary
+    pub fn sources(&self) -> BTreeMap<Option<String>, ReplicationSourceHealth> {
+        self.sources
+            .lock()
+            .expect("poisoned")
+            .iter()
+            .map(|(source, state)| (source.clone(), state.health()))
+            .collect()
+    }
+
+    /// Returns a handle to the health of replication from `source`, through which it's updated
+    pub(crate) fn source(&self, source: Option<&str>) -> SourceHealth {
+        SourceHealth(
+            self.sources
+                .lock()
+                .expect("poisoned")
+                .entry(source.map(str::to_owned))
+                .or_default()
+                .clone(),
+        )
+    }
+}
+
+/// A handle to the health of replication from a single upstream database, returned by
+/// [`ReplicationHealth::source`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceHealth(Arc<SourceState>);
+
+impl SourceHealth {
+    /// Record that we've just received a replication event, when replication was `lag` behind the
+    /// upstream database
+    pub(crate) fn received(&self, lag: Option<Duration>) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.0.connected.store(true, Ordering::Relaxed);
+        self.0.lag_ms.store(
+            lag.map_or(UNKNOWN, |lag| lag.as_millis() as u64),
+            Ordering::Relaxed,
+        );
+        self.0
+            .last_event_ms
+            .store(now.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Record that we aren't currently replicating from the upstream database, such as because we
+    /// haven't connected to it yet or because replication from it failed
+    pub(crate) fn disconnected(&self) {
+        self.0.connected.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_health_per_source() {
+        let health = ReplicationHealth::default();
+        let primary = health.source(None);
+        let other = health.source(Some("other"));
+        primary.disconnected();
+        other.received(Some(Duration::from_secs(3)));
+        assert_eq!(health.sources()[&None], ReplicationSourceHealth::default());
+        let other_health = health.sources()[&Some("other".to_owned())];
+        assert!(other_health.connected);
+        assert_eq!(other_health.lag, Some(Duration::from_secs(3)));
+        assert!(other_health.last_event.is_some());
+
+        primary.received(None);
+        other.disconnected();
+        assert!(health.sources()[&None].connected);
+        assert_eq!(health.sources()[&None].lag, None);
+        let other_health = health.sources()[&Some("other".to_owned())];
+        assert!(!other_health.connected);
+        assert_eq!(other_health.lag, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn handles_share_health() {
+        let health = ReplicationHealth::default();
+        health.source(None).received(Some(Duration::ZERO));
+        health.clone().source(None).disconnected();
+        assert_eq!(health.sources().len(), 1);
+        assert!(!health.sources()[&None].connected);
+    }
+}
@@ -0,0 +1,180 @@
+//! Requests to resnapshot individual tables, made with `ALTER READYSET RESNAPSHOT TABLE`
+//!
+//! A replicator that replicates a requested table stops streaming replication and takes a partial
+//! snapshot, which copies the table's rows from the upstream database again while leaving the
+//! other tables in place. The new rows replace the table's old ones in ReadySet all at once, so
+//! readers never see a partially copied table. The new copy of the table is given a later
+//! replication offset than the old one, so replication then skips the events it already includes.
+//!
+//! Requests are only completed once the table has been snapshotted, so if the snapshot fails or
+//! the replicator restarts, the table is resnapshotted again. A table that's requested again while
+//! it's being snapshotted is snapshotted once more afterwards, since the running snapshot may have
+//! started before whatever prompted the new request.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nom_sql::Relation;
+use tokio::sync::watch;
+
+use crate::table_filter::TableFilter;
+
+/// The tables that have been requested to be resnapshotted
+#[derive(Debug, Default)]
+struct Requests {
+    /// The number of requests made so far, used to number them
+    count: u64,
+    /// The number of the latest request for each table
+    tables: HashMap<Relation, u64>,
+}
+
+/// A handle to the set of tables that have been requested to be resnapshotted. Cloning a
+/// [`ResnapshotRequests`] returns a handle to the same set of tables.
+#[derive(Debug, Clone)]
+pub struct ResnapshotRequests {
+    sender: Arc<watch::Sender<Requests>>,
+    receiver: watch::Receiver<Requests>,
+}
+
+impl Default for ResnapshotRequests {
+    fn default() -> Self {
+        let (sender, receiver) = watch::channel(Requests::default());
+        Self {
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+}
+
+impl ResnapshotRequests {
+    /// Request that `table`, which must be qualified with its schema, be resnapshotted by
+    /// whichever replicator replicates it
+    pub fn request(&self, table: Relation) {
+        self.sender.send_modify(|requests| {
+            requests.count += 1;
+            requests.tables.insert(table, requests.count);
+        });
+    }
+
+    /// Returns the requested tables that are replicated according to `table_filter`, along with
+    /// the number of the latest request for each, to pass to [`Self::complete`]
+    pub(crate) fn pending(&self, table_filter: &TableFilter) -> HashMap<Relation, u64> {
+        self.receiver
+            .borrow()
+            .tables
+            .iter()
+            .filter(|(table, _)| replicates(table_filter, table))
+            .map(|(table, request)| (table.clone(), *request))
+            .collect()
+    }
+
+    /// Mark the requests returned by [`Self::pending`] as complete. Tables that have been requested
+    /// again since then stay pending.
+    pub(crate) fn complete(&self, tables: &HashMap<Relation, u64>) {
+        if tables.is_empty() {
+            return;
+        }
+        self.sender.send_modify(|requests| {
+            requests
+                .tables
+                .retain(|table, request| tables.get(table) != Some(request));
+        });
+    }
+
+    /// Wait until a table that is replicated according to `table_filter` is requested to be
+    /// resnapshotted, returning immediately if one already has been
+    pub(crate) async fn requested(&mut self, table_filter: &TableFilter) {
+        loop {
+            if self
+                .receiver
+                .borrow()
+                .tables
+                .keys()
+                .any(|table| replicates(table_filter, table))
+            {
+                return;
+            }
+            // We hold on to the sender, so this never returns an error
+            let _ = self.receiver.changed().await;
+        }
+    }
+}
+
+pub(crate) fn replicates(table_filter: &TableFilter, table: &Relation) -> bool {
+    table.schema.as_ref().map_or(false, |schema| {
+        table_filter.should_be_processed(schema.as_str(), table.name.as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nom_sql::Dialect;
+
+    use super::*;
+
+    fn table(name: &str) -> Relation {
+        Relation {
+            schema: Some("public".into()),
+            name: name.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_for_replicated_tables() {
+        let filter = TableFilter::try_new(
+            Dialect::PostgreSQL,
+            Some("public.t1, public.t2".to_string().into()),
+            None,
+            None,
+        )
+        .unwrap();
+        let mut requests = ResnapshotRequests::default();
+        requests.request(table("t3"));
+        assert!(requests.pending(&filter).is_empty());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), requests.requested(&filter))
+                .await
+                .is_err()
+        );
+
+        let handle = requests.clone();
+        let waiter = tokio::spawn(async move {
+            requests.requested(&filter).await;
+            requests.pending(&filter)
+        });
+        handle.request(table("t1"));
+        let pending = waiter.await.unwrap();
+        assert_eq!(pending.keys().collect::<Vec<_>>(), vec![&table("t1")]);
+
+        handle.complete(&pending);
+        assert_eq!(
+            handle.receiver.borrow().tables.keys().collect::<Vec<_>>(),
+            vec![&table("t3")]
+        );
+    }
+
+    #[test]
+    fn requests_made_during_snapshot_stay_pending() {
+        let filter = TableFilter::try_new(
+            Dialect::PostgreSQL,
+            Some("public.t1, public.t2".to_string().into()),
+            None,
+            None,
+        )
+        .unwrap();
+        let requests = ResnapshotRequests::default();
+        requests.request(table("t1"));
+        requests.request(table("t2"));
+        let pending = requests.pending(&filter);
+
+        // t1 is requested again while it's being snapshotted
+        requests.request(table("t1"));
+        requests.complete(&pending);
+        assert_eq!(
+            requests.pending(&filter).keys().collect::<Vec<_>>(),
+            vec![&table("t1")]
+        );
+    }
+}
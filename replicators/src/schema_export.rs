@@ -0,0 +1,778 @@
+//! Exporting the schema of each replicated table whenever replication starts or a schema change
+//! is replicated, per [`UpstreamConfig::schema_export_dir`] and
+//! [`UpstreamConfig::schema_export_url`], so that downstream data tooling can track the schema
+//! ReadySet is replicating.
+//!
+//! Each table's schema is exported as its own document, describing the rows of the table as either
+//! a JSON Schema or an Avro record schema. Both also list the table's primary key (as
+//! `primaryKey`) and unique keys (as `uniqueKeys`), and the SQL type of each column (as
+//! `sqlType`), none of which either format has a standard way of describing.
+//!
+//! [`UpstreamConfig::schema_export_dir`]: database_utils::UpstreamConfig::schema_export_dir
+//! [`UpstreamConfig::schema_export_url`]: database_utils::UpstreamConfig::schema_export_url
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use database_utils::{SchemaExportFormat, UpstreamConfig};
+use nom_sql::{
+    ColumnConstraint, ColumnSpecification, CreateTableBody, Relation, SqlType, TableKey,
+};
+use readyset_client::ReadySetHandle;
+use readyset_data::dialect::SqlEngine;
+use readyset_data::Dialect;
+use readyset_errors::{internal_err, ReadySetResult};
+use serde_json::{json, Value};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::table_filter::TableFilter;
+
+/// How long exporting the schema of the replicated tables may take before it's abandoned, until
+/// the schema next changes
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Exports the schema of the tables replicated from one upstream database. Schemas are exported by
+/// a background task, so that a slow export directory or schema registry never holds up
+/// replication.
+pub(crate) struct SchemaExporter {
+    /// Sends the table filter to export the schema of the tables replicated according to. Only the
+    /// latest request is kept, so requests made while an export is running are coalesced into one.
+    requests: watch::Sender<Option<TableFilter>>,
+}
+
+impl SchemaExporter {
+    /// Returns a [`SchemaExporter`] if `config` has somewhere to export schemas to, spawning the
+    /// task that exports the schemas of the tables in `noria`
+    pub(crate) fn new(
+        config: &UpstreamConfig,
+        mut noria: ReadySetHandle,
+        dialect: Dialect,
+    ) -> ReadySetResult<Option<Self>> {
+        if config.schema_export_dir.is_none() && config.schema_export_url.is_none() {
+            return Ok(None);
+        }
+        let mut exporter = Exporter {
+            format: config.schema_export_format,
+            dir: config.schema_export_dir.clone(),
+            registry_url: config
+                .schema_export_url
+                .as_deref()
+                .map(|url| url.trim_end_matches('/').to_owned()),
+            client: reqwest::Client::builder()
+                .timeout(EXPORT_TIMEOUT)
+                .build()
+                .map_err(|e| internal_err!("Could not build schema registry client: {e}"))?,
+            exported: None,
+        };
+
+        let (requests, mut rx) = watch::channel(None);
+        tokio::spawn(async move {
+            // Stops once the exporter is dropped
+            while rx.changed().await.is_ok() {
+                let Some(table_filter) = rx.borrow_and_update().clone() else {
+                    continue;
+                };
+                let export = exporter.export(&mut noria, &table_filter, dialect);
+                match tokio::time::timeout(EXPORT_TIMEOUT, export).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => {
+                        warn!(%error, "Could not export the schema of replicated tables")
+                    }
+                    Err(_) => warn!(
+                        timeout = ?EXPORT_TIMEOUT,
+                        "Timed out exporting the schema of replicated tables"
+                    ),
+                }
+            }
+        });
+
+        Ok(Some(Self { requests }))
+    }
+
+    /// Export the schema of each table in ReadySet that's replicated according to `table_filter`,
+    /// if it's changed since it was last exported. The export happens in the background, and any
+    /// errors are logged, since they shouldn't stop replication.
+    pub(crate) fn export(&self, table_filter: &TableFilter) {
+        self.requests.send_replace(Some(table_filter.clone()));
+    }
+}
+
+/// The state of the task exporting schemas for a [`SchemaExporter`]
+struct Exporter {
+    format: SchemaExportFormat,
+    dir: Option<PathBuf>,
+    registry_url: Option<String>,
+    client: reqwest::Client,
+    /// The schema last exported for each table, so that we only export the schemas that have
+    /// changed since, and know which tables have been dropped. `None` until the export directory
+    /// has been read.
+    exported: Option<HashMap<Relation, String>>,
+}
+
+impl Exporter {
+    async fn export(
+        &mut self,
+        noria: &mut ReadySetHandle,
+        table_filter: &TableFilter,
+        dialect: Dialect,
+    ) -> ReadySetResult<()> {
+        let mut schemas = HashMap::new();
+        for (relation, node) in noria.tables().await? {
+            let Some(schema) = relation.schema.as_deref() else {
+                continue;
+            };
+            if !table_filter.should_be_processed(schema, relation.name.as_str()) {
+                continue;
+            }
+            let table = noria.table_by_index(node).await?;
+            let Some(body) = table.schema() else {
+                continue;
+            };
+            let document = match self.format {
+                SchemaExportFormat::JsonSchema => json_schema(&relation, body, dialect),
+                SchemaExportFormat::Avro => avro_schema(&relation, body, dialect),
+            };
+            let document = serde_json::to_string_pretty(&document)
+                .map_err(|e| internal_err!("Could not serialize table schema: {e}"))?;
+            schemas.insert(relation, document);
+        }
+        self.update(schemas, table_filter).await
+    }
+
+    /// Publish each of `schemas` (the schema documents of every replicated table) that's changed
+    /// since it was last published, and remove the files of tables that are no longer replicated
+    async fn update(
+        &mut self,
+        schemas: HashMap<Relation, String>,
+        table_filter: &TableFilter,
+    ) -> ReadySetResult<()> {
+        let exported = match self.exported.take() {
+            Some(exported) => exported,
+            None => self.read_dir(table_filter).await?,
+        };
+
+        let mut changed = 0;
+        for (relation, document) in &schemas {
+            if exported.get(relation) != Some(document) {
+                if let Err(error) = self.publish(relation, document).await {
+                    // Publishing is idempotent, so just publish every changed schema next time
+                    self.exported = Some(exported);
+                    return Err(error);
+                }
+                changed += 1;
+            }
+        }
+        let dropped = exported
+            .keys()
+            .filter(|relation| !schemas.contains_key(relation))
+            .collect::<Vec<_>>();
+        if let Some(dir) = &self.dir {
+            for relation in &dropped {
+                let path = dir.join(self.file_name(relation));
+                match tokio::fs::remove_file(&path).await {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+
+        if changed > 0 || !dropped.is_empty() {
+            info!(
+                changed,
+                dropped = dropped.len(),
+                "Exported the schema of replicated tables"
+            );
+        }
+        self.exported = Some(schemas);
+        Ok(())
+    }
+
+    /// Returns the tables replicated according to `table_filter` whose schemas are in the export
+    /// directory, as if their schemas had been exported (but are out of date), so that the files
+    /// of tables dropped while we weren't replicating are removed
+    async fn read_dir(
+        &self,
+        table_filter: &TableFilter,
+    ) -> ReadySetResult<HashMap<Relation, String>> {
+        let mut exported = HashMap::new();
+        let Some(dir) = &self.dir else {
+            return Ok(exported);
+        };
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(exported),
+            Err(e) => return Err(e.into()),
+        };
+        let suffix = format!(".{}", self.extension());
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name();
+            let Some((schema, table)) = file_name
+                .to_str()
+                .and_then(|name| name.strip_suffix(&suffix))
+                .and_then(|name| name.split_once('.'))
+            else {
+                continue;
+            };
+            if table_filter.should_be_processed(schema, table) {
+                let relation = Relation {
+                    schema: Some(schema.into()),
+                    name: table.into(),
+                };
+                exported.insert(relation, String::new());
+            }
+        }
+        Ok(exported)
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.format {
+            SchemaExportFormat::JsonSchema => "json",
+            SchemaExportFormat::Avro => "avsc",
+        }
+    }
+
+    fn file_name(&self, relation: &Relation) -> String {
+        format!("{}.{}", relation.display_unquoted(), self.extension())
+    }
+
+    /// Write `document`, the schema of `relation`, to the export directory and schema registry
+    async fn publish(&self, relation: &Relation, document: &str) -> ReadySetResult<()> {
+        if let Some(dir) = &self.dir {
+            tokio::fs::create_dir_all(dir).await?;
+            // Write to a temporary file first, so readers never see a partially written schema
+            let path = dir.join(self.file_name(relation));
+            let tmp_path = path.with_extension("tmp");
+            tokio::fs::write(&tmp_path, document).await?;
+            tokio::fs::rename(&tmp_path, &path).await?;
+        }
+
+        if let Some(url) = &self.registry_url {
+            let schema_type = match self.format {
+                SchemaExportFormat::JsonSchema => "JSON",
+                SchemaExportFormat::Avro => "AVRO",
+            };
+            let subject = relation.display_unquoted().to_string();
+            self.client
+                .post(format!("{url}/subjects/{subject}/versions"))
+                .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+                .json(&json!({ "schemaType": schema_type, "schema": document }))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| {
+                    internal_err!("Could not register schema for {subject} with {url}: {e}")
+                })?;
+        }
+
+        Ok(())
+    }
+}
+
+fn sql_dialect(dialect: Dialect) -> nom_sql::Dialect {
+    match dialect.engine() {
+        SqlEngine::MySQL => nom_sql::Dialect::MySQL,
+        SqlEngine::PostgreSQL => nom_sql::Dialect::PostgreSQL,
+    }
+}
+
+/// The names of the columns in the primary key of `body`, if it has one
+fn primary_key(body: &CreateTableBody) -> Vec<&str> {
+    let inline = body
+        .fields
+        .iter()
+        .filter(|field| {
+            field
+                .constraints
+                .iter()
+                .any(|c| matches!(c, ColumnConstraint::PrimaryKey))
+        })
+        .map(|field| field.column.name.as_str());
+    let keys = body.keys.iter().flatten().flat_map(|key| match key {
+        TableKey::PrimaryKey { columns, .. } => columns.iter().map(|c| c.name.as_str()).collect(),
+        _ => vec![],
+    });
+    inline.chain(keys).collect()
+}
+
+/// The names of the columns in each unique key of `body`
+fn unique_keys(body: &CreateTableBody) -> Vec<Vec<&str>> {
+    let inline = body
+        .fields
+        .iter()
+        .filter(|field| {
+            field
+                .constraints
+                .iter()
+                .any(|c| matches!(c, ColumnConstraint::Unique))
+        })
+        .map(|field| vec![field.column.name.as_str()]);
+    let keys = body.keys.iter().flatten().filter_map(|key| match key {
+        TableKey::UniqueKey { columns, .. } => {
+            Some(columns.iter().map(|c| c.name.as_str()).collect())
+        }
+        _ => None,
+    });
+    inline.chain(keys).collect()
+}
+
+fn is_nullable(field: &ColumnSpecification, primary_key: &[&str]) -> bool {
+    !primary_key.contains(&field.column.name.as_str())
+        && !field
+            .constraints
+            .iter()
+            .any(|c| matches!(c, ColumnConstraint::NotNull))
+}
+
+/// Describe the rows of `body` as a JSON Schema
+fn json_schema(relation: &Relation, body: &CreateTableBody, dialect: Dialect) -> Value {
+    let primary_key = primary_key(body);
+    let properties = body
+        .fields
+        .iter()
+        .map(|field| {
+            let mut schema = json_schema_type(&field.sql_type);
+            if is_nullable(field, &primary_key) {
+                if let Some(ty) = schema.get_mut("type") {
+                    *ty = json!([ty.take(), "null"]);
+                }
+            }
+            schema["sqlType"] = field
+                .sql_type
+                .display(sql_dialect(dialect))
+                .to_string()
+                .into();
+            (field.column.name.to_string(), schema)
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": relation.display_unquoted().to_string(),
+        "type": "object",
+        "required": body.fields.iter().map(|f| f.column.name.as_str()).collect::<Vec<_>>(),
+        "additionalProperties": false,
+        "properties": properties,
+        "primaryKey": primary_key,
+        "uniqueKeys": unique_keys(body),
+    })
+}
+
+fn json_schema_type(sql_type: &SqlType) -> Value {
+    match sql_type {
+        SqlType::Bool => json!({ "type": "boolean" }),
+        SqlType::TinyInt(_)
+        | SqlType::UnsignedTinyInt(_)
+        | SqlType::SmallInt(_)
+        | SqlType::UnsignedSmallInt(_)
+        | SqlType::Int(_)
+        | SqlType::UnsignedInt(_)
+        | SqlType::BigInt(_)
+        | SqlType::UnsignedBigInt(_)
+        | SqlType::Serial
+        | SqlType::BigSerial => json!({ "type": "integer" }),
+        SqlType::Float
+        | SqlType::Double
+        | SqlType::Real
+        | SqlType::Numeric(_)
+        | SqlType::Decimal(..) => json!({ "type": "number" }),
+        SqlType::Date => json!({ "type": "string", "format": "date" }),
+        SqlType::Time => json!({ "type": "string", "format": "time" }),
+        SqlType::DateTime(_) | SqlType::Timestamp | SqlType::TimestampTz => {
+            json!({ "type": "string", "format": "date-time" })
+        }
+        SqlType::Uuid => json!({ "type": "string", "format": "uuid" }),
+        SqlType::Enum(variants) => json!({ "type": "string", "enum": &variants[..] }),
+        SqlType::Blob
+        | SqlType::TinyBlob
+        | SqlType::MediumBlob
+        | SqlType::LongBlob
+        | SqlType::Binary(_)
+        | SqlType::VarBinary(_)
+        | SqlType::ByteArray
+        | SqlType::Bit(_)
+        | SqlType::VarBit(_)
+        | SqlType::Geometry(_) => json!({ "type": "string", "contentEncoding": "base64" }),
+        SqlType::Json | SqlType::Jsonb => json!({}),
+        SqlType::Array(element) => json!({ "type": "array", "items": json_schema_type(element) }),
+        SqlType::Char(_)
+        | SqlType::VarChar(_)
+        | SqlType::TinyText
+        | SqlType::MediumText
+        | SqlType::LongText
+        | SqlType::Text
+        | SqlType::Citext
+        | SqlType::QuotedChar
+        | SqlType::MacAddr
+        | SqlType::Inet
+        | SqlType::Other(_) => json!({ "type": "string" }),
+    }
+}
+
+/// Returns `name` with any characters that aren't valid in an Avro name replaced by underscores
+fn avro_name(name: &str) -> String {
+    let mut avro_name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if !avro_name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        avro_name.insert(0, '_');
+    }
+    avro_name
+}
+
+/// Returns `name`, or if it's in `used`, `name` with the lowest number suffix that isn't, adding
+/// the returned name to `used`
+fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let name = if used.contains(&name) {
+        (2..)
+            .map(|n| format!("{name}_{n}"))
+            .find(|name| !used.contains(name))
+            .expect("unbounded")
+    } else {
+        name
+    };
+    used.insert(name.clone());
+    name
+}
+
+/// Describe the rows of `body` as an Avro record schema
+fn avro_schema(relation: &Relation, body: &CreateTableBody, dialect: Dialect) -> Value {
+    let primary_key = primary_key(body);
+    // Distinct column names can have the same Avro name, but field names have to be unique
+    let mut used = HashSet::new();
+    let fields = body
+        .fields
+        .iter()
+        .map(|field| {
+            let name = unique_name(avro_name(&field.column.name), &mut used);
+            let mut schema = if is_nullable(field, &primary_key) {
+                json!({
+                    "name": name,
+                    "type": ["null", avro_type(&field.sql_type)],
+                    "default": null,
+                })
+            } else {
+                json!({ "name": name, "type": avro_type(&field.sql_type) })
+            };
+            schema["sqlType"] = field
+                .sql_type
+                .display(sql_dialect(dialect))
+                .to_string()
+                .into();
+            if name != field.column.name.as_str() {
+                schema["sqlName"] = field.column.name.as_str().into();
+            }
+            schema
+        })
+        .collect::<Vec<_>>();
+
+    let mut schema = json!({
+        "type": "record",
+        "name": avro_name(&relation.name),
+        "fields": fields,
+        "primaryKey": primary_key,
+        "uniqueKeys": unique_keys(body),
+    });
+    if let Some(namespace) = &relation.schema {
+        schema["namespace"] = avro_name(namespace).into();
+    }
+    schema
+}
+
+fn avro_decimal(precision: u16, scale: u8) -> Value {
+    json!({ "type": "bytes", "logicalType": "decimal", "precision": precision, "scale": scale })
+}
+
+fn avro_type(sql_type: &SqlType) -> Value {
+    match sql_type {
+        SqlType::Bool => json!("boolean"),
+        SqlType::TinyInt(_)
+        | SqlType::UnsignedTinyInt(_)
+        | SqlType::SmallInt(_)
+        | SqlType::UnsignedSmallInt(_)
+        | SqlType::Int(_)
+        | SqlType::Serial => json!("int"),
+        SqlType::UnsignedInt(_) | SqlType::BigInt(_) | SqlType::BigSerial => json!("long"),
+        // Too large for a long
+        SqlType::UnsignedBigInt(_) => avro_decimal(20, 0),
+        SqlType::Float => json!("float"),
+        SqlType::Double | SqlType::Real => json!("double"),
+        SqlType::Numeric(Some((precision, scale))) => {
+            avro_decimal(*precision, scale.unwrap_or_default())
+        }
+        SqlType::Decimal(precision, scale) => avro_decimal((*precision).into(), *scale),
+        SqlType::Date => json!({ "type": "int", "logicalType": "date" }),
+        SqlType::Time => json!({ "type": "long", "logicalType": "time-micros" }),
+        SqlType::DateTime(_) | SqlType::Timestamp => {
+            json!({ "type": "long", "logicalType": "local-timestamp-micros" })
+        }
+        SqlType::TimestampTz => json!({ "type": "long", "logicalType": "timestamp-micros" }),
+        SqlType::Uuid => json!({ "type": "string", "logicalType": "uuid" }),
+        SqlType::Blob
+        | SqlType::TinyBlob
+        | SqlType::MediumBlob
+        | SqlType::LongBlob
+        | SqlType::Binary(_)
+        | SqlType::VarBinary(_)
+        | SqlType::ByteArray
+        | SqlType::Bit(_)
+        | SqlType::VarBit(_)
+        | SqlType::Geometry(_) => json!("bytes"),
+        SqlType::Array(element) => json!({ "type": "array", "items": avro_type(element) }),
+        // Numerics without a precision can't be represented as an Avro decimal, and Avro enum
+        // symbols are too restricted to hold every SQL enum value
+        SqlType::Numeric(None)
+        | SqlType::Enum(_)
+        | SqlType::Json
+        | SqlType::Jsonb
+        | SqlType::Char(_)
+        | SqlType::VarChar(_)
+        | SqlType::TinyText
+        | SqlType::MediumText
+        | SqlType::LongText
+        | SqlType::Text
+        | SqlType::Citext
+        | SqlType::QuotedChar
+        | SqlType::MacAddr
+        | SqlType::Inet
+        | SqlType::Other(_) => json!("string"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nom_sql::{parse_create_table, Dialect as ParserDialect};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn parse(statement: &str) -> (Relation, CreateTableBody) {
+        let stmt = parse_create_table(ParserDialect::MySQL, statement).unwrap();
+        (stmt.table, stmt.body.unwrap())
+    }
+
+    #[test]
+    fn exports_json_schema() {
+        let (relation, body) = parse(
+            "CREATE TABLE db.users (id INT NOT NULL, email VARCHAR(255), created DATETIME, \
+             PRIMARY KEY (id), UNIQUE KEY (email))",
+        );
+        let schema = json_schema(&relation, &body, Dialect::DEFAULT_MYSQL);
+        assert_eq!(schema["title"], "db.users");
+        assert_eq!(schema["required"], json!(["id", "email", "created"]));
+        assert_eq!(schema["primaryKey"], json!(["id"]));
+        assert_eq!(schema["uniqueKeys"], json!([["email"]]));
+        assert_eq!(
+            schema["properties"]["id"],
+            json!({ "type": "integer", "sqlType": "INT" })
+        );
+        assert_eq!(
+            schema["properties"]["email"],
+            json!({ "type": ["string", "null"], "sqlType": "VARCHAR(255)" })
+        );
+        assert_eq!(
+            schema["properties"]["created"],
+            json!({ "type": ["string", "null"], "format": "date-time", "sqlType": "DATETIME" })
+        );
+    }
+
+    #[test]
+    fn exports_avro() {
+        let (relation, body) =
+            parse("CREATE TABLE db.orders (id BIGINT PRIMARY KEY, `total amount` DECIMAL(10, 2))");
+        let schema = avro_schema(&relation, &body, Dialect::DEFAULT_MYSQL);
+        assert_eq!(schema["type"], "record");
+        assert_eq!(schema["name"], "orders");
+        assert_eq!(schema["namespace"], "db");
+        assert_eq!(schema["primaryKey"], json!(["id"]));
+        assert_eq!(
+            schema["fields"][0],
+            json!({ "name": "id", "type": "long", "sqlType": "BIGINT" })
+        );
+        assert_eq!(
+            schema["fields"][1],
+            json!({
+                "name": "total_amount",
+                "sqlName": "total amount",
+                "type": [
+                    "null",
+                    { "type": "bytes", "logicalType": "decimal", "precision": 10, "scale": 2 }
+                ],
+                "default": null,
+                "sqlType": "DECIMAL(10, 2)",
+            })
+        );
+    }
+
+    #[test]
+    fn avro_names() {
+        assert_eq!(avro_name("orders"), "orders");
+        assert_eq!(avro_name("order-items"), "order_items");
+        assert_eq!(avro_name("2023_events"), "_2023_events");
+    }
+
+    #[test]
+    fn avro_field_names_are_unique() {
+        let (relation, body) = parse("CREATE TABLE db.t (`a b` INT, a_b INT, `a-b` INT)");
+        let schema = avro_schema(&relation, &body, Dialect::DEFAULT_MYSQL);
+        let names = schema["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| (field["name"].clone(), field["sqlName"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (json!("a_b"), json!("a b")),
+                (json!("a_b_2"), json!("a_b")),
+                (json!("a_b_3"), json!("a-b")),
+            ]
+        );
+    }
+
+    fn exporter(dir: Option<PathBuf>, registry_url: Option<String>) -> Exporter {
+        Exporter {
+            format: SchemaExportFormat::JsonSchema,
+            dir,
+            registry_url,
+            client: reqwest::Client::new(),
+            exported: None,
+        }
+    }
+
+    fn table(name: &str) -> Relation {
+        Relation {
+            schema: Some("db".into()),
+            name: name.into(),
+        }
+    }
+
+    fn table_filter() -> TableFilter {
+        TableFilter::try_new(ParserDialect::MySQL, None, None, Some("db")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn publishes_to_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = || {
+            let mut files = std::fs::read_dir(dir.path())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect::<Vec<_>>();
+            files.sort();
+            files
+        };
+        // Left over from a table dropped while we weren't replicating, and from a table we don't
+        // replicate
+        std::fs::write(dir.path().join("db.dropped.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("other.t.json"), "{}").unwrap();
+
+        let mut exporter = exporter(Some(dir.path().into()), None);
+        let schemas = HashMap::from([
+            (table("t1"), "one".to_owned()),
+            (table("t2"), "two".to_owned()),
+        ]);
+        exporter.update(schemas, &table_filter()).await.unwrap();
+        assert_eq!(files(), ["db.t1.json", "db.t2.json", "other.t.json"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("db.t1.json")).unwrap(),
+            "one"
+        );
+
+        let schemas = HashMap::from([(table("t1"), "changed".to_owned())]);
+        exporter.update(schemas, &table_filter()).await.unwrap();
+        assert_eq!(files(), ["db.t1.json", "other.t.json"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("db.t1.json")).unwrap(),
+            "changed"
+        );
+    }
+
+    /// Serve a schema registry on a local port which replies to every request with `status`,
+    /// returning its URL along with the request line and JSON body of each request it receives
+    async fn registry(status: &'static str) -> (String, mpsc::UnboundedReceiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                let (head_len, content_length) = loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                        let content_length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .map_or(0, |len| len.trim().parse().unwrap());
+                        break (end + 4, content_length);
+                    }
+                };
+                while request.len() < head_len + content_length {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request_line = String::from_utf8_lossy(&request[..head_len])
+                    .lines()
+                    .next()
+                    .unwrap()
+                    .to_owned();
+                let body = serde_json::from_slice(&request[head_len..]).unwrap();
+                let _ = tx.send((request_line, body));
+                stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn publishes_to_registry() {
+        let (url, mut requests) = registry("200 OK").await;
+        let mut exporter = exporter(None, Some(url));
+        let schemas = HashMap::from([(table("t1"), r#"{"type":"object"}"#.to_owned())]);
+        exporter
+            .update(schemas.clone(), &table_filter())
+            .await
+            .unwrap();
+        assert_eq!(
+            requests.recv().await.unwrap(),
+            (
+                "POST /subjects/db.t1/versions HTTP/1.1".to_owned(),
+                json!({ "schemaType": "JSON", "schema": r#"{"type":"object"}"# })
+            )
+        );
+
+        // Unchanged schemas aren't registered again
+        exporter.update(schemas, &table_filter()).await.unwrap();
+        assert!(requests.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn retries_failed_registrations() {
+        let (url, mut requests) = registry("500 Internal Server Error").await;
+        let mut exporter = exporter(None, Some(url));
+        let schemas = HashMap::from([(table("t1"), "{}".to_owned())]);
+        exporter
+            .update(schemas.clone(), &table_filter())
+            .await
+            .unwrap_err();
+        exporter.update(schemas, &table_filter()).await.unwrap_err();
+        assert!(requests.recv().await.is_some());
+        assert!(requests.recv().await.is_some());
+    }
+}
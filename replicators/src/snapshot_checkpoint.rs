@@ -0,0 +1,272 @@
+//! Persisting the progress of chunked snapshots, per [`UpstreamConfig::snapshot_checkpoint_dir`]
+//!
+//! When tables are snapshotted in chunks ordered by their primary key, a checkpoint is written
+//! after each chunk recording the primary key of the last row written to ReadySet, along with the
+//! replication offset the table's snapshot was started at. If the snapshot is interrupted, the
+//! next snapshot of the table keeps the rows that were already written and only reads the rows
+//! after that key, before setting the table's replication offset to the one in the checkpoint.
+//!
+//! The rows read after resuming see a later state of the upstream table than the rows read
+//! before, so replication then replays some changes that those rows already include. Since
+//! writes to tables with a primary key are applied by key, replaying those changes converges on
+//! the same state as if the whole table had been read at once.
+//!
+//! [`UpstreamConfig::snapshot_checkpoint_dir`]: database_utils::UpstreamConfig::snapshot_checkpoint_dir
+
+use std::fmt::Write;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use nom_sql::Relation;
+use readyset_client::replication::ReplicationOffset;
+use readyset_data::DfValue;
+use readyset_errors::{internal_err, ReadySetResult};
+use serde::{Deserialize, Serialize};
+
+use crate::table_filter::TableFilter;
+
+/// The progress of the chunked snapshot of a single table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SnapshotCheckpoint {
+    /// The replication offset the table's snapshot was started at
+    pub(crate) offset: ReplicationOffset,
+    /// The primary key of the last row written to ReadySet
+    pub(crate) last_key: Vec<DfValue>,
+    /// The number of rows written to ReadySet so far
+    pub(crate) rows: usize,
+    /// The number of chunks written to ReadySet so far
+    pub(crate) chunks: usize,
+}
+
+/// A directory of [`SnapshotCheckpoint`]s, with one file per table
+#[derive(Debug, Clone)]
+pub(crate) struct SnapshotCheckpoints {
+    dir: PathBuf,
+}
+
+impl SnapshotCheckpoints {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The path of the file the checkpoint for `table` is stored in. Any characters in the table's
+    /// schema and name that might not be valid in a file name are escaped.
+    fn path(&self, table: &Relation) -> PathBuf {
+        let mut file_name = String::new();
+        for part in table.schema.iter().chain([&table.name]) {
+            for byte in part.as_bytes() {
+                if byte.is_ascii_alphanumeric() || *byte == b'_' || *byte == b'-' {
+                    file_name.push(*byte as char);
+                } else {
+                    let _ = write!(file_name, "%{byte:02x}");
+                }
+            }
+            file_name.push('.');
+        }
+        file_name.push_str("json");
+        self.dir.join(file_name)
+    }
+
+    /// The table whose checkpoint is stored in the file named `file_name`, as returned by
+    /// [`Self::path`], or `None` if `file_name` isn't the name of a checkpoint file
+    fn table(file_name: &str) -> Option<Relation> {
+        let unescape = |part: &str| {
+            let mut bytes = Vec::with_capacity(part.len());
+            let mut rest = part.as_bytes();
+            while let Some((byte, tail)) = rest.split_first() {
+                if *byte == b'%' {
+                    let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                    bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                    rest = &tail[2..];
+                } else {
+                    bytes.push(*byte);
+                    rest = tail;
+                }
+            }
+            String::from_utf8(bytes).ok()
+        };
+
+        let mut parts = file_name.strip_suffix(".json")?.split('.');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(name), None, _) => Some(Relation {
+                schema: None,
+                name: unescape(name)?.into(),
+            }),
+            (Some(schema), Some(name), None) => Some(Relation {
+                schema: Some(unescape(schema)?.into()),
+                name: unescape(name)?.into(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Load the checkpoint for `table`, if there is one
+    pub(crate) async fn load(
+        &self,
+        table: &Relation,
+    ) -> ReadySetResult<Option<SnapshotCheckpoint>> {
+        let path = self.path(table);
+        let contents = match tokio::fs::read(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&contents)
+            .map(Some)
+            .map_err(|e| internal_err!("Invalid snapshot checkpoint {}: {e}", path.display()))
+    }
+
+    /// Persist `checkpoint` as the checkpoint for `table`, replacing any previous one
+    pub(crate) async fn save(
+        &self,
+        table: &Relation,
+        checkpoint: &SnapshotCheckpoint,
+    ) -> ReadySetResult<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let contents = serde_json::to_vec(checkpoint)
+            .map_err(|e| internal_err!("Could not serialize snapshot checkpoint: {e}"))?;
+
+        // Write to a temporary file first, so that we never leave a partially written checkpoint
+        // behind
+        let path = self.path(table);
+        let tmp_path = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, contents).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+
+    /// Remove the checkpoint for `table`, if there is one
+    pub(crate) async fn remove(&self, table: &Relation) -> ReadySetResult<()> {
+        match tokio::fs::remove_file(self.path(table)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Remove the checkpoints for every table that `table_filter` selects for replication.
+    ///
+    /// The directory may be shared with other replicators (such as those for additional upstream
+    /// databases), so checkpoints for any other tables, and any files that aren't checkpoints,
+    /// are left alone.
+    pub(crate) async fn clear(&self, table_filter: &TableFilter) -> ReadySetResult<()> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let Some(table) = entry.file_name().to_str().and_then(Self::table) else {
+                continue;
+            };
+            let Some(schema) = &table.schema else {
+                continue;
+            };
+            if table_filter.should_be_processed(schema.as_str(), table.name.as_str()) {
+                self.remove(&table).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(schema: &str, name: &str) -> Relation {
+        Relation {
+            schema: Some(schema.into()),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn path_escapes_table_name() {
+        let checkpoints = SnapshotCheckpoints::new("/checkpoints".into());
+        assert_eq!(
+            checkpoints.path(&table("public", "users")),
+            PathBuf::from("/checkpoints/public.users.json")
+        );
+        assert_eq!(
+            checkpoints.path(&table("a.b", "../c")),
+            PathBuf::from("/checkpoints/a%2eb.%2e%2e%2fc.json")
+        );
+    }
+
+    #[tokio::test]
+    async fn save_load_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoints = SnapshotCheckpoints::new(dir.path().join("checkpoints"));
+        let users = table("public", "users");
+        assert_eq!(checkpoints.load(&users).await.unwrap(), None);
+
+        let checkpoint = SnapshotCheckpoint {
+            offset: ReplicationOffset {
+                offset: 42,
+                replication_log_name: "binlog.000001".into(),
+                gtid_set: None,
+            },
+            last_key: vec![DfValue::from(7), DfValue::from("a")],
+            rows: 100,
+            chunks: 2,
+        };
+        checkpoints.save(&users, &checkpoint).await.unwrap();
+        assert_eq!(checkpoints.load(&users).await.unwrap(), Some(checkpoint));
+        assert_eq!(
+            checkpoints.load(&table("public", "posts")).await.unwrap(),
+            None
+        );
+
+        checkpoints.remove(&users).await.unwrap();
+        assert_eq!(checkpoints.load(&users).await.unwrap(), None);
+        checkpoints.remove(&users).await.unwrap();
+    }
+
+    #[test]
+    fn table_from_path() {
+        let checkpoints = SnapshotCheckpoints::new("/checkpoints".into());
+        for table in [table("public", "users"), table("a.b", "../c%")] {
+            let path = checkpoints.path(&table);
+            let file_name = path.file_name().unwrap().to_str().unwrap();
+            assert_eq!(SnapshotCheckpoints::table(file_name), Some(table));
+        }
+        assert_eq!(SnapshotCheckpoints::table("public.users.json.tmp"), None);
+        assert_eq!(SnapshotCheckpoints::table("a.b.c.json"), None);
+        assert_eq!(SnapshotCheckpoints::table("public.u%zz.json"), None);
+    }
+
+    #[tokio::test]
+    async fn clear_only_removes_filtered_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoints = SnapshotCheckpoints::new(dir.path().to_owned());
+        let checkpoint = SnapshotCheckpoint {
+            offset: ReplicationOffset {
+                offset: 42,
+                replication_log_name: "binlog.000001".into(),
+                gtid_set: None,
+            },
+            last_key: vec![DfValue::from(7)],
+            rows: 100,
+            chunks: 2,
+        };
+        let ours = table("ours", "t");
+        let theirs = table("theirs", "t");
+        checkpoints.save(&ours, &checkpoint).await.unwrap();
+        checkpoints.save(&theirs, &checkpoint).await.unwrap();
+        let other_file = dir.path().join("other.txt");
+        tokio::fs::write(&other_file, "").await.unwrap();
+
+        let filter = TableFilter::try_new(
+            nom_sql::Dialect::MySQL,
+            Some("ours.*".to_string().into()),
+            None,
+            None,
+        )
+        .unwrap();
+        checkpoints.clear(&filter).await.unwrap();
+
+        assert_eq!(checkpoints.load(&ours).await.unwrap(), None);
+        assert_eq!(checkpoints.load(&theirs).await.unwrap(), Some(checkpoint));
+        assert!(other_file.exists());
+    }
+}
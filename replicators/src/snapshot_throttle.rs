@@ -0,0 +1,141 @@
+//! Rate limiting of snapshot reads, per [`UpstreamConfig::snapshot_max_rows_per_sec`] and
+//! [`UpstreamConfig::snapshot_max_bytes_per_sec`]
+//!
+//! A single [`SnapshotThrottle`] is shared by every table being snapshotted at the same time, so
+//! the limits apply to the snapshot as a whole rather than to each table. Rows are read from the
+//! upstream database as a stream, so waiting before writing a batch of rows to ReadySet also slows
+//! down reading them from the upstream database.
+//!
+//! [`UpstreamConfig::snapshot_max_rows_per_sec`]: database_utils::UpstreamConfig::snapshot_max_rows_per_sec
+//! [`UpstreamConfig::snapshot_max_bytes_per_sec`]: database_utils::UpstreamConfig::snapshot_max_bytes_per_sec
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use readyset_data::DfValue;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// The number of bytes a value that isn't stored out of line is estimated to take up
+const INLINE_VALUE_SIZE: u64 = 8;
+
+/// Limits the rate at which rows are snapshotted. Cloning a [`SnapshotThrottle`] returns a handle
+/// to the same limits.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SnapshotThrottle {
+    rows: Option<Arc<RateLimiter>>,
+    bytes: Option<Arc<RateLimiter>>,
+}
+
+impl SnapshotThrottle {
+    /// Create a new [`SnapshotThrottle`] allowing up to `rows_per_sec` rows and `bytes_per_sec`
+    /// bytes of rows to be snapshotted each second, if set
+    pub(crate) fn new(rows_per_sec: Option<u64>, bytes_per_sec: Option<u64>) -> Self {
+        Self {
+            rows: rows_per_sec.map(|rate| Arc::new(RateLimiter::new(rate))),
+            bytes: bytes_per_sec.map(|rate| Arc::new(RateLimiter::new(rate))),
+        }
+    }
+
+    /// Wait until `rows` can be snapshotted without exceeding the limits
+    pub(crate) async fn acquire(&self, rows: &[Vec<DfValue>]) {
+        if let Some(limiter) = &self.rows {
+            limiter.acquire(rows.len() as u64).await;
+        }
+        if let Some(limiter) = &self.bytes {
+            limiter
+                .acquire(rows.iter().map(|row| row_size(row)).sum())
+                .await;
+        }
+    }
+}
+
+/// Estimate the number of bytes `row` took up in the upstream database
+fn row_size(row: &[DfValue]) -> u64 {
+    row.iter()
+        .map(|value| match value {
+            DfValue::Text(t) => t.as_bytes().len() as u64,
+            DfValue::TinyText(t) => t.as_bytes().len() as u64,
+            DfValue::ByteArray(b) => b.len() as u64,
+            DfValue::BitVector(b) => (b.len() as u64 + 7) / 8,
+            _ => INLINE_VALUE_SIZE,
+        })
+        .sum()
+}
+
+/// A token bucket which refills at `rate` tokens per second, holding up to one second's worth of
+/// tokens
+#[derive(Debug)]
+struct RateLimiter {
+    rate: f64,
+    /// The number of tokens available, which is negative if tokens have been taken on credit, and
+    /// the time it was last refilled at
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    fn new(rate: u64) -> Self {
+        let rate = rate.max(1) as f64;
+        Self {
+            rate,
+            state: Mutex::new((rate, Instant::now())),
+        }
+    }
+
+    /// Take `amount` tokens, waiting until the bucket has refilled enough to pay for them.
+    ///
+    /// Requests for more tokens than the bucket holds are allowed, and paid for by waiting. The
+    /// lock is held while waiting, so that concurrent requests are served in order.
+    async fn acquire(&self, amount: u64) {
+        let mut state = self.state.lock().await;
+        let (available, last_refill) = &mut *state;
+        let now = Instant::now();
+        *available = (*available + now.duration_since(*last_refill).as_secs_f64() * self.rate)
+            .min(self.rate);
+        *last_refill = now;
+
+        *available -= amount as f64;
+        if *available < 0.0 {
+            tokio::time::sleep(Duration::from_secs_f64(-*available / self.rate)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_tokens() {
+        let limiter = RateLimiter::new(100);
+        let start = Instant::now();
+        limiter.acquire(100).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        limiter.acquire(50).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(500));
+
+        limiter.acquire(200).await;
+        assert_eq!(start.elapsed(), Duration::from_millis(2500));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_by_default() {
+        let throttle = SnapshotThrottle::default();
+        let start = Instant::now();
+        throttle.acquire(&vec![vec![DfValue::from(1)]; 1000]).await;
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn estimates_row_size() {
+        assert_eq!(
+            row_size(&[
+                DfValue::from(1),
+                DfValue::from("a".repeat(100)),
+                DfValue::None
+            ]),
+            116
+        );
+    }
+}
@@ -10,7 +10,8 @@ use readyset_util::redacted::RedactedString;
 /// list of tables that we explicitly want to filter out of replication.
 /// Tables may be filtered from replication in 2 ways:
 /// 1. All tables will be filtered other than the ones provided to the option --replication_tables,
-///    if it is used
+///    if it is used, and the ones matching the option --replication-tables-ignore will always be
+///    filtered
 /// 2. If we encounter a unrecoverable failure in replication for a table, we can filter out the
 ///    table to keep the process running without that table, which is better than being stuck until
 ///    we fix why that table isn't replicating.
@@ -36,6 +37,106 @@ pub(crate) struct TableFilter {
     /// Any other valid tables will be replicated, where a valid table is either one of the tables
     /// in `explicitly_replicated`, or all tables if that is empty.
     replication_denied: BTreeMap<SqlIdentifier, ReplicateTableSpec>,
+    /// Glob patterns from the --replication-tables option. Tables matching any of these are
+    /// replicated, as if they were in `explicitly_replicated`, unless they are denied.
+    replicated_patterns: Vec<TablePattern>,
+    /// Glob patterns from the --replication-tables-ignore option. Tables matching any of these are
+    /// never replicated.
+    ignored_patterns: Vec<TablePattern>,
+}
+
+/// A `schema.table` pattern, where both the schema and the table may contain the glob wildcards
+/// `*` (any sequence of characters) and `?` (any single character)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TablePattern {
+    schema: String,
+    table: String,
+}
+
+impl TablePattern {
+    /// Parse a single pattern, using `default_schema` if it doesn't specify a schema
+    fn parse(pattern: &str, default_schema: Option<&SqlIdentifier>) -> ReadySetResult<Self> {
+        let unquote = |s: &str| {
+            s.trim()
+                .trim_matches(|c: char| c == '`' || c == '"')
+                .to_owned()
+        };
+        let (schema, table) = match pattern.split_once('.') {
+            Some((schema, table)) => (unquote(schema), unquote(table)),
+            None => (
+                default_schema
+                    .ok_or_else(|| {
+                        ReadySetError::ReplicationFailed(format!(
+                            "No database and no default database for table {pattern}"
+                        ))
+                    })?
+                    .to_string(),
+                unquote(pattern),
+            ),
+        };
+
+        if schema.is_empty() || table.is_empty() {
+            return Err(ReadySetError::ReplicationFailed(format!(
+                "Invalid table pattern {pattern}"
+            )));
+        }
+
+        Ok(TablePattern { schema, table })
+    }
+
+    /// Returns true if this pattern contains any wildcards other than a plain `*` for the table,
+    /// which the table list parser supports on its own
+    fn is_glob(&self) -> bool {
+        let is_wildcard = |c: char| c == '*' || c == '?';
+        self.schema.contains(is_wildcard) || (self.table != "*" && self.table.contains(is_wildcard))
+    }
+
+    fn matches(&self, schema: &str, table: &str) -> bool {
+        glob_match(self.schema.as_bytes(), schema.as_bytes())
+            && glob_match(self.table.as_bytes(), table.as_bytes())
+    }
+}
+
+/// Match `s` against the glob `pattern`, where `*` matches any sequence of bytes and `?` matches
+/// any single byte
+fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // The position of the last `*` in the pattern, and the position in `s` it was matched at, to
+    // backtrack to if the rest of the pattern fails to match
+    let mut backtrack = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    i = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Parse a comma-separated list of [`TablePattern`]s
+fn parse_patterns(
+    list: &str,
+    default_schema: Option<&SqlIdentifier>,
+) -> ReadySetResult<Vec<TablePattern>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|pattern| !pattern.is_empty())
+        .map(|pattern| TablePattern::parse(pattern, default_schema))
+        .collect()
 }
 
 #[derive(Debug, Clone)]
@@ -92,10 +193,16 @@ impl TableFilter {
     pub(crate) fn try_new(
         dialect: Dialect,
         filter_table_list: Option<RedactedString>,
+        ignore_table_list: Option<RedactedString>,
         default_schema: Option<&str>,
     ) -> ReadySetResult<TableFilter> {
         let default_schema = default_schema.map(SqlIdentifier::from);
 
+        let ignored_patterns = match ignore_table_list {
+            Some(list) => parse_patterns(list.as_str(), default_schema.as_ref())?,
+            None => vec![],
+        };
+
         let mut schemas = BTreeMap::new();
 
         let filtered = match filter_table_list {
@@ -107,12 +214,17 @@ impl TableFilter {
                         return Ok(TableFilter {
                             explicitly_replicated: schemas,
                             replication_denied: BTreeMap::new(),
+                            replicated_patterns: vec![],
+                            ignored_patterns,
                         });
                     }
                     None => {
                         // We will learn what the tables are by `update_table_list` at snapshot
                         // time since `for_all_schemas` is true.
-                        return Ok(Self::for_all_tables());
+                        return Ok(TableFilter {
+                            ignored_patterns,
+                            ..Self::for_all_tables()
+                        });
                     }
                 };
             }
@@ -120,18 +232,41 @@ impl TableFilter {
         };
 
         if filtered.as_str() == "*.*" {
-            return Ok(Self::for_all_tables());
+            return Ok(TableFilter {
+                ignored_patterns,
+                ..Self::for_all_tables()
+            });
         }
 
-        let filter_list =
-            match replicator_table_list(dialect)(LocatedSpan::new(filtered.as_bytes())) {
-                Ok((rem, tables)) if rem.is_empty() => tables,
-                _ => {
-                    return Err(ReadySetError::ReplicationFailed(
-                        "Unable to parse filtered tables list".to_string(),
-                    ))
-                }
-            };
+        // Glob patterns are matched against every table we see, rather than being parsed into
+        // the list of explicitly replicated tables
+        let mut replicated_patterns = vec![];
+        let mut tables = vec![];
+        for entry in filtered.as_str().split(',').map(str::trim) {
+            match TablePattern::parse(entry, default_schema.as_ref()) {
+                Ok(pattern) if pattern.is_glob() => replicated_patterns.push(pattern),
+                _ => tables.push(entry),
+            }
+        }
+        if tables.is_empty() {
+            return Ok(TableFilter {
+                explicitly_replicated: schemas,
+                replication_denied: BTreeMap::new(),
+                replicated_patterns,
+                ignored_patterns,
+            });
+        }
+        let tables = tables.join(",");
+
+        let filter_list = match replicator_table_list(dialect)(LocatedSpan::new(tables.as_bytes()))
+        {
+            Ok((rem, tables)) if rem.is_empty() => tables,
+            _ => {
+                return Err(ReadySetError::ReplicationFailed(
+                    "Unable to parse filtered tables list".to_string(),
+                ))
+            }
+        };
 
         for table in filter_list {
             let table_name = table.name;
@@ -158,14 +293,18 @@ impl TableFilter {
         Ok(TableFilter {
             explicitly_replicated: schemas,
             replication_denied: BTreeMap::new(),
+            replicated_patterns,
+            ignored_patterns,
         })
     }
 
     /// Create a new filter that will pass all tables
-    fn for_all_tables() -> Self {
+    pub(crate) fn for_all_tables() -> Self {
         Self {
             explicitly_replicated: BTreeMap::new(),
             replication_denied: BTreeMap::new(),
+            replicated_patterns: vec![],
+            ignored_patterns: vec![],
         }
     }
 
@@ -186,12 +325,33 @@ impl TableFilter {
     /// Check if a given table should be processed
     pub(crate) fn should_be_processed<Q1, Q2>(&self, schema: &Q1, table: &Q2) -> bool
     where
-        Q1: Ord + ?Sized,
-        Q2: Ord + ?Sized,
+        Q1: Ord + AsRef<str> + ?Sized,
+        Q2: Ord + AsRef<str> + ?Sized,
         SqlIdentifier: Borrow<Q1> + Borrow<Q2>,
     {
-        self.explicitly_replicated.is_empty() && !self.is_denied(schema, table)
+        if self.is_ignored(schema.as_ref(), table.as_ref()) {
+            return false;
+        }
+
+        self.explicitly_replicated.is_empty()
+            && self.replicated_patterns.is_empty()
+            && !self.is_denied(schema, table)
             || self.is_explicitly_replicated(schema, table)
+            || self.matches_replicated_pattern(schema.as_ref(), table.as_ref())
+                && !self.is_denied(schema, table)
+    }
+
+    /// Returns true if the table matches one of the patterns from --replication-tables-ignore
+    fn is_ignored(&self, schema: &str, table: &str) -> bool {
+        self.ignored_patterns
+            .iter()
+            .any(|pattern| pattern.matches(schema, table))
+    }
+
+    fn matches_replicated_pattern(&self, schema: &str, table: &str) -> bool {
+        self.replicated_patterns
+            .iter()
+            .any(|pattern| pattern.matches(schema, table))
     }
 
     pub(crate) fn is_explicitly_replicated<Q1, Q2>(&self, schema: &Q1, table: &Q2) -> bool
@@ -241,7 +401,8 @@ mod tests {
 
     #[test]
     fn empty_list() {
-        let filter = TableFilter::try_new(nom_sql::Dialect::MySQL, None, Some("noria")).unwrap();
+        let filter =
+            TableFilter::try_new(nom_sql::Dialect::MySQL, None, None, Some("noria")).unwrap();
         // By default should only allow all tables from the default schema
         assert!(filter.should_be_processed("noria", "table"));
         assert!(!filter.should_be_processed("readyset", "table"));
@@ -252,6 +413,7 @@ mod tests {
         let filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             Some("*.*".to_string().into()),
+            None,
             Some("noria"),
         )
         .unwrap();
@@ -261,7 +423,7 @@ mod tests {
 
     #[test]
     fn all_schemas_implicit() {
-        let filter = TableFilter::try_new(nom_sql::Dialect::MySQL, None, None).unwrap();
+        let filter = TableFilter::try_new(nom_sql::Dialect::MySQL, None, None, None).unwrap();
         assert!(filter.should_be_processed("noria", "table"));
         assert!(filter.should_be_processed("readyset", "table"));
     }
@@ -271,6 +433,7 @@ mod tests {
         let filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             Some("t1,t2,t3".to_string().into()),
+            None,
             Some("noria"),
         )
         .unwrap();
@@ -287,6 +450,7 @@ mod tests {
         let filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             Some("t1,noria.t2,readyset.t4,t3".to_string().into()),
+            None,
             Some("noria"),
         )
         .unwrap();
@@ -303,6 +467,7 @@ mod tests {
         let filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             Some("noria.*, readyset.t4, t3".to_string().into()),
+            None,
             Some("noria"),
         )
         .unwrap();
@@ -320,6 +485,7 @@ mod tests {
         let mut filter = TableFilter::try_new(
             nom_sql::Dialect::MySQL,
             Some("noria.*, readyset.t4, t3".to_string().into()),
+            None,
             Some("noria"),
         )
         .unwrap();
@@ -336,4 +502,70 @@ mod tests {
         filter.deny_replication("readyset", "t4");
         assert!(!filter.should_be_processed("readyset", "t4"));
     }
+
+    #[test]
+    fn glob_patterns() {
+        let filter = TableFilter::try_new(
+            nom_sql::Dialect::MySQL,
+            Some("noria.t_*, app?.users, t3".to_string().into()),
+            None,
+            Some("noria"),
+        )
+        .unwrap();
+        assert!(filter.should_be_processed("noria", "t_1"));
+        assert!(filter.should_be_processed("noria", "t_"));
+        assert!(!filter.should_be_processed("noria", "t1"));
+        assert!(filter.should_be_processed("noria", "t3"));
+        assert!(filter.should_be_processed("app1", "users"));
+        assert!(!filter.should_be_processed("app", "users"));
+        assert!(!filter.should_be_processed("app1", "posts"));
+    }
+
+    #[test]
+    fn ignored_patterns() {
+        let filter = TableFilter::try_new(
+            nom_sql::Dialect::MySQL,
+            Some("noria.*, readyset.*".to_string().into()),
+            Some("*_archive, readyset.*".to_string().into()),
+            Some("noria"),
+        )
+        .unwrap();
+        assert!(filter.should_be_processed("noria", "t1"));
+        assert!(!filter.should_be_processed("noria", "t1_archive"));
+        assert!(!filter.should_be_processed("readyset", "t1"));
+
+        let mut filter = TableFilter::try_new(
+            nom_sql::Dialect::MySQL,
+            None,
+            Some("*.tmp_*".to_string().into()),
+            None,
+        )
+        .unwrap();
+        assert!(filter.should_be_processed("noria", "t1"));
+        assert!(!filter.should_be_processed("noria", "tmp_t1"));
+
+        filter = TableFilter::try_new(
+            nom_sql::Dialect::MySQL,
+            Some("noria.t*".to_string().into()),
+            None,
+            Some("noria"),
+        )
+        .unwrap();
+        assert!(filter.should_be_processed("noria", "t1"));
+        filter.deny_replication("noria", "t1");
+        assert!(!filter.should_be_processed("noria", "t1"));
+        assert!(filter.should_be_processed("noria", "t2"));
+    }
+
+    #[test]
+    fn glob_match() {
+        use super::glob_match;
+
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"a*b*c", b"aXbYbc"));
+        assert!(glob_match(b"a?c", b"abc"));
+        assert!(!glob_match(b"a?c", b"ac"));
+        assert!(!glob_match(b"a*b", b"aXbc"));
+        assert!(glob_match(b"**", b"abc"));
+    }
 }
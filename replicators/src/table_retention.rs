@@ -0,0 +1,109 @@
+//! Expiring old rows from append-only replicated tables, per [`UpstreamConfig::table_retention`]
+//!
+//! Inserts into these tables are replicated as usual, but rather than keeping every row the
+//! upstream database keeps, ReadySet periodically deletes the rows whose timestamp column is older
+//! than the table's retention period, so that the base table doesn't grow without bound.
+//!
+//! Values of columns with a time zone (such as Postgres `timestamptz`) are compared against the
+//! current time as is, while values without one (such as MySQL `DATETIME`, or Postgres
+//! `timestamp`) are taken to be in the time zone of the upstream database's sessions, which is
+//! what `NOW()` returns them in.
+//!
+//! [`UpstreamConfig::table_retention`]: database_utils::UpstreamConfig::table_retention
+
+use std::time::Duration;
+
+use database_utils::TableRetention;
+use nom_sql::{Relation, SqlType};
+use readyset_client::ReadySetHandle;
+use readyset_data::DfValue;
+use readyset_errors::{invalid_err, ReadySetResult};
+use tracing::{debug, warn};
+
+use crate::table_filter::TableFilter;
+use crate::time_zone::UpstreamTimeZone;
+
+/// How often to delete expired rows
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Periodically delete the rows of each of `tables` that are older than its retention period from
+/// ReadySet, skipping tables that aren't replicated according to `table_filter`. Timestamps
+/// without a time zone are taken to be in `time_zone`. Never returns.
+pub(crate) async fn expire_rows(
+    mut noria: ReadySetHandle,
+    tables: Vec<TableRetention>,
+    table_filter: &TableFilter,
+    time_zone: UpstreamTimeZone,
+) {
+    let tables = tables
+        .into_iter()
+        .filter_map(|retention| {
+            let (schema, name) = retention.table.split_once('.')?;
+            if !table_filter.should_be_processed(schema, name) {
+                return None;
+            }
+            Some((
+                Relation {
+                    schema: Some(schema.into()),
+                    name: name.into(),
+                },
+                retention.column,
+                retention.retention,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    if tables.is_empty() {
+        return futures::future::pending().await;
+    }
+
+    let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        for (table, column, retention) in &tables {
+            if let Err(error) = expire_table(&mut noria, table, column, *retention, time_zone).await
+            {
+                warn!(%error, %table, "Could not delete expired rows");
+            }
+        }
+    }
+}
+
+/// Delete the rows of `table` whose value in `column` is older than `retention`
+async fn expire_table(
+    noria: &mut ReadySetHandle,
+    table: &Relation,
+    column: &str,
+    retention: Duration,
+    time_zone: UpstreamTimeZone,
+) -> ReadySetResult<()> {
+    let mut mutator = noria.table(table.clone()).await?;
+    let column_index = mutator
+        .columns()
+        .iter()
+        .position(|c| c == column)
+        .ok_or_else(|| invalid_err!("Column {column} not found in table {table}"))?;
+    let has_time_zone = mutator.schema().map_or(false, |schema| {
+        schema
+            .fields
+            .iter()
+            .any(|field| field.column.name == column && field.sql_type == SqlType::TimestampTz)
+    });
+
+    let cutoff = chrono::Utc::now().naive_utc()
+        - chrono::Duration::from_std(retention)
+            .map_err(|_| invalid_err!("Retention period for table {table} is too long"))?;
+    // Timestamps with a time zone compare by the instant they represent, which a timestamp without
+    // one is taken to be in UTC for, so only the latter have to be converted
+    let cutoff = if has_time_zone {
+        cutoff
+    } else {
+        time_zone.localize(&cutoff)
+    };
+
+    debug!(%table, %column, %cutoff, "Deleting expired rows");
+    mutator
+        .delete_expired(column_index, DfValue::from(cutoff))
+        .await
+}
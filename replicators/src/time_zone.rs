@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use chrono::{FixedOffset, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use mysql::prelude::Queryable;
+use tracing::warn;
+use {mysql_async as mysql, tokio_postgres as pgsql};
+
+/// The time zone of the sessions of an upstream database, which the current date and time are
+/// given in, and which MySQL converts `TIMESTAMP` values to (from UTC, which they're stored in)
+/// whenever the server returns them.
+///
+/// MySQL rows events log `TIMESTAMP` values as the number of seconds since the UNIX epoch, so we
+/// have to do the same conversion for replicated values to match the values we read when
+/// snapshotting.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UpstreamTimeZone {
+    /// A fixed offset from UTC, such as `+00:00`
+    Fixed(FixedOffset),
+    /// A named time zone from the time zone database, such as `Europe/Berlin`
+    Named(Tz),
+}
+
+impl Default for UpstreamTimeZone {
+    fn default() -> Self {
+        UpstreamTimeZone::Fixed(FixedOffset::east(0))
+    }
+}
+
+impl UpstreamTimeZone {
+    /// Fetch the time zone of the session of `connection`, which is the default time zone of all
+    /// the sessions we connect to the upstream with.
+    ///
+    /// If the time zone (or, for `SYSTEM`, the time zone of the server's host) doesn't name a
+    /// time zone we know about, we fall back to its current offset from UTC, which won't follow
+    /// daylight saving time transitions.
+    pub(crate) async fn fetch_mysql(connection: &mut mysql::Conn) -> mysql::Result<Self> {
+        let (time_zone, system_time_zone, offset): (String, String, i32) = connection
+            .query_first(
+                "SELECT @@session.time_zone, @@system_time_zone, \
+                 TIMESTAMPDIFF(SECOND, UTC_TIMESTAMP(), NOW())",
+            )
+            .await?
+            .ok_or_else(|| "Unable to determine the time zone of the upstream".to_string())?;
+
+        let time_zone = if time_zone.eq_ignore_ascii_case("SYSTEM") {
+            system_time_zone
+        } else {
+            time_zone
+        };
+        Ok(Self::parse_or_offset(&time_zone, offset))
+    }
+
+    /// Fetch the `TimeZone` setting of the session of `client`, which is the default time zone of
+    /// all the sessions we connect to the upstream with.
+    ///
+    /// As for MySQL, if the time zone isn't one we know about (such as a POSIX-style time zone
+    /// specification), we fall back to its current offset from UTC.
+    pub(crate) async fn fetch_postgres(client: &pgsql::Client) -> Result<Self, pgsql::Error> {
+        let row = client
+            .query_one(
+                "SELECT current_setting('TimeZone'), EXTRACT(TIMEZONE FROM now())::int4",
+                &[],
+            )
+            .await?;
+        let time_zone: String = row.try_get(0)?;
+        let offset: i32 = row.try_get(1)?;
+        Ok(Self::parse_or_offset(&time_zone, offset))
+    }
+
+    /// Parse `time_zone`, falling back to a fixed `offset` in seconds from UTC if we can't
+    fn parse_or_offset(time_zone: &str, offset: i32) -> Self {
+        Self::parse(time_zone).unwrap_or_else(|| {
+            warn!(
+                %time_zone,
+                %offset,
+                "Unknown upstream time zone, converting timestamps using its current offset"
+            );
+            UpstreamTimeZone::Fixed(FixedOffset::east_opt(offset).unwrap_or(FixedOffset::east(0)))
+        })
+    }
+
+    /// Parse the name of a time zone, as used for the MySQL `time_zone` system variable
+    pub(crate) fn parse(time_zone: &str) -> Option<Self> {
+        let time_zone = time_zone.trim();
+        let sign = match time_zone.as_bytes().first()? {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return Tz::from_str(time_zone).ok().map(UpstreamTimeZone::Named),
+        };
+        let (hours, minutes) = time_zone[1..].split_once(':')?;
+        let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+        FixedOffset::east_opt(sign * seconds).map(UpstreamTimeZone::Fixed)
+    }
+
+    /// Convert a `TIMESTAMP` value, given as a number of seconds and nanoseconds since the UNIX
+    /// epoch, to the local date and time in this time zone
+    pub(crate) fn local_datetime(&self, secs: i64, nanos: u32) -> NaiveDateTime {
+        self.localize(&NaiveDateTime::from_timestamp(secs, nanos))
+    }
+
+    /// Convert a date and time in UTC to the local date and time in this time zone
+    pub(crate) fn localize(&self, utc: &NaiveDateTime) -> NaiveDateTime {
+        match self {
+            UpstreamTimeZone::Fixed(offset) => offset.from_utc_datetime(utc).naive_local(),
+            UpstreamTimeZone::Named(tz) => tz.from_utc_datetime(utc).naive_local(),
+        }
+    }
+
+    /// Convert a local date and time in this time zone to UTC, or return `None` if it doesn't
+    /// exist because it's skipped by a daylight saving time transition. Times repeated by a
+    /// transition are taken to be the earlier of the two.
+    pub(crate) fn to_utc(&self, local: &NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            UpstreamTimeZone::Fixed(offset) => offset
+                .from_local_datetime(local)
+                .earliest()
+                .map(|datetime| datetime.naive_utc()),
+            UpstreamTimeZone::Named(tz) => tz
+                .from_local_datetime(local)
+                .earliest()
+                .map(|datetime| datetime.naive_utc()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn parse_offsets() {
+        assert_eq!(
+            UpstreamTimeZone::parse("+00:00"),
+            Some(UpstreamTimeZone::Fixed(FixedOffset::east(0)))
+        );
+        assert_eq!(
+            UpstreamTimeZone::parse("+05:30"),
+            Some(UpstreamTimeZone::Fixed(FixedOffset::east(
+                5 * 3600 + 30 * 60
+            )))
+        );
+        assert_eq!(
+            UpstreamTimeZone::parse("-10:00"),
+            Some(UpstreamTimeZone::Fixed(FixedOffset::west(10 * 3600)))
+        );
+        assert_eq!(UpstreamTimeZone::parse("+5"), None);
+        assert_eq!(UpstreamTimeZone::parse("+99:00"), None);
+    }
+
+    #[test]
+    fn parse_names() {
+        assert_eq!(
+            UpstreamTimeZone::parse("UTC"),
+            Some(UpstreamTimeZone::Named(Tz::UTC))
+        );
+        assert_eq!(
+            UpstreamTimeZone::parse("Europe/Berlin"),
+            Some(UpstreamTimeZone::Named(Tz::Europe__Berlin))
+        );
+        assert_eq!(UpstreamTimeZone::parse("Not/A_Zone"), None);
+    }
+
+    #[test]
+    fn local_datetimes() {
+        // 2022-07-01 12:00:00.5 UTC
+        let (secs, nanos) = (1656676800, 500_000_000);
+        assert_eq!(
+            UpstreamTimeZone::default().local_datetime(secs, nanos),
+            NaiveDate::from_ymd(2022, 7, 1).and_hms_milli(12, 0, 0, 500)
+        );
+        assert_eq!(
+            UpstreamTimeZone::parse("-04:00")
+                .unwrap()
+                .local_datetime(secs, nanos),
+            NaiveDate::from_ymd(2022, 7, 1).and_hms_milli(8, 0, 0, 500)
+        );
+        // Berlin is on summer time in July, but not in January
+        let berlin = UpstreamTimeZone::parse("Europe/Berlin").unwrap();
+        assert_eq!(
+            berlin.local_datetime(secs, nanos),
+            NaiveDate::from_ymd(2022, 7, 1).and_hms_milli(14, 0, 0, 500)
+        );
+        assert_eq!(
+            berlin.local_datetime(secs - 181 * 24 * 3600, 0),
+            NaiveDate::from_ymd(2022, 1, 1).and_hms(13, 0, 0)
+        );
+    }
+
+    #[test]
+    fn utc_datetimes() {
+        let berlin = UpstreamTimeZone::parse("Europe/Berlin").unwrap();
+        assert_eq!(
+            berlin.to_utc(&NaiveDate::from_ymd(2022, 7, 1).and_hms(14, 0, 0)),
+            Some(NaiveDate::from_ymd(2022, 7, 1).and_hms(12, 0, 0))
+        );
+        // Skipped by the switch to summer time
+        assert_eq!(
+            berlin.to_utc(&NaiveDate::from_ymd(2022, 3, 27).and_hms(2, 30, 0)),
+            None
+        );
+        let offset = UpstreamTimeZone::parse("+05:30").unwrap();
+        let local = NaiveDate::from_ymd(2022, 1, 1).and_hms(0, 0, 0);
+        assert_eq!(offset.localize(&offset.to_utc(&local).unwrap()), local);
+    }
+}